@@ -0,0 +1,49 @@
+//! Example demonstrating the Cohere provider with grounded answers.
+//!
+//! This example shows how to:
+//! - Use Cohere's chat API
+//! - Set up the API key from the `CO_API_KEY` environment variable
+//! - Pass documents and print the citations backing the answer
+
+use dotenv::dotenv;
+
+use rsai::{
+    ApiKey, ChatRole, CohereDocument, CohereOptions, Message, Provider, TextResponse, llm, models,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let documents = [
+        CohereDocument::new("Rust 1.0 was released on May 15, 2015.")
+            .with_id("rust-1-0")
+            .with_title("Announcing Rust 1.0"),
+        CohereDocument::new("The 2021 edition was released with Rust 1.56 in October 2021.")
+            .with_id("edition-2021")
+            .with_title("The Rust 2021 edition"),
+    ];
+
+    let answer = llm::with(Provider::Cohere)
+        .api_key(ApiKey::Default)?
+        .model(models::cohere::COMMAND_A)
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "When was Rust 1.0 released?",
+        )])
+        .cohere_options(CohereOptions::new().with_documents(documents))
+        .complete::<TextResponse>()
+        .await?;
+
+    println!("{}", answer.text);
+    for citation in &answer.metadata.citations {
+        println!(
+            "{:?} {} ({})",
+            citation.span,
+            citation.source,
+            citation.title.as_deref().unwrap_or("untitled")
+        );
+    }
+
+    Ok(())
+}
@@ -58,14 +58,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let tools = toolset![get_weather, calculate_distance];
 
     let messages = vec![
-        Message {
-            role: ChatRole::System,
-            content: "You are a helpful assistant. Use the available tools to gather information, then provide a structured Weather response for the requested city.".to_string(),
-        },
-        Message {
-            role: ChatRole::User,
-            content: "What's the weather like in Tokyo?".to_string(),
-        }
+        Message::new(
+            ChatRole::System,
+            "You are a helpful assistant. Use the available tools to gather information, then provide a structured Weather response for the requested city.",
+        ),
+        Message::new(ChatRole::User, "What's the weather like in Tokyo?"),
     ];
 
     let response = llm::with(Provider::OpenAI)
@@ -2,7 +2,16 @@
 
 use dotenv::dotenv;
 
-use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm, tool, toolset};
+use rsai::{
+    ApiKey, ChatRole, Message, Provider, TextResponse, completion_schema, llm, tool, toolset,
+};
+
+#[completion_schema]
+struct TipSummary {
+    bill: f64,
+    tip: f64,
+    total: f64,
+}
 
 #[tool]
 /// Calculate the tip for a restaurant bill
@@ -30,16 +39,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let result = llm::with(Provider::Gemini)
         .api_key(ApiKey::Default)?
         .model("gemini-2.5-flash")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content: "My dinner bill is $85 and the service was excellent. How much should I tip?"
-                .to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "My dinner bill is $85 and the service was excellent. How much should I tip?",
+        )])
         .tools(tools)
         .complete::<TextResponse>()
         .await?;
 
     println!("{}", result.text);
 
+    // Structured output with tools runs the tool loop first, then a final structured request
+    let summary = llm::with(Provider::Gemini)
+        .api_key(ApiKey::Default)?
+        .model("gemini-2.5-flash")
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "My dinner bill is $85 and the service was good (4 stars). Summarize the tip.",
+        )])
+        .tools(toolset![calculate_tip])
+        .complete::<TipSummary>()
+        .await?;
+
+    println!(
+        "Bill ${:.2} + tip ${:.2} = ${:.2}",
+        summary.content.bill, summary.content.tip, summary.content.total
+    );
+
     Ok(())
 }
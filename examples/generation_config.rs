@@ -19,11 +19,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let creative_story = llm::with(Provider::OpenAI)
         .api_key(ApiKey::Default)?
         .model("gpt-4o-mini")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content: "Write a creative short story concept about a robot discovering emotions"
-                .to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Write a creative short story concept about a robot discovering emotions",
+        )])
         .temperature(1.5) // High temperature for creativity
         .max_tokens(500) // Limit the response length
         .complete::<CreativeStory>()
@@ -39,10 +38,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let factual_story = llm::with(Provider::OpenAI)
         .api_key(ApiKey::Default)?
         .model("gpt-4o-mini")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content: "Write a story concept about a robot discovering emotions".to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Write a story concept about a robot discovering emotions",
+        )])
         .temperature(0.2) // Low temperature for consistency
         .max_tokens(300) // Shorter response
         .complete::<CreativeStory>()
@@ -58,11 +57,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let nucleus_story = llm::with(Provider::OpenAI)
         .api_key(ApiKey::Default)?
         .model("gpt-4o-mini")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content: "Write an experimental story concept about a robot discovering emotions"
-                .to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Write an experimental story concept about a robot discovering emotions",
+        )])
         .top_p(0.9) // Nucleus sampling
         .max_tokens(400)
         .complete::<CreativeStory>()
@@ -11,10 +11,10 @@ struct Fact {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
 
-    let messages = vec![Message {
-        role: ChatRole::User,
-        content: "Tell me a random interesting fact about space.".to_string(),
-    }];
+    let messages = vec![Message::new(
+        ChatRole::User,
+        "Tell me a random interesting fact about space.",
+    )];
 
     // This sets a strict total request timeout.
     // If the model takes > 5 seconds, this will error with LlmError::Network.
@@ -36,12 +36,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let resilient_config = HttpClientConfig {
         // Total time for a single attempt
         timeout: Duration::from_secs(10),
+        // Give up quickly on unreachable hosts...
+        connect_timeout: Some(Duration::from_secs(2)),
+        // ...and on providers that accept the request but never start answering
+        first_byte_timeout: Some(Duration::from_secs(8)),
         // How many times to retry on 429 (Rate Limit) or 5xx (Server Error)
         max_retries: 5,
         // Start waiting 2s, then 4s, then 8s...
         initial_retry_delay: Duration::from_secs(2),
         // ...but don't wait longer than 15s between retries
         max_retry_delay: Duration::from_secs(15),
+        // Corporate networks can route traffic through a proxy and trust a custom CA
+        proxy_url: std::env::var("HTTPS_PROXY").ok(),
+        ..Default::default()
     };
 
     let response = llm::with(Provider::OpenAI)
@@ -24,14 +24,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let tools = toolset![get_weather];
 
     let messages = vec![
-        Message {
-            role: ChatRole::System,
-            content: "You are a helpful assistant.".to_string(),
-        },
-        Message {
-            role: ChatRole::User,
-            content: "What's the weather in Paris?".to_string(),
-        },
+        Message::new(ChatRole::System, "You are a helpful assistant."),
+        Message::new(ChatRole::User, "What's the weather in Paris?"),
     ];
 
     let response = llm::with(Provider::OpenAI)
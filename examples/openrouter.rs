@@ -24,12 +24,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let analysis = llm::with(Provider::OpenRouter)
         .api_key(ApiKey::Default)?
         .model("openai/gpt-4o-mini")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content:
-                "Analyze this text: 'The new AI library is incredibly powerful and easy to use!'"
-                    .to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Analyze this text: 'The new AI library is incredibly powerful and easy to use!'",
+        )])
         .complete::<Analysis>()
         .await?;
 
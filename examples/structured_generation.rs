@@ -14,10 +14,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let analysis = llm::with(Provider::OpenAI)
         .api_key(ApiKey::Default)?
         .model("gpt-4o-mini")
-        .messages(vec![Message {
-            role: ChatRole::User,
-            content: "Analyze: 'This library is amazing!'".to_string(),
-        }])
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Analyze: 'This library is amazing!'",
+        )])
         .complete::<Analysis>()
         .await?;
 
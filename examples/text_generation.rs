@@ -9,14 +9,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .api_key(ApiKey::Default)?
         .model("gpt-4o-mini")
         .messages(vec![
-            Message {
-                role: ChatRole::System,
-                content: "You are a concise, upbeat assistant.".to_string(),
-            },
-            Message {
-                role: ChatRole::User,
-                content: "Share a fun fact about Rust programming.".to_string(),
-            },
+            Message::new(ChatRole::System, "You are a concise, upbeat assistant."),
+            Message::new(ChatRole::User, "Share a fun fact about Rust programming."),
         ])
         .complete::<TextResponse>()
         .await?;
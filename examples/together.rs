@@ -0,0 +1,48 @@
+//! Example demonstrating the Together AI preset.
+//!
+//! This example shows how to:
+//! - Use an open-weight model over Together's OpenAI-compatible API
+//! - Set up the API key from the `TOGETHER_API_KEY` environment variable
+//! - Check a model's capabilities before asking for structured output
+//!
+//! Fireworks works the same way with `Provider::Fireworks` and `FIREWORKS_API_KEY`.
+
+use dotenv::dotenv;
+
+use rsai::{ApiKey, ChatRole, Message, Provider, completion_schema, llm, models};
+
+#[completion_schema]
+struct Analysis {
+    sentiment: String,
+    confidence: f32,
+    key_points: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let model = models::together::LLAMA_3_3_70B_INSTRUCT_TURBO;
+    if !Provider::Together
+        .capabilities_for(model.id)
+        .supports_structured_output
+    {
+        return Err(format!("{model} has no JSON schema mode").into());
+    }
+
+    let analysis = llm::with(Provider::Together)
+        .api_key(ApiKey::Default)?
+        .model(model)
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Analyze this text: 'The new AI library is incredibly powerful and easy to use!'",
+        )])
+        .complete::<Analysis>()
+        .await?;
+
+    println!("Sentiment: {}", analysis.content.sentiment);
+    println!("Confidence: {}", analysis.content.confidence);
+    println!("Key points: {:?}", analysis.content.key_points);
+
+    Ok(())
+}
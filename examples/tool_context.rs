@@ -77,16 +77,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .api_key(ApiKey::Default)?
         .model("gemini-2.5-flash")
         .messages(vec![
-            Message {
-                role: ChatRole::System,
-                content:
-                    "You have access to a knowledge base. Use search_docs to find information."
-                        .into(),
-            },
-            Message {
-                role: ChatRole::User,
-                content: "What can you tell me about Rust?".into(),
-            },
+            Message::new(
+                ChatRole::System,
+                "You have access to a knowledge base. Use search_docs to find information.",
+            ),
+            Message::new(ChatRole::User, "What can you tell me about Rust?"),
         ])
         .tools(tools)
         .complete::<TextResponse>()
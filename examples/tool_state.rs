@@ -0,0 +1,80 @@
+//! Tools that share mutable state.
+//!
+//! Tools receive their context by shared reference, so state they change lives
+//! behind a `Mutex`. Keeping an `Arc` to the context lets the application read
+//! what the agent accumulated once the request is done.
+//!
+//! Run with: `cargo run --example tool-state`
+
+use std::sync::{Arc, Mutex};
+
+use rsai::{ApiKey, ChatRole, Ctx, Message, Provider, TextResponse, llm, tool, toolset};
+
+#[derive(Debug, Default)]
+struct ShoppingCart {
+    items: Vec<(String, u32)>,
+}
+
+#[derive(Default)]
+struct Shop {
+    cart: Mutex<ShoppingCart>,
+}
+
+impl AsRef<Mutex<ShoppingCart>> for Shop {
+    fn as_ref(&self) -> &Mutex<ShoppingCart> {
+        &self.cart
+    }
+}
+
+#[tool]
+/// Add an item to the shopping cart.
+/// item: Name of the item.
+/// quantity: How many to add.
+fn add_to_cart(cart: Ctx<&Mutex<ShoppingCart>>, item: String, quantity: u32) -> String {
+    let mut cart = cart.lock().unwrap();
+    cart.items.push((item.clone(), quantity));
+    format!(
+        "Added {quantity} x {item}, cart has {} lines",
+        cart.items.len()
+    )
+}
+
+#[tool]
+/// Show the current contents of the shopping cart.
+fn view_cart(cart: Ctx<&Mutex<ShoppingCart>>) -> Vec<String> {
+    cart.lock()
+        .unwrap()
+        .items
+        .iter()
+        .map(|(item, quantity)| format!("{quantity} x {item}"))
+        .collect()
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv::dotenv().ok();
+
+    let shop = Arc::new(Shop::default());
+    let tools = toolset![Shop => add_to_cart, view_cart].with_shared_context(shop.clone());
+
+    let response = llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Default)?
+        .model("gpt-4o-mini")
+        .messages(vec![
+            Message::new(
+                ChatRole::System,
+                "You manage the user's shopping cart with the available tools.",
+            ),
+            Message::new(
+                ChatRole::User,
+                "I need 3 apples and a loaf of bread. What's in my cart now?",
+            ),
+        ])
+        .tools(tools)
+        .complete::<TextResponse>()
+        .await?;
+
+    println!("{}", response.text);
+    println!("Final cart: {:?}", shop.cart.lock().unwrap());
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Example demonstrating the xAI provider with Grok models.
+//!
+//! This example shows how to:
+//! - Use xAI's chat completions API
+//! - Set up the API key from the `XAI_API_KEY` environment variable
+//! - Generate structured output
+
+use dotenv::dotenv;
+
+use rsai::{ApiKey, ChatRole, Message, Provider, completion_schema, llm, models};
+
+#[completion_schema]
+struct Analysis {
+    sentiment: String,
+    confidence: f32,
+    key_points: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenv().ok();
+
+    let analysis = llm::with(Provider::XAI)
+        .api_key(ApiKey::Default)?
+        .model(models::xai::GROK_3_MINI)
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "Analyze this text: 'The new AI library is incredibly powerful and easy to use!'",
+        )])
+        .complete::<Analysis>()
+        .await?;
+
+    println!("Sentiment: {}", analysis.content.sentiment);
+    println!("Confidence: {}", analysis.content.confidence);
+    println!("Key points: {:?}", analysis.content.key_points);
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use darling::FromMeta;
+use darling::ast::NestedMeta;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, Result};
+
+/// Options accepted by `#[completion_schema(...)]`.
+#[derive(Debug, FromMeta)]
+struct SchemaOptions {
+    /// Forwarded to `#[serde(rename_all = "...")]`.
+    #[darling(default)]
+    rename_all: Option<String>,
+    /// Close the schema to unknown fields. Defaults to `true`.
+    #[darling(default)]
+    strict: Option<bool>,
+    /// JSON documents embedded as schema `examples`.
+    #[darling(multiple, rename = "example")]
+    examples: Vec<syn::LitStr>,
+    /// Implements `rsai::migration::SchemaVersion` with this version.
+    #[darling(default)]
+    version: Option<syn::LitInt>,
+}
+
+pub fn completion_schema_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    let options = SchemaOptions::from_list(&NestedMeta::parse_meta_list(attr)?)?;
+
+    let rename_all = options
+        .rename_all
+        .map(|case| quote! { #[serde(rename_all = #case)] });
+
+    let deny_unknown_fields = options
+        .strict
+        .unwrap_or(true)
+        .then(|| quote! { #[schemars(deny_unknown_fields)] });
+
+    let examples = options
+        .examples
+        .iter()
+        .map(|example| {
+            // Reject malformed examples at compile time so the runtime parse cannot fail
+            serde_json::from_str::<serde_json::Value>(&example.value()).map_err(|e| {
+                Error::new_spanned(example, format!("`example` must be valid JSON: {e}"))
+            })?;
+            Ok(quote! {
+                #[schemars(example = ::serde_json::from_str::<::serde_json::Value>(#example)
+                    .expect("example was validated by #[completion_schema]"))]
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let schema_version = options
+        .version
+        .map(|version| schema_version_impl(&version, item.clone()))
+        .transpose()?;
+
+    Ok(quote! {
+        #[derive(serde::Deserialize, schemars::JsonSchema)]
+        #rename_all
+        #deny_unknown_fields
+        #(#examples)*
+        #item
+
+        #schema_version
+    })
+}
+
+fn schema_version_impl(version: &syn::LitInt, item: TokenStream) -> Result<TokenStream> {
+    if version.base10_parse::<u32>()? == 0 {
+        return Err(Error::new_spanned(version, "`version` starts at 1"));
+    }
+
+    let input: syn::DeriveInput = syn::parse2(item)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics rsai::migration::SchemaVersion for #name #ty_generics #where_clause {
+            const VERSION: u32 = #version;
+        }
+    })
+}
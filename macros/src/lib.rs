@@ -35,8 +35,9 @@
 //! ```
 
 use proc_macro::TokenStream;
-use quote::quote;
 
+mod completion_schema;
+mod llm_test;
 mod tool;
 mod tools;
 
@@ -71,12 +72,40 @@ mod tools;
 /// }
 /// ```
 ///
+/// # Descriptions
+///
+/// Doc comments on the struct, its fields, nested types and enum variants are sent to the
+/// model as schema `description`s for every provider, so they double as field-level
+/// instructions.
+///
 /// # Field Validation
 ///
 /// The `deny_unknown_fields` attribute ensures that responses must exactly match
 /// your struct definition. Any extra fields will cause a deserialization error,
 /// providing predictable and safe responses.
 ///
+/// # Options
+///
+/// | Option | Effect |
+/// |--------|--------|
+/// | `rename_all = "camelCase"` | Forwarded to `#[serde(rename_all)]`, so the schema and the parsed output use that casing |
+/// | `strict = false` | Leaves out `deny_unknown_fields`, for providers that add metadata fields to the output |
+/// | `example = "<json>"` | Adds the JSON document to the schema's `examples`; may be repeated |
+/// | `version = 2` | Implements `rsai::migration::SchemaVersion`, so persisted values of older versions can be migrated |
+///
+/// ```rust
+/// use rsai_macros::completion_schema;
+///
+/// #[completion_schema(
+///     rename_all = "camelCase",
+///     example = r#"{"cityName": "Lisbon", "feelsLike": 21.5}"#
+/// )]
+/// struct Forecast {
+///     city_name: String,
+///     feels_like: f64,
+/// }
+/// ```
+///
 /// # Supported Types
 ///
 /// All types that implement [`serde::Deserialize`] and [`schemars::JsonSchema`] are supported,
@@ -85,18 +114,20 @@ mod tools;
 /// - Optionals ([`Option<T>`])
 /// - Vectors ([`Vec<T>`])
 /// - Nested structs and enums
+/// - Enums with data-carrying variants, externally or internally (`#[serde(tag = "...")]`)
+///   tagged. Strict structured output does not accept a `oneOf` root, so the schema is sent
+///   as a single object with a discriminator field and the fields of every variant, and the
+///   answer is converted back before deserializing.
+/// - String-keyed maps ([`HashMap<String, T>`](std::collections::HashMap)). Strict structured
+///   output forbids `additionalProperties` schemas, so maps are requested as arrays of
+///   `{"key": ..., "value": ...}` entries and rebuilt into maps before deserializing.
 /// - Custom types with appropriate trait implementations
 #[proc_macro_attribute]
-pub fn completion_schema(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let item_tokens: proc_macro2::TokenStream = item.into();
-
-    let expanded = quote! {
-        #[derive(serde::Deserialize, schemars::JsonSchema)]
-        #[schemars(deny_unknown_fields)]
-        #item_tokens
-    };
-
-    TokenStream::from(expanded)
+pub fn completion_schema(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match completion_schema::completion_schema_impl(attr.into(), item.into()) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
 }
 
 /// Attribute macro for marking functions as tools that can be called by LLMs.
@@ -158,6 +189,90 @@ pub fn completion_schema(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// }
 /// ```
 ///
+/// Tools returning `Result<T, E>` send the `Ok` value to the model as is. An `Err`
+/// becomes `LlmError::ToolExecution` with the error's `Display` text as the message.
+///
+/// ## Cacheable Tool
+///
+/// With `#[tool(cacheable)]`, repeated calls with identical arguments within one tool
+/// calling loop reuse the first result. Pass a `ToolCache` to `ToolCallingConfig` to
+/// also reuse results across runs.
+///
+/// ```rust
+/// use rsai_macros::tool;
+///
+/// #[tool(cacheable)]
+/// /// Look up the exchange rate between two currencies
+/// /// from: Source currency code
+/// /// to: Target currency code
+/// fn exchange_rate(from: String, to: String) -> f64 {
+///     if from == to { 1.0 } else { 0.92 }
+/// }
+/// ```
+///
+/// ## Tool With Output Schema
+///
+/// With `#[tool(output_schema)]`, the JSON Schema of the return type (which must
+/// implement `schemars::JsonSchema`) is appended to the tool description, and every
+/// result is validated against it before it is sent back to the model.
+///
+/// ```rust,ignore
+/// use rsai::tool;
+///
+/// #[derive(serde::Serialize, schemars::JsonSchema)]
+/// struct Forecast {
+///     city: String,
+///     celsius: f64,
+/// }
+///
+/// #[tool(output_schema)]
+/// /// Forecast the weather for a city
+/// /// city: Name of the city
+/// fn forecast(city: String) -> Forecast {
+///     Forecast { city, celsius: 21.5 }
+/// }
+/// ```
+///
+/// ## Deprecated and Hidden Tools
+///
+/// `#[tool(deprecated = "use get_weather_v2")]` appends the notice to the description
+/// and logs a warning whenever the tool runs. `#[tool(hidden)]` keeps the tool
+/// registered but leaves it out of the schemas sent to the model unless it is named
+/// in `enabled_tools`.
+///
+/// ```rust
+/// use rsai_macros::tool;
+///
+/// #[tool(deprecated = "use get_weather_v2")]
+/// /// Get current weather for a city
+/// /// city: The city to get weather for
+/// fn get_weather(city: String) -> String {
+///     format!("Weather for {}: 22°C", city)
+/// }
+/// ```
+///
+/// ## Default Values
+///
+/// End a parameter description with `@default <value>` to declare a default. It is
+/// emitted as `default` in the JSON schema, the parameter becomes optional for the
+/// model, and the value is used when the argument is omitted or `null`. Values are
+/// read as JSON, except that string parameters take the text as is. A default that is
+/// not a JSON value of the parameter's type, e.g. `@default many` for a `u32`, is a
+/// compile error.
+///
+/// ```rust
+/// use rsai_macros::tool;
+///
+/// #[tool]
+/// /// Get the weather forecast for a city
+/// /// city: The city to get weather for
+/// /// unit: Temperature unit @default celsius
+/// /// days: Number of days to forecast @default 3
+/// fn forecast(city: String, unit: String, days: u32) -> String {
+///     format!("{days} day forecast for {city} in {unit}")
+/// }
+/// ```
+///
 /// # Parameter Validation
 ///
 /// The macro performs comprehensive compile-time validation:
@@ -191,11 +306,25 @@ pub fn completion_schema(_attr: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// | Rust Type | JSON Schema Type |
 /// |-----------|------------------|
-/// | `String`, `&str` | `string` |
+/// | `String`, `&str`, `Cow<str>` | `string` |
 /// | `i8`, `i16`, `i32`, `i64`, `u8`, `u16`, `u32`, `u64`, `f32`, `f64` | `number` |
 /// | `bool` | `boolean` |
-/// | `Vec<T>` | `array` |
+/// | `Vec<T>`, `&[T]`, `Cow<[T]>` | `array` |
 /// | `Option<T>` | `T` (optional) |
+/// | `DateTime<Tz>`, `NaiveDateTime` | `string` (`format: date-time`) |
+/// | `NaiveDate` | `string` (`format: date`) |
+/// | `NaiveTime` | `string` (`format: time`) |
+/// | `Uuid` | `string` (`format: uuid`) |
+///
+/// Borrowed parameters are deserialized into an owned value first: `&str` into a
+/// `String`, `&[T]` into a `Vec<T>` and any other `&T` into a `T`.
+///
+/// Arguments are coerced to the schema types before deserializing, so a model sending
+/// `"42"` for an integer or `"true"` for a boolean doesn't fail the call; see
+/// `rsai::coerce_arguments`.
+///
+/// The `chrono` and `uuid` types require the matching `rsai` feature so the
+/// arguments can be deserialized.
 #[proc_macro_attribute]
 pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
     match tool::tool_impl(attr.into(), item.into()) {
@@ -289,6 +418,76 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// );
 /// ```
 ///
+/// ## Namespaced Tools
+///
+/// `namespace = name =>` registers every tool as `name.tool`, so toolsets with tools of the
+/// same name can be merged. It follows the context type, if any:
+/// `toolset![ContextType => namespace = weather => get_weather]`.
+///
+/// ```rust
+/// use rsai_macros::{tool, toolset};
+///
+/// #[tool]
+/// /// Get current weather for a city
+/// /// city: The city to get weather for
+/// fn get_weather(city: String) -> String {
+///     format!("Weather for {}: 22°C", city)
+/// }
+///
+/// let tools = toolset![namespace = weather => get_weather];
+/// assert_eq!(tools.tools().unwrap()[0].name, "weather.get_weather");
+/// ```
+///
+/// ## Typed Tool Choice
+///
+/// Used in item position with `enum Name`, the macro also declares an enum with one
+/// variant per tool. Pass a variant to `.tool_choice_typed(...)` instead of spelling the
+/// tool name as a string; an unknown tool fails to compile. Add `: ContextType` after the
+/// name for tools that need a context.
+///
+/// ```rust
+/// use rsai_macros::{tool, toolset};
+///
+/// #[tool]
+/// /// Get current weather for a city
+/// /// city: The city to get weather for
+/// fn get_weather(city: String) -> String {
+///     format!("Weather for {}: 22°C", city)
+/// }
+///
+/// toolset! {
+///     pub enum WeatherTools => get_weather
+/// }
+///
+/// assert_eq!(WeatherTools::GetWeather.name(), "get_weather");
+/// let tools = WeatherTools::toolset();
+/// assert_eq!(tools.tools().unwrap().len(), 1);
+/// ```
+///
+/// ## Tools From Other Modules
+///
+/// Tools may be referenced by path, including through re-exports of the function:
+/// `#[tool]` declares a hidden type under the function's name that `toolset!` resolves the
+/// wrapper struct through.
+///
+/// ```rust
+/// use rsai_macros::toolset;
+///
+/// mod tools {
+///     use rsai_macros::tool;
+///
+///     #[tool]
+///     /// Get current weather for a city
+///     /// city: The city to get weather for
+///     pub fn get_weather(city: String) -> String {
+///         format!("Weather for {}: 22°C", city)
+///     }
+/// }
+///
+/// let tools = toolset![tools::get_weather];
+/// assert_eq!(tools.tools().unwrap()[0].name, "get_weather");
+/// ```
+///
 /// # Generated Code
 ///
 /// The macro generates code that:
@@ -321,3 +520,40 @@ pub fn toolset(input: TokenStream) -> TokenStream {
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+/// Attribute macro for prompt regression tests.
+///
+/// Turns an async function taking an `rsai::testing::TestCase` into a `#[tokio::test]`. The
+/// case is named after the module path and function name, which determines where its cassette
+/// and snapshot are stored (`tests/cassettes/<module>/<name>.json` and
+/// `tests/snapshots/<module>/<name>.json`).
+///
+/// Requires `tokio` with the `macros` and `rt` features as a dev-dependency.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rsai::testing::TestCase;
+/// use rsai::{ChatRole, LlmError, Message, Provider, TextResponse, llm, llm_test};
+///
+/// #[llm_test]
+/// async fn greets_politely(case: TestCase) -> Result<(), LlmError> {
+///     let reply = llm::with(Provider::OpenAI)
+///         .api_key(case.api_key())?
+///         .model("gpt-4o-mini")
+///         .messages(vec![Message::new(ChatRole::User, "Say hello")])
+///         .transport(case.transport()?)
+///         .complete::<TextResponse>()
+///         .await?;
+///
+///     case.assert_snapshot(&reply.text);
+///     Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn llm_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match llm_test::llm_test_impl(attr.into(), item.into()) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
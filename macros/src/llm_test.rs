@@ -0,0 +1,48 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, FnArg, ItemFn, Result};
+
+pub fn llm_test_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
+    if !attr.is_empty() {
+        return Err(Error::new_spanned(attr, "#[llm_test] takes no arguments"));
+    }
+
+    let input = syn::parse2::<ItemFn>(item)?;
+    let sig = &input.sig;
+
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(
+            sig.fn_token,
+            "#[llm_test] functions must be async",
+        ));
+    }
+
+    let case_param = match sig.inputs.iter().collect::<Vec<_>>().as_slice() {
+        [FnArg::Typed(param)] => param,
+        _ => {
+            return Err(Error::new_spanned(
+                &sig.inputs,
+                "#[llm_test] functions take exactly one `rsai::testing::TestCase` parameter",
+            ));
+        }
+    };
+
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let name = &sig.ident;
+    let output = &sig.output;
+    let case_pat = &case_param.pat;
+    let case_ty = &case_param.ty;
+    let body = &input.block;
+
+    Ok(quote! {
+        #(#attrs)*
+        #[::tokio::test]
+        #vis async fn #name() #output {
+            let #case_pat: #case_ty = rsai::testing::TestCase::new(
+                concat!(module_path!(), "::", stringify!(#name))
+            );
+            #body
+        }
+    })
+}
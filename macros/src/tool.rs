@@ -1,6 +1,25 @@
+use darling::FromMeta;
+use darling::ast::NestedMeta;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Attribute, FnArg, ItemFn, Pat, Result, Type};
+use syn::{Attribute, FnArg, ItemFn, Pat, Result, ReturnType, Type};
+
+/// Options accepted by `#[tool(...)]`.
+#[derive(Debug, Default, FromMeta)]
+struct ToolOptions {
+    /// Reuse results of calls with identical arguments.
+    #[darling(default)]
+    cacheable: bool,
+    /// Describe the return type's JSON Schema to the model and validate results against it.
+    #[darling(default)]
+    output_schema: bool,
+    /// Deprecation notice added to the description and logged on execution.
+    #[darling(default)]
+    deprecated: Option<String>,
+    /// Leave the tool out of schemas unless it is explicitly enabled.
+    #[darling(default)]
+    hidden: bool,
+}
 
 /// Information about a context parameter (marked with #[context])
 struct ContextParam {
@@ -10,7 +29,7 @@ struct ContextParam {
 }
 
 pub fn tool_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
-    let _ = attr; // Currently unused, could be used for tool configuration
+    let options = ToolOptions::from_list(&NestedMeta::parse_meta_list(attr)?)?;
 
     let input = syn::parse2::<ItemFn>(item)?;
     let fn_name = &input.sig.ident;
@@ -47,14 +66,72 @@ pub fn tool_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
     // Check if function is async
     let is_async = input.sig.asyncness.is_some();
 
+    // Tools returning `Result` report `Err` as a tool execution error
+    let returns_result =
+        matches!(&input.sig.output, ReturnType::Type(_, ty) if result_ok_type(ty).is_some());
+
     // Generate the execution code
-    let execute_impl = generate_execute_impl(fn_name, &context_param, &params, is_async)?;
+    let execute_impl =
+        generate_execute_impl(fn_name, &context_param, &params, is_async, returns_result)?;
 
     // Generate inherent impl with schema() method.
     // This allows calling .schema() without type annotations since Rust prefers
     // inherent methods over trait methods during method resolution.
+    let description = match &options.deprecated {
+        Some(notice) => quote! {{
+            let deprecation = format!("Deprecated: {}", #notice);
+            let description: Option<String> = #description;
+            Some(match description {
+                Some(description) => format!("{description} ({deprecation})"),
+                None => deprecation,
+            })
+        }},
+        None => description,
+    };
+
+    let (output_schema_fn, description) = if options.output_schema {
+        let return_ty = match &input.sig.output {
+            ReturnType::Default => quote! { () },
+            ReturnType::Type(_, ty) => {
+                let ty = result_ok_type(ty).unwrap_or(ty);
+                quote! { #ty }
+            }
+        };
+        let output_schema_fn = quote! {
+            /// JSON Schema of the tool's result.
+            pub fn output_schema(&self) -> ::serde_json::Value {
+                let mut schema = ::serde_json::to_value(schemars::schema_for!(#return_ty))
+                    .expect("JSON Schema serializes to JSON");
+                if let Some(obj) = schema.as_object_mut() {
+                    obj.remove("$schema");
+                }
+                schema
+            }
+        };
+        let description = quote! {{
+            let returns = format!(
+                "Returns JSON matching this schema: {}",
+                self.output_schema()
+            );
+            let description: Option<String> = #description;
+            Some(match description {
+                Some(description) => format!("{description}\n\n{returns}"),
+                None => returns,
+            })
+        }};
+        (output_schema_fn, description)
+    } else {
+        (quote! {}, description)
+    };
+
     let inherent_impl = quote! {
         impl #wrapper_name {
+            /// Name the tool is registered under.
+            pub const NAME: &'static str = #fn_name_str;
+
+            /// The tool, for `toolset!` to reach through the function's path.
+            pub const TOOL: Self = Self;
+
             pub fn schema(&self) -> rsai::Tool {
                 use rsai::Tool;
                 Tool {
@@ -64,9 +141,43 @@ pub fn tool_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
                     strict: Some(true),
                 }
             }
+
+            #output_schema_fn
         }
     };
 
+    let cacheable_impl = options.cacheable.then(|| {
+        quote! {
+            fn cacheable(&self) -> bool {
+                true
+            }
+        }
+    });
+
+    let deprecated_impl = options.deprecated.as_ref().map(|notice| {
+        quote! {
+            fn deprecated(&self) -> Option<&str> {
+                Some(#notice)
+            }
+        }
+    });
+
+    let hidden_impl = options.hidden.then(|| {
+        quote! {
+            fn hidden(&self) -> bool {
+                true
+            }
+        }
+    });
+
+    let output_schema_impl = options.output_schema.then(|| {
+        quote! {
+            fn output_schema(&self) -> Option<::serde_json::Value> {
+                Some(#wrapper_name::output_schema(self))
+            }
+        }
+    });
+
     // Generate trait implementation based on whether there's a context param
     let trait_impl = if let Some(ctx_param) = &context_param {
         // Tool with context: impl<Ctx> ToolFunction<Ctx> for Tool where Ctx: AsRef<ContextType>
@@ -82,10 +193,23 @@ pub fn tool_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
 
                 fn execute<'a>(&'a self, __ctx: &'a __Ctx, params: ::serde_json::Value) -> rsai::BoxFuture<'a, Result<::serde_json::Value, rsai::LlmError>> {
                     use rsai::{BoxFuture, LlmError};
+                    let params = rsai::coerce_arguments(&#wrapper_name::schema(self).parameters, params);
                     Box::pin(async move {
                         #execute_impl
                     })
                 }
+
+                #cacheable_impl
+
+                #output_schema_impl
+
+                #deprecated_impl
+
+                #hidden_impl
+
+                fn coerces_arguments(&self) -> bool {
+                    true
+                }
             }
         }
     } else {
@@ -99,21 +223,40 @@ pub fn tool_impl(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
                 fn execute<'a>(&'a self, __ctx: &'a __Ctx, params: ::serde_json::Value) -> rsai::BoxFuture<'a, Result<::serde_json::Value, rsai::LlmError>> {
                     use rsai::{BoxFuture, LlmError};
                     let _ = __ctx; // Unused for context-free tools
+                    let params = rsai::coerce_arguments(&#wrapper_name::schema(self).parameters, params);
                     Box::pin(async move {
                         #execute_impl
                     })
                 }
+
+                #cacheable_impl
+
+                #output_schema_impl
+
+                #deprecated_impl
+
+                #hidden_impl
+
+                fn coerces_arguments(&self) -> bool {
+                    true
+                }
             }
         }
     };
 
-    // Generate the complete implementation
+    // The alias lives in the type namespace next to the function, so `toolset!` resolves the
+    // wrapper from the function's path and re-exporting the function brings it along
+    let fn_vis = &input.vis;
     let expanded = quote! {
         #input
 
         #[derive(Clone)]
         pub struct #wrapper_name;
 
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        #fn_vis type #fn_name = #wrapper_name;
+
         #inherent_impl
 
         #trait_impl
@@ -172,9 +315,110 @@ fn extract_doc_comment_and_params(
 
 struct Parameter {
     name: String,
+    /// Owned type the argument is deserialized into
     ty: Type,
     description: Option<String>,
     required: bool,
+    /// How the function receives the owned value
+    borrow: Borrow,
+    /// JSON text of the `@default` value used when the model omits the argument
+    default: Option<String>,
+}
+
+/// Marks the default value in a parameter description, e.g. `unit: Temperature unit @default celsius`.
+const DEFAULT_MARKER: &str = "@default";
+
+/// Split a parameter description into the text and the `@default` value, if any.
+fn split_default(doc: &str) -> (String, Option<String>) {
+    match doc.split_once(DEFAULT_MARKER) {
+        Some((description, default)) => (
+            description.trim().to_string(),
+            Some(default.trim().to_string()),
+        ),
+        None => (doc.to_string(), None),
+    }
+}
+
+/// Expression evaluating to the `serde_json::Value` of a default.
+fn default_value_tokens(default: &str) -> TokenStream {
+    quote! {
+        ::serde_json::from_str::<::serde_json::Value>(#default)
+            .expect("default was converted to JSON by #[tool]")
+    }
+}
+
+/// JSON text for a default value. Values meant for a string parameter, or for a type such
+/// as an enum that is not valid JSON, are taken as plain strings so `@default celsius` needs
+/// no quotes. Integer, number, boolean and array parameters need a JSON value of that type.
+fn default_json(default: &str, ty: &Type) -> std::result::Result<String, String> {
+    let json_type = type_to_json_type(ty).unwrap_or("object");
+    let value = serde_json::from_str::<serde_json::Value>(default).ok();
+    let matches = match (json_type, &value) {
+        ("string" | "object", _) => true,
+        ("integer", Some(value)) => value.is_i64() || value.is_u64(),
+        ("number", Some(value)) => value.is_number(),
+        ("boolean", Some(value)) => value.is_boolean(),
+        ("array", Some(value)) => value.is_array(),
+        _ => false,
+    };
+    if !matches {
+        return Err(format!(
+            "`@default {default}` is not a valid {json_type} for this parameter"
+        ));
+    }
+    Ok(match value {
+        Some(value) if json_type != "string" || value.is_string() => value.to_string(),
+        _ => serde_json::Value::String(default.to_string()).to_string(),
+    })
+}
+
+/// How a deserialized argument is passed to a tool function declared with a borrowed type.
+#[derive(Clone, Copy, PartialEq)]
+enum Borrow {
+    /// Passed by value
+    None,
+    /// `&T`, passed as `&owned`
+    Ref,
+    /// `&str` or `&[T]`, received as `String` / `Vec<T>` and passed through `Deref`
+    Deref,
+}
+
+/// Map a borrowed parameter type to an owned type it can be deserialized into.
+///
+/// `&str` becomes `String`, `&[T]` becomes `Vec<T>`, `&T` becomes `T`, and the lifetime of
+/// `Cow<'a, T>` becomes `'static` so the value can be deserialized as owned.
+fn owned_parameter_type(ty: Type) -> (Type, Borrow) {
+    match ty {
+        Type::Reference(reference) => match *reference.elem {
+            Type::Path(ref path) if path.path.is_ident("str") => {
+                (syn::parse_quote! { ::std::string::String }, Borrow::Deref)
+            }
+            Type::Slice(slice) => {
+                let elem = slice.elem;
+                (syn::parse_quote! { ::std::vec::Vec<#elem> }, Borrow::Deref)
+            }
+            elem => (elem, Borrow::Ref),
+        },
+        Type::Path(mut type_path)
+            if type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Cow") =>
+        {
+            if let Some(segment) = type_path.path.segments.last_mut()
+                && let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments
+            {
+                for arg in args.args.iter_mut() {
+                    if let syn::GenericArgument::Lifetime(lifetime) = arg {
+                        *lifetime = syn::Lifetime::new("'static", lifetime.span());
+                    }
+                }
+            }
+            (Type::Path(type_path), Borrow::None)
+        }
+        ty => (ty, Borrow::None),
+    }
 }
 
 /// Check if a type is `Ctx<T>` and extract the inner type.
@@ -239,7 +483,13 @@ fn parse_parameters(
                 }
 
                 // Get parameter description from docstring parsing
-                let description = param_descriptions.get(&name).cloned();
+                let (description, default) = match param_descriptions.get(&name) {
+                    Some(doc) => {
+                        let (description, default) = split_default(doc);
+                        (Some(description), default)
+                    }
+                    None => (None, None),
+                };
 
                 // Check if type is Option<T>
                 let (ty, required) = match &*pat_type.ty {
@@ -266,11 +516,19 @@ fn parse_parameters(
                     _ => ((*pat_type.ty).clone(), true),
                 };
 
+                let (ty, borrow) = owned_parameter_type(ty);
+                let default = default
+                    .map(|default| default_json(&default, &ty))
+                    .transpose()
+                    .map_err(|message| syn::Error::new_spanned(&pat_type.ty, message))?;
+
                 params.push(Parameter {
                     name,
                     ty,
                     description,
                     required,
+                    borrow,
+                    default,
                 });
             }
         }
@@ -332,17 +590,32 @@ fn generate_parameter_schema(params: &[Parameter]) -> Result<TokenStream> {
         .map(|param| {
             let name = &param.name;
             let type_str = type_to_json_type(&param.ty).unwrap_or("string");
+            let format = type_to_json_format(&param.ty)
+                .map(|format| quote! { "format": #format, })
+                .unwrap_or_default();
+            let default = param
+                .default
+                .as_deref()
+                .map(|default| {
+                    let value = default_value_tokens(default);
+                    quote! { "default": #value, }
+                })
+                .unwrap_or_default();
 
             if let Some(desc) = &param.description {
                 quote! {
                     (#name, ::serde_json::json!({
                         "type": #type_str,
+                        #format
+                        #default
                         "description": #desc
                     }))
                 }
             } else {
                 quote! {
                     (#name, ::serde_json::json!({
+                        #format
+                        #default
                         "type": #type_str
                     }))
                 }
@@ -352,7 +625,7 @@ fn generate_parameter_schema(params: &[Parameter]) -> Result<TokenStream> {
 
     let required_params: Vec<_> = params
         .iter()
-        .filter(|p| p.required)
+        .filter(|p| p.required && p.default.is_none())
         .map(|p| &p.name)
         .collect();
 
@@ -373,41 +646,104 @@ fn generate_parameter_schema(params: &[Parameter]) -> Result<TokenStream> {
     })
 }
 
+/// JSON Schema `format` for `chrono` and `uuid` types, which serialize as strings.
+fn type_to_json_format(ty: &Type) -> Option<&'static str> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+
+    match type_path.path.segments.last()?.ident.to_string().as_str() {
+        "DateTime" | "NaiveDateTime" => Some("date-time"),
+        "NaiveDate" => Some("date"),
+        "NaiveTime" => Some("time"),
+        "Uuid" => Some("uuid"),
+        _ => None,
+    }
+}
+
 fn type_to_json_type(ty: &Type) -> Result<&'static str> {
     match ty {
         Type::Path(type_path) => {
-            let ident = &type_path
+            let segment = type_path
                 .path
                 .segments
                 .last()
-                .ok_or_else(|| syn::Error::new_spanned(ty, "empty type path"))?
-                .ident;
+                .ok_or_else(|| syn::Error::new_spanned(ty, "empty type path"))?;
+
+            // `Cow<str>` and `Cow<[T]>` serialize like the type they borrow
+            if segment.ident == "Cow"
+                && let syn::PathArguments::AngleBracketed(args) = &segment.arguments
+                && let Some(borrowed) = args.args.iter().find_map(|arg| match arg {
+                    syn::GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+            {
+                return type_to_json_type(borrowed);
+            }
 
-            match ident.to_string().as_str() {
+            match segment.ident.to_string().as_str() {
                 "String" | "str" => Ok("string"),
                 "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
                 | "u128" | "usize" => Ok("integer"),
                 "f32" | "f64" => Ok("number"),
                 "bool" => Ok("boolean"),
                 "Vec" => Ok("array"),
+                _ if type_to_json_format(ty).is_some() => Ok("string"),
                 _ => Ok("object"), // Default to object for complex types
             }
         }
+        Type::Slice(_) => Ok("array"),
         _ => Ok("object"),
     }
 }
 
+/// The `T` of a `Result<T, E>` (or `Result<T>` alias) return type.
+fn result_ok_type(ty: &Type) -> Option<&Type> {
+    if let Type::Path(type_path) = ty
+        && let Some(segment) = type_path.path.segments.last()
+        && segment.ident == "Result"
+        && let syn::PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(syn::GenericArgument::Type(ok_ty)) = args.args.first()
+    {
+        return Some(ok_ty);
+    }
+    None
+}
+
 fn generate_execute_impl(
     fn_name: &syn::Ident,
     context_param: &Option<ContextParam>,
     params: &[Parameter],
     is_async: bool,
+    returns_result: bool,
 ) -> Result<TokenStream> {
     let param_extractions = params.iter().map(|param| {
         let name = &param.name;
         let name_ident = quote::format_ident!("{}", name);
         let ty = &param.ty;
 
+        if let Some(default) = &param.default {
+            let default_value = default_value_tokens(default);
+            let value = quote! {
+                ::serde_json::from_value(
+                    params
+                        .get(#name)
+                        .filter(|v| !v.is_null())
+                        .cloned()
+                        .unwrap_or_else(|| #default_value),
+                )
+                .map_err(|e| LlmError::ToolExecution {
+                    message: format!("Invalid parameter '{}': {:?}", #name, e),
+                    source: Some(Box::new(e)),
+                })?
+            };
+            return if param.required {
+                quote! { let #name_ident: #ty = #value; }
+            } else {
+                quote! { let #name_ident: Option<#ty> = Some(#value); }
+            };
+        }
+
         if param.required {
             quote! {
                 let #name_ident: #ty = params.get(#name)
@@ -439,7 +775,15 @@ fn generate_execute_impl(
     // Otherwise just: fn_name(param1, param2, ...)
     let param_names: Vec<_> = params
         .iter()
-        .map(|p| quote::format_ident!("{}", p.name))
+        .map(|p| {
+            let name = quote::format_ident!("{}", p.name);
+            match (p.borrow, p.required) {
+                (Borrow::None, _) => quote! { #name },
+                (_, true) => quote! { &#name },
+                (Borrow::Deref, false) => quote! { #name.as_deref() },
+                (Borrow::Ref, false) => quote! { #name.as_ref() },
+            }
+        })
         .collect();
 
     let function_call = if let Some(ctx) = context_param {
@@ -455,6 +799,17 @@ fn generate_execute_impl(
         quote! { #fn_name(#(#param_names),*) }
     };
 
+    let unwrap_result = if returns_result {
+        quote! {
+            let result = result.map_err(|e| LlmError::ToolExecution {
+                message: e.to_string(),
+                source: None,
+            })?;
+        }
+    } else {
+        quote! {}
+    };
+
     // Generate context extraction if needed
     let context_extraction = if let Some(ctx) = context_param {
         let ctx_name = quote::format_ident!("{}", ctx.name);
@@ -479,6 +834,7 @@ fn generate_execute_impl(
 
         // Call the function
         let result = #function_call;
+        #unwrap_result
 
         // Convert result to JSON
         ::serde_json::to_value(result)
@@ -1,25 +1,68 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Ident, Result, Token, Type, parse::Parse, parse::ParseStream};
+use syn::{Ident, Path, Result, Token, Type, Visibility, parse::Parse, parse::ParseStream};
 
-/// Parses a toolset definition with optional context type.
+/// Parses a toolset definition with optional context type and namespace.
 /// Syntax:
 /// - `toolset![tool1, tool2]` - context-free toolset
 /// - `toolset![ContextType => tool1, tool2]` - toolset with context type
+/// - `toolset![namespace = weather => tool1, tool2]` - tools registered as `weather.tool1`
+/// - `toolset![ContextType => namespace = weather => tool1, tool2]` - both
+///
+/// Tools may be given as paths (`toolset![tools::get_weather]`). `#[tool]` declares a type
+/// alias of the wrapper under the function's name, so the path also names the wrapper.
 struct ToolsList {
     context_type: Option<Type>,
-    tools: Vec<Ident>,
+    namespace: Option<Ident>,
+    tools: Vec<Path>,
+}
+
+/// Function name of a tool reference, which is also the name the tool is registered under.
+fn tool_ident(tool: &Path) -> &Ident {
+    &tool
+        .segments
+        .last()
+        .expect("a parsed path has at least one segment")
+        .ident
+}
+
+/// The wrapper generated by `#[tool]`, through the alias it declares under the function's name.
+fn wrapper_expr(tool: &Path) -> TokenStream {
+    quote::quote_spanned! {tool_ident(tool).span()=> <#tool>::TOOL }
+}
+
+/// Whether the input starts with `namespace = `.
+fn peek_namespace(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Ident>()
+        .is_ok_and(|ident| ident == "namespace")
+        && fork.peek(Token![=])
+        && !fork.peek(Token![=>])
+}
+
+fn parse_namespace(input: ParseStream) -> Result<Option<Ident>> {
+    if !peek_namespace(input) {
+        return Ok(None);
+    }
+    input.parse::<Ident>()?;
+    input.parse::<Token![=]>()?;
+    let namespace = input.parse::<Ident>()?;
+    input.parse::<Token![=>]>()?;
+    Ok(Some(namespace))
 }
 
 impl Parse for ToolsList {
     fn parse(input: ParseStream) -> Result<Self> {
         // Try to parse "Type =>" prefix for context-aware toolsets
-        let context_type = if input.peek2(Token![=>])
+        let context_type = if peek_namespace(input) {
+            None
+        } else if input.peek2(Token![=>])
             || (input.peek(syn::Ident) && {
                 // Look ahead to check if it's a type followed by =>
                 let fork = input.fork();
                 fork.parse::<Type>().is_ok() && fork.peek(Token![=>])
-            }) {
+            })
+        {
             let ty = input.parse::<Type>()?;
             input.parse::<Token![=>]>()?;
             Some(ty)
@@ -27,10 +70,12 @@ impl Parse for ToolsList {
             None
         };
 
-        // Parse comma-separated tool names
+        let namespace = parse_namespace(input)?;
+
+        // Parse comma-separated tool names or paths
         let mut tools = Vec::new();
         while !input.is_empty() {
-            tools.push(input.parse::<Ident>()?);
+            tools.push(input.parse::<Path>()?);
 
             if input.peek(Token![,]) {
                 input.parse::<Token![,]>()?;
@@ -41,11 +86,53 @@ impl Parse for ToolsList {
 
         Ok(ToolsList {
             context_type,
+            namespace,
             tools,
         })
     }
 }
 
+/// Parses the item form that also declares a typed enum of the tool names.
+/// Syntax:
+/// - `toolset! { pub enum MyTools => tool1, tool2 }` - context-free toolset
+/// - `toolset! { pub enum MyTools: ContextType => tool1, tool2 }` - toolset with context type
+/// - `toolset! { pub enum MyTools => namespace = weather => tool1, tool2 }` - namespaced tools
+struct ToolsEnum {
+    vis: Visibility,
+    name: Ident,
+    tools: ToolsList,
+}
+
+impl Parse for ToolsEnum {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis = input.parse::<Visibility>()?;
+        input.parse::<Token![enum]>()?;
+        let name = input.parse::<Ident>()?;
+
+        let context_type = if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            Some(input.parse::<Type>()?)
+        } else {
+            None
+        };
+        input.parse::<Token![=>]>()?;
+
+        let tools = ToolsList {
+            context_type,
+            namespace: parse_namespace(input)?,
+            tools: input.parse::<ToolsList>()?.tools,
+        };
+
+        Ok(ToolsEnum { vis, name, tools })
+    }
+}
+
+/// Whether the input is the item form (`[pub] enum Name ...`).
+fn is_enum_form(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.parse::<Visibility>().is_ok() && fork.peek(Token![enum])
+}
+
 /// Convert a snake_case function name to PascalCase struct name
 fn to_pascal_case(name: &str) -> String {
     name.split('_')
@@ -60,8 +147,127 @@ fn to_pascal_case(name: &str) -> String {
 }
 
 pub fn tools_impl(input: TokenStream) -> Result<TokenStream> {
-    let tools_list = syn::parse2::<ToolsList>(input)?;
+    let is_enum = syn::parse::Parser::parse2(
+        |input: ParseStream| {
+            let is_enum = is_enum_form(input);
+            input.parse::<TokenStream>()?;
+            Ok(is_enum)
+        },
+        input.clone(),
+    )?;
 
+    if is_enum {
+        tools_enum_impl(syn::parse2::<ToolsEnum>(input)?)
+    } else {
+        // Combined errors expand to several `compile_error!` calls, which need a block
+        // in expression position
+        Ok(
+            toolset_expr(syn::parse2::<ToolsList>(input)?).unwrap_or_else(|error| {
+                let errors = error.to_compile_error();
+                quote! {{ #errors }}
+            }),
+        )
+    }
+}
+
+fn tools_enum_impl(tools_enum: ToolsEnum) -> Result<TokenStream> {
+    let ToolsEnum { vis, name, tools } = tools_enum;
+
+    let variants: Vec<_> = tools
+        .tools
+        .iter()
+        .map(|tool| quote::format_ident!("{}", to_pascal_case(&tool_ident(tool).to_string())))
+        .collect();
+    let tool_names: Vec<_> = tools
+        .tools
+        .iter()
+        .map(tool_ident)
+        .map(|tool_name| match &tools.namespace {
+            Some(namespace) => format!("{namespace}.{tool_name}"),
+            None => tool_name.to_string(),
+        })
+        .collect();
+
+    let toolset_ty = match &tools.context_type {
+        Some(ctx_type) => quote! { rsai::ToolSetBuilder<#ctx_type> },
+        None => quote! { rsai::ToolSet },
+    };
+    let toolset = toolset_expr(tools)?;
+
+    Ok(quote! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #vis enum #name {
+            #( #variants, )*
+        }
+
+        impl #name {
+            /// Every tool in the toolset, in declaration order.
+            pub const ALL: &'static [Self] = &[#( Self::#variants ),*];
+
+            /// Name the tool is registered under.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #( Self::#variants => #tool_names, )*
+                }
+            }
+
+            /// Build the toolset containing every tool of this enum.
+            pub fn toolset() -> #toolset_ty {
+                #toolset
+            }
+        }
+
+        impl rsai::ToolName for #name {
+            fn tool_name(&self) -> &'static str {
+                self.name()
+            }
+        }
+
+        impl From<#name> for rsai::ToolChoice {
+            fn from(tool: #name) -> Self {
+                rsai::ToolChoice::Function {
+                    name: tool.name().to_string(),
+                }
+            }
+        }
+    })
+}
+
+/// Name a tool is sent to providers under: the registered name with the namespace separator
+/// encoded, as `wire_tool_name` in rsai does.
+fn wire_name(namespace: Option<&Ident>, tool: &Path) -> String {
+    let name = tool_ident(tool);
+    match namespace {
+        Some(namespace) => format!("{namespace}__{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// Reject tools listed twice, which would otherwise only fail at runtime registration.
+/// Tools are compared by the name providers receive, with the namespace applied, so the same
+/// function reached through two paths is a duplicate. The error points at both occurrences.
+fn check_duplicate_tools(namespace: Option<&Ident>, tools: &[Path]) -> Result<()> {
+    for (index, tool) in tools.iter().enumerate() {
+        let name = wire_name(namespace, tool);
+        if let Some(first) = tools[..index]
+            .iter()
+            .find(|earlier| wire_name(namespace, earlier) == name)
+        {
+            let mut error = syn::Error::new_spanned(
+                tool,
+                format!("tool `{name}` is listed more than once in this toolset"),
+            );
+            error.combine(syn::Error::new_spanned(
+                first,
+                format!("`{name}` is first listed here"),
+            ));
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+fn toolset_expr(tools_list: ToolsList) -> Result<TokenStream> {
     if tools_list.tools.is_empty() {
         return Err(syn::Error::new(
             proc_macro2::Span::call_site(),
@@ -69,13 +275,24 @@ pub fn tools_impl(input: TokenStream) -> Result<TokenStream> {
         ));
     }
 
-    // Use the same naming logic as the #[tool] macro to reference existing wrapper structs
-    let wrapper_names: Vec<_> = tools_list
-        .tools
+    check_duplicate_tools(tools_list.namespace.as_ref(), &tools_list.tools)?;
+
+    let wrapper_names: Vec<_> = tools_list.tools.iter().map(wrapper_expr).collect();
+
+    // Wrap each tool in a namespace if one was given
+    let tool_exprs: Vec<_> = wrapper_names
         .iter()
-        .map(|tool_name| quote::format_ident!("{}Tool", to_pascal_case(&tool_name.to_string())))
+        .map(|wrapper_name| match &tools_list.namespace {
+            Some(namespace) => {
+                let namespace = namespace.to_string();
+                quote! { rsai::NamespacedTool::new(#namespace, std::sync::Arc::new(#wrapper_name)) }
+            }
+            None => quote! { #wrapper_name },
+        })
         .collect();
 
+    let tool_paths = &tools_list.tools;
+
     // Generate different code based on whether context is present
     let expanded = if let Some(ctx_type) = tools_list.context_type {
         // Context-aware toolset: returns ToolSetBuilder<Ctx> that requires .with_context(ctx)
@@ -85,7 +302,7 @@ pub fn tools_impl(input: TokenStream) -> Result<TokenStream> {
 
                 let mut builder = ToolSetBuilder::<#ctx_type>::new();
                 #(
-                    builder = builder.add_tool(std::sync::Arc::new(#wrapper_names));
+                    builder = builder.add_tool(std::sync::Arc::new(#tool_exprs));
                 )*
                 builder
             }
@@ -98,8 +315,8 @@ pub fn tools_impl(input: TokenStream) -> Result<TokenStream> {
 
                 let registry = ToolRegistry::new();
                 #(
-                    registry.register(std::sync::Arc::new(#wrapper_names))
-                    .expect(&format!("Failed to register tool: {}", stringify!(#wrapper_names)));
+                    registry.register(std::sync::Arc::new(#tool_exprs))
+                    .expect(&format!("Failed to register tool: {}", stringify!(#tool_paths)));
                 )*
 
                 ToolSet {
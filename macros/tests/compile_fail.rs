@@ -9,3 +9,33 @@ fn test_missing_param_error() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/ui/missing_param_description.rs");
 }
+
+#[test]
+fn test_invalid_schema_example_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/invalid_schema_example.rs");
+}
+
+#[test]
+fn test_zero_schema_version_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/zero_schema_version.rs");
+}
+
+#[test]
+fn test_llm_test_requires_async() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/llm_test_not_async.rs");
+}
+
+#[test]
+fn test_toolset_duplicate_tool_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/duplicate_toolset_tool.rs");
+}
+
+#[test]
+fn test_invalid_default_error() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/invalid_default.rs");
+}
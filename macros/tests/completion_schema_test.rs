@@ -0,0 +1,76 @@
+use rsai_macros::completion_schema;
+use schemars::schema_for;
+use serde_json::json;
+
+#[completion_schema]
+struct Plain {
+    city_name: String,
+}
+
+#[completion_schema(rename_all = "camelCase")]
+struct Renamed {
+    city_name: String,
+    feels_like: f64,
+}
+
+#[completion_schema(strict = false)]
+#[allow(dead_code)]
+struct Relaxed {
+    city_name: String,
+}
+
+#[completion_schema(example = r#"{"label": "sunny"}"#, example = r#"{"label": "rain"}"#)]
+#[allow(dead_code)]
+struct WithExamples {
+    label: String,
+}
+
+fn schema<T: schemars::JsonSchema>() -> serde_json::Value {
+    serde_json::to_value(schema_for!(T)).unwrap()
+}
+
+#[test]
+fn test_defaults_deny_unknown_fields() {
+    assert_eq!(schema::<Plain>()["additionalProperties"], false);
+
+    let plain: Plain = serde_json::from_value(json!({ "city_name": "Lisbon" })).unwrap();
+    assert_eq!(plain.city_name, "Lisbon");
+}
+
+#[test]
+fn test_rename_all_applies_to_schema_and_parsing() {
+    let schema = schema::<Renamed>();
+    assert!(schema["properties"].get("cityName").is_some());
+    assert!(schema["properties"].get("city_name").is_none());
+
+    let renamed: Renamed =
+        serde_json::from_value(json!({ "cityName": "Lisbon", "feelsLike": 21.5 })).unwrap();
+    assert_eq!(renamed.city_name, "Lisbon");
+    assert_eq!(renamed.feels_like, 21.5);
+}
+
+#[test]
+fn test_strict_false_allows_unknown_fields() {
+    assert!(schema::<Relaxed>().get("additionalProperties").is_none());
+}
+
+#[test]
+fn test_examples_are_embedded() {
+    assert_eq!(
+        schema::<WithExamples>()["examples"],
+        json!([{ "label": "sunny" }, { "label": "rain" }])
+    );
+}
+
+#[completion_schema(version = 3)]
+#[allow(dead_code)]
+struct Versioned {
+    label: String,
+}
+
+#[test]
+fn test_version_implements_schema_version() {
+    use rsai::migration::SchemaVersion;
+
+    assert_eq!(Versioned::VERSION, 3);
+}
@@ -16,6 +16,24 @@ fn calculate_distance(_from: String, _to: String) -> f64 {
     42.5
 }
 
+toolset! {
+    pub enum TravelTools => get_weather, calculate_distance
+}
+
+mod geo {
+    use rsai_macros::tool;
+
+    /// Look up the country a city is in
+    /// city: The city to look up
+    #[tool]
+    pub fn city_country(city: String) -> String {
+        format!("{city} is in Germany")
+    }
+}
+
+// Re-exporting the function alone is enough for `toolset!`
+pub use geo::city_country;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -180,6 +198,129 @@ mod tests {
         assert_eq!(schemas.len(), 2);
     }
 
+    #[test]
+    fn test_typed_choice_enum() {
+        assert_eq!(TravelTools::GetWeather.name(), "get_weather");
+        assert_eq!(TravelTools::CalculateDistance.name(), "calculate_distance");
+        assert_eq!(
+            TravelTools::ALL,
+            &[TravelTools::GetWeather, TravelTools::CalculateDistance]
+        );
+
+        let choice: rsai::ToolChoice = TravelTools::CalculateDistance.into();
+        assert_eq!(
+            choice,
+            rsai::ToolChoice::Function {
+                name: "calculate_distance".to_string()
+            }
+        );
+
+        let tools = TravelTools::toolset()
+            .tools()
+            .expect("typed toolset should expose generated tools");
+        assert_eq!(tools.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_toolsets_merge() {
+        let toolset = toolset![namespace = weather => get_weather]
+            .merge(toolset![namespace = maps => get_weather, calculate_distance])
+            .expect("namespaced tools should not conflict");
+
+        let mut names: Vec<String> = toolset
+            .tools()
+            .expect("merged toolset should expose tools")
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "maps.calculate_distance",
+                "maps.get_weather",
+                "weather.get_weather"
+            ]
+        );
+
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            call_id: "call_1".to_string(),
+            name: "maps.calculate_distance".to_string(),
+            arguments: json!({"_from": "Berlin", "_to": "Paris"}),
+        };
+        let result = toolset.registry.execute(&call).await.unwrap();
+        assert_eq!(result, json!(42.5));
+    }
+
+    #[test]
+    fn test_lowercase_context_types_are_not_namespaces() {
+        let toolset = toolset![u32 => get_weather].with_context(7);
+        assert_eq!(*toolset.registry.context(), 7);
+        assert_eq!(toolset.tools().unwrap()[0].name, "get_weather");
+
+        let toolset = toolset![u32 => namespace = weather => get_weather].with_context(7);
+        assert_eq!(toolset.tools().unwrap()[0].name, "weather.get_weather");
+    }
+
+    #[tokio::test]
+    async fn test_path_qualified_and_reexported_tools() {
+        let toolset = toolset![geo::city_country, calculate_distance];
+        let mut names: Vec<String> = toolset
+            .tools()
+            .expect("toolset should expose tools")
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["calculate_distance", "city_country"]);
+
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            call_id: "call_1".to_string(),
+            name: "city_country".to_string(),
+            arguments: json!({"city": "Berlin"}),
+        };
+        let result = toolset.registry.execute(&call).await.unwrap();
+        assert_eq!(result, json!("Berlin is in Germany"));
+
+        let reexported = toolset![crate::city_country];
+        assert_eq!(reexported.tools().unwrap()[0].name, "city_country");
+    }
+
+    #[test]
+    fn test_filter_keeps_matching_tools() {
+        let toolset = toolset![get_weather, calculate_distance]
+            .filter(|schema| schema.name.starts_with("get_"))
+            .expect("filtering should succeed");
+
+        let tools = toolset
+            .tools()
+            .expect("filtered toolset should expose tools");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "get_weather");
+    }
+
+    #[test]
+    fn test_merge_detects_conflicts() {
+        let result = toolset![get_weather].merge(toolset![get_weather, calculate_distance]);
+
+        match result {
+            Err(rsai::LlmError::ToolRegistration { tool_name, .. }) => {
+                assert_eq!(tool_name, "get_weather")
+            }
+            Err(other) => panic!("expected registration error, got {other:?}"),
+            Ok(_) => panic!("expected registration error"),
+        }
+
+        let namespaced = toolset![get_weather]
+            .namespaced("weather")
+            .expect("namespacing should succeed")
+            .merge(toolset![get_weather])
+            .expect("namespaced tool should not conflict");
+        assert_eq!(namespaced.tools().unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_tool_invocation_through_registry() {
         let toolset = toolset![get_weather, calculate_distance];
@@ -0,0 +1,12 @@
+use rsai::{tool, toolset};
+
+#[tool]
+/// Get current weather for a city
+/// city: The city to get weather for
+fn get_weather(city: String) -> String {
+    format!("Weather for {city}: 22°C")
+}
+
+fn main() {
+    let _tools = toolset![get_weather, get_weather];
+}
@@ -0,0 +1,11 @@
+use rsai::tool;
+
+#[tool]
+/// Get the weather forecast for a city
+/// city: The city to get weather for
+/// days: Number of days to forecast @default many
+fn forecast(city: String, days: u32) -> String {
+    format!("{days} day forecast for {city}")
+}
+
+fn main() {}
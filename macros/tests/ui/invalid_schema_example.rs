@@ -0,0 +1,8 @@
+use rsai::completion_schema;
+
+#[completion_schema(example = "{ not json }")]
+struct Forecast {
+    city: String,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rsai::llm_test;
+
+#[llm_test]
+fn not_async(case: rsai::testing::TestCase) {
+    let _ = case;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use rsai::completion_schema;
+
+#[completion_schema(version = 0)]
+struct Forecast {
+    city: String,
+}
+
+fn main() {}
@@ -0,0 +1,506 @@
+//! OpenAI-compatible chat completions API (`/chat/completions`).
+//!
+//! Many providers expose this API instead of OpenAI's responses API. This module maps it
+//! through the completions abstraction, so a provider only supplies its
+//! [`CompletionProviderConfig`](super::CompletionProviderConfig).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{CompletionRequestBuilder, ConversationItem};
+use crate::core::{
+    FunctionCallData, LanguageModelUsage, LlmError, ProviderResponse, ResponseContent,
+    StructuredRequest, ToolChoice,
+};
+use crate::provider::Provider;
+use crate::responses::{Format, request::FormatType};
+
+// ============================================================================
+// Chat Completions Request Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ChatTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    /// `None` for assistant messages that only call tools
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+    /// The call a `tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Distinguishes participants with the same role
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub function: ChatFunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatFunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseFormat {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub json_schema: ResponseJsonSchema,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseJsonSchema {
+    pub name: String,
+    pub schema: Value,
+    pub strict: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatTool {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub function: ChatFunction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatFunction {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+// ============================================================================
+// Chat Completions Response Types
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatResponse {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub choices: Vec<ChatChoice>,
+    pub usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatChoice {
+    pub message: ChatResponseMessage,
+    #[allow(dead_code)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatResponseMessage {
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ChatToolCall>,
+    pub refusal: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatUsage {
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptTokensDetails {
+    pub cached_tokens: Option<i32>,
+}
+
+// ============================================================================
+// Request Builder Implementation
+// ============================================================================
+
+/// Builds chat completions requests for `provider`.
+pub struct ChatRequestBuilder {
+    provider: Provider,
+    strict_tools: bool,
+}
+
+impl ChatRequestBuilder {
+    pub fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            strict_tools: true,
+        }
+    }
+
+    /// Leave out the `strict` flag on tools, for providers that reject it.
+    pub fn without_strict_tools(mut self) -> Self {
+        self.strict_tools = false;
+        self
+    }
+}
+
+impl CompletionRequestBuilder for ChatRequestBuilder {
+    type Request = ChatRequest;
+    type Response = ChatResponse;
+
+    fn build_request(
+        &self,
+        request: &StructuredRequest,
+        format: &Format,
+        conversation: &[ConversationItem],
+    ) -> Result<Self::Request, LlmError> {
+        let gen_config = request.generation_config.as_ref();
+        let tool_config = request.tool_config.as_ref();
+
+        let response_format = match &format.format {
+            FormatType::JsonSchema(json_schema) => Some(ResponseFormat {
+                r#type: "json_schema".to_string(),
+                json_schema: ResponseJsonSchema {
+                    name: json_schema.name.clone(),
+                    schema: json_schema.schema.clone(),
+                    strict: true,
+                },
+            }),
+            FormatType::Text { .. } => None,
+        };
+
+        let tools = tool_config
+            .and_then(|tc| tc.tools.as_ref())
+            .filter(|tools| !tools.is_empty())
+            .map(|tools| {
+                tools
+                    .iter()
+                    .map(crate::responses::create_function_tool)
+                    .map(|tool| ChatTool {
+                        r#type: "function".to_string(),
+                        function: ChatFunction {
+                            name: tool.name,
+                            description: tool.description,
+                            parameters: tool.parameters,
+                            strict: self.strict_tools.then_some(tool.strict.unwrap_or(true)),
+                        },
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+        let tool_choice = tools
+            .as_ref()
+            .and(tool_config.and_then(|tc| tc.tool_choice.as_ref()))
+            .map(|choice| match choice {
+                ToolChoice::None => Value::from("none"),
+                ToolChoice::Auto => Value::from("auto"),
+                ToolChoice::Required => Value::from("required"),
+                ToolChoice::Function { name } => serde_json::json!({
+                    "type": "function",
+                    "function": { "name": crate::core::wire_tool_name(name) },
+                }),
+            });
+
+        Ok(ChatRequest {
+            model: request.model.clone(),
+            messages: build_messages(conversation),
+            max_tokens: gen_config.and_then(|c| c.max_tokens),
+            temperature: gen_config.and_then(|c| c.temperature),
+            top_p: gen_config.and_then(|c| c.top_p),
+            stop: gen_config.and_then(|c| c.stop.clone()),
+            response_format,
+            parallel_tool_calls: tools
+                .as_ref()
+                .and(tool_config.and_then(|tc| tc.parallel_tool_calls)),
+            tools,
+            tool_choice,
+        })
+    }
+
+    fn parse_response(&self, response: Self::Response) -> Result<ProviderResponse, LlmError> {
+        let usage = self.extract_usage(&response).unwrap_or(LanguageModelUsage {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            cached_tokens: None,
+        });
+
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| LlmError::Provider {
+                message: format!("No choices in {} response", self.provider),
+                source: None,
+            })?;
+
+        let content = if !message.tool_calls.is_empty() {
+            ResponseContent::FunctionCalls(function_calls(&message.tool_calls))
+        } else if let Some(refusal) = message.refusal {
+            ResponseContent::Refusal(refusal)
+        } else {
+            match message.content {
+                Some(text) if !text.is_empty() => ResponseContent::Text(text),
+                _ => {
+                    return Err(LlmError::Provider {
+                        message: format!("Empty response from {}", self.provider),
+                        source: None,
+                    });
+                }
+            }
+        };
+
+        Ok(ProviderResponse::new(
+            response.id,
+            response.model,
+            self.provider,
+            content,
+            usage,
+        ))
+    }
+
+    fn extract_usage(&self, response: &Self::Response) -> Option<LanguageModelUsage> {
+        response.usage.as_ref().map(|u| LanguageModelUsage {
+            prompt_tokens: u.prompt_tokens,
+            completion_tokens: u.completion_tokens,
+            total_tokens: u.total_tokens,
+            cached_tokens: u
+                .prompt_tokens_details
+                .as_ref()
+                .and_then(|d| d.cached_tokens),
+        })
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        "/chat/completions".to_string()
+    }
+
+    fn extract_function_calls(&self, response: &Self::Response) -> Option<Vec<FunctionCallData>> {
+        let message = &response.choices.first()?.message;
+        (!message.tool_calls.is_empty()).then(|| function_calls(&message.tool_calls))
+    }
+
+    fn extract_text(&self, response: &Self::Response) -> Option<String> {
+        let text = response.choices.first()?.message.content.as_deref()?.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Every tool call becomes its own assistant message, directly followed by its result.
+pub(crate) fn build_messages(conversation: &[ConversationItem]) -> Vec<ChatMessage> {
+    conversation
+        .iter()
+        .map(|item| match item {
+            ConversationItem::Message {
+                role,
+                content,
+                name,
+            } => ChatMessage {
+                role: role.clone(),
+                content: Some(content.clone()),
+                name: name.clone(),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            ConversationItem::FunctionCall {
+                id,
+                name,
+                arguments,
+            } => ChatMessage {
+                role: "assistant".to_string(),
+                content: None,
+                tool_calls: Some(vec![ChatToolCall {
+                    id: id.clone(),
+                    r#type: "function".to_string(),
+                    function: ChatFunctionCall {
+                        name: crate::core::wire_tool_name(name),
+                        arguments: arguments.to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+                name: None,
+            },
+            ConversationItem::FunctionResult { call_id, result } => ChatMessage {
+                role: "tool".to_string(),
+                content: Some(match result {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                }),
+                tool_calls: None,
+                tool_call_id: Some(call_id.clone()),
+                name: None,
+            },
+        })
+        .collect()
+}
+
+/// Arguments that are not valid JSON are passed on as a string for the tool to reject.
+pub(crate) fn function_calls(tool_calls: &[ChatToolCall]) -> Vec<FunctionCallData> {
+    tool_calls
+        .iter()
+        .map(|call| FunctionCallData {
+            id: call.id.clone(),
+            name: call.function.name.clone(),
+            arguments: serde_json::from_str(&call.function.arguments)
+                .unwrap_or_else(|_| Value::String(call.function.arguments.clone())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{GenerationConfig, Tool, ToolConfig};
+    use crate::responses::create_text_format;
+    use serde_json::json;
+
+    fn request_with_tool() -> StructuredRequest {
+        StructuredRequest {
+            model: "grok-4".to_string(),
+            messages: Vec::new(),
+            tool_config: Some(ToolConfig {
+                tools: Some(Box::new([Tool {
+                    name: "weather.lookup".to_string(),
+                    description: Some("Current weather".to_string()),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": { "city": { "type": "string" } }
+                    }),
+                    strict: None,
+                }])),
+                tool_choice: Some(ToolChoice::Function {
+                    name: "weather.lookup".to_string(),
+                }),
+                parallel_tool_calls: Some(false),
+            }),
+            generation_config: Some(GenerationConfig {
+                max_tokens: Some(256),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_build_request_maps_tools_and_conversation() {
+        let conversation = vec![
+            ConversationItem::Message {
+                role: "user".to_string(),
+                content: "Weather in Paris?".to_string(),
+                name: Some("ada".to_string()),
+            },
+            ConversationItem::FunctionCall {
+                id: "call_1".to_string(),
+                name: "weather.lookup".to_string(),
+                arguments: json!({ "city": "Paris" }),
+            },
+            ConversationItem::FunctionResult {
+                call_id: "call_1".to_string(),
+                result: json!({ "celsius": 21 }),
+            },
+        ];
+
+        let request = ChatRequestBuilder::new(Provider::XAI)
+            .without_strict_tools()
+            .build_request(&request_with_tool(), &create_text_format(), &conversation)
+            .unwrap();
+        let body = serde_json::to_value(request).unwrap();
+
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["parallel_tool_calls"], false);
+        assert_eq!(body["tools"][0]["function"]["name"], "weather__lookup");
+        assert_eq!(
+            body["tools"][0]["function"]["parameters"]["required"],
+            json!(["city"])
+        );
+        assert!(body["tools"][0]["function"].get("strict").is_none());
+        assert_eq!(
+            body["tool_choice"],
+            json!({ "type": "function", "function": { "name": "weather__lookup" } })
+        );
+        assert!(body.get("response_format").is_none());
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["name"], "ada");
+        assert_eq!(messages[1]["role"], "assistant");
+        assert!(messages[1].get("name").is_none());
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            r#"{"city":"Paris"}"#
+        );
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+        assert_eq!(messages[2]["content"], r#"{"celsius":21}"#);
+    }
+
+    #[test]
+    fn test_parse_response_prefers_tool_calls_over_text() {
+        let response: ChatResponse = serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "model": "grok-4",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Let me check.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "weather__lookup", "arguments": "{\"city\":\"Paris\"}" }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {
+                "prompt_tokens": 12,
+                "completion_tokens": 8,
+                "total_tokens": 20,
+                "prompt_tokens_details": { "cached_tokens": 4 }
+            }
+        }))
+        .unwrap();
+        let builder = ChatRequestBuilder::new(Provider::XAI);
+
+        assert_eq!(
+            builder.extract_text(&response).as_deref(),
+            Some("Let me check.")
+        );
+        let parsed = builder.parse_response(response).unwrap();
+        assert_eq!(parsed.id, "chatcmpl-1");
+        assert_eq!(parsed.usage.cached_tokens, Some(4));
+        let ResponseContent::FunctionCalls(calls) = parsed.content else {
+            panic!("expected function calls");
+        };
+        assert_eq!(calls[0].arguments, json!({ "city": "Paris" }));
+    }
+}
@@ -6,10 +6,11 @@ use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
     core::{
-        FunctionCallData, HttpClient, HttpClientConfig, InspectorConfig, LlmError,
-        ProviderResponse, StructuredRequest, ToolCall, ToolCallingGuard, ToolRegistry,
+        FunctionCallData, HttpClient, HttpClientConfig, HttpMethod, InspectorConfig,
+        LanguageModelUsage, LlmError, ProviderResponse, StructuredRequest, ToolCall,
+        ToolCallingGuard, ToolRegistry,
     },
-    responses::Format,
+    responses::{Format, request::FormatType},
 };
 
 /// Trait for building provider-specific requests and parsing responses.
@@ -33,19 +34,32 @@ pub trait CompletionRequestBuilder: Send + Sync {
     /// Parse a provider-specific response into a unified ProviderResponse.
     fn parse_response(&self, response: Self::Response) -> Result<ProviderResponse, LlmError>;
 
+    /// Extract the token usage of the response, if the provider reported it.
+    fn extract_usage(&self, response: &Self::Response) -> Option<LanguageModelUsage>;
+
     /// Get the API endpoint for a given model.
     fn endpoint(&self, model: &str) -> String;
 
     /// Extract function calls from the response for tool calling loop.
     /// Returns None if no function calls are present.
     fn extract_function_calls(&self, response: &Self::Response) -> Option<Vec<FunctionCallData>>;
+
+    /// Extract text the model wrote alongside its function calls, if any.
+    fn extract_text(&self, _response: &Self::Response) -> Option<String> {
+        None
+    }
 }
 
 /// An item in the conversation history for the tool calling loop.
 #[derive(Debug, Clone)]
 pub enum ConversationItem {
     /// A regular message (system, user, or assistant)
-    Message { role: String, content: String },
+    Message {
+        role: String,
+        content: String,
+        /// Name of the participant, for providers that distinguish them
+        name: Option<String>,
+    },
     /// A function call made by the model
     FunctionCall {
         id: String,
@@ -64,7 +78,8 @@ pub trait CompletionProviderConfig {
     /// Get the base URL for the API
     fn base_url(&self) -> &str;
 
-    /// Get the authentication header as (name, value) tuple
+    /// Get the authentication header as (name, value) tuple. Not sent when the HTTP config
+    /// has [credentials](HttpClientConfig::credentials), which authenticate instead.
     fn auth_header(&self) -> (String, String);
 
     /// Get additional headers to include with each request
@@ -94,7 +109,7 @@ pub struct CompletionClient<P: CompletionProviderConfig> {
     http: HttpClient,
 }
 
-impl<P: CompletionProviderConfig> CompletionClient<P> {
+impl<P: CompletionProviderConfig + Sync> CompletionClient<P> {
     /// Create a new completion client with the given configuration.
     pub fn new(config: P) -> Result<Self, LlmError> {
         let http_config = config.http_config();
@@ -106,6 +121,20 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
         Ok(Self { config, http })
     }
 
+    /// The authentication and extra headers of the provider. The authentication header is
+    /// left out when [credentials](HttpClientConfig::credentials) are set.
+    fn headers(&self) -> Vec<(String, String)> {
+        let auth = self
+            .config
+            .http_config()
+            .credentials
+            .is_none()
+            .then(|| self.config.auth_header());
+        auth.into_iter()
+            .chain(self.config.extra_headers())
+            .collect()
+    }
+
     /// Make an API request using the given request builder.
     pub async fn make_api_request<B: CompletionRequestBuilder>(
         &self,
@@ -115,12 +144,45 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
     ) -> Result<B::Response, LlmError> {
         let url = format!("{}{}", self.config.base_url(), builder.endpoint(model));
 
-        let mut headers = vec![self.config.auth_header()];
-        headers.extend(self.config.extra_headers());
+        let headers = self.headers();
 
         self.http.post_json(&url, &headers, &request).await
     }
 
+    /// Send `body` unchanged to the endpoint for `model` and parse the response like the
+    /// response to a built request, for request fields rsai doesn't model yet.
+    pub async fn complete_raw<B: CompletionRequestBuilder>(
+        &self,
+        builder: &B,
+        body: serde_json::Value,
+        model: &str,
+    ) -> Result<ProviderResponse, LlmError> {
+        let url = format!("{}{}", self.config.base_url(), builder.endpoint(model));
+
+        let headers = self.headers();
+
+        let response: B::Response = self.http.post_json(&url, &headers, &body).await?;
+        builder.parse_response(response)
+    }
+
+    /// Make a request to a path relative to the base URL, e.g. for resource management APIs.
+    pub async fn request_json<Req, Res>(
+        &self,
+        method: HttpMethod,
+        path: &str,
+        body: Option<&Req>,
+    ) -> Result<Res, LlmError>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        let url = format!("{}{}", self.config.base_url(), path);
+
+        let headers = self.headers();
+
+        self.http.send_json(method, &url, &headers, body).await
+    }
+
     /// Handle the complete tool calling loop until a final response is received.
     pub async fn handle_tool_calling_loop<B: CompletionRequestBuilder, Ctx>(
         &self,
@@ -130,12 +192,36 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
         guard: &mut ToolCallingGuard,
         format: Format,
     ) -> Result<ProviderResponse, LlmError>
+    where
+        Ctx: Send + Sync + 'static,
+    {
+        self.handle_tool_calling_loop_with_conversation(
+            builder,
+            request,
+            tool_registry,
+            guard,
+            format,
+        )
+        .await
+        .map(|(response, _)| response)
+    }
+
+    /// Like [`Self::handle_tool_calling_loop`], but also returns the accumulated conversation
+    /// (initial messages, tool calls and tool results) that led to the final response.
+    pub async fn handle_tool_calling_loop_with_conversation<B: CompletionRequestBuilder, Ctx>(
+        &self,
+        builder: &B,
+        request: StructuredRequest,
+        tool_registry: &ToolRegistry<Ctx>,
+        guard: &mut ToolCallingGuard,
+        format: Format,
+    ) -> Result<(ProviderResponse, Vec<ConversationItem>), LlmError>
     where
         Ctx: Send + Sync + 'static,
     {
         let timeout_duration = guard.timeout;
 
-        match tokio::time::timeout(
+        let error = match crate::core::runtime::timeout(
             timeout_duration,
             self.handle_tool_calling_loop_internal::<B, Ctx>(
                 builder,
@@ -146,12 +232,51 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
             ),
         )
         .await
-        {
-            Ok(result) => result,
-            Err(_) => Err(LlmError::ToolCallTimeout {
-                timeout: timeout_duration,
-            }),
-        }
+        .unwrap_or(Err(LlmError::ToolCallTimeout {
+            timeout: timeout_duration,
+        })) {
+            Ok(output) => return Ok(output),
+            Err(error) => error,
+        };
+        guard.failed(&error).await;
+        Err(error)
+    }
+
+    /// Run the tool loop in text mode, then ask for `format` in a final, tool-free request
+    /// over the accumulated conversation, for providers that reject tools combined with a
+    /// response schema. The usage of the response covers the requests of both phases.
+    pub async fn handle_tool_calling_loop_then_format<B: CompletionRequestBuilder, Ctx>(
+        &self,
+        builder: &B,
+        request: StructuredRequest,
+        tool_registry: &ToolRegistry<Ctx>,
+        guard: &mut ToolCallingGuard,
+        format: Format,
+    ) -> Result<ProviderResponse, LlmError>
+    where
+        Ctx: Send + Sync + 'static,
+    {
+        let (_, conversation) = self
+            .handle_tool_calling_loop_with_conversation(
+                builder,
+                request.clone(),
+                tool_registry,
+                guard,
+                tool_phase_format(&request, format.clone()),
+            )
+            .await?;
+
+        let request = StructuredRequest {
+            tool_config: None,
+            ..request
+        };
+        let api_request = builder.build_request(&request, &format, &conversation)?;
+        let api_response = self
+            .make_api_request(builder, api_request, &request.model)
+            .await?;
+        let mut response = builder.parse_response(api_response)?;
+        response.usage = guard.usage().combined(&response.usage);
+        Ok(response)
     }
 
     /// Internal implementation of the tool calling loop.
@@ -162,12 +287,12 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
         tool_registry: &ToolRegistry<Ctx>,
         guard: &mut ToolCallingGuard,
         format: Format,
-    ) -> Result<ProviderResponse, LlmError>
+    ) -> Result<(ProviderResponse, Vec<ConversationItem>), LlmError>
     where
         Ctx: Send + Sync + 'static,
     {
         // Convert initial messages to conversation items
-        let mut conversation = convert_messages_to_conversation(&request.messages)?;
+        let mut conversation = convert_messages_to_conversation(&guard.start(&request.messages))?;
         let is_parallel = request
             .tool_config
             .as_ref()
@@ -176,17 +301,24 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
 
         loop {
             guard.increment_iteration()?;
+            guard.iteration_started().await;
 
             let api_request = builder.build_request(&request, &format, &conversation)?;
             let api_response = self
                 .make_api_request(builder, api_request, &request.model)
                 .await?;
+            if let Some(usage) = builder.extract_usage(&api_response) {
+                guard.add_usage(&usage);
+            }
 
             // Check for function calls
             let function_calls = builder.extract_function_calls(&api_response);
 
             if let Some(calls) = function_calls.filter(|c| !c.is_empty()) {
                 tracing::info!(count = calls.len(), "Model requested tool execution");
+                if let Some(text) = builder.extract_text(&api_response) {
+                    guard.hooks.thought(&text).await;
+                }
 
                 for call in &calls {
                     // Add function call to conversation
@@ -203,7 +335,7 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
                         name: call.name.clone(),
                         arguments: call.arguments.clone(),
                     };
-                    let result = tool_registry.execute(&tool_call).await?;
+                    let result = guard.execute_tool(tool_registry, &tool_call).await?;
 
                     // Add result to conversation
                     conversation.push(ConversationItem::FunctionResult {
@@ -216,16 +348,36 @@ impl<P: CompletionProviderConfig> CompletionClient<P> {
                         break;
                     }
                 }
+                guard.iteration_finished().await;
+                guard.check_stop()?;
             } else {
                 tracing::debug!("No more tool calls, returning final response");
-                return builder.parse_response(api_response);
+                let response = builder.parse_response(api_response)?;
+                guard.finished(&response).await;
+                return Ok((response, conversation));
             }
         }
     }
 }
 
+/// The format of the first request for `request`: text while tools run, if the structured
+/// answer is asked for afterwards, see
+/// [`handle_tool_calling_loop_then_format`](CompletionClient::handle_tool_calling_loop_then_format).
+pub(crate) fn tool_phase_format(request: &StructuredRequest, format: Format) -> Format {
+    let has_tools = request
+        .tool_config
+        .as_ref()
+        .and_then(|tc| tc.tools.as_ref())
+        .is_some();
+    if has_tools && matches!(format.format, FormatType::JsonSchema(_)) {
+        crate::responses::create_text_format()
+    } else {
+        format
+    }
+}
+
 /// Convert core messages to conversation items.
-fn convert_messages_to_conversation(
+pub(crate) fn convert_messages_to_conversation(
     messages: &[crate::core::ConversationMessage],
 ) -> Result<Vec<ConversationItem>, LlmError> {
     messages
@@ -240,6 +392,7 @@ fn convert_messages_to_conversation(
                 Ok(ConversationItem::Message {
                     role: role.to_string(),
                     content: m.content.clone(),
+                    name: m.name.clone(),
                 })
             }
             crate::core::ConversationMessage::ToolCall(tc) => Ok(ConversationItem::FunctionCall {
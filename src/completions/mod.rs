@@ -1,7 +1,9 @@
 //! Generic completion API abstraction for providers that don't use OpenAI's responses API.
 //!
-//! This module provides infrastructure for completion-style APIs like Google Gemini.
+//! This module provides infrastructure for completion-style APIs like Google Gemini and the
+//! OpenAI-compatible chat completions API.
 
+pub mod chat;
 pub mod client;
 
 pub use client::{
@@ -1,21 +1,75 @@
+pub mod agents;
+mod binary;
 mod builder;
+mod cassette;
+mod coercion;
+mod collector;
+pub mod conversation;
+pub mod credentials;
 mod error;
+pub mod experiment;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod guardrails;
+pub(crate) mod hash;
+mod hedge;
 pub mod http;
+mod lenient;
+mod markdown;
+pub mod memory;
+pub mod migration;
+#[cfg(feature = "profiles")]
+pub mod profile;
+pub mod prompts;
+pub mod rag;
+pub mod redaction;
+pub mod replay;
+pub mod retry;
+pub(crate) mod runtime;
+mod schema;
+mod stop;
+mod stored;
+pub mod tasks;
+pub mod testing;
+mod timings;
 mod tool_guard;
+mod tool_stats;
 mod traits;
+pub mod transform;
+mod transport;
 mod types;
+pub mod usage;
 
-pub use builder::{ApiKey, Inspector, InspectorConfig, LlmBuilder, llm};
+pub use agents::{AgentTool, agent_as_tool};
+pub use binary::{BinaryResponse, Blob};
+pub use builder::{ApiKey, Inspector, InspectorConfig, LlmBuilder, LlmClient, llm};
+pub use cassette::{Cassette, CassetteMode, MatchOn};
+pub use coercion::coerce_arguments;
 
 pub use error::LlmError;
-pub use http::{HttpClient, HttpClientConfig};
-pub use tool_guard::{ToolCallingConfig, ToolCallingGuard};
-pub use traits::{CompletionTarget, LlmProvider, ToolFunction};
+pub use hedge::Hedge;
+pub use http::{Gateway, HttpClient, HttpClientConfig};
+pub use markdown::{CodeBlock, Section};
+pub(crate) use schema::{
+    inline_refs, restore_value, rewrite_root_refs, strict_schema, strict_value, validate_value,
+};
+pub use stop::StopCondition;
+pub use stored::StoredResponses;
+pub use timings::{Timings, ToolTiming};
+pub use tool_guard::{
+    DuplicateCalls, PendingCheckpoint, ToolCache, ToolCallingConfig, ToolCallingGuard,
+    ToolLoopCheckpoint, ToolLoopEvent, ToolLoopHooks,
+};
+pub use tool_stats::ToolStats;
+pub use traits::{CompletionTarget, LlmProvider, ToolFunction, ToolName};
+pub use transport::{HttpMethod, ReqwestTransport, Transport, TransportRequest, TransportResponse};
 
 pub use types::StructuredRequest;
+pub(crate) use types::wire_tool_name;
 pub use types::{
-    BoxFuture, ChatRole, ConversationMessage, Ctx, FunctionCallData, GenerationConfig,
-    LanguageModelUsage, Message, ProviderResponse, ResponseContent, ResponseMetadata,
-    StructuredResponse, TextResponse, Tool, ToolCall, ToolCallResult, ToolChoice, ToolConfig,
+    BoxFuture, ChatRole, Citation, Classification, Consensus, ConversationMessage, Ctx,
+    FunctionCallData, GenerationConfig, LanguageModelUsage, Message, NamespacedTool,
+    ProviderResponse, ResponseContent, ResponseMetadata, StructuredResponse,
+    TOOL_NAMESPACE_SEPARATOR, TextResponse, Tool, ToolCall, ToolCallResult, ToolChoice, ToolConfig,
     ToolRegistry, ToolSet, ToolSetBuilder,
 };
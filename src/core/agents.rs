@@ -0,0 +1,1085 @@
+//! Multi-agent orchestration.
+//!
+//! An [`Agent`] bundles a model, instructions and tools. A [`Router`] runs a conversation
+//! across agents: each agent is offered a `transfer_to_<name>` tool for every agent it may
+//! hand off to, and calling it passes the shared history, including the tool calls and their
+//! results so far, to that agent, which answers next.
+//! [`agent_as_tool`] takes the other route and lets one agent call another like any tool.
+//! [`PlanAndExecute`] has an agent write a plan first and then work through it step by step.
+//!
+//! Runs record a trace of [`TraceEntry`] values: the tools called, their results, handoffs
+//! and, with [`Agent::with_react`], the reasoning the model writes before acting. Their
+//! reports also count the calls of every tool in [`ToolStats`].
+//!
+//! # Example
+//! ```no_run
+//! use rsai::agents::{Agent, Router};
+//! use rsai::{ApiKey, ChatRole, Message, Provider};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let router = Router::new()
+//!     .with_agent(
+//!         Agent::new("triage", Provider::OpenAI, "gpt-4o-mini", ApiKey::Default)
+//!             .with_instructions("Route the customer to the right team.")
+//!             .with_handoff("billing"),
+//!     )
+//!     .with_agent(
+//!         Agent::new("billing", Provider::OpenAI, "gpt-4o", ApiKey::Default)
+//!             .with_description("Handles invoices and refunds")
+//!             .with_instructions("You resolve billing questions."),
+//!     );
+//!
+//! let run = router
+//!     .run(
+//!         "triage",
+//!         vec![Message::new(ChatRole::User, "I was charged twice this month")],
+//!     )
+//!     .await?;
+//! println!("{} answered: {}", run.agent, run.response.text);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio_util::sync::CancellationToken;
+
+use super::builder::{ApiKey, LlmBuilder, llm, private::Completable};
+use super::transport::Transport;
+use super::{
+    BoxFuture, ChatRole, CompletionTarget, ConversationMessage, LlmError, Message, TextResponse,
+    Tool, ToolCall, ToolCallingConfig, ToolFunction, ToolRegistry, ToolSet, ToolStats, tool_stats,
+};
+use crate::provider::Provider;
+
+/// Handoffs allowed in one [`Router::run`] unless changed with
+/// [`Router::with_max_handoffs`].
+const DEFAULT_MAX_HANDOFFS: u32 = 10;
+
+/// Prefix of the tool an agent calls to hand the conversation to another agent.
+const HANDOFF_TOOL_PREFIX: &str = "transfer_to_";
+
+const THOUGHT_PREFIX: &str = "Thought:";
+const ANSWER_PREFIX: &str = "Answer:";
+
+const REACT_PROMPT: &str = "Reason before you act. Write your reasoning on lines starting with \
+    `Thought:` before every tool call. When you are done, write your last thoughts the same way, \
+    then the final answer after `Answer:`.";
+
+/// Whether the reasoning of an agent in ReAct mode stays in its final answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Thoughts {
+    /// Strip `Thought:` lines from the answer; they are only kept on the trace
+    #[default]
+    Hidden,
+    /// Return the answer exactly as the model wrote it
+    Shown,
+}
+
+/// One event of an agent run, for debugging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEntry {
+    /// Reasoning the agent wrote, before a tool call or ahead of its answer.
+    Thought {
+        /// Name of the agent
+        agent: String,
+        /// One thought, without the `Thought:` prefix
+        text: String,
+    },
+    /// A tool call requested by the agent.
+    Action {
+        /// Name of the agent
+        agent: String,
+        /// Name of the tool called
+        tool: String,
+        /// Arguments of the call
+        arguments: serde_json::Value,
+    },
+    /// The result of a tool call.
+    Observation {
+        /// Name of the agent
+        agent: String,
+        /// Name of the tool called
+        tool: String,
+        /// What the tool returned
+        result: serde_json::Value,
+    },
+    /// The conversation was transferred to another agent.
+    Handoff(Handoff),
+    /// The final answer, without hidden thoughts.
+    Answer {
+        /// Name of the agent
+        agent: String,
+        /// The answer text
+        text: String,
+    },
+}
+
+type Trace = Arc<Mutex<Vec<TraceEntry>>>;
+
+fn record(trace: &Trace, entry: TraceEntry) {
+    if let Ok(mut trace) = trace.lock() {
+        trace.push(entry);
+    }
+}
+
+fn take_trace(trace: &Trace) -> Vec<TraceEntry> {
+    trace
+        .lock()
+        .map(|mut trace| std::mem::take(&mut *trace))
+        .unwrap_or_default()
+}
+
+/// Split ReAct output into its `Thought:` lines and the answer.
+///
+/// The answer is the text after the last `Answer:`, or the text outside thoughts when there
+/// is none. Unprefixed lines before `Answer:` continue the previous thought.
+fn split_scratchpad(text: &str) -> (Vec<String>, String) {
+    let (scratchpad, answer) = match text.rfind(ANSWER_PREFIX) {
+        Some(index) => (
+            &text[..index],
+            Some(text[index + ANSWER_PREFIX.len()..].trim()),
+        ),
+        None => (text, None),
+    };
+
+    let mut thoughts: Vec<String> = Vec::new();
+    let mut rest = Vec::new();
+    for line in scratchpad.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match (line.strip_prefix(THOUGHT_PREFIX), thoughts.last_mut()) {
+            (Some(thought), _) => thoughts.push(thought.trim().to_string()),
+            (None, Some(thought)) if answer.is_some() => {
+                thought.push('\n');
+                thought.push_str(line);
+            }
+            (None, _) => rest.push(line),
+        }
+    }
+
+    let answer = answer.map_or_else(|| rest.join("\n"), str::to_string);
+    (thoughts, answer)
+}
+
+/// A sub-agent exposed as a tool, created with [`agent_as_tool`].
+pub struct AgentTool<F> {
+    name: String,
+    description: String,
+    build: F,
+}
+
+/// Wrap a whole `llm` pipeline as a tool, so a planner agent can delegate to specialist
+/// sub-agents.
+///
+/// The model calls the tool with a `task` string. `build` turns it into a ready-to-complete
+/// builder with its own provider, model, system prompt and toolset, and the sub-agent's text
+/// answer becomes the tool result. The sub-agent runs inside the parent's tool call: parallel
+/// tool calls run sub-agents concurrently, and aborting the parent request cancels them.
+///
+/// ```rust,no_run
+/// # use rsai::{agent_as_tool, llm, ApiKey, ChatRole, Message, Provider, TextResponse, ToolRegistry};
+/// # use std::sync::Arc;
+/// # async fn example() -> Result<(), rsai::LlmError> {
+/// let researcher = agent_as_tool(
+///     "researcher",
+///     "Research a topic and summarize the findings",
+///     |task| {
+///         Ok(llm::with(Provider::OpenAI)
+///             .api_key(ApiKey::Default)?
+///             .model("gpt-4o-mini")
+///             .messages(vec![
+///                 Message::new(ChatRole::System, "You are a meticulous researcher."),
+///                 Message::new(ChatRole::User, task),
+///             ]))
+///     },
+/// );
+///
+/// let registry = ToolRegistry::new();
+/// registry.register(Arc::new(researcher))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn agent_as_tool<F, State, Ctx>(
+    name: impl Into<String>,
+    description: impl Into<String>,
+    build: F,
+) -> AgentTool<F>
+where
+    F: Fn(String) -> Result<LlmBuilder<State, Ctx>, LlmError> + Send + Sync,
+    State: Completable + Send + Sync,
+    Ctx: Send + Sync + 'static,
+{
+    AgentTool {
+        name: name.into(),
+        description: description.into(),
+        build,
+    }
+}
+
+impl<F, State, Ctx, ParentCtx> ToolFunction<ParentCtx> for AgentTool<F>
+where
+    F: Fn(String) -> Result<LlmBuilder<State, Ctx>, LlmError> + Send + Sync,
+    State: Completable + Send + Sync,
+    Ctx: Send + Sync + 'static,
+    ParentCtx: Send + Sync,
+{
+    fn schema(&self) -> Tool {
+        Tool {
+            name: self.name.clone(),
+            description: Some(self.description.clone()),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "task": {
+                        "type": "string",
+                        "description": "Self-contained description of the task to delegate"
+                    }
+                },
+                "required": ["task"],
+                "additionalProperties": false
+            }),
+            strict: Some(true),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a ParentCtx,
+        params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        Box::pin(async move {
+            let task = params
+                .get("task")
+                .and_then(|task| task.as_str())
+                .ok_or_else(|| LlmError::ToolExecution {
+                    message: format!("{} requires a string `task` argument", self.name),
+                    source: None,
+                })?;
+
+            let response = (self.build)(task.to_string())?
+                .complete::<TextResponse>()
+                .await
+                .map_err(|e| LlmError::ToolExecution {
+                    message: format!("Agent {} failed: {e}", self.name),
+                    source: Some(Box::new(e)),
+                })?;
+
+            Ok(serde_json::Value::String(response.text))
+        })
+    }
+}
+
+/// A model with its own instructions and tools, run by a [`Router`].
+pub struct Agent {
+    name: String,
+    description: Option<String>,
+    provider: Provider,
+    model: String,
+    api_key: ApiKey,
+    instructions: Option<String>,
+    tools: Option<ToolSet>,
+    handoffs: Vec<String>,
+    react: Option<Thoughts>,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+/// Outcome of [`Agent::run_traced`].
+#[derive(Debug)]
+pub struct AgentRun {
+    /// The final answer, without hidden thoughts.
+    pub response: TextResponse,
+    /// Everything the agent did, in order.
+    pub trace: Vec<TraceEntry>,
+    /// Calls of every tool during the run, by tool name.
+    pub tool_stats: HashMap<String, ToolStats>,
+}
+
+impl Agent {
+    /// Create an agent named `name` that answers with `model` of `provider`.
+    pub fn new(
+        name: impl Into<String>,
+        provider: Provider,
+        model: impl Into<String>,
+        api_key: ApiKey,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            provider,
+            model: model.into(),
+            api_key,
+            instructions: None,
+            tools: None,
+            handoffs: Vec::new(),
+            react: None,
+            transport: None,
+        }
+    }
+
+    /// What the agent is for, shown to agents that can hand off to it.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// System prompt sent ahead of the shared history whenever this agent answers.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Tools the agent may call, on top of its handoff tools.
+    pub fn with_tools(mut self, toolset: ToolSet) -> Self {
+        self.tools = Some(toolset);
+        self
+    }
+
+    /// Allow this agent to transfer the conversation to the agent named `agent`.
+    pub fn with_handoff(mut self, agent: impl Into<String>) -> Self {
+        self.handoffs.push(agent.into());
+        self
+    }
+
+    /// ReAct mode: ask the model to write `Thought:` lines before acting and to mark its
+    /// final answer with `Answer:`. The thoughts are recorded as [`TraceEntry::Thought`];
+    /// `thoughts` decides whether they also stay in the answer text.
+    pub fn with_react(mut self, thoughts: Thoughts) -> Self {
+        self.react = Some(thoughts);
+        self
+    }
+
+    /// Send the agent's requests through a custom transport, e.g. a [`Cassette`](crate::Cassette).
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Name of the agent, used for handoffs and on the trace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Answer `messages` on its own, without handoffs.
+    pub async fn run(&self, messages: Vec<Message>) -> Result<TextResponse, LlmError> {
+        Ok(self.run_traced(messages).await?.response)
+    }
+
+    /// Like [`run`](Self::run), but also returns the trace of the run.
+    pub async fn run_traced(&self, messages: Vec<Message>) -> Result<AgentRun, LlmError> {
+        let trace = Trace::default();
+        let (response, tool_stats) =
+            tool_stats::collect(self.complete::<TextResponse>(messages, None, None, Some(&trace)))
+                .await;
+        let response = self.answer(response?, &trace);
+        Ok(AgentRun {
+            response,
+            trace: take_trace(&trace),
+            tool_stats,
+        })
+    }
+
+    /// Run a request. With a `trace`, tool calls are recorded and ReAct mode applies. With a
+    /// `handover`, its tool history follows `history` and the turn ends on a handoff.
+    async fn complete<T: CompletionTarget + Send>(
+        &self,
+        history: Vec<Message>,
+        handoff_tools: Option<ToolSet>,
+        handover: Option<&Arc<Handover>>,
+        trace: Option<&Trace>,
+    ) -> Result<T::Output, LlmError> {
+        let react = trace.is_some() && self.react.is_some();
+        let messages = self
+            .instructions
+            .iter()
+            .cloned()
+            .chain(react.then(|| REACT_PROMPT.to_string()))
+            .map(|content| Message::new(ChatRole::System, content))
+            .chain(history)
+            .collect();
+        let builder = llm::with(self.provider)
+            .api_key(self.api_key.clone())?
+            .model(&self.model)
+            .messages(messages)
+            .tool_history(handover.map(|h| h.tool_history()).unwrap_or_default());
+
+        let toolset = match (&self.tools, handoff_tools) {
+            // Copy the agent's tools so the handoff tools do not leak into its toolset
+            (Some(tools), Some(handoffs)) => Some(tools.duplicate()?.merge(handoffs)?),
+            (Some(tools), None) => Some(tools.scoped(())),
+            (None, handoffs) => handoffs,
+        };
+
+        match (toolset, trace) {
+            (Some(toolset), Some(trace)) => {
+                let config = self.tracing_config(trace);
+                let config = match handover {
+                    Some(handover) => handover.watch(config),
+                    None => config,
+                };
+                self.configure(builder.tools(toolset))
+                    .tool_calling(config)
+                    .complete::<T>()
+                    .await
+            }
+            (Some(toolset), None) => self.configure(builder.tools(toolset)).complete::<T>().await,
+            (None, _) => self.configure(builder).complete::<T>().await,
+        }
+    }
+
+    /// Tool loop hooks that record thoughts, actions and observations on `trace`.
+    fn tracing_config(&self, trace: &Trace) -> ToolCallingConfig {
+        let (thoughts, actions, observations) = (trace.clone(), trace.clone(), trace.clone());
+        let (agent, action_agent, observation_agent) =
+            (self.name.clone(), self.name.clone(), self.name.clone());
+
+        ToolCallingConfig::default()
+            .on_thought(move |text| {
+                let (trace, agent) = (thoughts.clone(), agent.clone());
+                async move {
+                    let (mut entries, rest) = split_scratchpad(&text);
+                    entries.extend((!rest.is_empty()).then_some(rest));
+                    for text in entries {
+                        let agent = agent.clone();
+                        record(&trace, TraceEntry::Thought { agent, text });
+                    }
+                }
+            })
+            .on_tool_call(move |call| {
+                let (trace, agent) = (actions.clone(), action_agent.clone());
+                async move {
+                    record(
+                        &trace,
+                        TraceEntry::Action {
+                            agent,
+                            tool: call.name,
+                            arguments: call.arguments,
+                        },
+                    );
+                }
+            })
+            .on_tool_result(move |call, result| {
+                let (trace, agent) = (observations.clone(), observation_agent.clone());
+                async move {
+                    record(
+                        &trace,
+                        TraceEntry::Observation {
+                            agent,
+                            tool: call.name,
+                            result,
+                        },
+                    );
+                }
+            })
+    }
+
+    /// Record the thoughts in a ReAct reply and return the answer text to show.
+    fn scratchpad(&self, text: &str, trace: &Trace) -> String {
+        let Some(thoughts) = self.react else {
+            return text.to_string();
+        };
+        let (entries, answer) = split_scratchpad(text);
+        for text in entries {
+            let agent = self.name.clone();
+            record(trace, TraceEntry::Thought { agent, text });
+        }
+        match thoughts {
+            Thoughts::Hidden => answer,
+            Thoughts::Shown => text.to_string(),
+        }
+    }
+
+    /// Record the final reply on `trace`, hiding thoughts if configured.
+    fn answer(&self, mut response: TextResponse, trace: &Trace) -> TextResponse {
+        response.text = self.scratchpad(&response.text, trace);
+        record(
+            trace,
+            TraceEntry::Answer {
+                agent: self.name.clone(),
+                text: response.text.clone(),
+            },
+        );
+        response
+    }
+
+    fn configure<State: Completable, Ctx: Send + Sync + 'static>(
+        &self,
+        builder: LlmBuilder<State, Ctx>,
+    ) -> LlmBuilder<State, Ctx> {
+        match &self.transport {
+            Some(transport) => builder.transport(transport.clone()),
+            None => builder,
+        }
+    }
+}
+
+/// One transfer of the conversation between agents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handoff {
+    /// Name of the agent that handed off
+    pub from: String,
+    /// Name of the agent that took over
+    pub to: String,
+}
+
+/// Outcome of [`Router::run`].
+#[derive(Debug)]
+pub struct RouterRun {
+    /// Name of the agent that gave the final answer.
+    pub agent: String,
+    /// The final answer, without hidden thoughts.
+    pub response: TextResponse,
+    /// The shared history: the messages of the run, the tool calls and results of the agents,
+    /// then the final answer.
+    pub messages: Vec<ConversationMessage>,
+    /// Handoffs in the order they happened.
+    pub handoffs: Vec<Handoff>,
+    /// Everything the agents did, in order.
+    pub trace: Vec<TraceEntry>,
+    /// Calls of every tool during the run, by tool name, handoff tools included.
+    pub tool_stats: HashMap<String, ToolStats>,
+}
+
+/// Runs a conversation across [`Agent`]s that hand off to each other.
+pub struct Router {
+    agents: HashMap<String, Agent>,
+    max_handoffs: u32,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Router {
+    /// Create a router without agents.
+    pub fn new() -> Self {
+        Self {
+            agents: HashMap::new(),
+            max_handoffs: DEFAULT_MAX_HANDOFFS,
+        }
+    }
+
+    /// Add an agent, replacing any agent with the same name.
+    pub fn with_agent(mut self, agent: Agent) -> Self {
+        self.agents.insert(agent.name.clone(), agent);
+        self
+    }
+
+    /// Fail with [`LlmError::HandoffLimit`] once the conversation has been transferred
+    /// `max_handoffs` times without an answer.
+    pub fn with_max_handoffs(mut self, max_handoffs: u32) -> Self {
+        self.max_handoffs = max_handoffs;
+        self
+    }
+
+    /// Start the conversation with the agent named `agent`.
+    ///
+    /// When the active agent calls a handoff tool, its turn ends once the tools of that
+    /// iteration ran, without another request to its model. The target agent answers next,
+    /// with the tool calls and results of the earlier agents after the messages. If several
+    /// handoffs are called in one turn, the last one wins.
+    pub async fn run(&self, agent: &str, messages: Vec<Message>) -> Result<RouterRun, LlmError> {
+        let (run, tool_stats) = tool_stats::collect(self.route(agent, messages)).await;
+        Ok(RouterRun { tool_stats, ..run? })
+    }
+
+    async fn route(&self, agent: &str, messages: Vec<Message>) -> Result<RouterRun, LlmError> {
+        let mut current = self.agent(agent)?;
+        let mut handoffs = Vec::new();
+        let mut tool_history = Vec::new();
+        let trace = Trace::default();
+
+        loop {
+            let handover = Arc::new(Handover::new(tool_history));
+            let handoff_tools = self.handoff_tools(current, &handover)?;
+            let response = current
+                .complete::<TextResponse>(
+                    messages.clone(),
+                    handoff_tools,
+                    Some(&handover),
+                    Some(&trace),
+                )
+                .await;
+            tool_history = handover.tool_history();
+
+            let next = handover
+                .target
+                .lock()
+                .map_err(|_| LlmError::ToolRegistryAccess {
+                    message: "Handoff target lock poisoned".to_string(),
+                })?
+                .take();
+            let next = match (response, next) {
+                (Ok(response), None) => {
+                    let response = current.answer(response, &trace);
+                    let answer = Message::new(ChatRole::Assistant, response.text.clone());
+                    let messages = messages
+                        .into_iter()
+                        .map(ConversationMessage::Chat)
+                        .chain(tool_history)
+                        .chain([ConversationMessage::Chat(answer)])
+                        .collect();
+                    return Ok(RouterRun {
+                        agent: current.name.clone(),
+                        response,
+                        messages,
+                        handoffs,
+                        trace: take_trace(&trace),
+                        tool_stats: HashMap::new(),
+                    });
+                }
+                // The handoff tool stopped the tool loop after its iteration
+                (Ok(_) | Err(LlmError::Aborted), Some(next)) => next,
+                (Err(error), _) => return Err(error),
+            };
+
+            if handoffs.len() >= self.max_handoffs as usize {
+                return Err(LlmError::HandoffLimit {
+                    limit: self.max_handoffs,
+                });
+            }
+            let handoff = Handoff {
+                from: current.name.clone(),
+                to: next.clone(),
+            };
+            record(&trace, TraceEntry::Handoff(handoff.clone()));
+            handoffs.push(handoff);
+            current = self.agent(&next)?;
+        }
+    }
+
+    fn agent(&self, name: &str) -> Result<&Agent, LlmError> {
+        self.agents
+            .get(name)
+            .ok_or_else(|| LlmError::Builder(format!("Unknown agent '{name}'")))
+    }
+
+    fn handoff_tools(
+        &self,
+        agent: &Agent,
+        handover: &Arc<Handover>,
+    ) -> Result<Option<ToolSet>, LlmError> {
+        if agent.handoffs.is_empty() {
+            return Ok(None);
+        }
+
+        let registry = ToolRegistry::new();
+        for name in &agent.handoffs {
+            let to = self.agent(name)?;
+            registry.register(Arc::new(HandoffTool {
+                agent: to.name.clone(),
+                description: to.description.clone(),
+                handover: handover.clone(),
+            }))?;
+        }
+        Ok(Some(ToolSet { registry }))
+    }
+}
+
+/// State of one agent's turn in a [`Router`] conversation, shared with its tool loop.
+struct Handover {
+    /// Agent to transfer to, set by the last handoff tool called
+    target: Mutex<Option<String>>,
+    /// Cancelled by a handoff tool to end the tool loop after its iteration
+    stop: CancellationToken,
+    /// Tool calls and results of the shared history, updated after each iteration
+    tool_history: Mutex<Vec<ConversationMessage>>,
+}
+
+impl Handover {
+    fn new(tool_history: Vec<ConversationMessage>) -> Self {
+        Self {
+            target: Mutex::new(None),
+            stop: CancellationToken::new(),
+            tool_history: Mutex::new(tool_history),
+        }
+    }
+
+    fn tool_history(&self) -> Vec<ConversationMessage> {
+        self.tool_history
+            .lock()
+            .map(|history| history.clone())
+            .unwrap_or_default()
+    }
+
+    /// Have the tool loop of `config` keep the tool history and stop on a handoff.
+    fn watch(self: &Arc<Self>, config: ToolCallingConfig) -> ToolCallingConfig {
+        let handover = self.clone();
+        config
+            .stop_after_iteration(self.stop.clone())
+            .on_checkpoint(move |checkpoint| {
+                let handover = handover.clone();
+                async move {
+                    // The checkpoint starts from the request, which ends with the earlier history
+                    let history = checkpoint
+                        .messages
+                        .into_iter()
+                        .filter(|message| !matches!(message, ConversationMessage::Chat(_)))
+                        .collect();
+                    if let Ok(mut tool_history) = handover.tool_history.lock() {
+                        *tool_history = history;
+                    }
+                }
+            })
+    }
+}
+
+/// Records which agent to transfer to when called and ends the turn.
+struct HandoffTool {
+    agent: String,
+    description: Option<String>,
+    handover: Arc<Handover>,
+}
+
+impl ToolFunction for HandoffTool {
+    fn schema(&self) -> Tool {
+        let description = match &self.description {
+            Some(description) => format!(
+                "Transfer the conversation to the {} agent: {description}",
+                self.agent
+            ),
+            None => format!("Transfer the conversation to the {} agent", self.agent),
+        };
+        Tool {
+            name: format!("{HANDOFF_TOOL_PREFIX}{}", self.agent),
+            description: Some(description),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {},
+                "required": [],
+                "additionalProperties": false
+            }),
+            strict: Some(true),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a (),
+        _params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        Box::pin(async move {
+            *self
+                .handover
+                .target
+                .lock()
+                .map_err(|_| LlmError::ToolExecution {
+                    message: "Handoff target lock poisoned".to_string(),
+                    source: None,
+                })? = Some(self.agent.clone());
+            self.handover.stop.cancel();
+            Ok(serde_json::Value::String(format!(
+                "Transferred the conversation to {}.",
+                self.agent
+            )))
+        })
+    }
+}
+
+/// Replans allowed in one [`PlanAndExecute::run`] unless changed with
+/// [`PlanAndExecute::with_max_replans`].
+const DEFAULT_MAX_REPLANS: u32 = 2;
+
+const PLANNER_PROMPT: &str = "Break the user's goal into a short list of steps. \
+    A step either calls one of the tools below, with its arguments as a JSON object string, \
+    or leaves `tool` and `arguments` null to be carried out by you in writing.";
+
+/// One step of a plan written by [`PlanAndExecute`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Step {
+    /// What the step accomplishes
+    pub description: String,
+    /// Tool to call, or null to have the model carry out the step
+    pub tool: Option<String>,
+    /// Tool arguments as a JSON object string, or null for model steps
+    pub arguments: Option<String>,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct Plan {
+    /// Steps in the order they should run
+    steps: Vec<Step>,
+}
+
+/// How a step of a plan ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    /// The tool result, or the model's text as a JSON string.
+    Completed(serde_json::Value),
+    Failed(String),
+}
+
+/// A step that was run, with its outcome.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepReport {
+    pub step: Step,
+    pub outcome: StepOutcome,
+}
+
+/// Outcome of [`PlanAndExecute::run`].
+#[derive(Debug)]
+pub struct PlanRun {
+    pub goal: String,
+    /// Every step that was run, including failed ones, in order.
+    pub steps: Vec<StepReport>,
+    /// How often the plan was rewritten after a failed step.
+    pub replans: u32,
+    /// The final answer written from the step results.
+    pub answer: TextResponse,
+    /// Calls of every tool during the run, by tool name.
+    pub tool_stats: HashMap<String, ToolStats>,
+}
+
+type StepHook = Arc<dyn Fn(&StepReport) + Send + Sync>;
+
+/// Runs an [`Agent`] as plan-then-execute instead of a single tool loop.
+///
+/// A first request asks the agent for a [`Step`] list. Steps naming a tool call it directly
+/// from the agent's toolset; the other steps are answered by the model, which may still use
+/// its tools. When a step fails, the agent writes a new plan for the remaining work, up to
+/// [`with_max_replans`](Self::with_max_replans) times. A last request turns the step results
+/// into the answer.
+pub struct PlanAndExecute {
+    agent: Agent,
+    max_replans: u32,
+    on_step: Option<StepHook>,
+}
+
+impl PlanAndExecute {
+    pub fn new(agent: Agent) -> Self {
+        Self {
+            agent,
+            max_replans: DEFAULT_MAX_REPLANS,
+            on_step: None,
+        }
+    }
+
+    pub fn with_max_replans(mut self, max_replans: u32) -> Self {
+        self.max_replans = max_replans;
+        self
+    }
+
+    /// Called after every step, e.g. to report progress.
+    pub fn on_step<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&StepReport) + Send + Sync + 'static,
+    {
+        self.on_step = Some(Arc::new(hook));
+        self
+    }
+
+    /// Plan and carry out `goal`. Fails with [`LlmError::PlanStep`] when a step still fails
+    /// after all replans are used.
+    pub async fn run(&self, goal: impl Into<String>) -> Result<PlanRun, LlmError> {
+        let (run, tool_stats) = tool_stats::collect(self.carry_out(goal.into())).await;
+        Ok(PlanRun { tool_stats, ..run? })
+    }
+
+    async fn carry_out(&self, goal: String) -> Result<PlanRun, LlmError> {
+        let mut reports: Vec<StepReport> = Vec::new();
+        let mut replans = 0;
+        let mut plan = self.plan(&goal, &reports, None).await?;
+
+        'plan: loop {
+            for step in plan {
+                let outcome = match self.execute(&goal, &reports, &step).await {
+                    Ok(output) => StepOutcome::Completed(output),
+                    Err(e) => StepOutcome::Failed(e.to_string()),
+                };
+                let report = StepReport { step, outcome };
+                if let Some(hook) = &self.on_step {
+                    hook(&report);
+                }
+                let failure = match &report.outcome {
+                    StepOutcome::Failed(message) => Some(message.clone()),
+                    StepOutcome::Completed(_) => None,
+                };
+                let description = report.step.description.clone();
+                reports.push(report);
+
+                if let Some(message) = failure {
+                    if replans >= self.max_replans {
+                        return Err(LlmError::PlanStep {
+                            step: description,
+                            message,
+                        });
+                    }
+                    replans += 1;
+                    plan = self.plan(&goal, &reports, Some(&description)).await?;
+                    continue 'plan;
+                }
+            }
+            break;
+        }
+
+        let answer = self
+            .agent
+            .complete::<TextResponse>(
+                progress_messages(
+                    &goal,
+                    &reports,
+                    "Using these results, write the final answer to the goal.",
+                ),
+                None,
+                None,
+                None,
+            )
+            .await?;
+
+        Ok(PlanRun {
+            goal,
+            steps: reports,
+            replans,
+            answer,
+            tool_stats: HashMap::new(),
+        })
+    }
+
+    async fn plan(
+        &self,
+        goal: &str,
+        reports: &[StepReport],
+        failed: Option<&str>,
+    ) -> Result<Vec<Step>, LlmError> {
+        let tools: Vec<serde_json::Value> = match &self.agent.tools {
+            Some(toolset) => toolset
+                .tools()?
+                .into_iter()
+                .map(|tool| {
+                    serde_json::json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let tools = serde_json::Value::Array(tools);
+        let mut messages = vec![Message::new(
+            ChatRole::System,
+            format!("{PLANNER_PROMPT}\n\nTools: {tools}"),
+        )];
+        messages.extend(match failed {
+            Some(step) => progress_messages(
+                goal,
+                reports,
+                &format!("The step '{step}' failed. Plan only the steps still needed."),
+            ),
+            None => vec![user_message(goal.to_string())],
+        });
+
+        let plan = self
+            .agent
+            .complete::<Plan>(messages, None, None, None)
+            .await?;
+        Ok(plan.content.steps)
+    }
+
+    async fn execute(
+        &self,
+        goal: &str,
+        reports: &[StepReport],
+        step: &Step,
+    ) -> Result<serde_json::Value, LlmError> {
+        let Some(tool) = &step.tool else {
+            let response = self
+                .agent
+                .complete::<TextResponse>(
+                    progress_messages(
+                        goal,
+                        reports,
+                        &format!(
+                            "Carry out this step and reply with its result: {}",
+                            step.description
+                        ),
+                    ),
+                    None,
+                    None,
+                    None,
+                )
+                .await?;
+            return Ok(serde_json::Value::String(response.text));
+        };
+
+        let toolset = self
+            .agent
+            .tools
+            .as_ref()
+            .ok_or_else(|| LlmError::ToolNotFound(tool.clone()))?;
+        let arguments = match &step.arguments {
+            Some(arguments) => {
+                serde_json::from_str(arguments).map_err(|e| LlmError::ToolExecution {
+                    message: format!("Invalid arguments for {tool}: {e}"),
+                    source: Some(Box::new(e)),
+                })?
+            }
+            None => serde_json::json!({}),
+        };
+        toolset
+            .registry
+            .execute(&ToolCall {
+                id: format!("step_{}", reports.len() + 1),
+                call_id: format!("step_{}", reports.len() + 1),
+                name: tool.clone(),
+                arguments,
+            })
+            .await
+    }
+}
+
+fn user_message(content: String) -> Message {
+    Message::new(ChatRole::User, content)
+}
+
+/// The goal, the results of the steps run so far and what to do next.
+fn progress_messages(goal: &str, reports: &[StepReport], instruction: &str) -> Vec<Message> {
+    let progress: String = reports
+        .iter()
+        .enumerate()
+        .map(|(index, report)| {
+            let outcome = match &report.outcome {
+                StepOutcome::Completed(output) => format!("done: {output}"),
+                StepOutcome::Failed(message) => format!("failed: {message}"),
+            };
+            format!("{}. {} ({outcome})\n", index + 1, report.step.description)
+        })
+        .collect();
+
+    vec![
+        user_message(goal.to_string()),
+        user_message(format!("Steps so far:\n{progress}\n{instruction}")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_scratchpad() {
+        assert_eq!(
+            split_scratchpad(
+                "Thought: Check the docs\nThey cover this\nThought: Done\nAnswer: Yes"
+            ),
+            (
+                vec![
+                    "Check the docs\nThey cover this".to_string(),
+                    "Done".to_string()
+                ],
+                "Yes".to_string()
+            )
+        );
+        assert_eq!(
+            split_scratchpad("Thought: Easy one\nThe answer is 4"),
+            (vec!["Easy one".to_string()], "The answer is 4".to_string())
+        );
+        assert_eq!(
+            split_scratchpad("Just an answer"),
+            (Vec::new(), "Just an answer".to_string())
+        );
+    }
+}
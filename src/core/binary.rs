@@ -0,0 +1,183 @@
+//! Binary answers such as generated images or audio.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::Value;
+
+use super::error::LlmError;
+use super::traits::CompletionTarget;
+use super::types::{LanguageModelUsage, ProviderResponse, ResponseContent, ResponseMetadata};
+use crate::responses::{self, request::Format};
+
+/// Decoded binary data of an answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    /// MIME type such as `image/png`
+    pub mime_type: String,
+    /// The decoded bytes, not base64
+    pub data: Vec<u8>,
+}
+
+/// The binary outputs of an answer, e.g. images of an image generation model.
+///
+/// Blobs come from inline data parts of the provider, or from `data:<mime>;base64,...` URLs in
+/// a text answer for providers that only return text. Completing fails with
+/// [`LlmError::Parse`] if the answer has neither.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryResponse {
+    /// The binary outputs in the order the model returned them
+    pub blobs: Vec<Blob>,
+    /// Text the model answered alongside the blobs, e.g. a caption. For answers with data
+    /// URLs, the whole text including the URLs.
+    pub text: Option<String>,
+    /// Token usage of the request
+    pub usage: LanguageModelUsage,
+    /// Provider, model and timing of the request
+    pub metadata: ResponseMetadata,
+}
+
+impl CompletionTarget for BinaryResponse {
+    type Output = BinaryResponse;
+
+    fn format() -> Result<Format, LlmError> {
+        Ok(responses::create_text_format())
+    }
+
+    fn format_example(output: Value) -> String {
+        match output {
+            Value::String(text) => text,
+            other => other.to_string(),
+        }
+    }
+
+    fn parse_response(res: ProviderResponse) -> Result<Self::Output, LlmError> {
+        let metadata = res.metadata();
+        let (blobs, text) = match res.content {
+            ResponseContent::Binary { blobs, text } => (blobs, text),
+            ResponseContent::Text(text) => (data_urls(&text), Some(text)),
+            ResponseContent::FunctionCalls(_) => {
+                return Err(LlmError::Provider {
+                    message: "Function call response received when expecting binary output"
+                        .to_string(),
+                    source: None,
+                });
+            }
+            ResponseContent::Refusal(refusal) => {
+                return Err(LlmError::Api {
+                    message: format!("Model refused: {}", refusal),
+                    status_code: None,
+                    source: None,
+                });
+            }
+        };
+        if blobs.is_empty() {
+            return Err(LlmError::Parse {
+                message: "Response contains no binary data".to_string(),
+                source: "expected inline data or a base64 data URL".into(),
+            });
+        }
+        Ok(BinaryResponse {
+            blobs,
+            text,
+            usage: res.usage,
+            metadata,
+        })
+    }
+}
+
+/// The `data:<mime>;base64,<data>` URLs in `text` that decode, in order.
+fn data_urls(text: &str) -> Vec<Blob> {
+    let mut blobs = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("data:") {
+        rest = &rest[start + "data:".len()..];
+        let Some((mime_type, after)) = rest.split_once(";base64,") else {
+            break;
+        };
+        if mime_type.is_empty()
+            || !mime_type.contains('/')
+            || mime_type.contains(char::is_whitespace)
+        {
+            continue;
+        }
+        let len = after
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '='))
+            .unwrap_or(after.len());
+        if let Ok(data) = STANDARD.decode(&after[..len]) {
+            blobs.push(Blob {
+                mime_type: mime_type.to_string(),
+                data,
+            });
+        }
+        rest = &after[len..];
+    }
+    blobs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Provider;
+
+    fn response(content: ResponseContent) -> ProviderResponse {
+        ProviderResponse::new(
+            "resp",
+            "model",
+            Provider::Gemini,
+            content,
+            LanguageModelUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                cached_tokens: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_data_urls_in_text_are_decoded() {
+        let text = "Here you go: ![cat](data:image/png;base64,aGVsbG8=) and a sound \
+                    data:audio/wav;base64,d29ybGQ= plus data:broken;base64,!!";
+        let parsed =
+            BinaryResponse::parse_response(response(ResponseContent::Text(text.to_string())))
+                .unwrap();
+        assert_eq!(
+            parsed.blobs,
+            vec![
+                Blob {
+                    mime_type: "image/png".to_string(),
+                    data: b"hello".to_vec(),
+                },
+                Blob {
+                    mime_type: "audio/wav".to_string(),
+                    data: b"world".to_vec(),
+                },
+            ]
+        );
+        assert_eq!(parsed.text.as_deref(), Some(text));
+    }
+
+    #[test]
+    fn test_text_alongside_inline_data_is_kept() {
+        let blob = Blob {
+            mime_type: "image/png".to_string(),
+            data: b"png".to_vec(),
+        };
+        let parsed = BinaryResponse::parse_response(response(ResponseContent::Binary {
+            blobs: vec![blob.clone()],
+            text: Some("A cat on a mat".to_string()),
+        }))
+        .unwrap();
+        assert_eq!(parsed.blobs, vec![blob]);
+        assert_eq!(parsed.text.as_deref(), Some("A cat on a mat"));
+    }
+
+    #[test]
+    fn test_answers_without_binary_data_are_rejected() {
+        let err = BinaryResponse::parse_response(response(ResponseContent::Text(
+            "I can't draw".to_string(),
+        )))
+        .unwrap_err();
+        assert!(matches!(err, LlmError::Parse { .. }));
+    }
+}
@@ -1,5 +1,14 @@
-use std::{env, marker::PhantomData, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    env,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
+use futures::future::{self, Either};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, instrument};
 
 /// Type alias for inspection callbacks that receive raw JSON payloads.
@@ -15,20 +24,42 @@ pub struct InspectorConfig {
 }
 
 use crate::{
-    provider::{Provider, gemini, openai, openrouter},
-    responses::HttpClientConfig,
+    provider::{
+        Capabilities, CohereOptions, GeminiOptions, OpenRouterOptions, Provider, VertexConfig,
+        cohere, compatible, gemini, openai, openrouter,
+    },
+    responses::{
+        HttpClientConfig,
+        request::{Format, FormatType},
+    },
 };
 
 use super::{
+    credentials::CredentialsProvider,
     error::LlmError,
-    traits::LlmProvider,
+    guardrails::{Guardrails, OutputCheck},
+    hedge::Hedge,
+    lenient,
+    memory::Recall,
+    prompts::{PromptRegistry, PromptVersion},
+    rag::{self, RagAnswer, VectorIndex},
+    redaction::{Redactions, Redactor},
+    runtime::{self, abortable},
+    stop::{self, StopCondition},
+    stored::StoredResponses,
+    timings,
+    tool_guard::ToolCallingConfig,
+    traits::{CompletionTarget, LlmProvider},
+    transform::{self, FieldError, Rejected, Transform, Validator},
     types::{
-        ConversationMessage, GenerationConfig, Message, StructuredRequest, ToolChoice, ToolConfig,
-        ToolRegistry,
+        ChatRole, ConversationMessage, GenerationConfig, Message, ProviderResponse,
+        ResponseContent, StructuredRequest, TextResponse, ToolChoice, ToolConfig, ToolRegistry,
+        ToolSet,
     },
+    usage,
 };
 
-mod private {
+pub(crate) mod private {
     pub struct ProviderSet;
     pub struct ApiKeySet;
     pub struct Configuring;
@@ -36,9 +67,15 @@ mod private {
     pub struct ToolsSet;
 
     /// Marker trait for states that can call complete()
-    pub trait Completable {}
+    pub trait Completable: Configurable {}
     impl Completable for MessagesSet {}
     impl Completable for ToolsSet {}
+
+    /// Marker trait for states that accept request options, e.g. before [`client`](super::LlmBuilder::client)
+    pub trait Configurable {}
+    impl Configurable for Configuring {}
+    impl Configurable for MessagesSet {}
+    impl Configurable for ToolsSet {}
 }
 
 /// Builder fields that are shared across all states.
@@ -47,24 +84,63 @@ struct BuilderFields<Ctx = ()> {
     // Core configuration
     provider: Option<Provider>,
     api_key: Option<String>,
+    credentials: Option<Arc<dyn CredentialsProvider>>,
+    vertex: Option<VertexConfig>,
     model: Option<String>,
+    model_fallbacks: Vec<String>,
+    hedge: Option<Hedge>,
     http_client_config: Option<HttpClientConfig>,
 
     // Request content
     messages: Option<Vec<Message>>,
+    examples: Vec<(String, serde_json::Value)>,
+    memory: Option<Recall>,
+    prompts: Option<PromptRegistry>,
+    prompt: Option<PromptVersion>,
+    usage_tag: Option<String>,
+    previous_response_id: Option<String>,
+    store: Option<bool>,
+    metadata: BTreeMap<String, String>,
+
+    /// Tool calls and results sent after the messages, e.g. those of an agent that handed
+    /// the conversation over
+    tool_history: Vec<ConversationMessage>,
 
     // Tool configuration
     tool_choice: Option<ToolChoice>,
     parallel_tool_calls: Option<bool>,
     tool_registry: Option<ToolRegistry<Ctx>>,
+    enabled_tools: Option<Vec<String>>,
+    tool_calling_config: Option<ToolCallingConfig>,
 
     // Generation parameters
     max_tokens: Option<u32>,
+    max_output_tokens: Option<u32>,
+    candidates: Option<u32>,
     temperature: Option<f32>,
     top_p: Option<f32>,
+    stop: Vec<String>,
+    stop_conditions: Vec<Arc<dyn StopCondition>>,
+
+    // Validation
+    guardrails: Option<Guardrails>,
+    redactor: Option<Redactor>,
+    validators: Vec<Validator>,
+    partial_repair: bool,
+    lenient_json: bool,
+    strict_json: bool,
+
+    // Cancellation
+    abort_signal: Option<CancellationToken>,
 
     // Inspection hooks
     inspector_config: Option<InspectorConfig>,
+    expected_request: Option<serde_json::Value>,
+
+    // Provider-specific options
+    gemini_options: Option<GeminiOptions>,
+    openrouter_options: Option<OpenRouterOptions>,
+    cohere_options: Option<CohereOptions>,
 }
 
 impl BuilderFields<()> {
@@ -72,21 +148,100 @@ impl BuilderFields<()> {
         Self {
             provider: None,
             api_key: None,
+            credentials: None,
+            vertex: None,
             model: None,
+            model_fallbacks: Vec::new(),
+            hedge: None,
             messages: None,
+            examples: Vec::new(),
+            memory: None,
+            prompts: None,
+            prompt: None,
+            usage_tag: None,
+            previous_response_id: None,
+            store: None,
+            metadata: BTreeMap::new(),
+            tool_history: Vec::new(),
             tool_choice: None,
             parallel_tool_calls: None,
             tool_registry: None,
+            enabled_tools: None,
+            tool_calling_config: None,
             max_tokens: None,
+            max_output_tokens: None,
+            candidates: None,
             temperature: None,
             top_p: None,
+            stop: Vec::new(),
+            stop_conditions: Vec::new(),
             http_client_config: None,
+            guardrails: None,
+            redactor: None,
+            validators: Vec::new(),
+            partial_repair: false,
+            lenient_json: false,
+            strict_json: false,
+            abort_signal: None,
             inspector_config: None,
+            expected_request: None,
+            gemini_options: None,
+            openrouter_options: None,
+            cohere_options: None,
         }
     }
 }
 
 impl<Ctx> BuilderFields<Ctx> {
+    /// Copy every field except the tools into a fresh builder, for [`LlmClient`] to start each
+    /// request from.
+    fn duplicate(&self) -> Self {
+        Self {
+            provider: self.provider,
+            api_key: self.api_key.clone(),
+            credentials: self.credentials.clone(),
+            vertex: self.vertex.clone(),
+            model: self.model.clone(),
+            model_fallbacks: self.model_fallbacks.clone(),
+            hedge: self.hedge.clone(),
+            messages: self.messages.clone(),
+            examples: self.examples.clone(),
+            memory: self.memory.clone(),
+            prompts: self.prompts.clone(),
+            prompt: self.prompt.clone(),
+            usage_tag: self.usage_tag.clone(),
+            previous_response_id: self.previous_response_id.clone(),
+            store: self.store,
+            metadata: self.metadata.clone(),
+            tool_history: self.tool_history.clone(),
+            tool_choice: self.tool_choice.clone(),
+            parallel_tool_calls: self.parallel_tool_calls,
+            tool_registry: None,
+            enabled_tools: self.enabled_tools.clone(),
+            tool_calling_config: self.tool_calling_config.clone(),
+            max_tokens: self.max_tokens,
+            max_output_tokens: self.max_output_tokens,
+            candidates: self.candidates,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            stop: self.stop.clone(),
+            stop_conditions: self.stop_conditions.clone(),
+            http_client_config: self.http_client_config.clone(),
+            guardrails: self.guardrails.clone(),
+            redactor: self.redactor.clone(),
+            validators: self.validators.clone(),
+            partial_repair: self.partial_repair,
+            lenient_json: self.lenient_json,
+            strict_json: self.strict_json,
+            abort_signal: self.abort_signal.clone(),
+            inspector_config: self.inspector_config.clone(),
+            expected_request: self.expected_request.clone(),
+            gemini_options: self.gemini_options.clone(),
+            openrouter_options: self.openrouter_options.clone(),
+            cohere_options: self.cohere_options.clone(),
+        }
+    }
+
     /// Create a new BuilderFields with a different context type
     fn with_context_type<NewCtx>(
         self,
@@ -95,24 +250,56 @@ impl<Ctx> BuilderFields<Ctx> {
         BuilderFields {
             provider: self.provider,
             api_key: self.api_key,
+            credentials: self.credentials,
+            vertex: self.vertex,
             model: self.model,
+            model_fallbacks: self.model_fallbacks,
+            hedge: self.hedge,
             http_client_config: self.http_client_config,
             messages: self.messages,
+            examples: self.examples,
+            memory: self.memory,
+            prompts: self.prompts,
+            prompt: self.prompt,
+            usage_tag: self.usage_tag,
+            previous_response_id: self.previous_response_id,
+            store: self.store,
+            metadata: self.metadata,
+            tool_history: self.tool_history,
             tool_choice: self.tool_choice,
             parallel_tool_calls: self.parallel_tool_calls,
             tool_registry,
+            enabled_tools: self.enabled_tools,
+            tool_calling_config: self.tool_calling_config,
             max_tokens: self.max_tokens,
+            max_output_tokens: self.max_output_tokens,
+            candidates: self.candidates,
             temperature: self.temperature,
             top_p: self.top_p,
+            stop: self.stop,
+            stop_conditions: self.stop_conditions,
+            guardrails: self.guardrails,
+            redactor: self.redactor,
+            validators: self.validators,
+            partial_repair: self.partial_repair,
+            lenient_json: self.lenient_json,
+            strict_json: self.strict_json,
+            abort_signal: self.abort_signal,
             inspector_config: self.inspector_config,
+            expected_request: self.expected_request,
+            gemini_options: self.gemini_options,
+            openrouter_options: self.openrouter_options,
+            cohere_options: self.cohere_options,
         }
     }
 
     /// Validate that all required fields are present
     fn validate(&self) -> Result<(&Vec<Message>, Provider, &str), LlmError> {
-        self.api_key.as_ref().ok_or(LlmError::Builder(
-            "Missing API key. Make sure to specify an API key.".into(),
-        ))?;
+        if self.api_key.is_none() && self.credentials.is_none() && self.vertex.is_none() {
+            return Err(LlmError::Builder(
+                "Missing API key. Make sure to specify an API key.".into(),
+            ));
+        }
 
         let messages = self
             .messages
@@ -154,59 +341,198 @@ impl<State, Ctx> LlmBuilder<State, Ctx> {
         }
     }
 
+    /// Pass the usage of a response to the registered [`UsageReporter`](crate::usage::UsageReporter).
+    fn report_usage(&self, response: &ProviderResponse, latency: Duration) {
+        usage::report(
+            self.fields.usage_tag.as_deref(),
+            response.provider,
+            &response.model,
+            &response.usage,
+            latency,
+        );
+    }
+
     pub(crate) fn get_api_key(&self) -> Option<&str> {
-        self.fields.api_key.as_deref()
+        match (&self.fields.api_key, &self.fields.credentials) {
+            (Some(api_key), _) => Some(api_key),
+            // Never sent: clients leave out the API key header when credentials are set
+            (None, Some(_)) => Some(""),
+            (None, None) => None,
+        }
+    }
+
+    pub(crate) fn get_vertex_config(&self) -> Option<&VertexConfig> {
+        self.fields.vertex.as_ref()
     }
 
-    pub(crate) fn get_http_config(&self) -> Option<&HttpClientConfig> {
-        self.fields.http_client_config.as_ref()
+    /// The HTTP client configuration, with the [`credentials`](LlmBuilder::credentials) set
+    /// on the builder.
+    pub(crate) fn get_http_config(&self) -> Option<HttpClientConfig> {
+        let mut config = self.fields.http_client_config.clone();
+        if let Some(credentials) = &self.fields.credentials {
+            config.get_or_insert_default().credentials = Some(credentials.clone());
+        }
+        config
     }
 
     pub(crate) fn get_inspector_config(&self) -> Option<&InspectorConfig> {
         self.fields.inspector_config.as_ref()
     }
+
+    pub(crate) fn get_gemini_options(&self) -> Option<&GeminiOptions> {
+        self.fields.gemini_options.as_ref()
+    }
+
+    pub(crate) fn get_openrouter_options(&self) -> Option<&OpenRouterOptions> {
+        self.fields.openrouter_options.as_ref()
+    }
+
+    pub(crate) fn get_cohere_options(&self) -> Option<&CohereOptions> {
+        self.fields.cohere_options.as_ref()
+    }
+
+    pub(crate) fn get_previous_response_id(&self) -> Option<&str> {
+        self.fields.previous_response_id.as_deref()
+    }
+
+    pub(crate) fn get_store(&self) -> Option<bool> {
+        self.fields.store
+    }
+
+    pub(crate) fn get_metadata(&self) -> &BTreeMap<String, String> {
+        &self.fields.metadata
+    }
+
+    pub(crate) fn get_tool_calling_config(&self) -> Option<&ToolCallingConfig> {
+        self.fields.tool_calling_config.as_ref()
+    }
+
+    /// Send `items`, tool calls and their results, after the messages.
+    pub(crate) fn tool_history(mut self, items: Vec<ConversationMessage>) -> Self {
+        self.fields.tool_history = items;
+        self
+    }
+
+    /// Set the tool calling limits before tools are added, e.g. from a profile.
+    #[cfg(feature = "profiles")]
+    pub(crate) fn set_tool_calling_config(mut self, config: ToolCallingConfig) -> Self {
+        self.fields.tool_calling_config = Some(config);
+        self
+    }
 }
 
 /// Configuration for API key source
+#[derive(Clone)]
 pub enum ApiKey {
     /// Use the default environment variable for the provider
     Default,
     /// Use a custom API key string
     Custom(String),
+    /// Read the key from a file. Surrounding whitespace is trimmed.
+    FromFile(PathBuf),
+    /// Read the key from the OS credential store (macOS Keychain, Windows Credential Manager,
+    /// Linux kernel keyring)
+    #[cfg(feature = "keyring")]
+    Keyring { service: String, user: String },
 }
 
 impl LlmBuilder<private::ProviderSet, ()> {
     /// Set the API key for the provider.
-    /// Use `ApiKey::Default` to load from environment variables or `ApiKey::Custom` for a custom key.
+    /// Use `ApiKey::Default` to load from environment variables, `ApiKey::Custom` for a custom key
+    /// or `ApiKey::FromFile` to read it from disk.
     pub fn api_key(
         mut self,
         api_key: ApiKey,
     ) -> Result<LlmBuilder<private::ApiKeySet, ()>, LlmError> {
-        let key = match api_key {
-            ApiKey::Default => {
-                let provider = self.fields.provider.ok_or(LlmError::Builder(
-                    "Provider must be set before API key".into(),
-                ))?;
-
-                env::var(provider.default_api_key_env_var()).map_err(|_| {
-                    LlmError::Builder(format!(
-                        "Missing {} environment variable",
-                        provider.default_api_key_env_var()
-                    ))
-                })?
-            }
-            ApiKey::Custom(custom_key) => custom_key,
-        };
+        let provider = self.fields.provider.ok_or(LlmError::Builder(
+            "Provider must be set before API key".into(),
+        ))?;
+
+        self.fields.api_key = Some(resolve_api_key(provider, api_key)?);
+        Ok(self.transition_state())
+    }
+
+    /// Authenticate with credentials that expire instead of an API key. `credentials` is asked
+    /// for headers before every HTTP request, and the provider's API key header is not sent.
+    ///
+    /// Takes precedence over [`HttpClientConfig::credentials`], also of a config set later with
+    /// [`http_client_config`](Self::http_client_config). See [`credentials`](crate::credentials).
+    pub fn credentials(
+        mut self,
+        credentials: impl CredentialsProvider + 'static,
+    ) -> Result<LlmBuilder<private::ApiKeySet, ()>, LlmError> {
+        self.fields.provider.ok_or(LlmError::Builder(
+            "Provider must be set before credentials".into(),
+        ))?;
+
+        self.fields.credentials = Some(Arc::new(credentials));
+        Ok(self.transition_state())
+    }
+
+    /// Run Gemini on Vertex AI, authenticated with OAuth access tokens instead of an API key.
+    /// Tokens are cached in `vertex` and refreshed before they expire.
+    pub fn vertex(
+        mut self,
+        vertex: VertexConfig,
+    ) -> Result<LlmBuilder<private::ApiKeySet, ()>, LlmError> {
+        if self.fields.provider != Some(Provider::Gemini) {
+            return Err(LlmError::Builder(
+                "Vertex AI is only supported for Gemini".into(),
+            ));
+        }
 
-        self.fields.api_key = Some(key);
+        self.fields.vertex = Some(vertex);
         Ok(self.transition_state())
     }
 }
 
+/// Load the key `api_key` points to.
+pub(crate) fn resolve_api_key(provider: Provider, api_key: ApiKey) -> Result<String, LlmError> {
+    match api_key {
+        ApiKey::Default => env::var(provider.default_api_key_env_var()).map_err(|_| {
+            LlmError::Builder(format!(
+                "Missing {} environment variable",
+                provider.default_api_key_env_var()
+            ))
+        }),
+        ApiKey::Custom(custom_key) => Ok(custom_key),
+        ApiKey::FromFile(path) => read_api_key_file(&path),
+        #[cfg(feature = "keyring")]
+        ApiKey::Keyring { service, user } => keyring::Entry::new(&service, &user)
+            .and_then(|entry| entry.get_password())
+            .map_err(|e| {
+                LlmError::Builder(format!(
+                    "Failed to read API key from keyring ({service}/{user}): {e}"
+                ))
+            }),
+    }
+}
+
+fn read_api_key_file(path: &Path) -> Result<String, LlmError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        LlmError::Builder(format!(
+            "Failed to read API key file '{}': {e}",
+            path.display()
+        ))
+    })?;
+
+    let key = contents.trim();
+    if key.is_empty() {
+        return Err(LlmError::Builder(format!(
+            "API key file '{}' is empty",
+            path.display()
+        )));
+    }
+
+    Ok(key.to_string())
+}
+
 impl LlmBuilder<private::ApiKeySet, ()> {
     /// Set the model to use for the LLM request.
-    pub fn model(mut self, model_id: &str) -> LlmBuilder<private::Configuring, ()> {
-        self.fields.model = Some(model_id.to_string());
+    ///
+    /// Accepts a plain id or a constant from [`models`](crate::models).
+    pub fn model(mut self, model_id: impl AsRef<str>) -> LlmBuilder<private::Configuring, ()> {
+        self.fields.model = Some(model_id.as_ref().to_string());
         self.transition_state()
     }
 }
@@ -217,9 +543,116 @@ impl LlmBuilder<private::Configuring, ()> {
         self.fields.messages = Some(messages);
         self.transition_state()
     }
+
+    /// Set the messages to the prompt `reference` of the [`prompts`](Self::prompts)
+    /// registry, `name@version` or `name` for its latest version, with `vars` filled in. The
+    /// version is recorded in the [metadata](crate::ResponseMetadata::prompt) of the
+    /// response. See [`prompts`](crate::prompts).
+    ///
+    /// Returns [`LlmError::Prompt`] if no registry is set, the prompt isn't registered or a
+    /// variable of the template has no value.
+    pub fn prompt<K, V>(
+        mut self,
+        reference: &str,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<LlmBuilder<private::MessagesSet, ()>, LlmError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let registry = self
+            .fields
+            .prompts
+            .as_ref()
+            .ok_or_else(|| LlmError::Prompt {
+                prompt: reference.to_string(),
+                message: "No prompt registry set, see LlmBuilder::prompts".to_string(),
+            })?;
+        let (version, messages) = registry.render(reference, vars)?;
+        self.fields.prompt = Some(version);
+        Ok(self.messages(messages))
+    }
+
+    /// Send `body` unchanged as the provider's generation request, for provider features rsai
+    /// doesn't model yet. The request still gets the builder's authentication, retries,
+    /// gateway and inspectors, errors are typed and usage is reported.
+    ///
+    /// `body` is the provider's native JSON, e.g. a Responses API request for OpenAI. The
+    /// model set on the builder is used by providers that take it from the URL (Gemini).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, Provider};
+    /// # use serde_json::json;
+    /// # async fn example() -> Result<(), rsai::LlmError> {
+    /// let response = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .complete_raw(json!({
+    ///         "model": "gpt-4o-mini",
+    ///         "input": "Hello",
+    ///         "prompt_cache_key": "greeting",
+    ///     }))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn complete_raw(self, body: serde_json::Value) -> Result<ProviderResponse, LlmError> {
+        let provider = self
+            .fields
+            .provider
+            .ok_or_else(|| LlmError::Builder("Provider is required".to_string()))?;
+        let model = self.fields.model.clone().unwrap_or_default();
+
+        let (response, timings) = timings::record(async {
+            Ok::<_, LlmError>(match provider {
+                Provider::OpenAI => {
+                    openai::create_openai_client_from_builder(&self)?
+                        .complete_raw(&model, body)
+                        .await?
+                }
+                Provider::OpenRouter => {
+                    openrouter::create_openrouter_client_from_builder(&self)?
+                        .complete_raw(&model, body)
+                        .await?
+                }
+                Provider::Gemini => {
+                    gemini::create_gemini_client_from_builder(&self)?
+                        .complete_raw(&model, body)
+                        .await?
+                }
+                Provider::Cohere => {
+                    cohere::create_cohere_client_from_builder(&self)?
+                        .complete_raw(&model, body)
+                        .await?
+                }
+                Provider::XAI | Provider::Together | Provider::Fireworks => {
+                    compatible::create_compatible_client_from_builder(&self, provider)?
+                        .complete_raw(&model, body)
+                        .await?
+                }
+            })
+        })
+        .await;
+        let mut response = response?;
+        if response.model.is_empty() {
+            response.model = model;
+        }
+        self.report_usage(&response, timings.duration());
+        response.timings = Some(timings);
+        Ok(response)
+    }
+
+    /// Turn the configuration into a reusable [`LlmClient`] that sends many requests.
+    pub fn client(self) -> LlmClient {
+        LlmClient {
+            fields: self.fields,
+            tools: None,
+        }
+    }
 }
 
-impl<State: private::Completable, Ctx: Send + Sync + 'static> LlmBuilder<State, Ctx> {
+impl<State: private::Configurable, Ctx: Send + Sync + 'static> LlmBuilder<State, Ctx> {
     /// Set a custom timeout for the HTTP request.
     /// This is a convenience method that modifies the HttpClientConfig.
     pub fn timeout(mut self, duration: std::time::Duration) -> Self {
@@ -235,257 +668,1867 @@ impl<State: private::Completable, Ctx: Send + Sync + 'static> LlmBuilder<State,
         self
     }
 
-    /// Set the maximum number of tokens to generate.
-    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
-        self.fields.max_tokens = Some(max_tokens);
+    /// Replace the HTTP transport, e.g. with a [`Cassette`](crate::Cassette) for record/replay
+    /// tests or a mock in unit tests. Retries, inspectors and the gateway still apply to the
+    /// requests it sends. See [`HttpClientConfig::transport`].
+    pub fn transport(mut self, transport: Arc<dyn super::transport::Transport>) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.transport = Some(transport);
+        self.fields.http_client_config = Some(config);
         self
     }
 
-    /// Set the temperature for generation (0.0 to 2.0).
-    /// Lower values make output more focused and deterministic.
-    pub fn temperature(mut self, temperature: f32) -> Self {
-        self.fields.temperature = Some(temperature);
+    /// Decide whether and when failed HTTP requests are retried, replacing the exponential
+    /// backoff and the `max_retries` and delay fields of the HTTP config. See
+    /// [`retry`](crate::retry).
+    pub fn retry_policy(mut self, policy: impl super::retry::RetryPolicy + 'static) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.retry_policy = Some(Arc::new(policy));
+        self.fields.http_client_config = Some(config);
         self
     }
 
-    /// Set the top_p value for nucleus sampling (0.0 to 1.0).
-    /// An alternative to temperature; use one or the other, not both.
-    pub fn top_p(mut self, top_p: f32) -> Self {
-        self.fields.top_p = Some(top_p);
+    /// Share one HTTP call between concurrent identical requests, e.g. many tasks asking the
+    /// same question at once. See [`HttpClientConfig::coalesce_requests`].
+    ///
+    /// Leave this off when identical requests are meant to produce different answers, e.g.
+    /// OpenAI [`candidates`](Self::candidates), which sends one request per candidate.
+    pub fn coalesce_requests(mut self, enabled: bool) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.coalesce_requests = enabled;
+        self.fields.http_client_config = Some(config);
         self
     }
 
-    /// Set a callback to inspect raw JSON requests before they are sent.
+    /// Derive the `Idempotency-Key` of each request from `key` and the request body instead of
+    /// using a random key, e.g. to make a request that is repeated after a crash idempotent.
+    /// Requests with different bodies, such as re-asks after rejected answers and model
+    /// fallbacks, get different keys. See [`HttpClientConfig::idempotency_key`].
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.idempotency_keys = true;
+        config.idempotency_key = Some(key.into());
+        self.fields.http_client_config = Some(config);
+        self
+    }
+
+    /// Add a header to every outgoing HTTP request (e.g. `OpenAI-Organization` or a
+    /// `traceparent`). Overrides a provider header of the same name.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.headers.push((name.into(), value.into()));
+        self.fields.http_client_config = Some(config);
+        self
+    }
+
+    /// Send every request through an LLM gateway (e.g. Kong AI Gateway, a LiteLLM proxy or
+    /// Portkey), rewriting its URL, body and headers. Model fallbacks go through the gateway
+    /// too, hedged requests to another provider don't (see [`Hedge::to`]). See
+    /// [`Gateway`](super::http::Gateway).
+    pub fn gateway(mut self, gateway: super::http::Gateway) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.gateway = Some(gateway);
+        self.fields.http_client_config = Some(config);
+        self
+    }
+
+    /// Set the rules that mask secrets in inspector and tracing output, replacing the default
+    /// rules. Build on [`LogRedactor::new`](super::redaction::LogRedactor::new) to keep masking
+    /// API keys. See [`redaction`](crate::redaction).
+    pub fn redact_logs(mut self, redactor: super::redaction::LogRedactor) -> Self {
+        let mut config = self.fields.http_client_config.unwrap_or_default();
+        config.log_redaction = redactor;
+        self.fields.http_client_config = Some(config);
+        self
+    }
+
+    /// Add few-shot demonstrations of the expected output.
     ///
-    /// The callback receives a reference to the serialized request body as JSON.
-    /// This fires on ALL requests, including each iteration of tool-calling loops.
+    /// Each example is sent as a user message with the input followed by an assistant message
+    /// with the output, after any leading system messages. Structured outputs are rendered in
+    /// the exact shape the response schema asks for.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LlmError::Builder`] if an output cannot be serialized to JSON.
+    pub fn examples<T: serde::Serialize>(
+        mut self,
+        examples: Vec<(impl Into<String>, T)>,
+    ) -> Result<Self, LlmError> {
+        for (input, output) in examples {
+            let output = serde_json::to_value(output).map_err(|e| {
+                LlmError::Builder(format!("Failed to serialize few-shot example: {e}"))
+            })?;
+            self.fields.examples.push((input.into(), output));
+        }
+        Ok(self)
+    }
+
+    /// Add memories relevant to the last user message to the prompt before each call.
+    /// See [`memory`](crate::memory).
+    pub fn memory(mut self, recall: Recall) -> Self {
+        self.fields.memory = Some(recall);
+        self
+    }
+
+    /// Templates that [`prompt`](Self::prompt) renders from. Kept by [`client`](Self::client),
+    /// so its builders can use them too.
+    pub fn prompts(mut self, registry: PromptRegistry) -> Self {
+        self.fields.prompts = Some(registry);
+        self
+    }
+
+    /// Tag the usage reports of this call, e.g. `feature=checkout`, to aggregate usage and cost
+    /// per feature. See [`usage`](crate::usage).
+    pub fn usage_tag(mut self, tag: impl Into<String>) -> Self {
+        self.fields.usage_tag = Some(tag.into());
+        self
+    }
+
+    /// Models to try in order when the request fails because the model is unavailable or the
+    /// prompt exceeds its context window. Other errors are returned right away. The model that
+    /// served the response is in its metadata.
     ///
     /// # Example
     /// ```no_run
-    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
-    /// # #[tokio::main]
-    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let response = llm::with(Provider::OpenAI)
+    /// # use rsai::{llm, ApiKey, Provider};
+    /// # fn example() -> Result<(), rsai::LlmError> {
+    /// let builder = llm::with(Provider::OpenAI)
     ///     .api_key(ApiKey::Default)?
-    ///     .model("gpt-4o-mini")
-    ///     .messages(vec![Message {
-    ///         role: ChatRole::User,
-    ///         content: "Hello".to_string(),
-    ///     }])
-    ///     .inspect_request(|req| {
-    ///         println!("Request: {}", serde_json::to_string_pretty(req).unwrap());
-    ///     })
-    ///     .complete::<TextResponse>()
-    ///     .await?;
+    ///     .model("gpt-4.1")
+    ///     .model_fallbacks(["gpt-4o", "gpt-4o-mini"]);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn inspect_request<F>(mut self, inspector: F) -> Self
+    pub fn model_fallbacks<I, M>(mut self, models: I) -> Self
     where
-        F: Fn(&serde_json::Value) + Send + Sync + 'static,
+        I: IntoIterator<Item = M>,
+        M: AsRef<str>,
     {
-        let mut config = self.fields.inspector_config.take().unwrap_or_default();
-        config.request_inspector = Some(Arc::new(inspector));
-        self.fields.inspector_config = Some(config);
+        self.fields.model_fallbacks = models
+            .into_iter()
+            .map(|model| model.as_ref().to_string())
+            .collect();
         self
     }
 
-    /// Set a callback to inspect raw JSON responses after they are received.
+    /// Send a backup request when the primary hasn't answered in time and return whichever
+    /// answers first, cancelling the other. Cuts the tail latency of interactive apps at the
+    /// cost of paying for both requests when the backup is sent.
     ///
-    /// The callback receives a reference to the parsed response body as JSON.
-    /// This fires on ALL responses, including each iteration of tool-calling loops
-    /// and both success and error responses.
+    /// A [`Duration`] sends the backup to the same provider and model; see [`Hedge`] for another
+    /// model or provider. If one request fails, the other one's result is returned. Requests
+    /// with tools are not hedged, since their tool calls would run twice.
     ///
     /// # Example
     /// ```no_run
-    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
-    /// # #[tokio::main]
-    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// let response = llm::with(Provider::OpenAI)
+    /// # use std::time::Duration;
+    /// # use rsai::{llm, ApiKey, Hedge, Provider};
+    /// # fn example() -> Result<(), rsai::LlmError> {
+    /// let builder = llm::with(Provider::OpenAI)
     ///     .api_key(ApiKey::Default)?
     ///     .model("gpt-4o-mini")
-    ///     .messages(vec![Message {
-    ///         role: ChatRole::User,
-    ///         content: "Hello".to_string(),
-    ///     }])
-    ///     .inspect_response(|res| {
-    ///         println!("Response: {}", serde_json::to_string_pretty(res).unwrap());
-    ///     })
-    ///     .complete::<TextResponse>()
-    ///     .await?;
+    ///     .hedge(Hedge::after(Duration::from_millis(800)).model("gpt-4.1-mini"));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn inspect_response<F>(mut self, inspector: F) -> Self
-    where
-        F: Fn(&serde_json::Value) + Send + Sync + 'static,
-    {
-        let mut config = self.fields.inspector_config.take().unwrap_or_default();
-        config.response_inspector = Some(Arc::new(inspector));
-        self.fields.inspector_config = Some(config);
+    pub fn hedge(mut self, hedge: impl Into<Hedge>) -> Self {
+        self.fields.hedge = Some(hedge.into());
         self
     }
 
-    /// Execute the LLM request and return an output defined by `T`.
+    /// Set how many candidate answers [`complete_all`](Self::complete_all) and
+    /// [`complete_best`](Self::complete_best) generate. [`complete`](Self::complete) always
+    /// returns a single answer.
+    pub fn candidates(mut self, count: u32) -> Self {
+        self.fields.candidates = Some(count);
+        self
+    }
+
+    /// Validate user messages before the request and the model's answer before it is parsed.
+    /// See [`guardrails`](crate::guardrails).
     ///
-    /// The target type `T` must implement [`CompletionTarget`]. Structured schemas can be created
-    /// with the `#[completion_schema]` macro, while plain text responses can use [`TextResponse`].
+    /// Output guardrails see the raw answer text, which is JSON for structured outputs.
+    pub fn guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.fields.guardrails = Some(guardrails);
+        self
+    }
+
+    /// Check structured outputs of type `T` against a business rule, e.g. that an end date is
+    /// not before the start date. When `check` fails, its message is sent back to the model and
+    /// it is asked again, for up to [`Guardrails::max_retries`] retries (2 without guardrails)
+    /// before failing with [`LlmError::OutputRejected`]. Rules run after output guardrails, in
+    /// the order they were added, and are skipped by completions of other types.
     ///
     /// # Example
     /// ```no_run
-    /// # use rsai::{completion_schema, llm, Message, ChatRole, ApiKey, Provider, TextResponse};
-    /// # #[tokio::main]
-    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # use rsai::{completion_schema, llm, ApiKey, ChatRole, Message, Provider};
     /// #[completion_schema]
-    /// struct Analysis {
-    ///     sentiment: String,
-    ///     confidence: f32,
+    /// struct Trip {
+    ///     start_date: String,
+    ///     end_date: String,
     /// }
     ///
-    /// let analysis = llm::with(Provider::OpenAI)
+    /// # async fn example() -> Result<(), rsai::LlmError> {
+    /// let trip = llm::with(Provider::OpenAI)
     ///     .api_key(ApiKey::Default)?
     ///     .model("gpt-4o-mini")
-    ///     .messages(vec![Message {
-    ///         role: ChatRole::User,
-    ///         content: "Analyze: 'This library is amazing!'".to_string(),
-    ///     }])
-    ///     .complete::<Analysis>()
+    ///     .messages(vec![Message::new(ChatRole::User, "Plan a week in Lisbon in May 2026")])
+    ///     .validate(|trip: &Trip| {
+    ///         if trip.end_date < trip.start_date {
+    ///             return Err("end_date must not be before start_date".to_string());
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .complete::<Trip>()
     ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate<T>(
+        mut self,
+        check: impl Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema + Send,
+    {
+        self.fields.validators.push(Validator::new(check));
+        self
+    }
+
+    /// Like [`validate`](Self::validate), but `check` names the fields that break the rule, so
+    /// that [`partial_repair`](Self::partial_repair) can ask for just those fields again.
     ///
-    /// let text = llm::with(Provider::OpenAI)
+    /// ```no_run
+    /// # use rsai::{completion_schema, llm, ApiKey, ChatRole, Message, Provider};
+    /// use rsai::transform::FieldError;
+    ///
+    /// #[completion_schema]
+    /// struct Trip {
+    ///     start_date: String,
+    ///     end_date: String,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), rsai::LlmError> {
+    /// let trip = llm::with(Provider::OpenAI)
     ///     .api_key(ApiKey::Default)?
     ///     .model("gpt-4o-mini")
-    ///     .messages(vec![Message {
-    ///         role: ChatRole::User,
-    ///         content: "Say hello".to_string(),
-    ///     }])
-    ///     .complete::<TextResponse>()
+    ///     .messages(vec![Message::new(ChatRole::User, "Plan a week in Lisbon in May 2026")])
+    ///     .validate_fields(|trip: &Trip| {
+    ///         if trip.end_date < trip.start_date {
+    ///             return Err(vec![FieldError::new(
+    ///                 "/end_date",
+    ///                 "must not be before start_date",
+    ///             )]);
+    ///         }
+    ///         Ok(())
+    ///     })
+    ///     .partial_repair(true)
+    ///     .complete::<Trip>()
     ///     .await?;
     /// # Ok(())
     /// # }
     /// ```
-    #[instrument(
-        name = "generate_completion",
-        skip(self),
-        fields(
-            model = ?self.fields.model,
-            provider = ?self.fields.provider,
-            max_tokens = ?self.fields.max_tokens,
-        ),
-        err
-    )]
-    pub async fn complete<T>(self) -> Result<T::Output, LlmError>
+    pub fn validate_fields<T>(
+        mut self,
+        check: impl Fn(&T) -> Result<(), Vec<FieldError>> + Send + Sync + 'static,
+    ) -> Self
     where
-        T: super::traits::CompletionTarget + Send,
+        T: serde::de::DeserializeOwned + schemars::JsonSchema + Send,
     {
-        debug!("Starting generation request");
-        let (messages, provider, model) = self.fields.validate()?;
-        let model_string = model.to_string();
-        let messages = messages.to_vec();
-        let format = T::format()?;
+        self.fields.validators.push(Validator::fields(check));
+        self
+    }
 
-        if !T::supports_tools() && self.fields.tool_registry.is_some() {
-            return Err(LlmError::Builder(
-                "Tools are only supported with structured completion targets".to_string(),
-            ));
+    /// When a [validator](Self::validate_fields) rejects individual fields of a structured
+    /// output, send the model its answer with the field errors and ask for corrected values of
+    /// only those fields, then merge them into the answer (default: false). Cheaper than a
+    /// whole new answer for large outputs. Falls back to asking again for the whole answer
+    /// when the model's reply doesn't correct every field, and counts towards the same
+    /// retries.
+    pub fn partial_repair(mut self, enabled: bool) -> Self {
+        self.fields.partial_repair = enabled;
+        self
+    }
+
+    /// Repair structured answers that are not valid JSON before parsing them (default: false).
+    /// Accepts comments, trailing commas, unquoted keys, single-quoted strings and a markdown
+    /// code fence around the answer, as weaker models produce them. Repaired answers are marked
+    /// with [`ResponseMetadata::lenient_json`](crate::ResponseMetadata::lenient_json).
+    pub fn lenient_json(mut self, enabled: bool) -> Self {
+        self.fields.lenient_json = enabled;
+        self
+    }
+
+    /// Fail with [`LlmError::Parse`] when a structured answer is not exactly one JSON value
+    /// (default: false). Otherwise the first JSON object or array in the answer that parses
+    /// as the output type is used, e.g. after some prose or inside a markdown code fence.
+    /// Answers repaired by [`lenient_json`](Self::lenient_json) are accepted.
+    pub fn strict_json(mut self, enabled: bool) -> Self {
+        self.fields.strict_json = enabled;
+        self
+    }
+
+    /// Mask sensitive values in every message before the request is sent. See
+    /// [`redaction`](crate::redaction).
+    ///
+    /// Messages are redacted before input guardrails run. Placeholders are restored after
+    /// output guardrails, so those only ever see the redacted answer. Tool arguments keep
+    /// their placeholders.
+    pub fn redact(mut self, redactor: Redactor) -> Self {
+        self.fields.redactor = Some(redactor);
+        self
+    }
+
+    /// Cancel the request when `token` is cancelled. In-flight HTTP requests, the tool-calling
+    /// loop and running tools are dropped, and the request fails with [`LlmError::Aborted`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, CancellationToken, ChatRole, Message, Provider, TextResponse};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = CancellationToken::new();
+    ///
+    /// // Hand a clone to the UI, which calls `cancel()` when the user hits stop
+    /// let stop = token.clone();
+    ///
+    /// let result = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Write a long story")])
+    ///     .abort_signal(token)
+    ///     .complete::<TextResponse>()
+    ///     .await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abort_signal(mut self, token: CancellationToken) -> Self {
+        self.fields.abort_signal = Some(token);
+        self
+    }
+
+    /// Continue the server-side conversation of an earlier OpenAI response instead of resending
+    /// the history. Pass the `metadata.id` of that response; only the new messages need to be
+    /// set. The earlier response must have been stored, which is OpenAI's default.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, ChatRole, Message, Provider, TextResponse};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let first = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "My name is Ada.")])
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    ///
+    /// let second = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "What is my name?")])
+    ///     .continue_from(&first.metadata.id)
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn continue_from(mut self, response_id: impl Into<String>) -> Self {
+        self.fields.previous_response_id = Some(response_id.into());
+        self
+    }
+
+    /// Set whether OpenAI stores the response for later retrieval and evals (OpenAI's default
+    /// is to store). Ignored by other providers.
+    pub fn store(mut self, store: bool) -> Self {
+        self.fields.store = Some(store);
+        self
+    }
+
+    /// Tag the response with key-value pairs, shown in the OpenAI dashboard and usable as
+    /// filters. OpenAI accepts up to 16 pairs with keys of up to 64 and values of up to 512
+    /// characters. Ignored by other providers.
+    pub fn metadata<K, V>(mut self, metadata: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.fields.metadata.extend(
+            metadata
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Set the maximum number of tokens to generate.
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.fields.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Override the number of tokens the model can generate per answer, which is otherwise
+    /// known only for the models in [`models`](crate::models). Requests whose
+    /// [`max_tokens`](Self::max_tokens) exceed it fail before being sent.
+    ///
+    /// See [`Provider::capabilities_for`].
+    pub fn max_output_tokens(mut self, tokens: u32) -> Self {
+        self.fields.max_output_tokens = Some(tokens);
+        self
+    }
+
+    /// Set the temperature for generation (0.0 to 2.0).
+    /// Lower values make output more focused and deterministic.
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.fields.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the top_p value for nucleus sampling (0.0 to 1.0).
+    /// An alternative to temperature; use one or the other, not both.
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.fields.top_p = Some(top_p);
+        self
+    }
+
+    /// End the answer before the first of these sequences, e.g. `.stop(["###", "END"])`.
+    ///
+    /// Sent as the provider's stop parameter where
+    /// [`supports_stop_sequences`](crate::Capabilities::supports_stop_sequences), otherwise the
+    /// answer is cut at the sequences after it arrives.
+    pub fn stop<S: Into<String>>(mut self, sequences: impl IntoIterator<Item = S>) -> Self {
+        self.fields
+            .stop
+            .extend(sequences.into_iter().map(Into::into));
+        self
+    }
+
+    /// End text answers where `condition` says, checked on the client for every provider.
+    /// See [`StopCondition`].
+    pub fn stop_when(mut self, condition: impl StopCondition + 'static) -> Self {
+        self.fields.stop_conditions.push(Arc::new(condition));
+        self
+    }
+
+    /// Set Gemini-specific options such as a cached context. Ignored by other providers.
+    pub fn gemini_options(mut self, options: GeminiOptions) -> Self {
+        self.fields.gemini_options = Some(options);
+        self
+    }
+
+    /// Set OpenRouter-specific options such as model fallbacks and provider routing.
+    /// Ignored by other providers.
+    pub fn openrouter_options(mut self, options: OpenRouterOptions) -> Self {
+        self.fields.openrouter_options = Some(options);
+        self
+    }
+
+    /// Set Cohere-specific options such as documents to ground the answer in.
+    /// Ignored by other providers.
+    pub fn cohere_options(mut self, options: CohereOptions) -> Self {
+        self.fields.cohere_options = Some(options);
+        self
+    }
+
+    /// Set a callback to inspect raw JSON requests before they are sent.
+    ///
+    /// The callback receives a reference to the serialized request body as JSON, with secrets
+    /// masked (see [`redact_logs`](Self::redact_logs)).
+    /// This fires on ALL requests, including each iteration of tool-calling loops.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let response = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+    ///     .inspect_request(|req| {
+    ///         println!("Request: {}", serde_json::to_string_pretty(req).unwrap());
+    ///     })
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect_request<F>(mut self, inspector: F) -> Self
+    where
+        F: Fn(&serde_json::Value) + Send + Sync + 'static,
+    {
+        let mut config = self.fields.inspector_config.take().unwrap_or_default();
+        config.request_inspector = Some(Arc::new(inspector));
+        self.fields.inspector_config = Some(config);
+        self
+    }
+
+    /// Set a callback to inspect raw JSON responses after they are received.
+    ///
+    /// The callback receives a reference to the parsed response body as JSON, with secrets
+    /// masked (see [`redact_logs`](Self::redact_logs)).
+    /// This fires on ALL responses, including each iteration of tool-calling loops
+    /// and both success and error responses.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let response = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+    ///     .inspect_response(|res| {
+    ///         println!("Response: {}", serde_json::to_string_pretty(res).unwrap());
+    ///     })
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect_response<F>(mut self, inspector: F) -> Self
+    where
+        F: Fn(&serde_json::Value) + Send + Sync + 'static,
+    {
+        let mut config = self.fields.inspector_config.take().unwrap_or_default();
+        config.response_inspector = Some(Arc::new(inspector));
+        self.fields.inspector_config = Some(config);
+        self
+    }
+
+    /// Fail with [`LlmError::RequestMismatch`] before sending when the request body does not
+    /// contain `expected`, e.g. to test prompt construction with [`dry_run`](Self::dry_run).
+    ///
+    /// Fields missing from `expected` are not compared, and nested objects are compared the
+    /// same way. Arrays must have the same length.
+    ///
+    /// # Example
+    /// ```
+    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
+    /// # use serde_json::json;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Custom("sk-test".to_string()))?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+    ///     .max_tokens(100)
+    ///     .expect_request(json!({ "max_output_tokens": 100, "input": [{ "content": "Hello" }] }))
+    ///     .dry_run::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expect_request(mut self, expected: serde_json::Value) -> Self {
+        self.fields.expected_request = Some(expected);
+        self
+    }
+
+    /// Classify `text` into one of the variants of the enum `L`. The text is sent as a user
+    /// message after any messages already set, e.g. instructions describing the labels, and
+    /// the answer is constrained to the schema of `L`.
+    ///
+    /// The confidence is the fraction of samples that picked the label, so request several
+    /// with [`candidates`](Self::candidates) and a temperature above zero; with a single
+    /// sample it is `None`. Sampling follows [`complete_all`](LlmBuilder::complete_all).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsai::{llm, ApiKey, Provider, completion_schema};
+    /// #[completion_schema]
+    /// #[derive(Debug, PartialEq)]
+    /// enum Sentiment {
+    ///     Positive,
+    ///     Neutral,
+    ///     Negative,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let classification = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .temperature(0.7)
+    ///     .candidates(5)
+    ///     .classify::<Sentiment>("The delivery was late, but the food was great")
+    ///     .await?;
+    ///
+    /// if let Some(confidence) = classification.confidence {
+    ///     println!("{:?} ({:.0}%)", classification.label, confidence * 100.0);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn classify<L>(
+        mut self,
+        text: impl Into<String>,
+    ) -> Result<super::types::Classification<L>, LlmError>
+    where
+        L: serde::de::DeserializeOwned + schemars::JsonSchema + PartialEq + Send,
+    {
+        self.fields
+            .messages
+            .get_or_insert_with(Vec::new)
+            .push(Message::new(ChatRole::User, text));
+        let samples = self
+            .transition_state::<private::MessagesSet>()
+            .complete_all::<L>()
+            .await?;
+        super::types::Classification::from_samples(samples)
+            .ok_or_else(|| LlmError::Builder("No samples were generated".to_string()))
+    }
+}
+
+impl<State: private::Completable, Ctx: Send + Sync + 'static> LlmBuilder<State, Ctx> {
+    /// Execute the LLM request and return an output defined by `T`.
+    ///
+    /// The target type `T` must implement [`CompletionTarget`]. Structured schemas can be created
+    /// with the `#[completion_schema]` macro, while plain text responses can use [`TextResponse`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{completion_schema, llm, Message, ChatRole, ApiKey, Provider, TextResponse};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// #[completion_schema]
+    /// struct Analysis {
+    ///     sentiment: String,
+    ///     confidence: f32,
+    /// }
+    ///
+    /// let analysis = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Analyze: 'This library is amazing!'")])
+    ///     .complete::<Analysis>()
+    ///     .await?;
+    ///
+    /// let text = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Say hello")])
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(
+        name = "generate_completion",
+        skip(self),
+        fields(
+            model = ?self.fields.model,
+            provider = ?self.fields.provider,
+            max_tokens = ?self.fields.max_tokens,
+        ),
+        err
+    )]
+    pub async fn complete<T>(self) -> Result<T::Output, LlmError>
+    where
+        T: super::traits::CompletionTarget + Send,
+    {
+        debug!("Starting generation request");
+        let abort_signal = self.fields.abort_signal.clone();
+        abortable(abort_signal, self.execute::<T>()).await
+    }
+
+    /// Build the request [`complete`](Self::complete) would send for `T` and return its body
+    /// without sending it: model, messages, tools and schema in the provider's wire format.
+    ///
+    /// Redaction, input guardrails and memory run as they would for `complete`. With tools, this
+    /// is the first request of the tool-calling loop. Gemini takes the model from the URL, so its
+    /// body has no `model` field.
+    ///
+    /// # Example
+    /// ```
+    /// # use rsai::{llm, ApiKey, Provider, Message, ChatRole, TextResponse};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let request = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Custom("sk-test".to_string()))?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+    ///     .dry_run::<TextResponse>()
+    ///     .await?;
+    ///
+    /// assert_eq!(request["model"], "gpt-4o-mini");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn dry_run<T>(mut self) -> Result<serde_json::Value, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        self.redact_messages();
+        self.check_input().await?;
+        self.recall_memories().await?;
+        let (provider, req, format) = self.prepare::<T>()?;
+        self.request_body(provider, &req, format)
+    }
+
+    /// Generate a completion like [`complete`](Self::complete) and pass the parsed output
+    /// through `transform`. When the transform [rejects](Rejected) the output, the reason is sent
+    /// back to the model and it is asked again, for up to [`Guardrails::max_retries`] retries
+    /// (2 without guardrails) before failing with [`LlmError::OutputRejected`].
+    ///
+    /// See the [`transform`](crate::transform) module.
+    pub async fn complete_map<T, X>(self, transform: X) -> Result<X::Output, LlmError>
+    where
+        T: super::traits::CompletionTarget + Send,
+        X: Transform<T::Output>,
+    {
+        debug!("Starting generation request");
+        let abort_signal = self.fields.abort_signal.clone();
+        abortable(abort_signal, self.execute_map::<T, X>(transform)).await
+    }
+
+    /// Search `index` for the `top_k` chunks most relevant to the last user message, add them
+    /// to the prompt as numbered sources and generate the answer like
+    /// [`complete`](Self::complete). The chunks are returned as citations, in the order the
+    /// model was asked to cite them (`[1]` first).
+    ///
+    /// See the [`rag`](crate::rag) module for chunking and indexing documents.
+    pub async fn retrieve_and_answer<T>(
+        mut self,
+        index: &dyn VectorIndex,
+        top_k: usize,
+    ) -> Result<RagAnswer<T::Output>, LlmError>
+    where
+        T: super::traits::CompletionTarget + Send,
+    {
+        let Some(messages) = self.fields.messages.as_mut() else {
+            return Err(LlmError::Builder(
+                "Messages must be set before retrieve_and_answer".to_string(),
+            ));
+        };
+        let citations = rag::augment(index, top_k, messages).await?;
+        let answer = self.complete::<T>().await?;
+        Ok(RagAnswer { answer, citations })
+    }
+
+    /// Generate every candidate requested with [`candidates`](Self::candidates), one if unset.
+    ///
+    /// Gemini samples all candidates in a single request (`candidateCount`). OpenAI and
+    /// OpenRouter send one request per candidate, concurrently. A candidate that fails to parse
+    /// fails the whole call, and so does one rejected by an output guardrail, whatever its
+    /// [`GuardrailAction`](crate::guardrails::GuardrailAction), or by a
+    /// [validator](Self::validate).
+    #[instrument(
+        name = "generate_candidates",
+        skip(self),
+        fields(
+            model = ?self.fields.model,
+            provider = ?self.fields.provider,
+            candidates = ?self.fields.candidates,
+        ),
+        err
+    )]
+    pub async fn complete_all<T>(self) -> Result<Vec<T::Output>, LlmError>
+    where
+        T: super::traits::CompletionTarget + Send,
+        T::Output: Send,
+    {
+        debug!("Starting candidate generation request");
+        let count = self.fields.candidates.unwrap_or(1);
+        if count == 0 {
+            return Err(LlmError::Builder(
+                "Candidate count must be at least 1.".to_string(),
+            ));
+        }
+        let abort_signal = self.fields.abort_signal.clone();
+        abortable(abort_signal, self.execute_all::<T>(count)).await
+    }
+    /// Generate all candidates like [`complete_all`](Self::complete_all) and return the one with
+    /// the highest `score`. Ties go to the earliest candidate.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsai::{llm, Message, ChatRole, ApiKey, Provider, TextResponse};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let shortest = llm::with(Provider::Gemini)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gemini-2.5-flash")
+    ///     .messages(vec![Message::new(ChatRole::User, "Suggest a name for a bakery")])
+    ///     .candidates(4)
+    ///     .complete_best::<TextResponse, _>(|candidate| -(candidate.text.len() as i64))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn complete_best<T, S>(
+        self,
+        score: impl Fn(&T::Output) -> S,
+    ) -> Result<T::Output, LlmError>
+    where
+        T: super::traits::CompletionTarget + Send,
+        T::Output: Send,
+        S: PartialOrd,
+    {
+        let mut best: Option<(S, T::Output)> = None;
+        for candidate in self.complete_all::<T>().await? {
+            let candidate_score = score(&candidate);
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, _)| candidate_score > *best_score)
+            {
+                best = Some((candidate_score, candidate));
+            }
+        }
+
+        best.map(|(_, candidate)| candidate)
+            .ok_or_else(|| LlmError::Builder("No candidates were generated".to_string()))
+    }
+
+    /// Sample `samples` structured answers and return the most common one, with agreement
+    /// statistics (self-consistency). Answers are grouped by `PartialEq` on `T`, so compare
+    /// only what matters, e.g. the final answer and not the reasoning.
+    ///
+    /// Sampling follows [`complete_all`](Self::complete_all). A temperature above zero is
+    /// needed for the samples to differ.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsai::{llm, Message, ChatRole, ApiKey, Provider, completion_schema};
+    /// #[completion_schema]
+    /// #[derive(PartialEq)]
+    /// struct Answer {
+    ///     result: i64,
+    /// }
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let consensus = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("gpt-4o-mini")
+    ///     .messages(vec![Message::new(ChatRole::User, "What is 17 * 23?")])
+    ///     .temperature(0.8)
+    ///     .complete_consensus::<Answer>(5)
+    ///     .await?;
+    ///
+    /// println!("{} ({:.0}% agreement)", consensus.answer.content.result, consensus.agreement() * 100.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn complete_consensus<T>(
+        self,
+        samples: u32,
+    ) -> Result<super::types::Consensus<T>, LlmError>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema + PartialEq + Send,
+    {
+        let responses = self.candidates(samples).complete_all::<T>().await?;
+        super::types::Consensus::from_samples(responses)
+            .ok_or_else(|| LlmError::Builder("No samples were generated".to_string()))
+    }
+
+    /// Submit the request as an OpenAI background response and return right away with a
+    /// [`PendingResponse`](crate::PendingResponse) to poll, for long reasoning jobs that would
+    /// outlast an HTTP timeout.
+    ///
+    /// Redaction and input guardrails apply as usual, and the
+    /// [`abort_signal`](Self::abort_signal) also stops
+    /// [`await_result`](crate::PendingResponse::await_result).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LlmError::Builder`] for providers other than OpenAI, with tools, with output
+    /// guardrails or validators and when redacted values would need restoring.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rsai::{llm, Message, ChatRole, ApiKey, Provider, TextResponse};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pending = llm::with(Provider::OpenAI)
+    ///     .api_key(ApiKey::Default)?
+    ///     .model("o3")
+    ///     .messages(vec![Message::new(ChatRole::User, "Prove that there are infinitely many primes")])
+    ///     .complete_background::<TextResponse>()
+    ///     .await?;
+    ///
+    /// println!("Submitted {}", pending.id());
+    /// let proof = pending.await_result().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn complete_background<T>(
+        mut self,
+    ) -> Result<crate::provider::PendingResponse<T>, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        if self.fields.provider != Some(Provider::OpenAI) {
+            return Err(LlmError::Builder(
+                "Background responses are only supported by OpenAI".to_string(),
+            ));
+        }
+        if self.fields.tool_registry.is_some() {
+            return Err(LlmError::Builder(
+                "Background responses do not support tools".to_string(),
+            ));
+        }
+
+        let abort_signal = self.fields.abort_signal.clone();
+        let pending = abortable(abort_signal.clone(), async move {
+            let redactions = self.redact_messages();
+            self.check_input().await?;
+            self.recall_memories().await?;
+            let (_, req, format) = self.prepare::<T>()?;
+            if self.output_stage(redactions, &format).is_some() {
+                return Err(LlmError::Builder(
+                    "Background responses do not support output guardrails, validators or restoring redacted values"
+                        .to_string(),
+                ));
+            }
+
+            openai::create_openai_client_from_builder(&self)?
+                .submit_background::<T>(req, format)
+                .await
+        })
+        .await?;
+        Ok(match abort_signal {
+            Some(token) => pending.abort_signal(token),
+            None => pending,
+        })
+    }
+
+    /// Run a completion: input stages, the request and the output stage.
+    async fn execute<T>(mut self) -> Result<T::Output, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let redactions = self.redact_messages();
+        self.check_input().await?;
+        self.recall_memories().await?;
+        let (provider, req, format) = self.prepare::<T>()?;
+
+        match self.output_stage(redactions, &format) {
+            Some(stage) => {
+                self.generate_guarded::<T, _>(&stage, provider, req, format, Ok)
+                    .await
+            }
+            None => self.generate::<T>(provider, req, format).await,
+        }
+    }
+
+    /// Run a completion like [`execute`](Self::execute), passing the output through `transform`.
+    async fn execute_map<T, X>(mut self, transform: X) -> Result<X::Output, LlmError>
+    where
+        T: CompletionTarget + Send,
+        X: Transform<T::Output>,
+    {
+        let redactions = self.redact_messages();
+        self.check_input().await?;
+        self.recall_memories().await?;
+        let (provider, req, format) = self.prepare::<T>()?;
+
+        let stage = self.output_stage(redactions, &format).unwrap_or_default();
+        self.generate_guarded::<T, _>(&stage, provider, req, format, |output| {
+            transform.transform(output)
+        })
+        .await
+    }
+
+    /// Run a candidate completion like [`execute`](Self::execute).
+    async fn execute_all<T>(mut self, count: u32) -> Result<Vec<T::Output>, LlmError>
+    where
+        T: CompletionTarget + Send,
+        T::Output: Send,
+    {
+        let redactions = self.redact_messages();
+        self.check_input().await?;
+        self.recall_memories().await?;
+        let (provider, req, format) = self.prepare::<T>()?;
+
+        let Some(stage) = self.output_stage(redactions, &format) else {
+            return self.generate_all::<T>(provider, req, format, count).await;
+        };
+
+        let mut outputs = Vec::with_capacity(count as usize);
+        for mut response in self
+            .generate_all::<Unparsed<T>>(provider, req, format, count)
+            .await?
+        {
+            if let ResponseContent::Text(text) = &response.content {
+                match stage.check(text).await? {
+                    OutputCheck::Accept(text) => response.content = ResponseContent::Text(text),
+                    OutputCheck::Retry { guardrail, reason } => {
+                        return Err(LlmError::GuardrailViolation { guardrail, reason });
+                    }
+                }
+            }
+            stage
+                .validate(&response)
+                .map_err(|errors| LlmError::OutputRejected {
+                    reason: transform::describe(&errors),
+                })?;
+            outputs.push(T::parse_response(response)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Redact every message, returning the placeholders that were handed out.
+    fn redact_messages(&mut self) -> Redactions {
+        let mut redactions = Redactions::default();
+        if let (Some(redactor), Some(messages)) = (&self.fields.redactor, &mut self.fields.messages)
+        {
+            for message in messages.iter_mut() {
+                message.content = redactor.redact(&message.content, &mut redactions);
+            }
+        }
+        redactions
+    }
+
+    /// The checks to run on the answer to a request for `format` before parsing, if any.
+    fn output_stage(&self, redactions: Redactions, format: &Format) -> Option<OutputStage> {
+        let guardrails = self
+            .fields
+            .guardrails
+            .clone()
+            .filter(Guardrails::has_output);
+        let restore = self
+            .fields
+            .redactor
+            .as_ref()
+            .is_some_and(Redactor::restores)
+            && !redactions.is_empty();
+
+        let format = serde_json::to_value(format).ok();
+        let validators = self
+            .fields
+            .validators
+            .iter()
+            .filter(|validator| format.as_ref().is_some_and(|f| validator.applies_to(f)))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        (guardrails.is_some() || restore || !validators.is_empty()).then(|| OutputStage {
+            guardrails,
+            redactions: restore.then_some(redactions),
+            validators,
+        })
+    }
+
+    /// Add relevant memories to the messages if memory is enabled.
+    async fn recall_memories(&mut self) -> Result<(), LlmError> {
+        match (&self.fields.memory, &mut self.fields.messages) {
+            (Some(recall), Some(messages)) => recall.inject(messages).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Run the input guardrails on every user message.
+    async fn check_input(&mut self) -> Result<(), LlmError> {
+        let (Some(guardrails), Some(messages)) =
+            (&self.fields.guardrails, &mut self.fields.messages)
+        else {
+            return Ok(());
+        };
+
+        for message in messages
+            .iter_mut()
+            .filter(|message| message.role == ChatRole::User)
+        {
+            message.content = guardrails.check_input(&message.content).await?;
+        }
+        Ok(())
+    }
+
+    /// Generate a completion, running the output stage on the answer before parsing it and
+    /// `finish` on the parsed output. A rejected answer is sent back to the model with the
+    /// reason for up to [`Guardrails::max_retries`] retries.
+    async fn generate_guarded<T, O>(
+        &self,
+        stage: &OutputStage,
+        provider: Provider,
+        mut req: StructuredRequest,
+        format: Format,
+        finish: impl Fn(T::Output) -> Result<O, Rejected>,
+    ) -> Result<O, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let mut retries = 0;
+        let mut repaired = None;
+        loop {
+            let mut response = match repaired.take() {
+                Some(response) => response,
+                None => {
+                    self.generate::<Unparsed<T>>(provider, req.clone(), format.clone())
+                        .await?
+                }
+            };
+            let answer = match &response.content {
+                ResponseContent::Text(text) => Some(text.clone()),
+                _ => None,
+            };
+
+            let mut guardrail = None;
+            let mut invalid = None;
+            if let Some(text) = &answer {
+                match stage.check(text).await? {
+                    OutputCheck::Accept(text) => response.content = ResponseContent::Text(text),
+                    OutputCheck::Retry {
+                        guardrail: name,
+                        reason,
+                    } => {
+                        guardrail = Some((name, reason));
+                    }
+                }
+            }
+            let (guardrail, reason) = match guardrail {
+                Some((name, reason)) => (Some(name), reason),
+                None => match stage.validate(&response) {
+                    Ok(()) => match finish(T::parse_response(response)?) {
+                        Ok(output) => return Ok(output),
+                        Err(Rejected { reason }) => (None, reason),
+                    },
+                    Err(errors) => {
+                        let reason = transform::describe(&errors);
+                        invalid = Some((response, errors));
+                        (None, reason)
+                    }
+                },
+            };
+
+            if retries >= stage.retries() {
+                return Err(match guardrail {
+                    Some(guardrail) => LlmError::GuardrailViolation { guardrail, reason },
+                    None => LlmError::OutputRejected { reason },
+                });
+            }
+            retries += 1;
+            debug!(?guardrail, reason, retries, "Retrying rejected answer");
+
+            if self.fields.partial_repair
+                && let Some((response, errors)) = invalid
+            {
+                repaired = self
+                    .repair_fields(provider, &req, response, &errors)
+                    .await?;
+                if repaired.is_some() {
+                    continue;
+                }
+            }
+
+            if let Some(text) = answer {
+                req.messages.push(ConversationMessage::Chat(Message::new(
+                    ChatRole::Assistant,
+                    text,
+                )));
+            }
+            req.messages.push(ConversationMessage::Chat(Message::new(ChatRole::User, format!(
+                    "Your previous answer was rejected: {reason}. Answer again without this problem."
+                ))));
+        }
+    }
+
+    /// Ask the model for corrected values of the invalid fields of `response` and merge them
+    /// into it. Returns `None` when the answer is not JSON, an error concerns the whole answer
+    /// or the reply doesn't correct every field.
+    async fn repair_fields(
+        &self,
+        provider: Provider,
+        req: &StructuredRequest,
+        mut response: ProviderResponse,
+        errors: &[FieldError],
+    ) -> Result<Option<ProviderResponse>, LlmError> {
+        let ResponseContent::Text(answer) = &response.content else {
+            return Ok(None);
+        };
+        if errors.iter().any(|error| error.path.is_empty()) {
+            return Ok(None);
+        }
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(answer) else {
+            return Ok(None);
+        };
+
+        let fields = errors
+            .iter()
+            .map(|error| format!("- {}: {}", error.path, error.message))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut req = req.clone();
+        req.tool_config = None;
+        req.messages.push(ConversationMessage::Chat(Message::new(
+            ChatRole::Assistant,
+            answer.clone(),
+        )));
+        req.messages.push(ConversationMessage::Chat(Message::new(
+            ChatRole::User,
+            format!(
+                "These fields of your previous answer are invalid:\n{fields}\n\
+                 Reply with only a JSON object that maps each of these JSON pointers to its \
+                 corrected value."
+            ),
+        )));
+
+        let repair = self
+            .generate::<Unparsed<TextResponse>>(provider, req, TextResponse::format()?)
+            .await?;
+        let fixes = match &repair.content {
+            ResponseContent::Text(text) => {
+                serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(text.trim()).ok()
+            }
+            _ => None,
+        };
+        if !fixes.is_some_and(|fixes| transform::merge_fields(&mut value, errors, &fixes)) {
+            debug!("Partial repair did not correct every field");
+            return Ok(None);
+        }
+
+        debug!(
+            fields = errors.len(),
+            "Merged repaired fields into the answer"
+        );
+        response.content = ResponseContent::Text(value.to_string());
+        response.usage = response.usage.combined(&repair.usage);
+        Ok(Some(response))
+    }
+
+    /// Send the request to the provider.
+    async fn generate<T>(
+        &self,
+        provider: Provider,
+        req: StructuredRequest,
+        format: Format,
+    ) -> Result<T::Output, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let response = self
+            .with_model_fallbacks(req, |req| self.send::<T>(provider, req, format.clone()))
+            .await?;
+        T::parse_response(response)
+    }
+
+    /// Send the request, racing it against a backup request if [`hedge`](Self::hedge) is set.
+    async fn send<T>(
+        &self,
+        provider: Provider,
+        req: StructuredRequest,
+        format: Format,
+    ) -> Result<ProviderResponse, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let has_tools = req
+            .tool_config
+            .as_ref()
+            .and_then(|config| config.tools.as_ref())
+            .is_some_and(|tools| !tools.is_empty());
+        let Some(hedge) = self.fields.hedge.as_ref().filter(|_| !has_tools) else {
+            return self.send_once::<T>(provider, req, format).await;
+        };
+
+        // Boxed so that racing two requests doesn't double the size of every completion future
+        let mut primary = Box::pin(self.send_once::<T>(provider, req.clone(), format.clone()));
+        if let Ok(result) = runtime::timeout(hedge.delay, &mut primary).await {
+            return result;
+        }
+
+        let Some((backup, backup_provider, backup_req)) =
+            self.hedge_backup(hedge, provider, req, &format)
+        else {
+            return primary.await;
+        };
+        debug!(
+            provider = %backup_provider,
+            model = %backup_req.model,
+            "Primary request is slow, sending hedged request"
+        );
+        let backup = Box::pin(backup.send_once::<T>(backup_provider, backup_req, format));
+        match future::select(primary, backup).await {
+            Either::Left((Ok(response), _)) | Either::Right((Ok(response), _)) => Ok(response),
+            Either::Left((Err(_), backup)) => backup.await,
+            Either::Right((Err(_), primary)) => primary.await,
+        }
+    }
+
+    /// The builder, provider and request of the backup request of [`send`](Self::send), set
+    /// up like a builder for the backup provider. `None` if that provider can't handle the
+    /// request.
+    fn hedge_backup(
+        &self,
+        hedge: &Hedge,
+        provider: Provider,
+        mut req: StructuredRequest,
+        format: &Format,
+    ) -> Option<(Self, Provider, StructuredRequest)> {
+        let mut fields = self.fields.duplicate();
+        fields.tool_registry = self.fields.tool_registry.clone();
+        fields.hedge = None;
+        let backup_provider = hedge.provider.unwrap_or(provider);
+        if backup_provider != provider {
+            fields.provider = Some(backup_provider);
+            fields.http_client_config = hedge.http_client_config.clone();
+            fields.credentials = None;
+            fields.previous_response_id = None;
+        }
+        if let Some(api_key) = &hedge.api_key {
+            fields.api_key = Some(api_key.clone());
+            fields.credentials = None;
+            fields.vertex = None;
+            if let Some(config) = &mut fields.http_client_config {
+                config.credentials = None;
+            }
+        }
+        if let Some(model) = &hedge.model {
+            req.model = model.clone();
+        }
+
+        let capabilities = backup_provider.capabilities_for(&req.model);
+        if let Err(error) =
+            check_capabilities(&capabilities, &fields, format, backup_provider, &req.model)
+        {
+            debug!(%error, "Not sending a hedged request");
+            return None;
+        }
+        if let Some(config) = &mut req.generation_config {
+            config.stop = self.server_stop(backup_provider);
+        }
+        let backup = LlmBuilder {
+            fields,
+            _state: PhantomData,
+        };
+        Some((backup, backup_provider, req))
+    }
+
+    /// The stop sequences to send to `provider`, `None` if it cuts the answer on the client.
+    fn server_stop(&self, provider: Provider) -> Option<Vec<String>> {
+        (!self.fields.stop.is_empty() && provider.capabilities().supports_stop_sequences)
+            .then(|| self.fields.stop.clone())
+    }
+
+    /// Send the request to the provider once and report its usage.
+    async fn send_once<T>(
+        &self,
+        provider: Provider,
+        req: StructuredRequest,
+        format: Format,
+    ) -> Result<ProviderResponse, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let registry = self.fields.tool_registry.as_ref();
+        let model = req.model.clone();
+        let prefill = assistant_prefill(provider, &req);
+        let structured = matches!(format.format, FormatType::JsonSchema(_));
+
+        let (response, timings) = timings::record(async {
+            Ok::<_, LlmError>(match provider {
+                Provider::OpenAI => {
+                    let client = openai::create_openai_client_from_builder(self)?;
+                    client
+                        .generate_completion::<Unparsed<T>, Ctx>(req, format, registry)
+                        .await?
+                }
+                Provider::OpenRouter => {
+                    let client = openrouter::create_openrouter_client_from_builder(self)?;
+                    client
+                        .generate_completion::<Unparsed<T>, Ctx>(req, format, registry)
+                        .await?
+                }
+                Provider::Gemini => {
+                    let client = gemini::create_gemini_client_from_builder(self)?;
+                    client
+                        .generate_completion::<Unparsed<T>, Ctx>(req, format, registry)
+                        .await?
+                }
+                Provider::Cohere => {
+                    let client = cohere::create_cohere_client_from_builder(self)?;
+                    client
+                        .generate_completion::<Unparsed<T>, Ctx>(req, format, registry)
+                        .await?
+                }
+                Provider::XAI | Provider::Together | Provider::Fireworks => {
+                    let client = compatible::create_compatible_client_from_builder(self, provider)?;
+                    client
+                        .generate_completion::<Unparsed<T>, Ctx>(req, format, registry)
+                        .await?
+                }
+            })
+        })
+        .await;
+        let mut response = response?;
+        if response.model.is_empty() {
+            response.model = model;
+        }
+        prepend_prefill(&mut response, prefill.as_deref());
+        self.apply_stop(provider, &mut response);
+        if structured {
+            self.check_json(&mut response)?;
+        }
+        self.report_usage(&response, timings.duration());
+        response.timings = Some(timings);
+        response.prompt = self.fields.prompt.clone();
+        Ok(response)
+    }
+
+    /// Cut the answer at the stop sequences the provider didn't apply and at the stop
+    /// conditions.
+    fn apply_stop(&self, provider: Provider, response: &mut ProviderResponse) {
+        let sequences = if provider.capabilities().supports_stop_sequences {
+            &[][..]
+        } else {
+            &self.fields.stop[..]
+        };
+        stop::apply(response, sequences, &self.fields.stop_conditions);
+    }
+
+    /// Repair or reject a structured answer that is not exactly one JSON value, as configured
+    /// with [`lenient_json`](Self::lenient_json) and [`strict_json`](Self::strict_json).
+    fn check_json(&self, response: &mut ProviderResponse) -> Result<(), LlmError> {
+        if self.fields.lenient_json {
+            lenient::apply(response);
+        }
+        if self.fields.strict_json {
+            lenient::require_json(response)?;
+        }
+        Ok(())
+    }
+
+    /// Run `send` with the request, then with each fallback model while the error is one that
+    /// another model may not have.
+    async fn with_model_fallbacks<O, F, Fut>(
+        &self,
+        mut req: StructuredRequest,
+        send: F,
+    ) -> Result<O, LlmError>
+    where
+        F: Fn(StructuredRequest) -> Fut,
+        Fut: Future<Output = Result<O, LlmError>>,
+    {
+        let mut fallbacks = self
+            .fields
+            .model_fallbacks
+            .iter()
+            .filter(|model| **model != req.model)
+            .collect::<Vec<_>>()
+            .into_iter();
+        loop {
+            match send(req.clone()).await {
+                Err(err) if should_fall_back(&err) => {
+                    let Some(next) = fallbacks.next() else {
+                        return Err(err);
+                    };
+                    debug!(from = %req.model, to = %next, error = %err, "Falling back to next model");
+                    req.model = next.clone();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send the request to the provider for `count` candidates.
+    async fn generate_all<T>(
+        &self,
+        provider: Provider,
+        req: StructuredRequest,
+        format: Format,
+        count: u32,
+    ) -> Result<Vec<T::Output>, LlmError>
+    where
+        T: CompletionTarget + Send,
+        T::Output: Send,
+    {
+        let responses = self
+            .with_model_fallbacks(req, |req| {
+                self.send_all::<T>(provider, req, format.clone(), count)
+            })
+            .await?;
+        responses.into_iter().map(T::parse_response).collect()
+    }
+
+    /// Send the request for `count` candidates once and report their usage.
+    async fn send_all<T>(
+        &self,
+        provider: Provider,
+        req: StructuredRequest,
+        format: Format,
+        count: u32,
+    ) -> Result<Vec<ProviderResponse>, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let registry = self.fields.tool_registry.as_ref();
+        let model = req.model.clone();
+        let prefill = assistant_prefill(provider, &req);
+        let structured = matches!(format.format, FormatType::JsonSchema(_));
+
+        let (responses, timings) = timings::record(async {
+            Ok::<_, LlmError>(match provider {
+                Provider::OpenAI => {
+                    let client = openai::create_openai_client_from_builder(self)?;
+                    client
+                        .generate_candidates::<Unparsed<T>, Ctx>(req, format, registry, count)
+                        .await?
+                }
+                Provider::OpenRouter => {
+                    let client = openrouter::create_openrouter_client_from_builder(self)?;
+                    client
+                        .generate_candidates::<Unparsed<T>, Ctx>(req, format, registry, count)
+                        .await?
+                }
+                Provider::Gemini => {
+                    let client = gemini::create_gemini_client_from_builder(self)?;
+                    client
+                        .generate_candidates::<Unparsed<T>, Ctx>(req, format, registry, count)
+                        .await?
+                }
+                Provider::Cohere => {
+                    let client = cohere::create_cohere_client_from_builder(self)?;
+                    client
+                        .generate_candidates::<Unparsed<T>, Ctx>(req, format, registry, count)
+                        .await?
+                }
+                Provider::XAI | Provider::Together | Provider::Fireworks => {
+                    let client = compatible::create_compatible_client_from_builder(self, provider)?;
+                    client
+                        .generate_candidates::<Unparsed<T>, Ctx>(req, format, registry, count)
+                        .await?
+                }
+            })
+        })
+        .await;
+        let responses = responses?;
+        let latency = timings.duration();
+        responses
+            .into_iter()
+            .map(|mut response| {
+                if response.model.is_empty() {
+                    response.model = model.clone();
+                }
+                prepend_prefill(&mut response, prefill.as_deref());
+                self.apply_stop(provider, &mut response);
+                if structured {
+                    self.check_json(&mut response)?;
+                }
+                self.report_usage(&response, latency);
+                response.timings = Some(timings.clone());
+                response.prompt = self.fields.prompt.clone();
+                Ok(response)
+            })
+            .collect()
+    }
+
+    /// The body of the first request the provider client would send for `req`.
+    fn request_body(
+        &self,
+        provider: Provider,
+        req: &StructuredRequest,
+        format: Format,
+    ) -> Result<serde_json::Value, LlmError> {
+        match provider {
+            Provider::OpenAI => {
+                openai::create_openai_client_from_builder(self)?.request_body(req, format)
+            }
+            Provider::OpenRouter => {
+                openrouter::create_openrouter_client_from_builder(self)?.request_body(req, format)
+            }
+            Provider::Gemini => {
+                gemini::create_gemini_client_from_builder(self)?.request_body(req, format)
+            }
+            Provider::Cohere => {
+                cohere::create_cohere_client_from_builder(self)?.request_body(req, format)
+            }
+            Provider::XAI | Provider::Together | Provider::Fireworks => {
+                compatible::create_compatible_client_from_builder(self, provider)?
+                    .request_body(req, format)
+            }
+        }
+    }
+
+    /// Validate the builder and assemble the provider-agnostic request.
+    fn prepare<T>(&mut self) -> Result<(Provider, StructuredRequest, Format), LlmError>
+    where
+        T: super::traits::CompletionTarget,
+    {
+        let (messages, provider, model) = self.fields.validate()?;
+        let model = model.to_string();
+
+        if self.fields.previous_response_id.is_some() && provider != Provider::OpenAI {
+            return Err(LlmError::Builder(
+                "continue_from is only supported by OpenAI".to_string(),
+            ));
+        }
+        validate_metadata(&self.fields.metadata)?;
+        let mut capabilities = provider.capabilities_for(&model);
+        if let Some(tokens) = self.fields.max_output_tokens {
+            capabilities = capabilities.with_max_output_tokens(tokens);
+        }
+        let format = T::format()?;
+        check_capabilities(&capabilities, &self.fields, &format, provider, &model)?;
+        let messages = with_examples::<T>(messages, &self.fields.examples);
+
+        if !T::supports_tools() && self.fields.tool_registry.is_some() {
+            return Err(LlmError::Builder(
+                "Tools are only supported with structured completion targets".to_string(),
+            ));
+        }
+
+        if let (Some(enabled), Some(registry)) =
+            (&self.fields.enabled_tools, &self.fields.tool_registry)
+        {
+            let filtered = registry.filter(|schema| enabled.contains(&schema.name))?;
+            self.fields.tool_registry = Some(filtered);
         }
 
         // Deferred error handling for tool registry errors in case of a poisoned lock.
+        // Hidden tools are only offered when explicitly enabled by name.
         let tool_schemas = if T::supports_tools() {
             self.fields
                 .tool_registry
                 .as_ref()
-                .map(|registry| registry.get_schemas())
+                .map(|registry| match self.fields.enabled_tools {
+                    Some(_) => registry.get_all_schemas(),
+                    None => registry.get_schemas(),
+                })
                 .transpose()?
                 .map(|tools| tools.into_boxed_slice())
         } else {
             None
         };
 
-        match provider {
-            Provider::OpenAI => {
-                let conversation_messages: Vec<ConversationMessage> = messages
-                    .into_iter()
-                    .map(ConversationMessage::Chat)
-                    .collect();
-
-                let req = StructuredRequest {
-                    model: model_string,
-                    messages: conversation_messages,
-                    tool_config: tool_schemas.map(|tools| ToolConfig {
-                        tools: Some(tools),
-                        tool_choice: self.fields.tool_choice.clone(),
-                        parallel_tool_calls: self.fields.parallel_tool_calls,
-                    }),
-                    generation_config: Some(GenerationConfig {
-                        max_tokens: self.fields.max_tokens,
-                        temperature: self.fields.temperature,
-                        top_p: self.fields.top_p,
-                    }),
-                };
-                let client = openai::create_openai_client_from_builder(&self)?;
-                client
-                    .generate_completion::<T, Ctx>(
-                        req,
-                        format.clone(),
-                        self.fields.tool_registry.as_ref(),
-                    )
-                    .await
-            }
-            Provider::OpenRouter => {
-                let conversation_messages: Vec<ConversationMessage> = messages
-                    .into_iter()
-                    .map(ConversationMessage::Chat)
-                    .collect();
-
-                let req = StructuredRequest {
-                    model: model_string,
-                    messages: conversation_messages,
-                    tool_config: tool_schemas.map(|tools| ToolConfig {
-                        tools: Some(tools),
-                        tool_choice: self.fields.tool_choice.clone(),
-                        parallel_tool_calls: self.fields.parallel_tool_calls,
-                    }),
-                    generation_config: Some(GenerationConfig {
-                        max_tokens: self.fields.max_tokens,
-                        temperature: self.fields.temperature,
-                        top_p: self.fields.top_p,
-                    }),
-                };
-                let client = openrouter::create_openrouter_client_from_builder(&self)?;
-                client
-                    .generate_completion::<T, Ctx>(req, format, self.fields.tool_registry.as_ref())
-                    .await
-            }
-            Provider::Gemini => {
-                let conversation_messages: Vec<ConversationMessage> = messages
-                    .into_iter()
-                    .map(ConversationMessage::Chat)
-                    .collect();
-
-                let req = StructuredRequest {
-                    model: model_string,
-                    messages: conversation_messages,
-                    tool_config: tool_schemas.map(|tools| ToolConfig {
-                        tools: Some(tools),
-                        tool_choice: self.fields.tool_choice.clone(),
-                        parallel_tool_calls: self.fields.parallel_tool_calls,
-                    }),
-                    generation_config: Some(GenerationConfig {
-                        max_tokens: self.fields.max_tokens,
-                        temperature: self.fields.temperature,
-                        top_p: self.fields.top_p,
-                    }),
-                };
-                let client = gemini::create_gemini_client_from_builder(&self)?;
-                client
-                    .generate_completion::<T, Ctx>(req, format, self.fields.tool_registry.as_ref())
-                    .await
-            }
+        let req = StructuredRequest {
+            model,
+            messages: messages
+                .into_iter()
+                .map(ConversationMessage::Chat)
+                .chain(self.fields.tool_history.iter().cloned())
+                .collect(),
+            tool_config: tool_schemas.map(|tools| ToolConfig {
+                tools: Some(tools),
+                tool_choice: self.fields.tool_choice.clone(),
+                parallel_tool_calls: self.fields.parallel_tool_calls,
+            }),
+            generation_config: Some(GenerationConfig {
+                max_tokens: self.fields.max_tokens,
+                temperature: self.fields.temperature,
+                top_p: self.fields.top_p,
+                stop: self.server_stop(provider),
+            }),
+        };
+
+        if let Some(expected) = &self.fields.expected_request {
+            let actual = self.request_body(provider, &req, format.clone())?;
+            if let Err(path) = super::testing::contains(&actual, expected, String::new()) {
+                return Err(LlmError::RequestMismatch { path, actual });
+            }
+        }
+
+        Ok((provider, req, format))
+    }
+}
+
+/// Retries of answers rejected by a [`Transform`] when no guardrails are configured.
+const DEFAULT_OUTPUT_RETRIES: u32 = 2;
+
+/// Output guardrails and placeholder restoration, applied to the raw answer in that order,
+/// then the validators of the output type.
+#[derive(Default)]
+struct OutputStage {
+    guardrails: Option<Guardrails>,
+    redactions: Option<Redactions>,
+    validators: Vec<Validator>,
+}
+
+impl OutputStage {
+    async fn check(&self, text: &str) -> Result<OutputCheck, LlmError> {
+        let check = match &self.guardrails {
+            Some(guardrails) => guardrails.check_output(text).await?,
+            None => OutputCheck::Accept(text.to_string()),
+        };
+
+        Ok(match (check, &self.redactions) {
+            (OutputCheck::Accept(text), Some(redactions)) => {
+                OutputCheck::Accept(redactions.restore(&text))
+            }
+            (check, _) => check,
+        })
+    }
+
+    fn validate(&self, response: &ProviderResponse) -> Result<(), Vec<FieldError>> {
+        self.validators
+            .iter()
+            .try_for_each(|validator| validator.check(response))
+    }
+
+    fn retries(&self) -> u32 {
+        self.guardrails
+            .as_ref()
+            .map_or(DEFAULT_OUTPUT_RETRIES, Guardrails::retries)
+    }
+}
+
+/// Requests the format of `T` but returns the response unparsed, so output guardrails can
+/// inspect it first.
+struct Unparsed<T>(PhantomData<T>);
+
+impl<T: CompletionTarget + Send> CompletionTarget for Unparsed<T> {
+    type Output = ProviderResponse;
+
+    fn format() -> Result<Format, LlmError> {
+        T::format()
+    }
+
+    fn parse_response(res: ProviderResponse) -> Result<Self::Output, LlmError> {
+        Ok(res)
+    }
+
+    fn supports_tools() -> bool {
+        T::supports_tools()
+    }
+}
+
+/// Whether `err` means the model is unavailable or the prompt does not fit its context window,
+/// so a different model may succeed.
+fn should_fall_back(err: &LlmError) -> bool {
+    let LlmError::Api {
+        message,
+        status_code: Some(status),
+        ..
+    } = err
+    else {
+        return false;
+    };
+    if matches!(status, 404 | 503 | 529) {
+        return true;
+    }
+
+    let message = message.to_lowercase();
+    matches!(status, 400 | 413)
+        && [
+            "context_length_exceeded",
+            "context length",
+            "context window",
+            "maximum number of tokens",
+            "too many tokens",
+            "model_not_found",
+            "does not exist",
+            "is not found",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Check metadata against OpenAI's limits before sending it.
+fn validate_metadata(metadata: &BTreeMap<String, String>) -> Result<(), LlmError> {
+    if metadata.len() > 16 {
+        return Err(LlmError::Builder(format!(
+            "Metadata has {} entries, the limit is 16",
+            metadata.len()
+        )));
+    }
+    if let Some((key, _)) = metadata.iter().find(|(key, _)| key.chars().count() > 64) {
+        return Err(LlmError::Builder(format!(
+            "Metadata key '{key}' is longer than 64 characters"
+        )));
+    }
+    if let Some((key, _)) = metadata
+        .iter()
+        .find(|(_, value)| value.chars().count() > 512)
+    {
+        return Err(LlmError::Builder(format!(
+            "Metadata value of '{key}' is longer than 512 characters"
+        )));
+    }
+    Ok(())
+}
+
+/// Fail before sending a request the provider cannot handle.
+fn check_capabilities<Ctx>(
+    capabilities: &Capabilities,
+    fields: &BuilderFields<Ctx>,
+    format: &Format,
+    provider: Provider,
+    model: &str,
+) -> Result<(), LlmError> {
+    if fields.tool_registry.is_some() && !capabilities.supports_tools {
+        return Err(LlmError::Builder(format!(
+            "{provider} does not support tools with {model}"
+        )));
+    }
+    if matches!(format.format, FormatType::JsonSchema(_))
+        && !capabilities.supports_structured_output
+    {
+        return Err(LlmError::Builder(format!(
+            "{provider} does not support structured output with {model}"
+        )));
+    }
+    if fields.parallel_tool_calls == Some(true) && !capabilities.supports_parallel_tool_calls {
+        return Err(LlmError::Builder(format!(
+            "{provider} does not support parallel tool calls"
+        )));
+    }
+    if fields
+        .messages
+        .as_ref()
+        .and_then(|messages| messages.last())
+        .is_some_and(|message| message.role == ChatRole::Assistant)
+        && !capabilities.supports_assistant_prefill
+    {
+        return Err(LlmError::Builder(format!(
+            "{provider} does not support prefilling the assistant message; the last message must \
+             not be an assistant message"
+        )));
+    }
+    if let (Some(max_tokens), Some(limit)) = (fields.max_tokens, capabilities.max_output_tokens)
+        && max_tokens > limit
+    {
+        return Err(LlmError::Builder(format!(
+            "max_tokens ({max_tokens}) exceeds the output limit of {model} ({limit} tokens)"
+        )));
+    }
+    Ok(())
+}
+
+/// The content of a trailing assistant message that `provider` continues, see
+/// [`Capabilities::supports_assistant_prefill`].
+fn assistant_prefill(provider: Provider, req: &StructuredRequest) -> Option<String> {
+    if !provider.capabilities().supports_assistant_prefill {
+        return None;
+    }
+    match req.messages.last() {
+        Some(ConversationMessage::Chat(message)) if message.role == ChatRole::Assistant => {
+            Some(message.content.clone())
         }
+        _ => None,
+    }
+}
+
+/// Put the prefill in front of the continuation, so that the answer is parsed as a whole.
+fn prepend_prefill(response: &mut ProviderResponse, prefill: Option<&str>) {
+    if let (Some(prefill), ResponseContent::Text(text)) = (prefill, &mut response.content) {
+        text.insert_str(0, prefill);
     }
 }
 
+/// Insert few-shot examples as user/assistant turns after the leading system messages.
+fn with_examples<T: super::traits::CompletionTarget>(
+    messages: &[Message],
+    examples: &[(String, serde_json::Value)],
+) -> Vec<Message> {
+    let system = messages
+        .iter()
+        .take_while(|message| message.role == ChatRole::System)
+        .count();
+
+    let demonstrations = examples.iter().flat_map(|(input, output)| {
+        [
+            Message::new(ChatRole::User, input.clone()),
+            Message::new(ChatRole::Assistant, T::format_example(output.clone())),
+        ]
+    });
+
+    messages[..system]
+        .iter()
+        .cloned()
+        .chain(demonstrations)
+        .chain(messages[system..].iter().cloned())
+        .collect()
+}
+
 impl LlmBuilder<private::MessagesSet, ()> {
     /// Set the tools for the LLM request with automatic execution support.
     /// This transitions to the ToolsSet state where tool_choice and parallel_tool_calls can be configured.
@@ -502,6 +2545,16 @@ impl LlmBuilder<private::MessagesSet, ()> {
             _state: PhantomData,
         }
     }
+
+    /// Like [`tools`](Self::tools), but passes `context` to the tools for this request
+    /// only, so one toolset can serve many request-scoped contexts.
+    pub fn tools_with_context<NewCtx: Send + Sync + 'static>(
+        self,
+        toolset: &super::types::ToolSet<NewCtx>,
+        context: NewCtx,
+    ) -> LlmBuilder<private::ToolsSet, NewCtx> {
+        self.tools(toolset.scoped(context))
+    }
 }
 
 impl<Ctx: Send + Sync + 'static> LlmBuilder<private::ToolsSet, Ctx> {
@@ -510,12 +2563,108 @@ impl<Ctx: Send + Sync + 'static> LlmBuilder<private::ToolsSet, Ctx> {
         self
     }
 
+    /// Force a specific tool using the enum generated by `toolset! { enum Name => ... }`.
+    pub fn tool_choice_typed(self, tool: impl super::traits::ToolName) -> Self {
+        self.tool_choice(ToolChoice::Function {
+            name: tool.tool_name().to_string(),
+        })
+    }
+
+    /// Only expose (and allow execution of) the named tools for this request.
+    /// Names not present in the toolset are ignored. Naming a `#[tool(hidden)]` tool
+    /// offers it to the model.
+    pub fn enabled_tools(mut self, names: &[&str]) -> Self {
+        self.fields.enabled_tools = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
     /// Set whether to enable parallel tool calls.
     /// When true, the model can call multiple tools simultaneously.
     pub fn parallel_tool_calls(mut self, enabled: bool) -> Self {
         self.fields.parallel_tool_calls = Some(enabled);
         self
     }
+
+    /// Set the limits and progress hooks of the tool calling loop.
+    pub fn tool_calling(mut self, config: ToolCallingConfig) -> Self {
+        self.fields.tool_calling_config = Some(config);
+        self
+    }
+}
+
+/// A provider, API key, model and request defaults captured once to send many requests.
+///
+/// Created with [`LlmBuilder::client`] after the model is set. Every option set on the
+/// builder before that (generation parameters, HTTP config, inspectors, guardrails, ...)
+/// applies to all requests of the client.
+///
+/// # Example
+/// ```no_run
+/// # use rsai::{llm, ApiKey, ChatRole, Message, Provider, TextResponse};
+/// # async fn example() -> Result<(), rsai::LlmError> {
+/// let client = llm::with(Provider::OpenAI)
+///     .api_key(ApiKey::Default)?
+///     .model("gpt-4o-mini")
+///     .temperature(0.2)
+///     .client();
+///
+/// for question in ["What is Rust?", "What is Cargo?"] {
+///     let answer = client
+///         .complete::<TextResponse>(vec![Message::new(ChatRole::User, question.to_string())])
+///         .await?;
+///     println!("{}", answer.text);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LlmClient<Ctx = ()> {
+    fields: BuilderFields<()>,
+    tools: Option<ToolSet<Ctx>>,
+}
+
+impl LlmClient<()> {
+    /// Offer `toolset` to the model on every request.
+    pub fn with_tools<NewCtx: Send + Sync + 'static>(
+        self,
+        toolset: ToolSet<NewCtx>,
+    ) -> LlmClient<NewCtx> {
+        LlmClient {
+            fields: self.fields,
+            tools: Some(toolset),
+        }
+    }
+}
+
+impl<Ctx: Send + Sync + 'static> LlmClient<Ctx> {
+    /// A builder with the client's configuration, to customize a single request. The
+    /// client's tools are not included.
+    pub fn builder(&self) -> LlmBuilder<private::Configuring, ()> {
+        LlmBuilder {
+            fields: self.fields.duplicate(),
+            _state: PhantomData,
+        }
+    }
+
+    /// Generate a completion for `messages` like [`LlmBuilder::complete`].
+    pub async fn complete<T>(&self, messages: Vec<Message>) -> Result<T::Output, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let builder = self.builder().messages(messages);
+        match &self.tools {
+            Some(toolset) => builder.tools(toolset.duplicate()?).complete::<T>().await,
+            None => builder.complete::<T>().await,
+        }
+    }
+
+    /// Send `body` unchanged as the provider's generation request like
+    /// [`LlmBuilder::complete_raw`].
+    pub async fn complete_raw(
+        &self,
+        body: serde_json::Value,
+    ) -> Result<ProviderResponse, LlmError> {
+        self.builder().complete_raw(body).await
+    }
 }
 
 /// Module containing the main entry point for building LLM requests
@@ -544,13 +2693,275 @@ pub mod llm {
             _state: PhantomData,
         }
     }
+
+    /// Environment variable naming the provider for [`from_env`] in a form that [`Provider`]'s
+    /// [`FromStr`](std::str::FromStr) accepts, e.g. `openai`.
+    pub const PROVIDER_ENV_VAR: &str = "RSAI_PROVIDER";
+
+    /// Environment variable naming the model for [`from_env`].
+    pub const MODEL_ENV_VAR: &str = "RSAI_MODEL";
+
+    /// A builder with the provider in `RSAI_PROVIDER`, the model in `RSAI_MODEL` and the API
+    /// key from the provider's default environment variable (e.g. `OPENAI_API_KEY`).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, ChatRole, Message, TextResponse};
+    /// # async fn example() -> Result<(), rsai::LlmError> {
+    /// // RSAI_PROVIDER=gemini RSAI_MODEL=gemini-2.5-flash GEMINI_API_KEY=... cargo run
+    /// let answer = llm::from_env()?
+    ///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+    ///     .complete::<TextResponse>()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<LlmBuilder<private::Configuring, ()>, LlmError> {
+        from_vars(|name| env::var(name).ok())
+    }
+
+    /// [`from_env`] reading variables through `var`.
+    pub(super) fn from_vars(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<LlmBuilder<private::Configuring, ()>, LlmError> {
+        let require = |name: &str| {
+            var(name)
+                .filter(|value| !value.trim().is_empty())
+                .ok_or_else(|| LlmError::Builder(format!("Missing {name} environment variable")))
+        };
+        let provider: Provider = require(PROVIDER_ENV_VAR)?.parse()?;
+        let model = require(MODEL_ENV_VAR)?;
+        let api_key = require(provider.default_api_key_env_var())?;
+
+        Ok(with(provider)
+            .api_key(ApiKey::Custom(api_key))?
+            .model(model.trim()))
+    }
+
+    /// A builder from the profile `name` of the profile file in `RSAI_CONFIG`, or `rsai.toml`
+    /// in the working directory. See [`profile`](crate::profile).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::llm;
+    /// # fn example() -> Result<(), rsai::LlmError> {
+    /// let builder = llm::profile("fast")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "profiles")]
+    pub fn profile(name: &str) -> Result<LlmBuilder<private::Configuring, ()>, LlmError> {
+        super::super::profile::load_default()?.builder(name)
+    }
+
+    /// Manage responses stored by the provider (OpenAI only).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rsai::{llm, Provider};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let stored = llm::responses(Provider::OpenAI).get("resp_123").await?;
+    /// llm::responses(Provider::OpenAI).delete(&stored.id).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn responses(provider: Provider) -> StoredResponses {
+        StoredResponses::new(provider)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::fixtures::{
+        ScriptedTransport, Sum, mock_openai, responses_answer, sum_toolset,
+    };
+    use crate::core::transport::{Transport, TransportRequest, TransportResponse};
     use std::sync::atomic::{AtomicUsize, Ordering};
 
+    #[test]
+    fn test_from_env_reads_provider_model_and_key() {
+        let vars = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        let builder = llm::from_vars(vars(&[
+            ("RSAI_PROVIDER", "gemini"),
+            ("RSAI_MODEL", "gemini-2.5-flash"),
+            ("GEMINI_API_KEY", "test-key"),
+        ]))
+        .unwrap();
+        assert_eq!(builder.fields.provider, Some(Provider::Gemini));
+        assert_eq!(builder.fields.model.as_deref(), Some("gemini-2.5-flash"));
+        assert_eq!(builder.fields.api_key.as_deref(), Some("test-key"));
+
+        let missing_key = llm::from_vars(vars(&[
+            ("RSAI_PROVIDER", "openai"),
+            ("RSAI_MODEL", "gpt-4o-mini"),
+        ]));
+        assert!(matches!(
+            missing_key,
+            Err(LlmError::Builder(message)) if message.contains("OPENAI_API_KEY")
+        ));
+
+        let unknown = llm::from_vars(vars(&[("RSAI_PROVIDER", "acme"), ("RSAI_MODEL", "m")]));
+        assert!(matches!(unknown, Err(LlmError::ProviderConfiguration(_))));
+    }
+
+    #[test]
+    fn test_max_tokens_beyond_the_output_limit_fail_fast() {
+        let builder = || {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test".into()))
+                .unwrap()
+                .model(crate::models::openai::GPT_4O_MINI)
+                .messages(vec![Message::new(
+                    super::super::types::ChatRole::User,
+                    "test",
+                )])
+                .max_tokens(20_000)
+        };
+
+        // Within the context window, but above what the model generates
+        let result = builder().prepare::<crate::TextResponse>();
+        assert!(matches!(
+            result,
+            Err(LlmError::Builder(message)) if message.contains("output limit of gpt-4o-mini")
+        ));
+
+        assert!(
+            builder()
+                .max_output_tokens(32_000)
+                .prepare::<crate::TextResponse>()
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_the_provider_request_body() {
+        let request = llm::with(Provider::Gemini)
+            .api_key(ApiKey::Custom("test".into()))
+            .unwrap()
+            .model(crate::models::gemini::FLASH_2_5)
+            .messages(vec![
+                Message::new(super::super::types::ChatRole::System, "Be brief."),
+                Message::new(super::super::types::ChatRole::User, "Hello"),
+            ])
+            .temperature(0.5)
+            .dry_run::<crate::TextResponse>()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            request["systemInstruction"]["parts"][0]["text"],
+            "Be brief."
+        );
+        assert_eq!(request["contents"][0]["parts"][0]["text"], "Hello");
+        assert_eq!(request["generationConfig"]["temperature"], 0.5);
+        assert!(request.get("model").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_structured_output_fails_fast_for_models_without_json_mode() {
+        #[derive(schemars::JsonSchema, serde::Deserialize)]
+        struct Answer {
+            #[allow(dead_code)]
+            value: String,
+        }
+
+        let builder = |model| {
+            llm::with(Provider::Together)
+                .api_key(ApiKey::Custom("test".into()))
+                .unwrap()
+                .model(model)
+                .messages(vec![Message::new(
+                    super::super::types::ChatRole::User,
+                    "Hello",
+                )])
+        };
+
+        let err = builder(crate::models::together::DEEPSEEK_R1)
+            .dry_run::<Answer>()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, LlmError::Builder(message) if message.contains("structured output")),
+            "{err:?}"
+        );
+
+        let request = builder(crate::models::together::DEEPSEEK_V3)
+            .dry_run::<Answer>()
+            .await
+            .unwrap();
+        assert_eq!(request["response_format"]["type"], "json_schema");
+        assert_eq!(request["model"], "deepseek-ai/DeepSeek-V3");
+    }
+
+    #[tokio::test]
+    async fn test_trailing_assistant_message_needs_prefill_support() {
+        let builder = |provider| {
+            llm::with(provider)
+                .api_key(ApiKey::Custom("test".into()))
+                .unwrap()
+                .model("model")
+                .messages(vec![
+                    Message::new(super::super::types::ChatRole::User, "List three colors"),
+                    Message::new(super::super::types::ChatRole::Assistant, "1."),
+                ])
+        };
+
+        let err = builder(Provider::OpenAI)
+            .dry_run::<crate::TextResponse>()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, LlmError::Builder(message) if message.contains("prefilling")),
+            "{err:?}"
+        );
+
+        let request = builder(Provider::Fireworks)
+            .dry_run::<crate::TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(request["messages"][1]["role"], "assistant");
+        assert_eq!(request["messages"][1]["content"], "1.");
+    }
+
+    #[tokio::test]
+    async fn test_expect_request_reports_the_mismatching_field() {
+        let builder = || {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test".into()))
+                .unwrap()
+                .model("gpt-4o-mini")
+                .messages(vec![Message::new(
+                    super::super::types::ChatRole::User,
+                    "Hello",
+                )])
+                .max_tokens(100)
+        };
+
+        let matched = builder()
+            .expect_request(serde_json::json!({ "max_output_tokens": 100 }))
+            .dry_run::<crate::TextResponse>()
+            .await;
+        assert!(matched.is_ok());
+
+        let mismatch = builder()
+            .expect_request(serde_json::json!({ "input": [{ "content": "Hi" }] }))
+            .dry_run::<crate::TextResponse>()
+            .await;
+        assert!(matches!(
+            mismatch,
+            Err(LlmError::RequestMismatch { path, actual })
+                if path == ".input[0].content" && actual["model"] == "gpt-4o-mini"
+        ));
+    }
+
     #[test]
     fn test_inspect_request_is_chainable() {
         let call_count = Arc::new(AtomicUsize::new(0));
@@ -560,10 +2971,10 @@ mod tests {
             .api_key(ApiKey::Custom("test".into()))
             .unwrap()
             .model("gpt-4o-mini")
-            .messages(vec![Message {
-                role: super::super::types::ChatRole::User,
-                content: "test".to_string(),
-            }])
+            .messages(vec![Message::new(
+                super::super::types::ChatRole::User,
+                "test",
+            )])
             .inspect_request(move |_| {
                 count_clone.fetch_add(1, Ordering::SeqCst);
             })
@@ -587,10 +2998,10 @@ mod tests {
             .api_key(ApiKey::Custom("test".into()))
             .unwrap()
             .model("gpt-4o-mini")
-            .messages(vec![Message {
-                role: super::super::types::ChatRole::User,
-                content: "test".to_string(),
-            }])
+            .messages(vec![Message::new(
+                super::super::types::ChatRole::User,
+                "test",
+            )])
             .inspect_response(|_| {})
             .temperature(0.5);
 
@@ -612,10 +3023,10 @@ mod tests {
             .api_key(ApiKey::Custom("test".into()))
             .unwrap()
             .model("gpt-4o-mini")
-            .messages(vec![Message {
-                role: super::super::types::ChatRole::User,
-                content: "test".to_string(),
-            }])
+            .messages(vec![Message::new(
+                super::super::types::ChatRole::User,
+                "test",
+            )])
             .inspect_request(|_| {})
             .inspect_response(|_| {});
 
@@ -624,6 +3035,58 @@ mod tests {
         assert!(config.response_inspector.is_some());
     }
 
+    #[test]
+    fn test_headers_accumulate_and_keep_timeout() {
+        let builder = llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test".into()))
+            .unwrap()
+            .model("gpt-4o-mini")
+            .messages(vec![Message::new(
+                super::super::types::ChatRole::User,
+                "test",
+            )])
+            .timeout(std::time::Duration::from_secs(5))
+            .header("OpenAI-Organization", "org-123")
+            .header("traceparent", "00-abc-def-01");
+
+        let config = builder.fields.http_client_config.as_ref().unwrap();
+        assert_eq!(config.timeout, std::time::Duration::from_secs(5));
+        assert_eq!(
+            config.headers,
+            vec![
+                ("OpenAI-Organization".to_string(), "org-123".to_string()),
+                ("traceparent".to_string(), "00-abc-def-01".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_api_key_from_file_is_trimmed() {
+        let path = env::temp_dir().join(format!("rsai-api-key-{}", std::process::id()));
+        std::fs::write(&path, "  sk-from-file\n").unwrap();
+
+        let builder = llm::with(Provider::OpenAI)
+            .api_key(ApiKey::FromFile(path.clone()))
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(builder.fields.api_key.as_deref(), Some("sk-from-file"));
+    }
+
+    #[test]
+    fn test_api_key_from_missing_file_errors() {
+        let result = llm::with(Provider::OpenAI)
+            .api_key(ApiKey::FromFile(PathBuf::from("/nonexistent/rsai-api-key")));
+
+        match result {
+            Err(LlmError::Builder(message)) => {
+                assert!(message.contains("Failed to read API key file"))
+            }
+            Err(other) => panic!("expected builder error, got {other:?}"),
+            Ok(_) => panic!("expected builder error"),
+        }
+    }
+
     #[test]
     fn test_inspector_config_is_cloneable() {
         let config = InspectorConfig {
@@ -635,4 +3098,420 @@ mod tests {
         assert!(cloned.request_inspector.is_some());
         assert!(cloned.response_inspector.is_some());
     }
+
+    #[tokio::test]
+    async fn test_examples_are_sent_after_system_messages() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":3}"));
+
+        let result = mock_openai(transport.clone())
+            .messages(vec![
+                Message::new(ChatRole::System, "Add the numbers"),
+                Message::new(ChatRole::User, "1 and 2"),
+            ])
+            .examples(vec![("2 and 3", serde_json::json!({ "sum": 5 }))])
+            .unwrap()
+            .complete::<Sum>()
+            .await
+            .unwrap();
+        assert_eq!(result.content.sum, 3);
+
+        let bodies = transport.bodies();
+        let input: Vec<(&str, &str)> = bodies[0]["input"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                (
+                    item["role"].as_str().unwrap(),
+                    item["content"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            input,
+            vec![
+                ("system", "Add the numbers"),
+                ("user", "2 and 3"),
+                ("assistant", "{\"sum\":5}"),
+                ("user", "1 and 2"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_best_picks_highest_score() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":3}"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+                .candidates(3)
+        };
+
+        let all = builder().complete_all::<Sum>().await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(transport.bodies().len(), 3);
+
+        let best = builder()
+            .complete_best::<Sum, _>(|candidate| candidate.content.sum)
+            .await
+            .unwrap();
+        assert_eq!(best.content.sum, 3);
+
+        let err = builder()
+            .candidates(0)
+            .complete_all::<Sum>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Builder(_)));
+    }
+
+    #[tokio::test]
+    async fn test_classify_ranks_sampled_labels() {
+        #[derive(Debug, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+        enum Sentiment {
+            Positive,
+            Negative,
+        }
+
+        let answer = |label: &str| {
+            (
+                200,
+                responses_answer(&serde_json::json!({ "value": label }).to_string()),
+            )
+        };
+        let transport = ScriptedTransport::new(vec![
+            answer("Negative"),
+            answer("Positive"),
+            answer("Negative"),
+        ]);
+
+        let classification = mock_openai(transport.clone())
+            .candidates(3)
+            .classify::<Sentiment>("Late, but tasty")
+            .await
+            .unwrap();
+
+        assert_eq!(classification.label, Sentiment::Negative);
+        assert!((classification.confidence.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(classification.alternatives.len(), 1);
+        assert_eq!(classification.alternatives[0].0, Sentiment::Positive);
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(bodies[0]["input"][0]["content"], "Late, but tasty");
+        assert_eq!(
+            bodies[0]["text"]["format"]["schema"]["properties"]["value"]["enum"],
+            serde_json::json!(["Positive", "Negative"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abort_signal_cancels_in_flight_request() {
+        struct HangingTransport;
+
+        #[async_trait::async_trait]
+        impl Transport for HangingTransport {
+            async fn send(
+                &self,
+                _request: TransportRequest,
+            ) -> Result<TransportResponse, LlmError> {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                unreachable!("the request should have been aborted")
+            }
+        }
+
+        let token = CancellationToken::new();
+        let stop = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            stop.cancel();
+        });
+
+        let started = std::time::Instant::now();
+        let err = mock_openai(Arc::new(HangingTransport))
+            .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+            .abort_signal(token)
+            .complete::<Sum>()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, LlmError::Aborted));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_continue_from_sends_previous_response_id() {
+        let mut answer = responses_answer("Ada");
+        answer["id"] = "resp_2".into();
+        let transport = ScriptedTransport::answering(answer);
+        let builder = |provider| {
+            llm::with(provider)
+                .api_key(ApiKey::Custom("test-key".to_string()))
+                .unwrap()
+                .model("mock-model")
+                .messages(vec![Message::new(ChatRole::User, "What is my name?")])
+                .continue_from("resp_1")
+                .transport(transport.clone())
+        };
+
+        let err = builder(Provider::Gemini)
+            .complete::<TextResponse>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Builder(_)));
+
+        let response = builder(Provider::OpenAI)
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(response.metadata.id, "resp_2");
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies[0]["previous_response_id"], "resp_1");
+        assert_eq!(bodies[0]["input"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_store_and_metadata_are_sent() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":3}"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        };
+
+        builder()
+            .store(false)
+            .metadata([("feature", "calculator"), ("experiment", "b")])
+            .complete::<Sum>()
+            .await
+            .unwrap();
+
+        let err = builder()
+            .metadata((0..17).map(|i| (i.to_string(), "x")))
+            .complete::<Sum>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Builder(_)));
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 1);
+        assert_eq!(bodies[0]["store"], false);
+        assert_eq!(
+            bodies[0]["metadata"],
+            serde_json::json!({ "experiment": "b", "feature": "calculator" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assistant_prefill_is_prepended_to_the_answer() {
+        let transport = ScriptedTransport::answering(serde_json::json!({
+            "id": "chatcmpl-1",
+            "model": "accounts/fireworks/models/llama-v3p3-70b-instruct",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": " \"sum\": 3}" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 4, "total_tokens": 9 }
+        }));
+
+        let response = llm::with(Provider::Fireworks)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("accounts/fireworks/models/llama-v3p3-70b-instruct")
+            .messages(vec![
+                Message::new(ChatRole::User, "Add 1 and 2 and answer in JSON"),
+                Message::new(ChatRole::Assistant, "{"),
+            ])
+            .transport(transport.clone())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "{ \"sum\": 3}");
+        assert_eq!(
+            transport.bodies()[0]["messages"][1],
+            serde_json::json!({ "role": "assistant", "content": "{" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_raw_sends_body_unchanged() {
+        let transport = ScriptedTransport::answering(responses_answer("Hello"));
+        let body = serde_json::json!({
+            "model": "mock-model",
+            "input": "Hi",
+            "prompt_cache_key": "greeting",
+            "unmodeled": { "nested": [1, 2] }
+        });
+
+        let client = mock_openai(transport.clone()).client();
+        let response = client.complete_raw(body.clone()).await.unwrap();
+
+        assert_eq!(response.provider, Provider::OpenAI);
+        assert!(matches!(response.content, ResponseContent::Text(ref text) if text == "Hello"));
+        assert_eq!(transport.bodies(), vec![body]);
+    }
+
+    #[tokio::test]
+    async fn test_complete_raw_surfaces_typed_api_errors() {
+        let transport = ScriptedTransport::new(vec![(
+            400,
+            serde_json::json!({
+                "error": { "code": "unknown_parameter", "message": "Unknown parameter" }
+            }),
+        )]);
+
+        let err = mock_openai(transport)
+            .complete_raw(
+                serde_json::json!({ "model": "mock-model", "input": "Hi", "bogus": true }),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            LlmError::Api {
+                status_code: Some(400),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_reuses_configuration_across_requests() {
+        let transport = ScriptedTransport::answering(responses_answer("Answer"));
+        let client = mock_openai(transport.clone()).max_tokens(100).client();
+        let user = |content: &str| vec![Message::new(ChatRole::User, content.to_string())];
+
+        for question in ["First question", "Second question"] {
+            let answer = client
+                .complete::<TextResponse>(user(question))
+                .await
+                .unwrap();
+            assert_eq!(answer.text, "Answer");
+        }
+        client
+            .builder()
+            .messages(user("Third question"))
+            .max_tokens(50)
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        client
+            .with_tools(sum_toolset())
+            .complete::<TextResponse>(user("Add 2 and 3"))
+            .await
+            .unwrap();
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 4);
+        assert_eq!(bodies[0]["input"][0]["content"], "First question");
+        assert_eq!(bodies[1]["input"][0]["content"], "Second question");
+        assert_eq!(bodies[1]["model"], "mock-model");
+        assert_eq!(bodies[1]["max_output_tokens"], 100);
+        assert_eq!(bodies[2]["max_output_tokens"], 50);
+        assert!(bodies[2].get("tools").is_none());
+        assert_eq!(bodies[3]["tools"][0]["name"], "calculate_sum");
+    }
+
+    #[tokio::test]
+    async fn test_model_fallbacks_retry_with_next_model() {
+        /// Answers like a provider serving only `small-model`.
+        #[derive(Default)]
+        struct ModelTransport {
+            models: std::sync::Mutex<Vec<String>>,
+        }
+
+        #[async_trait::async_trait]
+        impl Transport for ModelTransport {
+            async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+                let model = request.body["model"].as_str().unwrap().to_string();
+                self.models.lock().unwrap().push(model.clone());
+                let (status, body) = if model == "small-model" {
+                    let mut answer = responses_answer("Hello");
+                    answer["model"] = model.into();
+                    (200, answer)
+                } else {
+                    let error = serde_json::json!({
+                        "error": { "code": "model_not_found", "message": "The model does not exist" }
+                    });
+                    (404, error)
+                };
+                Ok(TransportResponse {
+                    status,
+                    body: body.to_string(),
+                })
+            }
+        }
+
+        let transport = Arc::new(ModelTransport::default());
+        let request = |fallbacks: &[&str]| {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test-key".to_string()))
+                .unwrap()
+                .model("large-model")
+                .model_fallbacks(fallbacks)
+                .messages(vec![Message::new(ChatRole::User, "Hi")])
+                .transport(transport.clone())
+                .complete::<TextResponse>()
+        };
+
+        let response = request(&["large-model", "medium-model", "small-model"])
+            .await
+            .unwrap();
+        assert_eq!(response.text, "Hello");
+        assert_eq!(response.metadata.model, "small-model");
+        assert_eq!(
+            *transport.models.lock().unwrap(),
+            vec!["large-model", "medium-model", "small-model"]
+        );
+
+        let err = request(&["medium-model"]).await.unwrap_err();
+        assert!(matches!(
+            err,
+            LlmError::Api {
+                status_code: Some(404),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_survive_a_later_http_client_config() {
+        struct Token;
+
+        #[async_trait::async_trait]
+        impl CredentialsProvider for Token {
+            async fn auth_headers(&self) -> Result<Vec<(String, String)>, LlmError> {
+                Ok(vec![(
+                    "Authorization".to_string(),
+                    "Bearer short-lived".to_string(),
+                )])
+            }
+        }
+
+        let transport = ScriptedTransport::answering(responses_answer("Hello"));
+        llm::with(Provider::OpenAI)
+            .credentials(Token)
+            .unwrap()
+            .model("mock-model")
+            .messages(vec![Message::new(ChatRole::User, "Hi")])
+            .http_client_config(HttpClientConfig {
+                transport: Some(transport.clone()),
+                ..Default::default()
+            })
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        let authorization: Vec<_> = transport.requests()[0]
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("authorization"))
+            .map(|(_, value)| value.clone())
+            .collect();
+        assert_eq!(authorization, ["Bearer short-lived"]);
+    }
 }
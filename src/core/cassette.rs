@@ -0,0 +1,435 @@
+//! Record/replay transport for deterministic integration tests.
+//!
+//! A [`Cassette`] records real request/response pairs to a JSON file on the first run and
+//! replays them on later runs, so provider integration tests can run in CI without API keys.
+//!
+//! # Example
+//! ```no_run
+//! # use rsai::{llm, ApiKey, Cassette, ChatRole, Message, Provider, TextResponse};
+//! # use std::sync::Arc;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cassette = Cassette::new("tests/cassettes/hello.json")?;
+//!
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Custom("replayed".into()))?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Hello")])
+//!     .transport(Arc::new(cassette))
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::error::LlmError;
+use super::redaction::{REDACTED, SENSITIVE_HEADERS};
+use super::transport::{
+    HttpMethod, ReqwestTransport, Transport, TransportRequest, TransportResponse,
+};
+
+/// How a [`Cassette`] treats its file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Replay if the cassette file exists, otherwise record a new one.
+    Auto,
+    /// Always send real requests and overwrite the cassette file.
+    Record,
+    /// Only replay; unmatched requests fail instead of hitting the network.
+    Replay,
+}
+
+/// Which parts of a request must be equal for a recorded interaction to match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchOn {
+    /// Method, URL and JSON body must all match.
+    UrlAndBody,
+    /// Only method and URL must match; interactions are replayed in recorded order.
+    Url,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct RecordedRequest {
+    #[serde(default)]
+    method: HttpMethod,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct RecordedResponse {
+    status: u16,
+    body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Marks interactions that were already replayed, so repeated identical requests
+    /// (e.g. iterations of a tool loop) are served in recorded order.
+    used: Vec<bool>,
+}
+
+/// A [`Transport`] that records real traffic to a file and replays it later.
+pub struct Cassette {
+    path: PathBuf,
+    recording: bool,
+    match_on: MatchOn,
+    redacted_headers: Vec<String>,
+    secrets: Vec<String>,
+    inner: Arc<dyn Transport>,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// Open a cassette in [`CassetteMode::Auto`].
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, LlmError> {
+        Self::with_mode(path, CassetteMode::Auto)
+    }
+
+    /// Open a cassette with an explicit mode.
+    pub fn with_mode(path: impl AsRef<Path>, mode: CassetteMode) -> Result<Self, LlmError> {
+        let path = path.as_ref().to_path_buf();
+        let recording = match mode {
+            CassetteMode::Record => true,
+            CassetteMode::Replay => false,
+            CassetteMode::Auto => !path.exists(),
+        };
+
+        let interactions = if recording {
+            Vec::new()
+        } else {
+            load_interactions(&path)?
+        };
+
+        Ok(Self {
+            path,
+            recording,
+            match_on: MatchOn::UrlAndBody,
+            redacted_headers: SENSITIVE_HEADERS.iter().map(|h| h.to_string()).collect(),
+            secrets: Vec::new(),
+            inner: Arc::new(ReqwestTransport::default()),
+            state: Mutex::new(CassetteState {
+                used: vec![false; interactions.len()],
+                interactions,
+            }),
+        })
+    }
+
+    /// Set how requests are matched against recorded interactions.
+    pub fn match_on(mut self, match_on: MatchOn) -> Self {
+        self.match_on = match_on;
+        self
+    }
+
+    /// Redact an additional header (case-insensitive) in recorded requests.
+    pub fn redact_header(mut self, name: &str) -> Self {
+        self.redacted_headers.push(name.to_lowercase());
+        self
+    }
+
+    /// Replace every occurrence of `secret` in recorded URLs and bodies with `[REDACTED]`.
+    ///
+    /// Outgoing requests are redacted the same way before matching, so replay still works.
+    pub fn redact_secret(mut self, secret: impl Into<String>) -> Self {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            self.secrets.push(secret);
+        }
+        self
+    }
+
+    /// Use a custom transport for recording instead of a default `reqwest` client.
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.inner = transport;
+        self
+    }
+
+    /// Whether this cassette sends real requests.
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    fn redact_text(&self, text: &str) -> String {
+        self.secrets.iter().fold(text.to_string(), |acc, secret| {
+            acc.replace(secret, REDACTED)
+        })
+    }
+
+    fn redact_value(&self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::String(s) => serde_json::Value::String(self.redact_text(s)),
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            serde_json::Value::Object(map) => serde_json::Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.redact_value(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn redact_request(&self, request: &TransportRequest) -> RecordedRequest {
+        RecordedRequest {
+            method: request.method,
+            url: self.redact_text(&request.url),
+            headers: request
+                .headers
+                .iter()
+                .map(|(name, value)| {
+                    if self.redacted_headers.contains(&name.to_lowercase()) {
+                        (name.clone(), REDACTED.to_string())
+                    } else {
+                        (name.clone(), self.redact_text(value))
+                    }
+                })
+                .collect(),
+            body: self.redact_value(&request.body),
+        }
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, request: &RecordedRequest) -> bool {
+        let same_target = recorded.method == request.method && recorded.url == request.url;
+        match self.match_on {
+            MatchOn::UrlAndBody => same_target && recorded.body == request.body,
+            MatchOn::Url => same_target,
+        }
+    }
+
+    fn lock_state(&self) -> Result<std::sync::MutexGuard<'_, CassetteState>, LlmError> {
+        self.state
+            .lock()
+            .map_err(|_| LlmError::ProviderConfiguration("Cassette lock poisoned".to_string()))
+    }
+
+    fn replay(&self, request: &TransportRequest) -> Result<TransportResponse, LlmError> {
+        let redacted = self.redact_request(request);
+        let mut state = self.lock_state()?;
+
+        let index = state
+            .interactions
+            .iter()
+            .enumerate()
+            .position(|(idx, interaction)| {
+                !state.used[idx] && self.matches(&interaction.request, &redacted)
+            })
+            .ok_or_else(|| {
+                LlmError::ProviderConfiguration(format!(
+                    "No recorded interaction in cassette '{}' matches request to {}",
+                    self.path.display(),
+                    redacted.url
+                ))
+            })?;
+
+        state.used[index] = true;
+        let response = &state.interactions[index].response;
+        Ok(TransportResponse {
+            status: response.status,
+            body: response.body.clone(),
+        })
+    }
+
+    async fn record(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        let recorded_request = self.redact_request(&request);
+        let response = self.inner.send(request).await?;
+
+        let mut state = self.lock_state()?;
+        state.interactions.push(Interaction {
+            request: recorded_request,
+            response: RecordedResponse {
+                status: response.status,
+                body: self.redact_text(&response.body),
+            },
+        });
+        state.used.push(true);
+        save_interactions(&self.path, &state.interactions)?;
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Transport for Cassette {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        if self.recording {
+            self.record(request).await
+        } else {
+            self.replay(&request)
+        }
+    }
+}
+
+fn load_interactions(path: &Path) -> Result<Vec<Interaction>, LlmError> {
+    read_json::<CassetteFile>(path, "cassette").map(|file| file.interactions)
+}
+
+fn save_interactions(path: &Path, interactions: &[Interaction]) -> Result<(), LlmError> {
+    let file = CassetteFile {
+        interactions: interactions.to_vec(),
+    };
+    write_json(path, &file, "cassette")
+}
+
+/// Read the JSON file at `path`, called `what` in errors.
+pub(super) fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T, LlmError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        LlmError::ProviderConfiguration(format!("Failed to read {what} '{}': {e}", path.display()))
+    })?;
+
+    serde_json::from_str(&contents).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse {what} '{}'", path.display()),
+        source: Box::new(e),
+    })
+}
+
+/// Write `value` to `path` as pretty-printed JSON, creating missing directories.
+pub(super) fn write_json<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<(), LlmError> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            LlmError::ProviderConfiguration(format!(
+                "Failed to create {what} directory '{}': {e}",
+                parent.display()
+            ))
+        })?;
+    }
+
+    let contents = serde_json::to_string_pretty(value).map_err(|e| LlmError::Parse {
+        message: format!("Failed to serialize {what}"),
+        source: Box::new(e),
+    })?;
+
+    std::fs::write(path, contents).map_err(|e| {
+        LlmError::ProviderConfiguration(format!("Failed to write {what} '{}': {e}", path.display()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingTransport {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Transport for CountingTransport {
+        async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(TransportResponse {
+                status: 200,
+                body: serde_json::json!({ "call": call, "echo": request.body }).to_string(),
+            })
+        }
+    }
+
+    fn temp_cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rsai-cassette-{}-{name}.json", std::process::id()))
+    }
+
+    fn sample_request(prompt: &str) -> TransportRequest {
+        TransportRequest {
+            method: HttpMethod::Post,
+            url: "https://api.example.com/v1/responses".to_string(),
+            headers: vec![
+                ("Authorization".to_string(), "Bearer sk-secret".to_string()),
+                ("X-Title".to_string(), "rsai".to_string()),
+            ],
+            body: serde_json::json!({ "input": prompt, "user": "sk-secret" }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = temp_cassette_path("roundtrip");
+        let _ = std::fs::remove_file(&path);
+
+        let inner = Arc::new(CountingTransport {
+            calls: AtomicUsize::new(0),
+        });
+        let recorder = Cassette::with_mode(&path, CassetteMode::Record)
+            .unwrap()
+            .redact_secret("sk-secret")
+            .with_transport(inner.clone());
+
+        let recorded = recorder.send(sample_request("hello")).await.unwrap();
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("sk-secret"));
+        assert!(contents.contains(REDACTED));
+
+        let player = Cassette::new(&path).unwrap().redact_secret("sk-secret");
+        assert!(!player.is_recording());
+        let replayed = player.send(sample_request("hello")).await.unwrap();
+        assert_eq!(replayed.status, recorded.status);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_replay_unmatched_request_errors() {
+        let path = temp_cassette_path("unmatched");
+        let _ = std::fs::remove_file(&path);
+
+        let recorder = Cassette::with_mode(&path, CassetteMode::Record)
+            .unwrap()
+            .with_transport(Arc::new(CountingTransport {
+                calls: AtomicUsize::new(0),
+            }));
+        recorder.send(sample_request("hello")).await.unwrap();
+
+        let player = Cassette::with_mode(&path, CassetteMode::Replay).unwrap();
+        let result = player.send(sample_request("goodbye")).await;
+        assert!(matches!(result, Err(LlmError::ProviderConfiguration(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_replay_serves_identical_requests_in_order() {
+        let path = temp_cassette_path("ordered");
+        let _ = std::fs::remove_file(&path);
+
+        let recorder = Cassette::with_mode(&path, CassetteMode::Record)
+            .unwrap()
+            .with_transport(Arc::new(CountingTransport {
+                calls: AtomicUsize::new(0),
+            }));
+        recorder.send(sample_request("same")).await.unwrap();
+        recorder.send(sample_request("same")).await.unwrap();
+
+        let player = Cassette::with_mode(&path, CassetteMode::Replay)
+            .unwrap()
+            .match_on(MatchOn::Url);
+        let first = player.send(sample_request("same")).await.unwrap();
+        let second = player.send(sample_request("different")).await.unwrap();
+        assert!(first.body.contains("\"call\":0"));
+        assert!(second.body.contains("\"call\":1"));
+        assert!(player.send(sample_request("same")).await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
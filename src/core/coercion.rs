@@ -0,0 +1,311 @@
+//! Repairs of tool arguments that don't match the tool's parameter schema.
+
+use serde_json::{Number, Value};
+
+/// Convert scalar `arguments` that don't have the type their JSON `schema` declares into it
+/// when the conversion is lossless, e.g. `"42"` into `42` for an integer, `"true"` into `true`
+/// for a boolean and `7` into `"7"` for a string. Objects and arrays are coerced field by field
+/// and item by item; values that can't be converted are left for deserialization to reject.
+///
+/// Tools generated with `#[tool]` coerce their arguments before deserializing them. Other
+/// tools get the same with [`ToolRegistry::with_argument_coercion`](crate::ToolRegistry::with_argument_coercion).
+///
+/// ```
+/// use rsai::coerce_arguments;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": { "count": { "type": "integer" }, "exact": { "type": "boolean" } }
+/// });
+/// let arguments = coerce_arguments(&schema, json!({ "count": "42", "exact": "True" }));
+/// assert_eq!(arguments, json!({ "count": 42, "exact": true }));
+/// ```
+pub fn coerce_arguments(schema: &Value, arguments: Value) -> Value {
+    coerce(schema, arguments)
+}
+
+/// Remove the fields of `arguments` that `schema` doesn't declare where it sets
+/// `"additionalProperties": false`, returning their JSON pointers.
+pub(crate) fn remove_unknown_arguments(schema: &Value, arguments: &mut Value) -> Vec<String> {
+    let mut removed = Vec::new();
+    remove_unknown(schema, arguments, "", &mut removed);
+    removed
+}
+
+fn remove_unknown(schema: &Value, value: &mut Value, path: &str, removed: &mut Vec<String>) {
+    if let Some(variants) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        // Only unambiguous: the one variant that takes a value of this type
+        let mut matching = variants
+            .iter()
+            .filter(|variant| types(variant).iter().any(|ty| has_type(ty, value)));
+        if let (Some(variant), None) = (matching.next(), matching.next()) {
+            remove_unknown(variant, value, path, removed);
+        }
+        return;
+    }
+
+    match value {
+        Value::Object(fields) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                fields.retain(|name, _| {
+                    let known = properties.is_some_and(|p| p.contains_key(name));
+                    if !known {
+                        removed.push(format!("{path}/{}", escape(name)));
+                    }
+                    known
+                });
+            }
+            for (name, field) in fields.iter_mut() {
+                if let Some(field_schema) = properties.and_then(|p| p.get(name)) {
+                    remove_unknown(
+                        field_schema,
+                        field,
+                        &format!("{path}/{}", escape(name)),
+                        removed,
+                    );
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter_mut().enumerate() {
+                    remove_unknown(item_schema, item, &format!("{path}/{index}"), removed);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Escape a field name for a JSON pointer.
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+fn coerce(schema: &Value, value: Value) -> Value {
+    if let Some(variants) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        return coerce_variants(variants, value);
+    }
+
+    let value = match value {
+        Value::Object(mut fields) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema
+                .get("additionalProperties")
+                .filter(|additional| additional.is_object());
+            for (name, field) in fields.iter_mut() {
+                if let Some(field_schema) = properties.and_then(|p| p.get(name)).or(additional) {
+                    *field = coerce(field_schema, field.take());
+                }
+            }
+            Value::Object(fields)
+        }
+        Value::Array(items) => match schema.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| coerce(item_schema, item))
+                    .collect(),
+            ),
+            None => Value::Array(items),
+        },
+        scalar => scalar,
+    };
+
+    let types = types(schema);
+    if types.is_empty() || types.iter().any(|ty| has_type(ty, &value)) {
+        return value;
+    }
+    types
+        .iter()
+        .find_map(|ty| convert(ty, &value))
+        .unwrap_or(value)
+}
+
+/// Coerce into the first variant `value` already matches, or else the first it converts to.
+fn coerce_variants(variants: &[Value], value: Value) -> Value {
+    if let Some(variant) = variants
+        .iter()
+        .find(|variant| types(variant).iter().any(|ty| has_type(ty, &value)))
+    {
+        return coerce(variant, value);
+    }
+    variants
+        .iter()
+        .map(|variant| coerce(variant, value.clone()))
+        .find(|coerced| coerced != &value)
+        .unwrap_or(value)
+}
+
+/// The types a schema allows, from `"type": "integer"` or `"type": ["integer", "null"]`.
+fn types(schema: &Value) -> Vec<&str> {
+    match schema.get("type") {
+        Some(Value::String(ty)) => vec![ty.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn has_type(ty: &str, value: &Value) -> bool {
+    match ty {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => false,
+    }
+}
+
+fn convert(ty: &str, value: &Value) -> Option<Value> {
+    match (ty, value) {
+        ("integer", Value::String(text)) => {
+            let text = text.trim();
+            text.parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| text.parse::<u64>().map(Value::from))
+                .ok()
+                .or_else(|| whole_number(text.parse::<f64>().ok()?))
+        }
+        ("integer", Value::Number(number)) => whole_number(number.as_f64()?),
+        ("number", Value::String(text)) => {
+            let text = text.trim();
+            text.parse::<i64>()
+                .map(Value::from)
+                .ok()
+                .or_else(|| Number::from_f64(text.parse().ok()?).map(Value::Number))
+        }
+        ("boolean", Value::String(text)) => match text.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("string", Value::Number(number)) => Some(Value::String(number.to_string())),
+        ("string", Value::Bool(flag)) => Some(Value::String(flag.to_string())),
+        _ => None,
+    }
+}
+
+/// `number` as an integer if it has no fractional part and fits an `i64`.
+fn whole_number(number: f64) -> Option<Value> {
+    (number.fract() == 0.0 && number.abs() < i64::MAX as f64).then(|| Value::from(number as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_scalars_are_coerced_to_their_declared_types() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer" },
+                "ratio": { "type": "number" },
+                "exact": { "type": "boolean" },
+                "code": { "type": "string" },
+                "limit": { "type": ["integer", "null"] },
+                "ids": { "type": "array", "items": { "type": "integer" } },
+                "page": { "anyOf": [{ "type": "integer" }, { "type": "null" }] },
+                "nested": {
+                    "type": "object",
+                    "properties": { "flag": { "type": "boolean" } }
+                }
+            }
+        });
+        let arguments = json!({
+            "count": " 42 ",
+            "ratio": "0.5",
+            "exact": "FALSE",
+            "code": 7,
+            "limit": 3.0,
+            "ids": ["1", 2],
+            "page": "4",
+            "nested": { "flag": "true" },
+            "extra": "1"
+        });
+        assert_eq!(
+            coerce_arguments(&schema, arguments),
+            json!({
+                "count": 42,
+                "ratio": 0.5,
+                "exact": false,
+                "code": "7",
+                "limit": 3,
+                "ids": [1, 2],
+                "page": 4,
+                "nested": { "flag": true },
+                "extra": "1"
+            })
+        );
+    }
+
+    #[test]
+    fn test_unknown_arguments_are_removed_where_forbidden() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string" },
+                "stops": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "name": { "type": "string" } },
+                        "additionalProperties": false
+                    }
+                },
+                "extra": { "type": "object" }
+            },
+            "additionalProperties": false
+        });
+        let mut arguments = json!({
+            "city": "Lisbon",
+            "units": "metric",
+            "stops": [{ "name": "Belém", "order": 1 }],
+            "extra": { "anything": true }
+        });
+        assert_eq!(
+            remove_unknown_arguments(&schema, &mut arguments),
+            vec!["/units", "/stops/0/order"]
+        );
+        assert_eq!(
+            arguments,
+            json!({
+                "city": "Lisbon",
+                "stops": [{ "name": "Belém" }],
+                "extra": { "anything": true }
+            })
+        );
+    }
+
+    #[test]
+    fn test_lossy_conversions_are_left_alone() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer" },
+                "exact": { "type": "boolean" },
+                "limit": { "type": ["integer", "null"] }
+            }
+        });
+        let arguments = json!({ "count": "2.5", "exact": "yes", "limit": null });
+        assert_eq!(coerce_arguments(&schema, arguments.clone()), arguments);
+        assert_eq!(
+            coerce_arguments(&schema, json!({ "count": 2.5 })),
+            json!({ "count": 2.5 })
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Task-local collectors.
+//!
+//! Usage reports, timings and tool stats are gathered while a future runs without being
+//! passed through the providers: the future runs in the scope of a task-local collector and
+//! the code making requests or calling tools adds to it, if one is running.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::task::LocalKey;
+
+/// The value a collector gathers into, shared with the future it runs.
+pub(crate) type Collected<T> = Arc<Mutex<T>>;
+
+/// Run `future` in a scope of `key` and return its output with what was collected.
+///
+/// Wrappers return this future instead of awaiting it in an `async fn`, which would keep a
+/// second copy of `future` in its state.
+pub(crate) async fn collect<T, F>(key: &'static LocalKey<Collected<T>>, future: F) -> (F::Output, T)
+where
+    T: Default + 'static,
+    F: Future,
+{
+    let collected = Collected::default();
+    // Boxed: agent runs are deep futures and the scope would otherwise copy them on the stack
+    let output = key.scope(collected.clone(), Box::pin(future)).await;
+    let collected = std::mem::take(&mut *collected.lock().unwrap_or_else(|e| e.into_inner()));
+    (output, collected)
+}
+
+/// Whether the current task runs in a scope of `key`.
+pub(crate) fn collecting<T: 'static>(key: &'static LocalKey<Collected<T>>) -> bool {
+    key.try_with(|_| ()).is_ok()
+}
+
+/// Apply `update` to the value of `key`, if the current task runs in a scope of it.
+pub(crate) fn add<T: 'static>(key: &'static LocalKey<Collected<T>>, update: impl FnOnce(&mut T)) {
+    let _ = key.try_with(|collected| {
+        if let Ok(mut collected) = collected.lock() {
+            update(&mut collected);
+        }
+    });
+}
@@ -0,0 +1,196 @@
+//! Chat history with a sliding window.
+//!
+//! A [`Conversation`] collects the messages of a chat and keeps only the most recent ones once
+//! it grows past its window, so long chats stay within the model's context. Pinned messages are
+//! never dropped: system prompts are pinned when they are added, and
+//! [`push_pinned`](Conversation::push_pinned) or [`pin`](Conversation::pin) keep key facts
+//! around. The messages a push drops are returned, e.g. to summarize them into a pinned message.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::conversation::Conversation;
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let mut conversation = Conversation::new().with_window(20);
+//! conversation.push(Message::new(ChatRole::System, "You are a travel agent."));
+//! conversation.push_pinned(Message::new(ChatRole::User, "I am vegetarian and afraid of flying."));
+//!
+//! conversation.push(Message::new(ChatRole::User, "Plan a weekend in Lisbon."));
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(conversation.messages())
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! conversation.push(Message::new(ChatRole::Assistant, reply.text));
+//! # Ok(())
+//! # }
+//! ```
+
+use super::types::{ChatRole, Message};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    message: Message,
+    pinned: bool,
+}
+
+/// Chat history that drops its oldest messages beyond a window, except pinned ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    entries: Vec<Entry>,
+    /// Unpinned messages kept, `None` for all
+    window: Option<usize>,
+}
+
+impl Conversation {
+    /// A conversation that keeps every message until [`with_window`](Self::with_window) is set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `max_messages` messages that are not pinned. Pinned messages don't count
+    /// towards the window.
+    pub fn with_window(mut self, max_messages: usize) -> Self {
+        self.window = Some(max_messages);
+        self.truncate();
+        self
+    }
+
+    /// Add a message and return the messages that fell out of the window, oldest first.
+    /// System messages are pinned.
+    pub fn push(&mut self, message: Message) -> Vec<Message> {
+        let pinned = message.role == ChatRole::System;
+        self.entries.push(Entry { message, pinned });
+        self.truncate()
+    }
+
+    /// Add a message that is never dropped and return the messages that fell out of the
+    /// window, oldest first.
+    pub fn push_pinned(&mut self, message: Message) -> Vec<Message> {
+        self.entries.push(Entry {
+            message,
+            pinned: true,
+        });
+        self.truncate()
+    }
+
+    /// Keep the message at `index` regardless of the window. Returns `false` if there is no
+    /// such message.
+    pub fn pin(&mut self, index: usize) -> bool {
+        self.set_pinned(index, true)
+    }
+
+    /// Let the message at `index` be dropped again once it falls out of the window, which may
+    /// drop it right away. Returns the dropped messages, oldest first.
+    pub fn unpin(&mut self, index: usize) -> Vec<Message> {
+        self.set_pinned(index, false);
+        self.truncate()
+    }
+
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.entries.get(index).is_some_and(|entry| entry.pinned)
+    }
+
+    /// The retained messages in the order they were added, to pass to
+    /// [`LlmBuilder::messages`](crate::LlmBuilder::messages).
+    pub fn messages(&self) -> Vec<Message> {
+        self.entries
+            .iter()
+            .map(|entry| entry.message.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn set_pinned(&mut self, index: usize, pinned: bool) -> bool {
+        match self.entries.get_mut(index) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop the oldest unpinned messages beyond the window.
+    fn truncate(&mut self) -> Vec<Message> {
+        let Some(window) = self.window else {
+            return Vec::new();
+        };
+        let unpinned = self.entries.iter().filter(|entry| !entry.pinned).count();
+        let mut excess = unpinned.saturating_sub(window);
+        if excess == 0 {
+            return Vec::new();
+        }
+
+        let mut dropped = Vec::with_capacity(excess);
+        let mut kept = Vec::with_capacity(self.entries.len() - excess);
+        for entry in self.entries.drain(..) {
+            if excess > 0 && !entry.pinned {
+                excess -= 1;
+                dropped.push(entry.message);
+            } else {
+                kept.push(entry);
+            }
+        }
+        self.entries = kept;
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: ChatRole, content: &str) -> Message {
+        Message::new(role, content.to_string())
+    }
+
+    fn contents(conversation: &Conversation) -> Vec<String> {
+        conversation
+            .messages()
+            .into_iter()
+            .map(|message| message.content)
+            .collect()
+    }
+
+    #[test]
+    fn test_window_keeps_pinned_and_recent_messages() {
+        let mut conversation = Conversation::new().with_window(2);
+        conversation.push(message(ChatRole::System, "system"));
+        conversation.push(message(ChatRole::User, "u1"));
+        conversation.push_pinned(message(ChatRole::User, "fact"));
+        conversation.push(message(ChatRole::Assistant, "a1"));
+        let dropped = conversation.push(message(ChatRole::User, "u2"));
+
+        assert_eq!(dropped, vec![message(ChatRole::User, "u1")]);
+        assert_eq!(contents(&conversation), ["system", "fact", "a1", "u2"]);
+        assert!(conversation.is_pinned(0));
+        assert!(!conversation.is_pinned(2));
+    }
+
+    #[test]
+    fn test_unpinning_drops_messages_outside_the_window() {
+        let mut conversation = Conversation::new();
+        conversation.push(message(ChatRole::User, "u1"));
+        conversation.push(message(ChatRole::User, "u2"));
+        conversation.push(message(ChatRole::User, "u3"));
+        assert!(conversation.pin(0));
+        assert!(!conversation.pin(3));
+
+        let mut conversation = conversation.with_window(1);
+        assert_eq!(contents(&conversation), ["u1", "u3"]);
+
+        let dropped = conversation.unpin(0);
+        assert_eq!(dropped, vec![message(ChatRole::User, "u1")]);
+        assert_eq!(contents(&conversation), ["u3"]);
+    }
+}
@@ -0,0 +1,184 @@
+//! Authentication with credentials that expire.
+//!
+//! API keys are sent as a static header. Credentials such as OAuth access tokens, STS session
+//! tokens or tokens issued by an enterprise gateway instead come from a
+//! [`CredentialsProvider`], which the HTTP client asks for headers before every attempt. Its
+//! headers replace provider headers of the same name (case-insensitive), e.g. `Authorization`.
+//!
+//! [`RefreshingBearer`] covers the common case of a bearer token that is fetched again
+//! shortly before it expires. [`TokenCache`] is the building block for other schemes.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use rsai::credentials::{ExpiringToken, RefreshingBearer};
+//! use rsai::{ChatRole, LlmError, Message, Provider, TextResponse, llm};
+//!
+//! async fn fetch_gateway_token() -> Result<ExpiringToken, LlmError> {
+//!     // Exchange a client secret at the gateway's token endpoint
+//!     Ok(ExpiringToken::new("token", Duration::from_secs(3600)))
+//! }
+//!
+//! # async fn example() -> Result<(), LlmError> {
+//! let reply = llm::with(Provider::OpenAI)
+//!     .credentials(RefreshingBearer::new(fetch_gateway_token))?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Hello")])
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+
+use super::error::LlmError;
+
+/// Supplies the authentication headers of each HTTP request.
+#[async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    /// Headers authenticating the next attempt. Called before every attempt, including
+    /// retries, so implementations should cache credentials until they are about to expire.
+    async fn auth_headers(&self) -> Result<Vec<(String, String)>, LlmError>;
+}
+
+/// A token together with the time it stops being valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringToken {
+    pub value: String,
+    pub expires_at: SystemTime,
+}
+
+impl ExpiringToken {
+    /// A token valid for `expires_in` from now, e.g. from an OAuth `expires_in` field.
+    pub fn new(value: impl Into<String>, expires_in: Duration) -> Self {
+        Self {
+            value: value.into(),
+            expires_at: SystemTime::now() + expires_in,
+        }
+    }
+}
+
+/// Holds a token and fetches a new one when it expires within the refresh margin.
+#[derive(Debug)]
+pub struct TokenCache {
+    margin: Duration,
+    cached: tokio::sync::Mutex<Option<ExpiringToken>>,
+}
+
+impl Default for TokenCache {
+    /// Refreshes tokens five minutes before they expire.
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+impl TokenCache {
+    pub fn new(margin: Duration) -> Self {
+        Self {
+            margin,
+            cached: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// The cached token, or the one returned by `refresh` if there is none or it expires
+    /// within the margin. Concurrent callers wait for a single refresh.
+    pub async fn get_or_refresh<F, Fut>(&self, refresh: F) -> Result<String, LlmError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ExpiringToken, LlmError>>,
+    {
+        let mut cached = self.cached.lock().await;
+        if let Some(token) = cached.as_ref()
+            && token.expires_at > SystemTime::now() + self.margin
+        {
+            return Ok(token.value.clone());
+        }
+
+        let token = refresh().await?;
+        let value = token.value.clone();
+        *cached = Some(token);
+        Ok(value)
+    }
+
+    /// Drop the cached token, e.g. after the server rejected it.
+    pub async fn clear(&self) {
+        *self.cached.lock().await = None;
+    }
+}
+
+/// Sends `Authorization: Bearer <token>` with a token from `refresh`, fetched again shortly
+/// before it expires.
+pub struct RefreshingBearer<F> {
+    refresh: F,
+    cache: TokenCache,
+}
+
+impl<F, Fut> RefreshingBearer<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<ExpiringToken, LlmError>> + Send,
+{
+    pub fn new(refresh: F) -> Self {
+        Self {
+            refresh,
+            cache: TokenCache::default(),
+        }
+    }
+
+    /// Refresh tokens when they expire within `margin` instead of five minutes.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.cache = TokenCache::new(margin);
+        self
+    }
+}
+
+#[async_trait]
+impl<F, Fut> CredentialsProvider for RefreshingBearer<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<ExpiringToken, LlmError>> + Send,
+{
+    async fn auth_headers(&self) -> Result<Vec<(String, String)>, LlmError> {
+        let token = self.cache.get_or_refresh(&self.refresh).await?;
+        Ok(vec![(
+            "Authorization".to_string(),
+            format!("Bearer {token}"),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_refreshing_bearer_refreshes_only_near_expiry() {
+        let fetches = AtomicU32::new(0);
+        let bearer = RefreshingBearer::new(|| {
+            let n = fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                Ok(ExpiringToken::new(
+                    format!("t{n}"),
+                    Duration::from_secs(600),
+                ))
+            }
+        })
+        .with_refresh_margin(Duration::from_secs(60));
+
+        let first = bearer.auth_headers().await.unwrap();
+        let second = bearer.auth_headers().await.unwrap();
+        assert_eq!(first, vec![("Authorization".into(), "Bearer t1".into())]);
+        assert_eq!(first, second);
+
+        let bearer = bearer.with_refresh_margin(Duration::from_secs(900));
+        bearer.auth_headers().await.unwrap();
+        let refreshed = bearer.auth_headers().await.unwrap();
+        assert_eq!(refreshed[0].1, "Bearer t3");
+    }
+}
@@ -1,6 +1,7 @@
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum LlmError {
     #[error("LLM-Builder error: {0}")]
     Builder(String),
@@ -53,9 +54,45 @@ pub enum LlmError {
     #[error("Tool call iteration limit exceeded: {limit} iterations")]
     ToolCallIterationLimit { limit: u32 },
 
+    #[error("Tool call limit exceeded: {limit} calls")]
+    ToolCallLimit { limit: u32 },
+
+    #[error("Tool call limit exceeded for {tool_name}: {limit} calls")]
+    ToolCallLimitPerTool { tool_name: String, limit: u32 },
+
+    #[error("Duplicate call to {tool_name} with identical arguments")]
+    DuplicateToolCall { tool_name: String },
+
     #[error("Tool call processing timeout exceeded: {timeout:?}")]
     ToolCallTimeout { timeout: std::time::Duration },
 
     #[error("Toll registration failed for {tool_name}: {message}")]
     ToolRegistration { tool_name: String, message: String },
+
+    #[error("Agent handoff limit exceeded: {limit} handoffs")]
+    HandoffLimit { limit: u32 },
+
+    #[error("Plan step '{step}' failed: {message}")]
+    PlanStep { step: String, message: String },
+
+    #[error("Guardrail '{guardrail}' rejected the text: {reason}")]
+    GuardrailViolation { guardrail: String, reason: String },
+
+    #[error("Output was rejected: {reason}")]
+    OutputRejected { reason: String },
+
+    #[error("Request does not match the expected request at '{path}'")]
+    RequestMismatch {
+        path: String,
+        actual: serde_json::Value,
+    },
+
+    #[error("Request was aborted")]
+    Aborted,
+
+    #[error("Failed to migrate from schema version {from} to {to}: {message}")]
+    Migration { from: u32, to: u32, message: String },
+
+    #[error("Prompt '{prompt}': {message}")]
+    Prompt { prompt: String, message: String },
 }
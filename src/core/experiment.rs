@@ -0,0 +1,437 @@
+//! A/B experiments between models and prompts.
+//!
+//! An [`Experiment`] sends each call to one of its [`Variant`]s: the control, or an alternate
+//! client and system prompt that gets a configured percentage of the calls. Responses are
+//! tagged with the variant that answered, and [`Experiment::stats`] sums up calls, errors,
+//! token usage, cost and latency per variant so they can be compared.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::experiment::{Experiment, Variant};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let client = |model: &str| -> Result<_, rsai::LlmError> {
+//!     Ok(llm::with(Provider::OpenAI)
+//!         .api_key(ApiKey::Default)?
+//!         .model(model)
+//!         .client())
+//! };
+//! let experiment = Experiment::new("cart-summary", client("gpt-4o")?).with_variant(
+//!     "mini-terse",
+//!     20.0,
+//!     Variant::new(client("gpt-4o-mini")?).with_instructions("Answer in one sentence."),
+//! )?;
+//!
+//! let answer = experiment
+//!     .complete_for::<TextResponse>(
+//!         "user-42",
+//!         vec![Message::new(ChatRole::User, "Summarize my cart")],
+//!     )
+//!     .await?;
+//! println!("{} answered: {}", answer.variant, answer.response.text);
+//!
+//! for (variant, stats) in experiment.stats() {
+//!     println!("{variant}: {} calls, {:?} on average", stats.calls, stats.mean_latency());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::builder::LlmClient;
+use super::hash::fnv1a;
+use super::{ChatRole, CompletionTarget, LanguageModelUsage, LlmError, Message, usage};
+
+/// Name of the variant passed to [`Experiment::new`].
+pub const CONTROL: &str = "control";
+
+/// One arm of an [`Experiment`]: a client and, optionally, the system prompt it is sent.
+pub struct Variant<Ctx = ()> {
+    client: LlmClient<Ctx>,
+    instructions: Option<String>,
+}
+
+impl<Ctx> Variant<Ctx> {
+    /// A variant that sends the calls to `client` as they are.
+    pub fn new(client: LlmClient<Ctx>) -> Self {
+        Self {
+            client,
+            instructions: None,
+        }
+    }
+
+    /// Replace the system messages of every call with `instructions`.
+    pub fn with_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+}
+
+impl<Ctx> From<LlmClient<Ctx>> for Variant<Ctx> {
+    fn from(client: LlmClient<Ctx>) -> Self {
+        Self::new(client)
+    }
+}
+
+/// A response of [`Experiment::complete`], tagged with the variant that answered.
+#[derive(Debug, Clone)]
+pub struct ExperimentResponse<T> {
+    /// Name of the variant, [`CONTROL`] for the control.
+    pub variant: String,
+    /// Output of the completion, as returned by the client's `complete`.
+    pub response: T,
+    /// Usage of every request of the call, tool loops included.
+    pub usage: LanguageModelUsage,
+    /// Time until the response was complete.
+    pub latency: Duration,
+}
+
+/// Sums of the calls of one variant, see [`Experiment::stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantStats {
+    /// Calls routed to the variant, failed ones included.
+    pub calls: u64,
+    /// Calls that returned an error.
+    pub errors: u64,
+    /// Token usage summed over every request of every call.
+    pub usage: LanguageModelUsage,
+    /// Cost in USD of the requests whose model has a price, see
+    /// [`usage::set_price`](crate::usage::set_price).
+    pub cost: f64,
+    /// Number of requests without a price, not included in `cost`.
+    pub unpriced_requests: u64,
+    /// Latency summed over all calls, see [`mean_latency`](Self::mean_latency).
+    pub latency: Duration,
+}
+
+impl Default for VariantStats {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            errors: 0,
+            usage: LanguageModelUsage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                cached_tokens: None,
+            },
+            cost: 0.0,
+            unpriced_requests: 0,
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+impl VariantStats {
+    /// Average latency per call.
+    pub fn mean_latency(&self) -> Duration {
+        self.latency / self.calls.max(1) as u32
+    }
+
+    /// Share of calls that returned an error (0.0 to 1.0).
+    pub fn error_rate(&self) -> f64 {
+        self.errors as f64 / self.calls.max(1) as f64
+    }
+}
+
+/// Routes calls between a control and alternate [`Variant`]s, see the
+/// [module documentation](self).
+pub struct Experiment<Ctx = ()> {
+    name: String,
+    /// Variants with the share of calls in percent they receive, the control first.
+    variants: Vec<(String, f64, Variant<Ctx>)>,
+    stats: Arc<Mutex<BTreeMap<String, VariantStats>>>,
+}
+
+impl<Ctx: Send + Sync + 'static> Experiment<Ctx> {
+    /// An experiment named `name` that sends every call to `control` until variants are
+    /// added.
+    pub fn new(name: impl Into<String>, control: impl Into<Variant<Ctx>>) -> Self {
+        Self {
+            name: name.into(),
+            variants: vec![(CONTROL.to_string(), 100.0, control.into())],
+            stats: Arc::default(),
+        }
+    }
+
+    /// Send `percent` of the calls to `variant`, taken from the control's share.
+    ///
+    /// Returns [`LlmError::Builder`] if the name is taken, `percent` is outside 0 to 100 or
+    /// the variants would receive more than 100 percent in total.
+    pub fn with_variant(
+        mut self,
+        name: impl Into<String>,
+        percent: f64,
+        variant: impl Into<Variant<Ctx>>,
+    ) -> Result<Self, LlmError> {
+        let name = name.into();
+        if self.variants.iter().any(|(existing, ..)| *existing == name) {
+            return Err(LlmError::Builder(format!(
+                "Experiment '{}' already has a variant '{name}'",
+                self.name
+            )));
+        }
+        let control = &mut self.variants[0].1;
+        if !(0.0..=100.0).contains(&percent) || percent > *control + f64::EPSILON {
+            return Err(LlmError::Builder(format!(
+                "Variant '{name}' of experiment '{}' can receive at most {control}% of the calls, not {percent}%",
+                self.name
+            )));
+        }
+        *control = (*control - percent).max(0.0);
+        self.variants.push((name, percent, variant.into()));
+        Ok(self)
+    }
+
+    /// The name the experiment was created with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Generate a completion for `messages` with a randomly chosen variant.
+    pub async fn complete<T>(
+        &self,
+        messages: Vec<Message>,
+    ) -> Result<ExperimentResponse<T::Output>, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        self.complete_with::<T>(self.pick(rand::random::<f64>() * 100.0), messages)
+            .await
+    }
+
+    /// Like [`complete`](Self::complete), but calls with the same `key`, e.g. a user id,
+    /// always go to the same variant as long as the variants don't change.
+    pub async fn complete_for<T>(
+        &self,
+        key: &str,
+        messages: Vec<Message>,
+    ) -> Result<ExperimentResponse<T::Output>, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        self.complete_with::<T>(self.bucket(key), messages).await
+    }
+
+    /// Name of the variant that [`complete_for`](Self::complete_for) uses for `key`.
+    pub fn assignment(&self, key: &str) -> &str {
+        &self.variants[self.bucket(key)].0
+    }
+
+    /// Sums of the calls so far, by variant name. Variants without calls are left out.
+    pub fn stats(&self) -> BTreeMap<String, VariantStats> {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Forget the [`stats`](Self::stats) of every variant.
+    pub fn reset_stats(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.clear();
+        }
+    }
+
+    /// Index of the variant whose share contains `point`, from 0 to 100.
+    fn pick(&self, point: f64) -> usize {
+        let mut upper = 0.0;
+        for (index, (_, percent, _)) in self.variants.iter().enumerate() {
+            upper += percent;
+            if point < upper {
+                return index;
+            }
+        }
+        // Rounding can leave the top of the range uncovered
+        self.variants
+            .iter()
+            .rposition(|(_, percent, _)| *percent > 0.0)
+            .unwrap_or(0)
+    }
+
+    fn bucket(&self, key: &str) -> usize {
+        let hash = fnv1a(format!("{}\0{key}", self.name).as_bytes());
+        self.pick((hash % 10_000) as f64 / 100.0)
+    }
+
+    async fn complete_with<T>(
+        &self,
+        index: usize,
+        mut messages: Vec<Message>,
+    ) -> Result<ExperimentResponse<T::Output>, LlmError>
+    where
+        T: CompletionTarget + Send,
+    {
+        let (name, _, variant) = &self.variants[index];
+        if let Some(instructions) = &variant.instructions {
+            messages.retain(|message| message.role != ChatRole::System);
+            messages.insert(0, Message::new(ChatRole::System, instructions.clone()));
+        }
+
+        let started = Instant::now();
+        let (result, reports) = usage::collect(variant.client.complete::<T>(messages)).await;
+        let latency = started.elapsed();
+
+        let mut call = VariantStats {
+            calls: 1,
+            errors: u64::from(result.is_err()),
+            latency,
+            ..VariantStats::default()
+        };
+        for report in &reports {
+            call.usage = call.usage.combined(&report.usage);
+            match report.cost {
+                Some(cost) => call.cost += cost,
+                None => call.unpriced_requests += 1,
+            }
+        }
+        if let Ok(mut stats) = self.stats.lock() {
+            let stats = stats.entry(name.clone()).or_default();
+            stats.calls += call.calls;
+            stats.errors += call.errors;
+            stats.usage = stats.usage.combined(&call.usage);
+            stats.cost += call.cost;
+            stats.unpriced_requests += call.unpriced_requests;
+            stats.latency += call.latency;
+        }
+
+        Ok(ExperimentResponse {
+            variant: name.clone(),
+            response: result?,
+            usage: call.usage,
+            latency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, SlowModelTransport, responses_answer};
+    use crate::{ApiKey, Provider, llm};
+    use crate::{TextResponse, Transport};
+
+    fn client(model: &str) -> LlmClient {
+        llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model(model)
+            .client()
+    }
+
+    #[test]
+    fn test_calls_are_split_by_percentage() {
+        let experiment = Experiment::new("split", client("a"))
+            .with_variant("b", 20.0, client("b"))
+            .unwrap()
+            .with_variant("off", 0.0, client("c"))
+            .unwrap();
+        assert_eq!(experiment.pick(0.0), 0);
+        assert_eq!(experiment.pick(79.9), 0);
+        assert_eq!(experiment.pick(80.0), 1);
+        assert_eq!(experiment.pick(100.0), 1);
+
+        let to_b = (0..1_000)
+            .filter(|user| experiment.assignment(&format!("user-{user}")) == "b")
+            .count();
+        assert!((150..250).contains(&to_b), "{to_b} of 1000 keys went to b");
+        assert_eq!(
+            experiment.assignment("user-7"),
+            experiment.assignment("user-7")
+        );
+    }
+
+    #[test]
+    fn test_invalid_variants_are_rejected() {
+        let experiment = || Experiment::new("split", client("a"));
+        assert!(
+            experiment()
+                .with_variant("b", 60.0, client("b"))
+                .unwrap()
+                .with_variant("c", 50.0, client("c"))
+                .is_err()
+        );
+        assert!(experiment().with_variant("b", -1.0, client("b")).is_err());
+        assert!(
+            experiment()
+                .with_variant(CONTROL, 10.0, client("b"))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_routed_to_variants_and_compared() {
+        let capturing = ScriptedTransport::answering(responses_answer("Two items."));
+        let transport = SlowModelTransport::new("none");
+        let client = |model: &str, transport: Arc<dyn Transport>| {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test-key".to_string()))
+                .unwrap()
+                .model(model)
+                .transport(transport)
+                .client()
+        };
+        let messages = || {
+            vec![
+                Message::new(ChatRole::System, "Be thorough."),
+                Message::new(ChatRole::User, "Summarize my cart"),
+            ]
+        };
+
+        let experiment =
+            Experiment::new("cart-summary", client("control-model", transport.clone()))
+                .with_variant(
+                    "terse",
+                    100.0,
+                    Variant::new(client("terse-model", capturing.clone()))
+                        .with_instructions("Answer in one sentence."),
+                )
+                .unwrap();
+        let answer = experiment
+            .complete::<TextResponse>(messages())
+            .await
+            .unwrap();
+        assert_eq!(answer.variant, "terse");
+        assert_eq!(answer.response.text, "Two items.");
+        assert_eq!(answer.usage.total_tokens, 15);
+        let bodies = capturing.bodies();
+        let input = bodies[0]["input"].as_array().unwrap();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input[0]["content"], "Answer in one sentence.");
+        assert!(transport.models().is_empty());
+
+        let experiment =
+            Experiment::new("cart-summary", client("control-model", transport.clone()))
+                .with_variant("terse", 50.0, client("terse-model", transport.clone()))
+                .unwrap();
+        for user in ["user-1", "user-2", "user-3", "user-1"] {
+            let answer = experiment
+                .complete_for::<TextResponse>(user, messages())
+                .await
+                .unwrap();
+            assert_eq!(answer.variant, experiment.assignment(user));
+            let model = match answer.variant.as_str() {
+                CONTROL => "control-model",
+                _ => "terse-model",
+            };
+            assert_eq!(answer.response.text, format!("Answer from {model}"));
+        }
+
+        let stats = experiment.stats();
+        assert_eq!(stats.values().map(|stats| stats.calls).sum::<u64>(), 4);
+        assert!(stats.values().all(|stats| stats.errors == 0));
+        assert_eq!(
+            stats
+                .values()
+                .map(|stats| stats.usage.total_tokens)
+                .sum::<i32>(),
+            60
+        );
+        experiment.reset_stats();
+        assert!(experiment.stats().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+//! Scripted provider answers and transports shared by the unit tests.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+
+use super::builder::{ApiKey, LlmBuilder, llm, private::Configuring};
+use super::error::LlmError;
+use super::traits::ToolFunction;
+use super::transport::{Transport, TransportRequest, TransportResponse};
+use super::types::{BoxFuture, Tool, ToolRegistry, ToolSet};
+use crate::provider::Provider;
+
+/// Structured answer of the scripted models.
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub(crate) struct Sum {
+    pub sum: i64,
+}
+
+/// Tool adding `a` and `b`, registered as `calculate_sum`.
+pub(crate) struct SumTool;
+
+impl ToolFunction for SumTool {
+    fn schema(&self) -> Tool {
+        Tool {
+            name: "calculate_sum".to_string(),
+            description: Some("Add two numbers".to_string()),
+            parameters: json!({
+                "type": "object",
+                "properties": { "a": { "type": "integer" }, "b": { "type": "integer" } },
+                "required": ["a", "b"]
+            }),
+            strict: None,
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a (),
+        params: Value,
+    ) -> BoxFuture<'a, Result<Value, LlmError>> {
+        Box::pin(async move {
+            let sum =
+                params["a"].as_i64().unwrap_or_default() + params["b"].as_i64().unwrap_or_default();
+            Ok(json!({ "sum": sum }))
+        })
+    }
+}
+
+/// A toolset holding only [`SumTool`].
+pub(crate) fn sum_toolset() -> ToolSet {
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(SumTool)).unwrap();
+    ToolSet { registry }
+}
+
+/// Token usage of every scripted Responses API answer.
+pub(crate) fn usage_payload() -> Value {
+    json!({
+        "input_tokens": 10,
+        "output_tokens": 5,
+        "total_tokens": 15
+    })
+}
+
+/// A Responses API answer with `text` as its only output.
+pub(crate) fn responses_answer(text: &str) -> Value {
+    json!({
+        "id": "resp_1",
+        "model": "mock-model",
+        "output": [{
+            "id": "msg_1",
+            "type": "message",
+            "status": "completed",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": text }]
+        }],
+        "usage": usage_payload()
+    })
+}
+
+/// A Responses API answer calling the tool `name` with `arguments`.
+pub(crate) fn responses_tool_call(name: &str, arguments: Value) -> Value {
+    json!({
+        "id": "resp_call",
+        "model": "mock-model",
+        "output": [{
+            "type": "function_call",
+            "id": "call_1",
+            "call_id": "call_1",
+            "name": name,
+            "arguments": arguments.to_string(),
+        }],
+        "usage": usage_payload()
+    })
+}
+
+/// A builder for `mock-model` on OpenAI that sends its requests through `transport`.
+pub(crate) fn mock_openai(transport: Arc<dyn Transport>) -> LlmBuilder<Configuring> {
+    llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Custom("test-key".to_string()))
+        .unwrap()
+        .model("mock-model")
+        .transport(transport)
+}
+
+/// Transport that answers with scripted status codes and bodies in order, repeating the last
+/// one, and records the requests.
+pub(crate) struct ScriptedTransport {
+    responses: Mutex<VecDeque<(u16, Value)>>,
+    requests: Mutex<Vec<TransportRequest>>,
+}
+
+impl ScriptedTransport {
+    pub(crate) fn new(responses: Vec<(u16, Value)>) -> Arc<Self> {
+        assert!(!responses.is_empty(), "script at least one response");
+        Arc::new(Self {
+            responses: Mutex::new(responses.into()),
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Answer every request with `response`.
+    pub(crate) fn answering(response: Value) -> Arc<Self> {
+        Self::new(vec![(200, response)])
+    }
+
+    /// The requests sent so far.
+    pub(crate) fn requests(&self) -> Vec<TransportRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The bodies of the requests sent so far.
+    pub(crate) fn bodies(&self) -> Vec<Value> {
+        self.requests()
+            .into_iter()
+            .map(|request| request.body)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Transport for ScriptedTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        self.requests.lock().unwrap().push(request);
+        let mut responses = self.responses.lock().unwrap();
+        let (status, body) = if responses.len() > 1 {
+            responses.pop_front().unwrap()
+        } else {
+            responses[0].clone()
+        };
+        Ok(TransportResponse {
+            status,
+            body: body.to_string(),
+        })
+    }
+}
+
+/// Transport that answers with the requested model's name, after a long delay for `slow_model`.
+pub(crate) struct SlowModelTransport {
+    slow_model: &'static str,
+    models: Mutex<Vec<String>>,
+    headers: Mutex<Vec<Vec<(String, String)>>>,
+}
+
+impl SlowModelTransport {
+    pub(crate) fn new(slow_model: &'static str) -> Arc<Self> {
+        Arc::new(Self {
+            slow_model,
+            models: Mutex::new(Vec::new()),
+            headers: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The models requested so far.
+    pub(crate) fn models(&self) -> Vec<String> {
+        self.models.lock().unwrap().clone()
+    }
+
+    /// Value of the header `name` of every request, in order.
+    pub(crate) fn header(&self, name: &str) -> Vec<Option<String>> {
+        self.headers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.clone())
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Transport for SlowModelTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        let model = request.body["model"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        self.models.lock().unwrap().push(model.clone());
+        self.headers.lock().unwrap().push(request.headers);
+        if model == self.slow_model {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+        }
+        let mut response = responses_answer(&format!("Answer from {model}"));
+        response["model"] = Value::String(model);
+        Ok(TransportResponse {
+            status: 200,
+            body: response.to_string(),
+        })
+    }
+}
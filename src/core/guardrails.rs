@@ -0,0 +1,583 @@
+//! Validation of user input before a request and of model output before it is parsed.
+//!
+//! A [`Guardrail`] inspects text and reports a [`Check`]. Guardrails are registered on
+//! [`Guardrails`] for the input or the output stage, each with a [`GuardrailAction`] that
+//! decides what happens on a violation, and attached to a request with
+//! [`LlmBuilder::guardrails`](crate::LlmBuilder::guardrails).
+//!
+//! # Example
+//! ```no_run
+//! use rsai::guardrails::{Blocklist, GuardrailAction, Guardrails, MaxLength, Pii};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let guardrails = Guardrails::new()
+//!     .input(Pii::new(), GuardrailAction::Redact)
+//!     .input(MaxLength::new(4_000), GuardrailAction::Block)
+//!     .output(Blocklist::new(&[r"(?i)\bguarantee(d)?\b"])?, GuardrailAction::Retry);
+//!
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "I'm jane@example.com, will this fund double my money?")])
+//!     .guardrails(guardrails)
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use regex::Regex;
+
+use super::builder::{ApiKey, llm};
+use super::error::LlmError;
+use super::transport::Transport;
+use super::types::{BoxFuture, ChatRole, Message};
+use crate::provider::Provider;
+
+/// Result of running a [`Guardrail`] on a piece of text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Check {
+    Pass,
+    Violation {
+        /// Why the text was rejected.
+        reason: String,
+        /// The text with the offending parts removed, if the guardrail can redact.
+        redacted: Option<String>,
+    },
+}
+
+impl Check {
+    pub fn violation(reason: impl Into<String>) -> Self {
+        Check::Violation {
+            reason: reason.into(),
+            redacted: None,
+        }
+    }
+}
+
+/// What happens when a guardrail reports a violation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardrailAction {
+    /// Fail the request with [`LlmError::GuardrailViolation`].
+    Block,
+    /// Continue with the redacted text. Blocks if the guardrail cannot redact.
+    Redact,
+    /// Output only: ask the model again, telling it why the answer was rejected, up to
+    /// [`Guardrails::max_retries`] times. Blocks on input and once retries are exhausted.
+    Retry,
+}
+
+/// Validates a piece of text.
+pub trait Guardrail: Send + Sync {
+    /// Name reported in [`LlmError::GuardrailViolation`].
+    fn name(&self) -> &str;
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>>;
+}
+
+/// Outcome of the output stage.
+pub(crate) enum OutputCheck {
+    /// Accepted, possibly redacted.
+    Accept(String),
+    /// Rejected by a guardrail with [`GuardrailAction::Retry`].
+    Retry { guardrail: String, reason: String },
+}
+
+/// The guardrails of a request, per stage.
+#[derive(Clone)]
+pub struct Guardrails {
+    input: Vec<(Arc<dyn Guardrail>, GuardrailAction)>,
+    output: Vec<(Arc<dyn Guardrail>, GuardrailAction)>,
+    max_retries: u32,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Guardrails {
+    pub fn new() -> Self {
+        Self {
+            input: Vec::new(),
+            output: Vec::new(),
+            max_retries: 2,
+        }
+    }
+
+    /// Check every user message before the request is sent.
+    pub fn input(mut self, guardrail: impl Guardrail + 'static, action: GuardrailAction) -> Self {
+        self.input.push((Arc::new(guardrail), action));
+        self
+    }
+
+    /// Check the model's answer before it is parsed.
+    pub fn output(mut self, guardrail: impl Guardrail + 'static, action: GuardrailAction) -> Self {
+        self.output.push((Arc::new(guardrail), action));
+        self
+    }
+
+    /// How many times a rejected answer is requested again (default 2).
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    pub(crate) fn retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Run the input stage on `text`, returning the text to send.
+    pub async fn check_input(&self, text: &str) -> Result<String, LlmError> {
+        let mut text = text.to_string();
+        for (guardrail, action) in &self.input {
+            if let Check::Violation { reason, redacted } = guardrail.check(&text).await? {
+                text = match (action, redacted) {
+                    (GuardrailAction::Redact, Some(redacted)) => redacted,
+                    _ => return Err(violation(guardrail.as_ref(), reason)),
+                };
+            }
+        }
+        Ok(text)
+    }
+
+    /// Run the output stage on `text`.
+    pub(crate) async fn check_output(&self, text: &str) -> Result<OutputCheck, LlmError> {
+        let mut text = text.to_string();
+        for (guardrail, action) in &self.output {
+            if let Check::Violation { reason, redacted } = guardrail.check(&text).await? {
+                text = match (action, redacted) {
+                    (GuardrailAction::Redact, Some(redacted)) => redacted,
+                    (GuardrailAction::Retry, _) => {
+                        return Ok(OutputCheck::Retry {
+                            guardrail: guardrail.name().to_string(),
+                            reason,
+                        });
+                    }
+                    _ => return Err(violation(guardrail.as_ref(), reason)),
+                };
+            }
+        }
+        Ok(OutputCheck::Accept(text))
+    }
+
+    pub(crate) fn has_output(&self) -> bool {
+        !self.output.is_empty()
+    }
+}
+
+fn violation(guardrail: &dyn Guardrail, reason: String) -> LlmError {
+    LlmError::GuardrailViolation {
+        guardrail: guardrail.name().to_string(),
+        reason,
+    }
+}
+
+/// Replace every match with `replacement`, returning `None` if nothing matched.
+fn replace_all(patterns: &[(Regex, String)], text: &str) -> Option<String> {
+    let mut matched = false;
+    let redacted = patterns
+        .iter()
+        .fold(text.to_string(), |text, (pattern, replacement)| {
+            if pattern.is_match(&text) {
+                matched = true;
+                pattern
+                    .replace_all(&text, replacement.as_str())
+                    .into_owned()
+            } else {
+                text
+            }
+        });
+    matched.then_some(redacted)
+}
+
+fn compile(pattern: &str) -> Result<Regex, LlmError> {
+    Regex::new(pattern).map_err(|e| {
+        LlmError::ProviderConfiguration(format!("Invalid guardrail pattern '{pattern}': {e}"))
+    })
+}
+
+/// Rejects text matching any of a set of regular expressions. Redaction replaces the matches
+/// with `[BLOCKED]`.
+pub struct Blocklist {
+    patterns: Vec<(Regex, String)>,
+}
+
+impl Blocklist {
+    /// # Errors
+    ///
+    /// Returns [`LlmError::ProviderConfiguration`] if a pattern is not a valid regex.
+    pub fn new(patterns: &[&str]) -> Result<Self, LlmError> {
+        Ok(Self {
+            patterns: patterns
+                .iter()
+                .map(|pattern| Ok((compile(pattern)?, "[BLOCKED]".to_string())))
+                .collect::<Result<_, LlmError>>()?,
+        })
+    }
+}
+
+impl Guardrail for Blocklist {
+    fn name(&self) -> &str {
+        "blocklist"
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        let check = match replace_all(&self.patterns, text) {
+            Some(redacted) => Check::Violation {
+                reason: "Text contains blocked content".to_string(),
+                redacted: Some(redacted),
+            },
+            None => Check::Pass,
+        };
+        Box::pin(async move { Ok(check) })
+    }
+}
+
+/// Rejects text longer than a number of characters. Redaction truncates.
+pub struct MaxLength {
+    max_chars: usize,
+}
+
+impl MaxLength {
+    pub fn new(max_chars: usize) -> Self {
+        Self { max_chars }
+    }
+}
+
+impl Guardrail for MaxLength {
+    fn name(&self) -> &str {
+        "max_length"
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        let length = text.chars().count();
+        let check = if length > self.max_chars {
+            Check::Violation {
+                reason: format!(
+                    "Text is {length} characters long, the limit is {}",
+                    self.max_chars
+                ),
+                redacted: Some(text.chars().take(self.max_chars).collect()),
+            }
+        } else {
+            Check::Pass
+        };
+        Box::pin(async move { Ok(check) })
+    }
+}
+
+/// Detects email addresses, phone numbers and credit card numbers. Redaction replaces them
+/// with `[EMAIL]`, `[PHONE]` and `[CARD]`.
+pub struct Pii {
+    patterns: Vec<(Regex, String)>,
+}
+
+pub(crate) const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+pub(crate) const CARD_PATTERN: &str = r"\b(?:\d[ -]?){12,15}\d\b";
+pub(crate) const PHONE_PATTERN: &str =
+    r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)[ .-]?)?\d{3,4}[ .-]\d{3,4}(?:[ .-]\d{2,4})?\b";
+
+impl Default for Pii {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pii {
+    pub fn new() -> Self {
+        // Cards before phone numbers, which would otherwise match parts of card numbers
+        let patterns = [
+            (EMAIL_PATTERN, "[EMAIL]"),
+            (CARD_PATTERN, "[CARD]"),
+            (PHONE_PATTERN, "[PHONE]"),
+        ];
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|(pattern, replacement)| {
+                    (
+                        Regex::new(pattern).expect("built-in PII pattern is valid"),
+                        replacement.to_string(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Guardrail for Pii {
+    fn name(&self) -> &str {
+        "pii"
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        let check = match replace_all(&self.patterns, text) {
+            Some(redacted) => Check::Violation {
+                reason: "Text contains personal data".to_string(),
+                redacted: Some(redacted),
+            },
+            None => Check::Pass,
+        };
+        Box::pin(async move { Ok(check) })
+    }
+}
+
+/// A guardrail from a synchronous closure, created with [`from_fn`].
+pub struct FnGuardrail<F> {
+    name: String,
+    check: F,
+}
+
+/// Create a guardrail from a closure.
+///
+/// ```
+/// use rsai::guardrails::{Check, from_fn};
+///
+/// let no_sql = from_fn("no_sql", |text| {
+///     if text.to_lowercase().contains("drop table") {
+///         Check::violation("Looks like SQL")
+///     } else {
+///         Check::Pass
+///     }
+/// });
+/// ```
+pub fn from_fn<F>(name: impl Into<String>, check: F) -> FnGuardrail<F>
+where
+    F: Fn(&str) -> Check + Send + Sync,
+{
+    FnGuardrail {
+        name: name.into(),
+        check,
+    }
+}
+
+impl<F> Guardrail for FnGuardrail<F>
+where
+    F: Fn(&str) -> Check + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        let check = (self.check)(text);
+        Box::pin(async move { Ok(check) })
+    }
+}
+
+/// Asks a model whether text complies with a policy.
+pub struct LlmCheck {
+    provider: Provider,
+    model: String,
+    api_key: ApiKey,
+    policy: String,
+    transport: Option<Arc<dyn Transport>>,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+struct PolicyDecision {
+    /// Whether the text complies with the policy
+    compliant: bool,
+    /// Which part of the policy is violated, empty if compliant
+    reason: String,
+}
+
+impl LlmCheck {
+    pub fn new(
+        provider: Provider,
+        model: impl Into<String>,
+        api_key: ApiKey,
+        policy: impl Into<String>,
+    ) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            api_key,
+            policy: policy.into(),
+            transport: None,
+        }
+    }
+
+    /// Send the check through a custom transport, e.g. a [`Cassette`](crate::Cassette).
+    pub fn with_transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+}
+
+impl Guardrail for LlmCheck {
+    fn name(&self) -> &str {
+        "llm_check"
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        Box::pin(async move {
+            let mut builder = llm::with(self.provider)
+                .api_key(self.api_key.clone())?
+                .model(&self.model)
+                .messages(vec![
+                    Message::new(
+                        ChatRole::System,
+                        format!(
+                            "Decide whether the text complies with this policy:\n{}",
+                            self.policy
+                        ),
+                    ),
+                    Message::new(ChatRole::User, text.to_string()),
+                ])
+                .temperature(0.0);
+            if let Some(transport) = &self.transport {
+                builder = builder.transport(transport.clone());
+            }
+
+            let decision = builder.complete::<PolicyDecision>().await?.content;
+            Ok(if decision.compliant {
+                Check::Pass
+            } else {
+                Check::violation(decision.reason)
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, Sum, mock_openai, responses_answer};
+
+    #[tokio::test]
+    async fn test_pii_is_redacted() {
+        let check = Pii::new()
+            .check("Mail jane.doe@example.com or call +1 415-555-0132, card 4111 1111 1111 1111.")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            check,
+            Check::Violation {
+                reason: "Text contains personal data".to_string(),
+                redacted: Some("Mail [EMAIL] or call [PHONE], card [CARD].".to_string()),
+            }
+        );
+        assert_eq!(
+            Pii::new().check("Order 42 ships in 3 days").await.unwrap(),
+            Check::Pass
+        );
+    }
+
+    #[tokio::test]
+    async fn test_input_actions() {
+        let guardrails = Guardrails::new()
+            .input(
+                Blocklist::new(&["(?i)password"]).unwrap(),
+                GuardrailAction::Redact,
+            )
+            .input(MaxLength::new(20), GuardrailAction::Block);
+
+        assert_eq!(
+            guardrails.check_input("my Password is").await.unwrap(),
+            "my [BLOCKED] is"
+        );
+
+        let err = guardrails
+            .check_input("a message that is far too long")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LlmError::GuardrailViolation { ref guardrail, .. } if guardrail == "max_length"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_output_retry_and_block() {
+        let guardrails = Guardrails::new()
+            .output(
+                from_fn("no_promises", |text| {
+                    if text.contains("guarantee") {
+                        Check::violation("Makes a promise")
+                    } else {
+                        Check::Pass
+                    }
+                }),
+                GuardrailAction::Retry,
+            )
+            // Cannot redact, so it blocks
+            .output(
+                from_fn("short", |text| {
+                    if text.len() > 10 {
+                        Check::violation("Too long")
+                    } else {
+                        Check::Pass
+                    }
+                }),
+                GuardrailAction::Redact,
+            );
+
+        assert!(matches!(
+            guardrails.check_output("we guarantee it").await.unwrap(),
+            OutputCheck::Retry { ref reason, .. } if reason == "Makes a promise"
+        ));
+        assert!(matches!(
+            guardrails.check_output("ok").await.unwrap(),
+            OutputCheck::Accept(ref text) if text == "ok"
+        ));
+        assert!(guardrails.check_output("a long answer").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_input_is_redacted_and_rejected_output_retried() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":3}"));
+        let guardrails = Guardrails::new()
+            .input(Pii::new(), GuardrailAction::Redact)
+            .output(
+                from_fn("no_three", |text| {
+                    if text.contains('3') {
+                        Check::violation("The sum must not be 3")
+                    } else {
+                        Check::Pass
+                    }
+                }),
+                GuardrailAction::Retry,
+            )
+            .max_retries(1);
+
+        let err = mock_openai(transport.clone())
+            .messages(vec![Message::new(
+                ChatRole::User,
+                "Add 1 and 2, then mail me at jane@example.com",
+            )])
+            .guardrails(guardrails)
+            .complete::<Sum>()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LlmError::GuardrailViolation { ref guardrail, .. } if guardrail == "no_three"
+        ));
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(
+            bodies[0]["input"][0]["content"],
+            "Add 1 and 2, then mail me at [EMAIL]"
+        );
+
+        let retry = bodies[1]["input"].as_array().unwrap();
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1]["role"], "assistant");
+        assert_eq!(retry[1]["content"], "{\"sum\":3}");
+        assert!(
+            retry[2]["content"]
+                .as_str()
+                .unwrap()
+                .contains("The sum must not be 3")
+        );
+    }
+}
@@ -0,0 +1,9 @@
+//! Stable hashing for values that must not change across Rust versions or platforms, e.g.
+//! experiment buckets and hashes written to logs.
+
+/// 64-bit FNV-1a, stable across Rust versions unlike the standard library's hashers.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
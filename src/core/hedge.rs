@@ -0,0 +1,202 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::builder::{ApiKey, resolve_api_key};
+use super::error::LlmError;
+use super::http::HttpClientConfig;
+use super::redaction::REDACTED;
+use crate::provider::Provider;
+
+/// When and where [`LlmBuilder::hedge`](crate::LlmBuilder::hedge) sends a backup request.
+///
+/// A bare [`Duration`] sends the backup to the same provider and model.
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use rsai::{ApiKey, Hedge, Provider};
+///
+/// # fn example() -> Result<(), rsai::LlmError> {
+/// let hedge = Hedge::after(Duration::from_millis(800)).to(
+///     Provider::Gemini,
+///     "gemini-2.5-flash",
+///     ApiKey::Default,
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Hedge {
+    pub(crate) delay: Duration,
+    pub(crate) provider: Option<Provider>,
+    pub(crate) model: Option<String>,
+    pub(crate) api_key: Option<String>,
+    pub(crate) http_client_config: Option<HttpClientConfig>,
+}
+
+impl Hedge {
+    /// Send the backup once the primary request has been pending for `delay`.
+    pub fn after(delay: Duration) -> Self {
+        Self {
+            delay,
+            provider: None,
+            model: None,
+            api_key: None,
+            http_client_config: None,
+        }
+    }
+
+    /// Send the backup to another model of the same provider.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Send the backup to `model` of `provider`, authenticated with `api_key`.
+    ///
+    /// A backup to another provider is sent with [`HttpClientConfig::default`] instead of the
+    /// primary's HTTP settings, which may only apply to the primary (its gateway, transport,
+    /// headers or credentials); see [`with_http_client_config`](Self::with_http_client_config).
+    pub fn to(
+        mut self,
+        provider: Provider,
+        model: impl Into<String>,
+        api_key: ApiKey,
+    ) -> Result<Self, LlmError> {
+        self.api_key = Some(resolve_api_key(provider, api_key)?);
+        self.provider = Some(provider);
+        self.model = Some(model.into());
+        Ok(self)
+    }
+
+    /// The HTTP settings of a backup sent to another provider with [`to`](Self::to).
+    pub fn with_http_client_config(mut self, config: HttpClientConfig) -> Self {
+        self.http_client_config = Some(config);
+        self
+    }
+}
+
+impl fmt::Debug for Hedge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hedge")
+            .field("delay", &self.delay)
+            .field("provider", &self.provider)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("http_client_config", &self.http_client_config)
+            .finish()
+    }
+}
+
+impl From<Duration> for Hedge {
+    fn from(delay: Duration) -> Self {
+        Self::after(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::SlowModelTransport;
+    use crate::{ChatRole, Message, TextResponse, llm};
+
+    #[tokio::test]
+    async fn test_hedged_request_returns_the_faster_answer() {
+        let transport = SlowModelTransport::new("slow-model");
+        let request = |model: &str, hedge: Hedge| {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test-key".to_string()))
+                .unwrap()
+                .model(model)
+                .messages(vec![Message::new(ChatRole::User, "Hi")])
+                .hedge(hedge)
+                .transport(transport.clone())
+                .complete::<TextResponse>()
+        };
+
+        let started = std::time::Instant::now();
+        let response = request(
+            "slow-model",
+            Hedge::after(Duration::from_millis(20)).model("fast-model"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.text, "Answer from fast-model");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(transport.models(), ["slow-model", "fast-model"]);
+
+        // No backup when the primary answers in time
+        let response = request("fast-model", Duration::from_secs(5).into())
+            .await
+            .unwrap();
+        assert_eq!(response.text, "Answer from fast-model");
+        assert_eq!(
+            transport.models(),
+            ["slow-model", "fast-model", "fast-model"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backup_to_another_provider_uses_its_own_settings() {
+        let primary = SlowModelTransport::new("slow-model");
+        let backup = SlowModelTransport::new("slow-model");
+        let hedge = Hedge::after(Duration::from_millis(20))
+            .to(
+                Provider::OpenRouter,
+                "fast-model",
+                ApiKey::Custom("backup-key".to_string()),
+            )
+            .unwrap()
+            .with_http_client_config(HttpClientConfig {
+                transport: Some(backup.clone()),
+                ..Default::default()
+            });
+
+        let response = llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("slow-model")
+            .messages(vec![Message::new(ChatRole::User, "Hi")])
+            .hedge(hedge)
+            .header("x-primary", "yes")
+            .transport(primary.clone())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "Answer from fast-model");
+        assert_eq!(primary.models(), ["slow-model"]);
+        assert_eq!(backup.models(), ["fast-model"]);
+        assert_eq!(
+            backup.header("authorization"),
+            [Some("Bearer backup-key".to_string())]
+        );
+        assert_eq!(backup.header("x-primary"), [None]);
+
+        // A backup to the same provider keeps the transport but uses the hedge's key
+        let transport = SlowModelTransport::new("slow-model");
+        let hedge = Hedge::after(Duration::from_millis(20))
+            .to(
+                Provider::OpenAI,
+                "fast-model",
+                ApiKey::Custom("backup-key".to_string()),
+            )
+            .unwrap();
+        llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("slow-model")
+            .messages(vec![Message::new(ChatRole::User, "Hi")])
+            .hedge(hedge)
+            .transport(transport.clone())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(
+            transport.header("authorization"),
+            [
+                Some("Bearer test-key".to_string()),
+                Some("Bearer backup-key".to_string())
+            ]
+        );
+    }
+}
@@ -1,166 +1,531 @@
 //! Shared HTTP client with retry logic for all providers.
 
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, RandomState},
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
 
+use futures::future::{BoxFuture, FutureExt, Shared};
 use serde::{Serialize, de::DeserializeOwned};
 use tracing::{debug, warn};
 
 use super::builder::InspectorConfig;
+use super::credentials::CredentialsProvider;
 use super::error::LlmError;
+use super::redaction::LogRedactor;
+use super::retry::{ExponentialBackoff, RetryPolicy, is_transient_status};
+use super::transport::{
+    HttpMethod, ReqwestTransport, Transport, TransportRequest, TransportResponse,
+};
 
 /// Configuration for HTTP client resilience
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct HttpClientConfig {
+    /// Total time for a single attempt, from connecting to reading the last byte
     pub timeout: Duration,
+    /// Time to establish the connection, including the TLS handshake. Catches unreachable
+    /// hosts long before `timeout`.
+    pub connect_timeout: Option<Duration>,
+    /// Time from sending the request until the response headers arrive, e.g. while a
+    /// provider queues the request.
+    pub first_byte_timeout: Option<Duration>,
+    /// Longest pause between two chunks of the response body. Catches responses that
+    /// stall halfway through.
+    pub idle_timeout: Option<Duration>,
     pub max_retries: u32,
     /// Base duration for exponential backoff
     pub initial_retry_delay: Duration,
     /// Cap on the backoff duration
     pub max_retry_delay: Duration,
+    /// Replaces the exponential backoff configured by `max_retries`, `initial_retry_delay`
+    /// and `max_retry_delay`. See [`retry`](crate::retry).
+    pub retry_policy: Option<Arc<dyn RetryPolicy>>,
+    /// Route all requests through this proxy (e.g. `http://proxy.corp:8080`)
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts that bypass the proxy (same format as `NO_PROXY`)
+    pub no_proxy: Option<String>,
+    /// PEM-encoded root certificates to trust in addition to the system store
+    pub root_certificates: Vec<PathBuf>,
+    /// Disable TLS certificate validation. Only use this against trusted test endpoints.
+    #[cfg(feature = "danger-accept-invalid-certs")]
+    pub danger_accept_invalid_certs: bool,
+    /// Replace the default `reqwest` transport (e.g. with a [`Cassette`](crate::Cassette)).
+    /// When set, proxy and TLS options are the transport's responsibility.
+    pub transport: Option<Arc<dyn Transport>>,
+    /// Extra headers sent with every request. A header with the same name as a provider
+    /// header (case-insensitive) replaces it.
+    pub headers: Vec<(String, String)>,
+    /// Route every request through an LLM gateway, see [`Gateway`].
+    pub gateway: Option<Gateway>,
+    /// Asked for authentication headers before every attempt, for credentials that expire.
+    /// Its headers replace provider and extra headers of the same name. See
+    /// [`credentials`](crate::credentials).
+    pub credentials: Option<Arc<dyn CredentialsProvider>>,
+    /// Rules applied to everything passed to inspectors and tracing.
+    pub log_redaction: LogRedactor,
+    /// Share one in-flight HTTP call between concurrent identical requests (same method, URL,
+    /// headers and body) instead of sending each. Identical requests are still sent one by one
+    /// when they do not overlap in time.
+    pub coalesce_requests: bool,
+    /// Send an `Idempotency-Key` header with every generation request of the OpenAI and
+    /// OpenRouter clients, the same for all retries of a request, so a retried request is not
+    /// billed or executed twice. The key is recorded in the response metadata.
+    pub idempotency_keys: bool,
+    /// Derive keys from this one instead of using random keys. Each request sends it with a
+    /// suffix computed from its body, so repeating a request reuses its key while tool
+    /// calling iterations, re-asks and fallbacks, whose bodies differ, get keys of their own.
+    pub idempotency_key: Option<String>,
+}
+
+impl std::fmt::Debug for HttpClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("HttpClientConfig");
+        debug
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .field("first_byte_timeout", &self.first_byte_timeout)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("max_retries", &self.max_retries)
+            .field("initial_retry_delay", &self.initial_retry_delay)
+            .field("max_retry_delay", &self.max_retry_delay)
+            .field(
+                "retry_policy",
+                &self.retry_policy.as_ref().map(|_| "custom"),
+            )
+            .field("proxy_url", &self.proxy_url)
+            .field("no_proxy", &self.no_proxy)
+            .field("root_certificates", &self.root_certificates);
+        #[cfg(feature = "danger-accept-invalid-certs")]
+        debug.field(
+            "danger_accept_invalid_certs",
+            &self.danger_accept_invalid_certs,
+        );
+        debug
+            .field("transport", &self.transport.as_ref().map(|_| "custom"))
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .field("gateway", &self.gateway)
+            .field("credentials", &self.credentials.as_ref().map(|_| "custom"))
+            .field("log_redaction", &self.log_redaction)
+            .field("coalesce_requests", &self.coalesce_requests)
+            .field("idempotency_keys", &self.idempotency_keys)
+            .field("idempotency_key", &self.idempotency_key)
+            .finish()
+    }
 }
 
 impl Default for HttpClientConfig {
     fn default() -> Self {
         Self {
             timeout: Duration::from_secs(60),
+            connect_timeout: None,
+            first_byte_timeout: None,
+            idle_timeout: None,
             max_retries: 3,
             initial_retry_delay: Duration::from_millis(500),
             max_retry_delay: Duration::from_secs(10),
+            retry_policy: None,
+            proxy_url: None,
+            no_proxy: None,
+            root_certificates: Vec::new(),
+            #[cfg(feature = "danger-accept-invalid-certs")]
+            danger_accept_invalid_certs: false,
+            transport: None,
+            headers: Vec::new(),
+            gateway: None,
+            credentials: None,
+            log_redaction: LogRedactor::default(),
+            coalesce_requests: false,
+            idempotency_keys: true,
+            idempotency_key: None,
+        }
+    }
+}
+
+/// Routing through an LLM gateway such as Kong AI Gateway, a LiteLLM proxy or Portkey,
+/// applied to the requests of every provider.
+///
+/// ```
+/// use rsai::Gateway;
+///
+/// let gateway = Gateway::new()
+///     .with_base_url("https://llm-gateway.corp.example")
+///     .with_path_prefix("/openai")
+///     .with_body_field("user", "team-search")
+///     .with_header("x-gateway-key", "secret");
+///
+/// assert_eq!(
+///     gateway.rewrite_url("https://api.openai.com/v1/responses").unwrap(),
+///     "https://llm-gateway.corp.example/openai/v1/responses"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gateway {
+    /// Replaces the scheme, host and port of provider URLs. May include a path, which is kept
+    /// in front of the provider path.
+    pub base_url: Option<String>,
+    /// Inserted in front of the provider path, e.g. `/openai`
+    pub path_prefix: Option<String>,
+    /// Top-level fields merged into every JSON object body, replacing fields of the same name
+    pub body_fields: serde_json::Map<String, serde_json::Value>,
+    /// Headers sent with every request, replacing provider headers of the same name
+    pub headers: Vec<(String, String)>,
+}
+
+impl Gateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    pub fn with_path_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_body_field(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.body_fields.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The URL a provider request to `url` is sent to instead.
+    pub fn rewrite_url(&self, url: &str) -> Result<String, LlmError> {
+        if self.base_url.is_none() && self.path_prefix.is_none() {
+            return Ok(url.to_string());
         }
+
+        let parsed = reqwest::Url::parse(url).map_err(|e| {
+            LlmError::ProviderConfiguration(format!("Invalid request URL '{url}': {e}"))
+        })?;
+        let origin = match &self.base_url {
+            Some(base_url) => base_url.trim_end_matches('/').to_string(),
+            None => parsed.origin().ascii_serialization(),
+        };
+        let prefix = self
+            .path_prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_matches('/'))
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| format!("/{prefix}"))
+            .unwrap_or_default();
+        let query = parsed
+            .query()
+            .map(|query| format!("?{query}"))
+            .unwrap_or_default();
+
+        Ok(format!("{origin}{prefix}{}{query}", parsed.path()))
+    }
+
+    /// `body` with the gateway's body fields merged in. Bodies that are not JSON objects are
+    /// sent unchanged.
+    fn rewrite_body(&self, mut body: serde_json::Value) -> serde_json::Value {
+        if let Some(object) = body.as_object_mut() {
+            object.extend(self.body_fields.clone());
+        }
+        body
     }
 }
 
 /// Shared HTTP client with retry logic and exponential backoff.
 pub struct HttpClient {
-    client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     config: HttpClientConfig,
     inspector_config: Option<InspectorConfig>,
 }
 
 impl HttpClient {
     /// Create a new HTTP client with the given configuration.
+    ///
+    /// Unless a custom transport is set, the underlying `reqwest` client is taken from a
+    /// process-wide pool, so HTTP clients with the same connection options share connections.
     pub fn new(
         config: HttpClientConfig,
         user_agent: Option<&str>,
         inspector_config: Option<InspectorConfig>,
     ) -> Result<Self, LlmError> {
-        let default_ua = format!("rsai/{}", env!("CARGO_PKG_VERSION"));
-        let ua = user_agent.unwrap_or(&default_ua);
+        if let Some(transport) = config.transport.clone() {
+            return Ok(Self {
+                transport,
+                config,
+                inspector_config,
+            });
+        }
 
-        let client = reqwest::Client::builder()
-            .timeout(config.timeout)
-            .user_agent(ua)
-            .build()
-            .map_err(|e| {
-                LlmError::ProviderConfiguration(format!("Failed to build reqwest client: {e}"))
-            })?;
+        let default_ua = format!("rsai/{}", env!("CARGO_PKG_VERSION"));
+        let key = ClientKey {
+            user_agent: user_agent.unwrap_or(&default_ua).to_string(),
+            timeout: config.timeout,
+            connect_timeout: config.connect_timeout,
+            idle_timeout: config.idle_timeout,
+            proxy_url: config.proxy_url.clone(),
+            no_proxy: config.no_proxy.clone(),
+            root_certificates: config.root_certificates.clone(),
+            #[cfg(feature = "danger-accept-invalid-certs")]
+            danger_accept_invalid_certs: config.danger_accept_invalid_certs,
+        };
+        let client = shared_client(&key)?;
 
         Ok(Self {
-            client,
+            transport: Arc::new(
+                ReqwestTransport::new(client).with_first_byte_timeout(config.first_byte_timeout),
+            ),
             config,
             inspector_config,
         })
     }
 
+    /// Send a single request over the transport, joining an identical in-flight request if
+    /// [`HttpClientConfig::coalesce_requests`] is set. Returns the response with the
+    /// idempotency key of the request that was sent, which is another caller's key when
+    /// joining its request.
+    async fn send(
+        &self,
+        request: TransportRequest,
+    ) -> Result<(TransportResponse, Option<String>), LlmError> {
+        let sent_key = idempotency_key(&request);
+        if !self.config.coalesce_requests {
+            return Ok((self.transport.send(request).await?, sent_key));
+        }
+
+        let key = flight_key(&request);
+        let flight = {
+            let mut in_flight = IN_FLIGHT.lock().map_err(|_| LlmError::Provider {
+                message: "In-flight request lock poisoned".to_string(),
+                source: None,
+            })?;
+            in_flight
+                .entry(key)
+                .or_insert_with(|| {
+                    let transport = self.transport.clone();
+                    async move {
+                        match transport.send(request).await {
+                            Ok(response) => Ok((response, sent_key)),
+                            Err(error) => Err(Arc::new(error)),
+                        }
+                    }
+                    .boxed()
+                    .shared()
+                })
+                .clone()
+        };
+        if flight.peek().is_none() {
+            debug!("Waiting for in-flight request");
+        }
+
+        let result = flight.clone().await;
+        // Whoever finishes first retires the flight so later requests are sent again
+        if let Ok(mut in_flight) = IN_FLIGHT.lock()
+            && in_flight
+                .get(&key)
+                .is_some_and(|current| current.ptr_eq(&flight))
+        {
+            in_flight.remove(&key);
+        }
+        result.map_err(shared_error)
+    }
+
+    /// The configured retry policy, or exponential backoff from the retry fields.
+    fn retry_policy(&self) -> Arc<dyn RetryPolicy> {
+        self.config.retry_policy.clone().unwrap_or_else(|| {
+            Arc::new(ExponentialBackoff {
+                max_retries: self.config.max_retries,
+                initial_delay: self.config.initial_retry_delay,
+                max_delay: self.config.max_retry_delay,
+            })
+        })
+    }
+
     /// Make a POST request with JSON body and retry logic.
     ///
-    /// Retries on 429 (rate limit) and 5xx errors with exponential backoff.
-    /// Fails immediately on 4xx errors (except 429).
+    /// By default retries on 429 (rate limit) and 5xx errors with exponential backoff and
+    /// fails immediately on other 4xx errors. See [`HttpClientConfig::retry_policy`].
+    pub async fn post_json<Req, Res>(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: &Req,
+    ) -> Result<Res, LlmError>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.send_json(HttpMethod::Post, url, headers, Some(body))
+            .await
+    }
+
+    /// Like [`Self::post_json`], also returning the idempotency key the response belongs to.
+    /// That is the key in `headers`, unless the request joined an identical in-flight request
+    /// with another key.
+    pub(crate) async fn post_json_idempotent<Req, Res>(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: &Req,
+    ) -> Result<(Res, Option<String>), LlmError>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.exchange(HttpMethod::Post, url, headers, Some(body))
+            .await
+    }
+
+    /// Make a request with an optional JSON body and the same retry logic as [`Self::post_json`].
+    ///
+    /// An empty response body (e.g. from a `DELETE`) is parsed as JSON `null`.
+    pub async fn send_json<Req, Res>(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        headers: &[(String, String)],
+        body: Option<&Req>,
+    ) -> Result<Res, LlmError>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned,
+    {
+        self.exchange(method, url, headers, body)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// Send a request with retries and parse the response, returned with the idempotency key
+    /// of the request that was sent.
     #[tracing::instrument(
-        name = "http_post_json",
+        name = "http_send_json",
         skip(self, headers, body),
-        fields(url = %url),
+        fields(method = ?method, url = %self.config.log_redaction.redact_text(url)),
         err
     )]
-    pub async fn post_json<Req, Res>(
+    async fn exchange<Req, Res>(
         &self,
+        method: HttpMethod,
         url: &str,
         headers: &[(String, String)],
-        body: &Req,
-    ) -> Result<Res, LlmError>
+        body: Option<&Req>,
+    ) -> Result<(Res, Option<String>), LlmError>
     where
         Req: Serialize,
         Res: DeserializeOwned,
     {
         // Serialize request to Value for inspection
-        let body_value = serde_json::to_value(body).map_err(|e| LlmError::Parse {
-            message: "Failed to serialize request for inspection".to_string(),
-            source: Box::new(e),
-        })?;
+        let body_value = body
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(|e| LlmError::Parse {
+                message: "Failed to serialize request for inspection".to_string(),
+                source: Box::new(e),
+            })?
+            .unwrap_or(serde_json::Value::Null);
+
+        let (url, body_value, headers) = match &self.config.gateway {
+            Some(gateway) => (
+                gateway.rewrite_url(url)?,
+                gateway.rewrite_body(body_value),
+                merge_headers(headers, &gateway.headers),
+            ),
+            None => (url.to_string(), body_value, headers.to_vec()),
+        };
+        let headers = merge_headers(&headers, &self.config.headers);
+        let redactor = self.config.log_redaction.with_headers(&headers);
 
         // Call request inspector
         if let Some(ref config) = self.inspector_config
             && let Some(ref inspector) = config.request_inspector
+            && !body_value.is_null()
         {
-            inspector(&body_value);
+            inspector(&redactor.redact_value(&body_value));
         }
+        let policy = self.retry_policy();
+        let mut attempt = 0;
 
-        let mut last_error: Option<LlmError> = None;
-
-        for attempt in 0..=self.config.max_retries {
-            // Build request (must be rebuilt each attempt since .send() consumes it)
-            let mut req_builder = self.client.post(url).json(&body_value);
-
-            // Add headers
-            for (name, value) in headers {
-                req_builder = req_builder.header(name, value);
-            }
+        loop {
+            let headers = match &self.config.credentials {
+                Some(credentials) => merge_headers(&headers, &credentials.auth_headers().await?),
+                None => headers.clone(),
+            };
+            let request = TransportRequest {
+                method,
+                url: url.clone(),
+                headers,
+                body: body_value.clone(),
+            };
 
-            match req_builder.send().await {
-                Err(e) => {
-                    warn!(attempt, error = %e, "HTTP request failed, retrying");
-                    last_error = Some(LlmError::Network {
-                        message: format!(
-                            "Request failed (attempt {}/{})",
-                            attempt + 1,
-                            self.config.max_retries + 1
-                        ),
-                        source: Box::new(e),
-                    });
+            let error = match self.send(request).await {
+                Err(LlmError::Network { source, .. }) => {
+                    warn!(attempt, error = %source, "HTTP request failed");
+                    LlmError::Network {
+                        message: format!("Request failed (attempt {})", attempt + 1),
+                        source,
+                    }
                 }
-                Ok(res) => {
-                    let status = res.status();
+                Err(e) => return Err(e),
+                Ok((res, sent_key)) => {
+                    let status =
+                        reqwest::StatusCode::from_u16(res.status).map_err(|e| LlmError::Api {
+                            message: format!("Invalid HTTP status code: {}", res.status),
+                            status_code: Some(res.status),
+                            source: Some(Box::new(e)),
+                        })?;
 
                     // Success
                     if status.is_success() {
                         debug!(status = %status, "HTTP request successful");
 
-                        // Parse response to text first, then to Value for inspection
-                        let response_text = res.text().await.map_err(|e| LlmError::Parse {
-                            message: "Failed to read response body".to_string(),
-                            source: Box::new(e),
-                        })?;
-
-                        let response_value: serde_json::Value =
-                            serde_json::from_str(&response_text).map_err(|e| LlmError::Parse {
+                        let response_value: serde_json::Value = if res.body.trim().is_empty() {
+                            serde_json::Value::Null
+                        } else {
+                            serde_json::from_str(&res.body).map_err(|e| LlmError::Parse {
                                 message: "Failed to parse response as JSON".to_string(),
                                 source: Box::new(e),
-                            })?;
+                            })?
+                        };
 
                         // Call response inspector
                         if let Some(ref config) = self.inspector_config
                             && let Some(ref inspector) = config.response_inspector
                         {
-                            inspector(&response_value);
+                            inspector(&redactor.redact_value(&response_value));
                         }
 
                         // Deserialize to target type
-                        return serde_json::from_value(response_value).map_err(|e| {
-                            LlmError::Parse {
+                        return serde_json::from_value(response_value)
+                            .map(|response| (response, sent_key))
+                            .map_err(|e| LlmError::Parse {
                                 message: "Failed to parse API response".to_string(),
                                 source: Box::new(e),
-                            }
-                        });
+                            });
                     }
 
                     warn!(attempt, status = %status, "API returned error status");
 
-                    let is_retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS
-                        || status.is_server_error();
-                    let error_text = res
-                        .text()
-                        .await
-                        .unwrap_or_else(|_| "Unknown error".to_string());
+                    let error_text = if res.body.is_empty() {
+                        "Unknown error".to_string()
+                    } else {
+                        redactor.redact_text(&res.body)
+                    };
 
                     // Call response inspector for error responses
                     if let Some(ref config) = self.inspector_config
@@ -173,51 +538,608 @@ impl HttpClient {
                                 "status_code": status.as_u16()
                             })
                         });
-                        inspector(&error_value);
-                    }
-
-                    if !is_retryable {
-                        // Fatal errors - don't retry
-                        return Err(LlmError::Api {
-                            message: format!("Fatal API Error: {error_text}"),
-                            status_code: Some(status.as_u16()),
-                            source: None,
-                        });
+                        inspector(&redactor.redact_value(&error_value));
                     }
 
-                    // Retryable error - capture and continue
-                    last_error = Some(LlmError::Api {
-                        message: format!("Transient API error ({}): {}", status, error_text),
+                    let message = if is_transient_status(status.as_u16()) {
+                        format!("Transient API error ({}): {}", status, error_text)
+                    } else {
+                        format!("Fatal API Error: {error_text}")
+                    };
+                    LlmError::Api {
+                        message,
                         status_code: Some(status.as_u16()),
                         source: None,
-                    });
+                    }
                 }
+            };
+
+            let Some(delay) = policy.should_retry(&error, attempt) else {
+                return Err(error);
+            };
+            debug!(attempt, ?delay, "Retrying HTTP request");
+            super::timings::record_retry();
+            super::runtime::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Append `custom` headers to the provider headers, replacing any with the same name.
+fn merge_headers(
+    provider: &[(String, String)],
+    custom: &[(String, String)],
+) -> Vec<(String, String)> {
+    provider
+        .iter()
+        .filter(|(name, _)| {
+            !custom
+                .iter()
+                .any(|(custom_name, _)| custom_name.eq_ignore_ascii_case(name))
+        })
+        .chain(custom)
+        .cloned()
+        .collect()
+}
+
+/// An HTTP call shared by concurrent identical requests, resolving to the response and the
+/// idempotency key that was sent. Errors are behind an `Arc` because every caller gets a copy
+/// of the result.
+type Flight =
+    Shared<BoxFuture<'static, Result<(TransportResponse, Option<String>), Arc<LlmError>>>>;
+
+/// Identity of a request for coalescing, see [`flight_key`].
+type FlightKey = (u64, u64);
+
+static IN_FLIGHT: LazyLock<Mutex<HashMap<FlightKey, Flight>>> = LazyLock::new(Default::default);
+
+/// Two independently keyed SipHash hashers, salted randomly per process.
+static FLIGHT_HASHERS: LazyLock<(RandomState, RandomState)> = LazyLock::new(Default::default);
+
+/// Identity of a request for coalescing: a salted digest of the method, URL, headers and
+/// body, so that the process-wide map doesn't hold API keys or prompts. Idempotency keys
+/// differ between otherwise identical requests, so they are left out.
+fn flight_key(request: &TransportRequest) -> FlightKey {
+    let headers = request
+        .headers
+        .iter()
+        .filter(|(name, _)| !name.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+        .collect::<Vec<_>>();
+    let identity = format!(
+        "{:?} {}\n{:?}\n{}",
+        request.method, request.url, headers, request.body
+    );
+    let (first, second) = &*FLIGHT_HASHERS;
+    (first.hash_one(&identity), second.hash_one(&identity))
+}
+
+/// Header carrying the [idempotency key](HttpClientConfig::idempotency_keys) of a request.
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The idempotency key `request` is sent with, if any.
+fn idempotency_key(request: &TransportRequest) -> Option<String> {
+    request
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+        .map(|(_, key)| key.clone())
+}
+
+/// A random idempotency key.
+pub(crate) fn generate_idempotency_key() -> String {
+    format!("rsai-{:032x}", rand::random::<u128>())
+}
+
+/// A copy of an error returned to every caller of a shared call. Network and API errors keep
+/// their variant and status code so that each caller still handles and retries them.
+fn shared_error(error: Arc<LlmError>) -> LlmError {
+    Arc::try_unwrap(error).unwrap_or_else(|shared| {
+        let message = shared.to_string();
+        match *shared {
+            LlmError::Network { .. } => LlmError::Network {
+                message,
+                source: Box::new(shared),
+            },
+            LlmError::Api {
+                ref message,
+                status_code,
+                ..
+            } => LlmError::Api {
+                message: message.clone(),
+                status_code,
+                source: Some(Box::new(shared.clone())),
+            },
+            _ => LlmError::Provider {
+                message,
+                source: Some(Box::new(shared)),
+            },
+        }
+    })
+}
+
+/// Options that shape a `reqwest` client. Clients are shared between all HTTP clients with
+/// the same options, so connections and TLS sessions are reused across calls.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientKey {
+    user_agent: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    proxy_url: Option<String>,
+    no_proxy: Option<String>,
+    root_certificates: Vec<PathBuf>,
+    #[cfg(feature = "danger-accept-invalid-certs")]
+    danger_accept_invalid_certs: bool,
+}
+
+static CLIENTS: LazyLock<Mutex<HashMap<ClientKey, reqwest::Client>>> =
+    LazyLock::new(Default::default);
+
+/// The pooled `reqwest` client for `key`, built on first use.
+///
+/// Root certificates are read when the client is built; replacing a certificate file does
+/// not affect a client that is already pooled.
+fn shared_client(key: &ClientKey) -> Result<reqwest::Client, LlmError> {
+    let mut clients = CLIENTS.lock().map_err(|_| {
+        LlmError::ProviderConfiguration("HTTP client pool lock poisoned".to_string())
+    })?;
+    if let Some(client) = clients.get(key) {
+        return Ok(client.clone());
+    }
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(key.timeout)
+        .user_agent(&key.user_agent);
+    if let Some(timeout) = key.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = key.idle_timeout {
+        builder = builder.read_timeout(timeout);
+    }
+
+    if let Some(proxy_url) = &key.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| {
+                LlmError::ProviderConfiguration(format!("Invalid proxy URL '{proxy_url}': {e}"))
+            })?
+            .no_proxy(
+                key.no_proxy
+                    .as_deref()
+                    .and_then(reqwest::NoProxy::from_string),
+            );
+        builder = builder.proxy(proxy);
+    }
+
+    for path in &key.root_certificates {
+        builder = builder.add_root_certificate(load_root_certificate(path)?);
+    }
+
+    #[cfg(feature = "danger-accept-invalid-certs")]
+    if key.danger_accept_invalid_certs {
+        warn!("TLS certificate validation is disabled");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    let client = builder.build().map_err(|e| {
+        LlmError::ProviderConfiguration(format!("Failed to build reqwest client: {e}"))
+    })?;
+    clients.insert(key.clone(), client.clone());
+    Ok(client)
+}
+
+/// Read a PEM-encoded certificate from disk.
+fn load_root_certificate(path: &PathBuf) -> Result<reqwest::Certificate, LlmError> {
+    let pem = std::fs::read(path).map_err(|e| {
+        LlmError::ProviderConfiguration(format!(
+            "Failed to read root certificate '{}': {e}",
+            path.display()
+        ))
+    })?;
+
+    reqwest::Certificate::from_pem(&pem).map_err(|e| {
+        LlmError::ProviderConfiguration(format!(
+            "Invalid root certificate '{}': {e}",
+            path.display()
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::transport::TransportResponse;
+
+    #[test]
+    fn test_proxy_config_builds_client() {
+        let config = HttpClientConfig {
+            proxy_url: Some("http://127.0.0.1:8080".to_string()),
+            no_proxy: Some("localhost,127.0.0.1".to_string()),
+            ..Default::default()
+        };
+
+        assert!(HttpClient::new(config, None, None).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_proxy_url_errors() {
+        let config = HttpClientConfig {
+            proxy_url: Some("not a url".to_string()),
+            ..Default::default()
+        };
+
+        match HttpClient::new(config, None, None) {
+            Err(LlmError::ProviderConfiguration(message)) => {
+                assert!(message.contains("Invalid proxy URL"))
             }
+            Err(other) => panic!("expected configuration error, got {other:?}"),
+            Ok(_) => panic!("expected configuration error"),
+        }
+    }
 
-            // Exponential backoff with jitter
-            if attempt < self.config.max_retries {
-                let base_delay =
-                    self.config.initial_retry_delay.as_millis() as f64 * 2_f64.powi(attempt as i32);
+    #[test]
+    fn test_merge_headers_overrides_case_insensitively() {
+        let provider = vec![
+            ("Authorization".to_string(), "Bearer key".to_string()),
+            ("X-Title".to_string(), "default".to_string()),
+        ];
+        let custom = vec![
+            ("x-title".to_string(), "custom".to_string()),
+            ("OpenAI-Organization".to_string(), "org-123".to_string()),
+        ];
 
-                // +/- 10% jitter (0.9 to 1.1)
-                let jitter_factor = rand::random::<f64>() * 0.2 + 0.9;
-                let delay_ms = (base_delay * jitter_factor) as u64;
+        assert_eq!(
+            merge_headers(&provider, &custom),
+            vec![
+                ("Authorization".to_string(), "Bearer key".to_string()),
+                ("x-title".to_string(), "custom".to_string()),
+                ("OpenAI-Organization".to_string(), "org-123".to_string()),
+            ]
+        );
+    }
 
-                // Cap delay at max
-                let delay =
-                    std::time::Duration::from_millis(delay_ms).min(self.config.max_retry_delay);
+    #[test]
+    fn test_missing_root_certificate_errors() {
+        let config = HttpClientConfig {
+            root_certificates: vec![PathBuf::from("/nonexistent/rsai-test-ca.pem")],
+            ..Default::default()
+        };
 
-                tokio::time::sleep(delay).await;
+        match HttpClient::new(config, None, None) {
+            Err(LlmError::ProviderConfiguration(message)) => {
+                assert!(message.contains("Failed to read root certificate"))
             }
+            Err(other) => panic!("expected configuration error, got {other:?}"),
+            Ok(_) => panic!("expected configuration error"),
         }
+    }
 
-        Err(last_error.unwrap_or_else(|| LlmError::Api {
-            message: format!(
-                "Request failed after max retries ({}) with unknown error",
-                self.config.max_retries
-            ),
-            status_code: None,
+    #[test]
+    fn test_clients_with_same_options_share_a_pooled_client() {
+        let user_agent = "rsai-pool-test";
+        let pooled = || {
+            CLIENTS
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|key| key.user_agent == user_agent)
+                .count()
+        };
+
+        HttpClient::new(HttpClientConfig::default(), Some(user_agent), None).unwrap();
+        HttpClient::new(HttpClientConfig::default(), Some(user_agent), None).unwrap();
+        assert_eq!(pooled(), 1);
+
+        let slow = HttpClientConfig {
+            timeout: Duration::from_secs(600),
+            ..Default::default()
+        };
+        HttpClient::new(slow, Some(user_agent), None).unwrap();
+        assert_eq!(pooled(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_first_byte_timeout_fails_before_total_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // Accept connections but never answer
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let config = HttpClientConfig {
+            first_byte_timeout: Some(Duration::from_millis(100)),
+            max_retries: 0,
+            ..Default::default()
+        };
+        let client = HttpClient::new(config, Some("rsai-first-byte-test"), None).unwrap();
+
+        let started = std::time::Instant::now();
+        let result = client
+            .post_json::<_, serde_json::Value>(&url, &[], &serde_json::json!({}))
+            .await;
+        assert!(matches!(result, Err(LlmError::Network { .. })));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    struct EchoTransport;
+
+    #[async_trait::async_trait]
+    impl Transport for EchoTransport {
+        async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+            Ok(TransportResponse {
+                status: 200,
+                body: serde_json::json!({ "echo": request.headers }).to_string(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inspectors_see_redacted_payloads() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (requests, responses) = (seen.clone(), seen.clone());
+        let inspectors = InspectorConfig {
+            request_inspector: Some(Arc::new(move |body| {
+                requests.lock().unwrap().push(body.clone())
+            })),
+            response_inspector: Some(Arc::new(move |body| {
+                responses.lock().unwrap().push(body.clone())
+            })),
+        };
+        let config = HttpClientConfig {
+            transport: Some(Arc::new(EchoTransport)),
+            ..Default::default()
+        };
+        let client = HttpClient::new(config, None, Some(inspectors)).unwrap();
+
+        let _: serde_json::Value = client
+            .post_json(
+                "https://example.com",
+                &[("x-goog-api-key".to_string(), "secret-key-123".to_string())],
+                &serde_json::json!({ "prompt": "my key is secret-key-123" }),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                serde_json::json!({ "prompt": "my key is [REDACTED]" }),
+                serde_json::json!({ "echo": [["x-goog-api-key", "[REDACTED]"]] }),
+            ]
+        );
+    }
+
+    /// Transport that counts sends and answers slowly so that requests overlap.
+    struct SlowTransport {
+        sends: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for SlowTransport {
+        async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+            self.sends.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(TransportResponse {
+                status: 200,
+                body: request.body.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_shared_errors_keep_their_status_code() {
+        let error = Arc::new(LlmError::Api {
+            message: "Rate limited".to_string(),
+            status_code: Some(429),
             source: None,
-        }))
+        });
+        let _other_caller = error.clone();
+        assert!(matches!(
+            shared_error(error),
+            LlmError::Api {
+                status_code: Some(429),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_identical_requests_are_coalesced() {
+        let transport = Arc::new(SlowTransport {
+            sends: Default::default(),
+        });
+        let config = HttpClientConfig {
+            transport: Some(transport.clone()),
+            coalesce_requests: true,
+            ..Default::default()
+        };
+        let client = HttpClient::new(config, None, None).unwrap();
+        let post = |prompt: &'static str| {
+            let client = &client;
+            async move {
+                client
+                    .post_json::<_, serde_json::Value>(
+                        "https://example.com/coalesce",
+                        &[],
+                        &serde_json::json!({ "prompt": prompt }),
+                    )
+                    .await
+            }
+        };
+
+        let (a, b, c, other) =
+            tokio::join!(post("same"), post("same"), post("same"), post("other"));
+        assert_eq!(a.unwrap(), serde_json::json!({ "prompt": "same" }));
+        assert_eq!(b.unwrap(), c.unwrap());
+        assert_eq!(other.unwrap(), serde_json::json!({ "prompt": "other" }));
+        assert_eq!(transport.sends.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // A finished call is not reused
+        post("same").await.unwrap();
+        assert_eq!(transport.sends.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // Joined requests report the idempotency key that was sent
+        let post_with_key = |key: &'static str| {
+            let client = &client;
+            async move {
+                client
+                    .post_json_idempotent::<_, serde_json::Value>(
+                        "https://example.com/coalesce",
+                        &[(IDEMPOTENCY_KEY_HEADER.to_string(), key.to_string())],
+                        &serde_json::json!({ "prompt": "keyed" }),
+                    )
+                    .await
+                    .unwrap()
+                    .1
+            }
+        };
+        let (first, joined) = tokio::join!(post_with_key("key-1"), post_with_key("key-2"));
+        assert_eq!(first.as_deref(), Some("key-1"));
+        assert_eq!(joined.as_deref(), Some("key-1"));
+        assert_eq!(transport.sends.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_asked_before_every_attempt() {
+        struct Counter(std::sync::atomic::AtomicU32);
+
+        #[async_trait::async_trait]
+        impl CredentialsProvider for Counter {
+            async fn auth_headers(&self) -> Result<Vec<(String, String)>, LlmError> {
+                let n = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                Ok(vec![("authorization".to_string(), format!("Bearer {n}"))])
+            }
+        }
+
+        /// Fails the first attempt and echoes the headers of the second.
+        struct FlakyTransport(std::sync::atomic::AtomicU32);
+
+        #[async_trait::async_trait]
+        impl Transport for FlakyTransport {
+            async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+                if self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                    return Ok(TransportResponse {
+                        status: 503,
+                        body: String::new(),
+                    });
+                }
+                Ok(TransportResponse {
+                    status: 200,
+                    body: serde_json::to_string(&request.headers).unwrap(),
+                })
+            }
+        }
+
+        let config = HttpClientConfig {
+            transport: Some(Arc::new(FlakyTransport(Default::default()))),
+            credentials: Some(Arc::new(Counter(Default::default()))),
+            initial_retry_delay: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let client = HttpClient::new(config, None, None).unwrap();
+
+        let headers: Vec<(String, String)> = client
+            .post_json(
+                "https://example.com/credentials",
+                &[("Authorization".to_string(), "Bearer ".to_string())],
+                &serde_json::json!({}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            headers,
+            vec![("authorization".to_string(), "Bearer 2".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gateway_rewrites_url_body_and_headers() {
+        /// Echoes the URL, headers and body it was sent.
+        struct RequestEcho;
+
+        #[async_trait::async_trait]
+        impl Transport for RequestEcho {
+            async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+                Ok(TransportResponse {
+                    status: 200,
+                    body: serde_json::json!({
+                        "url": request.url,
+                        "headers": request.headers,
+                        "body": request.body,
+                    })
+                    .to_string(),
+                })
+            }
+        }
+
+        let gateway = Gateway::new()
+            .with_base_url("https://gateway.example.com/llm/")
+            .with_path_prefix("/openai/")
+            .with_body_field("user", "team-search")
+            .with_body_field("metadata", serde_json::json!({ "cost_center": "42" }))
+            .with_header("x-gateway-key", "gw-secret")
+            .with_header("authorization", "Bearer gateway");
+        let config = HttpClientConfig {
+            transport: Some(Arc::new(RequestEcho)),
+            gateway: Some(gateway),
+            headers: vec![("X-Gateway-Key".to_string(), "override".to_string())],
+            ..Default::default()
+        };
+        let client = HttpClient::new(config, None, None).unwrap();
+
+        let echo: serde_json::Value = client
+            .post_json(
+                "https://api.openai.com/v1/responses?stream=false",
+                &[("Authorization".to_string(), "Bearer provider".to_string())],
+                &serde_json::json!({ "model": "gpt-4o", "user": "alice" }),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            echo["url"],
+            "https://gateway.example.com/llm/openai/v1/responses?stream=false"
+        );
+        assert_eq!(
+            echo["body"],
+            serde_json::json!({
+                "model": "gpt-4o",
+                "user": "team-search",
+                "metadata": { "cost_center": "42" },
+            })
+        );
+        let headers: Vec<(String, String)> =
+            serde_json::from_value(echo["headers"].clone()).unwrap();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(header("authorization"), ["Bearer gateway"]);
+        assert_eq!(header("x-gateway-key"), ["override"]);
+    }
+
+    #[test]
+    fn test_gateway_path_prefix_keeps_provider_host() {
+        let gateway = Gateway::new().with_path_prefix("kong");
+        assert_eq!(
+            gateway
+                .rewrite_url("https://generativelanguage.googleapis.com/v1beta/models/x")
+                .unwrap(),
+            "https://generativelanguage.googleapis.com/kong/v1beta/models/x"
+        );
+        assert_eq!(
+            Gateway::new().rewrite_url("not a url").unwrap(),
+            "not a url"
+        );
+        assert!(matches!(
+            gateway.rewrite_url("not a url"),
+            Err(LlmError::ProviderConfiguration(_))
+        ));
     }
 }
@@ -0,0 +1,303 @@
+//! Structured answers that are not exactly one JSON value.
+//!
+//! Repairs for almost-JSON answers of weaker models are enabled with
+//! [`LlmBuilder::lenient_json`](crate::LlmBuilder::lenient_json). They accept what JSON5 allows
+//! on top of JSON and models commonly produce: comments, trailing commas, unquoted keys and
+//! single-quoted strings, as well as an answer wrapped in a markdown code fence.
+//!
+//! JSON embedded in prose is found by [`embedded_values`] when parsing, unless
+//! [`LlmBuilder::strict_json`](crate::LlmBuilder::strict_json) rejects such answers first.
+
+use serde_json::Value;
+
+use super::error::LlmError;
+use super::types::{ProviderResponse, ResponseContent};
+
+/// Replace a structured answer that is not valid JSON with its repaired form, marking the
+/// response as [`lenient_json`](ProviderResponse::lenient_json). Answers that can't be
+/// repaired are left for the parser to reject.
+pub(crate) fn apply(response: &mut ProviderResponse) {
+    let ResponseContent::Text(text) = &response.content else {
+        return;
+    };
+    if serde_json::from_str::<Value>(text).is_ok() {
+        return;
+    }
+    if let Some(repaired) = repair(text) {
+        tracing::debug!("Repaired structured output with lenient JSON parsing");
+        response.content = ResponseContent::Text(repaired);
+        response.lenient_json = true;
+    }
+}
+
+/// Fail unless a structured answer is exactly one JSON value.
+pub(crate) fn require_json(response: &ProviderResponse) -> Result<(), LlmError> {
+    let ResponseContent::Text(text) = &response.content else {
+        return Ok(());
+    };
+    serde_json::from_str::<Value>(text)
+        .map(|_| ())
+        .map_err(|e| LlmError::Parse {
+            message: "Structured output is not a single JSON value".to_string(),
+            source: Box::new(e),
+        })
+}
+
+/// The JSON objects and arrays embedded in `text`, e.g. after some prose or in a code fence,
+/// in the order they appear. Brackets inside a found value are not searched again.
+pub(crate) fn embedded_values(text: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = text[from..].find(['{', '[']) {
+        let start = from + offset;
+        let mut stream = serde_json::Deserializer::from_str(&text[start..]).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) => {
+                values.push(value);
+                from = start + stream.byte_offset();
+            }
+            _ => from = start + 1,
+        }
+    }
+    values
+}
+
+/// The strict JSON form of `text`, or `None` if it isn't valid even after the repairs.
+pub(crate) fn repair(text: &str) -> Option<String> {
+    let repaired = normalize(strip_fence(text));
+    serde_json::from_str::<Value>(&repaired).ok()?;
+    Some(repaired)
+}
+
+/// The content of a code fence around the whole text, e.g. ```` ```json ... ``` ````.
+fn strip_fence(text: &str) -> &str {
+    let text = text.trim();
+    let Some(inner) = text
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return text;
+    };
+    // Drop the language tag on the opening line
+    match inner.split_once('\n') {
+        Some((tag, body)) if !tag.trim_start().starts_with(['{', '[']) => body,
+        _ => inner,
+    }
+}
+
+/// Rewrite JSON5-style syntax to JSON, leaving the content of strings untouched.
+fn normalize(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '"' | '\'' => i = copy_string(&chars, i, &mut out),
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            ',' if closes_next(&chars, i + 1) => i += 1,
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                if is_key(&chars, i) {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(&word);
+                }
+            }
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Copy the string starting at `start` as a double-quoted JSON string and return the index
+/// after it.
+fn copy_string(chars: &[char], start: usize, out: &mut String) -> usize {
+    let quote = chars[start];
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() && chars[i] != quote {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'\'') => {
+                out.push('\'');
+                i += 2;
+                continue;
+            }
+            '\\' => {
+                out.push('\\');
+                if let Some(&escaped) = chars.get(i + 1) {
+                    out.push(escaped);
+                }
+                i += 2;
+                continue;
+            }
+            '"' => out.push_str("\\\""),
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    out.push('"');
+    i + 1
+}
+
+/// Whether the next significant character from `from` closes an object or array.
+fn closes_next(chars: &[char], from: usize) -> bool {
+    let mut i = from;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            c => return c == '}' || c == ']',
+        }
+    }
+    false
+}
+
+/// Whether a bare word ending before `from` is an object key, i.e. followed by a colon.
+fn is_key(chars: &[char], from: usize) -> bool {
+    chars[from..]
+        .iter()
+        .find(|c| !c.is_whitespace())
+        .is_some_and(|&c| c == ':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, Sum, mock_openai, responses_answer};
+    use crate::{ChatRole, Message, TextResponse};
+    use serde_json::json;
+
+    fn parsed(text: &str) -> Value {
+        serde_json::from_str(&repair(text).expect("repairable")).unwrap()
+    }
+
+    #[test]
+    fn test_repairs_json5_syntax() {
+        let text = r#"{
+            // the city
+            city: 'Lisbon',
+            "tags": ['old town', "it's sunny",], /* trailing */
+            quote: 'say "hi"',
+            visited: true,
+        }"#;
+        assert_eq!(
+            parsed(text),
+            json!({
+                "city": "Lisbon",
+                "tags": ["old town", "it's sunny"],
+                "quote": "say \"hi\"",
+                "visited": true
+            })
+        );
+    }
+
+    #[test]
+    fn test_strips_code_fences_and_keeps_string_content() {
+        let text = "```json\n{\"url\": \"https://example.com/a,}\", \"n\": 1,}\n```";
+        assert_eq!(
+            parsed(text),
+            json!({ "url": "https://example.com/a,}", "n": 1 })
+        );
+        assert_eq!(parsed("```[1, 2,]```"), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_finds_every_embedded_value() {
+        let text = "Example: {\"sum\": 0}. Answer:\n```json\n{\"sum\": [3]}\n```\n[oops";
+        assert_eq!(
+            embedded_values(text),
+            vec![json!({ "sum": 0 }), json!({ "sum": [3] })]
+        );
+        assert!(embedded_values("no json here").is_empty());
+    }
+
+    #[test]
+    fn test_prose_is_not_repaired() {
+        assert_eq!(repair("The answer is 42"), None);
+    }
+
+    #[tokio::test]
+    async fn test_lenient_json_repairs_almost_json_answers() {
+        let transport =
+            ScriptedTransport::answering(responses_answer("```json\n{ sum: 3, // 1 + 2\n}\n```"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        };
+
+        let response = builder()
+            .lenient_json(true)
+            .complete::<Sum>()
+            .await
+            .unwrap();
+        assert_eq!(response.content.sum, 3);
+        assert!(response.metadata.lenient_json);
+
+        let err = builder().complete::<Sum>().await.unwrap_err();
+        assert!(matches!(err, LlmError::Parse { .. }), "{err:?}");
+
+        // Text answers are never rewritten
+        let text = builder()
+            .lenient_json(true)
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert!(text.text.starts_with("```json"));
+        assert!(!text.metadata.lenient_json);
+    }
+
+    #[tokio::test]
+    async fn test_strict_json_rejects_json_wrapped_in_prose() {
+        let transport = ScriptedTransport::answering(responses_answer(
+            "Here you go:\n```json\n{\"sum\": 3}\n```",
+        ));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        };
+
+        let response = builder().complete::<Sum>().await.unwrap();
+        assert_eq!(response.content.sum, 3);
+        assert!(!response.metadata.lenient_json);
+
+        let err = builder()
+            .strict_json(true)
+            .complete::<Sum>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Parse { .. }), "{err:?}");
+    }
+}
@@ -0,0 +1,297 @@
+//! Accessors for the markdown structure of text answers.
+
+use super::types::TextResponse;
+
+/// A fenced code block of a text answer, see [`TextResponse::code_blocks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeBlock<'a> {
+    /// First word of the info string after the opening fence, e.g. `rust` for ```` ```rust ````
+    pub language: Option<&'a str>,
+    /// Lines between the fences
+    pub code: &'a str,
+}
+
+/// A part of a text answer under one heading, see [`TextResponse::sections`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    /// Heading text without the `#` markers, `None` for text before the first heading
+    pub heading: Option<&'a str>,
+    /// Number of `#` markers, 0 for text before the first heading
+    pub level: u8,
+    /// Text up to the next heading, trimmed
+    pub body: &'a str,
+}
+
+impl TextResponse {
+    /// The fenced code blocks (```` ``` ```` or `~~~`) of the answer, in order. A block without
+    /// a closing fence runs to the end of the answer.
+    pub fn code_blocks(&self) -> Vec<CodeBlock<'_>> {
+        let mut blocks = Vec::new();
+        let mut open: Option<(Fence, Option<&str>, usize)> = None;
+        for (start, next, line) in lines(&self.text) {
+            match open {
+                Some((fence, language, code_start)) if fence.closes(line) => {
+                    blocks.push(CodeBlock {
+                        language,
+                        code: code(&self.text, code_start, start),
+                    });
+                    open = None;
+                }
+                Some(_) => {}
+                None => {
+                    if let Some((fence, info)) = Fence::opening(line) {
+                        let language = info.split_whitespace().next();
+                        open = Some((fence, language, next));
+                    }
+                }
+            }
+        }
+        if let Some((_, language, code_start)) = open {
+            blocks.push(CodeBlock {
+                language,
+                code: code(&self.text, code_start, self.text.len()),
+            });
+        }
+        blocks
+    }
+
+    /// The answer split at its `#` headings. Text before the first heading is a section
+    /// without heading if it isn't blank; `#` lines inside code blocks are not headings.
+    pub fn sections(&self) -> Vec<Section<'_>> {
+        let mut sections = Vec::new();
+        let mut current = (None, 0, 0);
+        let mut fence: Option<Fence> = None;
+        for (start, next, line) in lines(&self.text) {
+            match fence {
+                Some(open) if open.closes(line) => fence = None,
+                Some(_) => {}
+                None => {
+                    if let Some((open, _)) = Fence::opening(line) {
+                        fence = Some(open);
+                    } else if let Some((level, heading)) = heading(line) {
+                        push_section(&mut sections, &self.text, current, start);
+                        current = (Some(heading), level, next);
+                    }
+                }
+            }
+        }
+        push_section(&mut sections, &self.text, current, self.text.len());
+        sections
+    }
+
+    /// The sentences of the answer, split after `.`, `!` or `?` followed by whitespace and at
+    /// blank lines. Abbreviations such as "e.g." end a sentence too.
+    pub fn sentences(&self) -> Vec<&str> {
+        let text = self.text.as_str();
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let next = chars.peek().map(|&(_, next)| next);
+            let end = match c {
+                '.' | '!' | '?' => next.is_none_or(char::is_whitespace),
+                '\n' => next == Some('\n') || text[i + 1..].starts_with("\r\n"),
+                _ => false,
+            };
+            if end {
+                let sentence = text[start..=i].trim();
+                if !sentence.is_empty() {
+                    sentences.push(sentence);
+                }
+                start = i + c.len_utf8();
+            }
+        }
+        let rest = text[start..].trim();
+        if !rest.is_empty() {
+            sentences.push(rest);
+        }
+        sentences
+    }
+}
+
+/// Lines of `text` without line endings, with the byte offsets of their start and of the
+/// next line.
+fn lines(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    text.split('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, *offset, line.strip_suffix('\r').unwrap_or(line)))
+    })
+}
+
+/// `text[start..end]` without the line ending before `end`, empty if the range is.
+fn code(text: &str, start: usize, end: usize) -> &str {
+    let start = start.min(end);
+    let code = &text[start..end];
+    code.strip_suffix('\n')
+        .map(|code| code.strip_suffix('\r').unwrap_or(code))
+        .unwrap_or(code)
+}
+
+fn push_section<'a>(
+    sections: &mut Vec<Section<'a>>,
+    text: &'a str,
+    (heading, level, start): (Option<&'a str>, u8, usize),
+    end: usize,
+) {
+    let body = text[start.min(end)..end].trim();
+    if heading.is_some() || !body.is_empty() {
+        sections.push(Section {
+            heading,
+            level,
+            body,
+        });
+    }
+}
+
+/// Level and text of an ATX heading such as `## Setup`, allowing up to three spaces of
+/// indentation.
+fn heading(line: &str) -> Option<(u8, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+    let rest = &trimmed[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // A closing sequence of `#` is not part of the heading
+    let text = rest.trim();
+    let text = match text.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => text,
+    };
+    Some((level as u8, text))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fence {
+    marker: u8,
+    len: usize,
+}
+
+impl Fence {
+    /// The fence opened by `line` and its info string.
+    fn opening(line: &str) -> Option<(Self, &str)> {
+        let trimmed = line.trim_start_matches(' ');
+        if line.len() - trimmed.len() > 3 {
+            return None;
+        }
+        let marker = *trimmed.as_bytes().first()?;
+        if marker != b'`' && marker != b'~' {
+            return None;
+        }
+        let len = trimmed.bytes().take_while(|&b| b == marker).count();
+        let info = trimmed[len..].trim();
+        // Backtick fences can't have backticks in their info string
+        if len < 3 || (marker == b'`' && info.contains('`')) {
+            return None;
+        }
+        Some((Self { marker, len }, info))
+    }
+
+    fn closes(self, line: &str) -> bool {
+        let trimmed = line.trim();
+        trimmed.len() >= self.len && trimmed.bytes().all(|b| b == self.marker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LanguageModelUsage, Provider, ResponseMetadata};
+
+    fn response(text: &str) -> TextResponse {
+        TextResponse {
+            text: text.to_string(),
+            usage: LanguageModelUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                cached_tokens: None,
+            },
+            metadata: ResponseMetadata::new(Provider::OpenAI, "mock-model", "resp_1"),
+        }
+    }
+
+    #[test]
+    fn test_code_blocks_with_languages() {
+        let answer = response(
+            "Install it:\n```sh\ncargo add rsai\n```\nThen:\n~~~~\nlet x = 1;\n```\nstill code\n~~~~\n```python title=\"a.py\"\nprint(1)",
+        );
+        assert_eq!(
+            answer.code_blocks(),
+            vec![
+                CodeBlock {
+                    language: Some("sh"),
+                    code: "cargo add rsai",
+                },
+                CodeBlock {
+                    language: None,
+                    code: "let x = 1;\n```\nstill code",
+                },
+                CodeBlock {
+                    language: Some("python"),
+                    code: "print(1)",
+                },
+            ]
+        );
+        assert_eq!(
+            response("```\n```").code_blocks(),
+            vec![CodeBlock {
+                language: None,
+                code: "",
+            }]
+        );
+    }
+
+    #[test]
+    fn test_sections_split_at_headings_outside_code() {
+        let answer = response(
+            "Intro text.\n\n# Setup\nRun this:\n```sh\n# not a heading\n```\n## Usage ##\nCall it.\n#hashtag\n### Empty",
+        );
+        assert_eq!(
+            answer.sections(),
+            vec![
+                Section {
+                    heading: None,
+                    level: 0,
+                    body: "Intro text.",
+                },
+                Section {
+                    heading: Some("Setup"),
+                    level: 1,
+                    body: "Run this:\n```sh\n# not a heading\n```",
+                },
+                Section {
+                    heading: Some("Usage"),
+                    level: 2,
+                    body: "Call it.\n#hashtag",
+                },
+                Section {
+                    heading: Some("Empty"),
+                    level: 3,
+                    body: "",
+                },
+            ]
+        );
+        assert!(response("  ").sections().is_empty());
+    }
+
+    #[test]
+    fn test_sentences() {
+        let answer =
+            response("It works! Does it? Yes. Version 1.2 is out\n\nA list follows:\n- one");
+        assert_eq!(
+            answer.sentences(),
+            vec![
+                "It works!",
+                "Does it?",
+                "Yes.",
+                "Version 1.2 is out",
+                "A list follows:\n- one",
+            ]
+        );
+    }
+}
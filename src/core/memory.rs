@@ -0,0 +1,516 @@
+//! Long-term memory recalled into prompts.
+//!
+//! A [`MemoryStore`] keeps facts across conversations and finds the ones relevant to a query.
+//! [`InMemoryStore`] ranks memories by the cosine similarity of their embeddings, computed by
+//! an [`Embedder`]: [`OpenAiEmbedder`] calls the OpenAI embeddings API, [`HashEmbedder`]
+//! hashes words locally and needs no network.
+//!
+//! Attach a [`Recall`] to a request with [`LlmBuilder::memory`](crate::LlmBuilder::memory)
+//! and the memories most relevant to the last user message are added to the prompt before
+//! each call.
+//!
+//! # Example
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use rsai::memory::{InMemoryStore, MemoryStore, OpenAiEmbedder, Recall};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let embedder = OpenAiEmbedder::new(ApiKey::Default, "text-embedding-3-small")?;
+//! let store = Arc::new(InMemoryStore::new(embedder));
+//! store.add("The user prefers answers in metric units").await?;
+//!
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "How tall is Mount Everest?")])
+//!     .memory(Recall::new(store).with_limit(3))
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use super::builder::{ApiKey, resolve_api_key};
+use super::error::LlmError;
+use super::hash::fnv1a;
+use super::http::{HttpClient, HttpClientConfig};
+use super::types::{BoxFuture, ChatRole, Message};
+use crate::provider::{Provider, constants};
+
+/// Memories added to the prompt unless changed with [`Recall::with_limit`].
+const DEFAULT_RECALL_LIMIT: usize = 5;
+
+/// Dimensions of [`HashEmbedder`] vectors unless changed with [`HashEmbedder::new`].
+const DEFAULT_HASH_DIMENSIONS: usize = 1024;
+
+/// A remembered fact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memory {
+    pub id: u64,
+    pub text: String,
+    pub created_at: SystemTime,
+}
+
+/// A memory returned by [`MemoryStore::search`] with its relevance to the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredMemory {
+    pub memory: Memory,
+    /// Higher is more relevant; cosine similarity for [`InMemoryStore`].
+    pub score: f32,
+}
+
+/// Which memories [`MemoryStore::prune`] removes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prune {
+    /// Remove memories added longer ago than this
+    OlderThan(Duration),
+    /// Keep only this many of the most recently added memories
+    KeepNewest(usize),
+}
+
+/// Storage for long-term memories.
+pub trait MemoryStore: Send + Sync {
+    /// Remember `text`.
+    fn add<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Memory, LlmError>>;
+
+    /// The `limit` memories most relevant to `query`, most relevant first.
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ScoredMemory>, LlmError>>;
+
+    /// Forget memories, returning how many were removed.
+    fn prune(&self, prune: Prune) -> BoxFuture<'_, Result<usize, LlmError>>;
+}
+
+/// Turns texts into embedding vectors.
+pub trait Embedder: Send + Sync {
+    /// One vector per text, in order.
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, LlmError>>;
+}
+
+/// Embeds text by hashing its words into a fixed number of buckets.
+///
+/// Similarity only reflects shared words, not meaning, but it is free, deterministic and
+/// works offline, e.g. in tests.
+#[derive(Debug, Clone)]
+pub struct HashEmbedder {
+    dimensions: usize,
+}
+
+impl Default for HashEmbedder {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_DIMENSIONS)
+    }
+}
+
+impl HashEmbedder {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    fn embed_text(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        for word in text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            vector[(fnv1a(word.as_bytes()) % self.dimensions as u64) as usize] += 1.0;
+        }
+        vector
+    }
+}
+
+impl Embedder for HashEmbedder {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, LlmError>> {
+        Box::pin(async move { Ok(texts.iter().map(|text| self.embed_text(text)).collect()) })
+    }
+}
+
+/// Embeds text with the OpenAI embeddings API, e.g. `text-embedding-3-small`.
+pub struct OpenAiEmbedder {
+    http: HttpClient,
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<Embedding>,
+}
+
+#[derive(serde::Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(api_key: ApiKey, model: impl Into<String>) -> Result<Self, LlmError> {
+        Ok(Self {
+            http: HttpClient::new(HttpClientConfig::default(), None, None)?,
+            api_key: resolve_api_key(Provider::OpenAI, api_key)?,
+            model: model.into(),
+            base_url: constants::openai::API_BASE.to_string(),
+        })
+    }
+
+    /// Send requests to an OpenAI-compatible API, e.g. `http://localhost:11434/v1`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Configure retries, timeouts or a custom transport.
+    pub fn with_http_client_config(mut self, config: HttpClientConfig) -> Result<Self, LlmError> {
+        self.http = HttpClient::new(config, None, None)?;
+        Ok(self)
+    }
+}
+
+impl Embedder for OpenAiEmbedder {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>, LlmError>> {
+        Box::pin(async move {
+            let url = format!(
+                "{}{}",
+                self.base_url.trim_end_matches('/'),
+                constants::openai::EMBEDDINGS_ENDPOINT
+            );
+            let headers = [(
+                "Authorization".to_string(),
+                format!("Bearer {}", self.api_key),
+            )];
+            let body = serde_json::json!({ "model": self.model, "input": texts });
+
+            let mut response: EmbeddingResponse =
+                self.http.post_json(&url, &headers, &body).await?;
+            response.data.sort_by_key(|embedding| embedding.index);
+            if response.data.len() != texts.len() {
+                return Err(LlmError::Provider {
+                    message: format!(
+                        "Expected {} embeddings, got {}",
+                        texts.len(),
+                        response.data.len()
+                    ),
+                    source: None,
+                });
+            }
+            Ok(response
+                .data
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect())
+        })
+    }
+}
+
+/// A memory with its embedding.
+type Entry = (Memory, Vec<f32>);
+
+/// Keeps memories and their embeddings in process memory.
+pub struct InMemoryStore<E> {
+    embedder: E,
+    next_id: AtomicU64,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl<E: Embedder> InMemoryStore<E> {
+    pub fn new(embedder: E) -> Self {
+        Self {
+            embedder,
+            next_id: AtomicU64::new(1),
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn entries(&self) -> Result<MutexGuard<'_, Vec<Entry>>, LlmError> {
+        self.entries.lock().map_err(|_| LlmError::Provider {
+            message: "Memory store lock poisoned".to_string(),
+            source: None,
+        })
+    }
+
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        self.embedder
+            .embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| LlmError::Provider {
+                message: "Embedder returned no vector".to_string(),
+                source: None,
+            })
+    }
+}
+
+impl<E: Embedder> MemoryStore for InMemoryStore<E> {
+    fn add<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Memory, LlmError>> {
+        Box::pin(async move {
+            let embedding = self.embed_one(text).await?;
+            let memory = Memory {
+                id: self.next_id.fetch_add(1, Ordering::Relaxed),
+                text: text.to_string(),
+                created_at: SystemTime::now(),
+            };
+            self.entries()?.push((memory.clone(), embedding));
+            Ok(memory)
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ScoredMemory>, LlmError>> {
+        Box::pin(async move {
+            let query = self.embed_one(query).await?;
+            let mut scored: Vec<ScoredMemory> = self
+                .entries()?
+                .iter()
+                .map(|(memory, embedding)| ScoredMemory {
+                    memory: memory.clone(),
+                    score: cosine_similarity(&query, embedding),
+                })
+                .collect();
+            scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+            scored.truncate(limit);
+            Ok(scored)
+        })
+    }
+
+    fn prune(&self, prune: Prune) -> BoxFuture<'_, Result<usize, LlmError>> {
+        Box::pin(async move {
+            let mut entries = self.entries()?;
+            let before = entries.len();
+            match prune {
+                Prune::OlderThan(age) => entries.retain(|(memory, _)| {
+                    memory
+                        .created_at
+                        .elapsed()
+                        .map_or(true, |elapsed| elapsed <= age)
+                }),
+                // Entries are kept in insertion order
+                Prune::KeepNewest(count) => {
+                    let excess = entries.len().saturating_sub(count);
+                    entries.drain(..excess);
+                }
+            }
+            Ok(before - entries.len())
+        })
+    }
+}
+
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+/// Retrieval of memories into the prompt, attached with
+/// [`LlmBuilder::memory`](crate::LlmBuilder::memory).
+#[derive(Clone)]
+pub struct Recall {
+    store: Arc<dyn MemoryStore>,
+    limit: usize,
+    min_score: f32,
+}
+
+impl std::fmt::Debug for Recall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recall")
+            .field("limit", &self.limit)
+            .field("min_score", &self.min_score)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Recall {
+    pub fn new(store: Arc<dyn MemoryStore>) -> Self {
+        Self {
+            store,
+            limit: DEFAULT_RECALL_LIMIT,
+            min_score: 0.0,
+        }
+    }
+
+    /// Add at most `limit` memories to the prompt.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Skip memories scoring below `min_score`.
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Add the memories relevant to the last user message as a system message after the
+    /// leading system messages.
+    pub(crate) async fn inject(&self, messages: &mut Vec<Message>) -> Result<(), LlmError> {
+        let Some(query) = last_user_message(messages) else {
+            return Ok(());
+        };
+
+        let memories: Vec<String> = self
+            .store
+            .search(query, self.limit)
+            .await?
+            .into_iter()
+            .filter(|scored| scored.score > 0.0 && scored.score >= self.min_score)
+            .map(|scored| format!("- {}", scored.memory.text))
+            .collect();
+        if memories.is_empty() {
+            return Ok(());
+        }
+
+        insert_context(
+            messages,
+            format!(
+                "Relevant memories from earlier conversations:\n{}",
+                memories.join("\n")
+            ),
+        );
+        Ok(())
+    }
+}
+
+/// The content of the last user message, to search for context relevant to it.
+pub(crate) fn last_user_message(messages: &[Message]) -> Option<&str> {
+    messages
+        .iter()
+        .rev()
+        .find(|message| message.role == ChatRole::User)
+        .map(|message| message.content.as_str())
+}
+
+/// Add `content` as a system message after the leading system messages.
+pub(crate) fn insert_context(messages: &mut Vec<Message>, content: String) {
+    let system = messages
+        .iter()
+        .take_while(|message| message.role == ChatRole::System)
+        .count();
+    messages.insert(system, Message::new(ChatRole::System, content));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextResponse;
+    use crate::core::fixtures::{ScriptedTransport, mock_openai, responses_answer};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_search_ranks_by_similarity() {
+        let store = InMemoryStore::new(HashEmbedder::default());
+        store.add("The user lives in Berlin").await.unwrap();
+        store.add("The user prefers metric units").await.unwrap();
+        store.add("Favorite color is green").await.unwrap();
+
+        let results = store
+            .search("Which units does the user prefer?", 2)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].memory.text, "The user prefers metric units");
+        assert!(results[0].score > results[1].score);
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let store = InMemoryStore::new(HashEmbedder::default());
+        for text in ["first", "second", "third"] {
+            store.add(text).await.unwrap();
+        }
+
+        assert_eq!(store.prune(Prune::KeepNewest(2)).await.unwrap(), 1);
+        let remaining: Vec<String> = store
+            .search("first second third", 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|scored| scored.memory.text)
+            .collect();
+        assert!(!remaining.contains(&"first".to_string()));
+        assert_eq!(remaining.len(), 2);
+
+        assert_eq!(
+            store
+                .prune(Prune::OlderThan(Duration::from_secs(3600)))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store.prune(Prune::OlderThan(Duration::ZERO)).await.unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_openai_embedder_request() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{ "index": 0, "embedding": [1.0, 0.0] }]
+            })))
+            .mount(&server)
+            .await;
+
+        let embedder = OpenAiEmbedder::new(ApiKey::Custom("test-key".to_string()), "embed-model")
+            .unwrap()
+            .with_base_url(format!("{}/v1", server.uri()));
+        let embedding = embedder.embed(&["hello".to_string()]).await.unwrap();
+        assert_eq!(embedding, vec![vec![1.0, 0.0]]);
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body, json!({ "model": "embed-model", "input": ["hello"] }));
+    }
+
+    #[tokio::test]
+    async fn test_recalled_memories_are_sent_before_the_question() {
+        let store = Arc::new(InMemoryStore::new(HashEmbedder::default()));
+        store.add("The user prefers metric units").await.unwrap();
+        store.add("The user's cat is called Miso").await.unwrap();
+
+        let transport = ScriptedTransport::answering(responses_answer("8,849 metres"));
+        mock_openai(transport.clone())
+            .messages(vec![
+                Message::new(ChatRole::System, "Be brief."),
+                Message::new(
+                    ChatRole::User,
+                    "Which units do I prefer for the height of Everest?",
+                ),
+            ])
+            .memory(Recall::new(store).with_limit(1))
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        let bodies = transport.bodies();
+        let input = bodies[0]["input"].as_array().unwrap();
+        assert_eq!(input.len(), 3);
+        assert_eq!(input[0]["content"], "Be brief.");
+        assert_eq!(input[1]["role"], "system");
+        assert_eq!(
+            input[1]["content"],
+            "Relevant memories from earlier conversations:\n- The user prefers metric units"
+        );
+        assert_eq!(input[2]["role"], "user");
+    }
+}
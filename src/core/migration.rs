@@ -0,0 +1,254 @@
+//! Schema versions for structured outputs that are persisted, e.g. in a conversation store or
+//! a cache, and read back after the Rust type changed.
+//!
+//! `#[completion_schema(version = 2)]` implements [`SchemaVersion`] for a type. Wrapping a
+//! persisted value in [`Versioned`] stores that version next to it. When a value of an older
+//! version is read back, [`Migratable::migrate`] upgrades its JSON one version at a time before
+//! it is deserialized, instead of failing on renamed or added fields. Values that were
+//! persisted without [`Versioned`] are read as version 1.
+//!
+//! # Example
+//! ```
+//! use rsai::completion_schema;
+//! use rsai::migration::{Migratable, Versioned};
+//! use serde_json::{Value, json};
+//!
+//! /// Version 1 had a single `name` field.
+//! #[completion_schema(version = 2)]
+//! #[derive(serde::Serialize)]
+//! struct Contact {
+//!     first_name: String,
+//!     last_name: String,
+//! }
+//!
+//! impl Migratable for Contact {
+//!     fn migrate(from: u32, value: Value) -> Result<Value, String> {
+//!         match from {
+//!             1 => {
+//!                 let name = value["name"].as_str().ok_or("missing name")?;
+//!                 let (first, last) = name.split_once(' ').unwrap_or((name, ""));
+//!                 Ok(json!({ "first_name": first, "last_name": last }))
+//!             }
+//!             _ => Err(format!("unknown version {from}")),
+//!         }
+//!     }
+//! }
+//!
+//! // Stored by an earlier release, before `Contact` was versioned
+//! let stored = json!({ "name": "Ada Lovelace" });
+//! let Versioned(contact) = serde_json::from_value::<Versioned<Contact>>(stored).unwrap();
+//! assert_eq!(contact.last_name, "Lovelace");
+//!
+//! let stored = serde_json::to_value(Versioned(contact)).unwrap();
+//! assert_eq!(stored["$schema_version"], 2);
+//! ```
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use super::error::LlmError;
+
+/// Field holding the version of a value persisted with [`Versioned`]. The `$` keeps the envelope
+/// apart from persisted types that have their own `schema_version` and `value` fields.
+pub const VERSION_FIELD: &str = "$schema_version";
+
+/// Field holding the value itself.
+const VALUE_FIELD: &str = "$value";
+
+/// The current schema version of a type. Implemented by `#[completion_schema(version = N)]`.
+pub trait SchemaVersion {
+    /// Starts at 1 and is raised whenever a change to the type breaks reading old values.
+    const VERSION: u32;
+}
+
+/// Upgrades persisted values of older schema versions.
+pub trait Migratable: SchemaVersion + DeserializeOwned {
+    /// Turn `value`, persisted with version `from`, into a value of version `from + 1`. Called
+    /// for every version from the persisted one up to [`SchemaVersion::VERSION`].
+    ///
+    /// The default rejects every old version, for types that never had a breaking change.
+    fn migrate(from: u32, value: Value) -> Result<Value, String> {
+        let _ = value;
+        Err(format!("no migration from version {from}"))
+    }
+}
+
+/// Persisted form of a value together with the schema version of its type.
+///
+/// Serializes as `{"$schema_version": N, "$value": ...}` and migrates older versions when
+/// deserialized, see [the module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned<T>(pub T);
+
+impl<T> Versioned<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for Versioned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: SchemaVersion + Serialize> Serialize for Versioned<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = serde_json::to_value(&self.0).map_err(serde::ser::Error::custom)?;
+        json!({ VERSION_FIELD: T::VERSION, VALUE_FIELD: value }).serialize(serializer)
+    }
+}
+
+impl<'de, T: Migratable> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        migrate::<T>(value)
+            .map(Versioned)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Read a value persisted with [`Versioned`], or without a version, migrating it to the
+/// current version of `T`.
+pub fn migrate<T: Migratable>(value: Value) -> Result<T, LlmError> {
+    let (mut version, mut value) = split_version(value);
+    if version > T::VERSION {
+        return Err(LlmError::Migration {
+            from: version,
+            to: T::VERSION,
+            message: "the value was written by a newer version of the type".to_string(),
+        });
+    }
+
+    while version < T::VERSION {
+        value = T::migrate(version, value).map_err(|message| LlmError::Migration {
+            from: version,
+            to: version + 1,
+            message,
+        })?;
+        version += 1;
+    }
+
+    serde_json::from_value(value).map_err(|e| LlmError::Parse {
+        message: format!("Failed to parse value of schema version {version}"),
+        source: Box::new(e),
+    })
+}
+
+/// The version and the value of a persisted value. Values without the [`Versioned`] envelope
+/// are version 1.
+fn split_version(value: Value) -> (u32, Value) {
+    match value {
+        Value::Object(mut object) if object.len() == 2 && object.contains_key(VALUE_FIELD) => {
+            match object.get(VERSION_FIELD).and_then(Value::as_u64) {
+                Some(version) => (
+                    u32::try_from(version).unwrap_or(u32::MAX),
+                    object.remove(VALUE_FIELD).unwrap_or_default(),
+                ),
+                None => (1, Value::Object(object)),
+            }
+        }
+        value => (1, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Price {
+        amount_cents: u64,
+        currency: String,
+    }
+
+    impl SchemaVersion for Price {
+        const VERSION: u32 = 3;
+    }
+
+    impl Migratable for Price {
+        fn migrate(from: u32, mut value: Value) -> Result<Value, String> {
+            match from {
+                // Version 2 added the currency
+                1 => value["currency"] = json!("EUR"),
+                // Version 3 stored cents instead of a float amount
+                2 => {
+                    let amount = value["amount"].as_f64().ok_or("missing amount")?;
+                    value = json!({
+                        "amount_cents": (amount * 100.0).round() as u64,
+                        "currency": value["currency"],
+                    });
+                }
+                _ => return Err(format!("unknown version {from}")),
+            }
+            Ok(value)
+        }
+    }
+
+    #[test]
+    fn test_old_versions_are_migrated_step_by_step() {
+        let price: Price = migrate(json!({ "amount": 12.5 })).unwrap();
+        assert_eq!(
+            price,
+            Price {
+                amount_cents: 1250,
+                currency: "EUR".to_string(),
+            }
+        );
+
+        let stored =
+            json!({ "$schema_version": 2, "$value": { "amount": 3.0, "currency": "USD" } });
+        let Versioned(price) = serde_json::from_value::<Versioned<Price>>(stored).unwrap();
+        assert_eq!(price.currency, "USD");
+        assert_eq!(price.amount_cents, 300);
+    }
+
+    #[test]
+    fn test_current_version_round_trips() {
+        let price = Versioned(Price {
+            amount_cents: 99,
+            currency: "CHF".to_string(),
+        });
+        let stored = serde_json::to_value(&price).unwrap();
+        assert_eq!(stored["$schema_version"], 3);
+        assert_eq!(
+            serde_json::from_value::<Versioned<Price>>(stored).unwrap(),
+            price
+        );
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Setting {
+        schema_version: u32,
+        value: String,
+    }
+
+    impl SchemaVersion for Setting {
+        const VERSION: u32 = 1;
+    }
+
+    impl Migratable for Setting {}
+
+    #[test]
+    fn test_fields_named_like_the_envelope_are_not_unwrapped() {
+        let setting: Setting = migrate(json!({ "schema_version": 7, "value": "dark" })).unwrap();
+        assert_eq!(
+            setting,
+            Setting {
+                schema_version: 7,
+                value: "dark".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_failed_and_newer_versions_are_errors() {
+        let err = migrate::<Price>(json!({ "$schema_version": 2, "$value": {} })).unwrap_err();
+        assert!(matches!(err, LlmError::Migration { from: 2, to: 3, .. }));
+
+        let err = migrate::<Price>(json!({ "$schema_version": 4, "$value": {} })).unwrap_err();
+        assert!(matches!(err, LlmError::Migration { from: 4, to: 3, .. }));
+    }
+}
@@ -0,0 +1,268 @@
+//! Builder presets loaded from a TOML file.
+//!
+//! Each `[profiles.<name>]` table names a provider and model together with default generation
+//! parameters, HTTP retries and tool calling budgets, so a deployment can switch models by
+//! editing the file instead of recompiling.
+//!
+//! ```toml
+//! [profiles.fast]
+//! provider = "openai"
+//! model = "gpt-4o-mini"
+//! temperature = 0.2
+//! max_tokens = 512
+//! retries = 2
+//! timeout_secs = 30
+//!
+//! [profiles.fast.budget]
+//! max_iterations = 5
+//! max_tool_calls = 10
+//!
+//! [profiles.smart]
+//! provider = "gemini"
+//! model = "gemini-2.5-pro"
+//! api_key_env = "GEMINI_PROD_KEY"
+//! ```
+//!
+//! # Example
+//! ```no_run
+//! use rsai::{ChatRole, LlmProfile, Message, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let profiles = LlmProfile::from_toml("rsai.toml")?;
+//! let answer = profiles
+//!     .builder("fast")?
+//!     .messages(vec![Message::new(ChatRole::User, "Hello")])
+//!     .complete::<TextResponse>()
+//!     .await?;
+//!
+//! // Or load `rsai.toml` (or the file in `RSAI_CONFIG`) directly
+//! let builder = llm::profile("fast")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde::Deserialize;
+
+use crate::provider::Provider;
+
+use super::{
+    builder::{ApiKey, LlmBuilder, llm, private},
+    error::LlmError,
+    http::HttpClientConfig,
+    tool_guard::ToolCallingConfig,
+};
+
+/// Environment variable naming the profile file [`llm::profile`] reads.
+pub const CONFIG_ENV_VAR: &str = "RSAI_CONFIG";
+
+/// File [`llm::profile`] reads when [`CONFIG_ENV_VAR`] is unset.
+pub const DEFAULT_CONFIG_FILE: &str = "rsai.toml";
+
+/// One named preset of a profile file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LlmProfile {
+    /// A provider name that [`Provider`]'s [`FromStr`](std::str::FromStr) accepts, e.g. `openai`
+    #[serde(deserialize_with = "deserialize_provider")]
+    pub provider: Provider,
+    pub model: String,
+    /// Environment variable holding the API key, the provider's default if unset.
+    pub api_key_env: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Retries of failed HTTP requests.
+    pub retries: Option<u32>,
+    /// Timeout of a single HTTP request in seconds.
+    pub timeout_secs: Option<u64>,
+    pub budget: Option<Budget>,
+}
+
+/// Limits of the tool calling loop, see [`ToolCallingConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    pub max_iterations: Option<u32>,
+    pub max_tool_calls: Option<u32>,
+    /// Timeout of the whole tool calling loop in seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+/// All profiles of a profile file, by name.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profiles {
+    #[serde(default)]
+    profiles: BTreeMap<String, LlmProfile>,
+}
+
+fn deserialize_provider<'de, D>(deserializer: D) -> Result<Provider, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    name.parse().map_err(serde::de::Error::custom)
+}
+
+impl LlmProfile {
+    /// Read every profile of the TOML file at `path`.
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Profiles, LlmError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            LlmError::Builder(format!(
+                "Failed to read profile file '{}': {e}",
+                path.display()
+            ))
+        })?;
+        Profiles::parse(&contents).map_err(|e| match e {
+            LlmError::Builder(message) => {
+                LlmError::Builder(format!("{message} in '{}'", path.display()))
+            }
+            other => other,
+        })
+    }
+
+    /// A builder with the profile's provider, API key, model and defaults applied.
+    pub fn builder(&self) -> Result<LlmBuilder<private::Configuring, ()>, LlmError> {
+        let api_key =
+            match &self.api_key_env {
+                Some(var) => ApiKey::Custom(std::env::var(var).map_err(|_| {
+                    LlmError::Builder(format!("Missing {var} environment variable"))
+                })?),
+                None => ApiKey::Default,
+            };
+        let mut builder = llm::with(self.provider)
+            .api_key(api_key)?
+            .model(&self.model);
+
+        if let Some(temperature) = self.temperature {
+            builder = builder.temperature(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            builder = builder.top_p(top_p);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            builder = builder.max_tokens(max_tokens);
+        }
+        if self.retries.is_some() || self.timeout_secs.is_some() {
+            let mut config = HttpClientConfig::default();
+            if let Some(retries) = self.retries {
+                config.max_retries = retries;
+            }
+            if let Some(timeout) = self.timeout_secs {
+                config.timeout = Duration::from_secs(timeout);
+            }
+            builder = builder.http_client_config(config);
+        }
+        if let Some(budget) = &self.budget {
+            let mut config = ToolCallingConfig::default();
+            if let Some(max_iterations) = budget.max_iterations {
+                config.max_iterations = max_iterations;
+            }
+            if let Some(timeout) = budget.timeout_secs {
+                config.timeout = Duration::from_secs(timeout);
+            }
+            config.max_tool_calls = budget.max_tool_calls;
+            builder = builder.set_tool_calling_config(config);
+        }
+        Ok(builder)
+    }
+}
+
+impl Profiles {
+    /// Parse profiles from TOML text.
+    pub fn parse(toml: &str) -> Result<Self, LlmError> {
+        toml::from_str(toml)
+            .map_err(|e| LlmError::Builder(format!("Invalid profile file: {}", e.message())))
+    }
+
+    /// The profile called `name`.
+    pub fn get(&self, name: &str) -> Result<&LlmProfile, LlmError> {
+        self.profiles.get(name).ok_or_else(|| {
+            LlmError::Builder(format!(
+                "Unknown profile '{name}', available: {}",
+                self.names().collect::<Vec<_>>().join(", ")
+            ))
+        })
+    }
+
+    /// Names of all profiles, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// A builder for the profile called `name`, see [`LlmProfile::builder`].
+    pub fn builder(&self, name: &str) -> Result<LlmBuilder<private::Configuring, ()>, LlmError> {
+        self.get(name)?.builder()
+    }
+}
+
+/// Load the profile file named by [`CONFIG_ENV_VAR`], or [`DEFAULT_CONFIG_FILE`].
+pub(crate) fn load_default() -> Result<Profiles, LlmError> {
+    let path = std::env::var(CONFIG_ENV_VAR).unwrap_or_else(|_| DEFAULT_CONFIG_FILE.to_string());
+    LlmProfile::from_toml(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILES: &str = r#"
+        [profiles.fast]
+        provider = "openai"
+        model = "gpt-4o-mini"
+        temperature = 0.2
+        retries = 1
+
+        [profiles.fast.budget]
+        max_tool_calls = 10
+
+        [profiles.smart]
+        provider = "Gemini"
+        model = "gemini-2.5-pro"
+        api_key_env = "RSAI_PROFILE_TEST_KEY_THAT_IS_NOT_SET"
+    "#;
+
+    #[test]
+    fn test_parse_profiles() {
+        let profiles = Profiles::parse(PROFILES).unwrap();
+        assert_eq!(profiles.names().collect::<Vec<_>>(), vec!["fast", "smart"]);
+
+        let fast = profiles.get("fast").unwrap();
+        assert_eq!(fast.provider, Provider::OpenAI);
+        assert_eq!(fast.model, "gpt-4o-mini");
+        assert_eq!(fast.temperature, Some(0.2));
+        assert_eq!(fast.retries, Some(1));
+        assert_eq!(fast.budget.as_ref().unwrap().max_tool_calls, Some(10));
+        assert_eq!(profiles.get("smart").unwrap().provider, Provider::Gemini);
+
+        match profiles.get("slow") {
+            Err(LlmError::Builder(message)) => assert!(message.contains("fast, smart")),
+            other => panic!("expected unknown profile error, got {other:?}"),
+        }
+        match profiles.builder("smart") {
+            Err(LlmError::Builder(message)) => {
+                assert!(message.contains("RSAI_PROFILE_TEST_KEY_THAT_IS_NOT_SET"))
+            }
+            Err(other) => panic!("expected missing key error, got {other:?}"),
+            Ok(_) => panic!("expected missing key error"),
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_provider_and_fields() {
+        let unknown_provider = "[profiles.fast]\nprovider = \"acme\"\nmodel = \"m\"";
+        assert!(matches!(
+            Profiles::parse(unknown_provider),
+            Err(LlmError::Builder(message)) if message.contains("Unknown provider 'acme'")
+        ));
+
+        let typo = "[profiles.fast]\nprovider = \"openai\"\nmodel = \"m\"\ntemprature = 0.1";
+        assert!(matches!(
+            Profiles::parse(typo),
+            Err(LlmError::Builder(message)) if message.contains("temprature")
+        ));
+    }
+}
@@ -0,0 +1,385 @@
+//! Versioned prompt templates.
+//!
+//! A [`PromptRegistry`] keeps templates by name and version. Builders given a registry with
+//! [`LlmBuilder::prompts`](crate::LlmBuilder::prompts) render one into the messages with
+//! [`LlmBuilder::prompt`](crate::LlmBuilder::prompt), referenced as `name@version` or just
+//! `name` for the version registered last. The [`PromptVersion`] used is recorded in the
+//! [metadata](crate::ResponseMetadata::prompt) of the response, so every answer can be
+//! traced back to the prompt that produced it.
+//!
+//! Templates fill `{variable}` placeholders; write `{{` and `}}` for literal braces.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::prompts::{PromptRegistry, PromptTemplate};
+//! use rsai::{ApiKey, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let prompts = PromptRegistry::new();
+//! prompts.register(
+//!     "extract_invoice",
+//!     "v3",
+//!     PromptTemplate::new("Extract the invoice number and total from:\n{invoice}")
+//!         .with_system("You read invoices for {company}. Answer as JSON."),
+//! )?;
+//!
+//! let response = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .prompts(prompts)
+//!     .prompt(
+//!         "extract_invoice@v3",
+//!         [("company", "ACME"), ("invoice", "INV-7, total 42 EUR")],
+//!     )?
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! assert_eq!(response.metadata.prompt.unwrap().version, "v3");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use super::{ChatRole, LlmError, Message};
+
+/// Separator between the name and the version in a prompt reference.
+pub const VERSION_SEPARATOR: char = '@';
+
+/// A prompt: the user message and, optionally, a system message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    system: Option<String>,
+    user: String,
+}
+
+impl PromptTemplate {
+    pub fn new(user: impl Into<String>) -> Self {
+        Self {
+            system: None,
+            user: user.into(),
+        }
+    }
+
+    /// Send `system` as the system message, ahead of the user message.
+    pub fn with_system(mut self, system: impl Into<String>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// The messages with every placeholder filled from `vars`.
+    fn render(&self, vars: &HashMap<String, String>) -> Result<Vec<Message>, String> {
+        let mut messages = Vec::new();
+        if let Some(system) = &self.system {
+            messages.push(Message::new(ChatRole::System, fill(system, vars)?));
+        }
+        messages.push(Message::new(ChatRole::User, fill(&self.user, vars)?));
+        Ok(messages)
+    }
+}
+
+impl From<&str> for PromptTemplate {
+    fn from(user: &str) -> Self {
+        Self::new(user)
+    }
+}
+
+impl From<String> for PromptTemplate {
+    fn from(user: String) -> Self {
+        Self::new(user)
+    }
+}
+
+/// The name and version of the prompt a request was rendered from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PromptVersion {
+    pub name: String,
+    pub version: String,
+}
+
+impl fmt::Display for PromptVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{VERSION_SEPARATOR}{}", self.name, self.version)
+    }
+}
+
+/// Versions of every prompt in the order they were registered.
+type PromptMap = Arc<RwLock<BTreeMap<String, Vec<(String, PromptTemplate)>>>>;
+
+/// Prompt templates by name and version. Clones share the templates, so prompts registered
+/// later are visible to builders and clients that already hold the registry.
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    prompts: PromptMap,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `version` of the prompt `name`, which becomes its latest version.
+    ///
+    /// Returns [`LlmError::Prompt`] if the version is already registered: versions don't
+    /// change once requests may have used them.
+    pub fn register(
+        &self,
+        name: impl Into<String>,
+        version: impl Into<String>,
+        template: impl Into<PromptTemplate>,
+    ) -> Result<(), LlmError> {
+        let (name, version) = (name.into(), version.into());
+        let invalid = |message: &str| LlmError::Prompt {
+            prompt: format!("{name}{VERSION_SEPARATOR}{version}"),
+            message: message.to_string(),
+        };
+        if name.is_empty() || version.is_empty() || name.contains(VERSION_SEPARATOR) {
+            return Err(invalid(&format!(
+                "Name and version must be non-empty and the name can't contain '{VERSION_SEPARATOR}'"
+            )));
+        }
+        let mut prompts = self.prompts.write().map_err(|_| lock_poisoned(&name))?;
+        let versions = prompts.entry(name.clone()).or_default();
+        if versions.iter().any(|(existing, _)| *existing == version) {
+            return Err(invalid("Version is already registered"));
+        }
+        versions.push((version, template.into()));
+        Ok(())
+    }
+
+    /// The versions of the prompt `name` in the order they were registered.
+    pub fn versions(&self, name: &str) -> Vec<String> {
+        self.prompts
+            .read()
+            .ok()
+            .and_then(|prompts| {
+                prompts.get(name).map(|versions| {
+                    versions
+                        .iter()
+                        .map(|(version, _)| version.clone())
+                        .collect()
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// The template `reference` points to, `name@version` or `name` for the latest version.
+    pub fn get(&self, reference: &str) -> Result<(PromptVersion, PromptTemplate), LlmError> {
+        let (name, version) = match reference.split_once(VERSION_SEPARATOR) {
+            Some((name, version)) => (name, Some(version)),
+            None => (reference, None),
+        };
+        let prompts = self.prompts.read().map_err(|_| lock_poisoned(name))?;
+        let found = prompts.get(name).and_then(|versions| match version {
+            Some(version) => versions.iter().find(|(existing, _)| existing == version),
+            None => versions.last(),
+        });
+        let (version, template) = found.ok_or_else(|| LlmError::Prompt {
+            prompt: reference.to_string(),
+            message: "Prompt is not registered".to_string(),
+        })?;
+        Ok((
+            PromptVersion {
+                name: name.to_string(),
+                version: version.clone(),
+            },
+            template.clone(),
+        ))
+    }
+
+    /// The messages of the prompt `reference` with `vars` filled in.
+    ///
+    /// Returns [`LlmError::Prompt`] if the prompt isn't registered or a placeholder has no
+    /// value. Values without a placeholder are ignored.
+    pub fn render<K, V>(
+        &self,
+        reference: &str,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<(PromptVersion, Vec<Message>), LlmError>
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let (version, template) = self.get(reference)?;
+        let vars = vars
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        let messages = template.render(&vars).map_err(|message| LlmError::Prompt {
+            prompt: version.to_string(),
+            message,
+        })?;
+        Ok((version, messages))
+    }
+}
+
+fn lock_poisoned(name: &str) -> LlmError {
+    LlmError::Prompt {
+        prompt: name.to_string(),
+        message: "Prompt registry lock poisoned".to_string(),
+    }
+}
+
+/// Replace the `{variable}` placeholders of `template`, and `{{` and `}}` with single braces.
+fn fill(template: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        filled.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            filled.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        // Only `{` opens a placeholder
+        if let Some(after) = tail.strip_prefix('}') {
+            filled.push('}');
+            rest = after;
+            continue;
+        }
+        let placeholder = tail[1..]
+            .find('}')
+            .map(|end| &tail[1..=end])
+            .filter(|name| {
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        match placeholder {
+            Some(name) => {
+                let value = vars
+                    .get(name)
+                    .ok_or_else(|| format!("No value for the variable '{name}'"))?;
+                filled.push_str(value);
+                rest = &tail[name.len() + 2..];
+            }
+            // Not a placeholder, e.g. a JSON example
+            None => {
+                filled.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextResponse;
+    use crate::core::fixtures::{ScriptedTransport, mock_openai, responses_answer};
+
+    #[test]
+    fn test_references_resolve_to_versions() {
+        let prompts = PromptRegistry::new();
+        prompts.register("greet", "v1", "Hi {name}").unwrap();
+        prompts
+            .register(
+                "greet",
+                "v2",
+                PromptTemplate::new("Hello {name}").with_system("Answer as {{\"greeting\": ...}}"),
+            )
+            .unwrap();
+        assert!(matches!(
+            prompts.register("greet", "v1", "Hey"),
+            Err(LlmError::Prompt { .. })
+        ));
+        assert_eq!(prompts.versions("greet"), vec!["v1", "v2"]);
+
+        let (version, messages) = prompts.render("greet@v1", [("name", "Ada")]).unwrap();
+        assert_eq!(version.to_string(), "greet@v1");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "Hi Ada");
+
+        let (version, messages) = prompts.render("greet", [("name", "Ada")]).unwrap();
+        assert_eq!(version.version, "v2");
+        assert_eq!(messages[0].role, ChatRole::System);
+        assert_eq!(messages[0].content, "Answer as {\"greeting\": ...}");
+        assert_eq!(messages[1].content, "Hello Ada");
+
+        assert!(prompts.get("greet@v3").is_err());
+        assert!(prompts.get("farewell").is_err());
+    }
+
+    #[test]
+    fn test_missing_variables_are_rejected() {
+        let vars = HashMap::from([("city".to_string(), "Lisbon".to_string())]);
+        assert_eq!(
+            fill("Weather in {city}: { \"unit\": \"C\" }", &vars).unwrap(),
+            "Weather in Lisbon: { \"unit\": \"C\" }"
+        );
+        assert_eq!(fill("a }city} c", &vars).unwrap(), "a }city} c");
+        assert_eq!(
+            fill("{city} on {date}", &vars).unwrap_err(),
+            "No value for the variable 'date'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_versions_are_rendered_and_recorded() {
+        let transport = ScriptedTransport::answering(responses_answer("INV-7: 42 EUR"));
+        let prompts = PromptRegistry::new();
+        prompts
+            .register("extract_invoice", "v2", "Extract the total of {invoice}")
+            .unwrap();
+        prompts
+            .register(
+                "extract_invoice",
+                "v3",
+                PromptTemplate::new("Invoice:\n{invoice}")
+                    .with_system("You read invoices for {company}."),
+            )
+            .unwrap();
+        let client = mock_openai(transport.clone())
+            .prompts(prompts.clone())
+            .client();
+
+        let response = client
+            .builder()
+            .prompt(
+                "extract_invoice@v3",
+                [("company", "ACME"), ("invoice", "INV-7, total 42 EUR")],
+            )
+            .unwrap()
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        let prompt = response.metadata.prompt.unwrap();
+        assert_eq!(prompt.to_string(), "extract_invoice@v3");
+        let bodies = transport.bodies();
+        assert_eq!(
+            bodies[0]["input"][0]["content"],
+            "You read invoices for ACME."
+        );
+        assert_eq!(
+            bodies[0]["input"][1]["content"],
+            "Invoice:\nINV-7, total 42 EUR"
+        );
+
+        let latest = client
+            .builder()
+            .prompt("extract_invoice", [("invoice", "INV-8")])
+            .err()
+            .unwrap();
+        assert!(
+            matches!(&latest, LlmError::Prompt { prompt, message }
+                if prompt == "extract_invoice@v3" && message.contains("company")),
+            "{latest:?}"
+        );
+        let plain = client
+            .complete::<TextResponse>(vec![Message::new(ChatRole::User, "Hi")])
+            .await
+            .unwrap();
+        assert_eq!(plain.metadata.prompt, None);
+
+        // A builder without a registry has nothing to resolve against
+        let err = mock_openai(transport)
+            .prompt("extract_invoice@v2", [("invoice", "INV-7")])
+            .err()
+            .unwrap();
+        assert!(matches!(err, LlmError::Prompt { .. }));
+    }
+}
@@ -0,0 +1,450 @@
+//! Retrieval-augmented generation.
+//!
+//! Split documents into chunks with a [`Chunker`], store them in a [`VectorIndex`] and answer
+//! questions with [`LlmBuilder::retrieve_and_answer`](crate::LlmBuilder::retrieve_and_answer),
+//! which adds the chunks most relevant to the last user message to the prompt and returns
+//! them as citations next to the answer.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::memory::OpenAiEmbedder;
+//! use rsai::rag::{InMemoryIndex, RecursiveChunker, VectorIndex, chunk_document};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let index = InMemoryIndex::new(OpenAiEmbedder::new(ApiKey::Default, "text-embedding-3-small")?);
+//! let handbook = std::fs::read_to_string("handbook.md").unwrap();
+//! index
+//!     .add(chunk_document("handbook.md", &handbook, &RecursiveChunker::new(1_000)))
+//!     .await?;
+//!
+//! let answer = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "How many vacation days do I get?")])
+//!     .retrieve_and_answer::<TextResponse>(&index, 4)
+//!     .await?;
+//! for (number, source) in answer.citations.iter().enumerate() {
+//!     println!("[{}] {}", number + 1, source.chunk.source);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Mutex;
+
+use super::error::LlmError;
+use super::memory::{Embedder, cosine_similarity, insert_context, last_user_message};
+use super::types::{BoxFuture, Message};
+
+/// Separators [`RecursiveChunker`] tries in order: paragraphs, lines, sentences, words.
+const DEFAULT_SEPARATORS: [&str; 4] = ["\n\n", "\n", ". ", " "];
+
+/// Splits text into pieces small enough to embed and to fit in a prompt.
+pub trait Chunker {
+    fn chunk(&self, text: &str) -> Vec<String>;
+}
+
+/// Chunks of at most `max_tokens` tokens, each repeating the last `overlap` tokens of the
+/// previous one so that no passage is only seen cut in half.
+///
+/// Tokens are approximated by whitespace-separated words.
+#[derive(Debug, Clone)]
+pub struct TokenChunker {
+    max_tokens: usize,
+    overlap: usize,
+}
+
+impl TokenChunker {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: max_tokens.max(1),
+            overlap: 0,
+        }
+    }
+
+    /// Repeat the last `overlap` tokens of each chunk at the start of the next one.
+    pub fn with_overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap.min(self.max_tokens - 1);
+        self
+    }
+}
+
+impl Chunker for TokenChunker {
+    fn chunk(&self, text: &str) -> Vec<String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let step = self.max_tokens - self.overlap;
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < words.len() {
+            let end = (start + self.max_tokens).min(words.len());
+            chunks.push(words[start..end].join(" "));
+            if end == words.len() {
+                break;
+            }
+            start += step;
+        }
+        chunks
+    }
+}
+
+/// Chunks of at most `max_chars` characters that split on the coarsest boundary possible:
+/// paragraphs first, then lines, sentences and words, and only as a last resort inside a word.
+/// Neighboring pieces are merged while they fit. Separators stay at the end of the piece they
+/// follow, so every chunk is a slice of the text, trimmed of surrounding whitespace.
+#[derive(Debug, Clone)]
+pub struct RecursiveChunker {
+    max_chars: usize,
+    separators: Vec<String>,
+}
+
+impl RecursiveChunker {
+    pub fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars: max_chars.max(1),
+            separators: DEFAULT_SEPARATORS.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Replace the separators, coarsest first.
+    pub fn with_separators(mut self, separators: &[&str]) -> Self {
+        self.separators = separators.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn split(&self, text: &str, separators: &[String]) -> Vec<String> {
+        if text.chars().count() <= self.max_chars {
+            return vec![text.to_string()];
+        }
+        let Some((separator, finer)) = separators.split_first() else {
+            // No separator left, cut at the character limit
+            let chars: Vec<char> = text.chars().collect();
+            return chars
+                .chunks(self.max_chars)
+                .map(|chunk| chunk.iter().collect())
+                .collect();
+        };
+        if !text.contains(separator.as_str()) {
+            return self.split(text, finer);
+        }
+
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        for piece in text.split_inclusive(separator.as_str()) {
+            let joined_len = current.chars().count() + piece.chars().count();
+            if joined_len <= self.max_chars {
+                current.push_str(piece);
+                continue;
+            }
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if piece.chars().count() <= self.max_chars {
+                current = piece.to_string();
+            } else {
+                chunks.extend(self.split(piece, finer));
+            }
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+impl Chunker for RecursiveChunker {
+    fn chunk(&self, text: &str) -> Vec<String> {
+        self.split(text, &self.separators)
+            .into_iter()
+            .map(|chunk| chunk.trim().to_string())
+            .filter(|chunk| !chunk.is_empty())
+            .collect()
+    }
+}
+
+/// A piece of a document stored in a [`VectorIndex`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Where the chunk comes from, e.g. a file name or URL.
+    pub source: String,
+    /// Position of the chunk within its source.
+    pub index: usize,
+    pub text: String,
+}
+
+/// A chunk returned by [`VectorIndex::search`] with its relevance to the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredChunk {
+    pub chunk: Chunk,
+    /// Higher is more relevant; cosine similarity for [`InMemoryIndex`].
+    pub score: f32,
+}
+
+/// Split `text` with `chunker` into chunks attributed to `source`.
+pub fn chunk_document(source: &str, text: &str, chunker: &impl Chunker) -> Vec<Chunk> {
+    chunker
+        .chunk(text)
+        .into_iter()
+        .enumerate()
+        .map(|(index, text)| Chunk {
+            source: source.to_string(),
+            index,
+            text,
+        })
+        .collect()
+}
+
+/// Storage for chunks that can be searched by relevance.
+pub trait VectorIndex: Send + Sync {
+    fn add(&self, chunks: Vec<Chunk>) -> BoxFuture<'_, Result<(), LlmError>>;
+
+    /// The `top_k` chunks most relevant to `query`, most relevant first.
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        top_k: usize,
+    ) -> BoxFuture<'a, Result<Vec<ScoredChunk>, LlmError>>;
+}
+
+/// Keeps chunks and their embeddings in process memory.
+pub struct InMemoryIndex<E> {
+    embedder: E,
+    entries: Mutex<Vec<(Chunk, Vec<f32>)>>,
+}
+
+impl<E: Embedder> InMemoryIndex<E> {
+    pub fn new(embedder: E) -> Self {
+        Self {
+            embedder,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Number of chunks in the index.
+    pub fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn poisoned() -> LlmError {
+        LlmError::Provider {
+            message: "Vector index lock poisoned".to_string(),
+            source: None,
+        }
+    }
+}
+
+impl<E: Embedder> VectorIndex for InMemoryIndex<E> {
+    fn add(&self, chunks: Vec<Chunk>) -> BoxFuture<'_, Result<(), LlmError>> {
+        Box::pin(async move {
+            if chunks.is_empty() {
+                return Ok(());
+            }
+            let texts: Vec<String> = chunks.iter().map(|chunk| chunk.text.clone()).collect();
+            let embeddings = self.embedder.embed(&texts).await?;
+            if embeddings.len() != chunks.len() {
+                return Err(LlmError::Provider {
+                    message: format!(
+                        "Expected {} embeddings, got {}",
+                        chunks.len(),
+                        embeddings.len()
+                    ),
+                    source: None,
+                });
+            }
+            self.entries
+                .lock()
+                .map_err(|_| Self::poisoned())?
+                .extend(chunks.into_iter().zip(embeddings));
+            Ok(())
+        })
+    }
+
+    fn search<'a>(
+        &'a self,
+        query: &'a str,
+        top_k: usize,
+    ) -> BoxFuture<'a, Result<Vec<ScoredChunk>, LlmError>> {
+        Box::pin(async move {
+            let query = self
+                .embedder
+                .embed(&[query.to_string()])
+                .await?
+                .pop()
+                .ok_or_else(|| LlmError::Provider {
+                    message: "Embedder returned no vector".to_string(),
+                    source: None,
+                })?;
+            let mut scored: Vec<ScoredChunk> = self
+                .entries
+                .lock()
+                .map_err(|_| Self::poisoned())?
+                .iter()
+                .map(|(chunk, embedding)| ScoredChunk {
+                    chunk: chunk.clone(),
+                    score: cosine_similarity(&query, embedding),
+                })
+                .collect();
+            scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+            scored.truncate(top_k);
+            Ok(scored)
+        })
+    }
+}
+
+/// Outcome of [`LlmBuilder::retrieve_and_answer`](crate::LlmBuilder::retrieve_and_answer).
+#[derive(Debug)]
+pub struct RagAnswer<O> {
+    pub answer: O,
+    /// The chunks given to the model as sources; the model cites the first as `[1]`.
+    pub citations: Vec<ScoredChunk>,
+}
+
+/// Retrieve the chunks relevant to the last user message and add them as numbered sources
+/// in a system message after the leading system messages.
+pub(crate) async fn augment(
+    index: &dyn VectorIndex,
+    top_k: usize,
+    messages: &mut Vec<Message>,
+) -> Result<Vec<ScoredChunk>, LlmError> {
+    let Some(query) = last_user_message(messages) else {
+        return Err(LlmError::Builder(
+            "retrieve_and_answer needs a user message to search for".to_string(),
+        ));
+    };
+
+    let citations = index.search(query, top_k).await?;
+    if citations.is_empty() {
+        return Ok(citations);
+    }
+
+    let sources: Vec<String> = citations
+        .iter()
+        .enumerate()
+        .map(|(number, scored)| {
+            format!(
+                "[{}] {}: {}",
+                number + 1,
+                scored.chunk.source,
+                scored.chunk.text
+            )
+        })
+        .collect();
+    insert_context(
+        messages,
+        format!(
+            "Answer using the numbered sources below and cite them like [1].\n\n{}",
+            sources.join("\n\n")
+        ),
+    );
+    Ok(citations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChatRole;
+    use crate::core::fixtures::{ScriptedTransport, mock_openai, responses_answer};
+    use crate::memory::HashEmbedder;
+
+    #[test]
+    fn test_token_chunker_overlaps() {
+        let chunks = TokenChunker::new(4)
+            .with_overlap(1)
+            .chunk("one two three four five six seven");
+        assert_eq!(chunks, vec!["one two three four", "four five six seven"]);
+    }
+
+    #[test]
+    fn test_recursive_chunker_prefers_coarse_boundaries() {
+        let text = "First paragraph.\n\nSecond paragraph is a little longer. It has two sentences.";
+        let chunks = RecursiveChunker::new(40).chunk(text);
+        assert_eq!(
+            chunks,
+            vec![
+                "First paragraph.",
+                "Second paragraph is a little longer.",
+                "It has two sentences."
+            ]
+        );
+        assert!(chunks.iter().all(|chunk| text.contains(chunk.as_str())));
+
+        let chunks = RecursiveChunker::new(4)
+            .with_separators(&[])
+            .chunk("abcdefghij");
+        assert_eq!(chunks, vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_index_search() {
+        let index = InMemoryIndex::new(HashEmbedder::default());
+        let chunker = RecursiveChunker::new(60);
+        index
+            .add(chunk_document(
+                "handbook.md",
+                "Employees get 30 vacation days per year.\n\nThe office opens at 8am.",
+                &chunker,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(index.len(), 2);
+
+        let results = index.search("How many vacation days?", 1).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.index, 0);
+        assert_eq!(results[0].chunk.source, "handbook.md");
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_and_answer_cites_retrieved_chunks() {
+        #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+        struct VacationAnswer {
+            days: i64,
+            #[allow(dead_code)]
+            source: i64,
+        }
+
+        let index = InMemoryIndex::new(HashEmbedder::default());
+        index
+            .add(chunk_document(
+                "handbook.md",
+                "Employees get 30 vacation days per year.\n\nThe office opens at 8am.",
+                &RecursiveChunker::new(60),
+            ))
+            .await
+            .unwrap();
+
+        let transport =
+            ScriptedTransport::answering(responses_answer("{\"days\":30,\"source\":1}"));
+        let answer = mock_openai(transport.clone())
+            .messages(vec![Message::new(
+                ChatRole::User,
+                "How many vacation days do I get?",
+            )])
+            .retrieve_and_answer::<VacationAnswer>(&index, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(answer.answer.content.days, 30);
+        assert_eq!(answer.citations.len(), 1);
+        assert_eq!(answer.citations[0].chunk.source, "handbook.md");
+        assert_eq!(
+            answer.citations[0].chunk.text,
+            "Employees get 30 vacation days per year."
+        );
+
+        let bodies = transport.bodies();
+        let input = bodies[0]["input"].as_array().unwrap();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input[0]["role"], "system");
+        assert_eq!(
+            input[0]["content"],
+            "Answer using the numbered sources below and cite them like [1].\n\n\
+             [1] handbook.md: Employees get 30 vacation days per year."
+        );
+        assert_eq!(input[1]["content"], "How many vacation days do I get?");
+    }
+}
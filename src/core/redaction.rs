@@ -0,0 +1,437 @@
+//! Masking of personal data in outgoing messages and of secrets in diagnostics.
+//!
+//! A [`Redactor`] replaces emails, phone numbers, credit card numbers and any custom patterns
+//! with numbered placeholders such as `[EMAIL_1]` before a request is sent. The same value
+//! always gets the same placeholder within a request, so the model can still refer to it.
+//! With [`Redactor::restore`] the placeholders in the answer are replaced with the original
+//! values before it is parsed.
+//!
+//! Attach it to a request with [`LlmBuilder::redact`](crate::LlmBuilder::redact), or register
+//! it on [`Guardrails`](crate::guardrails::Guardrails) like any other guardrail when nothing
+//! needs restoring.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::redaction::Redactor;
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let redactor = Redactor::new()
+//!     .pattern("EMPLOYEE_ID", r"\bEMP-\d{6}\b")?
+//!     .restore(true);
+//!
+//! // The model sees "[EMAIL_1]" and "[EMPLOYEE_ID_1]", the reply contains the real values
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Draft a welcome mail to jane@example.com for EMP-004211")])
+//!     .redact(redactor)
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Request inspectors, response inspectors and tracing only see data that went through a
+//! [`LogRedactor`], which masks API keys and other secrets by default. See
+//! [`LlmBuilder::redact_logs`](crate::LlmBuilder::redact_logs) to add rules.
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use super::error::LlmError;
+use super::guardrails::{CARD_PATTERN, Check, EMAIL_PATTERN, Guardrail, PHONE_PATTERN};
+use super::types::BoxFuture;
+
+/// Replaces sensitive values with placeholders.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<(String, Regex)>,
+    restore: bool,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Redactor {
+    /// Mask emails (`EMAIL`), credit card numbers (`CARD`) and phone numbers (`PHONE`).
+    pub fn new() -> Self {
+        // Cards before phone numbers, which would otherwise match parts of card numbers
+        let patterns = [
+            ("EMAIL", EMAIL_PATTERN),
+            ("CARD", CARD_PATTERN),
+            ("PHONE", PHONE_PATTERN),
+        ];
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|(label, pattern)| {
+                    (
+                        label.to_string(),
+                        Regex::new(pattern).expect("built-in PII pattern is valid"),
+                    )
+                })
+                .collect(),
+            restore: false,
+        }
+    }
+
+    /// Start without the built-in patterns.
+    pub fn empty() -> Self {
+        Self {
+            patterns: Vec::new(),
+            restore: false,
+        }
+    }
+
+    /// Also mask matches of `pattern`, with placeholders named after `label`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LlmError::ProviderConfiguration`] if `pattern` is not a valid regex.
+    pub fn pattern(mut self, label: impl Into<String>, pattern: &str) -> Result<Self, LlmError> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            LlmError::ProviderConfiguration(format!("Invalid redaction pattern '{pattern}': {e}"))
+        })?;
+        self.patterns.push((label.into(), regex));
+        Ok(self)
+    }
+
+    /// Replace placeholders in the model's answer with the original values (default `false`).
+    pub fn restore(mut self, restore: bool) -> Self {
+        self.restore = restore;
+        self
+    }
+
+    pub(crate) fn restores(&self) -> bool {
+        self.restore
+    }
+
+    /// Mask `text`, recording the placeholders in `redactions`.
+    pub fn redact(&self, text: &str, redactions: &mut Redactions) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, (label, pattern)| {
+                pattern
+                    .replace_all(&text, |caps: &Captures| {
+                        redactions.placeholder(label, &caps[0])
+                    })
+                    .into_owned()
+            })
+    }
+}
+
+impl Guardrail for Redactor {
+    fn name(&self) -> &str {
+        "redactor"
+    }
+
+    fn check<'a>(&'a self, text: &'a str) -> BoxFuture<'a, Result<Check, LlmError>> {
+        let mut redactions = Redactions::default();
+        let redacted = self.redact(text, &mut redactions);
+        let check = if redactions.is_empty() {
+            Check::Pass
+        } else {
+            Check::Violation {
+                reason: "Text contains sensitive data".to_string(),
+                redacted: Some(redacted),
+            }
+        };
+        Box::pin(async move { Ok(check) })
+    }
+}
+
+/// The placeholders handed out while redacting one request.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Redactions {
+    /// `(placeholder, original)` in the order they were first seen.
+    entries: Vec<(String, String)>,
+}
+
+impl Redactions {
+    fn placeholder(&mut self, label: &str, value: &str) -> String {
+        if let Some((placeholder, _)) = self.entries.iter().find(|(_, original)| original == value)
+        {
+            return placeholder.clone();
+        }
+
+        let prefix = format!("[{label}_");
+        let number = self
+            .entries
+            .iter()
+            .filter(|(placeholder, _)| placeholder.starts_with(&prefix))
+            .count()
+            + 1;
+        let placeholder = format!("{prefix}{number}]");
+        self.entries.push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+
+    /// Replace every placeholder in `text` with its original value.
+    pub fn restore(&self, text: &str) -> String {
+        self.entries
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder, original)
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// Headers whose values are credentials.
+pub(crate) const SENSITIVE_HEADERS: &[&str] =
+    &["authorization", "x-goog-api-key", "api-key", "x-api-key"];
+
+/// Rules applied to request and response bodies, URLs and error messages before they reach
+/// inspectors or tracing. Masked values are replaced with `[REDACTED]`.
+///
+/// By default it masks the credentials sent in the request headers, strings that look like
+/// OpenAI, OpenRouter or Google API keys, and the values of JSON fields named like
+/// credentials. Message content is left as is unless [`mask_content`](Self::mask_content) is
+/// set.
+#[derive(Debug, Clone)]
+pub struct LogRedactor {
+    secrets: Vec<String>,
+    fields: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+/// JSON fields whose values are always masked (compared case-insensitively).
+const SECRET_FIELDS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "authorization",
+    "password",
+    "secret",
+    "access_token",
+    "refresh_token",
+];
+
+/// Fields that carry message content in provider payloads.
+const CONTENT_FIELDS: &[&str] = &["content", "text", "instructions", "arguments"];
+
+const KEY_PATTERNS: &[&str] = &[r"\bsk-[A-Za-z0-9_-]{20,}", r"\bAIza[0-9A-Za-z_-]{35}"];
+
+impl Default for LogRedactor {
+    fn default() -> Self {
+        Self {
+            secrets: Vec::new(),
+            fields: SECRET_FIELDS
+                .iter()
+                .map(|field| field.to_string())
+                .collect(),
+            patterns: KEY_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("built-in key pattern is valid"))
+                .collect(),
+        }
+    }
+}
+
+impl LogRedactor {
+    /// The default rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No rules at all, not even for request headers. Only use this for local debugging.
+    pub fn none() -> Self {
+        Self {
+            secrets: Vec::new(),
+            fields: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Mask every occurrence of `secret`.
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        let secret = secret.into();
+        if !secret.is_empty() {
+            self.secrets.push(secret);
+        }
+        self
+    }
+
+    /// Mask the value of every JSON field named `name` (case-insensitive).
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into().to_lowercase());
+        self
+    }
+
+    /// Mask every match of `pattern`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LlmError::ProviderConfiguration`] if `pattern` is not a valid regex.
+    pub fn pattern(mut self, pattern: &str) -> Result<Self, LlmError> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            LlmError::ProviderConfiguration(format!("Invalid redaction pattern '{pattern}': {e}"))
+        })?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Also mask message content, tool arguments and instructions.
+    pub fn mask_content(self) -> Self {
+        CONTENT_FIELDS
+            .iter()
+            .fold(self, |redactor, field| redactor.field(*field))
+    }
+
+    /// These rules plus the credentials in `headers`.
+    pub(crate) fn with_headers(&self, headers: &[(String, String)]) -> Self {
+        let credentials = headers
+            .iter()
+            .filter(|(name, _)| SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()))
+            .map(|(_, value)| value.strip_prefix("Bearer ").unwrap_or(value).to_string())
+            // Masking very short values would garble unrelated text
+            .filter(|secret| secret.len() >= 8);
+        credentials.fold(self.clone(), LogRedactor::secret)
+    }
+
+    /// Mask secrets in `text`.
+    pub fn redact_text(&self, text: &str) -> String {
+        let text = self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret, REDACTED)
+        });
+        self.patterns.iter().fold(text, |text, pattern| {
+            pattern.replace_all(&text, REDACTED).into_owned()
+        })
+    }
+
+    /// Mask secrets in every string of `value`, and the whole value of masked fields.
+    pub fn redact_value(&self, value: &Value) -> Value {
+        match value {
+            Value::String(text) => Value::String(self.redact_text(text)),
+            Value::Array(items) => {
+                Value::Array(items.iter().map(|v| self.redact_value(v)).collect())
+            }
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        let value = if self.fields.contains(&key.to_lowercase()) {
+                            Value::String(REDACTED.to_string())
+                        } else {
+                            self.redact_value(value)
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, mock_openai, responses_answer};
+    use crate::{ChatRole, Message, TextResponse};
+
+    #[test]
+    fn test_redact_and_restore_round_trip() {
+        let redactor = Redactor::new().pattern("ORDER", r"\bORD-\d+\b").unwrap();
+        let mut redactions = Redactions::default();
+
+        let redacted = redactor.redact(
+            "jane@example.com (card 4111 1111 1111 1111) asked about ORD-77, cc bob@example.com and jane@example.com",
+            &mut redactions,
+        );
+
+        assert_eq!(
+            redacted,
+            "[EMAIL_1] (card [CARD_1]) asked about [ORDER_1], cc [EMAIL_2] and [EMAIL_1]"
+        );
+        assert_eq!(
+            redactions.restore("Replied to [EMAIL_2] about [ORDER_1]"),
+            "Replied to bob@example.com about ORD-77"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redactor_as_guardrail() {
+        let redactor = Redactor::empty().pattern("SECRET", "hunter2").unwrap();
+
+        assert_eq!(redactor.check("nothing here").await.unwrap(), Check::Pass);
+        assert_eq!(
+            redactor.check("password hunter2").await.unwrap(),
+            Check::Violation {
+                reason: "Text contains sensitive data".to_string(),
+                redacted: Some("password [SECRET_1]".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern_errors() {
+        assert!(matches!(
+            Redactor::new().pattern("BAD", "("),
+            Err(LlmError::ProviderConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn test_log_redactor_masks_credentials() {
+        let redactor = LogRedactor::new().with_headers(&[
+            ("Authorization".to_string(), "Bearer my-org-key".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
+
+        let value = redactor.redact_value(&serde_json::json!({
+            "model": "gpt-4o",
+            "input": "my-org-key and sk-abcdefghijklmnopqrstuvwxyz",
+            "metadata": { "api_key": { "nested": true } }
+        }));
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "model": "gpt-4o",
+                "input": "[REDACTED] and [REDACTED]",
+                "metadata": { "api_key": "[REDACTED]" }
+            })
+        );
+    }
+
+    #[test]
+    fn test_log_redactor_masks_content() {
+        let value = serde_json::json!({ "content": "hello", "role": "user" });
+
+        assert_eq!(LogRedactor::new().redact_value(&value), value);
+        assert_eq!(
+            LogRedactor::new().mask_content().redact_value(&value),
+            serde_json::json!({ "content": "[REDACTED]", "role": "user" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_placeholders_are_restored_in_the_answer() {
+        let transport =
+            ScriptedTransport::answering(responses_answer("Reminder sent to [EMAIL_1]"));
+
+        let result = mock_openai(transport.clone())
+            .messages(vec![Message::new(
+                ChatRole::User,
+                "Remind jane@example.com about her card 4111 1111 1111 1111",
+            )])
+            .redact(Redactor::new().restore(true))
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(result.text, "Reminder sent to jane@example.com");
+
+        assert_eq!(
+            transport.bodies()[0]["input"][0]["content"],
+            "Remind [EMAIL_1] about her card [CARD_1]"
+        );
+    }
+}
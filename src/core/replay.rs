@@ -0,0 +1,476 @@
+//! Record an agent run and replay it after changing the code around it.
+//!
+//! A [`RunRecorder`] captures the model responses and tool results of a run into a
+//! [`RunTrace`], which can be saved as JSON. Replaying the trace serves the recorded model
+//! responses in order, whatever the requests look like, so changes to prompts, tool handling
+//! or output parsing can be debugged against historical runs without calling the provider.
+//! Tools run live, or return their recorded results with [`RunTrace::stub_tools`].
+//!
+//! Unlike a [`Cassette`](crate::Cassette), which only replays requests that match the recorded
+//! ones, a replay keeps going when the requests change and only fails once the run makes more
+//! model requests or different tool calls than the recorded one.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::replay::{RunRecorder, RunTrace};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm, tool, toolset};
+//!
+//! /// Look up the status of an order
+//! /// order_id: Order to look up
+//! #[tool]
+//! fn order_status(order_id: String) -> String {
+//!     format!("{order_id} has shipped")
+//! }
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let messages = vec![Message::new(ChatRole::User, "Where is order 42?")];
+//!
+//! let recorder = RunRecorder::new();
+//! llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(messages.clone())
+//!     .transport(recorder.transport())
+//!     .tools(recorder.record_tools(toolset![order_status])?)
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! recorder.trace().save("traces/order-status.json")?;
+//!
+//! // Later, after changing the agent
+//! let trace = RunTrace::load("traces/order-status.json")?;
+//! let replayed = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Custom("replayed".to_string()))?
+//!     .model("gpt-4o-mini")
+//!     .messages(messages)
+//!     .transport(trace.transport())
+//!     .tools(trace.stub_tools(toolset![order_status])?)
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::cassette::{read_json, write_json};
+use super::error::LlmError;
+use super::traits::ToolFunction;
+use super::transport::{ReqwestTransport, Transport, TransportRequest, TransportResponse};
+use super::types::{BoxFuture, Tool, ToolSet};
+
+/// The model responses and tool results of a run, in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunTrace {
+    pub responses: Vec<RecordedResponse>,
+    pub tool_calls: Vec<RecordedToolCall>,
+}
+
+/// An HTTP response of the provider, including error responses that were retried.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// A tool call and the result the tool returned, or the error it failed with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedToolCall {
+    pub name: String,
+    pub arguments: Value,
+    /// `null` if the call failed
+    pub result: Value,
+    /// Message of the error the call failed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RunTrace {
+    /// Read a trace saved with [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LlmError> {
+        read_json(path.as_ref(), "run trace")
+    }
+
+    /// Write the trace as pretty-printed JSON, creating missing directories.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LlmError> {
+        write_json(path.as_ref(), self, "run trace")
+    }
+
+    /// A transport that answers every request with the next recorded model response.
+    pub fn transport(&self) -> Arc<dyn Transport> {
+        Arc::new(ReplayTransport {
+            responses: Mutex::new(self.responses.clone().into()),
+        })
+    }
+
+    /// Replace the tools of `toolset` with stubs that return the recorded result of the
+    /// first unused call with the same name and arguments, instead of executing the tool.
+    /// Calls that failed when recorded, or were not recorded, fail with
+    /// [`LlmError::ToolExecution`].
+    pub fn stub_tools<Ctx: Send + Sync + 'static>(
+        &self,
+        toolset: ToolSet<Ctx>,
+    ) -> Result<ToolSet<Ctx>, LlmError> {
+        let calls = Arc::new(Mutex::new(
+            self.tool_calls
+                .iter()
+                .cloned()
+                .map(|call| (call, false))
+                .collect::<Vec<_>>(),
+        ));
+        toolset.map_tools(|tool| {
+            Arc::new(StubbedTool {
+                tool,
+                calls: calls.clone(),
+            })
+        })
+    }
+}
+
+/// Records a run into a [`RunTrace`].
+pub struct RunRecorder {
+    inner: Arc<dyn Transport>,
+    trace: Arc<Mutex<RunTrace>>,
+}
+
+impl Default for RunRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunRecorder {
+    /// A recorder that sends requests with the default `reqwest` transport.
+    pub fn new() -> Self {
+        Self::with_transport(Arc::new(ReqwestTransport::default()))
+    }
+
+    /// A recorder that sends requests through `transport`.
+    pub fn with_transport(transport: Arc<dyn Transport>) -> Self {
+        Self {
+            inner: transport,
+            trace: Arc::new(Mutex::new(RunTrace::default())),
+        }
+    }
+
+    /// A transport that sends requests and records the responses.
+    pub fn transport(&self) -> Arc<dyn Transport> {
+        Arc::new(RecordingTransport {
+            inner: self.inner.clone(),
+            trace: self.trace.clone(),
+        })
+    }
+
+    /// Wrap the tools of `toolset` to record their calls and results, including errors.
+    pub fn record_tools<Ctx: Send + Sync + 'static>(
+        &self,
+        toolset: ToolSet<Ctx>,
+    ) -> Result<ToolSet<Ctx>, LlmError> {
+        toolset.map_tools(|tool| {
+            Arc::new(RecordingTool {
+                tool,
+                trace: self.trace.clone(),
+            })
+        })
+    }
+
+    /// The run recorded so far.
+    pub fn trace(&self) -> RunTrace {
+        self.trace.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    trace: Arc<Mutex<RunTrace>>,
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        let response = self.inner.send(request).await?;
+        if let Ok(mut trace) = self.trace.lock() {
+            trace.responses.push(RecordedResponse {
+                status: response.status,
+                body: response.body.clone(),
+            });
+        }
+        Ok(response)
+    }
+}
+
+struct ReplayTransport {
+    responses: Mutex<std::collections::VecDeque<RecordedResponse>>,
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, _request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        let response = self
+            .responses
+            .lock()
+            .map_err(|_| LlmError::ProviderConfiguration("Run trace lock poisoned".to_string()))?
+            .pop_front()
+            .ok_or_else(|| {
+                LlmError::ProviderConfiguration(
+                    "The replayed run made more model requests than the recorded one".to_string(),
+                )
+            })?;
+        Ok(TransportResponse {
+            status: response.status,
+            body: response.body,
+        })
+    }
+}
+
+struct RecordingTool<Ctx> {
+    tool: Arc<dyn ToolFunction<Ctx>>,
+    trace: Arc<Mutex<RunTrace>>,
+}
+
+impl<Ctx: Send + Sync> ToolFunction<Ctx> for RecordingTool<Ctx> {
+    fn schema(&self) -> Tool {
+        self.tool.schema()
+    }
+
+    fn execute<'a>(
+        &'a self,
+        ctx: &'a Ctx,
+        params: Value,
+    ) -> BoxFuture<'a, Result<Value, LlmError>> {
+        Box::pin(async move {
+            let result = self.tool.execute(ctx, params.clone()).await;
+            if let Ok(mut trace) = self.trace.lock() {
+                trace.tool_calls.push(RecordedToolCall {
+                    name: self.tool.schema().name,
+                    arguments: params,
+                    result: result.as_ref().cloned().unwrap_or(Value::Null),
+                    error: result.as_ref().err().map(|e| match e {
+                        LlmError::ToolExecution { message, .. } => message.clone(),
+                        other => other.to_string(),
+                    }),
+                });
+            }
+            result
+        })
+    }
+
+    fn cacheable(&self) -> bool {
+        self.tool.cacheable()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.tool.output_schema()
+    }
+
+    fn deprecated(&self) -> Option<&str> {
+        self.tool.deprecated()
+    }
+
+    fn hidden(&self) -> bool {
+        self.tool.hidden()
+    }
+
+    fn ignore_unknown_arguments(&self) -> bool {
+        self.tool.ignore_unknown_arguments()
+    }
+}
+
+struct StubbedTool<Ctx> {
+    tool: Arc<dyn ToolFunction<Ctx>>,
+    /// Recorded calls of all stubbed tools and whether they were replayed
+    calls: Arc<Mutex<Vec<(RecordedToolCall, bool)>>>,
+}
+
+impl<Ctx: Send + Sync> ToolFunction<Ctx> for StubbedTool<Ctx> {
+    fn schema(&self) -> Tool {
+        self.tool.schema()
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a Ctx,
+        params: Value,
+    ) -> BoxFuture<'a, Result<Value, LlmError>> {
+        let name = self.tool.schema().name;
+        let result = self
+            .calls
+            .lock()
+            .ok()
+            .and_then(|mut calls| {
+                let (call, used) = calls
+                    .iter_mut()
+                    .find(|(call, used)| !*used && call.name == name && call.arguments == params)?;
+                *used = true;
+                Some(call.clone())
+            })
+            .ok_or_else(|| LlmError::ToolExecution {
+                message: format!("No recorded result for {name} with arguments {params}"),
+                source: None,
+            })
+            .and_then(|call| match call.error {
+                Some(message) => Err(LlmError::ToolExecution {
+                    message,
+                    source: None,
+                }),
+                None => Ok(call.result),
+            });
+        Box::pin(async move { result })
+    }
+
+    fn cacheable(&self) -> bool {
+        self.tool.cacheable()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.tool.output_schema()
+    }
+
+    fn deprecated(&self) -> Option<&str> {
+        self.tool.deprecated()
+    }
+
+    fn hidden(&self) -> bool {
+        self.tool.hidden()
+    }
+
+    fn ignore_unknown_arguments(&self) -> bool {
+        self.tool.ignore_unknown_arguments()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{
+        ScriptedTransport, mock_openai, responses_answer, responses_tool_call, sum_toolset,
+    };
+    use crate::core::{ToolCall, ToolRegistry};
+    use crate::{ChatRole, Message, TextResponse};
+    use serde_json::json;
+
+    /// Fails for negative numbers.
+    struct SqrtTool;
+
+    impl ToolFunction for SqrtTool {
+        fn schema(&self) -> Tool {
+            Tool {
+                name: "sqrt".to_string(),
+                description: None,
+                parameters: json!({ "type": "object", "properties": { "x": { "type": "number" } } }),
+                strict: None,
+            }
+        }
+
+        fn execute<'a>(
+            &'a self,
+            _ctx: &'a (),
+            params: Value,
+        ) -> BoxFuture<'a, Result<Value, LlmError>> {
+            Box::pin(async move {
+                match params["x"].as_f64() {
+                    Some(x) if x >= 0.0 => Ok(json!(x.sqrt())),
+                    _ => Err(LlmError::ToolExecution {
+                        message: "x must not be negative".to_string(),
+                        source: None,
+                    }),
+                }
+            })
+        }
+    }
+
+    fn toolset() -> ToolSet {
+        let registry = ToolRegistry::new();
+        registry.register(Arc::new(SqrtTool)).unwrap();
+        ToolSet { registry }
+    }
+
+    fn call(x: f64) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            call_id: "call_1".to_string(),
+            name: "sqrt".to_string(),
+            arguments: json!({ "x": x }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_tool_calls_are_recorded_and_replayed() {
+        let recorder = RunRecorder::new();
+        let tools = recorder.record_tools(toolset()).unwrap();
+        assert_eq!(
+            tools.registry.execute(&call(4.0)).await.unwrap(),
+            json!(2.0)
+        );
+        assert!(tools.registry.execute(&call(-1.0)).await.is_err());
+
+        let trace = recorder.trace();
+        assert_eq!(trace.tool_calls.len(), 2);
+        assert_eq!(trace.tool_calls[0].error, None);
+        assert_eq!(trace.tool_calls[1].result, Value::Null);
+        assert_eq!(
+            trace.tool_calls[1].error.as_deref(),
+            Some("x must not be negative")
+        );
+
+        let stubs = trace.stub_tools(toolset()).unwrap();
+        assert_eq!(
+            stubs.registry.execute(&call(4.0)).await.unwrap(),
+            json!(2.0)
+        );
+        let err = stubs.registry.execute(&call(-1.0)).await.unwrap_err();
+        assert!(
+            matches!(err, LlmError::ToolExecution { ref message, .. } if message == "x must not be negative"),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorded_run_replays_with_stubbed_tools() {
+        let recorder = RunRecorder::with_transport(ScriptedTransport::new(vec![
+            (
+                200,
+                responses_tool_call("calculate_sum", json!({ "a": 1, "b": 2 })),
+            ),
+            (200, responses_answer("The sum is 3")),
+        ]));
+
+        mock_openai(recorder.transport())
+            .messages(vec![Message::new(ChatRole::User, "What is 1 + 2?")])
+            .tools(recorder.record_tools(sum_toolset()).unwrap())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("rsai-run-trace-{}.json", std::process::id()));
+        recorder.trace().save(&path).unwrap();
+        let trace = RunTrace::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(trace, recorder.trace());
+        assert_eq!(trace.responses.len(), 2);
+        assert_eq!(trace.tool_calls.len(), 1);
+        assert_eq!(trace.tool_calls[0].result, json!({ "sum": 3 }));
+
+        // A changed prompt still replays the recorded answers, without executing the tool
+        let replay = |trace: &RunTrace| {
+            mock_openai(trace.transport())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2.")])
+                .tools(trace.stub_tools(sum_toolset()).unwrap())
+        };
+        let response = replay(&trace).complete::<TextResponse>().await.unwrap();
+        assert_eq!(response.text, "The sum is 3");
+
+        // Tool calls that were not recorded fail the replay
+        let mut diverged = trace.clone();
+        diverged.tool_calls[0].arguments = json!({ "a": 2, "b": 2 });
+        let err = replay(&diverged)
+            .complete::<TextResponse>()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::ToolExecution { .. }), "{err:?}");
+    }
+}
@@ -0,0 +1,161 @@
+//! Strategies deciding whether and when failed HTTP requests are retried.
+//!
+//! Every attempt that fails with a network error or an error status is passed to a
+//! [`RetryPolicy`], which returns the delay before the next attempt or `None` to give up.
+//! [`ExponentialBackoff`] is the default, configured by the retry fields of
+//! [`HttpClientConfig`](crate::HttpClientConfig). Set another policy with
+//! [`LlmBuilder::retry_policy`](crate::LlmBuilder::retry_policy).
+//!
+//! # Example
+//! ```no_run
+//! use std::time::{Duration, Instant};
+//!
+//! use rsai::retry::{RetryPolicy, is_transient};
+//! use rsai::{ApiKey, ChatRole, LlmError, Message, Provider, TextResponse, llm};
+//!
+//! /// Retries every second until a deadline.
+//! struct Deadline(Instant);
+//!
+//! impl RetryPolicy for Deadline {
+//!     fn should_retry(&self, error: &LlmError, _attempt: u32) -> Option<Duration> {
+//!         let delay = Duration::from_secs(1);
+//!         (is_transient(error) && Instant::now() + delay < self.0).then_some(delay)
+//!     }
+//! }
+//!
+//! # async fn example() -> Result<(), LlmError> {
+//! let reply = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Hello")])
+//!     .retry_policy(Deadline(Instant::now() + Duration::from_secs(30)))
+//!     .complete::<TextResponse>()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use super::error::LlmError;
+
+/// Decides whether a failed HTTP request is sent again.
+pub trait RetryPolicy: Send + Sync {
+    /// The delay before retrying after attempt `attempt` (0 for the first request) failed
+    /// with `error`, or `None` to fail with `error`.
+    ///
+    /// `error` is an [`LlmError::Network`] or an [`LlmError::Api`] with the status code.
+    fn should_retry(&self, error: &LlmError, attempt: u32) -> Option<Duration>;
+}
+
+/// Whether `error` is worth retrying: network failures, rate limits (429) and server errors
+/// (5xx).
+pub fn is_transient(error: &LlmError) -> bool {
+    match error {
+        LlmError::Network { .. } => true,
+        LlmError::Api {
+            status_code: Some(status),
+            ..
+        } => is_transient_status(*status),
+        _ => false,
+    }
+}
+
+pub(crate) fn is_transient_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// Retry transient errors with exponentially growing delays and +/- 10% jitter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExponentialBackoff {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub initial_delay: Duration,
+    /// Cap on the delay
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy for ExponentialBackoff {
+    fn should_retry(&self, error: &LlmError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries || !is_transient(error) {
+            return None;
+        }
+
+        let base_delay = self.initial_delay.as_millis() as f64 * 2_f64.powi(attempt as i32);
+        let jitter_factor = rand::random::<f64>() * 0.2 + 0.9;
+        let delay = Duration::from_millis((base_delay * jitter_factor) as u64);
+        Some(delay.min(self.max_delay))
+    }
+}
+
+/// Retry transient errors after the same delay every time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedDelay {
+    pub max_retries: u32,
+    pub delay: Duration,
+}
+
+impl RetryPolicy for FixedDelay {
+    fn should_retry(&self, error: &LlmError, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_retries && is_transient(error)).then_some(self.delay)
+    }
+}
+
+/// Never retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoRetry;
+
+impl RetryPolicy for NoRetry {
+    fn should_retry(&self, _error: &LlmError, _attempt: u32) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(status: u16) -> LlmError {
+        LlmError::Api {
+            message: "error".to_string(),
+            status_code: Some(status),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn test_exponential_backoff_doubles_up_to_the_cap() {
+        let policy = ExponentialBackoff {
+            max_retries: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        };
+
+        let first = policy.should_retry(&api_error(503), 0).unwrap();
+        assert!(first >= Duration::from_millis(90) && first <= Duration::from_millis(110));
+        let second = policy.should_retry(&api_error(429), 1).unwrap();
+        assert!(second >= Duration::from_millis(180) && second <= Duration::from_millis(220));
+        assert_eq!(
+            policy.should_retry(&api_error(500), 4),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(policy.should_retry(&api_error(500), 5), None);
+        assert_eq!(policy.should_retry(&api_error(400), 0), None);
+    }
+
+    #[test]
+    fn test_fixed_delay_and_no_retry() {
+        let fixed = FixedDelay {
+            max_retries: 2,
+            delay: Duration::from_secs(1),
+        };
+        assert_eq!(
+            fixed.should_retry(&api_error(502), 1),
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(fixed.should_retry(&api_error(502), 2), None);
+        assert_eq!(fixed.should_retry(&api_error(401), 0), None);
+
+        assert_eq!(NoRetry.should_retry(&api_error(503), 0), None);
+    }
+}
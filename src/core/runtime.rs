@@ -0,0 +1,86 @@
+//! Timers for HTTP retries, background polling, hedged requests and the tool calling loop, and
+//! aborting requests with a [`CancellationToken`].
+//!
+//! With the default `runtime-tokio` feature these are tokio's timers. Without it, they are
+//! driven by the single timer thread of `futures-timer`, so rsai runs under any executor,
+//! e.g. async-std or smol. The default `reqwest` transport still needs a tokio reactor; set a
+//! custom [`Transport`](crate::Transport) when running without one.
+
+use std::fmt;
+use std::time::Duration;
+
+use futures::future::{Either, select};
+use tokio_util::sync::CancellationToken;
+
+use super::error::LlmError;
+
+/// Returned by [`timeout`] when the deadline passes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Wait until `duration` has passed.
+#[cfg(feature = "runtime-tokio")]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Wait until `duration` has passed.
+#[cfg(not(feature = "runtime-tokio"))]
+pub(crate) async fn sleep(duration: Duration) {
+    futures_timer::Delay::new(duration).await
+}
+
+/// Run `future` to completion unless `signal` is cancelled first, in which case the future is
+/// dropped, cancelling whatever it was waiting on.
+pub(crate) async fn abortable<R>(
+    signal: Option<CancellationToken>,
+    future: impl Future<Output = Result<R, LlmError>>,
+) -> Result<R, LlmError> {
+    match signal {
+        // The signal is polled first, so an abort wins over a result that is ready too
+        Some(signal) => {
+            match select(std::pin::pin!(signal.cancelled()), std::pin::pin!(future)).await {
+                Either::Left(_) => Err(LlmError::Aborted),
+                Either::Right((result, _)) => result,
+            }
+        }
+        None => future.await,
+    }
+}
+
+/// Run `future` to completion unless `duration` passes first, in which case it is dropped.
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    let future = std::pin::pin!(future);
+    let deadline = std::pin::pin!(sleep(duration));
+    match select(future, deadline).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout_drops_slow_futures() {
+        assert_eq!(timeout(Duration::from_secs(5), async { 1 }).await, Ok(1));
+
+        let pending = futures::future::pending::<()>();
+        assert_eq!(
+            timeout(Duration::from_millis(10), pending).await,
+            Err(Elapsed)
+        );
+    }
+}
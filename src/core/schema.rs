@@ -0,0 +1,1276 @@
+//! Rewrites JSON Schemas that strict structured output rejects into equivalent accepted forms.
+//!
+//! Enums with data-carrying variants produce a `oneOf` root, which OpenAI strict mode does not
+//! accept. [`TaggedUnion`] turns such a schema into a single object with a string discriminator
+//! plus the (nullable) fields of every variant, and turns the model's answer back into serde's
+//! representation before deserializing.
+//!
+//! String-keyed maps (`HashMap<String, T>`) are described by `additionalProperties`, which strict
+//! mode forbids, so they are sent as arrays of `{"key", "value"}` entries and rebuilt on parse.
+//!
+//! Strict mode also rejects keywords next to `$ref`, which is where schemars puts the doc comment
+//! of a field whose type is another schema, so such references are wrapped in a single-branch
+//! `anyOf` that keeps the description.
+//!
+//! Providers without `$ref` support get definitions inlined by [`inline_refs`], with recursive
+//! types expanded to a fixed depth.
+//!
+//! [`validate_value`] checks tool outputs against the schema a tool declares for its result.
+
+use serde_json::{Map, Value, json};
+
+/// Discriminator used for externally tagged enums, which have no tag field of their own.
+const EXTERNAL_DISCRIMINATOR: &str = "variant";
+
+/// How many times a recursive definition is expanded by [`inline_refs`].
+const MAX_INLINE_DEPTH: usize = 3;
+
+/// Marks the schema that replaces a reference past [`MAX_INLINE_DEPTH`].
+const DEPTH_LIMIT_DESCRIPTION: &str = "Maximum nesting depth reached; leave empty";
+
+/// Root keys carried over from the original schema.
+const ROOT_KEYS: [&str; 5] = ["$schema", "title", "description", "$defs", "definitions"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Representation {
+    /// `#[serde(tag = "...")]`: the tag lives next to the variant fields.
+    Internal,
+    /// serde's default: `{"Variant": {...}}`, or `"Variant"` for unit variants.
+    External,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Payload {
+    Unit,
+    /// Struct variant fields and whether each is required.
+    Fields(Vec<(String, bool)>),
+    /// Newtype or tuple variant, stored under a field named after the variant.
+    Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Variant {
+    name: String,
+    payload: Payload,
+    /// Doc comment of the variant.
+    description: Option<String>,
+}
+
+/// A tagged enum schema flattened into one object.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TaggedUnion {
+    discriminator: String,
+    representation: Representation,
+    variants: Vec<Variant>,
+    /// Schemas of all variant fields, shared between variants with identical field schemas.
+    fields: Map<String, Value>,
+}
+
+/// Apply every strict-mode rewrite to a generated schema.
+pub(crate) fn strict_schema(schema: Value) -> Value {
+    let mut schema = match TaggedUnion::from_schema(&schema) {
+        Some(union) => union.strict_schema(&schema),
+        None => schema,
+    };
+    maps_to_entries(&mut schema);
+    wrap_ref_siblings(&mut schema);
+    schema
+}
+
+/// Convert a value in the shape `original` describes into the shape [`strict_schema`]
+/// `(original)` asks for. The inverse of [`restore_value`].
+pub(crate) fn strict_value(original: &Value, value: Value) -> Value {
+    match TaggedUnion::from_schema(original) {
+        Some(union) => {
+            let flattened = union.strict_schema(original);
+            maps_to_entry_values(union.flatten(value), &flattened, &flattened)
+        }
+        None => maps_to_entry_values(value, original, original),
+    }
+}
+
+/// Convert a value produced against [`strict_schema`]`(original)` back into the shape
+/// `original` describes.
+pub(crate) fn restore_value(original: &Value, value: Value) -> Value {
+    match TaggedUnion::from_schema(original) {
+        Some(union) => {
+            let flattened = union.strict_schema(original);
+            union.restore(entries_to_maps(value, &flattened, &flattened))
+        }
+        None => entries_to_maps(value, original, original),
+    }
+}
+
+impl TaggedUnion {
+    /// Detect a tagged enum at the root of `schema`.
+    ///
+    /// Returns `None` for anything else, including enums whose variants declare the same
+    /// field with different schemas.
+    pub(crate) fn from_schema(schema: &Value) -> Option<Self> {
+        let alternatives = schema
+            .get("oneOf")
+            .or_else(|| schema.get("anyOf"))?
+            .as_array()?;
+
+        // A self-referencing enum would point at the flattened root, whose nested values
+        // `restore` does not convert back.
+        if contains_ref(schema, "#") {
+            return None;
+        }
+
+        Self::internal(alternatives).or_else(|| Self::external(alternatives))
+    }
+
+    fn internal(alternatives: &[Value]) -> Option<Self> {
+        let first = alternatives.first()?.get("properties")?.as_object()?;
+        let discriminator = first
+            .keys()
+            .find(|key| {
+                alternatives.iter().all(|alt| {
+                    alt.pointer(&format!("/properties/{key}"))
+                        .and_then(const_string)
+                        .is_some()
+                })
+            })?
+            .clone();
+
+        let mut union = Self::new(discriminator.clone(), Representation::Internal);
+        for alt in alternatives {
+            let properties = alt.get("properties")?.as_object()?;
+            let name = const_string(properties.get(&discriminator)?)?;
+            let required = required_fields(alt);
+
+            let fields = properties
+                .iter()
+                .filter(|(key, _)| **key != discriminator)
+                .map(|(key, field)| {
+                    union.add_field(key, field)?;
+                    Some((key.clone(), required.contains(&key.as_str())))
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            union.variants.push(Variant {
+                name: name.to_string(),
+                payload: if fields.is_empty() {
+                    Payload::Unit
+                } else {
+                    Payload::Fields(fields)
+                },
+                description: description(alt),
+            });
+        }
+
+        Some(union)
+    }
+
+    fn external(alternatives: &[Value]) -> Option<Self> {
+        let mut union = Self::new(EXTERNAL_DISCRIMINATOR.to_string(), Representation::External);
+
+        for alt in alternatives {
+            if let Some(names) = unit_variant_names(alt) {
+                // Variants sharing an `enum` are only grouped when none has a doc comment
+                let description = description(alt).filter(|_| names.len() == 1);
+                union.variants.extend(names.into_iter().map(|name| Variant {
+                    name,
+                    payload: Payload::Unit,
+                    description: description.clone(),
+                }));
+                continue;
+            }
+
+            let properties = alt.get("properties")?.as_object()?;
+            let [(name, payload)] = properties.iter().collect::<Vec<_>>()[..] else {
+                return None;
+            };
+
+            let payload = match payload.get("properties").and_then(Value::as_object) {
+                Some(fields) => {
+                    let required = required_fields(payload);
+                    Payload::Fields(
+                        fields
+                            .iter()
+                            .map(|(key, field)| {
+                                union.add_field(key, field)?;
+                                Some((key.clone(), required.contains(&key.as_str())))
+                            })
+                            .collect::<Option<Vec<_>>>()?,
+                    )
+                }
+                None => {
+                    union.add_field(name, payload)?;
+                    Payload::Value
+                }
+            };
+
+            union.variants.push(Variant {
+                name: name.clone(),
+                payload,
+                description: description(alt),
+            });
+        }
+
+        if union.fields.contains_key(EXTERNAL_DISCRIMINATOR) {
+            return None;
+        }
+
+        Some(union)
+    }
+
+    fn new(discriminator: String, representation: Representation) -> Self {
+        Self {
+            discriminator,
+            representation,
+            variants: Vec::new(),
+            fields: Map::new(),
+        }
+    }
+
+    /// Record a field schema, failing if another variant declared it differently.
+    fn add_field(&mut self, name: &str, schema: &Value) -> Option<()> {
+        match self.fields.get(name) {
+            Some(existing) if existing != schema => None,
+            Some(_) => Some(()),
+            None => {
+                self.fields.insert(name.to_string(), schema.clone());
+                Some(())
+            }
+        }
+    }
+
+    /// The strict-mode compatible object schema, keeping the root metadata and `$defs` of
+    /// `original`.
+    pub(crate) fn strict_schema(&self, original: &Value) -> Value {
+        let variant_names: Vec<&str> = self.variants.iter().map(|v| v.name.as_str()).collect();
+
+        let mut properties = Map::new();
+        properties.insert(
+            self.discriminator.clone(),
+            json!({
+                "type": "string",
+                "enum": variant_names,
+                "description": self.discriminator_description(),
+            }),
+        );
+        for (name, schema) in &self.fields {
+            properties.insert(name.clone(), nullable(schema));
+        }
+
+        let required: Vec<&String> = properties.keys().collect();
+        let mut schema = json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false,
+        });
+
+        for key in ROOT_KEYS {
+            if let (Some(value), Some(obj)) = (original.get(key), schema.as_object_mut()) {
+                obj.insert(key.to_string(), value.clone());
+            }
+        }
+
+        schema
+    }
+
+    /// Tell the model which fields belong to which variant; the others must be `null`.
+    fn discriminator_description(&self) -> String {
+        let variants: Vec<String> = self
+            .variants
+            .iter()
+            .map(|variant| {
+                let summary = match &variant.payload {
+                    Payload::Unit => format!("{} (no fields)", variant.name),
+                    Payload::Fields(fields) => format!(
+                        "{} ({})",
+                        variant.name,
+                        fields
+                            .iter()
+                            .map(|(name, _)| name.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Payload::Value => format!("{0} ({0})", variant.name),
+                };
+                match &variant.description {
+                    Some(description) => format!("{summary}: {description}"),
+                    None => summary,
+                }
+            })
+            .collect();
+
+        format!(
+            "Selected variant. Set only the fields of that variant and null for all others: {}",
+            variants.join("; ")
+        )
+    }
+
+    /// Convert serde's representation into a value matching [`Self::strict_schema`].
+    ///
+    /// Values of unknown variants are returned unchanged.
+    fn flatten(&self, value: Value) -> Value {
+        let variant = match (self.representation, &value) {
+            (Representation::Internal, Value::Object(obj)) => {
+                obj.get(&self.discriminator).and_then(Value::as_str)
+            }
+            (Representation::External, Value::String(name)) => Some(name.as_str()),
+            (Representation::External, Value::Object(obj)) if obj.len() == 1 => {
+                obj.keys().next().map(String::as_str)
+            }
+            _ => None,
+        }
+        .and_then(|name| self.variants.iter().find(|v| v.name == name));
+        let Some(variant) = variant else {
+            return value;
+        };
+
+        let mut fields = match (self.representation, &variant.payload, value) {
+            (Representation::Internal, _, Value::Object(obj)) => obj,
+            (Representation::External, Payload::Fields(_), Value::Object(mut obj)) => {
+                match obj.remove(&variant.name) {
+                    Some(Value::Object(fields)) => fields,
+                    _ => Map::new(),
+                }
+            }
+            // Newtype payloads are stored under a field named after the variant
+            (Representation::External, Payload::Value, Value::Object(obj)) => obj,
+            _ => Map::new(),
+        };
+
+        let mut flattened = Map::new();
+        flattened.insert(
+            self.discriminator.clone(),
+            Value::String(variant.name.clone()),
+        );
+        for field in self.fields.keys() {
+            flattened.insert(field.clone(), fields.remove(field).unwrap_or(Value::Null));
+        }
+        Value::Object(flattened)
+    }
+
+    /// Convert a value matching [`Self::strict_schema`] back into serde's representation.
+    ///
+    /// Values without a known discriminator are returned unchanged.
+    pub(crate) fn restore(&self, value: Value) -> Value {
+        let Value::Object(mut obj) = value else {
+            return value;
+        };
+        let Some(variant) = obj
+            .get(&self.discriminator)
+            .and_then(Value::as_str)
+            .and_then(|name| self.variants.iter().find(|v| v.name == name))
+        else {
+            return Value::Object(obj);
+        };
+
+        let mut take_fields = |fields: &[(String, bool)]| -> Map<String, Value> {
+            fields
+                .iter()
+                .filter_map(|(name, required)| {
+                    let value = obj.remove(name).unwrap_or(Value::Null);
+                    (*required || !value.is_null()).then(|| (name.clone(), value))
+                })
+                .collect()
+        };
+
+        match (self.representation, &variant.payload) {
+            (Representation::Internal, payload) => {
+                let mut restored = match payload {
+                    Payload::Fields(fields) => take_fields(fields),
+                    Payload::Unit | Payload::Value => Map::new(),
+                };
+                restored.insert(
+                    self.discriminator.clone(),
+                    Value::String(variant.name.clone()),
+                );
+                Value::Object(restored)
+            }
+            (Representation::External, Payload::Unit) => Value::String(variant.name.clone()),
+            (Representation::External, Payload::Fields(fields)) => {
+                json!({ variant.name.clone(): take_fields(fields) })
+            }
+            (Representation::External, Payload::Value) => {
+                let value = obj.remove(&variant.name).unwrap_or(Value::Null);
+                json!({ variant.name.clone(): value })
+            }
+        }
+    }
+}
+
+/// The value schema of a string-keyed map, if `schema` describes one.
+fn map_value_schema(schema: &Value) -> Option<&Value> {
+    let has_properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_some_and(|properties| !properties.is_empty());
+    match schema.get("additionalProperties") {
+        Some(value_schema @ Value::Object(_)) if !has_properties => Some(value_schema),
+        _ => None,
+    }
+}
+
+/// Replace every map schema with an array of `{"key", "value"}` entries.
+fn maps_to_entries(schema: &mut Value) {
+    match schema {
+        Value::Object(obj) => {
+            obj.values_mut().for_each(maps_to_entries);
+
+            let Some(value_schema) = map_value_schema(&Value::Object(obj.clone())).cloned() else {
+                return;
+            };
+            let key_schema = obj
+                .remove("propertyNames")
+                .unwrap_or_else(|| json!({ "type": "string" }));
+            let nullable = match obj.get("type") {
+                Some(Value::Array(types)) => types.iter().any(|t| t == "null"),
+                _ => false,
+            };
+
+            obj.remove("additionalProperties");
+            obj.remove("properties");
+            obj.insert(
+                "type".to_string(),
+                if nullable {
+                    json!(["array", "null"])
+                } else {
+                    json!("array")
+                },
+            );
+            obj.insert(
+                "items".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": { "key": key_schema, "value": value_schema },
+                    "required": ["key", "value"],
+                    "additionalProperties": false,
+                }),
+            );
+        }
+        Value::Array(items) => items.iter_mut().for_each(maps_to_entries),
+        _ => {}
+    }
+}
+
+/// Turn `{"key", "value"}` entry arrays back into objects wherever `schema` has a map.
+fn entries_to_maps(value: Value, schema: &Value, root: &Value) -> Value {
+    if let Some(target) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| root.pointer(pointer))
+    {
+        return entries_to_maps(value, target, root);
+    }
+
+    if let Some(alternatives) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        return match alternatives
+            .iter()
+            .find(|alt| accepts_shape(alt, &value, root, true))
+        {
+            Some(alt) => entries_to_maps(value, alt, root),
+            None => value,
+        };
+    }
+
+    match value {
+        Value::Array(entries) if map_value_schema(schema).is_some() => {
+            let value_schema = map_value_schema(schema).unwrap_or(&Value::Null);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .filter_map(|entry| {
+                        let Value::Object(mut entry) = entry else {
+                            return None;
+                        };
+                        let key = match entry.remove("key")? {
+                            Value::String(key) => key,
+                            other => other.to_string(),
+                        };
+                        let value = entry.remove("value").unwrap_or(Value::Null);
+                        Some((key, entries_to_maps(value, value_schema, root)))
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => match schema.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| entries_to_maps(item, item_schema, root))
+                    .collect(),
+            ),
+            None => Value::Array(items),
+        },
+        Value::Object(obj) => match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) => Value::Object(
+                obj.into_iter()
+                    .map(|(key, value)| {
+                        let value = match properties.get(&key) {
+                            Some(property) => entries_to_maps(value, property, root),
+                            None => value,
+                        };
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            None => Value::Object(obj),
+        },
+        other => other,
+    }
+}
+
+/// The inverse of [`entries_to_maps`]: turn objects into `{"key", "value"}` entry arrays
+/// wherever `schema` has a map.
+fn maps_to_entry_values(value: Value, schema: &Value, root: &Value) -> Value {
+    if let Some(target) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| root.pointer(pointer))
+    {
+        return maps_to_entry_values(value, target, root);
+    }
+
+    if let Some(alternatives) = schema
+        .get("anyOf")
+        .or_else(|| schema.get("oneOf"))
+        .and_then(Value::as_array)
+    {
+        return match alternatives
+            .iter()
+            .find(|alt| accepts_shape(alt, &value, root, false))
+        {
+            Some(alt) => maps_to_entry_values(value, alt, root),
+            None => value,
+        };
+    }
+
+    match value {
+        Value::Object(map) if map_value_schema(schema).is_some() => {
+            let value_schema = map_value_schema(schema).unwrap_or(&Value::Null);
+            Value::Array(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        json!({
+                            "key": key,
+                            "value": maps_to_entry_values(value, value_schema, root),
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        Value::Array(items) => match schema.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| maps_to_entry_values(item, item_schema, root))
+                    .collect(),
+            ),
+            None => Value::Array(items),
+        },
+        Value::Object(obj) => match schema.get("properties").and_then(Value::as_object) {
+            Some(properties) => Value::Object(
+                obj.into_iter()
+                    .map(|(key, value)| {
+                        let value = match properties.get(&key) {
+                            Some(property) => maps_to_entry_values(value, property, root),
+                            None => value,
+                        };
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            None => Value::Object(obj),
+        },
+        other => other,
+    }
+}
+
+/// Whether `value` fits the shape of `schema`, with maps expected as entry arrays when
+/// `entries` is set (that is, after [`maps_to_entries`]).
+fn accepts_shape(schema: &Value, value: &Value, root: &Value, entries: bool) -> bool {
+    if let Some(target) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| root.pointer(pointer))
+    {
+        return accepts_shape(target, value, root, entries);
+    }
+
+    let expected = if map_value_schema(schema).is_some() {
+        if entries { "array" } else { "object" }
+    } else {
+        match schema.get("type") {
+            Some(Value::String(ty)) => ty.as_str(),
+            _ => return true,
+        }
+    };
+
+    matches!(
+        (expected, value),
+        ("object", Value::Object(_))
+            | ("array", Value::Array(_))
+            | ("string", Value::String(_))
+            | ("number" | "integer", Value::Number(_))
+            | ("boolean", Value::Bool(_))
+            | ("null", Value::Null)
+    )
+}
+
+/// Replace every `$ref` with the schema it points to and drop the definitions.
+///
+/// Recursive references are expanded [`MAX_INLINE_DEPTH`] times. Past that, arrays of the
+/// type are limited to zero items and other references become an empty nullable object.
+pub(crate) fn inline_refs(schema: &Value) -> Value {
+    inline(schema, schema, &mut Vec::new())
+}
+
+fn inline(node: &Value, root: &Value, expanding: &mut Vec<String>) -> Value {
+    match node {
+        Value::Object(obj) => {
+            if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+                return inline_ref(obj, reference, root, expanding);
+            }
+
+            let mut result: Map<String, Value> = obj
+                .iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$defs" | "definitions"))
+                .map(|(key, value)| (key.clone(), inline(value, root, expanding)))
+                .collect();
+
+            if result.get("items").is_some_and(is_depth_limit) {
+                result.insert("maxItems".to_string(), json!(0));
+            }
+
+            Value::Object(result)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| inline(item, root, expanding))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn inline_ref(
+    obj: &Map<String, Value>,
+    reference: &str,
+    root: &Value,
+    expanding: &mut Vec<String>,
+) -> Value {
+    if expanding.iter().filter(|r| *r == reference).count() >= MAX_INLINE_DEPTH {
+        return json!({
+            "type": "object",
+            "nullable": true,
+            "description": DEPTH_LIMIT_DESCRIPTION,
+        });
+    }
+
+    let Some(target) = reference
+        .strip_prefix('#')
+        .and_then(|ptr| root.pointer(ptr))
+    else {
+        return Value::Object(obj.clone());
+    };
+
+    expanding.push(reference.to_string());
+    let mut inlined = inline(target, root, expanding);
+    expanding.pop();
+
+    // Keywords next to `$ref` (e.g. a field description) take precedence
+    if let Value::Object(map) = &mut inlined {
+        map.remove("$schema");
+        map.remove("title");
+        for (key, value) in obj.iter().filter(|(key, _)| *key != "$ref") {
+            map.insert(key.clone(), inline(value, root, expanding));
+        }
+    }
+
+    inlined
+}
+
+fn is_depth_limit(schema: &Value) -> bool {
+    schema.get("description").and_then(Value::as_str) == Some(DEPTH_LIMIT_DESCRIPTION)
+}
+
+/// Whether `schema` contains `{"$ref": reference}` anywhere.
+fn contains_ref(schema: &Value, reference: &str) -> bool {
+    match schema {
+        Value::Object(obj) => {
+            obj.get("$ref").and_then(Value::as_str) == Some(reference)
+                || obj.values().any(|value| contains_ref(value, reference))
+        }
+        Value::Array(items) => items.iter().any(|item| contains_ref(item, reference)),
+        _ => false,
+    }
+}
+
+/// Point `"$ref": "#"` at `target` instead, for when the schema is nested inside another.
+pub(crate) fn rewrite_root_refs(schema: &mut Value, target: &str) {
+    match schema {
+        Value::Object(obj) => {
+            if obj.get("$ref").and_then(Value::as_str) == Some("#") {
+                obj.insert("$ref".to_string(), Value::String(target.to_string()));
+            }
+            obj.values_mut()
+                .for_each(|value| rewrite_root_refs(value, target));
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| rewrite_root_refs(item, target)),
+        _ => {}
+    }
+}
+
+fn const_string(schema: &Value) -> Option<&str> {
+    schema.get("const").and_then(Value::as_str).or_else(|| {
+        match schema.get("enum").and_then(Value::as_array)?.as_slice() {
+            [Value::String(single)] => Some(single.as_str()),
+            _ => None,
+        }
+    })
+}
+
+/// Unit variants of an externally tagged enum: `{"type": "string", "enum": [...]}` or a
+/// single `const`.
+fn unit_variant_names(schema: &Value) -> Option<Vec<String>> {
+    if schema.get("type").and_then(Value::as_str) != Some("string") {
+        return None;
+    }
+    if let Some(name) = schema.get("const").and_then(Value::as_str) {
+        return Some(vec![name.to_string()]);
+    }
+    schema
+        .get("enum")?
+        .as_array()?
+        .iter()
+        .map(|name| name.as_str().map(str::to_string))
+        .collect()
+}
+
+fn required_fields(schema: &Value) -> Vec<&str> {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Wrap every `$ref` that has sibling keywords as `{"anyOf": [{"$ref": ...}], ...siblings}`.
+fn wrap_ref_siblings(schema: &mut Value) {
+    match schema {
+        Value::Object(obj) => {
+            obj.values_mut().for_each(wrap_ref_siblings);
+
+            if obj.len() > 1 && obj.contains_key("$ref") {
+                let reference = obj.remove("$ref");
+                obj.insert("anyOf".to_string(), json!([{ "$ref": reference }]));
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(wrap_ref_siblings),
+        _ => {}
+    }
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Allow `null` in addition to the values `schema` accepts.
+fn nullable(schema: &Value) -> Value {
+    match schema.get("type") {
+        Some(Value::String(ty)) if ty != "null" && schema.get("enum").is_none() => {
+            let mut nullable = schema.clone();
+            nullable["type"] = json!([ty, "null"]);
+            nullable
+        }
+        Some(Value::Array(types)) if types.iter().any(|t| t == "null") => schema.clone(),
+        _ => {
+            // The description documents the field, so it stays on the outer schema
+            let mut inner = schema.clone();
+            let description = inner
+                .as_object_mut()
+                .and_then(|obj| obj.remove("description"));
+            let mut nullable = json!({ "anyOf": [inner, { "type": "null" }] });
+            if let Some(description) = description {
+                nullable["description"] = description;
+            }
+            nullable
+        }
+    }
+}
+
+/// Check `value` against the subset of JSON Schema that schemars produces.
+///
+/// Returns a description of the first mismatch, prefixed with its JSON pointer.
+pub(crate) fn validate_value(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_node(schema, value, schema, "")
+}
+
+fn validate_node(schema: &Value, value: &Value, root: &Value, at: &str) -> Result<(), String> {
+    let obj = match schema {
+        Value::Bool(true) => return Ok(()),
+        Value::Bool(false) => return Err(format!("{at}: no value is allowed")),
+        Value::Object(obj) => obj,
+        _ => return Ok(()),
+    };
+
+    if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+        let target = reference
+            .strip_prefix('#')
+            .and_then(|ptr| root.pointer(ptr))
+            .ok_or_else(|| format!("{at}: unresolved reference {reference}"))?;
+        validate_node(target, value, root, at)?;
+    }
+
+    if let Some(types) = obj.get("type") {
+        let allowed: Vec<&str> = match types {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(tys) => tys.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|ty| has_type(value, ty)) {
+            return Err(format!(
+                "{at}: expected {}, got {value}",
+                allowed.join(" or ")
+            ));
+        }
+    }
+
+    if let Some(expected) = obj.get("const")
+        && expected != value
+    {
+        return Err(format!("{at}: expected {expected}, got {value}"));
+    }
+
+    if let Some(Value::Array(options)) = obj.get("enum")
+        && !options.contains(value)
+    {
+        return Err(format!(
+            "{at}: {value} is not one of {}",
+            Value::Array(options.clone())
+        ));
+    }
+
+    for key in ["anyOf", "oneOf"] {
+        if let Some(Value::Array(branches)) = obj.get(key)
+            && !branches
+                .iter()
+                .any(|branch| validate_node(branch, value, root, at).is_ok())
+        {
+            return Err(format!(
+                "{at}: {value} matches none of the {key} alternatives"
+            ));
+        }
+    }
+
+    if let Some(Value::Array(branches)) = obj.get("allOf") {
+        for branch in branches {
+            validate_node(branch, value, root, at)?;
+        }
+    }
+
+    if let Value::Object(fields) = value {
+        let properties = obj.get("properties").and_then(Value::as_object);
+        for field in required_fields(schema) {
+            if !fields.contains_key(field) {
+                return Err(format!("{at}: missing required field `{field}`"));
+            }
+        }
+        for (name, field) in fields {
+            let path = format!("{at}/{name}");
+            match (
+                properties.and_then(|p| p.get(name)),
+                obj.get("additionalProperties"),
+            ) {
+                (Some(field_schema), _) => validate_node(field_schema, field, root, &path)?,
+                (None, Some(additional)) => validate_node(additional, field, root, &path)?,
+                (None, None) => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, obj.get("items")) {
+        for (index, item) in items.iter().enumerate() {
+            validate_node(item_schema, item, root, &format!("{at}/{index}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::JsonSchema;
+    use std::collections::HashMap;
+
+    impl TaggedUnion {
+        fn for_type<T: JsonSchema>() -> Option<Self> {
+            Self::from_schema(&schema_of::<T>())
+        }
+    }
+
+    fn schema_of<T: JsonSchema>() -> Value {
+        serde_json::to_value(schemars::schema_for!(T)).unwrap()
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Debug, PartialEq)]
+    #[serde(tag = "type")]
+    #[schemars(deny_unknown_fields)]
+    enum Shape {
+        Circle { radius: f64 },
+        Rect { w: f64, h: f64 },
+        Empty,
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Debug, PartialEq)]
+    #[schemars(deny_unknown_fields)]
+    enum Answer {
+        Number { value: i64, unit: Option<String> },
+        Text(String),
+        Unknown,
+    }
+
+    #[test]
+    fn test_internally_tagged_enum_is_flattened() {
+        let union = TaggedUnion::for_type::<Shape>().expect("tagged union");
+        let original = serde_json::to_value(schemars::schema_for!(Shape)).unwrap();
+        let schema = union.strict_schema(&original);
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["title"], "Shape");
+        assert!(schema.get("oneOf").is_none());
+        assert_eq!(
+            schema["properties"]["type"]["enum"],
+            json!(["Circle", "Rect", "Empty"])
+        );
+        assert_eq!(
+            schema["properties"]["radius"]["type"],
+            json!(["number", "null"])
+        );
+        assert_eq!(schema["required"].as_array().unwrap().len(), 4);
+
+        let answer = json!({"type": "Rect", "radius": null, "w": 2.0, "h": 3.0});
+        let shape: Shape = serde_json::from_value(union.restore(answer)).unwrap();
+        assert_eq!(shape, Shape::Rect { w: 2.0, h: 3.0 });
+
+        let answer = json!({"type": "Empty", "radius": null, "w": null, "h": null});
+        let shape: Shape = serde_json::from_value(union.restore(answer)).unwrap();
+        assert_eq!(shape, Shape::Empty);
+    }
+
+    #[test]
+    fn test_externally_tagged_enum_round_trips() {
+        let union = TaggedUnion::for_type::<Answer>().expect("tagged union");
+        let original = serde_json::to_value(schemars::schema_for!(Answer)).unwrap();
+        let schema = union.strict_schema(&original);
+
+        assert_eq!(
+            schema["properties"]["variant"]["enum"],
+            json!(["Unknown", "Number", "Text"])
+        );
+
+        let restore =
+            |answer: Value| -> Answer { serde_json::from_value(union.restore(answer)).unwrap() };
+
+        assert_eq!(
+            restore(json!({"variant": "Number", "value": 3, "unit": null, "Text": null})),
+            Answer::Number {
+                value: 3,
+                unit: None
+            }
+        );
+        assert_eq!(
+            restore(json!({"variant": "Text", "value": null, "unit": null, "Text": "hi"})),
+            Answer::Text("hi".to_string())
+        );
+        assert_eq!(
+            restore(json!({"variant": "Unknown", "value": null, "unit": null, "Text": null})),
+            Answer::Unknown
+        );
+    }
+
+    #[derive(serde::Deserialize, JsonSchema)]
+    #[serde(tag = "kind")]
+    #[allow(dead_code)]
+    enum Payment {
+        /// Paid by card
+        Card {
+            /// Last four digits
+            last4: String,
+        },
+        /// Paid in cash
+        Cash,
+    }
+
+    #[test]
+    fn test_descriptions_survive_flattening() {
+        let schema = strict_schema(schema_of::<Payment>());
+        let discriminator = schema["properties"]["kind"]["description"]
+            .as_str()
+            .unwrap();
+
+        assert!(discriminator.contains("Card (last4): Paid by card"));
+        assert!(discriminator.contains("Cash (no fields): Paid in cash"));
+        assert_eq!(
+            schema["properties"]["last4"]["description"],
+            "Last four digits"
+        );
+    }
+
+    #[test]
+    fn test_ref_siblings_move_next_to_any_of() {
+        let mut schema = json!({
+            "properties": {
+                "plain": { "$ref": "#/$defs/A" },
+                "documented": { "$ref": "#/$defs/A", "description": "An A" }
+            }
+        });
+
+        wrap_ref_siblings(&mut schema);
+
+        assert_eq!(
+            schema["properties"]["plain"],
+            json!({ "$ref": "#/$defs/A" })
+        );
+        assert_eq!(
+            schema["properties"]["documented"],
+            json!({ "anyOf": [{ "$ref": "#/$defs/A" }], "description": "An A" })
+        );
+    }
+
+    #[test]
+    fn test_conflicting_field_schemas_are_left_alone() {
+        #[derive(serde::Deserialize, JsonSchema)]
+        #[serde(tag = "type")]
+        #[allow(dead_code)]
+        enum Conflict {
+            A { id: i64 },
+            B { id: String },
+        }
+
+        assert!(TaggedUnion::for_type::<Conflict>().is_none());
+    }
+
+    #[derive(serde::Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Node {
+        name: String,
+        children: Vec<Node>,
+    }
+
+    #[derive(serde::Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Thread {
+        root: Node,
+        reply: Option<Box<Thread>>,
+    }
+
+    fn depth(schema: &Value, path: &str) -> usize {
+        let mut depth = 0;
+        let mut current = schema;
+        while let Some(next) = current.pointer(path) {
+            depth += 1;
+            current = next;
+        }
+        depth
+    }
+
+    #[test]
+    fn test_inline_refs_expands_recursive_tree_to_depth_limit() {
+        let schema = serde_json::to_value(schemars::schema_for!(Node)).unwrap();
+        let inlined = inline_refs(&schema);
+
+        assert!(!contains_ref(&inlined, "#"));
+        assert_eq!(
+            depth(&inlined, "/properties/children/items"),
+            MAX_INLINE_DEPTH + 1
+        );
+
+        let mut deepest = &inlined;
+        for _ in 0..MAX_INLINE_DEPTH {
+            deepest = &deepest["properties"]["children"]["items"];
+        }
+        assert_eq!(deepest["properties"]["children"]["maxItems"], 0);
+        assert_eq!(deepest["properties"]["name"]["type"], "string");
+    }
+
+    #[test]
+    fn test_inline_refs_resolves_defs() {
+        let schema = serde_json::to_value(schemars::schema_for!(Thread)).unwrap();
+        assert!(schema.get("$defs").is_some());
+
+        let inlined = inline_refs(&schema);
+        let text = inlined.to_string();
+        assert!(!text.contains("$ref"));
+        assert!(!text.contains("$defs"));
+        assert_eq!(inlined["properties"]["root"]["type"], "object");
+        assert_eq!(
+            inlined["properties"]["root"]["properties"]["children"]["items"]["properties"]["name"]
+                ["type"],
+            "string"
+        );
+        assert_eq!(
+            inlined.pointer("/properties/reply/anyOf/0/type"),
+            Some(&json!("object"))
+        );
+    }
+
+    #[test]
+    fn test_rewrite_root_refs() {
+        let mut schema = serde_json::to_value(schemars::schema_for!(Node)).unwrap();
+        rewrite_root_refs(&mut schema, "#/properties/value");
+        assert_eq!(
+            schema["properties"]["children"]["items"]["$ref"],
+            "#/properties/value"
+        );
+    }
+
+    #[derive(serde::Deserialize, JsonSchema, Debug, PartialEq)]
+    #[schemars(deny_unknown_fields)]
+    struct Inventory {
+        counts: HashMap<String, u32>,
+        notes: Option<HashMap<String, Vec<String>>>,
+        nested: HashMap<String, HashMap<String, bool>>,
+    }
+
+    #[test]
+    fn test_maps_become_entry_arrays() {
+        let schema = strict_schema(schema_of::<Inventory>());
+        assert!(!schema.to_string().contains("\"additionalProperties\":{"));
+
+        let counts = &schema["properties"]["counts"];
+        assert_eq!(counts["type"], "array");
+        assert_eq!(counts["items"]["required"], json!(["key", "value"]));
+        assert_eq!(counts["items"]["properties"]["key"]["type"], "string");
+        assert_eq!(counts["items"]["properties"]["value"]["type"], "integer");
+        assert_eq!(
+            schema["properties"]["nested"]["items"]["properties"]["value"]["type"],
+            "array"
+        );
+    }
+
+    #[test]
+    fn test_entry_arrays_restore_to_maps() {
+        let original = schema_of::<Inventory>();
+        let answer = json!({
+            "counts": [{"key": "apples", "value": 3}, {"key": "pears", "value": 0}],
+            "notes": [{"key": "apples", "value": ["ripe"]}],
+            "nested": [{"key": "shelf", "value": [{"key": "full", "value": true}]}]
+        });
+
+        let inventory: Inventory =
+            serde_json::from_value(restore_value(&original, answer)).unwrap();
+
+        assert_eq!(
+            inventory,
+            Inventory {
+                counts: HashMap::from([("apples".to_string(), 3), ("pears".to_string(), 0)]),
+                notes: Some(HashMap::from([(
+                    "apples".to_string(),
+                    vec!["ripe".to_string()]
+                )])),
+                nested: HashMap::from([(
+                    "shelf".to_string(),
+                    HashMap::from([("full".to_string(), true)])
+                )]),
+            }
+        );
+
+        let without_notes = json!({ "counts": [], "notes": null, "nested": [] });
+        let inventory: Inventory =
+            serde_json::from_value(restore_value(&original, without_notes)).unwrap();
+        assert_eq!(inventory.notes, None);
+    }
+
+    #[test]
+    fn test_strict_value_is_inverse_of_restore() {
+        let shape = schema_of::<Shape>();
+        let circle = json!({ "type": "Circle", "radius": 1.5 });
+        assert_eq!(
+            strict_value(&shape, circle.clone()),
+            json!({ "type": "Circle", "radius": 1.5, "w": null, "h": null })
+        );
+        assert_eq!(
+            restore_value(&shape, strict_value(&shape, circle.clone())),
+            circle
+        );
+
+        let answer = schema_of::<Answer>();
+        for value in [
+            json!({ "Number": { "value": 4, "unit": "kg" } }),
+            json!({ "Text": "four" }),
+            json!("Unknown"),
+        ] {
+            let strict = strict_value(&answer, value.clone());
+            assert!(strict["variant"].is_string());
+            assert_eq!(restore_value(&answer, strict), value);
+        }
+
+        let inventory = schema_of::<Inventory>();
+        let value = json!({
+            "counts": { "apples": 3 },
+            "notes": null,
+            "nested": { "shelf": { "full": true } }
+        });
+        let strict = strict_value(&inventory, value.clone());
+        assert_eq!(strict["counts"], json!([{ "key": "apples", "value": 3 }]));
+        assert_eq!(
+            strict["nested"],
+            json!([{ "key": "shelf", "value": [{ "key": "full", "value": true }] }])
+        );
+        assert_eq!(restore_value(&inventory, strict), value);
+    }
+
+    #[test]
+    fn test_validate_value_reports_first_mismatch() {
+        let inventory = schema_of::<Inventory>();
+        assert_eq!(
+            validate_value(
+                &inventory,
+                &json!({ "counts": { "apples": 3 }, "notes": null, "nested": {} })
+            ),
+            Ok(())
+        );
+
+        let err = validate_value(
+            &inventory,
+            &json!({ "counts": { "apples": "three" }, "notes": null, "nested": {} }),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("/counts/apples: expected integer"), "{err}");
+
+        let err = validate_value(&inventory, &json!({ "counts": {} })).unwrap_err();
+        assert!(err.contains("missing required field"), "{err}");
+
+        let shape = schema_of::<Shape>();
+        assert_eq!(
+            validate_value(&shape, &json!({ "type": "Circle", "radius": 1.5 })),
+            Ok(())
+        );
+        assert!(validate_value(&shape, &json!({ "type": "Hexagon" })).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Stop sequences and client-side stop conditions.
+//!
+//! [`LlmBuilder::stop`](crate::LlmBuilder::stop) sequences are sent to providers that support
+//! them and applied here for the others. [`StopCondition`]s always run here, on the text of the
+//! answer.
+
+use std::sync::Arc;
+
+use super::types::{ProviderResponse, ResponseContent};
+
+/// Ends an answer early, checked against its text on the client.
+///
+/// Returns the byte offset at which the answer ends, or `None` to keep all of it. Implemented
+/// for closures `Fn(&str) -> Option<usize>`, e.g. to stop at the first blank line:
+///
+/// ```
+/// use rsai::StopCondition;
+///
+/// let condition = |text: &str| text.find("\n\n");
+/// assert_eq!(condition.stop_at("first\n\nsecond"), Some(5));
+/// ```
+///
+/// Answers are not streamed yet, so the condition runs once on the complete text and cuts it,
+/// rather than ending generation on the server.
+pub trait StopCondition: Send + Sync {
+    fn stop_at(&self, text: &str) -> Option<usize>;
+}
+
+impl<F> StopCondition for F
+where
+    F: Fn(&str) -> Option<usize> + Send + Sync,
+{
+    fn stop_at(&self, text: &str) -> Option<usize> {
+        self(text)
+    }
+}
+
+/// Cut a text answer at the earliest of the stop `sequences` and `conditions`, excluding the
+/// matched sequence like the providers do.
+pub(crate) fn apply(
+    response: &mut ProviderResponse,
+    sequences: &[String],
+    conditions: &[Arc<dyn StopCondition>],
+) {
+    let ResponseContent::Text(text) = &mut response.content else {
+        return;
+    };
+    let end = sequences
+        .iter()
+        .filter(|sequence| !sequence.is_empty())
+        .filter_map(|sequence| text.find(sequence.as_str()))
+        .chain(
+            conditions
+                .iter()
+                .filter_map(|condition| condition.stop_at(text))
+                .filter(|&end| text.is_char_boundary(end)),
+        )
+        .min();
+    if let Some(end) = end {
+        text.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, mock_openai, responses_answer};
+    use crate::{ApiKey, ChatRole, Message, TextResponse, llm};
+    use crate::{LanguageModelUsage, Provider};
+    use serde_json::json;
+
+    fn text_response(text: &str) -> ProviderResponse {
+        ProviderResponse::new(
+            "resp",
+            "model",
+            Provider::Together,
+            ResponseContent::Text(text.to_string()),
+            LanguageModelUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                cached_tokens: None,
+            },
+        )
+    }
+
+    fn text(response: &ProviderResponse) -> &str {
+        match &response.content {
+            ResponseContent::Text(text) => text,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_answer_ends_at_the_earliest_stop() {
+        let mut response = text_response("Step 1\nEND\nStep 2\n###");
+        apply(&mut response, &["###".to_string(), "END".to_string()], &[]);
+        assert_eq!(text(&response), "Step 1\n");
+
+        let mut response = text_response("Step 1\nStep 2\n###");
+        let conditions: Vec<Arc<dyn StopCondition>> =
+            vec![Arc::new(|text: &str| text.find('\n').map(|i| i + 1))];
+        apply(&mut response, &["###".to_string()], &conditions);
+        assert_eq!(text(&response), "Step 1\n");
+    }
+
+    #[test]
+    fn test_offsets_inside_a_character_are_ignored() {
+        let mut response = text_response("héllo");
+        let conditions: Vec<Arc<dyn StopCondition>> = vec![Arc::new(|_: &str| Some(2))];
+        apply(&mut response, &[], &conditions);
+        assert_eq!(text(&response), "héllo");
+    }
+
+    #[tokio::test]
+    async fn test_stop_sequences_are_sent_or_applied_on_the_client() {
+        let transport = ScriptedTransport::answering(json!({
+            "id": "chatcmpl-1",
+            "model": "accounts/fireworks/models/llama-v3p3-70b-instruct",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "1. Preheat" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 4, "total_tokens": 9 }
+        }));
+
+        llm::with(Provider::Fireworks)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("accounts/fireworks/models/llama-v3p3-70b-instruct")
+            .messages(vec![Message::new(ChatRole::User, "Write a recipe")])
+            .stop(["###", "END"])
+            .transport(transport.clone())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(transport.bodies()[0]["stop"], json!(["###", "END"]));
+
+        // The Responses API has no stop parameter
+        let transport =
+            ScriptedTransport::answering(responses_answer("1. Preheat\nEND\n2. Bake\n\nEnjoy"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Write a recipe")])
+        };
+
+        let response = builder()
+            .stop(["###", "END"])
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(response.text, "1. Preheat\n");
+        assert!(transport.bodies()[0].get("stop").is_none());
+
+        let response = builder()
+            .stop_when(|text: &str| text.find("\n\n"))
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(response.text, "1. Preheat\nEND\n2. Bake");
+    }
+}
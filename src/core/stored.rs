@@ -0,0 +1,132 @@
+//! Access to responses stored by the provider.
+
+use std::sync::Arc;
+
+use crate::provider::{OpenAiClient, Provider};
+use crate::responses::HttpClientConfig;
+
+use super::builder::{ApiKey, resolve_api_key};
+use super::error::LlmError;
+use super::transport::Transport;
+use super::types::ProviderResponse;
+
+/// Fetch and delete stored responses by id, created with [`llm::responses`](crate::llm::responses).
+///
+/// The key from the provider's default environment variable is used unless
+/// [`api_key`](Self::api_key) is set.
+pub struct StoredResponses {
+    provider: Provider,
+    api_key: Option<String>,
+    http_client_config: HttpClientConfig,
+}
+
+impl StoredResponses {
+    pub(crate) fn new(provider: Provider) -> Self {
+        Self {
+            provider,
+            api_key: None,
+            http_client_config: HttpClientConfig::default(),
+        }
+    }
+
+    /// Set the API key.
+    pub fn api_key(mut self, api_key: ApiKey) -> Result<Self, LlmError> {
+        self.api_key = Some(resolve_api_key(self.provider, api_key)?);
+        Ok(self)
+    }
+
+    /// Set the full HTTP client configuration.
+    pub fn http_client_config(mut self, config: HttpClientConfig) -> Self {
+        self.http_client_config = config;
+        self
+    }
+
+    /// Replace the HTTP transport, e.g. with a [`Cassette`](crate::Cassette).
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> Self {
+        self.http_client_config.transport = Some(transport);
+        self
+    }
+
+    /// Fetch a stored response.
+    pub async fn get(&self, id: &str) -> Result<ProviderResponse, LlmError> {
+        self.client()?.get_response(id).await
+    }
+
+    /// Delete a stored response.
+    pub async fn delete(&self, id: &str) -> Result<(), LlmError> {
+        self.client()?.delete_response(id).await
+    }
+
+    fn client(&self) -> Result<OpenAiClient, LlmError> {
+        if self.provider != Provider::OpenAI {
+            return Err(LlmError::ProviderConfiguration(format!(
+                "Stored responses are not supported by {}",
+                self.provider
+            )));
+        }
+
+        let api_key = match &self.api_key {
+            Some(api_key) => api_key.clone(),
+            None => resolve_api_key(self.provider, ApiKey::Default)?,
+        };
+        OpenAiClient::new(api_key)?.with_http_config(self.http_client_config.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, responses_answer};
+    use crate::core::transport::HttpMethod;
+    use crate::{ResponseContent, llm};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_get_and_delete() {
+        let transport = ScriptedTransport::new(vec![
+            (200, responses_answer("{\"sum\":3}")),
+            (
+                200,
+                json!({ "id": "resp_1", "object": "response", "deleted": true }),
+            ),
+        ]);
+        let stored = llm::responses(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .transport(transport.clone());
+
+        let response = stored.get("resp_1").await.unwrap();
+        assert_eq!(response.id, "resp_1");
+        assert!(
+            matches!(response.content, ResponseContent::Text(ref text) if text == "{\"sum\":3}")
+        );
+        stored.delete("resp_1").await.unwrap();
+
+        let requests: Vec<(HttpMethod, String)> = transport
+            .requests()
+            .into_iter()
+            .map(|request| (request.method, request.url))
+            .collect();
+        assert_eq!(
+            requests,
+            [
+                (
+                    HttpMethod::Get,
+                    "https://api.openai.com/v1/responses/resp_1".to_string()
+                ),
+                (
+                    HttpMethod::Delete,
+                    "https://api.openai.com/v1/responses/resp_1".to_string()
+                ),
+            ]
+        );
+
+        let err = llm::responses(Provider::Gemini)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .get("resp_1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LlmError::ProviderConfiguration(_)));
+    }
+}
@@ -0,0 +1,441 @@
+//! Prompt regression tests on top of [`Cassette`] recordings.
+//!
+//! A [`TestCase`] gives each test its own cassette and snapshot file, named after the test.
+//! The first run records the provider traffic and the parsed output; later runs replay the
+//! cassette and fail when the output drifts from the snapshot. Use the
+//! [`llm_test`](crate::llm_test) attribute to create the case from the test name.
+//!
+//! Prompt construction can be tested without any provider traffic: build the request with
+//! [`dry_run`](crate::LlmBuilder::dry_run) and compare it to a golden file with
+//! [`TestCase::assert_request_snapshot`], or check individual fields with
+//! [`expect_request`](crate::LlmBuilder::expect_request).
+//!
+//! Environment variables:
+//! - `RSAI_CASSETTE_MODE`: `record`, `replay` or `auto` (default), see [`CassetteMode`].
+//! - `RSAI_UPDATE_SNAPSHOTS=1`: overwrite snapshots with the current output instead of
+//!   comparing.
+//!
+//! # Example
+//! ```no_run
+//! use rsai::testing::{Judge, TestCase};
+//! use rsai::{ChatRole, LlmError, Message, Provider, completion_schema, llm, llm_test};
+//! use serde::Serialize;
+//!
+//! #[completion_schema]
+//! #[derive(Serialize)]
+//! struct Invoice {
+//!     vendor: String,
+//!     total: f64,
+//! }
+//!
+//! #[llm_test]
+//! async fn extracts_invoice(case: TestCase) -> Result<(), LlmError> {
+//!     let invoice = llm::with(Provider::OpenAI)
+//!         .api_key(case.api_key())?
+//!         .model("gpt-4o-mini")
+//!         .messages(vec![Message::new(ChatRole::User, "ACME Corp, total due: $120.50")])
+//!         .transport(case.transport()?)
+//!         .complete::<Invoice>()
+//!         .await?;
+//!
+//!     case.assert_fields(&invoice.content, serde_json::json!({ "vendor": "ACME Corp" }));
+//!     case.assert_snapshot(&invoice.content);
+//!
+//!     let verdict = case
+//!         .judge(&Judge::new(Provider::OpenAI, "gpt-4o-mini"), &invoice.content, "The total is 120.50")
+//!         .await?;
+//!     assert!(verdict.score >= 0.8, "{}", verdict.reasoning);
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::builder::{ApiKey, llm};
+use super::cassette::{Cassette, CassetteMode};
+use super::error::LlmError;
+use super::transport::Transport;
+use super::types::{ChatRole, Message};
+use crate::provider::Provider;
+
+/// Selects the [`CassetteMode`] of every [`TestCase`].
+pub const CASSETTE_MODE_ENV: &str = "RSAI_CASSETTE_MODE";
+
+/// When set to `1` or `true`, snapshots are rewritten instead of compared.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "RSAI_UPDATE_SNAPSHOTS";
+
+/// A single prompt regression test with its own cassette and snapshot.
+pub struct TestCase {
+    name: String,
+    cassette_dir: PathBuf,
+    snapshot_dir: PathBuf,
+    cassette: OnceLock<Arc<Cassette>>,
+}
+
+impl TestCase {
+    /// Create a case named `name`. `::` separators (as in `module_path!()`) become directories.
+    ///
+    /// Cassettes are stored in `tests/cassettes` and snapshots in `tests/snapshots`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cassette_dir: PathBuf::from("tests/cassettes"),
+            snapshot_dir: PathBuf::from("tests/snapshots"),
+            cassette: OnceLock::new(),
+        }
+    }
+
+    /// Store the cassette under `dir` instead of `tests/cassettes`.
+    pub fn with_cassette_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cassette_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Store the snapshot under `dir` instead of `tests/snapshots`.
+    pub fn with_snapshot_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.snapshot_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn cassette_path(&self) -> PathBuf {
+        self.cassette_dir.join(self.file_name())
+    }
+
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.snapshot_dir.join(self.file_name())
+    }
+
+    /// Where [`assert_request_snapshot`](Self::assert_request_snapshot) stores the request,
+    /// next to the output snapshot.
+    pub fn request_snapshot_path(&self) -> PathBuf {
+        let mut path = self.snapshot_path();
+        path.set_extension("request.json");
+        path
+    }
+
+    fn file_name(&self) -> PathBuf {
+        let mut path: PathBuf = self.name.split("::").collect();
+        path.set_extension("json");
+        path
+    }
+
+    /// The transport to pass to [`LlmBuilder::transport`](crate::LlmBuilder::transport).
+    ///
+    /// All requests of the case, including those of a [`Judge`], share one cassette.
+    pub fn transport(&self) -> Result<Arc<dyn Transport>, LlmError> {
+        if let Some(cassette) = self.cassette.get() {
+            return Ok(cassette.clone());
+        }
+
+        let cassette = Arc::new(Cassette::with_mode(self.cassette_path(), cassette_mode()?)?);
+        Ok(self.cassette.get_or_init(|| cassette).clone())
+    }
+
+    /// [`ApiKey::Default`] while recording, a placeholder while replaying so tests run
+    /// without credentials.
+    pub fn api_key(&self) -> ApiKey {
+        let recording = self
+            .transport()
+            .ok()
+            .and_then(|_| self.cassette.get())
+            .is_some_and(|cassette| cassette.is_recording());
+
+        if recording {
+            ApiKey::Default
+        } else {
+            ApiKey::Custom("replayed".to_string())
+        }
+    }
+
+    /// Assert that `value` serializes to the stored snapshot.
+    ///
+    /// The snapshot is written if it does not exist yet or `RSAI_UPDATE_SNAPSHOTS=1` is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the output differs from the snapshot or the snapshot cannot be read or written.
+    #[track_caller]
+    pub fn assert_snapshot<S: Serialize>(&self, value: &S) {
+        self.compare_snapshot("Output", &self.snapshot_path(), to_json(value));
+    }
+
+    /// Assert that a request built with [`dry_run`](crate::LlmBuilder::dry_run) matches the
+    /// stored golden file, to test prompt construction without sending anything.
+    ///
+    /// The golden file is written like the output snapshot, see
+    /// [`assert_snapshot`](Self::assert_snapshot).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the request differs from the golden file or the file cannot be read or
+    /// written.
+    #[track_caller]
+    pub fn assert_request_snapshot(&self, request: &Value) {
+        self.compare_snapshot("Request", &self.request_snapshot_path(), request.clone());
+    }
+
+    #[track_caller]
+    fn compare_snapshot(&self, what: &str, path: &Path, actual: Value) {
+        if update_snapshots() || !path.exists() {
+            write_snapshot(path, &actual);
+            return;
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read snapshot '{}': {e}", path.display()));
+        let expected: Value = serde_json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Failed to parse snapshot '{}': {e}", path.display()));
+
+        if actual != expected {
+            panic!(
+                "{what} of '{}' differs from snapshot '{}'.\n\nExpected:\n{}\n\nActual:\n{}\n\n\
+                 Rerun with {UPDATE_SNAPSHOTS_ENV}=1 to accept the new {}.",
+                self.name,
+                path.display(),
+                pretty(&expected),
+                pretty(&actual),
+                what.to_lowercase(),
+            );
+        }
+    }
+
+    /// Assert that every field in `expected` has the same value in `value`. Fields missing
+    /// from `expected` are not compared, and nested objects are compared the same way.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the path of the first mismatching field.
+    #[track_caller]
+    pub fn assert_fields<S: Serialize>(&self, value: &S, expected: Value) {
+        let actual = to_json(value);
+        if let Err(path) = contains(&actual, &expected, String::new()) {
+            panic!(
+                "Output of '{}' does not match at '{path}'.\n\nExpected fields:\n{}\n\nActual:\n{}",
+                self.name,
+                pretty(&expected),
+                pretty(&actual),
+            );
+        }
+    }
+
+    /// Ask `judge` to score `output` against `criteria`. The judge's requests are recorded in
+    /// the case's cassette like any other request.
+    pub async fn judge<S: Serialize>(
+        &self,
+        judge: &Judge,
+        output: &S,
+        criteria: &str,
+    ) -> Result<Verdict, LlmError> {
+        let output = serde_json::to_string_pretty(output).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize output for the judge".to_string(),
+            source: Box::new(e),
+        })?;
+
+        let response = llm::with(judge.provider)
+            .api_key(self.api_key())?
+            .model(&judge.model)
+            .messages(vec![
+                Message::new(ChatRole::System, JUDGE_INSTRUCTIONS.to_string()),
+                Message::new(
+                    ChatRole::User,
+                    format!("Criteria:\n{criteria}\n\nOutput:\n{output}"),
+                ),
+            ])
+            .temperature(0.0)
+            .transport(self.transport()?)
+            .complete::<Verdict>()
+            .await?;
+
+        Ok(response.content)
+    }
+}
+
+const JUDGE_INSTRUCTIONS: &str = "You grade the output of another model against the given \
+criteria. Reply with a score from 0.0 (fails the criteria) to 1.0 (fully meets them) and a \
+short reasoning.";
+
+/// The model that grades outputs in [`TestCase::judge`].
+#[derive(Debug, Clone)]
+pub struct Judge {
+    provider: Provider,
+    model: String,
+}
+
+impl Judge {
+    pub fn new(provider: Provider, model: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+        }
+    }
+}
+
+/// A judge's grade for an output.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, schemars::JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Verdict {
+    /// How well the output meets the criteria, from 0.0 to 1.0
+    pub score: f64,
+    /// Why the output received this score
+    pub reasoning: String,
+}
+
+fn cassette_mode() -> Result<CassetteMode, LlmError> {
+    match env::var(CASSETTE_MODE_ENV).ok().as_deref() {
+        None | Some("") | Some("auto") => Ok(CassetteMode::Auto),
+        Some("record") => Ok(CassetteMode::Record),
+        Some("replay") => Ok(CassetteMode::Replay),
+        Some(other) => Err(LlmError::ProviderConfiguration(format!(
+            "Invalid {CASSETTE_MODE_ENV} '{other}', expected 'record', 'replay' or 'auto'"
+        ))),
+    }
+}
+
+fn update_snapshots() -> bool {
+    matches!(
+        env::var(UPDATE_SNAPSHOTS_ENV).ok().as_deref(),
+        Some("1") | Some("true")
+    )
+}
+
+#[track_caller]
+fn to_json<S: Serialize>(value: &S) -> Value {
+    serde_json::to_value(value).unwrap_or_else(|e| panic!("Failed to serialize output: {e}"))
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+#[track_caller]
+fn write_snapshot(path: &Path, value: &Value) {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).unwrap_or_else(|e| {
+            panic!(
+                "Failed to create snapshot directory '{}': {e}",
+                parent.display()
+            )
+        });
+    }
+    std::fs::write(path, pretty(value) + "\n")
+        .unwrap_or_else(|e| panic!("Failed to write snapshot '{}': {e}", path.display()));
+}
+
+/// Check that `actual` contains `expected`, returning the path of the first mismatch.
+pub(crate) fn contains(actual: &Value, expected: &Value, path: String) -> Result<(), String> {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            expected.iter().try_for_each(|(key, expected)| {
+                let path = format!("{path}.{key}");
+                match actual.get(key) {
+                    Some(actual) => contains(actual, expected, path),
+                    None => Err(path),
+                }
+            })
+        }
+        (Value::Array(actual), Value::Array(expected)) if actual.len() == expected.len() => actual
+            .iter()
+            .zip(expected)
+            .enumerate()
+            .try_for_each(|(index, (actual, expected))| {
+                contains(actual, expected, format!("{path}[{index}]"))
+            }),
+        _ if actual == expected => Ok(()),
+        _ if path.is_empty() => Err("$".to_string()),
+        _ => Err(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("rsai-testing-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_paths_follow_module_path() {
+        let case = TestCase::new("invoices::extracts_total");
+        assert_eq!(
+            case.cassette_path(),
+            Path::new("tests/cassettes/invoices/extracts_total.json")
+        );
+        assert_eq!(
+            case.snapshot_path(),
+            Path::new("tests/snapshots/invoices/extracts_total.json")
+        );
+    }
+
+    #[test]
+    fn test_snapshot_is_written_then_compared() {
+        let dir = temp_dir("snapshot");
+        let case = TestCase::new("snap").with_snapshot_dir(&dir);
+
+        case.assert_snapshot(&json!({ "total": 120.5 }));
+        assert!(case.snapshot_path().exists());
+        case.assert_snapshot(&json!({ "total": 120.5 }));
+
+        let drifted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            case.assert_snapshot(&json!({ "total": 99 }))
+        }));
+        assert!(drifted.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_request_snapshot_sits_next_to_the_output_snapshot() {
+        let dir = temp_dir("request");
+        let case = TestCase::new("prompts::greets").with_snapshot_dir(&dir);
+        assert_eq!(
+            case.request_snapshot_path(),
+            dir.join("prompts/greets.request.json")
+        );
+
+        case.assert_request_snapshot(&json!({ "model": "gpt-4o-mini" }));
+        case.assert_request_snapshot(&json!({ "model": "gpt-4o-mini" }));
+        let changed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            case.assert_request_snapshot(&json!({ "model": "gpt-4.1" }))
+        }));
+        assert!(changed.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_contains_reports_first_mismatch() {
+        let actual = json!({ "vendor": "ACME", "lines": [{ "qty": 1 }, { "qty": 2 }], "total": 3 });
+
+        assert_eq!(
+            contains(&actual, &json!({ "vendor": "ACME" }), String::new()),
+            Ok(())
+        );
+        assert_eq!(
+            contains(
+                &actual,
+                &json!({ "lines": [{ "qty": 1 }, { "qty": 5 }] }),
+                String::new()
+            ),
+            Err(".lines[1].qty".to_string())
+        );
+        assert_eq!(
+            contains(&actual, &json!({ "currency": "EUR" }), String::new()),
+            Err(".currency".to_string())
+        );
+    }
+}
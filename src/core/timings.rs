@@ -0,0 +1,161 @@
+//! Timing of a completion, recorded while it runs.
+//!
+//! [`LlmBuilder`](crate::LlmBuilder) runs every request in a recording scope. The HTTP client
+//! counts its retries and the tool-calling loop times each tool in the scope, so the numbers
+//! cover every request of a tool-calling loop without being passed through the providers.
+
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::collector::{self, Collected};
+
+/// When a completion ran and where its time went, in
+/// [`ResponseMetadata::timings`](crate::ResponseMetadata::timings).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    pub started_at: SystemTime,
+    pub finished_at: SystemTime,
+    /// Time until the response headers of the first request arrived. Recorded by
+    /// [`ReqwestTransport`](crate::ReqwestTransport), `None` with other transports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_to_first_byte: Option<Duration>,
+    /// HTTP requests that were retried, across all requests of a tool-calling loop
+    pub retries: u32,
+    /// Tools executed by the tool-calling loop, in the order they ran. Results reused from
+    /// earlier calls are not listed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolTiming>,
+}
+
+impl Timings {
+    /// Time from sending the first request to receiving the answer.
+    pub fn duration(&self) -> Duration {
+        self.finished_at
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+    }
+}
+
+/// How long one tool call took to execute.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolTiming {
+    pub name: String,
+    pub duration: Duration,
+}
+
+#[derive(Default)]
+struct Recording {
+    first_byte: Option<Instant>,
+    retries: u32,
+    tools: Vec<ToolTiming>,
+}
+
+tokio::task_local! {
+    static RECORDING: Collected<Recording>;
+}
+
+/// Run `future` and return its output with the timings recorded while it ran.
+pub(crate) async fn record<F: Future>(future: F) -> (F::Output, Timings) {
+    let started_at = SystemTime::now();
+    let started = Instant::now();
+    let (output, recording) = collector::collect(&RECORDING, future).await;
+    let timings = Timings {
+        started_at,
+        finished_at: started_at + started.elapsed(),
+        time_to_first_byte: recording.first_byte.map(|at| at.duration_since(started)),
+        retries: recording.retries,
+        tools: recording.tools,
+    };
+    (output, timings)
+}
+
+/// Note that a response started to arrive, if a recording is running and none did before.
+pub(crate) fn record_first_byte() {
+    collector::add(&RECORDING, |recording| {
+        recording.first_byte.get_or_insert_with(Instant::now);
+    });
+}
+
+/// Count a retried HTTP request, if a recording is running.
+pub(crate) fn record_retry() {
+    collector::add(&RECORDING, |recording| recording.retries += 1);
+}
+
+/// Note the execution time of a tool, if a recording is running.
+pub(crate) fn record_tool(name: &str, duration: Duration) {
+    collector::add(&RECORDING, |recording| {
+        recording.tools.push(ToolTiming {
+            name: name.to_string(),
+            duration,
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{
+        ScriptedTransport, mock_openai, responses_answer, responses_tool_call, sum_toolset,
+    };
+    use crate::{ChatRole, HttpClientConfig, Message, TextResponse};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_records_retries_and_tools_inside_the_scope() {
+        let ((), timings) = record(async {
+            record_retry();
+            record_first_byte();
+            record_tool("lookup", Duration::from_millis(5));
+            record_retry();
+            record_first_byte();
+        })
+        .await;
+        // Outside of a recording
+        record_retry();
+
+        assert_eq!(timings.retries, 2);
+        assert_eq!(
+            timings.tools,
+            vec![ToolTiming {
+                name: "lookup".to_string(),
+                duration: Duration::from_millis(5),
+            }]
+        );
+        assert!(timings.time_to_first_byte.unwrap() <= timings.duration());
+        assert!(timings.finished_at >= timings.started_at);
+    }
+
+    #[tokio::test]
+    async fn test_client_counts_retries_and_times_tools() {
+        let transport = ScriptedTransport::new(vec![
+            (503, json!({ "error": { "message": "overloaded" } })),
+            (
+                200,
+                responses_tool_call("calculate_sum", json!({ "a": 1, "b": 2 })),
+            ),
+            (200, responses_answer("The sum is 3")),
+        ]);
+
+        let response = mock_openai(transport.clone())
+            .messages(vec![Message::new(ChatRole::User, "What is 1 + 2?")])
+            .http_client_config(HttpClientConfig {
+                initial_retry_delay: Duration::from_millis(1),
+                transport: Some(transport),
+                ..Default::default()
+            })
+            .tools(sum_toolset())
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+
+        let timings = response.metadata.timings.expect("timings");
+        assert_eq!(timings.retries, 1);
+        assert_eq!(timings.tools.len(), 1);
+        assert_eq!(timings.tools[0].name, "calculate_sum");
+        assert!(timings.duration() >= timings.tools[0].duration);
+        // Only the reqwest transport sees the response headers arrive
+        assert_eq!(timings.time_to_first_byte, None);
+    }
+}
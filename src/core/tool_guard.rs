@@ -1,5 +1,246 @@
-use crate::core::LlmError;
-use std::time::Duration;
+use crate::core::hash::fnv1a;
+use crate::core::{
+    BoxFuture, ConversationMessage, LanguageModelUsage, LlmError, ProviderResponse, ToolCall,
+    ToolCallResult, ToolRegistry,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+type IterationHook = Arc<dyn Fn(u32) -> BoxFuture<'static, ()> + Send + Sync>;
+type ToolCallHook = Arc<dyn Fn(ToolCall) -> BoxFuture<'static, ()> + Send + Sync>;
+type ToolResultHook =
+    Arc<dyn Fn(ToolCall, serde_json::Value) -> BoxFuture<'static, ()> + Send + Sync>;
+type ThoughtHook = Arc<dyn Fn(String) -> BoxFuture<'static, ()> + Send + Sync>;
+type FinalHook = Arc<dyn Fn(ProviderResponse) -> BoxFuture<'static, ()> + Send + Sync>;
+type EventHook = Arc<dyn Fn(ToolLoopEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+type CheckpointHook = Arc<dyn Fn(ToolLoopCheckpoint) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A state transition of the tool calling loop, to persist a run for replaying or debugging
+/// it. Register a callback with [`ToolCallingConfig::on_event`].
+///
+/// Serializes with an `event` tag, e.g.
+/// `{"event":"tool_called","iteration":1,"tool":"get_weather","call_id":"call_1","arguments_hash":"8c3f5a1e2b9d0c47"}`.
+/// Arguments and results are not included, only a hash and a size, so logs don't leak what
+/// the tools handle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ToolLoopEvent {
+    /// A model request is about to be sent. `usage` are the tokens used by the loop so far.
+    IterationStarted {
+        iteration: u32,
+        usage: LanguageModelUsage,
+    },
+    /// The model requested a tool, about to be executed
+    ToolCalled {
+        iteration: u32,
+        tool: String,
+        call_id: String,
+        /// FNV-1a hash of the serialized arguments, equal for identical arguments
+        arguments_hash: String,
+    },
+    /// A tool returned its result
+    ToolFinished {
+        iteration: u32,
+        tool: String,
+        call_id: String,
+        /// Length of the serialized result in bytes
+        result_bytes: usize,
+        /// Whether the result of an earlier call was reused instead of executing the tool
+        reused: bool,
+    },
+    /// The model answered without requesting more tools
+    Finished {
+        iterations: u32,
+        tool_calls: u32,
+        usage: LanguageModelUsage,
+    },
+    /// The loop ended with an error instead of an answer, e.g. a failed request or tool, a
+    /// call or iteration limit, the timeout or a stop
+    Failed {
+        iterations: u32,
+        tool_calls: u32,
+        usage: LanguageModelUsage,
+        error: String,
+    },
+}
+
+/// The state of a tool calling loop between two iterations, to continue it after a restart
+/// or on another worker.
+///
+/// Register a callback with [`ToolCallingConfig::on_checkpoint`] to receive one after the
+/// tools of each iteration ran, and continue from it with [`ToolCallingConfig::resume`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolLoopCheckpoint {
+    /// The messages of the request followed by the tool calls and results so far
+    pub messages: Vec<ConversationMessage>,
+    /// Iterations completed, counted towards the iteration limit when resuming
+    pub iteration: u32,
+    /// Tool executions so far, counted towards the tool call limits when resuming
+    pub tool_calls: u32,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub calls_per_tool: HashMap<String, u32>,
+    /// Tokens used by the model requests so far
+    pub usage: LanguageModelUsage,
+}
+
+/// Async callbacks invoked while the tool calling loop runs.
+///
+/// The loop awaits each callback before continuing, so keep them short
+/// (e.g. forward to a channel) to avoid slowing the conversation down.
+/// Register them through the `on_*` methods of [`ToolCallingConfig`].
+#[derive(Clone, Default)]
+pub struct ToolLoopHooks {
+    on_iteration_start: Option<IterationHook>,
+    on_tool_call: Option<ToolCallHook>,
+    on_tool_result: Option<ToolResultHook>,
+    on_thought: Option<ThoughtHook>,
+    on_final: Option<FinalHook>,
+    on_event: Option<EventHook>,
+    on_checkpoint: Option<CheckpointHook>,
+    /// Ends the loop with [`LlmError::Aborted`] after the iteration in which it is cancelled
+    stop: Option<CancellationToken>,
+}
+
+impl ToolLoopHooks {
+    pub(crate) async fn iteration_start(&self, iteration: u32) {
+        if let Some(hook) = &self.on_iteration_start {
+            hook(iteration).await;
+        }
+    }
+
+    pub(crate) async fn tool_call(&self, call: &ToolCall) {
+        if let Some(hook) = &self.on_tool_call {
+            hook(call.clone()).await;
+        }
+    }
+
+    pub(crate) async fn tool_result(&self, call: &ToolCall, result: &serde_json::Value) {
+        if let Some(hook) = &self.on_tool_result {
+            hook(call.clone(), result.clone()).await;
+        }
+    }
+
+    pub(crate) async fn thought(&self, text: &str) {
+        if let Some(hook) = &self.on_thought {
+            hook(text.to_string()).await;
+        }
+    }
+
+    pub(crate) async fn final_response(&self, response: &ProviderResponse) {
+        if let Some(hook) = &self.on_final {
+            hook(response.clone()).await;
+        }
+    }
+
+    /// Send the event built by `event`, which is only called if a callback is registered.
+    async fn event(&self, event: impl FnOnce() -> ToolLoopEvent) {
+        if let Some(hook) = &self.on_event {
+            hook(event()).await;
+        }
+    }
+}
+
+impl fmt::Debug for ToolLoopHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToolLoopHooks")
+            .field("on_iteration_start", &self.on_iteration_start.is_some())
+            .field("on_tool_call", &self.on_tool_call.is_some())
+            .field("on_tool_result", &self.on_tool_result.is_some())
+            .field("on_thought", &self.on_thought.is_some())
+            .field("on_final", &self.on_final.is_some())
+            .field("on_event", &self.on_event.is_some())
+            .field("on_checkpoint", &self.on_checkpoint.is_some())
+            .field("stop", &self.stop.is_some())
+            .finish()
+    }
+}
+
+/// What to do when the model repeats a tool call with identical arguments
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateCalls {
+    /// Execute the tool again
+    #[default]
+    Allow,
+    /// Skip execution and answer with the result of the earlier call
+    ReuseResult,
+    /// Abort the loop with [`LlmError::DuplicateToolCall`]
+    Error,
+}
+
+/// Cache scope of the registry's context, tool name and serialized arguments.
+type CacheKey = (u64, String, String);
+
+/// Results of `#[tool(cacheable)]` tools that outlive a single tool calling loop.
+///
+/// Clones share the same entries, so one cache can be passed to many requests. Results are
+/// kept per context: registries created with another context, e.g. through
+/// [`ToolRegistry::scoped`] or [`LlmBuilder::tools_with_context`](crate::LlmBuilder::tools_with_context),
+/// don't see each other's results.
+#[derive(Debug, Clone)]
+pub struct ToolCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<CacheKey, (Instant, serde_json::Value)>>>,
+}
+
+impl ToolCache {
+    /// Create a cache whose entries expire `ttl` after the tool ran.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Remove all cached results.
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<serde_json::Value> {
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: CacheKey, value: serde_json::Value) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(key, (Instant::now(), value));
+        }
+    }
+}
+
+/// A checkpoint that the next loop started with a [`ToolCallingConfig`] resumes, see
+/// [`ToolCallingConfig::resume`]. Clones share it, so it is resumed only once.
+#[derive(Debug, Clone, Default)]
+pub struct PendingCheckpoint(Arc<Mutex<Option<ToolLoopCheckpoint>>>);
+
+impl PendingCheckpoint {
+    pub fn new(checkpoint: ToolLoopCheckpoint) -> Self {
+        Self(Arc::new(Mutex::new(Some(checkpoint))))
+    }
+
+    /// Remove the checkpoint, `None` if there is none or it was already taken.
+    pub fn take(&self) -> Option<ToolLoopCheckpoint> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut checkpoint| checkpoint.take())
+    }
+}
 
 /// Configuration for tool calling behavior and limits
 #[derive(Debug, Clone)]
@@ -8,14 +249,25 @@ pub struct ToolCallingConfig {
     pub max_iterations: u32,
     /// Timeout for tool calling loop (default: 5 minutes)
     pub timeout: Duration,
+    /// Maximum number of tool executions across the whole loop (default: unlimited)
+    pub max_tool_calls: Option<u32>,
+    /// Maximum number of executions per tool name (default: none)
+    pub tool_call_limits: HashMap<String, u32>,
+    /// Handling of repeated calls with identical arguments (default: allow)
+    pub duplicate_calls: DuplicateCalls,
+    /// Results of cacheable tools shared across runs (default: none, results are
+    /// only reused within one loop)
+    pub tool_cache: Option<ToolCache>,
+    /// Progress callbacks for the loop (default: none)
+    pub hooks: ToolLoopHooks,
+    /// State of an earlier run to continue from, see [`resume`](Self::resume) (default:
+    /// none, start a new loop)
+    pub checkpoint: PendingCheckpoint,
 }
 
 impl Default for ToolCallingConfig {
     fn default() -> Self {
-        Self {
-            max_iterations: 50,
-            timeout: Duration::from_secs(300),
-        }
+        Self::new(50, Duration::from_secs(300))
     }
 }
 
@@ -25,6 +277,132 @@ impl ToolCallingConfig {
         Self {
             max_iterations,
             timeout,
+            max_tool_calls: None,
+            tool_call_limits: HashMap::new(),
+            duplicate_calls: DuplicateCalls::default(),
+            tool_cache: None,
+            hooks: ToolLoopHooks::default(),
+            checkpoint: PendingCheckpoint::default(),
+        }
+    }
+
+    /// Limit the total number of tool executions in the loop.
+    pub fn with_max_tool_calls(mut self, limit: u32) -> Self {
+        self.max_tool_calls = Some(limit);
+        self
+    }
+
+    /// Limit how often the named tool may be executed, e.g. `("web_search", 3)`.
+    pub fn with_tool_call_limit(mut self, tool_name: impl Into<String>, limit: u32) -> Self {
+        self.tool_call_limits.insert(tool_name.into(), limit);
+        self
+    }
+
+    /// Set how repeated calls with identical arguments are handled.
+    ///
+    /// Tools marked `#[tool(cacheable)]` always reuse their results instead.
+    pub fn with_duplicate_calls(mut self, policy: DuplicateCalls) -> Self {
+        self.duplicate_calls = policy;
+        self
+    }
+
+    /// Share results of cacheable tools with other runs using the same cache.
+    pub fn with_tool_cache(mut self, cache: ToolCache) -> Self {
+        self.tool_cache = Some(cache);
+        self
+    }
+
+    /// Called with the 1-based iteration number before each model request.
+    pub fn on_iteration_start<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_iteration_start = Some(Arc::new(move |iteration| Box::pin(hook(iteration))));
+        self
+    }
+
+    /// Called before a tool requested by the model is executed.
+    pub fn on_tool_call<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolCall) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_tool_call = Some(Arc::new(move |call| Box::pin(hook(call))));
+        self
+    }
+
+    /// Called with the output of each successfully executed tool.
+    pub fn on_tool_result<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolCall, serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_tool_result =
+            Some(Arc::new(move |call, result| Box::pin(hook(call, result))));
+        self
+    }
+
+    /// Called with text the model writes alongside its tool calls, e.g. its reasoning
+    /// before acting. That text is not part of the final response.
+    pub fn on_thought<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_thought = Some(Arc::new(move |text| Box::pin(hook(text))));
+        self
+    }
+
+    /// Called with the final model response once no more tools are requested.
+    pub fn on_final<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ProviderResponse) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_final = Some(Arc::new(move |response| Box::pin(hook(response))));
+        self
+    }
+
+    /// Called with every [`ToolLoopEvent`] of the loop, e.g. to write them to a JSON lines
+    /// file.
+    pub fn on_event<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolLoopEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_event = Some(Arc::new(move |event| Box::pin(hook(event))));
+        self
+    }
+
+    /// Called with a [`ToolLoopCheckpoint`] after the tools of each iteration ran, e.g. to
+    /// persist it so that the run survives a restart.
+    pub fn on_checkpoint<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(ToolLoopCheckpoint) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hooks.on_checkpoint = Some(Arc::new(move |checkpoint| Box::pin(hook(checkpoint))));
+        self
+    }
+
+    /// End the loop with [`LlmError::Aborted`] once the tools of the iteration in which
+    /// `token` is cancelled ran, instead of sending their results to the model.
+    pub(crate) fn stop_after_iteration(mut self, token: CancellationToken) -> Self {
+        self.hooks.stop = Some(token);
+        self
+    }
+
+    /// Continue the loop of `checkpoint` instead of starting a new one. Its messages replace
+    /// the messages of the request, and its iterations, tool calls and usage count towards
+    /// the limits and totals of the resumed loop.
+    ///
+    /// Only the first loop started with this config, or a clone of it, resumes the
+    /// checkpoint. Re-asks, fallbacks and later requests of the same client start new loops.
+    pub fn resume(self, checkpoint: ToolLoopCheckpoint) -> Self {
+        Self {
+            checkpoint: PendingCheckpoint::new(checkpoint),
+            ..self
         }
     }
 }
@@ -38,34 +416,65 @@ pub struct ToolCallingGuard {
     pub timeout: Duration,
     /// Current iteration count
     current_iteration: u32,
+    max_tool_calls: Option<u32>,
+    tool_call_limits: HashMap<String, u32>,
+    duplicate_calls: DuplicateCalls,
+    /// Number of executed tool calls
+    tool_calls: u32,
+    calls_per_tool: HashMap<String, u32>,
+    /// Results of earlier calls keyed by tool name and serialized arguments
+    previous_results: HashMap<CacheKey, serde_json::Value>,
+    tool_cache: Option<ToolCache>,
+    /// Tokens used by the model requests of the loop so far
+    usage: LanguageModelUsage,
+    /// Messages of the request followed by the tool calls and results, once the loop started
+    /// or when resuming a checkpoint
+    messages: Option<Vec<ConversationMessage>>,
+    /// Progress callbacks for the loop
+    pub(crate) hooks: ToolLoopHooks,
 }
 
 impl ToolCallingGuard {
     /// Create a new ToolCallingGuard with default limits
     pub fn new() -> Self {
-        Self {
-            max_iterations: 50,
-            timeout: Duration::from_secs(300), // 5 minutes default
-            current_iteration: 0,
-        }
+        Self::from_config(&ToolCallingConfig::default())
     }
 
     /// Create a new ToolCallingGuard with custom limits
     pub fn with_limits(max_iterations: u32, timeout: Duration) -> Self {
-        Self {
-            max_iterations,
-            timeout,
-            current_iteration: 0,
-        }
+        Self::from_config(&ToolCallingConfig::new(max_iterations, timeout))
     }
 
     /// Create a new ToolCallingGuard from a config
     pub fn from_config(config: &ToolCallingConfig) -> Self {
-        Self {
+        let mut guard = Self {
             max_iterations: config.max_iterations,
             timeout: config.timeout,
             current_iteration: 0,
+            max_tool_calls: config.max_tool_calls,
+            tool_call_limits: config.tool_call_limits.clone(),
+            duplicate_calls: config.duplicate_calls,
+            tool_calls: 0,
+            calls_per_tool: HashMap::new(),
+            previous_results: HashMap::new(),
+            tool_cache: config.tool_cache.clone(),
+            usage: LanguageModelUsage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                cached_tokens: None,
+            },
+            messages: None,
+            hooks: config.hooks.clone(),
+        };
+        if let Some(checkpoint) = config.checkpoint.take() {
+            guard.current_iteration = checkpoint.iteration;
+            guard.tool_calls = checkpoint.tool_calls;
+            guard.calls_per_tool = checkpoint.calls_per_tool;
+            guard.usage = checkpoint.usage;
+            guard.messages = Some(checkpoint.messages);
         }
+        guard
     }
 
     /// Increment iteration count and check if limit is exceeded
@@ -83,6 +492,223 @@ impl ToolCallingGuard {
     pub fn current_iteration(&self) -> u32 {
         self.current_iteration
     }
+
+    /// Get the number of tool calls executed so far
+    pub fn tool_calls(&self) -> u32 {
+        self.tool_calls
+    }
+
+    /// The messages the loop starts from: those of a resumed checkpoint, or else `messages`.
+    pub(crate) fn start(&mut self, messages: &[ConversationMessage]) -> Vec<ConversationMessage> {
+        self.messages
+            .get_or_insert_with(|| messages.to_vec())
+            .clone()
+    }
+
+    /// The state of the loop after the tools of the current iteration ran.
+    pub fn checkpoint(&self) -> ToolLoopCheckpoint {
+        ToolLoopCheckpoint {
+            messages: self.messages.clone().unwrap_or_default(),
+            iteration: self.current_iteration,
+            tool_calls: self.tool_calls,
+            calls_per_tool: self.calls_per_tool.clone(),
+            usage: self.usage.clone(),
+        }
+    }
+
+    /// Report a checkpoint of the completed iteration to the loop hooks.
+    pub(crate) async fn iteration_finished(&self) {
+        if let Some(hook) = &self.hooks.on_checkpoint {
+            hook(self.checkpoint()).await;
+        }
+    }
+
+    /// Fail with [`LlmError::Aborted`] if the loop was told to stop after this iteration.
+    pub(crate) fn check_stop(&self) -> Result<(), LlmError> {
+        match &self.hooks.stop {
+            Some(token) if token.is_cancelled() => Err(LlmError::Aborted),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tokens used by the model requests of the loop so far.
+    pub(crate) fn usage(&self) -> &LanguageModelUsage {
+        &self.usage
+    }
+
+    /// Add the usage of a model response to the tokens used so far.
+    pub(crate) fn add_usage(&mut self, usage: &LanguageModelUsage) {
+        self.usage = self.usage.combined(usage);
+    }
+
+    /// Report the start of the current iteration to the loop hooks.
+    pub(crate) async fn iteration_started(&self) {
+        let iteration = self.current_iteration;
+        self.hooks.iteration_start(iteration).await;
+        self.hooks
+            .event(|| ToolLoopEvent::IterationStarted {
+                iteration,
+                usage: self.usage.clone(),
+            })
+            .await;
+    }
+
+    /// Report the final response to the loop hooks.
+    pub(crate) async fn finished(&self, response: &ProviderResponse) {
+        self.hooks.final_response(response).await;
+        self.hooks
+            .event(|| ToolLoopEvent::Finished {
+                iterations: self.current_iteration,
+                tool_calls: self.tool_calls,
+                usage: self.usage.clone(),
+            })
+            .await;
+    }
+
+    /// Report the error that ended the loop to the loop hooks.
+    pub(crate) async fn failed(&self, error: &LlmError) {
+        self.hooks
+            .event(|| ToolLoopEvent::Failed {
+                iterations: self.current_iteration,
+                tool_calls: self.tool_calls,
+                usage: self.usage.clone(),
+                error: error.to_string(),
+            })
+            .await;
+    }
+
+    /// Execute a tool call after checking the call limits and duplicate policy,
+    /// reporting progress to the loop hooks.
+    pub(crate) async fn execute_tool<Ctx>(
+        &mut self,
+        tool_registry: &ToolRegistry<Ctx>,
+        tool_call: &ToolCall,
+    ) -> Result<serde_json::Value, LlmError>
+    where
+        Ctx: Send + Sync + 'static,
+    {
+        let result = self.run_tool(tool_registry, tool_call).await?;
+        if let Some(messages) = &mut self.messages {
+            messages.push(ConversationMessage::ToolCall(tool_call.clone()));
+            messages.push(ConversationMessage::ToolCallResult(ToolCallResult {
+                id: tool_call.id.clone(),
+                tool_call_id: tool_call.call_id.clone(),
+                content: result.clone(),
+            }));
+        }
+        Ok(result)
+    }
+
+    async fn run_tool<Ctx>(
+        &mut self,
+        tool_registry: &ToolRegistry<Ctx>,
+        tool_call: &ToolCall,
+    ) -> Result<serde_json::Value, LlmError>
+    where
+        Ctx: Send + Sync + 'static,
+    {
+        let key = (
+            tool_registry.cache_scope(),
+            tool_call.name.clone(),
+            tool_call.arguments.to_string(),
+        );
+        let cacheable = tool_registry.is_cacheable(&tool_call.name);
+
+        let reused = if cacheable {
+            self.cached_result(&key)
+        } else {
+            match (self.previous_results.get(&key), self.duplicate_calls) {
+                (Some(previous), DuplicateCalls::ReuseResult) => Some(previous.clone()),
+                (Some(_), DuplicateCalls::Error) => {
+                    return Err(LlmError::DuplicateToolCall {
+                        tool_name: tool_call.name.clone(),
+                    });
+                }
+                _ => None,
+            }
+        };
+
+        if let Some(result) = reused {
+            tracing::debug!(tool = %tool_call.name, "Reusing earlier tool result");
+            self.tool_called(tool_call, &key.2).await;
+            self.tool_finished(tool_call, &result, true).await;
+            return Ok(result);
+        }
+
+        self.count_tool_call(&tool_call.name)?;
+
+        self.tool_called(tool_call, &key.2).await;
+        let started = Instant::now();
+        let result = tool_registry.execute(tool_call).await?;
+        super::timings::record_tool(&tool_call.name, started.elapsed());
+        self.tool_finished(tool_call, &result, false).await;
+
+        if cacheable && let Some(cache) = &self.tool_cache {
+            cache.insert(key.clone(), result.clone());
+        }
+        if cacheable || self.duplicate_calls != DuplicateCalls::Allow {
+            self.previous_results.insert(key, result.clone());
+        }
+        Ok(result)
+    }
+
+    async fn tool_called(&self, tool_call: &ToolCall, arguments: &str) {
+        self.hooks.tool_call(tool_call).await;
+        self.hooks
+            .event(|| ToolLoopEvent::ToolCalled {
+                iteration: self.current_iteration,
+                tool: tool_call.name.clone(),
+                call_id: tool_call.call_id.clone(),
+                arguments_hash: format!("{:016x}", fnv1a(arguments.as_bytes())),
+            })
+            .await;
+    }
+
+    async fn tool_finished(&self, tool_call: &ToolCall, result: &serde_json::Value, reused: bool) {
+        self.hooks.tool_result(tool_call, result).await;
+        self.hooks
+            .event(|| ToolLoopEvent::ToolFinished {
+                iteration: self.current_iteration,
+                tool: tool_call.name.clone(),
+                call_id: tool_call.call_id.clone(),
+                result_bytes: result.to_string().len(),
+                reused,
+            })
+            .await;
+    }
+
+    /// Result of an earlier call in this loop, or in an earlier run via the shared cache
+    fn cached_result(&self, key: &CacheKey) -> Option<serde_json::Value> {
+        self.previous_results
+            .get(key)
+            .cloned()
+            .or_else(|| self.tool_cache.as_ref()?.get(key))
+    }
+
+    /// Count a tool execution and check the total and per-tool limits
+    fn count_tool_call(&mut self, tool_name: &str) -> Result<(), LlmError> {
+        self.tool_calls = self.tool_calls.saturating_add(1);
+        if let Some(limit) = self.max_tool_calls
+            && self.tool_calls > limit
+        {
+            return Err(LlmError::ToolCallLimit { limit });
+        }
+
+        let count = self
+            .calls_per_tool
+            .entry(tool_name.to_string())
+            .or_default();
+        *count = count.saturating_add(1);
+        if let Some(&limit) = self.tool_call_limits.get(tool_name)
+            && *count > limit
+        {
+            return Err(LlmError::ToolCallLimitPerTool {
+                tool_name: tool_name.to_string(),
+                limit,
+            });
+        }
+        Ok(())
+    }
 }
 
 impl Default for ToolCallingGuard {
@@ -132,6 +758,9 @@ mod tests {
         let config = ToolCallingConfig::default();
         assert_eq!(config.max_iterations, 50);
         assert_eq!(config.timeout, Duration::from_secs(300));
+        assert_eq!(config.max_tool_calls, None);
+        assert!(config.tool_call_limits.is_empty());
+        assert_eq!(config.duplicate_calls, DuplicateCalls::Allow);
     }
 
     #[test]
@@ -143,4 +772,33 @@ mod tests {
         assert_eq!(guard.timeout, Duration::from_secs(450));
         assert_eq!(guard.current_iteration(), 0);
     }
+
+    #[test]
+    fn test_checkpoint_is_resumed_once() {
+        let config = ToolCallingConfig::default().resume(ToolLoopCheckpoint {
+            messages: Vec::new(),
+            iteration: 3,
+            tool_calls: 2,
+            calls_per_tool: HashMap::new(),
+            usage: LanguageModelUsage {
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+                cached_tokens: None,
+            },
+        });
+        let shared = config.clone();
+
+        let resumed = ToolCallingGuard::from_config(&config);
+        assert_eq!(resumed.current_iteration(), 3);
+        assert_eq!(resumed.tool_calls(), 2);
+        assert_eq!(
+            ToolCallingGuard::from_config(&shared).current_iteration(),
+            0
+        );
+        assert_eq!(
+            ToolCallingGuard::from_config(&config).current_iteration(),
+            0
+        );
+    }
 }
@@ -0,0 +1,117 @@
+//! Usage counters of tools, kept by every [`ToolRegistry`](crate::ToolRegistry).
+//!
+//! Agent runs also [collect](super::collector) the calls made while they run, the same way
+//! [`timings`](super::timings) records a completion, for the
+//! [`tool_stats`](crate::agents::AgentRun::tool_stats) of their report.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::collector::{self, Collected};
+
+/// Calls of one tool, see [`ToolRegistry::stats`](crate::ToolRegistry::stats).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolStats {
+    pub calls: u64,
+    /// Calls that failed, including results that didn't match the tool's output schema
+    pub errors: u64,
+    /// Execution time summed over all calls
+    pub total_duration: Duration,
+    /// When the tool was last called
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_called: Option<SystemTime>,
+}
+
+impl ToolStats {
+    /// Mean execution time, `None` before the first call.
+    pub fn average_duration(&self) -> Option<Duration> {
+        (self.calls > 0).then(|| self.total_duration.div_f64(self.calls as f64))
+    }
+
+    fn add(&mut self, started_at: SystemTime, duration: Duration, failed: bool) {
+        self.calls += 1;
+        self.errors += u64::from(failed);
+        self.total_duration += duration;
+        self.last_called = Some(
+            self.last_called
+                .map_or(started_at, |last| last.max(started_at)),
+        );
+    }
+}
+
+pub(crate) type StatsMap = Collected<HashMap<String, ToolStats>>;
+
+tokio::task_local! {
+    static RUN_STATS: StatsMap;
+}
+
+/// Count a call of `name` in `stats` and in the run being collected, if any.
+pub(crate) fn record(
+    stats: &StatsMap,
+    name: &str,
+    started_at: SystemTime,
+    duration: Duration,
+    failed: bool,
+) {
+    let add = |stats: &mut HashMap<String, ToolStats>| {
+        stats
+            .entry(name.to_string())
+            .or_default()
+            .add(started_at, duration, failed)
+    };
+    if let Ok(mut stats) = stats.lock() {
+        add(&mut stats);
+    }
+    collector::add(&RUN_STATS, add);
+}
+
+/// Run `future` and return its output with the tool calls made while it ran.
+pub(crate) fn collect<F: Future>(
+    future: F,
+) -> impl Future<Output = (F::Output, HashMap<String, ToolStats>)> {
+    collector::collect(&RUN_STATS, future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_are_counted_in_the_registry_and_the_run() {
+        let registry = StatsMap::default();
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        record(
+            &registry,
+            "lookup",
+            started_at,
+            Duration::from_millis(4),
+            false,
+        );
+
+        let ((), run) = collect(async {
+            record(
+                &registry,
+                "lookup",
+                started_at + Duration::from_secs(1),
+                Duration::from_millis(2),
+                true,
+            );
+        })
+        .await;
+
+        let lookup = registry.lock().unwrap()["lookup"].clone();
+        assert_eq!(lookup.calls, 2);
+        assert_eq!(lookup.errors, 1);
+        assert_eq!(lookup.total_duration, Duration::from_millis(6));
+        assert_eq!(lookup.average_duration(), Some(Duration::from_millis(3)));
+        assert_eq!(
+            lookup.last_called,
+            Some(started_at + Duration::from_secs(1))
+        );
+        assert_eq!(run["lookup"].calls, 1);
+        assert_eq!(ToolStats::default().average_duration(), None);
+    }
+}
@@ -18,6 +18,62 @@ pub trait LlmProvider {
     where
         T: CompletionTarget + Send,
         Ctx: Send + Sync + 'static;
+
+    /// The body of the first request [`generate_completion`](Self::generate_completion) would
+    /// send for `request`, without sending it.
+    ///
+    /// The default returns [`LlmError::Builder`] for providers that can't build it up front.
+    fn request_body(
+        &self,
+        request: &StructuredRequest,
+        format: Format,
+    ) -> Result<serde_json::Value, LlmError> {
+        let _ = (request, format);
+        Err(LlmError::Builder(
+            "This provider doesn't support building request bodies".to_string(),
+        ))
+    }
+
+    /// Send `body` unchanged as the provider's generation request, with the client's
+    /// authentication, retries and inspectors, and parse the response. For provider features
+    /// rsai doesn't model yet.
+    ///
+    /// `model` is only used by providers that take the model from the URL (Gemini); the
+    /// others read it from `body`. The default returns [`LlmError::Builder`] for providers
+    /// without raw requests.
+    async fn complete_raw(
+        &self,
+        model: &str,
+        body: serde_json::Value,
+    ) -> Result<ProviderResponse, LlmError> {
+        let _ = (model, body);
+        Err(LlmError::Builder(
+            "This provider doesn't support raw requests".to_string(),
+        ))
+    }
+
+    /// Generate `count` independent candidate answers for the same request.
+    ///
+    /// The default sends `count` concurrent requests. Providers that can sample several
+    /// candidates in a single request override this.
+    async fn generate_candidates<T, Ctx>(
+        &self,
+        request: StructuredRequest,
+        format: Format,
+        tool_registry: Option<&ToolRegistry<Ctx>>,
+        count: u32,
+    ) -> Result<Vec<T::Output>, LlmError>
+    where
+        Self: Sync,
+        T: CompletionTarget + Send,
+        T::Output: Send,
+        Ctx: Send + Sync + 'static,
+    {
+        futures::future::try_join_all((0..count).map(|_| {
+            self.generate_completion::<T, Ctx>(request.clone(), format.clone(), tool_registry)
+        }))
+        .await
+    }
 }
 
 pub trait ToolFunction<Ctx = ()>: Send + Sync {
@@ -27,6 +83,49 @@ pub trait ToolFunction<Ctx = ()>: Send + Sync {
         ctx: &'a Ctx,
         params: serde_json::Value,
     ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>>;
+
+    /// Whether results may be reused for identical arguments instead of re-executing.
+    /// Set with `#[tool(cacheable)]`.
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    /// JSON Schema of the tool's result. When present, results are validated against it
+    /// before they are sent back to the model. Set with `#[tool(output_schema)]`.
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// Deprecation notice, logged as a warning whenever the tool is executed.
+    /// Set with `#[tool(deprecated = "...")]`.
+    fn deprecated(&self) -> Option<&str> {
+        None
+    }
+
+    /// Hidden tools can be executed but are left out of the schemas sent to the model
+    /// unless named in `enabled_tools`. Set with `#[tool(hidden)]`.
+    fn hidden(&self) -> bool {
+        false
+    }
+
+    /// Whether arguments the parameter schema doesn't declare are removed with a warning
+    /// before executing, instead of reaching the tool. Tools generated with `#[tool]` already
+    /// skip arguments they don't take.
+    fn ignore_unknown_arguments(&self) -> bool {
+        false
+    }
+
+    /// Whether [`execute`](Self::execute) coerces its arguments to the parameter schema
+    /// itself, see [`coerce_arguments`](crate::coerce_arguments). Tools generated with
+    /// `#[tool]` do.
+    fn coerces_arguments(&self) -> bool {
+        false
+    }
+}
+
+/// A typed reference to a tool in a toolset, generated by the item form of `toolset!`.
+pub trait ToolName: Copy {
+    fn tool_name(&self) -> &'static str;
 }
 
 pub trait CompletionTarget: Sized + Send {
@@ -37,6 +136,11 @@ pub trait CompletionTarget: Sized + Send {
     /// Parse a provider-agnostic response into the target output type.
     fn parse_response(res: ProviderResponse) -> Result<Self::Output, LlmError>;
 
+    /// Render the expected output of a few-shot example as the assistant message content.
+    fn format_example(output: serde_json::Value) -> String {
+        output.to_string()
+    }
+
     fn supports_tools() -> bool {
         true
     }
@@ -0,0 +1,392 @@
+//! Post-processing of parsed outputs.
+//!
+//! A [`Transform`] runs after the answer is parsed and before
+//! [`complete_map`](crate::LlmBuilder::complete_map) returns. It can turn the output into a
+//! different type, or [reject](Rejected) it: the reason is sent back to the model, which is asked
+//! to answer again, like an output guardrail with
+//! [`GuardrailAction::Retry`](crate::guardrails::GuardrailAction::Retry).
+//!
+//! # Example
+//! ```no_run
+//! use rsai::transform::Rejected;
+//! use rsai::{ApiKey, ChatRole, Message, Provider, StructuredResponse, completion_schema, llm};
+//!
+//! #[completion_schema]
+//! struct Meeting {
+//!     date: String,
+//!     attendees: Vec<String>,
+//! }
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! let attendees = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Extract the meeting from: ...")])
+//!     .complete_map::<Meeting, _>(|meeting: StructuredResponse<Meeting>| {
+//!         if meeting.content.attendees.is_empty() {
+//!             return Err(Rejected::new("the meeting has no attendees"));
+//!         }
+//!         Ok(meeting.content.attendees)
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Business rules that only accept or reject an output, without changing it, are registered with
+//! [`LlmBuilder::validate`](crate::LlmBuilder::validate) and apply to every completion of that
+//! type. Rules registered with [`LlmBuilder::validate_fields`](crate::LlmBuilder::validate_fields)
+//! name the [fields](FieldError) that broke them, so that
+//! [`LlmBuilder::partial_repair`](crate::LlmBuilder::partial_repair) can ask the model for just
+//! those fields instead of a whole new answer.
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use super::traits::CompletionTarget;
+use super::types::ProviderResponse;
+
+/// Reason a [`Transform`] rejected an output, sent back to the model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rejected {
+    pub reason: String,
+}
+
+impl Rejected {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A field of a structured output that broke a business rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// JSON pointer to the field, e.g. `/end_date` or `/stops/2/city`. Empty for the output as
+    /// a whole.
+    pub path: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Turns a parsed output of type `T` into the value returned to the caller.
+///
+/// Implemented for closures `Fn(T) -> Result<U, Rejected>`.
+pub trait Transform<T>: Send + Sync {
+    type Output;
+
+    fn transform(&self, value: T) -> Result<Self::Output, Rejected>;
+}
+
+impl<T, U, F> Transform<T> for F
+where
+    F: Fn(T) -> Result<U, Rejected> + Send + Sync,
+{
+    type Output = U;
+
+    fn transform(&self, value: T) -> Result<U, Rejected> {
+        self(value)
+    }
+}
+
+/// A business rule on structured outputs of one type, registered with
+/// [`LlmBuilder::validate`](crate::LlmBuilder::validate).
+#[derive(Clone)]
+pub(crate) struct Validator {
+    /// Serialized response format of the output type, to recognize its completions.
+    format: Option<serde_json::Value>,
+    check: Arc<Check>,
+}
+
+/// Checks a response, or returns `None` when it is not an output of the rule's type.
+type Check = dyn Fn(&ProviderResponse) -> Option<Result<(), Vec<FieldError>>> + Send + Sync;
+
+impl Validator {
+    pub(crate) fn new<T>(check: impl Fn(&T) -> Result<(), String> + Send + Sync + 'static) -> Self
+    where
+        T: DeserializeOwned + JsonSchema + Send,
+    {
+        Self::fields(move |value: &T| {
+            check(value).map_err(|reason| vec![FieldError::new("", reason)])
+        })
+    }
+
+    pub(crate) fn fields<T>(
+        check: impl Fn(&T) -> Result<(), Vec<FieldError>> + Send + Sync + 'static,
+    ) -> Self
+    where
+        T: DeserializeOwned + JsonSchema + Send,
+    {
+        Self {
+            format: T::format()
+                .ok()
+                .and_then(|format| serde_json::to_value(format).ok()),
+            // Answers that are not a `T` are left to the parser of the completion target
+            check: Arc::new(move |response| {
+                let parsed = <T as CompletionTarget>::parse_response(response.clone()).ok()?;
+                Some(check(&parsed.content))
+            }),
+        }
+    }
+
+    /// Whether the rule is for outputs requested with `format`.
+    pub(crate) fn applies_to(&self, format: &serde_json::Value) -> bool {
+        self.format.as_ref() == Some(format)
+    }
+
+    pub(crate) fn check(&self, response: &ProviderResponse) -> Result<(), Vec<FieldError>> {
+        match (self.check)(response) {
+            Some(Err(errors)) => Err(errors),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The reason sent back to the model for `errors`, prefixing each message with its field.
+pub(crate) fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| match error.path.as_str() {
+            "" => error.message.clone(),
+            path => format!("{path}: {}", error.message),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Replace the fields of `errors` in `value` with the corrected values in `fixes`, keyed by
+/// JSON pointer. Returns `false`, leaving `value` partly updated, if a field has no
+/// correction or its parent doesn't exist.
+pub(crate) fn merge_fields(
+    value: &mut Value,
+    errors: &[FieldError],
+    fixes: &Map<String, Value>,
+) -> bool {
+    errors.iter().all(|error| {
+        fixes
+            .get(&error.path)
+            .is_some_and(|fix| set_pointer(value, &error.path, fix.clone()))
+    })
+}
+
+/// Set the value at `pointer`, adding the last key to its parent object if it's missing.
+fn set_pointer(value: &mut Value, pointer: &str, new: Value) -> bool {
+    if let Some(target) = value.pointer_mut(pointer) {
+        *target = new;
+        return true;
+    }
+    let Some((parent, key)) = pointer.rsplit_once('/') else {
+        return false;
+    };
+    match value.pointer_mut(parent) {
+        Some(Value::Object(object)) => {
+            object.insert(key.replace("~1", "/").replace("~0", "~"), new);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, Sum, mock_openai, responses_answer};
+    use crate::{ChatRole, LlmError, Message, StructuredResponse, TextResponse};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_corrected_fields_are_merged_into_the_answer() {
+        let mut value = json!({
+            "start_date": "2026-05-10",
+            "end_date": "2026-05-03",
+            "stops": [{ "city": "Lisbn" }, { "city": "Porto" }]
+        });
+        let errors = vec![
+            FieldError::new("/end_date", "must not be before start_date"),
+            FieldError::new("/stops/0/city", "unknown city"),
+            FieldError::new("/notes", "required for trips over a week"),
+        ];
+        let fixes = json!({
+            "/end_date": "2026-05-17",
+            "/stops/0/city": "Lisbon",
+            "/notes": "Book the train early"
+        });
+
+        assert!(merge_fields(
+            &mut value,
+            &errors,
+            fixes.as_object().unwrap()
+        ));
+        assert_eq!(
+            value,
+            json!({
+                "start_date": "2026-05-10",
+                "end_date": "2026-05-17",
+                "stops": [{ "city": "Lisbon" }, { "city": "Porto" }],
+                "notes": "Book the train early"
+            })
+        );
+        assert_eq!(
+            describe(&errors[..2]),
+            "/end_date: must not be before start_date; /stops/0/city: unknown city"
+        );
+    }
+
+    #[test]
+    fn test_merge_fails_without_a_correction_for_every_field() {
+        let mut value = json!({ "end_date": "2026-05-03" });
+        let errors = vec![FieldError::new("/end_date", "too early")];
+        assert!(!merge_fields(&mut value, &errors, &Map::new()));
+
+        let errors = vec![FieldError::new("/trip/end_date", "too early")];
+        let fixes = json!({ "/trip/end_date": "2026-05-17" });
+        assert!(!merge_fields(
+            &mut value,
+            &errors,
+            fixes.as_object().unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_complete_map_transforms_and_re_asks_on_rejection() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":3}"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        };
+
+        let attempts = AtomicUsize::new(0);
+        let doubled = builder()
+            .complete_map::<Sum, _>(|response: StructuredResponse<Sum>| {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(Rejected::new("Show your work"));
+                }
+                Ok(response.content.sum * 2)
+            })
+            .await
+            .unwrap();
+        assert_eq!(doubled, 6);
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 2);
+        let retry = bodies[1]["input"].as_array().unwrap();
+        assert_eq!(retry.len(), 3);
+        assert_eq!(retry[1]["content"], "{\"sum\":3}");
+        assert!(
+            retry[2]["content"]
+                .as_str()
+                .unwrap()
+                .contains("Show your work")
+        );
+
+        let err = builder()
+            .complete_map::<Sum, _>(|_: StructuredResponse<Sum>| {
+                Err::<i64, _>(Rejected::new("Never good enough"))
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LlmError::OutputRejected { ref reason } if reason == "Never good enough"
+        ));
+        assert_eq!(transport.bodies().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_validate_re_asks_until_business_rule_holds() {
+        let transport = ScriptedTransport::answering(responses_answer("{\"sum\":4}"));
+        let builder = || {
+            mock_openai(transport.clone())
+                .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        };
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let response = builder()
+            .validate(move |response: &Sum| {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(format!("{} is not 1 + 2", response.sum));
+                }
+                Ok(())
+            })
+            .complete::<Sum>()
+            .await
+            .unwrap();
+        assert_eq!(response.content.sum, 4);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 2);
+        let retry = bodies[1]["input"].as_array().unwrap();
+        assert!(
+            retry[2]["content"]
+                .as_str()
+                .unwrap()
+                .contains("4 is not 1 + 2")
+        );
+
+        let err = builder()
+            .validate(|response: &Sum| match response.sum {
+                3 => Ok(()),
+                sum => Err(format!("{sum} is not 1 + 2")),
+            })
+            .complete::<Sum>()
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            LlmError::OutputRejected { ref reason } if reason == "4 is not 1 + 2"
+        ));
+        assert_eq!(transport.bodies().len(), 5);
+
+        // Validators of other output types are skipped
+        let text = builder()
+            .validate(|_: &Sum| Err("never valid".to_string()))
+            .complete::<TextResponse>()
+            .await
+            .unwrap();
+        assert_eq!(text.text, "{\"sum\":4}");
+    }
+
+    #[tokio::test]
+    async fn test_partial_repair_regenerates_only_invalid_fields() {
+        let transport = ScriptedTransport::new(vec![
+            (200, responses_answer("{\"sum\":4}")),
+            (200, responses_answer("{\"/sum\": 3}")),
+        ]);
+
+        let response = mock_openai(transport.clone())
+            .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+            .validate_fields(|response: &Sum| match response.sum {
+                3 => Ok(()),
+                sum => Err(vec![FieldError::new("/sum", format!("{sum} is not 1 + 2"))]),
+            })
+            .partial_repair(true)
+            .complete::<Sum>()
+            .await
+            .unwrap();
+        assert_eq!(response.content.sum, 3);
+        assert_eq!(response.usage.total_tokens, 30);
+
+        let bodies = transport.bodies();
+        assert_eq!(bodies.len(), 2);
+        let repair = bodies[1]["input"].as_array().unwrap();
+        assert_eq!(repair[1]["content"], "{\"sum\":4}");
+        let prompt = repair[2]["content"].as_str().unwrap();
+        assert!(prompt.contains("- /sum: 4 is not 1 + 2"), "{prompt}");
+        assert_eq!(bodies[1]["text"]["format"]["type"], "text");
+    }
+}
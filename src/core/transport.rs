@@ -0,0 +1,125 @@
+//! Pluggable transport layer underneath [`HttpClient`](super::HttpClient).
+//!
+//! The HTTP client owns retries, backoff and inspection; a [`Transport`] only moves a single
+//! request over the wire. Swapping the transport makes it possible to record, replay or stub
+//! provider traffic without touching provider code.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::error::LlmError;
+
+/// HTTP method of a [`TransportRequest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    Get,
+    #[default]
+    Post,
+    Patch,
+    Delete,
+}
+
+impl From<HttpMethod> for reqwest::Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => reqwest::Method::GET,
+            HttpMethod::Post => reqwest::Method::POST,
+            HttpMethod::Patch => reqwest::Method::PATCH,
+            HttpMethod::Delete => reqwest::Method::DELETE,
+        }
+    }
+}
+
+/// A single outgoing JSON request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// JSON body; `Null` sends no body.
+    pub body: serde_json::Value,
+}
+
+/// The raw response to a [`TransportRequest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Sends a single request and returns the raw response.
+///
+/// Implementations should return [`LlmError::Network`] for failures that are worth retrying;
+/// any other error is treated as fatal by the HTTP client.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError>;
+}
+
+/// Default transport backed by `reqwest`.
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    first_byte_timeout: Option<Duration>,
+}
+
+impl ReqwestTransport {
+    /// Create a transport from a preconfigured `reqwest` client.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            first_byte_timeout: None,
+        }
+    }
+
+    /// Fail with [`LlmError::Network`] when the response headers do not arrive within
+    /// `timeout` of sending the request.
+    pub fn with_first_byte_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.first_byte_timeout = timeout;
+        self
+    }
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        let mut req_builder = self.client.request(request.method.into(), &request.url);
+
+        if !request.body.is_null() {
+            req_builder = req_builder.json(&request.body);
+        }
+
+        for (name, value) in &request.headers {
+            req_builder = req_builder.header(name, value);
+        }
+
+        let send = req_builder.send();
+        let res = match self.first_byte_timeout {
+            Some(timeout) => {
+                super::runtime::timeout(timeout, send)
+                    .await
+                    .map_err(|e| LlmError::Network {
+                        message: format!("No response within {timeout:?}"),
+                        source: Box::new(e),
+                    })?
+            }
+            None => send.await,
+        }
+        .map_err(|e| LlmError::Network {
+            message: "Request failed".to_string(),
+            source: Box::new(e),
+        })?;
+        super::timings::record_first_byte();
+
+        let status = res.status().as_u16();
+        let body = res.text().await.map_err(|e| LlmError::Network {
+            message: "Failed to read response body".to_string(),
+            source: Box::new(e),
+        })?;
+
+        Ok(TransportResponse { status, body })
+    }
+}
@@ -1,15 +1,22 @@
-use crate::core::{LlmError, traits::CompletionTarget, traits::ToolFunction};
+use crate::core::{
+    Blob, LlmError, Timings, ToolStats, coerce_arguments, coercion::remove_unknown_arguments,
+    prompts::PromptVersion, restore_value, strict_schema, strict_value, tool_stats,
+    tool_stats::StatsMap, traits::CompletionTarget, traits::ToolFunction, validate_value,
+};
 use crate::provider::Provider;
 use crate::responses::{self, request::Format};
 use schemars::JsonSchema;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::ops::Deref;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::warn;
 
 /// Marker type for context/dependency injection in tools.
@@ -32,6 +39,42 @@ use tracing::warn;
 ///     db.search(&query)
 /// }
 /// ```
+///
+/// # Mutable state
+///
+/// Tools only get shared references, so state they change goes behind interior
+/// mutability, e.g. `Ctx<&Mutex<ShoppingCart>>` (or `tokio::sync::Mutex` when the lock is
+/// held across an `.await`). Finalize the toolset with
+/// [`ToolSetBuilder::with_shared_context`] to read the state after the request:
+///
+/// ```rust,ignore
+/// use std::sync::{Arc, Mutex};
+///
+/// #[derive(Default)]
+/// struct Shop {
+///     cart: Mutex<Vec<String>>,
+/// }
+///
+/// impl AsRef<Mutex<Vec<String>>> for Shop {
+///     fn as_ref(&self) -> &Mutex<Vec<String>> {
+///         &self.cart
+///     }
+/// }
+///
+/// #[tool]
+/// /// Add an item to the shopping cart
+/// /// item: Name of the item
+/// fn add_to_cart(cart: Ctx<&Mutex<Vec<String>>>, item: String) -> usize {
+///     let mut cart = cart.lock().unwrap();
+///     cart.push(item);
+///     cart.len()
+/// }
+///
+/// let shop = Arc::new(Shop::default());
+/// let tools = toolset![Shop => add_to_cart].with_shared_context(shop.clone());
+/// // ... run the request with `.tools(tools)` ...
+/// println!("{:?}", shop.cart.lock().unwrap());
+/// ```
 #[derive(Debug, Clone, Copy)]
 pub struct Ctx<T>(pub T);
 
@@ -49,20 +92,54 @@ impl<T> From<T> for Ctx<T> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ChatRole {
     System,
     User,
     Assistant,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub role: ChatRole,
     pub content: String,
+    /// Distinguishes participants with the same role, e.g. the users of a group chat. Sent by
+    /// providers whose API has a message name (OpenAI-compatible chat completions) and left
+    /// out by the others: OpenAI's Responses API, Gemini and Cohere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Application data attached to the message, e.g. a database id or timestamp. No provider
+    /// API accepts per-message metadata, so it is never sent.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub metadata: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl Message {
+    /// A message without a name or metadata.
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            name: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    /// Set the [`name`](Self::name) of the participant who wrote the message.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Attach `value` under `key` to the [`metadata`](Self::metadata) of the message.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
     pub call_id: String,
@@ -70,14 +147,15 @@ pub struct ToolCall {
     pub arguments: Value,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCallResult {
     pub id: String,
     pub tool_call_id: String,
     pub content: serde_json::Value,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ConversationMessage {
     Chat(Message),
     ToolCall(ToolCall),
@@ -120,7 +198,11 @@ pub struct ToolConfig {
 }
 
 /// Configuration for text generation parameters
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Non-exhaustive so that parameters can be added; start from [`GenerationConfig::default`]
+/// and set the fields you need.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
 pub struct GenerationConfig {
     /// Maximum number of tokens to generate
     pub max_tokens: Option<u32>,
@@ -130,49 +212,309 @@ pub struct GenerationConfig {
 
     /// Nucleus sampling parameter (0.0 to 1.0)
     pub top_p: Option<f32>,
+
+    /// Sequences that end generation, not included in the answer
+    pub stop: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StructuredResponse<T> {
     pub content: T,
     pub usage: LanguageModelUsage,
     pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The majority answer of several samples, returned by
+/// [`complete_consensus`](crate::core::LlmBuilder::complete_consensus).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Consensus<T> {
+    /// The most common answer. Ties go to the answer that was sampled first.
+    pub answer: StructuredResponse<T>,
+    /// Number of samples equal to `answer`.
+    pub votes: usize,
+    /// Number of samples taken.
+    pub samples: usize,
+    /// Number of distinct answers among the samples.
+    pub distinct_answers: usize,
+    /// Token usage summed over all samples.
+    pub usage: LanguageModelUsage,
+}
+
+impl<T> Consensus<T> {
+    /// Fraction of samples that agree with the answer (0.0 to 1.0).
+    pub fn agreement(&self) -> f64 {
+        self.votes as f64 / self.samples as f64
+    }
+
+    /// Pick the most common answer among `responses`.
+    ///
+    /// Returns `None` if `responses` is empty.
+    pub(crate) fn from_samples(responses: Vec<StructuredResponse<T>>) -> Option<Self>
+    where
+        T: PartialEq,
+    {
+        let samples = responses.len();
+        let usage = responses.iter().fold(
+            LanguageModelUsage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                cached_tokens: None,
+            },
+            |total, response| total.combined(&response.usage),
+        );
+
+        // (first sample of the answer, votes)
+        let mut groups: Vec<(StructuredResponse<T>, usize)> = Vec::new();
+        for response in responses {
+            match groups
+                .iter_mut()
+                .find(|(answer, _)| answer.content == response.content)
+            {
+                Some((_, votes)) => *votes += 1,
+                None => groups.push((response, 1)),
+            }
+        }
+
+        let distinct_answers = groups.len();
+        let mut best: Option<(StructuredResponse<T>, usize)> = None;
+        for (answer, votes) in groups {
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_votes)| votes > *best_votes)
+            {
+                best = Some((answer, votes));
+            }
+        }
+
+        best.map(|(answer, votes)| Consensus {
+            answer,
+            votes,
+            samples,
+            distinct_answers,
+            usage,
+        })
+    }
+}
+
+/// The label picked for a text, returned by [`classify`](crate::core::LlmBuilder::classify).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Classification<L> {
+    /// The most common label among the samples. Ties go to the label that was sampled first.
+    pub label: L,
+    /// Fraction of samples that picked `label` (0.0 to 1.0). `None` for a single sample,
+    /// which says nothing about how sure the model is.
+    pub confidence: Option<f64>,
+    /// The other labels that were sampled with their fraction of the samples, most common
+    /// first.
+    pub alternatives: Vec<(L, f64)>,
+    /// Token usage summed over all samples.
+    pub usage: LanguageModelUsage,
+    /// Metadata of the first sample that picked `label`.
+    pub metadata: ResponseMetadata,
+}
+
+impl<L: PartialEq> Classification<L> {
+    /// Rank the labels of `samples` by votes.
+    ///
+    /// Returns `None` if `samples` is empty.
+    pub(crate) fn from_samples(samples: Vec<StructuredResponse<L>>) -> Option<Self> {
+        let usage = samples.iter().fold(
+            LanguageModelUsage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                cached_tokens: None,
+            },
+            |total, sample| total.combined(&sample.usage),
+        );
+        let count = samples.len() as f64;
+
+        // (first sample of the label, votes), in the order the labels were first sampled
+        let mut groups: Vec<(StructuredResponse<L>, usize)> = Vec::new();
+        for sample in samples {
+            match groups
+                .iter_mut()
+                .find(|(label, _)| label.content == sample.content)
+            {
+                Some((_, votes)) => *votes += 1,
+                None => groups.push((sample, 1)),
+            }
+        }
+        // Stable, so ties keep the sampling order
+        groups.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let mut groups = groups.into_iter();
+        let (first, votes) = groups.next()?;
+        Some(Classification {
+            label: first.content,
+            confidence: (count > 1.0).then(|| votes as f64 / count),
+            alternatives: groups
+                .map(|(sample, votes)| (sample.content, votes as f64 / count))
+                .collect(),
+            usage,
+            metadata: first.metadata,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TextResponse {
     pub text: String,
     pub usage: LanguageModelUsage,
     pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LanguageModelUsage {
     pub prompt_tokens: i32,
     pub completion_tokens: i32,
     pub total_tokens: i32,
+    /// Prompt tokens served from the provider's prompt cache, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<i32>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl LanguageModelUsage {
+    /// Sum of two usages, e.g. for several requests.
+    pub fn combined(&self, other: &LanguageModelUsage) -> LanguageModelUsage {
+        LanguageModelUsage {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            cached_tokens: match (self.cached_tokens, other.cached_tokens) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            },
+        }
+    }
+
+    /// Fraction of prompt tokens that were served from cache (0.0 to 1.0).
+    /// Returns `None` if the provider did not report cached tokens or the prompt was empty.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let cached = self.cached_tokens?;
+        (self.prompt_tokens > 0).then(|| cached as f64 / self.prompt_tokens as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct ResponseMetadata {
     pub provider: Provider,
     pub model: String,
     pub id: String,
+    /// Sources the answer is attributed to. Empty unless the provider grounded the answer,
+    /// e.g. with search.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+    /// `Idempotency-Key` header of the request that produced the response, see
+    /// [`HttpClientConfig::idempotency_keys`](crate::HttpClientConfig::idempotency_keys).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Duration, retries and tool execution times of the completion. Recorded for completions
+    /// sent through [`LlmBuilder`](crate::LlmBuilder), `None` when calling a provider client
+    /// directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timings: Option<Timings>,
+    /// Whether the answer only became valid JSON after
+    /// [lenient parsing](crate::LlmBuilder::lenient_json) repaired it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lenient_json: bool,
+    /// The registered prompt the request was rendered from, see
+    /// [`LlmBuilder::prompt`](crate::LlmBuilder::prompt).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<PromptVersion>,
+}
+
+impl ResponseMetadata {
+    /// Metadata of the response `id` of `model`, without citations or request details.
+    pub fn new(provider: Provider, model: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            id: id.into(),
+            citations: Vec::new(),
+            idempotency_key: None,
+            timings: None,
+            lenient_json: false,
+            prompt: None,
+        }
+    }
+}
+
+/// A source backing part of an answer, parsed from Gemini grounding metadata, OpenAI
+/// output annotations or Cohere citations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    /// URL of the source, or the file name (falling back to the file id) for file citations.
+    /// Cohere documents without a `url` field are identified by their id.
+    pub source: String,
+    pub title: Option<String>,
+    /// Part of the answer text backed by the source, as reported by the provider. `None` if the
+    /// source is not tied to a specific part of the answer.
+    pub span: Option<std::ops::Range<usize>>,
 }
 
 /// Provider-agnostic response type that all providers convert to.
 /// This is the unified response format used by `CompletionTarget::parse_response`.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ProviderResponse {
     pub id: String,
     pub model: String,
     pub provider: Provider,
     pub content: ResponseContent,
     pub usage: LanguageModelUsage,
+    pub citations: Vec<Citation>,
+    pub idempotency_key: Option<String>,
+    pub timings: Option<Timings>,
+    /// Whether lenient parsing repaired the answer into valid JSON
+    pub lenient_json: bool,
+    /// The registered prompt the request was rendered from
+    pub prompt: Option<PromptVersion>,
 }
 
-/// The content of a provider response - either text, function calls, or a refusal.
+impl ProviderResponse {
+    /// A response with `content`, without citations or request details.
+    pub fn new(
+        id: impl Into<String>,
+        model: impl Into<String>,
+        provider: Provider,
+        content: ResponseContent,
+        usage: LanguageModelUsage,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            model: model.into(),
+            provider,
+            content,
+            usage,
+            citations: Vec::new(),
+            idempotency_key: None,
+            timings: None,
+            lenient_json: false,
+            prompt: None,
+        }
+    }
+
+    /// The metadata of the response, everything but its content and usage.
+    pub fn metadata(&self) -> ResponseMetadata {
+        ResponseMetadata {
+            provider: self.provider,
+            model: self.model.clone(),
+            id: self.id.clone(),
+            citations: self.citations.clone(),
+            idempotency_key: self.idempotency_key.clone(),
+            timings: self.timings.clone(),
+            lenient_json: self.lenient_json,
+            prompt: self.prompt.clone(),
+        }
+    }
+}
+
+/// The content of a provider response - text, function calls, a refusal or binary data.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ResponseContent {
     /// Plain text or structured JSON text response
     Text(String),
@@ -180,6 +522,12 @@ pub enum ResponseContent {
     FunctionCalls(Vec<FunctionCallData>),
     /// Model refused to respond
     Refusal(String),
+    /// Binary data the model returned inline, e.g. generated images, with the text that
+    /// came with it, if any
+    Binary {
+        blobs: Vec<Blob>,
+        text: Option<String>,
+    },
 }
 
 /// Data for a function call requested by the model.
@@ -198,6 +546,82 @@ type ToolMap<Ctx> = Arc<RwLock<HashMap<String, Arc<dyn ToolFunction<Ctx>>>>>;
 pub struct ToolRegistry<Ctx = ()> {
     tools: ToolMap<Ctx>,
     context: Arc<Ctx>,
+    coerce_arguments: bool,
+    ignore_unknown_arguments: bool,
+    stats: StatsMap,
+    /// Identifies the context in [`ToolCache`](crate::ToolCache) keys, so results of
+    /// cacheable tools aren't shared between registries with different contexts.
+    cache_scope: u64,
+}
+
+/// A cache scope for a new context. Zero-sized contexts carry no state, so their registries
+/// all share scope 0.
+fn new_cache_scope<Ctx>() -> u64 {
+    static NEXT_SCOPE: AtomicU64 = AtomicU64::new(1);
+    if std::mem::size_of::<Ctx>() == 0 {
+        0
+    } else {
+        NEXT_SCOPE.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Separator between a namespace and a tool name, e.g. `weather.get_weather`.
+pub const TOOL_NAMESPACE_SEPARATOR: &str = ".";
+
+/// Wraps a tool so it is registered as `{namespace}.{name}`.
+pub struct NamespacedTool<Ctx = ()> {
+    namespace: String,
+    tool: Arc<dyn ToolFunction<Ctx>>,
+}
+
+impl<Ctx> NamespacedTool<Ctx> {
+    pub fn new(namespace: impl Into<String>, tool: Arc<dyn ToolFunction<Ctx>>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            tool,
+        }
+    }
+}
+
+impl<Ctx: Send + Sync> ToolFunction<Ctx> for NamespacedTool<Ctx> {
+    fn schema(&self) -> Tool {
+        let schema = self.tool.schema();
+        Tool {
+            name: format!(
+                "{}{TOOL_NAMESPACE_SEPARATOR}{}",
+                self.namespace, schema.name
+            ),
+            ..schema
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        ctx: &'a Ctx,
+        params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        self.tool.execute(ctx, params)
+    }
+
+    fn cacheable(&self) -> bool {
+        self.tool.cacheable()
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        self.tool.output_schema()
+    }
+
+    fn deprecated(&self) -> Option<&str> {
+        self.tool.deprecated()
+    }
+
+    fn hidden(&self) -> bool {
+        self.tool.hidden()
+    }
+
+    fn ignore_unknown_arguments(&self) -> bool {
+        self.tool.ignore_unknown_arguments()
+    }
 }
 
 impl ToolRegistry<()> {
@@ -206,6 +630,10 @@ impl ToolRegistry<()> {
         Self {
             tools: Arc::new(RwLock::new(HashMap::new())),
             context: Arc::new(()),
+            coerce_arguments: false,
+            ignore_unknown_arguments: false,
+            stats: StatsMap::default(),
+            cache_scope: new_cache_scope::<()>(),
         }
     }
 }
@@ -216,6 +644,10 @@ impl<Ctx: Send + Sync + 'static> ToolRegistry<Ctx> {
         Self {
             tools: Arc::new(RwLock::new(HashMap::new())),
             context: Arc::new(context),
+            coerce_arguments: false,
+            ignore_unknown_arguments: false,
+            stats: StatsMap::default(),
+            cache_scope: new_cache_scope::<Ctx>(),
         }
     }
 
@@ -253,6 +685,22 @@ impl<Ctx: Send + Sync + 'static> ToolRegistry<Ctx> {
             });
         }
 
+        // Names that only differ in the namespace separator look the same to providers
+        // that receive encoded names
+        let wire_name = wire_tool_name(&schema.name);
+        if let Some(registered) = w_tools
+            .keys()
+            .find(|registered| wire_tool_name(registered) == wire_name)
+        {
+            return Err(LlmError::ToolRegistration {
+                tool_name: schema.name.clone(),
+                message: format!(
+                    "Tool {} is sent to providers as {wire_name}, like the already registered tool {registered}",
+                    schema.name
+                ),
+            });
+        }
+
         w_tools.insert(schema.name, tool);
         Ok(())
     }
@@ -275,17 +723,238 @@ impl<Ctx: Send + Sync + 'static> ToolRegistry<Ctx> {
         Ok(())
     }
 
+    /// Create a new tool registry around a context the caller keeps a handle to.
+    ///
+    /// Use this when tools mutate the context through interior mutability (e.g. a
+    /// `Mutex`) and the accumulated state is needed after the request completes.
+    pub fn with_shared_context(context: Arc<Ctx>) -> Self {
+        Self {
+            tools: Arc::new(RwLock::new(HashMap::new())),
+            context,
+            coerce_arguments: false,
+            ignore_unknown_arguments: false,
+            stats: StatsMap::default(),
+            cache_scope: new_cache_scope::<Ctx>(),
+        }
+    }
+
+    /// The context passed to tools.
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+
+    /// Coerce the arguments of every tool call to the types of the tool's parameter schema
+    /// before executing it, see [`coerce_arguments`](crate::coerce_arguments). Tools generated
+    /// with `#[tool]` always do; this covers hand-written [`ToolFunction`]s.
+    pub fn with_argument_coercion(mut self, enabled: bool) -> Self {
+        self.coerce_arguments = enabled;
+        self
+    }
+
+    /// Remove arguments the tool's parameter schema doesn't declare, where it forbids
+    /// additional properties, and log a warning instead of passing them to the tool, e.g. to
+    /// tools deserializing into types with `#[serde(deny_unknown_fields)]`. Single tools opt
+    /// in with [`ToolFunction::ignore_unknown_arguments`].
+    pub fn with_unknown_arguments_ignored(mut self, enabled: bool) -> Self {
+        self.ignore_unknown_arguments = enabled;
+        self
+    }
+
+    /// A registry with the same tools that passes `context` to them instead, e.g. the
+    /// session of the user making the current request.
+    ///
+    /// The tools and [`stats`](Self::stats) are shared, so tools registered later are
+    /// visible to both registries.
+    pub fn scoped(&self, context: Ctx) -> Self {
+        self.derive(
+            self.tools.clone(),
+            Arc::new(context),
+            new_cache_scope::<Ctx>(),
+        )
+    }
+
+    /// A registry of `tools` with `context` that keeps the settings and shares the
+    /// [`stats`](Self::stats) of this one.
+    fn derive(&self, tools: ToolMap<Ctx>, context: Arc<Ctx>, cache_scope: u64) -> Self {
+        Self {
+            tools,
+            context,
+            coerce_arguments: self.coerce_arguments,
+            ignore_unknown_arguments: self.ignore_unknown_arguments,
+            stats: Arc::clone(&self.stats),
+            cache_scope,
+        }
+    }
+
+    /// Usage of every tool called through this registry, by tool name. Clones, duplicates,
+    /// scoped and filtered registries count into the same stats.
+    pub fn stats(&self) -> HashMap<String, ToolStats> {
+        self.stats
+            .lock()
+            .map(|stats| stats.clone())
+            .unwrap_or_default()
+    }
+
+    /// Reset the [`stats`](Self::stats) of every tool.
+    pub fn reset_stats(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.clear();
+        }
+    }
+
+    fn tool_functions(&self) -> Result<Vec<Arc<dyn ToolFunction<Ctx>>>, LlmError> {
+        let r_tools = self
+            .tools
+            .read()
+            .map_err(|_| LlmError::ToolRegistryAccess {
+                message: "Failed to acquire read lock (lock poisoned)".to_string(),
+            })?;
+        Ok(r_tools.values().cloned().collect())
+    }
+
+    /// A registry with the tools of this one that shares its context and
+    /// [`stats`](Self::stats). Tools registered on either one later are not seen by the other.
+    pub(crate) fn duplicate(&self) -> Result<Self, LlmError> {
+        let tools = self
+            .tools
+            .read()
+            .map_err(|_| LlmError::ToolRegistryAccess {
+                message: "Failed to acquire read lock (lock poisoned)".to_string(),
+            })?
+            .clone();
+        Ok(self.derive(
+            Arc::new(RwLock::new(tools)),
+            self.context.clone(),
+            self.cache_scope,
+        ))
+    }
+
+    /// Create a registry with the tools whose schema matches `predicate`, sharing this
+    /// registry's context and [`stats`](Self::stats).
+    pub fn filter(&self, predicate: impl Fn(&Tool) -> bool) -> Result<Self, LlmError> {
+        let registry = self.derive(ToolMap::default(), self.context.clone(), self.cache_scope);
+        for tool in self.tool_functions()? {
+            if predicate(&tool.schema()) {
+                registry.register(tool)?;
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Map a tool name as sent by a provider back to the registered name.
+    ///
+    /// Providers that reject `.` in function names receive namespaced tools with the
+    /// separator encoded (see [`wire_tool_name`]); exact matches always win.
+    pub(crate) fn resolve_name(&self, name: &str) -> String {
+        let Ok(r_tools) = self.tools.read() else {
+            return name.to_string();
+        };
+
+        if r_tools.contains_key(name) {
+            return name.to_string();
+        }
+
+        r_tools
+            .keys()
+            .find(|registered| wire_tool_name(registered) == name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Scope of the registry's context in tool cache keys.
+    pub(crate) fn cache_scope(&self) -> u64 {
+        self.cache_scope
+    }
+
+    /// Whether the named tool opted into result caching with `#[tool(cacheable)]`.
+    pub fn is_cacheable(&self, name: &str) -> bool {
+        self.tools
+            .read()
+            .is_ok_and(|r_tools| r_tools.get(name).is_some_and(|tool| tool.cacheable()))
+    }
+
+    /// Schemas of the tools offered to the model, leaving out `#[tool(hidden)]` tools.
     pub fn get_schemas(&self) -> Result<Vec<Tool>, LlmError> {
+        self.schemas_where(|tool| !tool.hidden())
+    }
+
+    /// Schemas of all registered tools, including hidden ones.
+    pub fn get_all_schemas(&self) -> Result<Vec<Tool>, LlmError> {
+        self.schemas_where(|_| true)
+    }
+
+    fn schemas_where(
+        &self,
+        include: impl Fn(&dyn ToolFunction<Ctx>) -> bool,
+    ) -> Result<Vec<Tool>, LlmError> {
         let r_tools = self
             .tools
             .read()
             .map_err(|_| LlmError::ToolRegistryAccess {
                 message: "Failed to acquire read lock (lock poisoned)".to_string(),
             })?;
-        let schema = r_tools.values().map(|tool| tool.schema()).collect();
+        let schema = r_tools
+            .values()
+            .filter(|tool| include(tool.as_ref()))
+            .map(|tool| tool.schema())
+            .collect();
         Ok(schema)
     }
 
+    /// Check that `tool_call` names a registered tool and that its arguments match the tool's
+    /// parameter schema, without executing it, e.g. before asking a user to approve the call or
+    /// to check the calls of a replayed trace. The arguments are checked as
+    /// the tool would receive them, after the registry's
+    /// [coercion](Self::with_argument_coercion) and removal of
+    /// [unknown arguments](Self::with_unknown_arguments_ignored), and the coercion of tools
+    /// that [coerce their own arguments](ToolFunction::coerces_arguments).
+    ///
+    /// Returns [`LlmError::ToolNotFound`] for unknown tools and [`LlmError::ToolExecution`]
+    /// describing the first mismatch for invalid arguments.
+    pub fn validate_call(&self, tool_call: &ToolCall) -> Result<(), LlmError> {
+        let tool = self.tool(&tool_call.name)?;
+        let arguments = self.prepare_arguments(tool.as_ref(), tool_call.arguments.clone(), true);
+        validate_value(&tool.schema().parameters, &arguments).map_err(|mismatch| {
+            LlmError::ToolExecution {
+                message: format!("Invalid arguments for {}: {mismatch}", tool_call.name),
+                source: None,
+            }
+        })
+    }
+
+    fn tool(&self, name: &str) -> Result<Arc<dyn ToolFunction<Ctx>>, LlmError> {
+        let r_tools = self
+            .tools
+            .read()
+            .map_err(|_| LlmError::ToolRegistryAccess {
+                message: "Failed to acquire read lock (lock poisoned)".to_string(),
+            })?;
+        r_tools
+            .get(name)
+            .cloned()
+            .ok_or_else(|| LlmError::ToolNotFound(name.to_string()))
+    }
+
+    /// The arguments passed to `tool`, after the registry's repairs. A `dry_run` also applies
+    /// the coercion the tool does itself and doesn't warn about unknown arguments.
+    fn prepare_arguments(
+        &self,
+        tool: &dyn ToolFunction<Ctx>,
+        mut arguments: Value,
+        dry_run: bool,
+    ) -> Value {
+        if self.ignore_unknown_arguments || tool.ignore_unknown_arguments() {
+            let unknown = remove_unknown_arguments(&tool.schema().parameters, &mut arguments);
+            if !unknown.is_empty() && !dry_run {
+                warn!(?unknown, "Ignoring unknown tool arguments");
+            }
+        }
+        if self.coerce_arguments || (dry_run && tool.coerces_arguments()) {
+            arguments = coerce_arguments(&tool.schema().parameters, arguments);
+        }
+        arguments
+    }
+
     #[tracing::instrument(
         name = "execute_tool",
         skip(self, tool_call),
@@ -298,22 +967,48 @@ impl<Ctx: Send + Sync + 'static> ToolRegistry<Ctx> {
     pub async fn execute(&self, tool_call: &ToolCall) -> Result<serde_json::Value, LlmError> {
         tracing::trace!(arguments = ?tool_call.arguments, "Executing tool with arguments");
 
-        let tool = {
-            let r_tools = self
-                .tools
-                .read()
-                .map_err(|_| LlmError::ToolRegistryAccess {
-                    message: "Failed to acquire read lock (lock poisoned)".to_string(),
-                })?;
-            r_tools.get(&tool_call.name).cloned()
-        };
+        let tool = self.tool(&tool_call.name).inspect_err(|_| {
+            // Calls of tools the model made up count as failed calls
+            tool_stats::record(
+                &self.stats,
+                &tool_call.name,
+                SystemTime::now(),
+                Duration::ZERO,
+                true,
+            );
+        })?;
 
-        let result = if let Some(tool) = tool {
-            tool.execute(&self.context, tool_call.arguments.clone())
-                .await
-        } else {
-            Err(LlmError::ToolNotFound(tool_call.name.clone()))
-        };
+        if let Some(notice) = tool.deprecated() {
+            warn!(notice, "Executing deprecated tool");
+        }
+
+        let arguments = self.prepare_arguments(tool.as_ref(), tool_call.arguments.clone(), false);
+        let started_at = SystemTime::now();
+        let started = Instant::now();
+        let result = tool
+            .execute(&self.context, arguments)
+            .await
+            .and_then(|output| {
+                if let Some(schema) = tool.output_schema() {
+                    validate_value(&schema, &output).map_err(|mismatch| {
+                        LlmError::ToolExecution {
+                            message: format!(
+                                "Output of {} does not match its declared schema: {mismatch}",
+                                tool_call.name
+                            ),
+                            source: None,
+                        }
+                    })?;
+                }
+                Ok(output)
+            });
+        tool_stats::record(
+            &self.stats,
+            &tool_call.name,
+            started_at,
+            started.elapsed(),
+            result.is_err(),
+        );
 
         if result.is_ok() {
             tracing::debug!("Tool execution completed successfully");
@@ -323,6 +1018,20 @@ impl<Ctx: Send + Sync + 'static> ToolRegistry<Ctx> {
     }
 }
 
+impl<Ctx> Clone for ToolRegistry<Ctx> {
+    /// Shares the tools, the context and the stats with the original.
+    fn clone(&self) -> Self {
+        Self {
+            tools: Arc::clone(&self.tools),
+            context: Arc::clone(&self.context),
+            coerce_arguments: self.coerce_arguments,
+            ignore_unknown_arguments: self.ignore_unknown_arguments,
+            stats: Arc::clone(&self.stats),
+            cache_scope: self.cache_scope,
+        }
+    }
+}
+
 impl Default for ToolRegistry<()> {
     fn default() -> Self {
         Self::new()
@@ -335,10 +1044,86 @@ pub struct ToolSet<Ctx = ()> {
     pub registry: ToolRegistry<Ctx>,
 }
 
+/// Encode a tool name for providers that only accept `[a-zA-Z0-9_-]` in function names.
+pub(crate) fn wire_tool_name(name: &str) -> String {
+    name.replace(TOOL_NAMESPACE_SEPARATOR, "__")
+}
+
 impl<Ctx: Send + Sync + 'static> ToolSet<Ctx> {
     pub fn tools(&self) -> Result<Vec<Tool>, LlmError> {
         self.registry.get_schemas()
     }
+
+    /// Move every tool of `other` into this toolset, keeping this toolset's context.
+    ///
+    /// Returns [`LlmError::ToolRegistration`] if both toolsets contain a tool with the same
+    /// name; use [`ToolSet::namespaced`] to keep them apart.
+    pub fn merge(self, other: ToolSet<Ctx>) -> Result<Self, LlmError> {
+        for tool in other.registry.tool_functions()? {
+            self.registry.register(tool)?;
+        }
+        Ok(self)
+    }
+
+    /// A toolset with the same tools that passes `context` to them instead.
+    /// See [`ToolRegistry::scoped`].
+    pub fn scoped(&self, context: Ctx) -> Self {
+        ToolSet {
+            registry: self.registry.scoped(context),
+        }
+    }
+
+    /// Coerce tool arguments to their schema types before executing them.
+    /// See [`ToolRegistry::with_argument_coercion`].
+    pub fn with_argument_coercion(self, enabled: bool) -> Self {
+        ToolSet {
+            registry: self.registry.with_argument_coercion(enabled),
+        }
+    }
+
+    /// Drop undeclared tool arguments with a warning.
+    /// See [`ToolRegistry::with_unknown_arguments_ignored`].
+    pub fn with_unknown_arguments_ignored(self, enabled: bool) -> Self {
+        ToolSet {
+            registry: self.registry.with_unknown_arguments_ignored(enabled),
+        }
+    }
+
+    /// Keep only the tools whose schema matches `predicate`.
+    pub fn filter(self, predicate: impl Fn(&Tool) -> bool) -> Result<Self, LlmError> {
+        Ok(ToolSet {
+            registry: self.registry.filter(predicate)?,
+        })
+    }
+
+    /// A toolset with the same tools, context and stats that can be merged with other tools
+    /// without changing this one. See [`ToolRegistry::duplicate`].
+    pub(crate) fn duplicate(&self) -> Result<Self, LlmError> {
+        Ok(ToolSet {
+            registry: self.registry.duplicate()?,
+        })
+    }
+
+    /// Prefix every tool name with `{namespace}.`.
+    pub fn namespaced(self, namespace: &str) -> Result<Self, LlmError> {
+        self.map_tools(|tool| Arc::new(NamespacedTool::new(namespace, tool)))
+    }
+
+    /// Replace every tool with `wrap(tool)`, keeping the context and the stats.
+    pub(crate) fn map_tools(
+        self,
+        wrap: impl Fn(Arc<dyn ToolFunction<Ctx>>) -> Arc<dyn ToolFunction<Ctx>>,
+    ) -> Result<Self, LlmError> {
+        let registry = self.registry.derive(
+            ToolMap::default(),
+            self.registry.context.clone(),
+            self.registry.cache_scope,
+        );
+        for tool in self.registry.tool_functions()? {
+            registry.register(wrap(tool))?;
+        }
+        Ok(ToolSet { registry })
+    }
 }
 
 /// Builder for creating a ToolSet with context.
@@ -361,9 +1146,48 @@ impl<Ctx: Send + Sync + 'static> ToolSetBuilder<Ctx> {
         self
     }
 
+    /// Prefix every tool added so far with `{namespace}.`.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.tools = self
+            .tools
+            .into_iter()
+            .map(|tool| {
+                Arc::new(NamespacedTool::new(namespace, tool)) as Arc<dyn ToolFunction<Ctx>>
+            })
+            .collect();
+        self
+    }
+
+    /// Append the tools of `other`.
+    ///
+    /// Returns [`LlmError::ToolRegistration`] if a tool name appears in both builders.
+    pub fn merge(mut self, other: ToolSetBuilder<Ctx>) -> Result<Self, LlmError> {
+        let existing: Vec<String> = self.tools.iter().map(|tool| tool.schema().name).collect();
+        if let Some(duplicate) = other
+            .tools
+            .iter()
+            .map(|tool| tool.schema().name)
+            .find(|name| existing.contains(name))
+        {
+            return Err(LlmError::ToolRegistration {
+                message: format!("Tool {duplicate} already registered"),
+                tool_name: duplicate,
+            });
+        }
+
+        self.tools.extend(other.tools);
+        Ok(self)
+    }
+
     /// Finalize the toolset with the given context.
     pub fn with_context(self, context: Ctx) -> ToolSet<Ctx> {
-        let registry = ToolRegistry::with_context(context);
+        self.with_shared_context(Arc::new(context))
+    }
+
+    /// Finalize the toolset with a context the caller keeps a handle to, so state that
+    /// tools accumulate can be read after the request.
+    pub fn with_shared_context(self, context: Arc<Ctx>) -> ToolSet<Ctx> {
+        let registry = ToolRegistry::with_shared_context(context);
         for tool in self.tools {
             registry
                 .register(tool)
@@ -387,6 +1211,63 @@ struct ValueWrapper<T> {
     value: T,
 }
 
+/// Parse structured output, undoing the strict-mode rewrites applied to the schema
+/// (tagged enum flattening, maps as entry arrays, non-object roots wrapped in `value`).
+fn parse_structured_output<T>(text: &str) -> Result<T, LlmError>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    let parse_error = |e: serde_json::Error| LlmError::Parse {
+        message: "Failed to parse structured output".to_string(),
+        source: Box::new(e),
+    };
+
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => structured_value(value).map_err(parse_error),
+        // Prose or a code fence around the JSON: use the first embedded value that is a `T`
+        Err(e) => super::lenient::embedded_values(text)
+            .into_iter()
+            .find_map(|value| structured_value(value).ok())
+            .ok_or_else(|| parse_error(e)),
+    }
+}
+
+fn structured_value<T>(mut value: Value) -> Result<T, serde_json::Error>
+where
+    T: DeserializeOwned + JsonSchema,
+{
+    let Ok(schema) = serde_json::to_value(schemars::schema_for!(T)) else {
+        return serde_json::from_value(value);
+    };
+
+    let wrapped = strict_schema(schema.clone())
+        .get("type")
+        .is_some_and(|ty| ty != "object");
+    if wrapped && let Some(inner) = value.get_mut("value") {
+        value = inner.take();
+    }
+
+    serde_json::from_value(restore_value(&schema, value))
+}
+
+/// Put a value of `T` into the shape requested by its strict schema, the inverse of
+/// [`parse_structured_output`].
+fn structured_example<T: JsonSchema>(value: Value) -> Value {
+    let Ok(schema) = serde_json::to_value(schemars::schema_for!(T)) else {
+        return value;
+    };
+
+    let wrapped = strict_schema(schema.clone())
+        .get("type")
+        .is_some_and(|ty| ty != "object");
+    let value = strict_value(&schema, value);
+    if wrapped {
+        serde_json::json!({ "value": value })
+    } else {
+        value
+    }
+}
+
 impl<T> CompletionTarget for T
 where
     T: DeserializeOwned + JsonSchema + Send,
@@ -397,7 +1278,12 @@ where
         responses::create_format_for_type::<T>()
     }
 
+    fn format_example(output: Value) -> String {
+        structured_example::<T>(output).to_string()
+    }
+
     fn parse_response(res: ProviderResponse) -> Result<Self::Output, LlmError> {
+        let metadata = res.metadata();
         match res.content {
             ResponseContent::Text(text) => {
                 // Try to parse as wrapped value first, then fall back to direct parsing
@@ -405,20 +1291,13 @@ where
                     if let Ok(wrapped) = serde_json::from_str::<ValueWrapper<T>>(&text) {
                         wrapped.value
                     } else {
-                        serde_json::from_str(&text).map_err(|e| LlmError::Parse {
-                            message: "Failed to parse structured output".to_string(),
-                            source: Box::new(e),
-                        })?
+                        parse_structured_output(&text)?
                     };
 
                 Ok(StructuredResponse {
                     content: parsed_content,
                     usage: res.usage,
-                    metadata: ResponseMetadata {
-                        provider: res.provider,
-                        model: res.model,
-                        id: res.id,
-                    },
+                    metadata,
                 })
             }
             ResponseContent::FunctionCalls(_) => Err(LlmError::Provider {
@@ -426,6 +1305,10 @@ where
                     .to_string(),
                 source: None,
             }),
+            ResponseContent::Binary { .. } => Err(LlmError::Provider {
+                message: "Binary response received when expecting structured output".to_string(),
+                source: None,
+            }),
             ResponseContent::Refusal(refusal) => Err(LlmError::Api {
                 message: format!("Model refused: {}", refusal),
                 status_code: None,
@@ -442,21 +1325,29 @@ impl CompletionTarget for TextResponse {
         Ok(responses::create_text_format())
     }
 
+    fn format_example(output: Value) -> String {
+        match output {
+            Value::String(text) => text,
+            other => other.to_string(),
+        }
+    }
+
     fn parse_response(res: ProviderResponse) -> Result<Self::Output, LlmError> {
+        let metadata = res.metadata();
         match res.content {
             ResponseContent::Text(text) => Ok(TextResponse {
                 text,
                 usage: res.usage,
-                metadata: ResponseMetadata {
-                    provider: res.provider,
-                    model: res.model,
-                    id: res.id,
-                },
+                metadata,
             }),
             ResponseContent::FunctionCalls(_) => Err(LlmError::Provider {
                 message: "Function call response received when expecting text output".to_string(),
                 source: None,
             }),
+            ResponseContent::Binary { .. } => Err(LlmError::Provider {
+                message: "Binary response received when expecting text output".to_string(),
+                source: None,
+            }),
             ResponseContent::Refusal(refusal) => Err(LlmError::Api {
                 message: format!("Model refused: {}", refusal),
                 status_code: None,
@@ -469,7 +1360,7 @@ impl CompletionTarget for TextResponse {
         // TextResponse supports tools - the tool calling loop processes function calls
         // and the model eventually returns text after tools are executed.
         // Provider-specific constraints (e.g., Gemini can't combine tools with structured
-        // JSON output) are validated at the provider level.
+        // JSON output in one request) are handled at the provider level.
         true
     }
 }
@@ -510,6 +1401,106 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cache_hit_rate() {
+        let usage = LanguageModelUsage {
+            prompt_tokens: 1000,
+            completion_tokens: 50,
+            total_tokens: 1050,
+            cached_tokens: Some(250),
+        };
+        assert_eq!(usage.cache_hit_rate(), Some(0.25));
+
+        let unreported = LanguageModelUsage {
+            cached_tokens: None,
+            ..usage.clone()
+        };
+        assert_eq!(unreported.cache_hit_rate(), None);
+
+        let empty_prompt = LanguageModelUsage {
+            prompt_tokens: 0,
+            ..usage
+        };
+        assert_eq!(empty_prompt.cache_hit_rate(), None);
+    }
+
+    fn sample(answer: &str, completion_tokens: i32) -> StructuredResponse<String> {
+        StructuredResponse {
+            content: answer.to_string(),
+            usage: LanguageModelUsage {
+                prompt_tokens: 10,
+                completion_tokens,
+                total_tokens: 10 + completion_tokens,
+                cached_tokens: None,
+            },
+            metadata: ResponseMetadata::new(
+                Provider::OpenAI,
+                "mock-model",
+                format!("resp_{completion_tokens}"),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_consensus_picks_majority_answer() {
+        let consensus = Consensus::from_samples(vec![
+            sample("391", 1),
+            sample("401", 2),
+            sample("391", 3),
+            sample("381", 4),
+        ])
+        .unwrap();
+
+        assert_eq!(consensus.answer.content, "391");
+        assert_eq!(consensus.answer.metadata.id, "resp_1");
+        assert_eq!(consensus.votes, 2);
+        assert_eq!(consensus.samples, 4);
+        assert_eq!(consensus.distinct_answers, 3);
+        assert_eq!(consensus.agreement(), 0.5);
+        assert_eq!(consensus.usage.prompt_tokens, 40);
+        assert_eq!(consensus.usage.completion_tokens, 10);
+        assert_eq!(consensus.usage.cached_tokens, None);
+    }
+
+    #[test]
+    fn test_consensus_tie_goes_to_first_answer() {
+        let consensus = Consensus::from_samples(vec![
+            sample("a", 1),
+            sample("b", 2),
+            sample("b", 3),
+            sample("a", 4),
+        ])
+        .unwrap();
+        assert_eq!(consensus.answer.content, "a");
+
+        assert!(Consensus::<String>::from_samples(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_classification_ranks_alternatives_by_votes() {
+        let classification = Classification::from_samples(vec![
+            sample("spam", 1),
+            sample("ham", 2),
+            sample("phishing", 3),
+            sample("ham", 4),
+        ])
+        .unwrap();
+
+        assert_eq!(classification.label, "ham");
+        assert_eq!(classification.confidence, Some(0.5));
+        assert_eq!(classification.metadata.id, "resp_2");
+        assert_eq!(
+            classification.alternatives,
+            vec![("spam".to_string(), 0.25), ("phishing".to_string(), 0.25)]
+        );
+        assert_eq!(classification.usage.completion_tokens, 10);
+        assert!(Classification::<String>::from_samples(Vec::new()).is_none());
+
+        let single = Classification::from_samples(vec![sample("spam", 1)]).unwrap();
+        assert_eq!(single.label, "spam");
+        assert_eq!(single.confidence, None);
+    }
+
     #[tokio::test]
     async fn test_tool_registry_preservers_object_types() {
         let registry = ToolRegistry::new();
@@ -532,4 +1523,121 @@ mod tests {
         assert_eq!(result["value"], 42);
         assert_eq!(result["active"], true);
     }
+
+    #[test]
+    fn test_namespaced_tool_names_resolve_from_wire() {
+        let registry = ToolRegistry::new();
+        registry
+            .register(Arc::new(NamespacedTool::new("data", Arc::new(ObjectTool))))
+            .expect("Failed to register namespaced tool");
+
+        assert_eq!(wire_tool_name("data.object_tool"), "data__object_tool");
+        assert_eq!(
+            registry.resolve_name("data__object_tool"),
+            "data.object_tool"
+        );
+        assert_eq!(
+            registry.resolve_name("data.object_tool"),
+            "data.object_tool"
+        );
+        assert_eq!(registry.resolve_name("unknown"), "unknown");
+    }
+
+    #[test]
+    fn test_parse_response_rebuilds_root_map() {
+        let response = ProviderResponse::new(
+            "resp_1",
+            "mock-model",
+            Provider::OpenAI,
+            ResponseContent::Text(
+                r#"{"value":[{"key":"en","value":2},{"key":"de","value":1}]}"#.to_string(),
+            ),
+            LanguageModelUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                cached_tokens: None,
+            },
+        );
+
+        let parsed = <HashMap<String, u32> as CompletionTarget>::parse_response(response).unwrap();
+        assert_eq!(
+            parsed.content,
+            HashMap::from([("en".to_string(), 2), ("de".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_parse_response_extracts_json_from_prose() {
+        let text = "The format is {\"en\": \"count\"}. Here are the counts:\n\
+                    ```json\n{\"value\":[{\"key\":\"en\",\"value\":2}]}\n```";
+        let response = ProviderResponse::new(
+            "resp_1",
+            "mock-model",
+            Provider::OpenAI,
+            ResponseContent::Text(text.to_string()),
+            LanguageModelUsage {
+                prompt_tokens: 1,
+                completion_tokens: 1,
+                total_tokens: 2,
+                cached_tokens: None,
+            },
+        );
+
+        let parsed = <HashMap<String, u32> as CompletionTarget>::parse_response(response).unwrap();
+        assert_eq!(parsed.content, HashMap::from([("en".to_string(), 2)]));
+    }
+
+    #[test]
+    fn test_text_response_round_trips_through_json() {
+        let response = TextResponse {
+            text: "Hello".to_string(),
+            usage: LanguageModelUsage {
+                prompt_tokens: 3,
+                completion_tokens: 1,
+                total_tokens: 4,
+                cached_tokens: None,
+            },
+            metadata: ResponseMetadata::new(Provider::OpenRouter, "mock-model", "resp_1"),
+        };
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "text": "Hello",
+                "usage": { "prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4 },
+                "metadata": { "provider": "openrouter", "model": "mock-model", "id": "resp_1" }
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<TextResponse>(value).unwrap(),
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_shares_stats_but_not_later_tools() {
+        let registry = ToolRegistry::new();
+        registry
+            .register(Arc::new(ObjectTool))
+            .expect("Failed to register object_tool");
+
+        let duplicate = registry.duplicate().unwrap();
+        duplicate
+            .register(Arc::new(NamespacedTool::new("data", Arc::new(ObjectTool))))
+            .expect("Failed to register namespaced tool");
+
+        let tool_call = ToolCall {
+            id: "test_Id".to_string(),
+            call_id: "call_123".to_string(),
+            name: "object_tool".to_string(),
+            arguments: serde_json::json!({}),
+        };
+        duplicate.execute(&tool_call).await.unwrap();
+
+        assert_eq!(registry.get_schemas().unwrap().len(), 1);
+        assert_eq!(duplicate.get_schemas().unwrap().len(), 2);
+        assert_eq!(registry.stats()["object_tool"].calls, 1);
+    }
 }
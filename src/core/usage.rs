@@ -0,0 +1,367 @@
+//! Usage and cost accounting.
+//!
+//! Register a [`UsageReporter`] once with [`set_reporter`] and it receives a [`UsageReport`]
+//! after every completed request, tagged with the builder's
+//! [`usage_tag`](crate::LlmBuilder::usage_tag). Costs are computed from the prices registered
+//! with [`set_price`]. [`UsageTotals`] is a reporter that sums everything up per tag.
+//!
+//! # Example
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use rsai::usage::{self, Pricing, UsageTotals};
+//! use rsai::{ApiKey, ChatRole, Message, Provider, TextResponse, llm};
+//!
+//! # async fn example() -> Result<(), rsai::LlmError> {
+//! usage::set_price("gpt-4o-mini", Pricing::per_million_tokens(0.15, 0.60));
+//! let totals = Arc::new(UsageTotals::new());
+//! usage::set_reporter(totals.clone());
+//!
+//! llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model("gpt-4o-mini")
+//!     .messages(vec![Message::new(ChatRole::User, "Summarize my cart")])
+//!     .usage_tag("feature=checkout")
+//!     .complete::<TextResponse>()
+//!     .await?;
+//!
+//! if let Some(checkout) = totals.get(Some("feature=checkout")) {
+//!     println!("{} calls, ${:.4}", checkout.calls, checkout.cost);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use crate::provider::Provider;
+
+use super::collector::{self, Collected};
+use super::types::LanguageModelUsage;
+
+static REPORTER: RwLock<Option<Arc<dyn UsageReporter>>> = RwLock::new(None);
+static PRICES: RwLock<BTreeMap<String, Pricing>> = RwLock::new(BTreeMap::new());
+
+/// Usage of one completed request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageReport {
+    /// Tag set with [`usage_tag`](crate::LlmBuilder::usage_tag), if any.
+    pub tag: Option<String>,
+    pub provider: Provider,
+    /// Model that served the request, as reported by the provider.
+    pub model: String,
+    pub usage: LanguageModelUsage,
+    /// Cost in USD, `None` if no price is registered for the model.
+    pub cost: Option<f64>,
+    /// Time from sending the request to receiving the complete response, including tool calls.
+    pub latency: Duration,
+}
+
+/// Receives a [`UsageReport`] after every completed request.
+///
+/// Called on the task that made the request, so implementations should return quickly.
+pub trait UsageReporter: Send + Sync {
+    fn report(&self, report: &UsageReport);
+}
+
+impl<F> UsageReporter for F
+where
+    F: Fn(&UsageReport) + Send + Sync,
+{
+    fn report(&self, report: &UsageReport) {
+        self(report)
+    }
+}
+
+/// Send usage reports of all requests to `reporter`, replacing the previous reporter.
+pub fn set_reporter(reporter: Arc<dyn UsageReporter>) {
+    if let Ok(mut current) = REPORTER.write() {
+        *current = Some(reporter);
+    }
+}
+
+/// Stop reporting usage.
+pub fn clear_reporter() {
+    if let Ok(mut current) = REPORTER.write() {
+        *current = None;
+    }
+}
+
+/// Prices of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+    /// Price of prompt tokens served from cache, `input` if unset.
+    pub cached_input: Option<f64>,
+}
+
+impl Pricing {
+    pub const fn per_million_tokens(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            cached_input: None,
+        }
+    }
+
+    pub const fn with_cached_input(mut self, cached_input: f64) -> Self {
+        self.cached_input = Some(cached_input);
+        self
+    }
+
+    /// Cost of `usage` in USD.
+    pub fn cost(&self, usage: &LanguageModelUsage) -> f64 {
+        let cached = usage
+            .cached_tokens
+            .unwrap_or(0)
+            .clamp(0, usage.prompt_tokens);
+        let uncached = usage.prompt_tokens - cached;
+        (uncached as f64 * self.input
+            + cached as f64 * self.cached_input.unwrap_or(self.input)
+            + usage.completion_tokens as f64 * self.output)
+            / 1_000_000.0
+    }
+}
+
+/// Register the price of `model`, used for the cost in usage reports.
+///
+/// Providers often report a dated model version (`gpt-4o-mini-2024-07-18`); a price registered
+/// for `gpt-4o-mini` applies to those as well. The longest matching prefix wins.
+pub fn set_price(model: impl Into<String>, pricing: Pricing) {
+    if let Ok(mut prices) = PRICES.write() {
+        prices.insert(model.into(), pricing);
+    }
+}
+
+/// Price registered for `model` or the longest registered prefix of it.
+pub fn price(model: &str) -> Option<Pricing> {
+    let prices = PRICES.read().ok()?;
+    prices
+        .iter()
+        .filter(|(id, _)| model.starts_with(id.as_str()))
+        .max_by_key(|(id, _)| id.len())
+        .map(|(_, pricing)| *pricing)
+}
+
+/// Report usage to the registered reporter, if any, and to the requests being collected.
+pub(crate) fn report(
+    tag: Option<&str>,
+    provider: Provider,
+    model: &str,
+    usage: &LanguageModelUsage,
+    latency: Duration,
+) {
+    let reporter = REPORTER.read().ok().and_then(|current| current.clone());
+    if reporter.is_none() && !collector::collecting(&COLLECTED) {
+        return;
+    }
+    let report = UsageReport {
+        tag: tag.map(str::to_string),
+        provider,
+        model: model.to_string(),
+        usage: usage.clone(),
+        cost: price(model).map(|pricing| pricing.cost(usage)),
+        latency,
+    };
+    if let Some(reporter) = reporter {
+        reporter.report(&report);
+    }
+    collector::add(&COLLECTED, |collected| collected.push(report));
+}
+
+tokio::task_local! {
+    static COLLECTED: Collected<Vec<UsageReport>>;
+}
+
+/// Run `future` and return its output with the usage of the requests it made.
+pub(crate) fn collect<F: Future>(future: F) -> impl Future<Output = (F::Output, Vec<UsageReport>)> {
+    collector::collect(&COLLECTED, future)
+}
+
+/// Sums of the usage reports for one tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagTotals {
+    pub calls: u64,
+    pub usage: LanguageModelUsage,
+    /// Cost in USD of the calls whose model has a price.
+    pub cost: f64,
+    /// Number of calls without a price, not included in `cost`.
+    pub unpriced_calls: u64,
+    pub latency: Duration,
+}
+
+impl TagTotals {
+    /// Average latency per call.
+    pub fn mean_latency(&self) -> Duration {
+        self.latency / self.calls.max(1) as u32
+    }
+}
+
+/// A [`UsageReporter`] that aggregates reports per tag.
+#[derive(Debug, Default)]
+pub struct UsageTotals {
+    totals: Mutex<BTreeMap<Option<String>, TagTotals>>,
+}
+
+impl UsageTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Totals for `tag`, `None` for untagged calls.
+    pub fn get(&self, tag: Option<&str>) -> Option<TagTotals> {
+        let totals = self.totals.lock().ok()?;
+        totals.get(&tag.map(str::to_string)).cloned()
+    }
+
+    /// Totals of every tag seen so far, untagged calls under `None`.
+    pub fn all(&self) -> BTreeMap<Option<String>, TagTotals> {
+        self.totals
+            .lock()
+            .map(|totals| totals.clone())
+            .unwrap_or_default()
+    }
+
+    /// Forget all totals.
+    pub fn reset(&self) {
+        if let Ok(mut totals) = self.totals.lock() {
+            totals.clear();
+        }
+    }
+}
+
+impl UsageReporter for UsageTotals {
+    fn report(&self, report: &UsageReport) {
+        let Ok(mut totals) = self.totals.lock() else {
+            return;
+        };
+        let entry = totals
+            .entry(report.tag.clone())
+            .or_insert_with(|| TagTotals {
+                calls: 0,
+                usage: LanguageModelUsage {
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    total_tokens: 0,
+                    cached_tokens: None,
+                },
+                cost: 0.0,
+                unpriced_calls: 0,
+                latency: Duration::ZERO,
+            });
+        entry.calls += 1;
+        entry.usage = entry.usage.combined(&report.usage);
+        match report.cost {
+            Some(cost) => entry.cost += cost,
+            None => entry.unpriced_calls += 1,
+        }
+        entry.latency += report.latency;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{ScriptedTransport, responses_answer};
+    use crate::{ApiKey, ChatRole, Message, TextResponse, llm};
+    use serde_json::json;
+
+    fn usage(prompt_tokens: i32, cached_tokens: Option<i32>) -> LanguageModelUsage {
+        LanguageModelUsage {
+            prompt_tokens,
+            completion_tokens: 100,
+            total_tokens: prompt_tokens + 100,
+            cached_tokens,
+        }
+    }
+
+    #[test]
+    fn test_pricing_charges_cached_tokens_at_cached_price() {
+        let pricing = Pricing::per_million_tokens(2.0, 8.0).with_cached_input(0.5);
+        let cost = pricing.cost(&usage(1_000, Some(400)));
+        assert!((cost - (600.0 * 2.0 + 400.0 * 0.5 + 100.0 * 8.0) / 1e6).abs() < 1e-12);
+
+        set_price("unit-test-model", Pricing::per_million_tokens(1.0, 1.0));
+        set_price(
+            "unit-test-model-large",
+            Pricing::per_million_tokens(5.0, 5.0),
+        );
+        assert_eq!(price("unit-test-model-2024-01-01").unwrap().input, 1.0);
+        assert_eq!(price("unit-test-model-large-preview").unwrap().input, 5.0);
+        assert_eq!(price("other-model"), None);
+    }
+
+    #[test]
+    fn test_usage_totals_aggregate_per_tag() {
+        let totals = UsageTotals::new();
+        let report = |tag: Option<&str>, cost| UsageReport {
+            tag: tag.map(str::to_string),
+            provider: Provider::OpenAI,
+            model: "mock-model".to_string(),
+            usage: usage(10, None),
+            cost,
+            latency: Duration::from_millis(100),
+        };
+        totals.report(&report(Some("checkout"), Some(0.25)));
+        totals.report(&report(Some("checkout"), None));
+        totals.report(&report(None, Some(1.0)));
+
+        let checkout = totals.get(Some("checkout")).unwrap();
+        assert_eq!(checkout.calls, 2);
+        assert_eq!(checkout.usage.total_tokens, 220);
+        assert_eq!(checkout.cost, 0.25);
+        assert_eq!(checkout.unpriced_calls, 1);
+        assert_eq!(checkout.mean_latency(), Duration::from_millis(100));
+        assert_eq!(totals.get(None).unwrap().calls, 1);
+        assert_eq!(totals.all().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_reported_with_tag_and_cost() {
+        set_price(
+            "usage-test-model",
+            Pricing::per_million_tokens(1.0, 2.0).with_cached_input(0.5),
+        );
+        let totals = Arc::new(UsageTotals::new());
+        set_reporter(totals.clone());
+
+        let mut answer = responses_answer("Done");
+        answer["model"] = "usage-test-model-2025-01-01".into();
+        answer["usage"] = json!({
+            "input_tokens": 1000,
+            "input_tokens_details": { "cached_tokens": 200 },
+            "output_tokens": 500,
+            "total_tokens": 1500
+        });
+        let transport = ScriptedTransport::answering(answer);
+        for _ in 0..2 {
+            llm::with(Provider::OpenAI)
+                .api_key(ApiKey::Custom("test-key".to_string()))
+                .unwrap()
+                .model("usage-test-model")
+                .messages(vec![Message::new(ChatRole::User, "Summarize my cart")])
+                .usage_tag("feature=usage-test")
+                .transport(transport.clone())
+                .complete::<TextResponse>()
+                .await
+                .unwrap();
+        }
+
+        // Other tests run concurrently and may report untagged usage
+        let checkout = totals
+            .get(Some("feature=usage-test"))
+            .expect("tagged usage should be reported");
+        assert_eq!(checkout.calls, 2);
+        assert_eq!(checkout.usage.total_tokens, 3000);
+        assert_eq!(checkout.usage.cached_tokens, Some(400));
+        assert_eq!(checkout.unpriced_calls, 0);
+        let expected = 2.0 * (800.0 * 1.0 + 200.0 * 0.5 + 500.0 * 2.0) / 1_000_000.0;
+        assert!((checkout.cost - expected).abs() < 1e-12);
+    }
+}
@@ -23,10 +23,7 @@
 //! let analysis = llm::with(Provider::OpenAI)
 //!     .api_key(ApiKey::Default)?
 //!     .model("gpt-4o-mini")
-//!     .messages(vec![Message {
-//!         role: ChatRole::User,
-//!         content: "Analyze: 'This library is amazing!'".to_string(),
-//!     }])
+//!     .messages(vec![Message::new(ChatRole::User, "Analyze: 'This library is amazing!'")])
 //!     .complete::<Analysis>()
 //!     .await?;
 //!
@@ -34,14 +31,8 @@
 //!     .api_key(ApiKey::Default)?
 //!     .model("gpt-4o-mini")
 //!     .messages(vec![
-//!         Message {
-//!             role: ChatRole::System,
-//!             content: "You are friendly and concise.".to_string(),
-//!         },
-//!         Message {
-//!             role: ChatRole::User,
-//!             content: "Share a fun fact about Rust.".to_string(),
-//!         },
+//!         Message::new(ChatRole::System, "You are friendly and concise."),
+//!         Message::new(ChatRole::User, "Share a fun fact about Rust."),
 //!     ])
 //!     .complete::<TextResponse>()
 //!     .await?;
@@ -57,20 +48,37 @@ mod provider;
 mod responses;
 
 // Core types
+pub use core::coerce_arguments;
 pub use core::{ChatRole, ConversationMessage, Ctx, Message};
-pub use core::{Tool, ToolCall, ToolCallResult, ToolRegistry, ToolSet, ToolSetBuilder};
-pub use core::{ToolCallingConfig, ToolCallingGuard};
+pub use core::{
+    DuplicateCalls, PendingCheckpoint, ToolCache, ToolCallingConfig, ToolCallingGuard,
+    ToolLoopCheckpoint, ToolLoopEvent, ToolLoopHooks,
+};
+pub use core::{NamespacedTool, TOOL_NAMESPACE_SEPARATOR};
+pub use core::{Tool, ToolCall, ToolCallResult, ToolRegistry, ToolSet, ToolSetBuilder, ToolStats};
 
 // Configuration types
 pub use core::{
-    ApiKey, GenerationConfig, Inspector, InspectorConfig, LlmBuilder, ToolChoice, ToolConfig,
+    ApiKey, GenerationConfig, Inspector, InspectorConfig, LlmBuilder, LlmClient, StopCondition,
+    ToolChoice, ToolConfig,
 };
+pub use core::{Gateway, Hedge};
 pub use responses::{Format, HttpClientConfig};
+pub use tokio_util::sync::CancellationToken;
+
+// Transport types
+pub use core::{Cassette, CassetteMode, MatchOn};
+pub use core::{HttpMethod, ReqwestTransport, Transport, TransportRequest, TransportResponse};
 
 // Response types
+pub use core::StoredResponses;
+pub use core::{BinaryResponse, Blob};
 pub use core::{
-    LanguageModelUsage, ResponseMetadata, StructuredRequest, StructuredResponse, TextResponse,
+    Citation, Classification, Consensus, FunctionCallData, LanguageModelUsage, ProviderResponse,
+    ResponseContent, ResponseMetadata, StructuredRequest, StructuredResponse, TextResponse,
+    Timings, ToolTiming,
 };
+pub use core::{CodeBlock, Section};
 
 // Async helpers
 pub use core::BoxFuture;
@@ -82,14 +90,73 @@ pub type Result<T> = std::result::Result<T, LlmError>;
 // Gen AI request builders
 pub use core::llm;
 
+// Sub-agents and multi-agent orchestration
+pub use core::agents;
+pub use core::{AgentTool, agent_as_tool};
+
+// Ready-made prompts for summarization and translation
+pub use core::tasks;
+
+// A/B experiments between models and prompts
+pub use core::experiment;
+
+// Versioned prompt templates
+pub use core::prompts;
+
 // Gen AI providers
+#[cfg(feature = "vertex")]
+pub use provider::ServiceAccount;
+pub use provider::{BackgroundStatus, PendingResponse};
+pub use provider::{CachedContent, CachedContentUsage, CreateCachedContent, GeminiOptions};
+pub use provider::{Capabilities, schema_json};
+pub use provider::{CohereClient, CohereConfig, CohereDocument, CohereOptions};
+pub use provider::{CompatibleClient, CompatibleConfig};
 pub use provider::{
     GeminiClient, GeminiConfig, OpenAiClient, OpenAiConfig, OpenRouterClient, OpenRouterConfig,
     Provider,
 };
+pub use provider::{OpenRouterOptions, OpenRouterProviderPreferences};
+pub use provider::{VertexConfig, VertexCredentials};
+
+// Known model ids
+pub use provider::models;
+
+// Chat history and long-term memory
+pub use core::conversation;
+pub use core::memory;
+
+// Input and output validation
+pub use core::guardrails;
+pub use core::rag;
+pub use core::redaction;
+
+// HTTP retry strategies and expiring credentials
+pub use core::credentials;
+pub use core::retry;
+pub use core::transform;
+
+// Prompt regression testing and replays of recorded runs
+pub use core::replay;
+pub use core::testing;
+
+// Usage and cost accounting
+pub use core::usage;
+
+// Schema versions of persisted structured outputs
+pub use core::migration;
+
+// Builder presets from a config file
+#[cfg(feature = "profiles")]
+pub use core::profile::{self, LlmProfile, Profiles};
 
 // Traits
-pub use core::{CompletionTarget, LlmProvider, ToolFunction};
+pub use core::{CompletionTarget, LlmProvider, ToolFunction, ToolName};
+
+// Re-exported so structured outputs use the same versions rsai generates schemas for
+#[cfg(feature = "chrono")]
+pub use chrono;
+#[cfg(feature = "uuid")]
+pub use uuid;
 
 // Macros from `rsai-macros`
-pub use rsai_macros::{completion_schema, tool, toolset};
+pub use rsai_macros::{completion_schema, llm_test, tool, toolset};
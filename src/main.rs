@@ -0,0 +1,240 @@
+//! `rsai` command line tool for quick prompting and debugging schemas and tools.
+//!
+//! ```text
+//! rsai complete -p openai -m gpt-4o-mini "Why is the sky blue?"
+//! rsai complete -p gemini -m gemini-2.5-flash --dry-run "Why is the sky blue?"
+//! rsai schema -p gemini schema.json
+//! rsai schema -p openai schema.json --check schemas/openai.json
+//! rsai --output json tools list tools.json
+//! ```
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rsai::{ApiKey, ChatRole, LlmError, Message, Provider, TextResponse, llm};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Parser)]
+#[command(
+    name = "rsai",
+    version,
+    about = "Prompt LLMs and inspect what rsai sends them"
+)]
+struct Cli {
+    /// How results are printed
+    #[arg(short, long, global = true, value_enum, default_value_t = Output::Plain)]
+    output: Output,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Plain,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Send a prompt and print the text response
+    Complete {
+        /// openai, openrouter, gemini, xai, cohere, together
+        /// or fireworks
+        #[arg(short, long, env = "RSAI_PROVIDER", value_parser = parse_provider)]
+        provider: Provider,
+
+        #[arg(short, long, env = "RSAI_MODEL")]
+        model: String,
+
+        /// System prompt sent before the user prompt
+        #[arg(short, long)]
+        system: Option<String>,
+
+        #[arg(long)]
+        temperature: Option<f32>,
+
+        #[arg(long)]
+        max_tokens: Option<u32>,
+
+        /// Print the request body instead of sending it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Read from stdin when omitted
+        prompt: Option<String>,
+    },
+    /// Print the response schema rsai sends for a JSON Schema file
+    Schema {
+        /// JSON Schema with a `title`, e.g. the output of `schemars::schema_for!`
+        file: PathBuf,
+
+        /// openai, openrouter, gemini, xai, cohere, together
+        /// or fireworks
+        #[arg(short, long, default_value = "openai", value_parser = parse_provider)]
+        provider: Provider,
+
+        /// Fail if the schema differs from this previously exported schema
+        #[arg(long)]
+        check: Option<PathBuf>,
+    },
+    /// Inspect tool definitions
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ToolsCommand {
+    /// List the tools in a JSON file of `{ name, description, parameters }` objects
+    List { file: PathBuf },
+}
+
+#[derive(Serialize, Deserialize)]
+struct ToolDefinition {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    parameters: Value,
+}
+
+fn parse_provider(name: &str) -> Result<Provider, String> {
+    name.parse().map_err(|e: LlmError| e.to_string())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, LlmError> {
+    match cli.command {
+        Command::Complete {
+            provider,
+            model,
+            system,
+            temperature,
+            max_tokens,
+            dry_run,
+            prompt,
+        } => {
+            let prompt = match prompt {
+                Some(prompt) => prompt,
+                None => read_stdin()?,
+            };
+
+            let mut messages = Vec::new();
+            if let Some(system) = system {
+                messages.push(Message::new(ChatRole::System, system));
+            }
+            messages.push(Message::new(ChatRole::User, prompt));
+
+            let mut builder = llm::with(provider)
+                .api_key(ApiKey::Default)?
+                .model(model)
+                .messages(messages);
+            if let Some(temperature) = temperature {
+                builder = builder.temperature(temperature);
+            }
+            if let Some(max_tokens) = max_tokens {
+                builder = builder.max_tokens(max_tokens);
+            }
+            if dry_run {
+                print_json(&builder.dry_run::<TextResponse>().await?)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            let response = builder.complete::<TextResponse>().await?;
+
+            match cli.output {
+                Output::Plain => println!("{}", response.text),
+                Output::Json => print_json(&response)?,
+            }
+        }
+        Command::Schema {
+            file,
+            provider,
+            check,
+        } => {
+            let schema = provider.response_schema(read_json(&file)?)?;
+            print_json(&schema)?;
+            if let Some(expected) = check
+                && read_json(&expected)? != schema
+            {
+                eprintln!("error: schema differs from {}", expected.display());
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Tools {
+            command: ToolsCommand::List { file },
+        } => {
+            let tools: Vec<ToolDefinition> =
+                serde_json::from_value(read_json(&file)?).map_err(|e| LlmError::Parse {
+                    message: format!("{} is not a list of tool definitions", file.display()),
+                    source: Box::new(e),
+                })?;
+            for tool in &tools {
+                if tool.parameters.get("type").and_then(Value::as_str) != Some("object") {
+                    return Err(LlmError::ToolRegistration {
+                        tool_name: tool.name.clone(),
+                        message: "parameters must be a JSON schema of type object".to_string(),
+                    });
+                }
+            }
+
+            match cli.output {
+                Output::Plain => {
+                    for tool in &tools {
+                        match &tool.description {
+                            Some(description) => println!("{}\t{description}", tool.name),
+                            None => println!("{}", tool.name),
+                        }
+                    }
+                }
+                Output::Json => print_json(&tools)?,
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn read_stdin() -> Result<String, LlmError> {
+    let mut prompt = String::new();
+    std::io::stdin()
+        .read_to_string(&mut prompt)
+        .map_err(|e| LlmError::Parse {
+            message: "Failed to read the prompt from stdin".to_string(),
+            source: Box::new(e),
+        })?;
+    Ok(prompt)
+}
+
+fn read_json(path: &Path) -> Result<Value, LlmError> {
+    let content = std::fs::read_to_string(path).map_err(|e| LlmError::Parse {
+        message: format!("Failed to read {}", path.display()),
+        source: Box::new(e),
+    })?;
+    serde_json::from_str(&content).map_err(|e| LlmError::Parse {
+        message: format!("{} is not valid JSON", path.display()),
+        source: Box::new(e),
+    })
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), LlmError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| LlmError::Parse {
+        message: "Failed to serialize output".to_string(),
+        source: Box::new(e),
+    })?;
+    println!("{json}");
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use super::{Provider, models};
+
+/// What a provider's API supports, to branch on before building a request.
+///
+/// ```
+/// use rsai::Provider;
+///
+/// let capabilities = Provider::Gemini.capabilities_for("gemini-2.5-flash");
+/// assert!(!capabilities.supports_tools_with_structured_output);
+/// assert_eq!(capabilities.max_context, Some(1_048_576));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Function calling
+    pub supports_tools: bool,
+    /// Responses constrained to a JSON schema
+    pub supports_structured_output: bool,
+    /// Tools and a JSON schema in the same request. rsai still combines them for providers
+    /// without support by running the tool calls first and asking for the structured answer
+    /// in a separate request.
+    pub supports_tools_with_structured_output: bool,
+    /// Several tool calls in one model turn
+    pub supports_parallel_tool_calls: bool,
+    /// Image inputs
+    pub supports_vision: bool,
+    /// Continuing a trailing assistant message instead of starting a new one, to steer the
+    /// format of the answer
+    pub supports_assistant_prefill: bool,
+    /// Stop sequences that end generation on the server. rsai cuts the answer at them on the
+    /// client for providers without support.
+    pub supports_stop_sequences: bool,
+    /// Maximum number of input + output tokens, when the model is known
+    pub max_context: Option<u32>,
+    /// Maximum number of tokens the model generates per answer, when the model is known
+    pub max_output_tokens: Option<u32>,
+}
+
+impl Capabilities {
+    /// Replace the context window, e.g. for a model missing from [`models`].
+    pub fn with_max_context(mut self, tokens: u32) -> Self {
+        self.max_context = Some(tokens);
+        self
+    }
+
+    /// Replace the output limit, e.g. for a model missing from [`models`].
+    pub fn with_max_output_tokens(mut self, tokens: u32) -> Self {
+        self.max_output_tokens = Some(tokens);
+        self
+    }
+}
+
+impl Provider {
+    /// The capabilities of this provider's API. `max_context` and `max_output_tokens` are
+    /// unknown without a model, see [`capabilities_for`](Self::capabilities_for).
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            Provider::OpenAI
+            | Provider::OpenRouter
+            | Provider::XAI
+            | Provider::Together
+            | Provider::Fireworks => Capabilities {
+                supports_tools: true,
+                supports_structured_output: true,
+                supports_tools_with_structured_output: true,
+                supports_parallel_tool_calls: true,
+                supports_vision: true,
+                // Open models behind chat completions continue the assistant turn, OpenAI's
+                // Responses API and xAI start a new one
+                supports_assistant_prefill: matches!(
+                    self,
+                    Provider::Together | Provider::Fireworks
+                ),
+                // The Responses API has no `stop`, and xAI's reasoning models reject it
+                supports_stop_sequences: matches!(self, Provider::Together | Provider::Fireworks),
+                max_context: None,
+                max_output_tokens: None,
+            },
+            Provider::Gemini | Provider::Cohere => Capabilities {
+                supports_tools: true,
+                supports_structured_output: true,
+                supports_tools_with_structured_output: false,
+                supports_parallel_tool_calls: true,
+                supports_vision: true,
+                supports_assistant_prefill: false,
+                supports_stop_sequences: true,
+                max_context: None,
+                max_output_tokens: None,
+            },
+        }
+    }
+
+    /// The capabilities of this provider with the context window and output limit of `model`,
+    /// when it is one of the [known models](models).
+    ///
+    /// For Together and Fireworks, known models without function calling or JSON schema mode
+    /// have those capabilities turned off.
+    pub fn capabilities_for(&self, model: &str) -> Capabilities {
+        let capabilities = self.capabilities();
+        let (without_tools, without_json_mode) = models::limitations(*self);
+        let lacks = |known: &[models::Model]| known.iter().any(|known| known.id == model);
+        let known = models::find(*self, model);
+        Capabilities {
+            supports_tools: capabilities.supports_tools && !lacks(without_tools),
+            supports_structured_output: capabilities.supports_structured_output
+                && !lacks(without_json_mode),
+            max_context: known.map(|model| model.context_window),
+            max_output_tokens: known.map(|model| model.max_output_tokens),
+            ..capabilities
+        }
+    }
+}
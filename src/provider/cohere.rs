@@ -0,0 +1,895 @@
+//! Cohere provider implementation.
+//!
+//! This module implements Cohere's v2 chat API using the completions abstraction layer.
+//! It supports text generation, structured output, function calling and grounded answers
+//! over documents passed with [`CohereOptions::with_documents`], whose citations are returned
+//! in [`ResponseMetadata::citations`](crate::ResponseMetadata::citations).
+//!
+//! The v2 API has no connectors, so documents must be retrieved before the request, for
+//! example from a [`VectorIndex`](crate::rag::VectorIndex).
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::completions::{
+    CompletionClient, CompletionProviderConfig, CompletionRequestBuilder, ConversationItem,
+    chat::{self, ChatFunction, ChatMessage, ChatTool, ChatToolCall},
+    client::{convert_messages_to_conversation, tool_phase_format},
+};
+use crate::core::{
+    Citation, FunctionCallData, HttpClientConfig, InspectorConfig, LanguageModelUsage, LlmBuilder,
+    LlmError, LlmProvider, ProviderResponse, ResponseContent, StructuredRequest, ToolCallingConfig,
+    ToolCallingGuard, ToolChoice, ToolRegistry,
+};
+use crate::provider::Provider;
+use crate::provider::constants::cohere;
+use crate::responses::{Format, request::FormatType};
+
+// ============================================================================
+// Cohere API Request Types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohereRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub documents: Vec<CohereDocument>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<CohereResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ChatTool>>,
+    /// `REQUIRED` or `NONE`; omitted to let the model decide
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Top-p sampling
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CohereResponseFormat {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub json_schema: Value,
+}
+
+/// A document the model can ground its answer in and cite.
+///
+/// `data` holds arbitrary text fields. `title` and `url` are used for the
+/// [`Citation`] of the document, falling back to the id.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct CohereDocument {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub data: BTreeMap<String, String>,
+}
+
+impl CohereDocument {
+    /// A document with `snippet` as its text.
+    pub fn new(snippet: impl Into<String>) -> Self {
+        Self::default().with_field("snippet", snippet)
+    }
+
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        self.with_field("title", title)
+    }
+
+    pub fn with_url(self, url: impl Into<String>) -> Self {
+        self.with_field("url", url)
+    }
+
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.data.insert(name.into(), value.into());
+        self
+    }
+}
+
+// ============================================================================
+// Cohere API Response Types
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereResponse {
+    #[serde(default)]
+    pub id: String,
+    pub message: CohereResponseMessage,
+    #[allow(dead_code)]
+    pub finish_reason: Option<String>,
+    pub usage: Option<CohereUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereResponseMessage {
+    #[serde(default)]
+    pub content: Vec<CohereContent>,
+    /// The model's reasoning before calling tools
+    pub tool_plan: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Vec<ChatToolCall>,
+    #[serde(default)]
+    pub citations: Vec<CohereCitation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CohereContent {
+    Text {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// A span of the answer and the documents or tool results backing it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereCitation {
+    pub start: usize,
+    pub end: usize,
+    #[allow(dead_code)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub sources: Vec<CohereSource>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereSource {
+    /// `document` or `tool`
+    #[serde(rename = "type")]
+    #[allow(dead_code)]
+    pub r#type: String,
+    pub id: Option<String>,
+    /// The cited document's fields, including its `id`
+    pub document: Option<BTreeMap<String, Value>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereUsage {
+    pub tokens: Option<CohereTokens>,
+    pub billed_units: Option<CohereTokens>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CohereTokens {
+    pub input_tokens: Option<f64>,
+    pub output_tokens: Option<f64>,
+}
+
+impl CohereCitation {
+    fn citations(&self) -> impl Iterator<Item = Citation> + '_ {
+        self.sources.iter().filter_map(|source| {
+            let field = |name: &str| {
+                source
+                    .document
+                    .as_ref()?
+                    .get(name)?
+                    .as_str()
+                    .map(str::to_string)
+            };
+            Some(Citation {
+                source: field("url")
+                    .or_else(|| field("id"))
+                    .or_else(|| source.id.clone())?,
+                title: field("title"),
+                span: Some(self.start..self.end),
+            })
+        })
+    }
+}
+
+// ============================================================================
+// Cohere Configuration
+// ============================================================================
+
+/// Cohere-specific request options.
+///
+/// # Example
+/// ```no_run
+/// # use rsai::{llm, ApiKey, ChatRole, CohereDocument, CohereOptions, Message, Provider, TextResponse};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let answer = llm::with(Provider::Cohere)
+///     .api_key(ApiKey::Default)?
+///     .model("command-a-03-2025")
+///     .messages(vec![Message::new(ChatRole::User, "When was the bridge opened?")])
+///     .cohere_options(CohereOptions::new().with_documents([
+///         CohereDocument::new("The bridge opened to traffic in May 1937.")
+///             .with_title("Golden Gate Bridge")
+///             .with_url("https://example.com/golden-gate"),
+///     ]))
+///     .complete::<TextResponse>()
+///     .await?;
+///
+/// for citation in &answer.metadata.citations {
+///     println!("{:?} {}", citation.span, citation.source);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CohereOptions {
+    /// Documents to ground the answer in
+    pub documents: Vec<CohereDocument>,
+}
+
+impl CohereOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_documents(mut self, documents: impl IntoIterator<Item = CohereDocument>) -> Self {
+        self.documents.extend(documents);
+        self
+    }
+}
+
+#[derive(Clone)]
+pub struct CohereConfig {
+    pub api_key: String,
+    pub base_url: String,
+    pub tool_calling_config: Option<ToolCallingConfig>,
+    pub http_config: HttpClientConfig,
+    /// Configuration for request/response inspection
+    pub inspector_config: Option<InspectorConfig>,
+    /// Cohere-specific request options
+    pub options: CohereOptions,
+}
+
+impl CohereConfig {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            base_url: cohere::API_BASE.to_string(),
+            tool_calling_config: Some(ToolCallingConfig::default()),
+            http_config: HttpClientConfig::default(),
+            inspector_config: None,
+            options: CohereOptions::default(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn with_tool_calling_config(mut self, config: ToolCallingConfig) -> Self {
+        self.tool_calling_config = Some(config);
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpClientConfig) -> Self {
+        self.http_config = config;
+        self
+    }
+
+    pub fn with_inspector_config(mut self, config: InspectorConfig) -> Self {
+        self.inspector_config = Some(config);
+        self
+    }
+
+    pub fn with_options(mut self, options: CohereOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn get_tool_calling_guard(&self) -> ToolCallingGuard {
+        if let Some(ref config) = self.tool_calling_config {
+            ToolCallingGuard::from_config(config)
+        } else {
+            ToolCallingGuard::new()
+        }
+    }
+}
+
+impl CompletionProviderConfig for CohereConfig {
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn auth_header(&self) -> (String, String) {
+        (
+            "Authorization".to_string(),
+            format!("Bearer {}", self.api_key),
+        )
+    }
+
+    fn http_config(&self) -> HttpClientConfig {
+        self.http_config.clone()
+    }
+
+    fn inspector_config(&self) -> Option<&InspectorConfig> {
+        self.inspector_config.as_ref()
+    }
+}
+
+// ============================================================================
+// Request Builder Implementation
+// ============================================================================
+
+#[derive(Default)]
+pub struct CohereRequestBuilder {
+    documents: Vec<CohereDocument>,
+}
+
+impl CohereRequestBuilder {
+    pub fn new(options: &CohereOptions) -> Self {
+        Self {
+            documents: options.documents.clone(),
+        }
+    }
+}
+
+impl CompletionRequestBuilder for CohereRequestBuilder {
+    type Request = CohereRequest;
+    type Response = CohereResponse;
+
+    fn build_request(
+        &self,
+        request: &StructuredRequest,
+        format: &Format,
+        conversation: &[ConversationItem],
+    ) -> Result<Self::Request, LlmError> {
+        let gen_config = request.generation_config.as_ref();
+        let (tools, tool_choice) = build_tools_config(request);
+
+        // Cohere doesn't support combining tools with a JSON response format.
+        // `CohereClient` splits such requests into a tool phase and a structured phase.
+        if tools.is_some() && matches!(format.format, FormatType::JsonSchema(_)) {
+            return Err(LlmError::ProviderConfiguration(
+                "Cohere does not support combining tools with structured JSON output in a \
+                 single request."
+                    .to_string(),
+            ));
+        }
+
+        let response_format = match &format.format {
+            FormatType::JsonSchema(json_schema) => Some(CohereResponseFormat {
+                r#type: "json_object".to_string(),
+                json_schema: json_schema.schema.clone(),
+            }),
+            FormatType::Text { .. } => None,
+        };
+
+        Ok(CohereRequest {
+            model: request.model.clone(),
+            messages: build_messages(conversation),
+            documents: self.documents.clone(),
+            response_format,
+            tools,
+            tool_choice,
+            max_tokens: gen_config.and_then(|c| c.max_tokens),
+            temperature: gen_config.and_then(|c| c.temperature),
+            p: gen_config.and_then(|c| c.top_p),
+            stop_sequences: gen_config.and_then(|c| c.stop.clone()),
+        })
+    }
+
+    fn parse_response(&self, response: Self::Response) -> Result<ProviderResponse, LlmError> {
+        let usage = self.extract_usage(&response).unwrap_or(LanguageModelUsage {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            cached_tokens: None,
+        });
+
+        let message = response.message;
+        let text: String = message
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                CohereContent::Text { text } => Some(text.as_str()),
+                CohereContent::Other => None,
+            })
+            .collect();
+
+        let content = if !message.tool_calls.is_empty() {
+            ResponseContent::FunctionCalls(chat::function_calls(&message.tool_calls))
+        } else if !text.is_empty() {
+            ResponseContent::Text(text)
+        } else {
+            return Err(LlmError::Provider {
+                message: "Empty response from Cohere".to_string(),
+                source: None,
+            });
+        };
+
+        Ok(ProviderResponse {
+            citations: message
+                .citations
+                .iter()
+                .flat_map(CohereCitation::citations)
+                .collect(),
+            // Cohere doesn't return the model
+            ..ProviderResponse::new(response.id, "", Provider::Cohere, content, usage)
+        })
+    }
+
+    fn extract_usage(&self, response: &Self::Response) -> Option<LanguageModelUsage> {
+        let usage = response.usage.as_ref()?;
+        let tokens = usage.tokens.as_ref().or(usage.billed_units.as_ref())?;
+        let prompt_tokens = tokens.input_tokens.unwrap_or(0.0) as i32;
+        let completion_tokens = tokens.output_tokens.unwrap_or(0.0) as i32;
+        Some(LanguageModelUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            cached_tokens: None,
+        })
+    }
+
+    fn endpoint(&self, _model: &str) -> String {
+        "/chat".to_string()
+    }
+
+    fn extract_function_calls(&self, response: &Self::Response) -> Option<Vec<FunctionCallData>> {
+        let tool_calls = &response.message.tool_calls;
+        (!tool_calls.is_empty()).then(|| chat::function_calls(tool_calls))
+    }
+
+    fn extract_text(&self, response: &Self::Response) -> Option<String> {
+        let text = response.message.tool_plan.as_deref()?.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Messages as in the chat completions API, whose shape Cohere's v2 API shares.
+fn build_messages(conversation: &[ConversationItem]) -> Vec<ChatMessage> {
+    chat::build_messages(conversation)
+        .into_iter()
+        // Cohere has no participant names
+        .map(|message| ChatMessage {
+            name: None,
+            ..message
+        })
+        .collect()
+}
+
+/// Cohere can only require some tool call, so a specific function is required by offering
+/// only that tool.
+fn build_tools_config(request: &StructuredRequest) -> (Option<Vec<ChatTool>>, Option<String>) {
+    let Some(tool_config) = request.tool_config.as_ref() else {
+        return (None, None);
+    };
+    let Some(tools) = tool_config.tools.as_ref().filter(|tools| !tools.is_empty()) else {
+        return (None, None);
+    };
+
+    let (only, tool_choice) = match &tool_config.tool_choice {
+        Some(ToolChoice::None) => (None, Some("NONE")),
+        Some(ToolChoice::Required) => (None, Some("REQUIRED")),
+        Some(ToolChoice::Function { name }) => (Some(name), Some("REQUIRED")),
+        Some(ToolChoice::Auto) | None => (None, None),
+    };
+
+    let tools = tools
+        .iter()
+        .filter(|tool| only.is_none_or(|name| *name == tool.name))
+        .map(|tool| ChatTool {
+            r#type: "function".to_string(),
+            function: ChatFunction {
+                name: crate::core::wire_tool_name(&tool.name),
+                description: tool.description.clone(),
+                parameters: tool.parameters.clone(),
+                strict: None,
+            },
+        })
+        .collect();
+
+    (Some(tools), tool_choice.map(str::to_string))
+}
+
+// ============================================================================
+// Cohere Client
+// ============================================================================
+
+pub struct CohereClient {
+    completion_client: CompletionClient<CohereConfig>,
+}
+
+impl CohereClient {
+    pub fn new(api_key: String) -> Result<Self, LlmError> {
+        Self::from_config(CohereConfig::new(api_key))
+    }
+
+    pub fn from_config(config: CohereConfig) -> Result<Self, LlmError> {
+        Ok(Self {
+            completion_client: CompletionClient::new(config)?,
+        })
+    }
+
+    pub fn with_base_url(self, base_url: String) -> Result<Self, LlmError> {
+        Self::from_config(self.completion_client.config.with_base_url(base_url))
+    }
+
+    pub fn with_options(self, options: CohereOptions) -> Result<Self, LlmError> {
+        Self::from_config(self.completion_client.config.with_options(options))
+    }
+
+    fn request_builder(&self) -> CohereRequestBuilder {
+        CohereRequestBuilder::new(&self.completion_client.config.options)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for CohereClient {
+    async fn generate_completion<T, Ctx>(
+        &self,
+        request: StructuredRequest,
+        format: Format,
+        tool_registry: Option<&ToolRegistry<Ctx>>,
+    ) -> Result<T::Output, LlmError>
+    where
+        T: crate::CompletionTarget + Send,
+        Ctx: Send + Sync + 'static,
+    {
+        let builder = self.request_builder();
+
+        // If tools are present and we have a registry, handle automatic tool calling
+        let has_tools = request
+            .tool_config
+            .as_ref()
+            .and_then(|tc| tc.tools.as_ref())
+            .is_some();
+
+        if has_tools && let Some(tool_registry) = tool_registry {
+            let mut guard = self.completion_client.config.get_tool_calling_guard();
+
+            // Like Gemini, structured targets ask for the structured answer after the tool loop.
+            if matches!(format.format, FormatType::JsonSchema(_)) {
+                let provider_response = self
+                    .completion_client
+                    .handle_tool_calling_loop_then_format::<_, Ctx>(
+                        &builder,
+                        request,
+                        tool_registry,
+                        &mut guard,
+                        format,
+                    )
+                    .await?;
+                return T::parse_response(provider_response);
+            }
+
+            let provider_response = self
+                .completion_client
+                .handle_tool_calling_loop::<_, Ctx>(
+                    &builder,
+                    request,
+                    tool_registry,
+                    &mut guard,
+                    format,
+                )
+                .await?;
+            return T::parse_response(provider_response);
+        }
+
+        // Single request without tool calling loop
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let api_request = builder.build_request(&request, &format, &conversation)?;
+        let api_response = self
+            .completion_client
+            .make_api_request(&builder, api_request, &request.model)
+            .await?;
+        T::parse_response(builder.parse_response(api_response)?)
+    }
+
+    /// With tools, structured targets start with the text-mode tool phase, see
+    /// [`generate_completion`](Self::generate_completion).
+    fn request_body(&self, request: &StructuredRequest, format: Format) -> Result<Value, LlmError> {
+        let format = tool_phase_format(request, format);
+
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let api_request = self
+            .request_builder()
+            .build_request(request, &format, &conversation)?;
+        serde_json::to_value(api_request).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize request".to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    async fn complete_raw(&self, model: &str, body: Value) -> Result<ProviderResponse, LlmError> {
+        self.completion_client
+            .complete_raw(&self.request_builder(), body, model)
+            .await
+    }
+}
+
+// ============================================================================
+// Builder Integration
+// ============================================================================
+
+pub fn create_cohere_client_from_builder<State, Ctx>(
+    builder: &LlmBuilder<State, Ctx>,
+) -> Result<CohereClient, LlmError> {
+    let api_key = builder
+        .get_api_key()
+        .ok_or_else(|| LlmError::ProviderConfiguration("CO_API_KEY not set.".to_string()))?
+        .to_string();
+
+    let mut config = CohereConfig::new(api_key);
+
+    if let Some(http_config) = builder.get_http_config() {
+        config = config.with_http_config(http_config);
+    }
+
+    if let Some(inspector_config) = builder.get_inspector_config() {
+        config = config.with_inspector_config(inspector_config.clone());
+    }
+
+    if let Some(options) = builder.get_cohere_options() {
+        config = config.with_options(options.clone());
+    }
+
+    if let Some(tool_calling_config) = builder.get_tool_calling_config() {
+        config = config.with_tool_calling_config(tool_calling_config.clone());
+    }
+
+    CohereClient::from_config(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ChatRole, ConversationMessage, Message};
+    use crate::{CompletionTarget, TextResponse};
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_partial_json, method, path},
+    };
+
+    #[tokio::test]
+    async fn test_documents_are_sent_and_citations_parsed() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(body_partial_json(json!({
+                "model": "command-a-03-2025",
+                "documents": [{
+                    "id": "bridge",
+                    "data": { "snippet": "Opened in May 1937.", "title": "Golden Gate Bridge" }
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "c1",
+                "finish_reason": "COMPLETE",
+                "message": {
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "It opened in May 1937." }],
+                    "citations": [{
+                        "start": 14,
+                        "end": 22,
+                        "text": "May 1937",
+                        "type": "TEXT_CONTENT",
+                        "sources": [{
+                            "type": "document",
+                            "id": "bridge",
+                            "document": {
+                                "id": "bridge",
+                                "snippet": "Opened in May 1937.",
+                                "title": "Golden Gate Bridge"
+                            }
+                        }]
+                    }]
+                },
+                "usage": {
+                    "billed_units": { "input_tokens": 20, "output_tokens": 7 },
+                    "tokens": { "input_tokens": 120, "output_tokens": 7 }
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CohereClient::new("test-key".to_string())
+            .unwrap()
+            .with_base_url(server.uri())
+            .unwrap()
+            .with_options(
+                CohereOptions::new().with_documents([CohereDocument::new("Opened in May 1937.")
+                    .with_id("bridge")
+                    .with_title("Golden Gate Bridge")]),
+            )
+            .unwrap();
+        let request = StructuredRequest {
+            model: "command-a-03-2025".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "When did it open?",
+            ))],
+            tool_config: None,
+            generation_config: None,
+        };
+
+        let response = client
+            .generate_completion::<TextResponse, ()>(request, TextResponse::format().unwrap(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "It opened in May 1937.");
+        assert_eq!(response.usage.prompt_tokens, 120);
+        assert_eq!(
+            response.metadata.citations,
+            vec![Citation {
+                source: "bridge".to_string(),
+                title: Some("Golden Gate Bridge".to_string()),
+                span: Some(14..22),
+            }]
+        );
+    }
+
+    struct WeatherTool;
+
+    impl crate::ToolFunction for WeatherTool {
+        fn schema(&self) -> crate::core::Tool {
+            crate::core::Tool {
+                name: "weather".to_string(),
+                description: None,
+                parameters: json!({ "type": "object", "properties": {} }),
+                strict: None,
+            }
+        }
+
+        fn execute<'a>(
+            &'a self,
+            _ctx: &'a (),
+            _params: Value,
+        ) -> crate::BoxFuture<'a, Result<Value, LlmError>> {
+            Box::pin(async { Ok(json!("Sunny")) })
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    struct Forecast {
+        sky: String,
+    }
+
+    fn chat_response(message: Value, input_tokens: u32, output_tokens: u32) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "id": "c1",
+            "finish_reason": "COMPLETE",
+            "message": message,
+            "usage": {
+                "tokens": { "input_tokens": input_tokens, "output_tokens": output_tokens }
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_structured_answer_after_tools_sums_usage_of_both_phases() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .respond_with(chat_response(
+                json!({
+                    "role": "assistant",
+                    "tool_plan": "Checking the weather",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "weather", "arguments": "{}" }
+                    }]
+                }),
+                10,
+                2,
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .respond_with(chat_response(
+                json!({ "role": "assistant", "content": [{ "type": "text", "text": "Sunny" }] }),
+                12,
+                3,
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(body_partial_json(
+                json!({ "response_format": { "type": "json_object" } }),
+            ))
+            .respond_with(chat_response(
+                json!({
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "{\"sky\":\"sunny\"}" }]
+                }),
+                15,
+                4,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CohereClient::new("test-key".to_string())
+            .unwrap()
+            .with_base_url(server.uri())
+            .unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(std::sync::Arc::new(WeatherTool)).unwrap();
+        let request = StructuredRequest {
+            model: "command-a-03-2025".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "How is the weather?",
+            ))],
+            tool_config: Some(crate::core::ToolConfig {
+                tools: Some(registry.get_schemas().unwrap().into_boxed_slice()),
+                tool_choice: None,
+                parallel_tool_calls: None,
+            }),
+            generation_config: None,
+        };
+
+        let response = client
+            .generate_completion::<Forecast, ()>(
+                request,
+                Forecast::format().unwrap(),
+                Some(&registry),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.content.sky, "sunny");
+        assert_eq!(response.usage.prompt_tokens, 37);
+        assert_eq!(response.usage.completion_tokens, 9);
+        assert_eq!(response.usage.total_tokens, 46);
+    }
+
+    #[test]
+    fn test_function_tool_choice_offers_only_that_tool() {
+        let request = StructuredRequest {
+            model: "command-a-03-2025".to_string(),
+            messages: Vec::new(),
+            tool_config: Some(crate::core::ToolConfig {
+                tools: Some(Box::new([
+                    crate::core::Tool {
+                        name: "search".to_string(),
+                        description: None,
+                        parameters: json!({ "type": "object" }),
+                        strict: None,
+                    },
+                    crate::core::Tool {
+                        name: "lookup".to_string(),
+                        description: None,
+                        parameters: json!({ "type": "object" }),
+                        strict: None,
+                    },
+                ])),
+                tool_choice: Some(ToolChoice::Function {
+                    name: "lookup".to_string(),
+                }),
+                parallel_tool_calls: None,
+            }),
+            generation_config: None,
+        };
+
+        let (tools, tool_choice) = build_tools_config(&request);
+        let tools = tools.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].function.name, "lookup");
+        assert_eq!(tool_choice.as_deref(), Some("REQUIRED"));
+    }
+}
@@ -0,0 +1,352 @@
+//! Presets for providers serving an OpenAI-compatible chat completions API.
+//!
+//! xAI serves Grok, and Together AI and Fireworks host open-weight models, behind
+//! `/chat/completions`, mapped with [`ChatRequestBuilder`]. A preset supplies the base URL,
+//! bearer auth and these known quirks:
+//! - Function definitions have no `strict` flag, so it is left out of the request.
+//! - JSON schema mode and function calling are only available for some models. Known models
+//!   without them are reported by [`Provider::capabilities_for`] and rejected before sending.
+//! - xAI's reasoning models (`grok-3-mini`, `grok-4`) reject `presence_penalty`,
+//!   `frequency_penalty` and `stop`. rsai sends neither penalty and cuts the answer at
+//!   [`stop`](crate::LlmBuilder::stop) sequences itself.
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::completions::{
+    CompletionClient, CompletionProviderConfig, CompletionRequestBuilder, chat::ChatRequestBuilder,
+    client::convert_messages_to_conversation,
+};
+use crate::core::{
+    HttpClientConfig, InspectorConfig, LlmBuilder, LlmError, LlmProvider, ProviderResponse,
+    StructuredRequest, ToolCallingConfig, ToolCallingGuard, ToolRegistry,
+};
+use crate::provider::Provider;
+use crate::provider::constants::{fireworks, together, xai};
+use crate::responses::Format;
+
+#[derive(Clone)]
+pub struct CompatibleConfig {
+    pub provider: Provider,
+    pub api_key: String,
+    pub base_url: String,
+    pub tool_calling_config: Option<ToolCallingConfig>,
+    pub http_config: HttpClientConfig,
+    /// Configuration for request/response inspection
+    pub inspector_config: Option<InspectorConfig>,
+}
+
+impl CompatibleConfig {
+    /// Preset for xAI's Grok models.
+    pub fn xai(api_key: String) -> Self {
+        Self::new(Provider::XAI, api_key, xai::API_BASE)
+    }
+
+    /// Preset for Together AI.
+    pub fn together(api_key: String) -> Self {
+        Self::new(Provider::Together, api_key, together::API_BASE)
+    }
+
+    /// Preset for Fireworks.
+    pub fn fireworks(api_key: String) -> Self {
+        Self::new(Provider::Fireworks, api_key, fireworks::API_BASE)
+    }
+
+    fn new(provider: Provider, api_key: String, base_url: &str) -> Self {
+        Self {
+            provider,
+            api_key,
+            base_url: base_url.to_string(),
+            tool_calling_config: Some(ToolCallingConfig::default()),
+            http_config: HttpClientConfig::default(),
+            inspector_config: None,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn with_tool_calling_config(mut self, config: ToolCallingConfig) -> Self {
+        self.tool_calling_config = Some(config);
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpClientConfig) -> Self {
+        self.http_config = config;
+        self
+    }
+
+    pub fn with_inspector_config(mut self, config: InspectorConfig) -> Self {
+        self.inspector_config = Some(config);
+        self
+    }
+
+    pub fn get_tool_calling_guard(&self) -> ToolCallingGuard {
+        if let Some(ref config) = self.tool_calling_config {
+            ToolCallingGuard::from_config(config)
+        } else {
+            ToolCallingGuard::new()
+        }
+    }
+}
+
+impl CompletionProviderConfig for CompatibleConfig {
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn auth_header(&self) -> (String, String) {
+        (
+            "Authorization".to_string(),
+            format!("Bearer {}", self.api_key),
+        )
+    }
+
+    fn http_config(&self) -> HttpClientConfig {
+        self.http_config.clone()
+    }
+
+    fn inspector_config(&self) -> Option<&InspectorConfig> {
+        self.inspector_config.as_ref()
+    }
+}
+
+pub struct CompatibleClient {
+    completion_client: CompletionClient<CompatibleConfig>,
+}
+
+impl CompatibleClient {
+    pub fn from_config(config: CompatibleConfig) -> Result<Self, LlmError> {
+        Ok(Self {
+            completion_client: CompletionClient::new(config)?,
+        })
+    }
+
+    pub fn with_base_url(self, base_url: String) -> Result<Self, LlmError> {
+        Self::from_config(self.completion_client.config.with_base_url(base_url))
+    }
+
+    fn request_builder(&self) -> ChatRequestBuilder {
+        ChatRequestBuilder::new(self.completion_client.config.provider).without_strict_tools()
+    }
+}
+
+#[async_trait]
+impl LlmProvider for CompatibleClient {
+    async fn generate_completion<T, Ctx>(
+        &self,
+        request: StructuredRequest,
+        format: Format,
+        tool_registry: Option<&ToolRegistry<Ctx>>,
+    ) -> Result<T::Output, LlmError>
+    where
+        T: crate::CompletionTarget + Send,
+        Ctx: Send + Sync + 'static,
+    {
+        let builder = self.request_builder();
+
+        // If tools are present and we have a registry, handle automatic tool calling
+        let has_tools = request
+            .tool_config
+            .as_ref()
+            .and_then(|tc| tc.tools.as_ref())
+            .is_some();
+
+        if has_tools && let Some(tool_registry) = tool_registry {
+            let mut guard = self.completion_client.config.get_tool_calling_guard();
+            let provider_response = self
+                .completion_client
+                .handle_tool_calling_loop::<_, Ctx>(
+                    &builder,
+                    request,
+                    tool_registry,
+                    &mut guard,
+                    format,
+                )
+                .await?;
+            return T::parse_response(provider_response);
+        }
+
+        // Single request without tool calling loop
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let api_request = builder.build_request(&request, &format, &conversation)?;
+        let api_response = self
+            .completion_client
+            .make_api_request(&builder, api_request, &request.model)
+            .await?;
+        T::parse_response(builder.parse_response(api_response)?)
+    }
+
+    fn request_body(&self, request: &StructuredRequest, format: Format) -> Result<Value, LlmError> {
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let api_request = self
+            .request_builder()
+            .build_request(request, &format, &conversation)?;
+        serde_json::to_value(api_request).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize request".to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    async fn complete_raw(&self, model: &str, body: Value) -> Result<ProviderResponse, LlmError> {
+        self.completion_client
+            .complete_raw(&self.request_builder(), body, model)
+            .await
+    }
+}
+
+// ============================================================================
+// Builder Integration
+// ============================================================================
+
+pub fn create_compatible_client_from_builder<State, Ctx>(
+    builder: &LlmBuilder<State, Ctx>,
+    provider: Provider,
+) -> Result<CompatibleClient, LlmError> {
+    let api_key = builder
+        .get_api_key()
+        .ok_or_else(|| {
+            LlmError::ProviderConfiguration(format!(
+                "{} not set.",
+                provider.default_api_key_env_var()
+            ))
+        })?
+        .to_string();
+
+    let mut config = match provider {
+        Provider::XAI => CompatibleConfig::xai(api_key),
+        Provider::Together => CompatibleConfig::together(api_key),
+        Provider::Fireworks => CompatibleConfig::fireworks(api_key),
+        _ => {
+            return Err(LlmError::ProviderConfiguration(format!(
+                "{provider} has no OpenAI-compatible preset"
+            )));
+        }
+    };
+
+    if let Some(http_config) = builder.get_http_config() {
+        config = config.with_http_config(http_config);
+    }
+
+    if let Some(inspector_config) = builder.get_inspector_config() {
+        config = config.with_inspector_config(inspector_config.clone());
+    }
+
+    if let Some(tool_calling_config) = builder.get_tool_calling_config() {
+        config = config.with_tool_calling_config(tool_calling_config.clone());
+    }
+
+    CompatibleClient::from_config(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ChatRole, ConversationMessage, GenerationConfig, Message};
+    use crate::{CompletionTarget, TextResponse};
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_partial_json, header, method, path},
+    };
+
+    #[tokio::test]
+    async fn test_xai_preset_posts_chat_completions() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "grok-4",
+                "max_tokens": 64,
+                "messages": [{ "role": "user", "content": "Hello" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "model": "grok-4",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hi there" },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CompatibleClient::from_config(
+            CompatibleConfig::xai("test-key".to_string()).with_base_url(server.uri()),
+        )
+        .unwrap();
+        let request = StructuredRequest {
+            model: "grok-4".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "Hello",
+            ))],
+            tool_config: None,
+            generation_config: Some(GenerationConfig {
+                max_tokens: Some(64),
+                ..Default::default()
+            }),
+        };
+
+        let response = client
+            .generate_completion::<TextResponse, ()>(request, TextResponse::format().unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.text, "Hi there");
+        assert_eq!(response.usage.total_tokens, 7);
+    }
+
+    #[tokio::test]
+    async fn test_fireworks_preset_posts_chat_completions() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "accounts/fireworks/models/llama-v3p3-70b-instruct",
+                "messages": [{ "role": "user", "content": "Hello" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "model": "accounts/fireworks/models/llama-v3p3-70b-instruct",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hi there" },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = CompatibleClient::from_config(
+            CompatibleConfig::fireworks("test-key".to_string()).with_base_url(server.uri()),
+        )
+        .unwrap();
+        let request = StructuredRequest {
+            model: "accounts/fireworks/models/llama-v3p3-70b-instruct".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "Hello",
+            ))],
+            tool_config: None,
+            generation_config: None,
+        };
+
+        let response = client
+            .generate_completion::<TextResponse, ()>(request, TextResponse::format().unwrap(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.text, "Hi there");
+        assert_eq!(response.metadata.provider, Provider::Fireworks);
+    }
+}
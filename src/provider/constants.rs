@@ -1,6 +1,7 @@
 pub mod openai {
     pub const API_BASE: &str = "https://api.openai.com/v1";
     pub const RESPONSES_ENDPOINT: &str = "/responses";
+    pub const EMBEDDINGS_ENDPOINT: &str = "/embeddings";
     pub const API_KEY_ENV_VAR: &str = "OPENAI_API_KEY";
 }
 
@@ -10,6 +11,26 @@ pub mod openrouter {
     pub const API_KEY_ENV_VAR: &str = "OPENROUTER_API_KEY";
 }
 
+pub mod xai {
+    pub const API_BASE: &str = "https://api.x.ai/v1";
+    pub const API_KEY_ENV_VAR: &str = "XAI_API_KEY";
+}
+
+pub mod cohere {
+    pub const API_BASE: &str = "https://api.cohere.com/v2";
+    pub const API_KEY_ENV_VAR: &str = "CO_API_KEY";
+}
+
+pub mod together {
+    pub const API_BASE: &str = "https://api.together.xyz/v1";
+    pub const API_KEY_ENV_VAR: &str = "TOGETHER_API_KEY";
+}
+
+pub mod fireworks {
+    pub const API_BASE: &str = "https://api.fireworks.ai/inference/v1";
+    pub const API_KEY_ENV_VAR: &str = "FIREWORKS_API_KEY";
+}
+
 pub mod gemini {
     pub const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
     pub const API_KEY_ENV_VAR: &str = "GEMINI_API_KEY";
@@ -2,20 +2,32 @@
 //!
 //! This module implements the Gemini API using the completions abstraction layer.
 //! It supports text generation, structured output, and function calling.
+//!
+//! Requests go to the Gemini API with an API key, or to Vertex AI with OAuth access tokens
+//! when configured with a [`VertexConfig`].
+
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::completions::{
     CompletionClient, CompletionProviderConfig, CompletionRequestBuilder, ConversationItem,
+    client::{convert_messages_to_conversation, tool_phase_format},
 };
 use crate::core::{
-    FunctionCallData, HttpClientConfig, InspectorConfig, LanguageModelUsage, LlmBuilder, LlmError,
-    LlmProvider, ProviderResponse, ResponseContent, StructuredRequest, ToolCallingConfig,
-    ToolCallingGuard, ToolRegistry,
+    Blob, Citation, FunctionCallData, HttpClientConfig, HttpMethod, InspectorConfig,
+    LanguageModelUsage, LlmBuilder, LlmError, LlmProvider, Message, ProviderResponse,
+    ResponseContent, StructuredRequest, ToolCallingConfig, ToolCallingGuard, ToolRegistry,
+    inline_refs,
 };
 use crate::provider::constants::gemini;
+use crate::provider::vertex::VertexConfig;
 use crate::responses::{Format, request::FormatType};
 
 // ============================================================================
@@ -34,6 +46,9 @@ pub struct GeminiRequest {
     pub tools: Option<Vec<GeminiTool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_config: Option<GeminiToolConfig>,
+    /// Name of a cached context (`cachedContents/...`) to prepend to this request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_content: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +64,7 @@ pub enum Part {
     Text(TextPart),
     FunctionCall(FunctionCallPart),
     FunctionResponse(FunctionResponsePart),
+    InlineData(InlineDataPart),
 }
 
 impl Part {
@@ -86,6 +102,20 @@ pub struct FunctionResponsePart {
     pub function_response: FunctionResponse,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineDataPart {
+    pub inline_data: InlineData,
+}
+
+/// Base64 encoded binary data, e.g. an image of an image generation model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineData {
+    pub mime_type: String,
+    pub data: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionCall {
     pub name: String,
@@ -98,7 +128,7 @@ pub struct FunctionResponse {
     pub response: Value,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GeminiGenerationConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -113,6 +143,11 @@ pub struct GeminiGenerationConfig {
     pub response_mime_type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response_schema: Option<Value>,
+    /// Number of candidates to sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -164,6 +199,90 @@ pub struct Candidate {
     pub finish_reason: Option<String>,
     #[allow(dead_code)]
     pub safety_ratings: Option<Vec<SafetyRating>>,
+    /// Sources used when the answer was grounded, e.g. with Google Search
+    pub grounding_metadata: Option<GroundingMetadata>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroundingMetadata {
+    #[serde(default)]
+    pub grounding_chunks: Vec<GroundingChunk>,
+    #[serde(default)]
+    pub grounding_supports: Vec<GroundingSupport>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroundingChunk {
+    pub web: Option<GroundingSource>,
+    pub retrieved_context: Option<GroundingSource>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroundingSource {
+    pub uri: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Links a segment of the answer to the grounding chunks backing it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroundingSupport {
+    pub segment: Option<Segment>,
+    #[serde(default)]
+    pub grounding_chunk_indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    /// Omitted by the API when 0
+    #[serde(default)]
+    pub start_index: usize,
+    pub end_index: Option<usize>,
+}
+
+impl GroundingMetadata {
+    /// One citation per supported segment and chunk, then one without a span for every chunk
+    /// no segment refers to.
+    fn citations(&self) -> Vec<Citation> {
+        let sources: Vec<Option<&GroundingSource>> = self
+            .grounding_chunks
+            .iter()
+            .map(|chunk| chunk.web.as_ref().or(chunk.retrieved_context.as_ref()))
+            .collect();
+        let citation = |index: usize, span: Option<Range<usize>>| {
+            let source = sources.get(index).copied().flatten()?;
+            Some(Citation {
+                source: source.uri.clone().or_else(|| source.title.clone())?,
+                title: source.title.clone(),
+                span,
+            })
+        };
+
+        let mut cited = vec![false; sources.len()];
+        let mut citations = Vec::new();
+        for support in &self.grounding_supports {
+            let span = support
+                .segment
+                .as_ref()
+                .and_then(|segment| Some(segment.start_index..segment.end_index?));
+            for &index in &support.grounding_chunk_indices {
+                if let Some(c) = citation(index, span.clone()) {
+                    cited[index] = true;
+                    citations.push(c);
+                }
+            }
+        }
+        citations.extend(
+            (0..sources.len())
+                .filter(|&index| !cited[index])
+                .filter_map(|index| citation(index, None)),
+        );
+        citations
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -180,12 +299,104 @@ pub struct UsageMetadata {
     pub prompt_token_count: Option<i32>,
     pub candidates_token_count: Option<i32>,
     pub total_token_count: Option<i32>,
+    /// Prompt tokens served from a cached context
+    pub cached_content_token_count: Option<i32>,
+}
+
+// ============================================================================
+// Gemini Context Caching Types
+// ============================================================================
+
+/// Request to create a cached context with the `cachedContents` API.
+///
+/// Large system prompts or documents are uploaded once and referenced by name in later
+/// requests via [`GeminiOptions::with_cached_content`].
+#[derive(Debug, Clone)]
+pub struct CreateCachedContent {
+    /// Model the cache is bound to (e.g. `gemini-2.5-flash`)
+    pub model: String,
+    /// Messages to cache; system messages become the cached system instruction
+    pub messages: Vec<Message>,
+    /// How long the cache lives before it expires
+    pub ttl: Duration,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedContentRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    ttl: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct CachedContentTtlUpdate {
+    ttl: String,
+}
+
+/// A cached context returned by the `cachedContents` API.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedContent {
+    /// Resource name (`cachedContents/...`) used to reference the cache
+    pub name: String,
+    pub model: Option<String>,
+    pub display_name: Option<String>,
+    pub create_time: Option<String>,
+    pub update_time: Option<String>,
+    /// RFC 3339 timestamp after which the cache is deleted
+    pub expire_time: Option<String>,
+    pub usage_metadata: Option<CachedContentUsage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedContentUsage {
+    /// Number of tokens stored in the cache
+    pub total_token_count: Option<i32>,
+}
+
+fn format_ttl(ttl: Duration) -> String {
+    format!("{}s", ttl.as_secs_f64())
+}
+
+fn cached_content_path(name: &str) -> String {
+    if name.starts_with("cachedContents/") {
+        format!("/{name}")
+    } else {
+        format!("/cachedContents/{name}")
+    }
 }
 
 // ============================================================================
 // Gemini Configuration
 // ============================================================================
 
+/// Gemini-specific request options.
+#[derive(Debug, Clone, Default)]
+pub struct GeminiOptions {
+    /// Cached context (`cachedContents/...`) to reference in every request.
+    /// System instructions and tools should then live in the cache, not the request.
+    pub cached_content: Option<String>,
+}
+
+impl GeminiOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_cached_content(mut self, name: impl Into<String>) -> Self {
+        self.cached_content = Some(name.into());
+        self
+    }
+}
+
 pub struct GeminiConfig {
     pub api_key: String,
     pub base_url: String,
@@ -193,6 +404,10 @@ pub struct GeminiConfig {
     pub http_config: HttpClientConfig,
     /// Configuration for request/response inspection
     pub inspector_config: Option<InspectorConfig>,
+    /// Gemini-specific request options
+    pub options: GeminiOptions,
+    /// Authenticate with Vertex AI instead of the API key
+    pub vertex: Option<VertexConfig>,
 }
 
 impl GeminiConfig {
@@ -203,9 +418,34 @@ impl GeminiConfig {
             tool_calling_config: Some(ToolCallingConfig::default()),
             http_config: HttpClientConfig::default(),
             inspector_config: None,
+            options: GeminiOptions::default(),
+            vertex: None,
         }
     }
 
+    /// Configuration for Gemini on Vertex AI, authenticated with OAuth access tokens as the
+    /// [credentials](HttpClientConfig::credentials) of the HTTP config.
+    pub fn vertex(vertex: VertexConfig) -> Self {
+        let config = Self {
+            base_url: vertex.base_url(),
+            vertex: Some(vertex),
+            ..Self::new(String::new())
+        };
+        Self {
+            http_config: config.with_vertex_credentials(HttpClientConfig::default()),
+            ..config
+        }
+    }
+
+    /// `http_config` authenticated with the Vertex AI access tokens, unless it has
+    /// credentials of its own.
+    fn with_vertex_credentials(&self, mut http_config: HttpClientConfig) -> HttpClientConfig {
+        if let (None, Some(vertex)) = (&http_config.credentials, &self.vertex) {
+            http_config.credentials = Some(Arc::new(vertex.clone()));
+        }
+        http_config
+    }
+
     pub fn with_base_url(mut self, base_url: String) -> Self {
         self.base_url = base_url;
         self
@@ -217,7 +457,7 @@ impl GeminiConfig {
     }
 
     pub fn with_http_config(mut self, config: HttpClientConfig) -> Self {
-        self.http_config = config;
+        self.http_config = self.with_vertex_credentials(config);
         self
     }
 
@@ -226,9 +466,14 @@ impl GeminiConfig {
         self
     }
 
+    pub fn with_options(mut self, options: GeminiOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn get_tool_calling_guard(&self) -> ToolCallingGuard {
         if let Some(ref config) = self.tool_calling_config {
-            ToolCallingGuard::with_limits(config.max_iterations, config.timeout)
+            ToolCallingGuard::from_config(config)
         } else {
             ToolCallingGuard::new()
         }
@@ -257,7 +502,26 @@ impl CompletionProviderConfig for GeminiConfig {
 // Request Builder Implementation
 // ============================================================================
 
-pub struct GeminiRequestBuilder;
+#[derive(Default)]
+pub struct GeminiRequestBuilder {
+    cached_content: Option<String>,
+    vertex: bool,
+}
+
+impl GeminiRequestBuilder {
+    pub fn new(options: &GeminiOptions) -> Self {
+        Self {
+            cached_content: options.cached_content.clone(),
+            vertex: false,
+        }
+    }
+
+    /// Address models by their Vertex AI publisher path.
+    pub fn on_vertex(mut self) -> Self {
+        self.vertex = true;
+        self
+    }
+}
 
 impl CompletionRequestBuilder for GeminiRequestBuilder {
     type Request = GeminiRequest;
@@ -274,11 +538,13 @@ impl CompletionRequestBuilder for GeminiRequestBuilder {
         let generation_config = build_generation_config(request, format);
         let (tools, tool_config) = build_tools_config(request);
 
-        // Gemini doesn't support combining function calling with structured JSON output
+        // Gemini doesn't support combining function calling with structured JSON output.
+        // `GeminiClient` avoids this by splitting such requests into a tool phase and a
+        // structured phase, so this only triggers when the builder is used directly.
         if tools.is_some() && matches!(format.format, FormatType::JsonSchema(_)) {
             return Err(LlmError::ProviderConfiguration(
-                "Gemini does not support combining function calling with structured JSON output. \
-                 Use TextResponse with tools, or structured output without tools."
+                "Gemini does not support combining function calling with structured JSON output \
+                 in a single request."
                     .to_string(),
             ));
         }
@@ -289,53 +555,30 @@ impl CompletionRequestBuilder for GeminiRequestBuilder {
             system_instruction,
             tools,
             tool_config,
+            cached_content: self.cached_content.clone(),
         })
     }
 
     fn parse_response(&self, response: Self::Response) -> Result<ProviderResponse, LlmError> {
-        let candidate = response
-            .candidates
-            .as_ref()
-            .and_then(|c| c.first())
+        parse_candidates(response)?
+            .into_iter()
+            .next()
             .ok_or_else(|| LlmError::Provider {
                 message: "No candidates in Gemini response".to_string(),
                 source: None,
-            })?;
-
-        let content = candidate
-            .content
-            .as_ref()
-            .ok_or_else(|| LlmError::Provider {
-                message: "No content in Gemini candidate".to_string(),
-                source: None,
-            })?;
-
-        let response_content = parse_parts_to_content(&content.parts)?;
-
-        let usage = response
-            .usage_metadata
-            .map(|u| LanguageModelUsage {
-                prompt_tokens: u.prompt_token_count.unwrap_or(0),
-                completion_tokens: u.candidates_token_count.unwrap_or(0),
-                total_tokens: u.total_token_count.unwrap_or(0),
             })
-            .unwrap_or(LanguageModelUsage {
-                prompt_tokens: 0,
-                completion_tokens: 0,
-                total_tokens: 0,
-            });
-
-        Ok(ProviderResponse {
-            id: String::new(), // Gemini doesn't return an ID
-            model: response.model_version.unwrap_or_default(),
-            provider: super::Provider::Gemini,
-            content: response_content,
-            usage,
-        })
     }
 
     fn endpoint(&self, model: &str) -> String {
-        format!("/models/{}:generateContent", model)
+        if self.vertex {
+            format!("/publishers/google/models/{}:generateContent", model)
+        } else {
+            format!("/models/{}:generateContent", model)
+        }
+    }
+
+    fn extract_usage(&self, response: &Self::Response) -> Option<LanguageModelUsage> {
+        response_usage(response)
     }
 
     fn extract_function_calls(&self, response: &Self::Response) -> Option<Vec<FunctionCallData>> {
@@ -355,6 +598,22 @@ impl CompletionRequestBuilder for GeminiRequestBuilder {
 
         if calls.is_empty() { None } else { Some(calls) }
     }
+
+    fn extract_text(&self, response: &Self::Response) -> Option<String> {
+        let candidate = response.candidates.as_ref()?.first()?;
+        let content = candidate.content.as_ref()?;
+
+        let text: String = content
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Text(TextPart { text }) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
 }
 
 // ============================================================================
@@ -369,7 +628,7 @@ fn build_contents_from_conversation(
 
     for item in conversation {
         match item {
-            ConversationItem::Message { role, content } => {
+            ConversationItem::Message { role, content, .. } => {
                 if role == "system" {
                     system_instruction = Some(Content {
                         role: None,
@@ -435,6 +694,7 @@ fn find_function_name_by_call_id(
 /// Convert standard JSON Schema to Gemini's schema format.
 ///
 /// Gemini uses a simplified schema with uppercase type names and fewer fields.
+/// It has no `$ref`, so references must be inlined with [`inline_refs`] first.
 /// If multiple providers adopt this format in the future, this should become
 /// the default schema format, with OpenAI doing its own conversion.
 fn convert_to_gemini_schema(schema: &Value) -> Value {
@@ -446,11 +706,46 @@ fn convert_to_gemini_schema(schema: &Value) -> Value {
                 match key.as_str() {
                     // Skip unsupported fields
                     "$schema" | "additionalProperties" | "title" => continue,
-                    // Convert type to uppercase
-                    "type" => {
-                        if let Value::String(t) = value {
+                    // Convert type to uppercase; `["T", "null"]` becomes a nullable `T`
+                    "type" => match value {
+                        Value::String(t) => {
                             result.insert("type".to_string(), Value::String(t.to_uppercase()));
                         }
+                        Value::Array(types) => {
+                            let mut non_null = types
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .filter(|t| *t != "null");
+                            if let (Some(t), None) = (non_null.next(), non_null.next()) {
+                                result.insert("type".to_string(), Value::String(t.to_uppercase()));
+                            }
+                            if types.iter().any(|t| t == "null") {
+                                result.insert("nullable".to_string(), Value::Bool(true));
+                            }
+                        }
+                        _ => {}
+                    },
+                    // `anyOf: [schema]` is `schema`, and with `{"type": "null"}` a nullable one.
+                    // Keywords next to `anyOf`, such as a field description, take precedence.
+                    "anyOf" => {
+                        if let Some(variants) = value.as_array() {
+                            let (nulls, others): (Vec<_>, Vec<_>) =
+                                variants.iter().partition(|v| {
+                                    v.get("type").and_then(Value::as_str) == Some("null")
+                                });
+                            if let [schema] = others.as_slice() {
+                                if let Value::Object(converted) = convert_to_gemini_schema(schema) {
+                                    for (k, v) in converted {
+                                        if !obj.contains_key(&k) {
+                                            result.insert(k, v);
+                                        }
+                                    }
+                                }
+                                if !nulls.is_empty() {
+                                    result.insert("nullable".to_string(), Value::Bool(true));
+                                }
+                            }
+                        }
                     }
                     // Recurse into nested schemas
                     "properties" => {
@@ -465,8 +760,12 @@ fn convert_to_gemini_schema(schema: &Value) -> Value {
                     "items" => {
                         result.insert("items".to_string(), convert_to_gemini_schema(value));
                     }
+                    // Gemini only understands the `date-time` string format
+                    "format" if value == "date-time" => {
+                        result.insert(key.clone(), value.clone());
+                    }
                     // Pass through supported fields
-                    "required" | "enum" | "description" => {
+                    "required" | "enum" | "description" | "nullable" | "minItems" | "maxItems" => {
                         result.insert(key.clone(), value.clone());
                     }
                     _ => {}
@@ -479,6 +778,11 @@ fn convert_to_gemini_schema(schema: &Value) -> Value {
     }
 }
 
+/// The `responseSchema` sent for a strict JSON schema, with `$ref`s inlined.
+pub(crate) fn response_schema(schema: &Value) -> Value {
+    convert_to_gemini_schema(&inline_refs(schema))
+}
+
 fn build_generation_config(
     request: &StructuredRequest,
     format: &Format,
@@ -488,7 +792,7 @@ fn build_generation_config(
     let (response_mime_type, response_schema) = match &format.format {
         FormatType::JsonSchema(json_schema) => (
             Some("application/json".to_string()),
-            Some(convert_to_gemini_schema(&json_schema.schema)),
+            Some(response_schema(&json_schema.schema)),
         ),
         FormatType::Text { .. } => (None, None),
     };
@@ -505,6 +809,8 @@ fn build_generation_config(
         max_output_tokens: gen_config.and_then(|c| c.max_tokens),
         response_mime_type,
         response_schema,
+        candidate_count: None,
+        stop_sequences: gen_config.and_then(|c| c.stop.clone()),
     })
 }
 
@@ -527,7 +833,7 @@ fn build_tools_config(
         .map(|t| GeminiFunctionDeclaration {
             name: t.name.clone(),
             description: t.description.clone(),
-            parameters: convert_to_gemini_schema(&t.parameters),
+            parameters: convert_to_gemini_schema(&inline_refs(&t.parameters)),
         })
         .collect();
 
@@ -568,6 +874,7 @@ fn build_tools_config(
 fn parse_parts_to_content(parts: &[Part]) -> Result<ResponseContent, LlmError> {
     let mut text_parts = Vec::new();
     let mut function_calls = Vec::new();
+    let mut blobs = Vec::new();
 
     for (idx, part) in parts.iter().enumerate() {
         match part {
@@ -580,11 +887,25 @@ fn parse_parts_to_content(parts: &[Part]) -> Result<ResponseContent, LlmError> {
                 });
             }
             Part::FunctionResponse(_) => {}
+            Part::InlineData(InlineDataPart { inline_data }) => blobs.push(Blob {
+                mime_type: inline_data.mime_type.clone(),
+                data: STANDARD
+                    .decode(&inline_data.data)
+                    .map_err(|e| LlmError::Parse {
+                        message: format!("Invalid base64 in {} inline data", inline_data.mime_type),
+                        source: Box::new(e),
+                    })?,
+            }),
         }
     }
 
     if !function_calls.is_empty() {
         Ok(ResponseContent::FunctionCalls(function_calls))
+    } else if !blobs.is_empty() {
+        Ok(ResponseContent::Binary {
+            blobs,
+            text: (!text_parts.is_empty()).then(|| text_parts.join("")),
+        })
     } else if !text_parts.is_empty() {
         Ok(ResponseContent::Text(text_parts.join("")))
     } else {
@@ -615,6 +936,14 @@ impl GeminiClient {
         })
     }
 
+    /// A client for Gemini on Vertex AI, see [`VertexConfig`].
+    pub fn vertex(vertex: VertexConfig) -> Result<Self, LlmError> {
+        Ok(Self {
+            completion_client: CompletionClient::new(GeminiConfig::vertex(vertex.clone()))?,
+            config: GeminiConfig::vertex(vertex),
+        })
+    }
+
     pub fn with_base_url(mut self, base_url: String) -> Result<Self, LlmError> {
         let new_config = GeminiConfig {
             api_key: self.config.api_key.clone(),
@@ -622,6 +951,8 @@ impl GeminiClient {
             tool_calling_config: self.config.tool_calling_config.clone(),
             http_config: self.config.http_config.clone(),
             inspector_config: self.config.inspector_config.clone(),
+            options: self.config.options.clone(),
+            vertex: self.config.vertex.clone(),
         };
         self.config = GeminiConfig {
             api_key: self.config.api_key.clone(),
@@ -629,6 +960,8 @@ impl GeminiClient {
             tool_calling_config: self.config.tool_calling_config.clone(),
             http_config: self.config.http_config.clone(),
             inspector_config: self.config.inspector_config.clone(),
+            options: self.config.options.clone(),
+            vertex: self.config.vertex.clone(),
         };
         self.completion_client = CompletionClient::new(new_config)?;
         Ok(self)
@@ -644,6 +977,8 @@ impl GeminiClient {
             tool_calling_config: Some(tool_config.clone()),
             http_config: self.config.http_config.clone(),
             inspector_config: self.config.inspector_config.clone(),
+            options: self.config.options.clone(),
+            vertex: self.config.vertex.clone(),
         };
         self.config.tool_calling_config = Some(tool_config);
         self.completion_client = CompletionClient::new(new_config)?;
@@ -651,12 +986,15 @@ impl GeminiClient {
     }
 
     pub fn with_http_config(mut self, http_config: HttpClientConfig) -> Result<Self, LlmError> {
+        let http_config = self.config.with_vertex_credentials(http_config);
         let new_config = GeminiConfig {
             api_key: self.config.api_key.clone(),
             base_url: self.config.base_url.clone(),
             tool_calling_config: self.config.tool_calling_config.clone(),
             http_config: http_config.clone(),
             inspector_config: self.config.inspector_config.clone(),
+            options: self.config.options.clone(),
+            vertex: self.config.vertex.clone(),
         };
         self.config.http_config = http_config;
         self.completion_client = CompletionClient::new(new_config)?;
@@ -673,11 +1011,106 @@ impl GeminiClient {
             tool_calling_config: self.config.tool_calling_config.clone(),
             http_config: self.config.http_config.clone(),
             inspector_config: Some(inspector_config.clone()),
+            options: self.config.options.clone(),
+            vertex: self.config.vertex.clone(),
         };
         self.config.inspector_config = Some(inspector_config);
         self.completion_client = CompletionClient::new(new_config)?;
         Ok(self)
     }
+
+    pub fn with_options(mut self, options: GeminiOptions) -> Result<Self, LlmError> {
+        let new_config = GeminiConfig {
+            api_key: self.config.api_key.clone(),
+            base_url: self.config.base_url.clone(),
+            tool_calling_config: self.config.tool_calling_config.clone(),
+            http_config: self.config.http_config.clone(),
+            inspector_config: self.config.inspector_config.clone(),
+            options: options.clone(),
+            vertex: self.config.vertex.clone(),
+        };
+        self.config.options = options;
+        self.completion_client = CompletionClient::new(new_config)?;
+        Ok(self)
+    }
+
+    fn request_builder(&self) -> GeminiRequestBuilder {
+        let builder = GeminiRequestBuilder::new(&self.config.options);
+        if self.config.vertex.is_some() {
+            builder.on_vertex()
+        } else {
+            builder
+        }
+    }
+
+    /// Create a cached context that later requests can reference by name.
+    pub async fn create_cached_content(
+        &self,
+        request: CreateCachedContent,
+    ) -> Result<CachedContent, LlmError> {
+        let conversation = convert_messages_to_conversation(
+            &request
+                .messages
+                .into_iter()
+                .map(crate::core::ConversationMessage::Chat)
+                .collect::<Vec<_>>(),
+        )?;
+        let (system_instruction, contents) = build_contents_from_conversation(&conversation)?;
+
+        let model = if let Some(vertex) = &self.config.vertex {
+            vertex.model_name(&request.model)
+        } else if request.model.starts_with("models/") {
+            request.model
+        } else {
+            format!("models/{}", request.model)
+        };
+
+        let body = CachedContentRequest {
+            model,
+            contents,
+            system_instruction,
+            ttl: format_ttl(request.ttl),
+            display_name: request.display_name,
+        };
+
+        self.completion_client
+            .request_json(HttpMethod::Post, "/cachedContents", Some(&body))
+            .await
+    }
+
+    /// Fetch metadata (including the expiry time) of a cached context.
+    pub async fn get_cached_content(&self, name: &str) -> Result<CachedContent, LlmError> {
+        self.completion_client
+            .request_json::<(), _>(HttpMethod::Get, &cached_content_path(name), None)
+            .await
+    }
+
+    /// Extend or shorten the lifetime of a cached context.
+    pub async fn update_cached_content_ttl(
+        &self,
+        name: &str,
+        ttl: Duration,
+    ) -> Result<CachedContent, LlmError> {
+        let path = format!("{}?updateMask=ttl", cached_content_path(name));
+        let body = CachedContentTtlUpdate {
+            ttl: format_ttl(ttl),
+        };
+        self.completion_client
+            .request_json(HttpMethod::Patch, &path, Some(&body))
+            .await
+    }
+
+    /// Delete a cached context before it expires.
+    pub async fn delete_cached_content(&self, name: &str) -> Result<(), LlmError> {
+        self.completion_client
+            .request_json::<(), serde_json::Value>(
+                HttpMethod::Delete,
+                &cached_content_path(name),
+                None,
+            )
+            .await
+            .map(|_| ())
+    }
 }
 
 #[async_trait]
@@ -692,7 +1125,7 @@ impl LlmProvider for GeminiClient {
         T: crate::CompletionTarget + Send,
         Ctx: Send + Sync + 'static,
     {
-        let builder = GeminiRequestBuilder;
+        let builder = self.request_builder();
 
         // If tools are present and we have a registry, handle automatic tool calling
         let has_tools = request
@@ -701,14 +1134,31 @@ impl LlmProvider for GeminiClient {
             .and_then(|tc| tc.tools.as_ref())
             .is_some();
 
-        if has_tools && tool_registry.is_some() {
+        if has_tools && let Some(tool_registry) = tool_registry {
             let mut guard = self.config.get_tool_calling_guard();
+
+            // Gemini rejects tools combined with a response schema, so structured targets ask
+            // for the structured answer after the tool loop, whose usage is included.
+            if matches!(format.format, FormatType::JsonSchema(_)) {
+                let provider_response = self
+                    .completion_client
+                    .handle_tool_calling_loop_then_format::<_, Ctx>(
+                        &builder,
+                        request,
+                        tool_registry,
+                        &mut guard,
+                        format,
+                    )
+                    .await?;
+                return T::parse_response(provider_response);
+            }
+
             let provider_response = self
                 .completion_client
                 .handle_tool_calling_loop::<_, Ctx>(
                     &builder,
                     request,
-                    tool_registry.unwrap(),
+                    tool_registry,
                     &mut guard,
                     format,
                 )
@@ -726,36 +1176,141 @@ impl LlmProvider for GeminiClient {
         let provider_response = builder.parse_response(api_response)?;
         T::parse_response(provider_response)
     }
+
+    /// With tools, structured targets start with the text-mode tool phase, see
+    /// [`generate_completion`](Self::generate_completion).
+    fn request_body(&self, request: &StructuredRequest, format: Format) -> Result<Value, LlmError> {
+        let format = tool_phase_format(request, format);
+
+        let builder = self.request_builder();
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let api_request = builder.build_request(request, &format, &conversation)?;
+        serde_json::to_value(api_request).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize request".to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    async fn complete_raw(&self, model: &str, body: Value) -> Result<ProviderResponse, LlmError> {
+        self.completion_client
+            .complete_raw(&self.request_builder(), body, model)
+            .await
+    }
+
+    /// Samples all candidates in one request via `candidateCount`. With automatic tool
+    /// calling every candidate needs its own conversation, so those run as separate requests.
+    async fn generate_candidates<T, Ctx>(
+        &self,
+        request: StructuredRequest,
+        format: Format,
+        tool_registry: Option<&ToolRegistry<Ctx>>,
+        count: u32,
+    ) -> Result<Vec<T::Output>, LlmError>
+    where
+        Self: Sync,
+        T: crate::CompletionTarget + Send,
+        T::Output: Send,
+        Ctx: Send + Sync + 'static,
+    {
+        let has_tools = request
+            .tool_config
+            .as_ref()
+            .and_then(|tc| tc.tools.as_ref())
+            .is_some();
+
+        if has_tools && tool_registry.is_some() {
+            return futures::future::try_join_all((0..count).map(|_| {
+                self.generate_completion::<T, Ctx>(request.clone(), format.clone(), tool_registry)
+            }))
+            .await;
+        }
+
+        let builder = self.request_builder();
+        let conversation = convert_messages_to_conversation(&request.messages)?;
+        let mut api_request = builder.build_request(&request, &format, &conversation)?;
+        api_request
+            .generation_config
+            .get_or_insert_with(GeminiGenerationConfig::default)
+            .candidate_count = Some(count);
+
+        let api_response = self
+            .completion_client
+            .make_api_request(&builder, api_request, &request.model)
+            .await?;
+        parse_candidates(api_response)?
+            .into_iter()
+            .map(T::parse_response)
+            .collect()
+    }
 }
 
-fn convert_messages_to_conversation(
-    messages: &[crate::core::ConversationMessage],
-) -> Result<Vec<ConversationItem>, LlmError> {
-    messages
-        .iter()
-        .map(|msg| match msg {
-            crate::core::ConversationMessage::Chat(m) => {
-                let role = match m.role {
-                    crate::core::ChatRole::System => "system",
-                    crate::core::ChatRole::User => "user",
-                    crate::core::ChatRole::Assistant => "assistant",
-                };
-                Ok(ConversationItem::Message {
-                    role: role.to_string(),
-                    content: m.content.clone(),
-                })
-            }
-            crate::core::ConversationMessage::ToolCall(tc) => Ok(ConversationItem::FunctionCall {
-                id: tc.call_id.clone(),
-                name: tc.name.clone(),
-                arguments: tc.arguments.clone(),
-            }),
-            crate::core::ConversationMessage::ToolCallResult(tr) => {
-                Ok(ConversationItem::FunctionResult {
-                    call_id: tr.tool_call_id.clone(),
-                    result: tr.content.clone(),
-                })
-            }
+fn response_usage(response: &GeminiResponse) -> Option<LanguageModelUsage> {
+    response
+        .usage_metadata
+        .as_ref()
+        .map(|u| LanguageModelUsage {
+            prompt_tokens: u.prompt_token_count.unwrap_or(0),
+            completion_tokens: u.candidates_token_count.unwrap_or(0),
+            total_tokens: u.total_token_count.unwrap_or(0),
+            cached_tokens: u.cached_content_token_count,
+        })
+}
+
+/// Convert every candidate of a response. Usage covers the whole request, so it is reported
+/// on the first candidate only and summing over candidates gives the request total.
+fn parse_candidates(response: GeminiResponse) -> Result<Vec<ProviderResponse>, LlmError> {
+    let usage = response_usage(&response).unwrap_or(LanguageModelUsage {
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        total_tokens: 0,
+        cached_tokens: None,
+    });
+    let model = response.model_version.unwrap_or_default();
+
+    let candidates = response
+        .candidates
+        .filter(|candidates| !candidates.is_empty())
+        .ok_or_else(|| LlmError::Provider {
+            message: "No candidates in Gemini response".to_string(),
+            source: None,
+        })?;
+
+    let empty_usage = LanguageModelUsage {
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        total_tokens: 0,
+        cached_tokens: None,
+    };
+
+    candidates
+        .into_iter()
+        .enumerate()
+        .map(|(index, candidate)| {
+            let content = candidate.content.ok_or_else(|| LlmError::Provider {
+                message: "No content in Gemini candidate".to_string(),
+                source: None,
+            })?;
+
+            let usage = if index == 0 {
+                usage.clone()
+            } else {
+                empty_usage.clone()
+            };
+            Ok(ProviderResponse {
+                citations: candidate
+                    .grounding_metadata
+                    .as_ref()
+                    .map(GroundingMetadata::citations)
+                    .unwrap_or_default(),
+                // Gemini doesn't return an ID
+                ..ProviderResponse::new(
+                    "",
+                    model.clone(),
+                    super::Provider::Gemini,
+                    parse_parts_to_content(&content.parts)?,
+                    usage,
+                )
+            })
         })
         .collect()
 }
@@ -767,20 +1322,586 @@ fn convert_messages_to_conversation(
 pub fn create_gemini_client_from_builder<State, Ctx>(
     builder: &LlmBuilder<State, Ctx>,
 ) -> Result<GeminiClient, LlmError> {
-    let api_key = builder
-        .get_api_key()
-        .ok_or_else(|| LlmError::ProviderConfiguration("GEMINI_API_KEY not set.".to_string()))?
-        .to_string();
-
-    let mut client = GeminiClient::new(api_key)?;
+    let mut client = match builder.get_vertex_config() {
+        Some(vertex) => GeminiClient::vertex(vertex.clone())?,
+        None => {
+            let api_key = builder
+                .get_api_key()
+                .ok_or_else(|| {
+                    LlmError::ProviderConfiguration("GEMINI_API_KEY not set.".to_string())
+                })?
+                .to_string();
+            GeminiClient::new(api_key)?
+        }
+    };
 
     if let Some(http_config) = builder.get_http_config() {
-        client = client.with_http_config(http_config.clone())?;
+        client = client.with_http_config(http_config)?;
     }
 
     if let Some(inspector_config) = builder.get_inspector_config() {
         client = client.with_inspector_config(inspector_config.clone())?;
     }
 
+    if let Some(options) = builder.get_gemini_options() {
+        client = client.with_options(options.clone())?;
+    }
+
+    if let Some(tool_calling_config) = builder.get_tool_calling_config() {
+        client = client.with_tool_calling_config(tool_calling_config.clone())?;
+    }
+
     Ok(client)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{ChatRole, ConversationMessage};
+    use crate::responses::create_text_format;
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_partial_json, method, path},
+    };
+
+    fn client_for(server: &MockServer) -> GeminiClient {
+        GeminiClient::new("test-key".to_string())
+            .unwrap()
+            .with_base_url(server.uri())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_cached_content_sends_model_ttl_and_system_instruction() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/cachedContents"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "cachedContents/abc123",
+                "model": "models/gemini-2.5-flash",
+                "expireTime": "2026-01-01T00:05:00Z",
+                "usageMetadata": { "totalTokenCount": 4096 }
+            })))
+            .mount(&server)
+            .await;
+
+        let cache = client_for(&server)
+            .create_cached_content(CreateCachedContent {
+                model: "gemini-2.5-flash".to_string(),
+                messages: vec![
+                    Message::new(ChatRole::System, "You are a contract analyst."),
+                    Message::new(ChatRole::User, "<large document>"),
+                ],
+                ttl: Duration::from_secs(300),
+                display_name: Some("contracts".to_string()),
+            })
+            .await
+            .expect("cached content");
+
+        assert_eq!(cache.name, "cachedContents/abc123");
+        assert_eq!(cache.usage_metadata.unwrap().total_token_count, Some(4096));
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["model"], "models/gemini-2.5-flash");
+        assert_eq!(body["ttl"], "300s");
+        assert_eq!(body["displayName"], "contracts");
+        assert_eq!(
+            body["systemInstruction"]["parts"][0]["text"],
+            "You are a contract analyst."
+        );
+        assert_eq!(body["contents"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_complete_raw_posts_body_to_model_endpoint() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .and(wiremock::matchers::body_json(json!({
+                "contents": [{ "role": "user", "parts": [{ "text": "Hi" }] }],
+                "generationConfig": { "mediaResolution": "MEDIA_RESOLUTION_LOW" }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [
+                    { "content": { "role": "model", "parts": [{ "text": "Hello" }] } }
+                ],
+                "usageMetadata": { "promptTokenCount": 1, "candidatesTokenCount": 1, "totalTokenCount": 2 },
+                "modelVersion": "gemini-2.5-flash"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = client_for(&server)
+            .complete_raw(
+                "gemini-2.5-flash",
+                json!({
+                    "contents": [{ "role": "user", "parts": [{ "text": "Hi" }] }],
+                    "generationConfig": { "mediaResolution": "MEDIA_RESOLUTION_LOW" }
+                }),
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(response.content, ResponseContent::Text(ref text) if text == "Hello"));
+        assert_eq!(response.usage.total_tokens, 2);
+    }
+
+    #[tokio::test]
+    async fn test_generate_candidates_uses_candidate_count() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [
+                    { "content": { "role": "model", "parts": [{ "text": "Crumb" }] } },
+                    { "content": { "role": "model", "parts": [{ "text": "Rise & Shine" }] } }
+                ],
+                "usageMetadata": { "promptTokenCount": 5, "candidatesTokenCount": 6, "totalTokenCount": 11 },
+                "modelVersion": "gemini-2.5-flash"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let candidates = client_for(&server)
+            .generate_candidates::<crate::TextResponse, ()>(
+                StructuredRequest {
+                    model: "gemini-2.5-flash".to_string(),
+                    messages: vec![ConversationMessage::Chat(Message::new(
+                        ChatRole::User,
+                        "Name a bakery",
+                    ))],
+                    tool_config: None,
+                    generation_config: None,
+                },
+                create_text_format(),
+                None,
+                2,
+            )
+            .await
+            .expect("candidates");
+
+        let texts: Vec<&str> = candidates.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Crumb", "Rise & Shine"]);
+        // Usage covers the request, so it is only reported once
+        assert_eq!(candidates[0].usage.total_tokens, 11);
+        assert_eq!(candidates[1].usage.total_tokens, 0);
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["generationConfig"]["candidateCount"], 2);
+    }
+
+    struct WeatherTool;
+
+    impl crate::ToolFunction for WeatherTool {
+        fn schema(&self) -> crate::core::Tool {
+            crate::core::Tool {
+                name: "weather".to_string(),
+                description: None,
+                parameters: json!({ "type": "object", "properties": {} }),
+                strict: None,
+            }
+        }
+
+        fn execute<'a>(
+            &'a self,
+            _ctx: &'a (),
+            _params: Value,
+        ) -> crate::BoxFuture<'a, Result<Value, LlmError>> {
+            Box::pin(async { Ok(json!("Sunny")) })
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+    struct Forecast {
+        sky: String,
+    }
+
+    fn model_response(part: Value, prompt_tokens: u32, candidate_tokens: u32) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{ "content": { "role": "model", "parts": [part] } }],
+            "usageMetadata": {
+                "promptTokenCount": prompt_tokens,
+                "candidatesTokenCount": candidate_tokens,
+                "totalTokenCount": prompt_tokens + candidate_tokens
+            },
+            "modelVersion": "gemini-2.5-flash"
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_structured_answer_after_tools_sums_usage_of_both_phases() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .respond_with(model_response(
+                json!({ "functionCall": { "name": "weather", "args": {} } }),
+                10,
+                2,
+            ))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .respond_with(model_response(json!({ "text": "Sunny" }), 12, 3))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .and(body_partial_json(json!({
+                "generationConfig": { "responseMimeType": "application/json" }
+            })))
+            .respond_with(model_response(
+                json!({ "text": "{\"sky\":\"sunny\"}" }),
+                15,
+                4,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let registry = ToolRegistry::new();
+        registry.register(std::sync::Arc::new(WeatherTool)).unwrap();
+        let request = StructuredRequest {
+            model: "gemini-2.5-flash".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "How is the weather?",
+            ))],
+            tool_config: Some(crate::core::ToolConfig {
+                tools: Some(registry.get_schemas().unwrap().into_boxed_slice()),
+                tool_choice: None,
+                parallel_tool_calls: None,
+            }),
+            generation_config: None,
+        };
+
+        let response = client_for(&server)
+            .generate_completion::<Forecast, ()>(
+                request,
+                <Forecast as crate::CompletionTarget>::format().unwrap(),
+                Some(&registry),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.content.sky, "sunny");
+        // The tool loop's two requests count towards the final usage
+        assert_eq!(response.usage.prompt_tokens, 37);
+        assert_eq!(response.usage.completion_tokens, 9);
+        assert_eq!(response.usage.total_tokens, 46);
+
+        // The structured phase sends the tool exchange but no tools
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+        let final_body: serde_json::Value = serde_json::from_slice(&requests[2].body).unwrap();
+        assert!(final_body.get("tools").is_none());
+        let contents = final_body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert!(contents[1]["parts"][0].get("functionCall").is_some());
+        assert!(contents[2]["parts"][0].get("functionResponse").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_vertex_uses_publisher_path_and_bearer_token() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .and(wiremock::matchers::header("metadata-flavor", "Google"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.token",
+                "expires_in": 3599
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/publishers/google/models/gemini-2.5-flash:generateContent",
+            ))
+            .and(wiremock::matchers::header(
+                "authorization",
+                "Bearer ya29.token",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{ "content": { "role": "model", "parts": [{ "text": "Hi" }] } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let vertex = VertexConfig::new(
+            "proj",
+            "us-central1",
+            crate::provider::VertexCredentials::MetadataServer,
+        )
+        .with_token_uri(format!("{}/token", server.uri()));
+        // An HTTP config without credentials keeps authenticating with Vertex AI
+        let response = GeminiClient::vertex(vertex)
+            .unwrap()
+            .with_http_config(HttpClientConfig::default())
+            .unwrap()
+            .with_base_url(server.uri())
+            .unwrap()
+            .generate_completion::<crate::TextResponse, ()>(
+                StructuredRequest {
+                    model: "gemini-2.5-flash".to_string(),
+                    messages: vec![ConversationMessage::Chat(Message::new(
+                        ChatRole::User,
+                        "Hello",
+                    ))],
+                    tool_config: None,
+                    generation_config: None,
+                },
+                create_text_format(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, "Hi");
+        let requests = server.received_requests().await.unwrap();
+        let generate = requests
+            .iter()
+            .find(|request| request.method == wiremock::http::Method::POST)
+            .unwrap();
+        assert!(!generate.headers.contains_key("x-goog-api-key"));
+    }
+
+    #[test]
+    fn test_grounding_metadata_becomes_citations() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": "Spain won Euro 2024." }] },
+                "groundingMetadata": {
+                    "groundingChunks": [
+                        { "web": { "uri": "https://uefa.com/final", "title": "uefa.com" } },
+                        { "web": { "uri": "https://example.org/recap", "title": "example.org" } }
+                    ],
+                    "groundingSupports": [{
+                        "segment": { "endIndex": 20, "text": "Spain won Euro 2024." },
+                        "groundingChunkIndices": [0]
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let citations = &parse_candidates(response).unwrap()[0].citations;
+        assert_eq!(
+            citations,
+            &vec![
+                Citation {
+                    source: "https://uefa.com/final".to_string(),
+                    title: Some("uefa.com".to_string()),
+                    span: Some(0..20),
+                },
+                Citation {
+                    source: "https://example.org/recap".to_string(),
+                    title: Some("example.org".to_string()),
+                    span: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_data_becomes_binary_content() {
+        let response: GeminiResponse = serde_json::from_value(json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [
+                    { "text": "Here is your image" },
+                    { "inlineData": { "mimeType": "image/png", "data": "iVBORw0=" } }
+                ] }
+            }]
+        }))
+        .unwrap();
+
+        let content = &parse_candidates(response).unwrap()[0].content;
+        let ResponseContent::Binary { blobs, text } = content else {
+            panic!("expected binary content, got {content:?}");
+        };
+        assert_eq!(text.as_deref(), Some("Here is your image"));
+        assert_eq!(
+            blobs,
+            &vec![Blob {
+                mime_type: "image/png".to_string(),
+                data: vec![0x89, b'P', b'N', b'G', 0x0d],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_cached_content_use_resource_path() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/cachedContents/abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "cachedContents/abc123",
+                "expireTime": "2026-01-01T01:00:00Z"
+            })))
+            .mount(&server)
+            .await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/cachedContents/abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(""))
+            .mount(&server)
+            .await;
+
+        let client = client_for(&server);
+        let updated = client
+            .update_cached_content_ttl("cachedContents/abc123", Duration::from_secs(3600))
+            .await
+            .expect("updated cache");
+        assert_eq!(updated.expire_time.as_deref(), Some("2026-01-01T01:00:00Z"));
+
+        client
+            .delete_cached_content("abc123")
+            .await
+            .expect("deleted cache");
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[0].url.query(), Some("updateMask=ttl"));
+        let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["ttl"], "3600s");
+    }
+
+    #[test]
+    fn test_request_references_cached_content() {
+        let builder = GeminiRequestBuilder::new(
+            &GeminiOptions::new().with_cached_content("cachedContents/abc123"),
+        );
+        let request = StructuredRequest {
+            model: "gemini-2.5-flash".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "Summarize clause 4",
+            ))],
+            tool_config: None,
+            generation_config: None,
+        };
+        let conversation = convert_messages_to_conversation(&request.messages).unwrap();
+
+        let api_request = builder
+            .build_request(&request, &create_text_format(), &conversation)
+            .unwrap();
+        let body = serde_json::to_value(api_request).unwrap();
+        assert_eq!(body["cachedContent"], "cachedContents/abc123");
+    }
+
+    #[derive(schemars::JsonSchema, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct TreeNode {
+        label: String,
+        children: Vec<TreeNode>,
+    }
+
+    /// A postal address
+    #[derive(schemars::JsonSchema, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Address {
+        /// Street and house number
+        street: String,
+    }
+
+    #[derive(schemars::JsonSchema, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Customer {
+        /// Where the customer lives
+        home: Address,
+        /// Where the customer works, if known
+        work: Option<Address>,
+    }
+
+    #[test]
+    fn test_nested_descriptions_are_kept() {
+        let format = crate::responses::create_format_for_type::<Customer>().unwrap();
+        let config = build_generation_config(
+            &StructuredRequest {
+                model: "gemini-2.5-flash".to_string(),
+                messages: vec![],
+                tool_config: None,
+                generation_config: None,
+            },
+            &format,
+        )
+        .unwrap();
+
+        let properties = &config.response_schema.unwrap()["properties"];
+        assert_eq!(properties["home"]["type"], "OBJECT");
+        assert_eq!(
+            properties["home"]["description"],
+            "Where the customer lives"
+        );
+        assert_eq!(
+            properties["home"]["properties"]["street"]["description"],
+            "Street and house number"
+        );
+        assert_eq!(properties["work"]["nullable"], true);
+        assert_eq!(
+            properties["work"]["description"],
+            "Where the customer works, if known"
+        );
+    }
+
+    #[test]
+    fn test_recursive_response_schema_is_inlined() {
+        let format = crate::responses::create_format_for_type::<TreeNode>().unwrap();
+        let config = build_generation_config(
+            &StructuredRequest {
+                model: "gemini-2.5-flash".to_string(),
+                messages: vec![],
+                tool_config: None,
+                generation_config: None,
+            },
+            &format,
+        )
+        .unwrap();
+
+        let schema = config.response_schema.unwrap();
+        assert!(!schema.to_string().contains("$ref"));
+        assert_eq!(
+            schema["properties"]["children"]["items"]["properties"]["label"]["type"],
+            "STRING"
+        );
+    }
+
+    #[test]
+    fn test_nullable_schemas_convert_to_gemini_nullable() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "radius": { "type": ["number", "null"] },
+                "shape": { "anyOf": [{ "$ref": "#/$defs/Shape" }, { "type": "null" }] },
+                "label": { "anyOf": [{ "type": "string" }, { "type": "null" }] }
+            }
+        });
+
+        let converted = convert_to_gemini_schema(&schema);
+
+        assert_eq!(
+            converted["properties"]["radius"],
+            json!({ "type": "NUMBER", "nullable": true })
+        );
+        assert_eq!(
+            converted["properties"]["label"],
+            json!({ "type": "STRING", "nullable": true })
+        );
+        assert_eq!(
+            converted["properties"]["shape"],
+            json!({ "nullable": true })
+        );
+    }
+}
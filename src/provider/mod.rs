@@ -1,17 +1,43 @@
-mod constants;
+mod capabilities;
+pub(crate) mod cohere;
+pub(crate) mod compatible;
+pub(crate) mod constants;
 pub(crate) mod gemini;
+pub mod models;
 pub(crate) mod openai;
 pub(crate) mod openrouter;
+pub(crate) mod vertex;
 
-pub use gemini::{GeminiClient, GeminiConfig};
-pub use openai::{OpenAiClient, OpenAiConfig};
-pub use openrouter::{OpenRouterClient, OpenRouterConfig};
+pub use capabilities::Capabilities;
+pub use cohere::{CohereClient, CohereConfig, CohereDocument, CohereOptions};
+pub use compatible::{CompatibleClient, CompatibleConfig};
+pub use gemini::{
+    CachedContent, CachedContentUsage, CreateCachedContent, GeminiClient, GeminiConfig,
+    GeminiOptions,
+};
+pub use openai::{BackgroundStatus, OpenAiClient, OpenAiConfig, PendingResponse};
+pub use openrouter::{
+    OpenRouterClient, OpenRouterConfig, OpenRouterOptions, OpenRouterProviderPreferences,
+};
+#[cfg(feature = "vertex")]
+pub use vertex::ServiceAccount;
+pub use vertex::{VertexConfig, VertexCredentials};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Serialized in lowercase (`openai`, `openrouter`, `gemini`, `xai`,
+/// `cohere`, `together`, `fireworks`), the names accepted by
+/// [`FromStr`](std::str::FromStr).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Provider {
     OpenAI,
     OpenRouter,
     Gemini,
+    /// xAI's Grok models
+    XAI,
+    Cohere,
+    /// Together AI
+    Together,
+    Fireworks,
 }
 
 impl std::fmt::Display for Provider {
@@ -20,6 +46,30 @@ impl std::fmt::Display for Provider {
             Provider::OpenAI => write!(f, "OpenAI"),
             Provider::OpenRouter => write!(f, "OpenRouter"),
             Provider::Gemini => write!(f, "Gemini"),
+            Provider::XAI => write!(f, "xAI"),
+            Provider::Cohere => write!(f, "Cohere"),
+            Provider::Together => write!(f, "Together AI"),
+            Provider::Fireworks => write!(f, "Fireworks"),
+        }
+    }
+}
+
+impl std::str::FromStr for Provider {
+    type Err = crate::core::LlmError;
+
+    /// Parse a provider name case-insensitively, e.g. `openai` or `Gemini`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "openai" => Ok(Provider::OpenAI),
+            "openrouter" => Ok(Provider::OpenRouter),
+            "gemini" => Ok(Provider::Gemini),
+            "xai" => Ok(Provider::XAI),
+            "cohere" => Ok(Provider::Cohere),
+            "together" => Ok(Provider::Together),
+            "fireworks" => Ok(Provider::Fireworks),
+            _ => Err(crate::core::LlmError::ProviderConfiguration(format!(
+                "Unknown provider '{name}', expected openai, openrouter, gemini, xai, cohere, together or fireworks"
+            ))),
         }
     }
 }
@@ -31,6 +81,98 @@ impl Provider {
             Provider::OpenAI => constants::openai::API_KEY_ENV_VAR,
             Provider::OpenRouter => constants::openrouter::API_KEY_ENV_VAR,
             Provider::Gemini => constants::gemini::API_KEY_ENV_VAR,
+            Provider::XAI => constants::xai::API_KEY_ENV_VAR,
+            Provider::Cohere => constants::cohere::API_KEY_ENV_VAR,
+            Provider::Together => constants::together::API_KEY_ENV_VAR,
+            Provider::Fireworks => constants::fireworks::API_KEY_ENV_VAR,
         }
     }
+
+    /// The response schema sent to this provider for a structured completion with `schema`,
+    /// a JSON Schema whose `title` names the response.
+    ///
+    /// The schema is made strict and non-object roots are wrapped in a `value` property. For
+    /// Gemini, `$ref`s are inlined and the schema is converted to Gemini's OpenAPI subset.
+    pub fn response_schema(
+        &self,
+        schema: serde_json::Value,
+    ) -> Result<serde_json::Value, crate::core::LlmError> {
+        let format = crate::responses::create_format_from_value(schema)?;
+        let crate::responses::FormatType::JsonSchema(json_schema) = format.format else {
+            unreachable!("create_format_from_value always returns a JSON schema format");
+        };
+        Ok(match self {
+            Provider::OpenAI
+            | Provider::OpenRouter
+            | Provider::XAI
+            | Provider::Cohere
+            | Provider::Together
+            | Provider::Fireworks => json_schema.schema,
+            Provider::Gemini => gemini::response_schema(&json_schema.schema),
+        })
+    }
+}
+
+/// The exact response schema rsai sends to `provider` when completing `T`, see
+/// [`Provider::response_schema`].
+///
+/// Commit the schemas of your response types and compare them in a test to catch changes
+/// to what the model is asked for:
+///
+/// ```
+/// use rsai::{Provider, completion_schema, schema_json};
+///
+/// #[completion_schema]
+/// struct Invoice {
+///     total_cents: u64,
+///     currency: String,
+/// }
+///
+/// let schema = schema_json::<Invoice>(Provider::OpenAI)?;
+/// assert_eq!(schema["required"], serde_json::json!(["total_cents", "currency"]));
+/// # Ok::<(), rsai::LlmError>(())
+/// ```
+pub fn schema_json<T: schemars::JsonSchema>(
+    provider: Provider,
+) -> Result<serde_json::Value, crate::core::LlmError> {
+    let schema = serde_json::to_value(schemars::schema_for!(T)).map_err(|e| {
+        crate::core::LlmError::Parse {
+            message: "Failed to build JSON Schema".to_string(),
+            source: Box::new(e),
+        }
+    })?;
+    provider.response_schema(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_response_schema_is_provider_specific() {
+        let schema = json!({
+            "title": "Tags",
+            "type": "array",
+            "items": { "type": "string" }
+        });
+
+        let openai = Provider::OpenAI.response_schema(schema.clone()).unwrap();
+        assert_eq!(openai["additionalProperties"], json!(false));
+        assert_eq!(openai["properties"]["value"]["type"], json!("array"));
+
+        let gemini = Provider::Gemini.response_schema(schema).unwrap();
+        assert_eq!(gemini["type"], json!("OBJECT"));
+        assert_eq!(
+            gemini["properties"]["value"]["items"]["type"],
+            json!("STRING")
+        );
+        assert!(gemini.get("additionalProperties").is_none());
+
+        assert!(
+            Provider::OpenAI
+                .response_schema(json!({"type": "object"}))
+                .is_err()
+        );
+    }
 }
@@ -0,0 +1,241 @@
+//! Known model ids with their token limits.
+//!
+//! ```no_run
+//! # use rsai::{llm, models, ApiKey, Provider};
+//! # fn main() -> Result<(), rsai::LlmError> {
+//! let builder = llm::with(Provider::OpenAI)
+//!     .api_key(ApiKey::Default)?
+//!     .model(models::openai::GPT_4O_MINI);
+//!
+//! const BUDGET: u32 = models::openai::GPT_4O_MINI.context_window / 2;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The lists are not exhaustive; any model id string is still accepted by
+//! [`LlmBuilder::model`](crate::LlmBuilder::model).
+
+use super::Provider;
+
+/// A model id together with the provider that serves it and its token limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Model {
+    pub id: &'static str,
+    pub provider: Provider,
+    /// Maximum number of input + output tokens
+    pub context_window: u32,
+    /// Maximum number of tokens the model can generate in one response
+    pub max_output_tokens: u32,
+}
+
+impl Model {
+    pub const fn new(
+        id: &'static str,
+        provider: Provider,
+        context_window: u32,
+        max_output_tokens: u32,
+    ) -> Self {
+        Self {
+            id,
+            provider,
+            context_window,
+            max_output_tokens,
+        }
+    }
+}
+
+impl AsRef<str> for Model {
+    fn as_ref(&self) -> &str {
+        self.id
+    }
+}
+
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id)
+    }
+}
+
+/// Look up a known model by provider and id.
+pub fn find(provider: Provider, id: &str) -> Option<Model> {
+    let known: &[Model] = match provider {
+        Provider::OpenAI => openai::ALL,
+        Provider::Gemini => gemini::ALL,
+        Provider::OpenRouter => openrouter::ALL,
+        Provider::XAI => xai::ALL,
+        Provider::Cohere => cohere::ALL,
+        Provider::Together => together::ALL,
+        Provider::Fireworks => fireworks::ALL,
+    };
+    known.iter().find(|model| model.id == id).copied()
+}
+
+/// Known models of `provider` without function calling and without JSON schema mode.
+pub(super) fn limitations(provider: Provider) -> (&'static [Model], &'static [Model]) {
+    match provider {
+        Provider::Together => (together::WITHOUT_TOOLS, together::WITHOUT_JSON_MODE),
+        Provider::Fireworks => (fireworks::WITHOUT_TOOLS, fireworks::WITHOUT_JSON_MODE),
+        _ => (&[], &[]),
+    }
+}
+
+pub mod openai {
+    use super::{Model, Provider};
+
+    const fn model(id: &'static str, context_window: u32, max_output_tokens: u32) -> Model {
+        Model::new(id, Provider::OpenAI, context_window, max_output_tokens)
+    }
+
+    pub const GPT_4O: Model = model("gpt-4o", 128_000, 16_384);
+    pub const GPT_4O_MINI: Model = model("gpt-4o-mini", 128_000, 16_384);
+    pub const GPT_4_1: Model = model("gpt-4.1", 1_047_576, 32_768);
+    pub const GPT_4_1_MINI: Model = model("gpt-4.1-mini", 1_047_576, 32_768);
+    pub const GPT_4_1_NANO: Model = model("gpt-4.1-nano", 1_047_576, 32_768);
+    pub const O3: Model = model("o3", 200_000, 100_000);
+    pub const O4_MINI: Model = model("o4-mini", 200_000, 100_000);
+    pub const GPT_5: Model = model("gpt-5", 400_000, 128_000);
+    pub const GPT_5_MINI: Model = model("gpt-5-mini", 400_000, 128_000);
+    pub const GPT_5_NANO: Model = model("gpt-5-nano", 400_000, 128_000);
+
+    pub(super) const ALL: &[Model] = &[
+        GPT_4O,
+        GPT_4O_MINI,
+        GPT_4_1,
+        GPT_4_1_MINI,
+        GPT_4_1_NANO,
+        O3,
+        O4_MINI,
+        GPT_5,
+        GPT_5_MINI,
+        GPT_5_NANO,
+    ];
+}
+
+pub mod gemini {
+    use super::{Model, Provider};
+
+    const fn model(id: &'static str, context_window: u32, max_output_tokens: u32) -> Model {
+        Model::new(id, Provider::Gemini, context_window, max_output_tokens)
+    }
+
+    pub const FLASH_2_0: Model = model("gemini-2.0-flash", 1_048_576, 8_192);
+    pub const FLASH_LITE_2_0: Model = model("gemini-2.0-flash-lite", 1_048_576, 8_192);
+    pub const FLASH_2_5: Model = model("gemini-2.5-flash", 1_048_576, 65_536);
+    pub const FLASH_LITE_2_5: Model = model("gemini-2.5-flash-lite", 1_048_576, 65_536);
+    pub const PRO_2_5: Model = model("gemini-2.5-pro", 1_048_576, 65_536);
+
+    pub(super) const ALL: &[Model] = &[
+        FLASH_2_0,
+        FLASH_LITE_2_0,
+        FLASH_2_5,
+        FLASH_LITE_2_5,
+        PRO_2_5,
+    ];
+}
+
+pub mod openrouter {
+    use super::{Model, Provider};
+
+    const fn model(id: &'static str, context_window: u32, max_output_tokens: u32) -> Model {
+        Model::new(id, Provider::OpenRouter, context_window, max_output_tokens)
+    }
+
+    pub const OPENAI_GPT_4O_MINI: Model = model("openai/gpt-4o-mini", 128_000, 16_384);
+    pub const OPENAI_GPT_4_1: Model = model("openai/gpt-4.1", 1_047_576, 32_768);
+    pub const OPENAI_GPT_5: Model = model("openai/gpt-5", 400_000, 128_000);
+    pub const ANTHROPIC_CLAUDE_SONNET_4: Model =
+        model("anthropic/claude-sonnet-4", 200_000, 64_000);
+    pub const ANTHROPIC_CLAUDE_3_5_HAIKU: Model =
+        model("anthropic/claude-3.5-haiku", 200_000, 8_192);
+    pub const GOOGLE_GEMINI_2_5_FLASH: Model = model("google/gemini-2.5-flash", 1_048_576, 65_536);
+    pub const GOOGLE_GEMINI_2_5_PRO: Model = model("google/gemini-2.5-pro", 1_048_576, 65_536);
+
+    pub(super) const ALL: &[Model] = &[
+        OPENAI_GPT_4O_MINI,
+        OPENAI_GPT_4_1,
+        OPENAI_GPT_5,
+        ANTHROPIC_CLAUDE_SONNET_4,
+        ANTHROPIC_CLAUDE_3_5_HAIKU,
+        GOOGLE_GEMINI_2_5_FLASH,
+        GOOGLE_GEMINI_2_5_PRO,
+    ];
+}
+
+pub mod xai {
+    use super::{Model, Provider};
+
+    /// xAI documents no output limit separate from the context window.
+    const fn model(id: &'static str, context_window: u32) -> Model {
+        Model::new(id, Provider::XAI, context_window, context_window)
+    }
+
+    pub const GROK_3: Model = model("grok-3", 131_072);
+    pub const GROK_3_MINI: Model = model("grok-3-mini", 131_072);
+    pub const GROK_4: Model = model("grok-4", 256_000);
+
+    pub(super) const ALL: &[Model] = &[GROK_3, GROK_3_MINI, GROK_4];
+}
+
+pub mod cohere {
+    use super::{Model, Provider};
+
+    const fn model(id: &'static str, context_window: u32, max_output_tokens: u32) -> Model {
+        Model::new(id, Provider::Cohere, context_window, max_output_tokens)
+    }
+
+    pub const COMMAND_A: Model = model("command-a-03-2025", 256_000, 8_192);
+    pub const COMMAND_R_PLUS: Model = model("command-r-plus-08-2024", 128_000, 4_096);
+    pub const COMMAND_R: Model = model("command-r-08-2024", 128_000, 4_096);
+    pub const COMMAND_R7B: Model = model("command-r7b-12-2024", 128_000, 4_096);
+
+    pub(super) const ALL: &[Model] = &[COMMAND_A, COMMAND_R_PLUS, COMMAND_R, COMMAND_R7B];
+}
+
+pub mod together {
+    use super::{Model, Provider};
+
+    /// Together documents no output limit separate from the context window.
+    const fn model(id: &'static str, context_window: u32) -> Model {
+        Model::new(id, Provider::Together, context_window, context_window)
+    }
+
+    pub const LLAMA_3_3_70B_INSTRUCT_TURBO: Model =
+        model("meta-llama/Llama-3.3-70B-Instruct-Turbo", 131_072);
+    pub const QWEN_2_5_72B_INSTRUCT_TURBO: Model = model("Qwen/Qwen2.5-72B-Instruct-Turbo", 32_768);
+    pub const DEEPSEEK_V3: Model = model("deepseek-ai/DeepSeek-V3", 131_072);
+    pub const DEEPSEEK_R1: Model = model("deepseek-ai/DeepSeek-R1", 163_840);
+
+    pub(super) const ALL: &[Model] = &[
+        LLAMA_3_3_70B_INSTRUCT_TURBO,
+        QWEN_2_5_72B_INSTRUCT_TURBO,
+        DEEPSEEK_V3,
+        DEEPSEEK_R1,
+    ];
+    pub(super) const WITHOUT_TOOLS: &[Model] = &[DEEPSEEK_R1];
+    pub(super) const WITHOUT_JSON_MODE: &[Model] = &[DEEPSEEK_R1];
+}
+
+pub mod fireworks {
+    use super::{Model, Provider};
+
+    /// Fireworks documents no output limit separate from the context window.
+    const fn model(id: &'static str, context_window: u32) -> Model {
+        Model::new(id, Provider::Fireworks, context_window, context_window)
+    }
+
+    pub const LLAMA_V3P3_70B_INSTRUCT: Model =
+        model("accounts/fireworks/models/llama-v3p3-70b-instruct", 131_072);
+    pub const QWEN2P5_72B_INSTRUCT: Model =
+        model("accounts/fireworks/models/qwen2p5-72b-instruct", 32_768);
+    pub const DEEPSEEK_V3: Model = model("accounts/fireworks/models/deepseek-v3", 131_072);
+    pub const DEEPSEEK_R1: Model = model("accounts/fireworks/models/deepseek-r1", 163_840);
+
+    pub(super) const ALL: &[Model] = &[
+        LLAMA_V3P3_70B_INSTRUCT,
+        QWEN2P5_72B_INSTRUCT,
+        DEEPSEEK_V3,
+        DEEPSEEK_R1,
+    ];
+    pub(super) const WITHOUT_TOOLS: &[Model] = &[DEEPSEEK_R1];
+    pub(super) const WITHOUT_JSON_MODE: &[Model] = &[];
+}
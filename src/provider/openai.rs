@@ -11,14 +11,20 @@
 //! When adding new API structs, include all fields from the OpenAI documentation and mark
 //! unused ones with `#[allow(dead_code)]` rather than omitting them.
 
+use std::{collections::BTreeMap, marker::PhantomData, time::Duration};
+
 use crate::provider::constants::openai;
 
+use crate::CompletionTarget;
+use crate::core::runtime::abortable;
 use crate::core::{
-    InspectorConfig, LlmBuilder, LlmError, LlmProvider, StructuredRequest, ToolCallingConfig,
-    ToolCallingGuard, ToolRegistry,
+    HttpMethod, InspectorConfig, LlmBuilder, LlmError, LlmProvider, ProviderResponse,
+    StructuredRequest, ToolCallingConfig, ToolCallingGuard, ToolRegistry,
 };
 use crate::responses::{HttpClientConfig, ResponsesClient, ResponsesProviderConfig};
 use async_trait::async_trait;
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
 
 /// OpenAI-specific configuration for the responses client
 pub struct OpenAiConfig {
@@ -29,6 +35,12 @@ pub struct OpenAiConfig {
     pub http_config: HttpClientConfig,
     /// Configuration for request/response inspection
     pub inspector_config: Option<InspectorConfig>,
+    /// Continue the server-side conversation of this stored response
+    pub previous_response_id: Option<String>,
+    /// Whether OpenAI stores responses for later retrieval (OpenAI defaults to `true`)
+    pub store: Option<bool>,
+    /// Tags attached to stored responses
+    pub metadata: BTreeMap<String, String>,
 }
 
 impl OpenAiConfig {
@@ -39,6 +51,9 @@ impl OpenAiConfig {
             tool_calling_config: Some(ToolCallingConfig::default()),
             http_config: HttpClientConfig::default(),
             inspector_config: None,
+            previous_response_id: None,
+            store: None,
+            metadata: BTreeMap::new(),
         }
     }
 
@@ -59,7 +74,7 @@ impl OpenAiConfig {
 
     pub fn get_tool_calling_guard(&self) -> ToolCallingGuard {
         if let Some(ref config) = self.tool_calling_config {
-            ToolCallingGuard::with_limits(config.max_iterations, config.timeout)
+            ToolCallingGuard::from_config(config)
         } else {
             ToolCallingGuard::new()
         }
@@ -69,6 +84,21 @@ impl OpenAiConfig {
         self.http_config = config;
         self
     }
+
+    pub fn with_previous_response_id(mut self, id: impl Into<String>) -> Self {
+        self.previous_response_id = Some(id.into());
+        self
+    }
+
+    pub fn with_store(mut self, store: bool) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: BTreeMap<String, String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
 }
 
 impl ResponsesProviderConfig for OpenAiConfig {
@@ -98,6 +128,24 @@ impl ResponsesProviderConfig for OpenAiConfig {
     fn inspector_config(&self) -> Option<&InspectorConfig> {
         self.inspector_config.as_ref()
     }
+
+    fn store(&self) -> Option<bool> {
+        self.store
+    }
+
+    fn extra_body(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut body = serde_json::Map::new();
+        if let Some(id) = &self.previous_response_id {
+            body.insert("previous_response_id".to_string(), id.clone().into());
+        }
+        if !self.metadata.is_empty() {
+            body.insert(
+                "metadata".to_string(),
+                serde_json::to_value(&self.metadata).unwrap_or_default(),
+            );
+        }
+        body
+    }
 }
 
 impl OpenAiConfig {
@@ -128,6 +176,9 @@ impl OpenAiClient {
             tool_calling_config: self.responses_client.config.tool_calling_config.clone(),
             http_config: self.responses_client.config.http_config.clone(),
             inspector_config: self.responses_client.config.inspector_config.clone(),
+            previous_response_id: self.responses_client.config.previous_response_id.clone(),
+            store: self.responses_client.config.store,
+            metadata: self.responses_client.config.metadata.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
@@ -142,6 +193,9 @@ impl OpenAiClient {
             tool_calling_config: Some(config),
             http_config: self.responses_client.config.http_config.clone(),
             inspector_config: self.responses_client.config.inspector_config.clone(),
+            previous_response_id: self.responses_client.config.previous_response_id.clone(),
+            store: self.responses_client.config.store,
+            metadata: self.responses_client.config.metadata.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
@@ -158,12 +212,254 @@ impl OpenAiClient {
             tool_calling_config: tool_config.clone(),
             http_config: config,
             inspector_config: self.responses_client.config.inspector_config.clone(),
+            previous_response_id: self.responses_client.config.previous_response_id.clone(),
+            store: self.responses_client.config.store,
+            metadata: self.responses_client.config.metadata.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
     }
 }
 
+impl OpenAiClient {
+    /// Submit the request as a background response and return a handle to poll it.
+    ///
+    /// Background responses cannot run the automatic tool-calling loop.
+    pub async fn submit_background<T: CompletionTarget>(
+        self,
+        request: StructuredRequest,
+        format: crate::responses::Format,
+    ) -> Result<PendingResponse<T>, LlmError> {
+        let mut responses_request = self.responses_client.build_request_with_format(
+            &request,
+            &crate::responses::convert_messages_to_responses_format(request.messages.clone())?,
+            format,
+        )?;
+        responses_request.background = Some(true);
+        // Background responses are only available for stored responses
+        responses_request.store = Some(true);
+
+        let submitted: BackgroundState = self
+            .responses_client
+            .request_json(
+                HttpMethod::Post,
+                openai::RESPONSES_ENDPOINT,
+                Some(&responses_request),
+            )
+            .await?;
+
+        Ok(PendingResponse::new(self, submitted.id))
+    }
+
+    /// Fetch a stored response.
+    pub async fn get_response(&self, id: &str) -> Result<ProviderResponse, LlmError> {
+        let response = self
+            .responses_client
+            .request_json::<(), _>(HttpMethod::Get, &response_path(id), None)
+            .await?;
+        crate::responses::convert_to_provider_response(response, super::Provider::OpenAI)
+    }
+
+    /// Delete a stored response.
+    pub async fn delete_response(&self, id: &str) -> Result<(), LlmError> {
+        self.responses_client
+            .request_json::<(), serde_json::Value>(HttpMethod::Delete, &response_path(id), None)
+            .await
+            .map(|_| ())
+    }
+
+    async fn fetch_response(&self, id: &str) -> Result<serde_json::Value, LlmError> {
+        self.responses_client
+            .request_json::<(), _>(HttpMethod::Get, &response_path(id), None)
+            .await
+    }
+}
+
+fn response_path(id: &str) -> String {
+    format!("{}/{id}", openai::RESPONSES_ENDPOINT)
+}
+
+/// Lifecycle of a background response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackgroundStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Incomplete,
+    Failed,
+    Cancelled,
+}
+
+impl BackgroundStatus {
+    /// Whether the response has stopped running.
+    pub fn is_finished(self) -> bool {
+        !matches!(
+            self,
+            BackgroundStatus::Queued | BackgroundStatus::InProgress
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BackgroundState {
+    id: String,
+    status: BackgroundStatus,
+    #[serde(default)]
+    error: Option<BackgroundError>,
+    #[serde(default)]
+    incomplete_details: Option<IncompleteDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IncompleteDetails {
+    reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackgroundError {
+    #[allow(dead_code)]
+    code: Option<String>,
+    message: String,
+}
+
+/// A response running in the background on OpenAI, created with
+/// [`LlmBuilder::complete_background`](crate::LlmBuilder::complete_background).
+///
+/// The id can be stored and the response picked up later, even from another process, with
+/// [`PendingResponse::new`].
+pub struct PendingResponse<T> {
+    client: OpenAiClient,
+    id: String,
+    poll_interval: Duration,
+    abort_signal: Option<CancellationToken>,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T: CompletionTarget> PendingResponse<T> {
+    /// Resume polling the background response `id`.
+    pub fn new(client: OpenAiClient, id: impl Into<String>) -> Self {
+        Self {
+            client,
+            id: id.into(),
+            poll_interval: Duration::from_secs(2),
+            abort_signal: None,
+            _target: PhantomData,
+        }
+    }
+
+    /// Set how long [`await_result`](Self::await_result) waits between polls (default 2s).
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Stop [`await_result`](Self::await_result) with [`LlmError::Aborted`] when `token` is
+    /// cancelled. The response keeps running on OpenAI until [`cancel`](Self::cancel)led.
+    pub fn abort_signal(mut self, token: CancellationToken) -> Self {
+        self.abort_signal = Some(token);
+        self
+    }
+
+    /// The response id.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Fetch the current status.
+    pub async fn status(&self) -> Result<BackgroundStatus, LlmError> {
+        Ok(self
+            .state(self.client.fetch_response(&self.id).await?)?
+            .status)
+    }
+
+    /// Cancel the response. Awaiting it afterwards returns [`LlmError::Aborted`].
+    pub async fn cancel(&self) -> Result<(), LlmError> {
+        self.client
+            .responses_client
+            .request_json::<(), serde_json::Value>(
+                HttpMethod::Post,
+                &format!("{}/cancel", response_path(&self.id)),
+                None,
+            )
+            .await
+            .map(|_| ())
+    }
+
+    /// Poll until the response has finished and parse it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LlmError::Api`] when the response failed or ended incomplete, e.g. at the
+    /// output token limit, and [`LlmError::Aborted`] when it was cancelled or the
+    /// [`abort_signal`](Self::abort_signal) fired.
+    pub async fn await_result(self) -> Result<T::Output, LlmError> {
+        let abort_signal = self.abort_signal.clone();
+        abortable(abort_signal, self.poll()).await
+    }
+
+    async fn poll(self) -> Result<T::Output, LlmError> {
+        loop {
+            let value = self.client.fetch_response(&self.id).await?;
+            let state = self.state(value.clone())?;
+
+            match state.status {
+                BackgroundStatus::Queued | BackgroundStatus::InProgress => {
+                    crate::core::runtime::sleep(self.poll_interval).await;
+                }
+                BackgroundStatus::Completed => {
+                    let response: crate::responses::response::Response =
+                        serde_json::from_value(value).map_err(|e| LlmError::Parse {
+                            message: "Failed to parse API response".to_string(),
+                            source: Box::new(e),
+                        })?;
+                    let provider_response = crate::responses::convert_to_provider_response(
+                        response,
+                        super::Provider::OpenAI,
+                    )?;
+                    return T::parse_response(provider_response);
+                }
+                BackgroundStatus::Failed => {
+                    return Err(LlmError::Api {
+                        message: format!(
+                            "Background response {} failed: {}",
+                            self.id,
+                            state
+                                .error
+                                .map(|error| error.message)
+                                .unwrap_or_else(|| "Unknown error".to_string())
+                        ),
+                        status_code: None,
+                        source: None,
+                    });
+                }
+                BackgroundStatus::Incomplete => {
+                    return Err(LlmError::Api {
+                        message: format!(
+                            "Background response {} is incomplete: {}",
+                            self.id,
+                            state
+                                .incomplete_details
+                                .map(|details| details.reason)
+                                .unwrap_or_else(|| "unknown reason".to_string())
+                        ),
+                        status_code: None,
+                        source: None,
+                    });
+                }
+                BackgroundStatus::Cancelled => return Err(LlmError::Aborted),
+            }
+        }
+    }
+
+    fn state(&self, value: serde_json::Value) -> Result<BackgroundState, LlmError> {
+        serde_json::from_value(value).map_err(|e| LlmError::Parse {
+            message: format!("Failed to parse status of background response {}", self.id),
+            source: Box::new(e),
+        })
+    }
+}
+
 #[async_trait]
 impl LlmProvider for OpenAiClient {
     async fn generate_completion<T, Ctx>(
@@ -206,6 +502,22 @@ impl LlmProvider for OpenAiClient {
             crate::responses::convert_to_provider_response(api_response, super::Provider::OpenAI)?;
         T::parse_response(provider_response)
     }
+
+    fn request_body(
+        &self,
+        request: &StructuredRequest,
+        format: crate::responses::Format,
+    ) -> Result<serde_json::Value, LlmError> {
+        self.responses_client.request_body(request, format)
+    }
+
+    async fn complete_raw(
+        &self,
+        _model: &str,
+        body: serde_json::Value,
+    ) -> Result<ProviderResponse, LlmError> {
+        self.responses_client.complete_raw(body).await
+    }
 }
 
 pub fn create_openai_client_from_builder<State, Ctx>(
@@ -219,15 +531,147 @@ pub fn create_openai_client_from_builder<State, Ctx>(
     let mut config = OpenAiConfig::new(api_key);
 
     if let Some(http_config) = builder.get_http_config() {
-        config = config.with_http_config(http_config.clone());
+        config = config.with_http_config(http_config);
     }
 
     if let Some(inspector_config) = builder.get_inspector_config() {
         config = config.with_inspector_config(inspector_config.clone());
     }
 
+    if let Some(id) = builder.get_previous_response_id() {
+        config = config.with_previous_response_id(id);
+    }
+
+    if let Some(store) = builder.get_store() {
+        config = config.with_store(store);
+    }
+
+    config = config.with_metadata(builder.get_metadata().clone());
+
+    if let Some(tool_calling_config) = builder.get_tool_calling_config() {
+        config = config.with_tool_calling_config(tool_calling_config.clone());
+    }
+
     let client = ResponsesClient::new(config)?;
     Ok(OpenAiClient {
         responses_client: client,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fixtures::{Sum, responses_answer};
+    use crate::core::{ChatRole, ConversationMessage, Message};
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client_for(server: &MockServer) -> OpenAiClient {
+        OpenAiClient::new("test-key".to_string())
+            .unwrap()
+            .with_base_url(format!("{}/v1", server.uri()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_background_response_is_polled_until_completed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .and(body_partial_json(json!({ "background": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_bg",
+                "status": "queued",
+                "output": []
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/responses/resp_bg"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_bg",
+                "status": "in_progress",
+                "output": []
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        let mut completed = responses_answer("{\"sum\":3}");
+        completed["id"] = "resp_bg".into();
+        completed["status"] = "completed".into();
+        Mock::given(method("GET"))
+            .and(path("/v1/responses/resp_bg"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completed))
+            .mount(&server)
+            .await;
+
+        let request = StructuredRequest {
+            model: "mock-model".to_string(),
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "Add 1 and 2",
+            ))],
+            tool_config: None,
+            generation_config: None,
+        };
+        let pending = client_for(&server)
+            .submit_background::<Sum>(request, Sum::format().unwrap())
+            .await
+            .unwrap()
+            .poll_interval(Duration::from_millis(10));
+
+        assert_eq!(pending.id(), "resp_bg");
+        assert_eq!(
+            pending.status().await.unwrap(),
+            BackgroundStatus::InProgress
+        );
+        let result = pending.await_result().await.unwrap();
+        assert_eq!(result.content.sum, 3);
+    }
+
+    #[tokio::test]
+    async fn test_background_response_incomplete_or_aborted_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/responses/resp_cut"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_cut",
+                "status": "incomplete",
+                "incomplete_details": { "reason": "max_output_tokens" },
+                "output": []
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/responses/resp_slow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_slow",
+                "status": "in_progress",
+                "output": []
+            })))
+            .mount(&server)
+            .await;
+
+        let err = PendingResponse::<Sum>::new(client_for(&server), "resp_cut")
+            .await_result()
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, LlmError::Api { ref message, .. } if message.contains("max_output_tokens"))
+        );
+
+        let token = CancellationToken::new();
+        let pending = PendingResponse::<Sum>::new(client_for(&server), "resp_slow")
+            .poll_interval(Duration::from_millis(10))
+            .abort_signal(token.clone());
+        let abort = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        };
+        let (result, ()) = tokio::join!(pending.await_result(), abort);
+        assert!(matches!(result, Err(LlmError::Aborted)));
+    }
+}
@@ -15,10 +15,120 @@ use crate::provider::constants::openrouter;
 use crate::responses::{HttpClientConfig, ResponsesClient, ResponsesProviderConfig};
 
 use crate::core::{
-    InspectorConfig, LlmBuilder, LlmError, LlmProvider, StructuredRequest, ToolCallingConfig,
-    ToolCallingGuard, ToolRegistry,
+    InspectorConfig, LlmBuilder, LlmError, LlmProvider, ProviderResponse, StructuredRequest,
+    ToolCallingConfig, ToolCallingGuard, ToolRegistry,
 };
 use async_trait::async_trait;
+use serde::Serialize;
+
+/// OpenRouter routing preferences for choosing between upstream providers.
+///
+/// See <https://openrouter.ai/docs/features/provider-routing>.
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct OpenRouterProviderPreferences {
+    /// Upstream providers to try in order (e.g. `["anthropic", "openai"]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Whether OpenRouter may fall back to providers not listed in `order`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only route to providers that support every parameter in the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Restrict routing to these providers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+    /// Never route to these providers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+}
+
+/// OpenRouter-specific request options: model fallbacks, provider routing and transforms.
+///
+/// # Example
+/// ```no_run
+/// # use rsai::{llm, ApiKey, ChatRole, Message, OpenRouterOptions, Provider, TextResponse};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let reply = llm::with(Provider::OpenRouter)
+///     .api_key(ApiKey::Default)?
+///     .model("openai/gpt-4o-mini")
+///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+///     .openrouter_options(
+///         OpenRouterOptions::new()
+///             .with_fallback_models(["anthropic/claude-3.5-haiku"])
+///             .with_provider_order(["openai", "azure"])
+///             .allow_fallbacks(false),
+///     )
+///     .complete::<TextResponse>()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct OpenRouterOptions {
+    /// Models to try if the primary model is unavailable
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<OpenRouterProviderPreferences>,
+    /// Prompt transforms to apply (e.g. `["middle-out"]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<String>,
+}
+
+impl OpenRouterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fallback_models<I, S>(mut self, models: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.models = models.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_provider_order<I, S>(mut self, order: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.provider.get_or_insert_with(Default::default).order =
+            order.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn allow_fallbacks(mut self, allow: bool) -> Self {
+        self.provider
+            .get_or_insert_with(Default::default)
+            .allow_fallbacks = Some(allow);
+        self
+    }
+
+    pub fn with_provider_preferences(mut self, preferences: OpenRouterProviderPreferences) -> Self {
+        self.provider = Some(preferences);
+        self
+    }
+
+    pub fn with_transforms<I, S>(mut self, transforms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.transforms = transforms.into_iter().map(Into::into).collect();
+        self
+    }
+
+    fn to_body(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+}
 
 /// OpenRouter-specific configuration for the responses client
 pub struct OpenRouterConfig {
@@ -31,6 +141,8 @@ pub struct OpenRouterConfig {
     pub tool_calling_config: Option<ToolCallingConfig>,
     /// Configuration for request/response inspection
     pub inspector_config: Option<InspectorConfig>,
+    /// Model fallback, provider routing and transform options
+    pub options: OpenRouterOptions,
 }
 
 impl OpenRouterConfig {
@@ -43,6 +155,7 @@ impl OpenRouterConfig {
             tool_calling_config: Some(ToolCallingConfig::default()),
             http_config: HttpClientConfig::default(),
             inspector_config: None,
+            options: OpenRouterOptions::default(),
         }
     }
 
@@ -76,9 +189,14 @@ impl OpenRouterConfig {
         self
     }
 
+    pub fn with_options(mut self, options: OpenRouterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn get_tool_calling_guard(&self) -> ToolCallingGuard {
         if let Some(ref config) = self.tool_calling_config {
-            ToolCallingGuard::with_limits(config.max_iterations, config.timeout)
+            ToolCallingGuard::from_config(config)
         } else {
             ToolCallingGuard::new()
         }
@@ -126,6 +244,10 @@ impl ResponsesProviderConfig for OpenRouterConfig {
     fn inspector_config(&self) -> Option<&InspectorConfig> {
         self.inspector_config.as_ref()
     }
+
+    fn extra_body(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.options.to_body()
+    }
 }
 
 impl OpenRouterConfig {
@@ -163,6 +285,7 @@ impl OpenRouterClient {
             tool_calling_config: self.responses_client.config.tool_calling_config.clone(),
             http_config,
             inspector_config,
+            options: self.responses_client.config.options.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
@@ -178,6 +301,11 @@ impl OpenRouterClient {
         self
     }
 
+    pub fn with_options(mut self, options: OpenRouterOptions) -> Self {
+        self.responses_client.config.options = options;
+        self
+    }
+
     pub fn with_tool_calling_config(mut self, config: ToolCallingConfig) -> Result<Self, LlmError> {
         let current_api_key = &self.responses_client.config.api_key;
         let base_url = &self.responses_client.config.base_url;
@@ -194,6 +322,7 @@ impl OpenRouterClient {
             tool_calling_config: Some(config),
             http_config,
             inspector_config,
+            options: self.responses_client.config.options.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
@@ -209,6 +338,7 @@ impl OpenRouterClient {
             tool_calling_config: current_config.tool_calling_config.clone(),
             http_config: config,
             inspector_config: current_config.inspector_config.clone(),
+            options: current_config.options.clone(),
         };
         self.responses_client = ResponsesClient::new(new_config)?;
         Ok(self)
@@ -259,6 +389,22 @@ impl LlmProvider for OpenRouterClient {
         )?;
         T::parse_response(provider_response)
     }
+
+    fn request_body(
+        &self,
+        request: &StructuredRequest,
+        format: crate::responses::Format,
+    ) -> Result<serde_json::Value, LlmError> {
+        self.responses_client.request_body(request, format)
+    }
+
+    async fn complete_raw(
+        &self,
+        _model: &str,
+        body: serde_json::Value,
+    ) -> Result<ProviderResponse, LlmError> {
+        self.responses_client.complete_raw(body).await
+    }
 }
 
 pub fn create_openrouter_client_from_builder<State, Ctx>(
@@ -272,16 +418,56 @@ pub fn create_openrouter_client_from_builder<State, Ctx>(
     let mut config = OpenRouterConfig::new(api_key);
 
     if let Some(http_config) = builder.get_http_config() {
-        config = config.with_http_config(http_config.clone());
+        config = config.with_http_config(http_config);
     }
 
     if let Some(inspector_config) = builder.get_inspector_config() {
         config = config.with_inspector_config(inspector_config.clone());
     }
 
+    if let Some(options) = builder.get_openrouter_options() {
+        config = config.with_options(options.clone());
+    }
+
+    if let Some(tool_calling_config) = builder.get_tool_calling_config() {
+        config = config.with_tool_calling_config(tool_calling_config.clone());
+    }
+
     let client = ResponsesClient::new(config)?;
 
     Ok(OpenRouterClient {
         responses_client: client,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_options_serialize_into_request_body() {
+        let config = OpenRouterConfig::new("test-key".to_string()).with_options(
+            OpenRouterOptions::new()
+                .with_fallback_models(["anthropic/claude-3.5-haiku", "google/gemini-2.5-flash"])
+                .with_provider_order(["openai", "azure"])
+                .allow_fallbacks(false)
+                .with_transforms(["middle-out"]),
+        );
+
+        assert_eq!(
+            serde_json::Value::Object(config.extra_body()),
+            json!({
+                "models": ["anthropic/claude-3.5-haiku", "google/gemini-2.5-flash"],
+                "provider": { "order": ["openai", "azure"], "allow_fallbacks": false },
+                "transforms": ["middle-out"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_default_options_add_nothing() {
+        let config = OpenRouterConfig::new("test-key".to_string());
+        assert!(config.extra_body().is_empty());
+    }
+}
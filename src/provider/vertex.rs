@@ -0,0 +1,556 @@
+//! Vertex AI authentication for the Gemini provider.
+//!
+//! Vertex serves Gemini under a project and region and authenticates with short-lived OAuth
+//! access tokens instead of an API key. [`VertexConfig`] holds the project, the region and a
+//! token source that mints tokens from [`VertexCredentials`] and refreshes them shortly before
+//! they expire.
+//!
+//! Signing service-account tokens requires the `vertex` feature.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(feature = "vertex")]
+use std::time::SystemTime;
+#[cfg(feature = "vertex")]
+use std::time::UNIX_EPOCH;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::core::LlmError;
+use crate::core::credentials::{CredentialsProvider, ExpiringToken, TokenCache};
+
+const TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const METADATA_TOKEN_URI: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+#[cfg(feature = "vertex")]
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Tokens are refreshed when they expire within this margin.
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// Where Vertex access tokens come from.
+#[derive(Clone)]
+pub enum VertexCredentials {
+    /// A service-account key file, signed locally into a JWT bearer grant
+    #[cfg(feature = "vertex")]
+    ServiceAccount(ServiceAccount),
+    /// User credentials from `gcloud auth application-default login`
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+    /// The metadata server of a GCE VM, Cloud Run service or GKE workload
+    MetadataServer,
+}
+
+impl std::fmt::Debug for VertexCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "vertex")]
+            VertexCredentials::ServiceAccount(account) => f
+                .debug_tuple("ServiceAccount")
+                .field(&account.client_email)
+                .finish(),
+            VertexCredentials::AuthorizedUser { client_id, .. } => f
+                .debug_struct("AuthorizedUser")
+                .field("client_id", client_id)
+                .finish_non_exhaustive(),
+            VertexCredentials::MetadataServer => f.write_str("MetadataServer"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CredentialsFile {
+    #[serde(rename = "type")]
+    r#type: String,
+    #[cfg_attr(not(feature = "vertex"), allow(dead_code))]
+    client_email: Option<String>,
+    #[cfg_attr(not(feature = "vertex"), allow(dead_code))]
+    private_key: Option<String>,
+    #[cfg_attr(not(feature = "vertex"), allow(dead_code))]
+    token_uri: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+}
+
+impl VertexCredentials {
+    /// Parse a `service_account` or `authorized_user` credentials JSON file.
+    pub fn from_json(json: &str) -> Result<Self, LlmError> {
+        let file: CredentialsFile = serde_json::from_str(json).map_err(|e| LlmError::Parse {
+            message: "Invalid Google credentials JSON".to_string(),
+            source: Box::new(e),
+        })?;
+        let missing = |field: &str| {
+            LlmError::ProviderConfiguration(format!(
+                "Google {} credentials are missing '{field}'",
+                file.r#type
+            ))
+        };
+
+        match file.r#type.as_str() {
+            #[cfg(feature = "vertex")]
+            "service_account" => Ok(VertexCredentials::ServiceAccount(ServiceAccount::new(
+                file.client_email
+                    .clone()
+                    .ok_or_else(|| missing("client_email"))?,
+                file.private_key
+                    .as_deref()
+                    .ok_or_else(|| missing("private_key"))?,
+                file.token_uri.clone(),
+            )?)),
+            #[cfg(not(feature = "vertex"))]
+            "service_account" => Err(LlmError::ProviderConfiguration(
+                "Service-account credentials require the `vertex` feature".to_string(),
+            )),
+            "authorized_user" => Ok(VertexCredentials::AuthorizedUser {
+                client_id: file.client_id.clone().ok_or_else(|| missing("client_id"))?,
+                client_secret: file
+                    .client_secret
+                    .clone()
+                    .ok_or_else(|| missing("client_secret"))?,
+                refresh_token: file
+                    .refresh_token
+                    .clone()
+                    .ok_or_else(|| missing("refresh_token"))?,
+            }),
+            other => Err(LlmError::ProviderConfiguration(format!(
+                "Unsupported Google credentials type '{other}'"
+            ))),
+        }
+    }
+
+    /// Read a credentials JSON file, see [`from_json`](Self::from_json).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LlmError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            LlmError::ProviderConfiguration(format!(
+                "Failed to read Google credentials '{}': {e}",
+                path.display()
+            ))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Application Default Credentials: the file named by `GOOGLE_APPLICATION_CREDENTIALS`,
+    /// then the file written by `gcloud auth application-default login`, then the metadata
+    /// server.
+    pub fn application_default() -> Result<Self, LlmError> {
+        if let Ok(path) = std::env::var("GOOGLE_APPLICATION_CREDENTIALS") {
+            return Self::from_file(path);
+        }
+        match gcloud_credentials_path().filter(|path| path.is_file()) {
+            Some(path) => Self::from_file(path),
+            None => Ok(VertexCredentials::MetadataServer),
+        }
+    }
+}
+
+fn gcloud_credentials_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(windows) {
+        PathBuf::from(std::env::var_os("APPDATA")?)
+    } else {
+        PathBuf::from(std::env::var_os("HOME")?).join(".config")
+    };
+    Some(
+        config_dir
+            .join("gcloud")
+            .join("application_default_credentials.json"),
+    )
+}
+
+/// A parsed service-account key.
+#[cfg(feature = "vertex")]
+#[derive(Clone)]
+pub struct ServiceAccount {
+    client_email: String,
+    token_uri: String,
+    key_pair: Arc<ring::signature::RsaKeyPair>,
+}
+
+#[cfg(feature = "vertex")]
+impl ServiceAccount {
+    fn new(
+        client_email: String,
+        private_key_pem: &str,
+        token_uri: Option<String>,
+    ) -> Result<Self, LlmError> {
+        use base64::Engine;
+
+        let der: String = private_key_pem
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(der.trim())
+            .map_err(|e| LlmError::Parse {
+                message: "Invalid service-account private key".to_string(),
+                source: Box::new(e),
+            })?;
+        let key_pair = ring::signature::RsaKeyPair::from_pkcs8(&der).map_err(|e| {
+            LlmError::ProviderConfiguration(format!("Invalid service-account private key: {e}"))
+        })?;
+
+        Ok(Self {
+            client_email,
+            token_uri: token_uri.unwrap_or_else(|| TOKEN_URI.to_string()),
+            key_pair: Arc::new(key_pair),
+        })
+    }
+
+    /// The email of the service account
+    pub fn client_email(&self) -> &str {
+        &self.client_email
+    }
+
+    /// A signed JWT asserting this account, exchanged for an access token at `token_uri`.
+    fn assertion(&self, now: u64) -> Result<String, LlmError> {
+        use base64::Engine;
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            serde_json::json!({
+                "iss": self.client_email,
+                "scope": SCOPE,
+                "aud": self.token_uri,
+                "iat": now,
+                "exp": now + 3600,
+            })
+            .to_string(),
+        );
+        let message = format!("{header}.{claims}");
+
+        let mut signature = vec![0; self.key_pair.public().modulus_len()];
+        self.key_pair
+            .sign(
+                &ring::signature::RSA_PKCS1_SHA256,
+                &ring::rand::SystemRandom::new(),
+                message.as_bytes(),
+                &mut signature,
+            )
+            .map_err(|e| {
+                LlmError::ProviderConfiguration(format!("Failed to sign service-account JWT: {e}"))
+            })?;
+
+        Ok(format!("{message}.{}", URL_SAFE_NO_PAD.encode(signature)))
+    }
+}
+
+/// Project, region and credentials of a Vertex AI deployment of Gemini.
+///
+/// Clones share the cached access token.
+///
+/// # Example
+/// ```no_run
+/// # use rsai::{llm, ChatRole, Message, Provider, TextResponse, VertexConfig, VertexCredentials};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let vertex = VertexConfig::new(
+///     "my-project",
+///     "europe-west4",
+///     VertexCredentials::application_default()?,
+/// );
+///
+/// let answer = llm::with(Provider::Gemini)
+///     .vertex(vertex)?
+///     .model("gemini-2.5-flash")
+///     .messages(vec![Message::new(ChatRole::User, "Hello")])
+///     .complete::<TextResponse>()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct VertexConfig {
+    pub project: String,
+    /// Region such as `us-central1`, or `global`
+    pub location: String,
+    tokens: Arc<TokenSource>,
+}
+
+impl VertexConfig {
+    pub fn new(
+        project: impl Into<String>,
+        location: impl Into<String>,
+        credentials: VertexCredentials,
+    ) -> Self {
+        Self {
+            project: project.into(),
+            location: location.into(),
+            tokens: Arc::new(TokenSource::new(credentials)),
+        }
+    }
+
+    /// Fetch tokens from `uri` instead of Google's endpoint, e.g. for tests.
+    pub fn with_token_uri(self, uri: impl Into<String>) -> Self {
+        Self {
+            tokens: Arc::new(TokenSource {
+                token_uri: Some(uri.into()),
+                ..TokenSource::new(self.tokens.credentials.clone())
+            }),
+            ..self
+        }
+    }
+
+    /// The regional endpoint, up to and including the location.
+    pub fn base_url(&self) -> String {
+        let host = if self.location == "global" {
+            "aiplatform.googleapis.com".to_string()
+        } else {
+            format!("{}-aiplatform.googleapis.com", self.location)
+        };
+        format!(
+            "https://{host}/v1/projects/{}/locations/{}",
+            self.project, self.location
+        )
+    }
+
+    /// The full resource name of a Google model, e.g. for cached contents.
+    pub(crate) fn model_name(&self, model: &str) -> String {
+        let model = model.strip_prefix("models/").unwrap_or(model);
+        format!(
+            "projects/{}/locations/{}/publishers/google/models/{model}",
+            self.project, self.location
+        )
+    }
+
+    /// A valid access token, refreshed if it expires within five minutes.
+    pub async fn access_token(&self) -> Result<String, LlmError> {
+        self.tokens.access_token().await
+    }
+}
+
+/// Bearer authentication with the access token, e.g. for other Google APIs behind
+/// [`LlmBuilder::credentials`](crate::LlmBuilder::credentials).
+#[async_trait]
+impl CredentialsProvider for VertexConfig {
+    async fn auth_headers(&self) -> Result<Vec<(String, String)>, LlmError> {
+        Ok(vec![(
+            "Authorization".to_string(),
+            format!("Bearer {}", self.access_token().await?),
+        )])
+    }
+}
+
+struct TokenSource {
+    credentials: VertexCredentials,
+    /// Overrides the credentials' token endpoint
+    token_uri: Option<String>,
+    http: reqwest::Client,
+    cache: TokenCache,
+}
+
+impl std::fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenSource")
+            .field("credentials", &self.credentials)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+impl TokenSource {
+    fn new(credentials: VertexCredentials) -> Self {
+        Self {
+            credentials,
+            token_uri: None,
+            http: reqwest::Client::new(),
+            cache: TokenCache::new(REFRESH_MARGIN),
+        }
+    }
+
+    async fn access_token(&self) -> Result<String, LlmError> {
+        self.cache
+            .get_or_refresh(|| async {
+                let response = self.fetch().await?;
+                Ok(ExpiringToken::new(
+                    response.access_token,
+                    Duration::from_secs(response.expires_in),
+                ))
+            })
+            .await
+    }
+
+    async fn fetch(&self) -> Result<TokenResponse, LlmError> {
+        let request = match &self.credentials {
+            #[cfg(feature = "vertex")]
+            VertexCredentials::ServiceAccount(account) => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let uri = self.token_uri.as_deref().unwrap_or(&account.token_uri);
+                self.http.post(uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", &account.assertion(now)?),
+                ])
+            }
+            VertexCredentials::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => self
+                .http
+                .post(self.token_uri.as_deref().unwrap_or(TOKEN_URI))
+                .form(&[
+                    ("grant_type", "refresh_token"),
+                    ("client_id", client_id),
+                    ("client_secret", client_secret),
+                    ("refresh_token", refresh_token),
+                ]),
+            VertexCredentials::MetadataServer => self
+                .http
+                .get(self.token_uri.as_deref().unwrap_or(METADATA_TOKEN_URI))
+                .header("Metadata-Flavor", "Google"),
+        };
+
+        let response = request.send().await.map_err(|e| LlmError::Network {
+            message: "Failed to fetch a Google access token".to_string(),
+            source: Box::new(e),
+        })?;
+        let status = response.status();
+        let body = response.text().await.map_err(|e| LlmError::Network {
+            message: "Failed to read the Google token response".to_string(),
+            source: Box::new(e),
+        })?;
+        if !status.is_success() {
+            return Err(LlmError::Api {
+                message: format!("Google token request failed: {body}"),
+                status_code: Some(status.as_u16()),
+                source: None,
+            });
+        }
+
+        serde_json::from_str(&body).map_err(|e| LlmError::Parse {
+            message: "Invalid Google token response".to_string(),
+            source: Box::new(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_string_contains, method, path},
+    };
+
+    fn authorized_user() -> VertexCredentials {
+        VertexCredentials::from_json(
+            &json!({
+                "type": "authorized_user",
+                "client_id": "client",
+                "client_secret": "secret",
+                "refresh_token": "refresh"
+            })
+            .to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_base_url_uses_the_regional_endpoint() {
+        let config = VertexConfig::new("proj", "us-central1", VertexCredentials::MetadataServer);
+        assert_eq!(
+            config.base_url(),
+            "https://us-central1-aiplatform.googleapis.com/v1/projects/proj/locations/us-central1"
+        );
+
+        let config = VertexConfig::new("proj", "global", VertexCredentials::MetadataServer);
+        assert_eq!(
+            config.base_url(),
+            "https://aiplatform.googleapis.com/v1/projects/proj/locations/global"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_access_token_is_cached_until_near_expiry() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.first",
+                "expires_in": 3599,
+                "token_type": "Bearer"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let config = VertexConfig::new("proj", "us-central1", authorized_user())
+            .with_token_uri(format!("{}/token", server.uri()));
+
+        assert_eq!(config.access_token().await.unwrap(), "ya29.first");
+        assert_eq!(config.clone().access_token().await.unwrap(), "ya29.first");
+    }
+
+    #[tokio::test]
+    async fn test_access_token_is_refreshed_when_about_to_expire() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.short",
+                "expires_in": 60
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let config = VertexConfig::new("proj", "us-central1", authorized_user())
+            .with_token_uri(format!("{}/token", server.uri()));
+
+        config.access_token().await.unwrap();
+        config.access_token().await.unwrap();
+    }
+
+    #[cfg(feature = "vertex")]
+    #[tokio::test]
+    async fn test_service_account_exchanges_a_signed_jwt() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/token"))
+            .and(body_string_contains(
+                "grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer",
+            ))
+            .and(body_string_contains("assertion=eyJ"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "access_token": "ya29.service",
+                "expires_in": 3599
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let credentials = VertexCredentials::from_json(
+            &json!({
+                "type": "service_account",
+                "client_email": "rsai@proj.iam.gserviceaccount.com",
+                "private_key": include_str!("../../tests/fixtures/service_account_key.pem"),
+                "token_uri": format!("{}/token", server.uri())
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let config = VertexConfig::new("proj", "us-central1", credentials);
+
+        assert_eq!(config.access_token().await.unwrap(), "ya29.service");
+    }
+}
@@ -10,14 +10,17 @@
 use crate::{
     CompletionTarget, Provider,
     core::{
-        ChatRole, ConversationMessage, HttpClient, InspectorConfig, LlmError, StructuredRequest,
-        Tool, ToolCall, ToolCallingGuard, ToolRegistry,
+        ChatRole, ConversationMessage, HttpClient, HttpMethod, InspectorConfig, LanguageModelUsage,
+        LlmError, ProviderResponse, StructuredRequest, Tool, ToolCall, ToolCallingGuard,
+        ToolRegistry,
+        hash::fnv1a,
+        http::{IDEMPOTENCY_KEY_HEADER, generate_idempotency_key},
     },
     responses::{
         Format, FormatType, FunctionToolCall, FunctionToolCallOutput, JsonSchema, JsonSchemaType,
         TextType,
         request::{InputItem, InputMessage, InputMessageRole, Request},
-        response::{MessageContent, OutputContent, Response},
+        response::{Annotation, MessageContent, OutputContent, Response, Usage},
     },
 };
 use schemars::schema_for;
@@ -37,7 +40,8 @@ pub trait ResponsesProviderConfig {
     /// API endpoint for responses (e.g., `/v1/responses`)
     fn endpoint(&self) -> &str;
 
-    /// Authentication header as (header_name, header_value) tuple
+    /// Authentication header as (header_name, header_value) tuple. Not sent when the HTTP
+    /// config has [credentials](HttpClientConfig::credentials), which authenticate instead.
     fn auth_header(&self) -> (String, String);
 
     /// Additional headers to include with each request
@@ -58,6 +62,16 @@ pub trait ResponsesProviderConfig {
     fn inspector_config(&self) -> Option<&InspectorConfig> {
         None
     }
+
+    /// Whether the provider should store responses, `None` for the provider default
+    fn store(&self) -> Option<bool> {
+        None
+    }
+
+    /// Provider-specific top-level fields to merge into every request body
+    fn extra_body(&self) -> serde_json::Map<String, serde_json::Value> {
+        serde_json::Map::new()
+    }
 }
 
 /// Shared client for providers using the OpenAI-style responses API
@@ -78,6 +92,33 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         Ok(Self { config, http })
     }
 
+    /// The authentication and extra headers of the provider. The authentication header is
+    /// left out when [credentials](HttpClientConfig::credentials) are set.
+    fn headers(&self) -> Vec<(String, String)> {
+        let auth = self
+            .config
+            .http_config()
+            .credentials
+            .is_none()
+            .then(|| self.config.auth_header());
+        auth.into_iter()
+            .chain(self.config.extra_headers())
+            .collect()
+    }
+
+    /// The idempotency key for a generation request with `body`, if enabled. A fixed key is
+    /// combined with a digest of the body, so only a repeat of the same request reuses it.
+    fn idempotency_key(&self, body: &serde_json::Value) -> Option<String> {
+        let http_config = self.config.http_config();
+        if !http_config.idempotency_keys {
+            return None;
+        }
+        Some(match http_config.idempotency_key {
+            Some(key) => format!("{key}-{:016x}", fnv1a(body.to_string().as_bytes())),
+            None => generate_idempotency_key(),
+        })
+    }
+
     /// Make an API request to the responses endpoint
     #[tracing::instrument(
         name = "http_request",
@@ -88,14 +129,54 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         ),
         err
     )]
-    pub async fn make_api_request(&self, request: Request) -> Result<Response, LlmError> {
+    pub async fn make_api_request<Req: serde::Serialize>(
+        &self,
+        request: Req,
+    ) -> Result<Response, LlmError> {
         let url = format!("{}{}", self.config.base_url(), self.config.endpoint());
 
-        // Build headers
-        let mut headers = vec![self.config.auth_header()];
-        headers.extend(self.config.extra_headers());
+        let mut headers = self.headers();
+        let body = serde_json::to_value(&request).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize request".to_string(),
+            source: Box::new(e),
+        })?;
+        if let Some(key) = self.idempotency_key(&body) {
+            headers.push((IDEMPOTENCY_KEY_HEADER.to_string(), key));
+        }
+
+        let (mut response, idempotency_key): (Response, _) = self
+            .http
+            .post_json_idempotent(&url, &headers, &body)
+            .await?;
+        response.idempotency_key = idempotency_key;
+        Ok(response)
+    }
+
+    /// Send `body` unchanged to the responses endpoint and convert the response, for request
+    /// fields rsai doesn't model yet.
+    pub async fn complete_raw(
+        &self,
+        body: serde_json::Value,
+    ) -> Result<ProviderResponse, LlmError> {
+        let response = self.make_api_request(body).await?;
+        convert_to_provider_response(response, self.config.provider())
+    }
+
+    /// Make a request to a path relative to the base URL, e.g. to manage stored responses.
+    pub async fn request_json<Req, Res>(
+        &self,
+        method: HttpMethod,
+        path: &str,
+        body: Option<&Req>,
+    ) -> Result<Res, LlmError>
+    where
+        Req: serde::Serialize,
+        Res: serde::de::DeserializeOwned,
+    {
+        let url = format!("{}{}", self.config.base_url(), path);
 
-        self.http.post_json(&url, &headers, &request).await
+        let headers = self.headers();
+        self.http.send_json(method, &url, &headers, body).await
     }
 
     /// Handle the complete tool calling loop until a final response is received
@@ -112,18 +193,19 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
     {
         let timeout_duration = guard.timeout;
 
-        // Use tokio::time::timeout to add timeout protection
-        match tokio::time::timeout(
+        let error = match crate::core::runtime::timeout(
             timeout_duration,
             self.handle_tool_calling_loop_internal::<T, Ctx>(request, tool_registry, guard, format),
         )
         .await
-        {
-            Ok(result) => result,
-            Err(_) => Err(LlmError::ToolCallTimeout {
-                timeout: timeout_duration,
-            }),
-        }
+        .unwrap_or(Err(LlmError::ToolCallTimeout {
+            timeout: timeout_duration,
+        })) {
+            Ok(output) => return Ok(output),
+            Err(error) => error,
+        };
+        guard.failed(&error).await;
+        Err(error)
     }
 
     /// Internal implementation of the tool calling loop without timeout wrapper
@@ -148,7 +230,8 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         T: CompletionTarget,
         Ctx: Send + Sync + 'static,
     {
-        let mut responses_input = convert_messages_to_responses_format(request.messages.clone())?;
+        let mut responses_input =
+            convert_messages_to_responses_format(guard.start(&request.messages))?;
         let is_parallel = request
             .tool_config
             .as_ref()
@@ -162,10 +245,12 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
             let iteration_span =
                 tracing::debug_span!("tool_loop_iteration", iteration = guard.current_iteration());
             let _enter = iteration_span.enter();
+            guard.iteration_started().await;
 
             let responses_request =
                 self.build_request_with_format(&request, &responses_input, format.clone())?;
             let api_response = self.make_api_request(responses_request).await?;
+            guard.add_usage(&language_model_usage(&api_response.usage));
 
             let function_calls = self.extract_function_calls(&api_response);
 
@@ -173,6 +258,7 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
                 tracing::debug!("No more tool calls, returning final response");
                 let provider_response =
                     convert_to_provider_response(api_response, self.config.provider())?;
+                guard.finished(&provider_response).await;
                 return T::parse_response(provider_response);
             }
 
@@ -180,14 +266,20 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
                 count = function_calls.len(),
                 "Model requested tool execution"
             );
+            if let Some(text) = self.extract_text(&api_response) {
+                guard.hooks.thought(&text).await;
+            }
 
             self.process_function_calls(
                 &function_calls,
                 &mut responses_input,
                 tool_registry,
+                guard,
                 is_parallel,
             )
             .await?;
+            guard.iteration_finished().await;
+            guard.check_stop()?;
         }
     }
 
@@ -198,7 +290,25 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         responses_input: &[InputItem],
         format: Format,
     ) -> Result<Request, LlmError> {
-        build_request_payload_with_format(request, responses_input, format)
+        let mut req = build_request_payload_with_format(request, responses_input, format)?;
+        req.store = self.config.store();
+        req.extra_body = self.config.extra_body();
+        Ok(req)
+    }
+
+    /// The serialized request for `request` without tool results, as the first request of a
+    /// completion would send it.
+    pub fn request_body(
+        &self,
+        request: &StructuredRequest,
+        format: Format,
+    ) -> Result<serde_json::Value, LlmError> {
+        let input = convert_messages_to_responses_format(request.messages.clone())?;
+        let req = self.build_request_with_format(request, &input, format)?;
+        serde_json::to_value(req).map_err(|e| LlmError::Parse {
+            message: "Failed to serialize request".to_string(),
+            source: Box::new(e),
+        })
     }
 
     /// Extract function calls from API response
@@ -216,23 +326,53 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
             .collect()
     }
 
+    /// Extract the text of output messages, e.g. reasoning written alongside function calls
+    pub fn extract_text(&self, api_response: &Response) -> Option<String> {
+        let text: String = api_response
+            .output
+            .iter()
+            .filter_map(|output| match output {
+                OutputContent::OutputMessage(message) => Some(&message.content),
+                OutputContent::FunctionCall(_) => None,
+            })
+            .flatten()
+            .filter_map(|content| match content {
+                MessageContent::OutputText(output) => Some(output.text.as_str()),
+                MessageContent::Refusal(_) => None,
+            })
+            .collect();
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
     /// Process function calls either in parallel or sequentially
     pub async fn process_function_calls<Ctx>(
         &self,
         function_calls: &[&FunctionToolCall],
         responses_input: &mut Vec<InputItem>,
         tool_registry: &ToolRegistry<Ctx>,
+        guard: &mut ToolCallingGuard,
         is_parallel: bool,
     ) -> Result<(), LlmError>
     where
         Ctx: Send + Sync + 'static,
     {
         if is_parallel && function_calls.len() > 1 {
-            self.process_parallel_function_calls(function_calls, responses_input, tool_registry)
-                .await
+            self.process_parallel_function_calls(
+                function_calls,
+                responses_input,
+                tool_registry,
+                guard,
+            )
+            .await
         } else {
-            self.process_sequential_function_calls(function_calls, responses_input, tool_registry)
-                .await
+            self.process_sequential_function_calls(
+                function_calls,
+                responses_input,
+                tool_registry,
+                guard,
+            )
+            .await
         }
     }
 
@@ -242,6 +382,7 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         function_calls: &[&FunctionToolCall],
         responses_input: &mut Vec<InputItem>,
         tool_registry: &ToolRegistry<Ctx>,
+        guard: &mut ToolCallingGuard,
     ) -> Result<(), LlmError>
     where
         Ctx: Send + Sync + 'static,
@@ -256,7 +397,7 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
             pending_executions.push((
                 function_call.id.clone(),
                 function_call.call_id.clone(),
-                function_call.name.clone(),
+                tool_registry.resolve_name(&function_call.name),
                 arguments,
             ));
         }
@@ -269,7 +410,7 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
                 name,
                 arguments,
             };
-            let result = tool_registry.execute(&tool_call).await?;
+            let result = guard.execute_tool(tool_registry, &tool_call).await?;
 
             responses_input.push(InputItem::FunctionCallOutput(FunctionToolCallOutput {
                 call_id,
@@ -287,6 +428,7 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
         function_calls: &[&FunctionToolCall],
         responses_input: &mut Vec<InputItem>,
         tool_registry: &ToolRegistry<Ctx>,
+        guard: &mut ToolCallingGuard,
     ) -> Result<(), LlmError>
     where
         Ctx: Send + Sync + 'static,
@@ -298,11 +440,11 @@ impl<P: ResponsesProviderConfig> ResponsesClient<P> {
             let tool_call = ToolCall {
                 id: function_call.id.clone(),
                 call_id: function_call.call_id.clone(),
-                name: function_call.name.clone(),
+                name: tool_registry.resolve_name(&function_call.name),
                 arguments,
             };
 
-            let result = tool_registry.execute(&tool_call).await?;
+            let result = guard.execute_tool(tool_registry, &tool_call).await?;
 
             responses_input.push(InputItem::FunctionCallOutput(FunctionToolCallOutput {
                 call_id: function_call.call_id.clone(),
@@ -347,11 +489,13 @@ pub(crate) fn build_request_payload_with_format(
         instructions: None,
         max_output_tokens: None,
         max_tool_calls: None,
+        background: None,
         store: None,
         top_logprobs: None,
         top_p: None,
         truncation: None,
         user: None,
+        extra_body: serde_json::Map::new(),
     };
 
     // Apply tool configuration if present
@@ -397,7 +541,7 @@ pub(crate) fn create_function_tool(tool: &Tool) -> crate::responses::Tool {
     }
 
     crate::responses::Tool {
-        name: tool.name.clone(),
+        name: crate::core::wire_tool_name(&tool.name),
         parameters,
         strict: Some(strict),
         description: tool.description.clone(),
@@ -411,19 +555,24 @@ pub(crate) fn convert_messages_to_responses_format(
     messages
         .into_iter()
         .map(|msg| match msg {
-            ConversationMessage::Chat(m) => Ok(InputItem::Message(InputMessage {
-                role: match m.role {
-                    ChatRole::System => InputMessageRole::System,
-                    ChatRole::User => InputMessageRole::User,
-                    ChatRole::Assistant => InputMessageRole::Assistant,
-                },
-                content: m.content,
-            })),
+            ConversationMessage::Chat(m) => {
+                if let Some(name) = &m.name {
+                    tracing::debug!("Dropping message name '{name}': the Responses API has none");
+                }
+                Ok(InputItem::Message(InputMessage {
+                    role: match m.role {
+                        ChatRole::System => InputMessageRole::System,
+                        ChatRole::User => InputMessageRole::User,
+                        ChatRole::Assistant => InputMessageRole::Assistant,
+                    },
+                    content: m.content,
+                }))
+            }
             ConversationMessage::ToolCall(tc) => Ok(InputItem::FunctionCall(FunctionToolCall {
                 r#type: "function_call".to_string(),
                 id: tc.id,
                 call_id: tc.call_id,
-                name: tc.name,
+                name: crate::core::wire_tool_name(&tc.name),
                 arguments: serde_json::Value::String(
                     serde_json::to_string(&tc.arguments).map_err(|e| LlmError::Parse {
                         message: "Failed to serialize tool call arguments".to_string(),
@@ -476,6 +625,8 @@ pub(crate) fn create_format_from_value(
         })?
         .to_owned();
 
+    schema_value = crate::core::strict_schema(schema_value);
+
     let needs_wrapping = schema_value
         .get("type")
         .and_then(|t| t.as_str())
@@ -483,6 +634,12 @@ pub(crate) fn create_format_from_value(
         .unwrap_or(false);
 
     if needs_wrapping {
+        // References are resolved against the root, so definitions move to the wrapper
+        let defs = schema_value
+            .as_object_mut()
+            .and_then(|obj| obj.remove("$defs"));
+        crate::core::rewrite_root_refs(&mut schema_value, "#/properties/value");
+
         schema_value = serde_json::json!({
             "type": "object",
             "properties": {
@@ -491,6 +648,10 @@ pub(crate) fn create_format_from_value(
             "required": ["value"],
             "additionalProperties": false
         });
+
+        if let (Some(defs), Some(obj)) = (defs, schema_value.as_object_mut()) {
+            obj.insert("$defs".to_string(), defs);
+        }
     }
 
     Ok(Format {
@@ -515,13 +676,14 @@ pub fn convert_to_provider_response(
     res: Response,
     provider: crate::provider::Provider,
 ) -> Result<crate::core::ProviderResponse, LlmError> {
-    use crate::core::{FunctionCallData, LanguageModelUsage, ProviderResponse, ResponseContent};
+    use crate::core::{FunctionCallData, ProviderResponse, ResponseContent};
 
     let output_content = res.output.first().ok_or_else(|| LlmError::Provider {
         message: "No output in response".to_string(),
         source: None,
     })?;
 
+    let mut citations = Vec::new();
     let content = match output_content {
         OutputContent::OutputMessage(message) => {
             let msg_content = message.content.first().ok_or_else(|| LlmError::Provider {
@@ -530,7 +692,14 @@ pub fn convert_to_provider_response(
             })?;
 
             match msg_content {
-                MessageContent::OutputText(output) => ResponseContent::Text(output.text.clone()),
+                MessageContent::OutputText(output) => {
+                    citations = output
+                        .annotations
+                        .iter()
+                        .filter_map(Annotation::citation)
+                        .collect();
+                    ResponseContent::Text(output.text.clone())
+                }
                 MessageContent::Refusal(refusal) => {
                     ResponseContent::Refusal(refusal.refusal.clone())
                 }
@@ -564,19 +733,26 @@ pub fn convert_to_provider_response(
         }
     };
 
+    let usage = language_model_usage(&res.usage);
     Ok(ProviderResponse {
-        id: res.id,
-        model: res.model,
-        provider,
-        content,
-        usage: LanguageModelUsage {
-            prompt_tokens: res.usage.input_tokens,
-            completion_tokens: res.usage.output_tokens,
-            total_tokens: res.usage.total_tokens,
-        },
+        citations,
+        idempotency_key: res.idempotency_key,
+        ..ProviderResponse::new(res.id, res.model, provider, content, usage)
     })
 }
 
+fn language_model_usage(usage: &Usage) -> LanguageModelUsage {
+    LanguageModelUsage {
+        prompt_tokens: usage.input_tokens,
+        completion_tokens: usage.output_tokens,
+        total_tokens: usage.total_tokens,
+        cached_tokens: usage
+            .input_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -595,6 +771,7 @@ mod tests {
     struct TestProviderConfig {
         base_url: String,
         max_retries: u32,
+        idempotency_key: Option<String>,
     }
 
     impl TestProviderConfig {
@@ -602,6 +779,7 @@ mod tests {
             Self {
                 base_url,
                 max_retries: 3,
+                idempotency_key: None,
             }
         }
     }
@@ -629,6 +807,8 @@ mod tests {
                 max_retries: self.max_retries,
                 initial_retry_delay: Duration::from_millis(10), // Fast retries for tests
                 max_retry_delay: Duration::from_millis(100),
+                idempotency_key: self.idempotency_key.clone(),
+                ..Default::default()
             }
         }
     }
@@ -652,11 +832,13 @@ mod tests {
             instructions: None,
             max_output_tokens: None,
             max_tool_calls: None,
+            background: None,
             store: None,
             top_logprobs: None,
             top_p: None,
             truncation: None,
             user: None,
+            extra_body: serde_json::Map::new(),
         }
     }
 
@@ -714,6 +896,92 @@ mod tests {
         assert!(result.is_ok(), "Client should succeed after retries");
     }
 
+    #[tokio::test]
+    async fn test_retries_reuse_the_idempotency_key() {
+        let server = MockServer::start().await;
+        let client = create_client(&server).await;
+
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "resp_123",
+                "model": "test-model",
+                "output": [],
+                "usage": { "input_tokens": 1, "output_tokens": 1, "total_tokens": 2 }
+            })))
+            .mount(&server)
+            .await;
+
+        let first = client
+            .make_api_request(create_basic_request())
+            .await
+            .unwrap();
+        let second = client
+            .make_api_request(create_basic_request())
+            .await
+            .unwrap();
+
+        let keys = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| {
+                request.headers["idempotency-key"]
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+        assert_eq!(first.idempotency_key.as_deref(), Some(keys[1].as_str()));
+        assert_eq!(second.idempotency_key.as_deref(), Some(keys[2].as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_fixed_idempotency_keys_are_derived_per_body() {
+        let server = MockServer::start().await;
+        let client = ResponsesClient::new(TestProviderConfig {
+            idempotency_key: Some("order-7".to_string()),
+            ..TestProviderConfig::new(server.uri())
+        })
+        .unwrap();
+        Mock::given(method("POST"))
+            .and(path("/responses"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "resp_123",
+                "model": "test-model",
+                "output": [],
+                "usage": { "input_tokens": 1, "output_tokens": 1, "total_tokens": 2 }
+            })))
+            .mount(&server)
+            .await;
+
+        let send = |model: &str| {
+            let request = Request {
+                model: model.to_string(),
+                ..create_basic_request()
+            };
+            let client = &client;
+            async move { client.make_api_request(request).await.unwrap() }
+        };
+        let first = send("test-model").await.idempotency_key.unwrap();
+        let repeated = send("test-model").await.idempotency_key.unwrap();
+        let fallback = send("fallback-model").await.idempotency_key.unwrap();
+
+        assert!(first.starts_with("order-7-"), "{first}");
+        assert_eq!(first, repeated);
+        assert_ne!(first, fallback);
+    }
+
     #[tokio::test]
     async fn test_fatal_errors_401() {
         let server = MockServer::start().await;
@@ -805,10 +1073,10 @@ mod tests {
 
         let request = StructuredRequest {
             model: "test-model".to_string(),
-            messages: vec![ConversationMessage::Chat(Message {
-                role: ChatRole::User,
-                content: "test".to_string(),
-            })],
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "test",
+            ))],
             tool_config: None,
             generation_config: None,
         };
@@ -882,6 +1150,92 @@ mod tests {
         assert_eq!(result.unwrap().content, "wrapped_success");
     }
 
+    #[tokio::test]
+    async fn test_response_parsing_cached_tokens() {
+        let server = MockServer::start().await;
+
+        let cached_response = serde_json::json!({
+            "id": "resp_cached",
+            "model": "test-model",
+            "output": [{
+                "id": "msg_cached",
+                "type": "message",
+                "status": "completed",
+                "role": "assistant",
+                "content": [{
+                    "type": "output_text",
+                    "text": "{\"value\": \"cached\"}"
+                }]
+            }],
+            "usage": {
+                "input_tokens": 2048,
+                "input_tokens_details": { "cached_tokens": 1536 },
+                "output_tokens": 10,
+                "output_tokens_details": { "reasoning_tokens": 0 },
+                "total_tokens": 2058
+            }
+        });
+
+        let result = run_parsing_test::<TestResponse>(&server, cached_response)
+            .await
+            .expect("parsed response");
+
+        assert_eq!(result.usage.cached_tokens, Some(1536));
+        assert_eq!(result.usage.cache_hit_rate(), Some(0.75));
+    }
+
+    #[tokio::test]
+    async fn test_response_parsing_annotations() {
+        let server = MockServer::start().await;
+
+        let annotated_response = serde_json::json!({
+            "id": "resp_annotated",
+            "model": "test-model",
+            "output": [{
+                "id": "msg_annotated",
+                "type": "message",
+                "status": "completed",
+                "role": "assistant",
+                "content": [{
+                    "type": "output_text",
+                    "text": "{\"value\": \"cited\"}",
+                    "annotations": [
+                        {
+                            "type": "url_citation",
+                            "url": "https://example.com/news",
+                            "title": "News",
+                            "start_index": 2,
+                            "end_index": 9
+                        },
+                        { "type": "file_citation", "file_id": "file_123", "index": 4 },
+                        { "type": "file_path", "file_id": "file_456", "index": 0 }
+                    ]
+                }]
+            }],
+            "usage": { "input_tokens": 1, "output_tokens": 1, "total_tokens": 2 }
+        });
+
+        let result = run_parsing_test::<TestResponse>(&server, annotated_response)
+            .await
+            .expect("parsed response");
+
+        assert_eq!(
+            result.metadata.citations,
+            vec![
+                crate::core::Citation {
+                    source: "https://example.com/news".to_string(),
+                    title: Some("News".to_string()),
+                    span: Some(2..9),
+                },
+                crate::core::Citation {
+                    source: "file_123".to_string(),
+                    title: None,
+                    span: None,
+                },
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_response_parsing_empty() {
         let server = MockServer::start().await;
@@ -953,10 +1307,10 @@ mod schema_tests {
     ) -> StructuredRequest {
         StructuredRequest {
             model: "gpt-4o-mini".to_string(),
-            messages: vec![ConversationMessage::Chat(Message {
-                role: ChatRole::User,
-                content: "Weather for Lisbon".to_string(),
-            })],
+            messages: vec![ConversationMessage::Chat(Message::new(
+                ChatRole::User,
+                "Weather for Lisbon",
+            ))],
             tool_config,
             generation_config,
         }
@@ -1013,6 +1367,97 @@ mod schema_tests {
         assert_eq!(enum_values, &expected);
     }
 
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct TreeNode {
+        label: String,
+        children: Vec<TreeNode>,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Forest(Vec<Forest>);
+
+    #[test]
+    fn test_recursive_schema_keeps_defs_at_wrapper_root() {
+        let format = create_format_for_type::<Vec<TreeNode>>().expect("schema");
+
+        let schema = match format.format {
+            crate::responses::FormatType::JsonSchema(schema) => schema.schema,
+            _ => panic!("expected JSON schema format"),
+        };
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(
+            schema["properties"]["value"]["items"]["$ref"],
+            "#/$defs/TreeNode"
+        );
+        assert_eq!(
+            schema["$defs"]["TreeNode"]["properties"]["children"]["items"]["$ref"],
+            "#/$defs/TreeNode"
+        );
+        assert!(schema["properties"]["value"].get("$defs").is_none());
+    }
+
+    #[test]
+    fn test_recursive_root_reference_points_into_wrapper() {
+        let format = create_format_for_type::<Forest>().expect("schema");
+
+        let schema = match format.format {
+            crate::responses::FormatType::JsonSchema(schema) => schema.schema,
+            _ => panic!("expected JSON schema format"),
+        };
+
+        assert_eq!(
+            schema["properties"]["value"]["items"]["$ref"],
+            "#/properties/value"
+        );
+    }
+
+    /// A postal address
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Address {
+        /// Street and house number
+        street: String,
+    }
+
+    #[derive(JsonSchema, Deserialize)]
+    #[allow(dead_code)]
+    struct Customer {
+        /// Where the customer lives
+        home: Address,
+        /// Where the customer works, if known
+        work: Option<Address>,
+    }
+
+    #[test]
+    fn test_field_descriptions_survive_references() {
+        let format = create_format_for_type::<Customer>().expect("schema");
+
+        let schema = match format.format {
+            crate::responses::FormatType::JsonSchema(schema) => schema.schema,
+            _ => panic!("expected JSON schema format"),
+        };
+
+        // Strict mode rejects keywords next to `$ref`
+        assert_eq!(
+            schema["properties"]["home"],
+            json!({
+                "anyOf": [{ "$ref": "#/$defs/Address" }],
+                "description": "Where the customer lives"
+            })
+        );
+        assert_eq!(
+            schema["properties"]["work"]["description"],
+            "Where the customer works, if known"
+        );
+        assert_eq!(
+            schema["$defs"]["Address"]["properties"]["street"]["description"],
+            "Street and house number"
+        );
+    }
+
     #[test]
     fn test_schema_missing_title_errors() {
         let err = create_format_from_value(json!({ "type": "object" }))
@@ -1086,6 +1531,7 @@ mod schema_tests {
             max_tokens: Some(256),
             temperature: Some(0.2),
             top_p: Some(0.9),
+            ..Default::default()
         };
 
         let request = sample_request(Some(tool_config), Some(generation_config));
@@ -22,6 +22,10 @@ pub struct Request {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tool_calls: Option<u32>,
 
+    /// Run the response asynchronously and return immediately. Requires `store`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<bool>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store: Option<bool>,
 
@@ -52,6 +56,10 @@ pub struct Request {
     /// Used to boost cache hit rates by better bucketing similar requests
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+
+    /// Provider-specific top-level fields merged into the request body
+    #[serde(flatten)]
+    pub extra_body: serde_json::Map<String, Value>,
 }
 
 /// Custom serializer for ToolChoice to match API format
@@ -110,7 +118,9 @@ impl From<crate::core::ToolChoice> for ToolChoice {
             crate::core::ToolChoice::None => ToolChoice::None,
             crate::core::ToolChoice::Auto => ToolChoice::Auto,
             crate::core::ToolChoice::Required => ToolChoice::Required,
-            crate::core::ToolChoice::Function { name } => ToolChoice::Function { name },
+            crate::core::ToolChoice::Function { name } => ToolChoice::Function {
+                name: crate::core::wire_tool_name(&name),
+            },
         }
     }
 }
@@ -188,7 +198,7 @@ struct FunctionTool {
 fn create_serializable_tool(tool: &Tool) -> SerializableTool {
     let strict = tool.strict.unwrap_or(true);
     SerializableTool::Function(FunctionTool {
-        name: tool.name.clone(),
+        name: crate::core::wire_tool_name(&tool.name),
         parameters: tool.parameters.clone(),
         strict,
         r#type: FunctionType::Function,
@@ -8,6 +8,9 @@ pub struct Response {
     pub model: String,
     pub output: Vec<OutputContent>,
     pub usage: Usage,
+    /// Key sent in the `Idempotency-Key` header of the request, set by the client
+    #[serde(skip)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -22,6 +25,21 @@ pub struct Usage {
     pub input_tokens: i32,
     pub output_tokens: i32,
     pub total_tokens: i32,
+    pub input_tokens_details: Option<InputTokensDetails>,
+    #[allow(dead_code)]
+    pub output_tokens_details: Option<OutputTokensDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InputTokensDetails {
+    /// Prompt tokens that were a prompt cache hit
+    pub cached_tokens: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputTokensDetails {
+    #[allow(dead_code)]
+    pub reasoning_tokens: Option<i32>,
 }
 
 // TODO: Remove this, once text input is supported
@@ -66,8 +84,50 @@ pub struct OutputText {
     pub r#type: String,
 
     pub text: String,
-    // TODO
-    // annotations
+
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    UrlCitation {
+        url: String,
+        title: Option<String>,
+        start_index: usize,
+        end_index: usize,
+    },
+    FileCitation {
+        file_id: String,
+        filename: Option<String>,
+    },
+    /// Annotation types that carry no source, e.g. `file_path`
+    #[serde(other)]
+    Other,
+}
+
+impl Annotation {
+    pub fn citation(&self) -> Option<crate::core::Citation> {
+        match self {
+            Annotation::UrlCitation {
+                url,
+                title,
+                start_index,
+                end_index,
+            } => Some(crate::core::Citation {
+                source: url.clone(),
+                title: title.clone(),
+                span: Some(*start_index..*end_index),
+            }),
+            Annotation::FileCitation { file_id, filename } => Some(crate::core::Citation {
+                source: filename.clone().unwrap_or_else(|| file_id.clone()),
+                title: None,
+                span: None,
+            }),
+            Annotation::Other => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
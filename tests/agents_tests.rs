@@ -0,0 +1,424 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use rsai::agents::{Agent, Handoff, PlanAndExecute, Router, StepOutcome, Thoughts, TraceEntry};
+use rsai::{
+    ApiKey, ChatRole, ConversationMessage, LlmError, Message, Provider, TextResponse, ToolRegistry,
+    ToolSet, Transport, TransportRequest, TransportResponse, agent_as_tool, llm, tool, toolset,
+};
+use serde_json::{Value, json};
+
+/// Look up the status of an invoice
+/// invoice_id: Invoice to look up
+#[tool]
+fn invoice_status(invoice_id: String) -> String {
+    format!("{invoice_id} was charged twice, refund pending")
+}
+
+/// Transport that answers with scripted responses in order and records request bodies.
+struct ScriptedTransport {
+    responses: Mutex<VecDeque<Value>>,
+    bodies: Mutex<Vec<Value>>,
+}
+
+impl ScriptedTransport {
+    fn new(responses: Vec<Value>) -> Arc<Self> {
+        Arc::new(Self {
+            responses: Mutex::new(responses.into()),
+            bodies: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+#[async_trait]
+impl Transport for ScriptedTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        self.bodies.lock().unwrap().push(request.body);
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no scripted response left");
+        Ok(TransportResponse {
+            status: 200,
+            body: response.to_string(),
+        })
+    }
+}
+
+fn tool_call(name: &str, arguments: Value) -> Value {
+    json!({
+        "id": "resp_call",
+        "model": "mock-model",
+        "output": [{
+            "type": "function_call",
+            "id": "call_1",
+            "call_id": "call_1",
+            "name": name,
+            "arguments": arguments.to_string(),
+        }],
+        "usage": usage_payload(),
+    })
+}
+
+fn text(text: &str) -> Value {
+    json!({
+        "id": "resp_text",
+        "model": "mock-model",
+        "output": [{
+            "id": "msg_1",
+            "type": "message",
+            "status": "completed",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": text }]
+        }],
+        "usage": usage_payload(),
+    })
+}
+
+fn usage_payload() -> Value {
+    json!({ "input_tokens": 10, "output_tokens": 5, "total_tokens": 15 })
+}
+
+fn user(content: &str) -> Vec<Message> {
+    vec![Message::new(ChatRole::User, content.to_string())]
+}
+
+fn agent(name: &str, transport: Arc<ScriptedTransport>) -> Agent {
+    Agent::new(
+        name,
+        Provider::OpenAI,
+        "mock-model",
+        ApiKey::Custom("test-key".to_string()),
+    )
+    .with_transport(transport)
+}
+
+#[tokio::test]
+async fn test_router_hands_off_with_shared_history() {
+    // The handoff ends triage's turn, so it is not asked for a reply after the transfer
+    let triage = ScriptedTransport::new(vec![tool_call("transfer_to_billing", json!({}))]);
+    let billing = ScriptedTransport::new(vec![
+        tool_call("invoice_status", json!({ "invoice_id": "INV-7" })),
+        text("INV-7 was charged twice, your refund is on its way"),
+    ]);
+
+    let router = Router::new()
+        .with_agent(
+            agent("triage", triage.clone())
+                .with_instructions("Route the customer")
+                .with_handoff("billing"),
+        )
+        .with_agent(
+            agent("billing", billing.clone())
+                .with_description("Handles invoices and refunds")
+                .with_instructions("Resolve billing questions")
+                .with_tools(toolset![invoice_status]),
+        );
+
+    let run = router
+        .run("triage", user("I was charged twice for INV-7"))
+        .await
+        .expect("router run should succeed");
+
+    assert_eq!(run.agent, "billing");
+    assert_eq!(
+        run.response.text,
+        "INV-7 was charged twice, your refund is on its way"
+    );
+    assert_eq!(
+        run.handoffs,
+        vec![Handoff {
+            from: "triage".to_string(),
+            to: "billing".to_string(),
+        }]
+    );
+    // The user message, both tool calls with their results, then the answer
+    assert_eq!(run.messages.len(), 6);
+    assert!(matches!(
+        &run.messages[0],
+        ConversationMessage::Chat(message) if message.content == "I was charged twice for INV-7"
+    ));
+    assert!(matches!(
+        &run.messages[1],
+        ConversationMessage::ToolCall(call) if call.name == "transfer_to_billing"
+    ));
+    assert!(matches!(
+        &run.messages[3],
+        ConversationMessage::ToolCall(call) if call.name == "invoice_status"
+    ));
+    assert!(matches!(
+        &run.messages[5],
+        ConversationMessage::Chat(message) if message.role == ChatRole::Assistant
+    ));
+    assert!(run.trace.contains(&TraceEntry::Handoff(Handoff {
+        from: "triage".to_string(),
+        to: "billing".to_string(),
+    })));
+    assert_eq!(
+        run.trace.last(),
+        Some(&TraceEntry::Answer {
+            agent: "billing".to_string(),
+            text: "INV-7 was charged twice, your refund is on its way".to_string(),
+        })
+    );
+    assert_eq!(run.tool_stats["transfer_to_billing"].calls, 1);
+    assert_eq!(run.tool_stats["invoice_status"].calls, 1);
+
+    let triage_bodies = triage.bodies.lock().unwrap();
+    assert_eq!(
+        triage_bodies[0]["input"][0]["content"],
+        "Route the customer"
+    );
+    assert_eq!(triage_bodies[0]["tools"][0]["name"], "transfer_to_billing");
+    assert_eq!(
+        triage_bodies[0]["tools"][0]["description"],
+        "Transfer the conversation to the billing agent: Handles invoices and refunds"
+    );
+
+    assert_eq!(triage_bodies.len(), 1);
+
+    // Billing answers the shared history under its own instructions, with triage's tool call
+    let billing_bodies = billing.bodies.lock().unwrap();
+    let input = billing_bodies[0]["input"].as_array().unwrap();
+    assert_eq!(input.len(), 4);
+    assert_eq!(input[0]["content"], "Resolve billing questions");
+    assert_eq!(input[1]["content"], "I was charged twice for INV-7");
+    assert_eq!(input[2]["type"], "function_call");
+    assert_eq!(input[2]["name"], "transfer_to_billing");
+    assert_eq!(input[3]["type"], "function_call_output");
+    let tools = billing_bodies[0]["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "invoice_status");
+}
+
+#[tokio::test]
+async fn test_router_limits_handoffs() {
+    let ping = ScriptedTransport::new(vec![tool_call("transfer_to_pong", json!({}))]);
+    let pong = ScriptedTransport::new(vec![tool_call("transfer_to_ping", json!({}))]);
+
+    let router = Router::new()
+        .with_agent(agent("ping", ping).with_handoff("pong"))
+        .with_agent(agent("pong", pong).with_handoff("ping"))
+        .with_max_handoffs(1);
+
+    let err = router.run("ping", user("Hello")).await.unwrap_err();
+    assert!(matches!(err, LlmError::HandoffLimit { limit: 1 }));
+
+    let err = router.run("nobody", user("Hello")).await.unwrap_err();
+    assert!(matches!(err, LlmError::Builder(_)));
+}
+
+#[tokio::test]
+async fn test_react_trace_keeps_thoughts_out_of_the_answer() {
+    let responses = || {
+        let mut call = tool_call("invoice_status", json!({ "invoice_id": "INV-7" }));
+        call["output"].as_array_mut().unwrap().insert(
+            0,
+            json!({
+                "id": "msg_thought",
+                "type": "message",
+                "status": "completed",
+                "role": "assistant",
+                "content": [{ "type": "output_text", "text": "Thought: I should check the invoice" }]
+            }),
+        );
+        vec![
+            call,
+            text("Thought: It was charged twice\nAnswer: Your refund is pending."),
+        ]
+    };
+
+    let hidden = ScriptedTransport::new(responses());
+    let run = agent("support", hidden.clone())
+        .with_tools(toolset![invoice_status])
+        .with_react(Thoughts::Hidden)
+        .run_traced(user("Why was I charged twice for INV-7?"))
+        .await
+        .expect("run should succeed");
+
+    assert_eq!(run.response.text, "Your refund is pending.");
+    assert_eq!(run.tool_stats["invoice_status"].calls, 1);
+    assert_eq!(run.tool_stats["invoice_status"].errors, 0);
+    let agent_name = || "support".to_string();
+    assert_eq!(
+        run.trace,
+        vec![
+            TraceEntry::Thought {
+                agent: agent_name(),
+                text: "I should check the invoice".to_string(),
+            },
+            TraceEntry::Action {
+                agent: agent_name(),
+                tool: "invoice_status".to_string(),
+                arguments: json!({ "invoice_id": "INV-7" }),
+            },
+            TraceEntry::Observation {
+                agent: agent_name(),
+                tool: "invoice_status".to_string(),
+                result: json!("INV-7 was charged twice, refund pending"),
+            },
+            TraceEntry::Thought {
+                agent: agent_name(),
+                text: "It was charged twice".to_string(),
+            },
+            TraceEntry::Answer {
+                agent: agent_name(),
+                text: "Your refund is pending.".to_string(),
+            },
+        ]
+    );
+    let system_prompt = hidden.bodies.lock().unwrap()[0]["input"][0]["content"].clone();
+    assert!(system_prompt.as_str().unwrap().contains("Thought:"));
+
+    let shown = agent("support", ScriptedTransport::new(responses()))
+        .with_tools(toolset![invoice_status])
+        .with_react(Thoughts::Shown)
+        .run(user("Why was I charged twice for INV-7?"))
+        .await
+        .expect("run should succeed");
+    assert_eq!(
+        shown.text,
+        "Thought: It was charged twice\nAnswer: Your refund is pending."
+    );
+}
+
+fn plan(steps: Value) -> Value {
+    text(&json!({ "steps": steps }).to_string())
+}
+
+#[tokio::test]
+async fn test_plan_and_execute_replans_after_failed_step() {
+    let transport = ScriptedTransport::new(vec![
+        plan(json!([
+            {
+                "description": "Look up the invoice",
+                "tool": "lookup_invoice",
+                "arguments": "{\"invoice_id\":\"INV-7\"}"
+            },
+            { "description": "Draft a reply", "tool": null, "arguments": null }
+        ])),
+        plan(json!([
+            {
+                "description": "Check the invoice status",
+                "tool": "invoice_status",
+                "arguments": "{\"invoice_id\":\"INV-7\"}"
+            },
+            { "description": "Draft a reply", "tool": null, "arguments": null }
+        ])),
+        text("Draft: your refund is pending"),
+        text("Your refund for INV-7 is pending."),
+    ]);
+
+    let steps_seen = Arc::new(AtomicUsize::new(0));
+    let counter = steps_seen.clone();
+    let run = PlanAndExecute::new(
+        agent("support", transport.clone()).with_tools(toolset![invoice_status]),
+    )
+    .on_step(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    })
+    .run("Why was I charged twice for INV-7?")
+    .await
+    .expect("plan should complete");
+
+    assert_eq!(run.replans, 1);
+    assert_eq!(run.answer.text, "Your refund for INV-7 is pending.");
+    assert_eq!(run.tool_stats.len(), 2);
+    assert_eq!(run.tool_stats["invoice_status"].calls, 1);
+    assert_eq!(run.tool_stats["lookup_invoice"].errors, 1);
+    assert_eq!(steps_seen.load(Ordering::SeqCst), 3);
+    assert_eq!(run.steps.len(), 3);
+    assert!(matches!(run.steps[0].outcome, StepOutcome::Failed(_)));
+    assert_eq!(
+        run.steps[1].outcome,
+        StepOutcome::Completed(json!("INV-7 was charged twice, refund pending"))
+    );
+    assert_eq!(
+        run.steps[2].outcome,
+        StepOutcome::Completed(json!("Draft: your refund is pending"))
+    );
+
+    let bodies = transport.bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 4);
+    let planner_prompt = bodies[0]["input"][0]["content"].as_str().unwrap();
+    assert!(planner_prompt.contains("invoice_status"));
+    let replan_request = bodies[1]["input"][2]["content"].as_str().unwrap();
+    assert!(replan_request.contains("failed"));
+    assert!(replan_request.contains("Look up the invoice"));
+    let final_request = bodies[3]["input"][1]["content"].as_str().unwrap();
+    assert!(final_request.contains("Draft: your refund is pending"));
+}
+
+#[tokio::test]
+async fn test_plan_and_execute_fails_when_replans_run_out() {
+    let transport = ScriptedTransport::new(vec![plan(json!([
+        { "description": "Look up the invoice", "tool": "lookup_invoice", "arguments": null }
+    ]))]);
+
+    let err = PlanAndExecute::new(agent("support", transport))
+        .with_max_replans(0)
+        .run("Why was I charged twice?")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        LlmError::PlanStep { ref step, .. } if step == "Look up the invoice"
+    ));
+}
+
+#[tokio::test]
+async fn test_agent_as_tool_delegates_to_sub_agent() {
+    let sub_transport = ScriptedTransport::new(vec![
+        tool_call("invoice_status", json!({ "invoice_id": "INV-7" })),
+        text("INV-7 was charged twice"),
+    ]);
+    let transport = sub_transport.clone();
+    let billing = agent_as_tool("billing", "Answers billing questions", move |task| {
+        Ok(llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))?
+            .model("mock-sub-model")
+            .messages(vec![
+                Message::new(ChatRole::System, "You answer billing questions."),
+                Message::new(ChatRole::User, task),
+            ])
+            .tools(toolset![invoice_status])
+            .transport(transport.clone()))
+    });
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(billing)).unwrap();
+
+    let parent_transport = ScriptedTransport::new(vec![
+        tool_call("billing", json!({ "task": "Why was INV-7 charged twice?" })),
+        text("You were charged twice; a refund is pending."),
+    ]);
+    let answer = llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Custom("test-key".to_string()))
+        .unwrap()
+        .model("mock-model")
+        .messages(user("Why was I charged twice for INV-7?"))
+        .tools(ToolSet { registry })
+        .transport(parent_transport.clone())
+        .complete::<TextResponse>()
+        .await
+        .unwrap();
+    assert_eq!(answer.text, "You were charged twice; a refund is pending.");
+
+    let parent_bodies = parent_transport.bodies.lock().unwrap();
+    let output = &parent_bodies[1]["input"][2];
+    assert_eq!(output["type"], "function_call_output");
+    assert_eq!(output["output"], "INV-7 was charged twice");
+
+    let sub_bodies = sub_transport.bodies.lock().unwrap();
+    assert_eq!(sub_bodies.len(), 2);
+    assert_eq!(sub_bodies[0]["model"], "mock-sub-model");
+    assert_eq!(
+        sub_bodies[0]["input"][1]["content"],
+        "Why was INV-7 charged twice?"
+    );
+    assert_eq!(sub_bodies[0]["tools"][0]["name"], "invoice_status");
+}
@@ -1,9 +1,13 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use async_trait::async_trait;
 use rsai::{
-    ChatRole, CompletionTarget, ConversationMessage, LlmError, LlmProvider, Message, OpenAiClient,
-    StructuredRequest, ToolCallingConfig, ToolChoice, ToolConfig, ToolSet, completion_schema, tool,
-    toolset,
+    ApiKey, ChatRole, CompletionTarget, ConversationMessage, Ctx, DuplicateCalls, LlmError,
+    LlmProvider, Message, OpenAiClient, Provider, StructuredRequest, TextResponse, ToolCache,
+    ToolCallingConfig, ToolChoice, ToolConfig, ToolLoopCheckpoint, ToolLoopEvent, ToolSet,
+    Transport, TransportRequest, TransportResponse, completion_schema, llm, tool, toolset,
 };
 use serde_json::{Value, json};
 use wiremock::{
@@ -38,6 +42,33 @@ fn multiply_values(a: i64, b: i64) -> MultiplyResponse {
     MultiplyResponse { product: a * b }
 }
 
+#[tool(hidden)]
+/// Subtract two integers.
+/// a: Minuend.
+/// b: Subtrahend.
+fn subtract_values(a: i64, b: i64) -> SumResponse {
+    SumResponse { sum: a - b }
+}
+
+/// Context counting the executions of `cached_sum`.
+#[derive(Default)]
+struct SumCalls(AtomicUsize);
+
+impl AsRef<AtomicUsize> for SumCalls {
+    fn as_ref(&self) -> &AtomicUsize {
+        &self.0
+    }
+}
+
+#[tool(cacheable)]
+/// Add two integers, reusing earlier answers.
+/// a: First addend.
+/// b: Second addend.
+fn cached_sum(calls: Ctx<&AtomicUsize>, a: i64, b: i64) -> SumResponse {
+    calls.fetch_add(1, Ordering::SeqCst);
+    SumResponse { sum: a + b }
+}
+
 #[derive(Clone)]
 struct BodyContains(&'static str);
 
@@ -258,6 +289,559 @@ async fn tool_call_timeout_triggers_error() {
     }
 }
 
+#[tokio::test]
+async fn enabled_tools_limits_exposed_tools() {
+    let transport = Arc::new(CapturingTransport::new(responses_answer("{\"sum\":3}")));
+
+    let result = llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Custom("test-key".to_string()))
+        .unwrap()
+        .model("mock-model")
+        .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+        .tools(sum_and_product_toolset())
+        .enabled_tools(&["calculate_sum"])
+        .transport(transport.clone())
+        .complete::<SumResponse>()
+        .await
+        .expect("completion should succeed");
+    assert_eq!(result.content.sum, 3);
+
+    let bodies = transport.bodies.lock().unwrap();
+    let tool_names: Vec<&str> = bodies[0]["tools"]
+        .as_array()
+        .expect("tools array")
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(tool_names, vec!["calculate_sum"]);
+}
+
+#[tokio::test]
+async fn test_tool_loop_hooks_report_progress() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyNotContains("function_call_output"))
+        .respond_with(tool_call_response(vec![function_call(
+            "call_sum",
+            "calculate_sum",
+            json!({ "a": 1, "b": 2 }),
+        )]))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyContains("function_call_output"))
+        .respond_with(final_response(json!({ "sum": 3 })))
+        .mount(&server)
+        .await;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let record = |events: &Arc<Mutex<Vec<String>>>, event: String| {
+        let events = events.clone();
+        async move { events.lock().unwrap().push(event) }
+    };
+    let config = ToolCallingConfig::default()
+        .on_iteration_start({
+            let events = events.clone();
+            move |iteration| record(&events, format!("iteration {iteration}"))
+        })
+        .on_tool_call({
+            let events = events.clone();
+            move |call| record(&events, format!("call {}", call.name))
+        })
+        .on_tool_result({
+            let events = events.clone();
+            move |call, result| record(&events, format!("result {} {}", call.name, result["sum"]))
+        })
+        .on_final({
+            let events = events.clone();
+            move |response| record(&events, format!("final {}", response.id))
+        });
+
+    let toolset = sum_toolset();
+    let tool_config = tool_config_for(&toolset, Some(false));
+    let request = build_request("Add 1 and 2", tool_config);
+
+    let client = client_for(&server, Some(config));
+    client
+        .generate_completion::<SumResponse, ()>(
+            request,
+            <SumResponse as CompletionTarget>::format().expect("format"),
+            Some(&toolset.registry),
+        )
+        .await
+        .expect("structured response");
+
+    let events = events.lock().unwrap();
+    assert_eq!(
+        *events,
+        vec![
+            "iteration 1",
+            "call calculate_sum",
+            "result calculate_sum 3",
+            "iteration 2",
+            "final resp_1",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_tool_loop_events_serialize_to_json() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyNotContains("function_call_output"))
+        .respond_with(tool_call_response(vec![
+            function_call("call_1", "calculate_sum", json!({ "a": 1, "b": 2 })),
+            function_call("call_2", "calculate_sum", json!({ "a": 1, "b": 2 })),
+        ]))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyContains("function_call_output"))
+        .respond_with(final_response(json!({ "sum": 3 })))
+        .mount(&server)
+        .await;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let config = ToolCallingConfig::default()
+        .with_duplicate_calls(DuplicateCalls::ReuseResult)
+        .on_event({
+            let events = events.clone();
+            move |event| {
+                let events = events.clone();
+                async move {
+                    events
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::to_value(event).unwrap())
+                }
+            }
+        });
+
+    let toolset = sum_toolset();
+    client_for(&server, Some(config))
+        .generate_completion::<SumResponse, ()>(
+            build_request("Add 1 and 2", tool_config_for(&toolset, Some(true))),
+            <SumResponse as CompletionTarget>::format().expect("format"),
+            Some(&toolset.registry),
+        )
+        .await
+        .expect("structured response");
+
+    let events = events.lock().unwrap();
+    let kinds = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            "iteration_started",
+            "tool_called",
+            "tool_finished",
+            "tool_called",
+            "tool_finished",
+            "iteration_started",
+            "finished",
+        ]
+    );
+    assert_eq!(events[1]["tool"], "calculate_sum");
+    assert_eq!(events[1]["arguments_hash"], events[3]["arguments_hash"]);
+    assert_eq!(events[2]["reused"], false);
+    assert_eq!(events[4]["reused"], true);
+    assert_eq!(events[5]["iteration"], 2);
+    assert!(events[5]["usage"]["total_tokens"].as_i64().unwrap() > 0);
+    assert_eq!(events[6]["tool_calls"], 1);
+
+    let replayed: ToolLoopEvent = serde_json::from_value(events[6].clone()).unwrap();
+    assert!(matches!(
+        replayed,
+        ToolLoopEvent::Finished { iterations: 2, .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_tool_loop_reports_failure_as_last_event() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .respond_with(tool_call_response(vec![
+            function_call("call_1", "calculate_sum", json!({ "a": 1, "b": 2 })),
+            function_call("call_2", "calculate_sum", json!({ "a": 3, "b": 4 })),
+        ]))
+        .mount(&server)
+        .await;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let config = ToolCallingConfig::default()
+        .with_max_tool_calls(1)
+        .on_event({
+            let events = events.clone();
+            move |event| {
+                let events = events.clone();
+                async move { events.lock().unwrap().push(event) }
+            }
+        });
+
+    let toolset = sum_toolset();
+    let err = client_for(&server, Some(config))
+        .generate_completion::<SumResponse, ()>(
+            build_request("Add 1 and 2", tool_config_for(&toolset, Some(true))),
+            <SumResponse as CompletionTarget>::format().expect("format"),
+            Some(&toolset.registry),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LlmError::ToolCallLimit { limit: 1 }));
+
+    let events = events.lock().unwrap();
+    let Some(ToolLoopEvent::Failed {
+        iterations, error, ..
+    }) = events.last()
+    else {
+        panic!("expected a failed event, got {:?}", events.last());
+    };
+    assert_eq!(*iterations, 1);
+    assert_eq!(*error, err.to_string());
+}
+
+#[tokio::test]
+async fn test_tool_call_limits_and_duplicate_policy() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyNotContains("function_call_output"))
+        .respond_with(tool_call_response(vec![
+            function_call("call_1", "calculate_sum", json!({ "a": 1, "b": 2 })),
+            function_call("call_2", "calculate_sum", json!({ "a": 1, "b": 2 })),
+        ]))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyContains("function_call_output"))
+        .respond_with(final_response(json!({ "sum": 3 })))
+        .mount(&server)
+        .await;
+
+    let toolset = sum_toolset();
+    let run = async |config: ToolCallingConfig| {
+        client_for(&server, Some(config))
+            .generate_completion::<SumResponse, ()>(
+                build_request("Add 1 and 2", tool_config_for(&toolset, Some(true))),
+                <SumResponse as CompletionTarget>::format().expect("format"),
+                Some(&toolset.registry),
+            )
+            .await
+    };
+
+    let err = run(ToolCallingConfig::default().with_max_tool_calls(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LlmError::ToolCallLimit { limit: 1 }));
+
+    let err = run(ToolCallingConfig::default().with_tool_call_limit("calculate_sum", 1))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        LlmError::ToolCallLimitPerTool { ref tool_name, limit: 1 } if tool_name == "calculate_sum"
+    ));
+
+    let err = run(ToolCallingConfig::default().with_duplicate_calls(DuplicateCalls::Error))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LlmError::DuplicateToolCall { .. }));
+
+    // The duplicate reuses the first result, so it does not count against the limit
+    let response = run(ToolCallingConfig::default()
+        .with_max_tool_calls(1)
+        .with_duplicate_calls(DuplicateCalls::ReuseResult))
+    .await
+    .expect("duplicate call should reuse the earlier result");
+    assert_eq!(response.content.sum, 3);
+}
+
+#[tokio::test]
+async fn test_cacheable_tool_results_are_reused() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyNotContains("function_call_output"))
+        .respond_with(tool_call_response(vec![
+            function_call("call_1", "cached_sum", json!({ "a": 1, "b": 2 })),
+            function_call("call_2", "cached_sum", json!({ "a": 1, "b": 2 })),
+        ]))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/v1/responses"))
+        .and(BodyContains("function_call_output"))
+        .respond_with(final_response(json!({ "sum": 3 })))
+        .mount(&server)
+        .await;
+
+    let calls = Arc::new(SumCalls::default());
+    let toolset = toolset![SumCalls => cached_sum].with_shared_context(calls.clone());
+    let run = async |toolset: &ToolSet<SumCalls>, config: ToolCallingConfig| {
+        client_for(&server, Some(config))
+            .generate_completion::<SumResponse, SumCalls>(
+                build_request("Add 1 and 2", tool_config_for(toolset, Some(true))),
+                <SumResponse as CompletionTarget>::format().expect("format"),
+                Some(&toolset.registry),
+            )
+            .await
+            .expect("structured response")
+    };
+
+    // Identical calls within one loop execute the tool once
+    let cache = ToolCache::new(Duration::from_secs(60));
+    let config = || ToolCallingConfig::default().with_tool_cache(cache.clone());
+    run(&toolset, config()).await;
+    assert_eq!(calls.0.load(Ordering::SeqCst), 1);
+
+    // A later run sharing the cache and the context does not execute it at all
+    run(&toolset, config()).await;
+    assert_eq!(calls.0.load(Ordering::SeqCst), 1);
+
+    // Another context doesn't see the cached result
+    let scoped = toolset.scoped(SumCalls::default());
+    run(&scoped, config()).await;
+    assert_eq!(scoped.registry.context().0.load(Ordering::SeqCst), 1);
+    assert_eq!(calls.0.load(Ordering::SeqCst), 1);
+
+    cache.clear();
+    run(&toolset, config()).await;
+    assert_eq!(calls.0.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_tools_with_context_reuses_one_toolset() {
+    let transport = Arc::new(CapturingTransport::new(responses_answer("Done")));
+
+    let toolset = sum_toolset();
+    for _ in 0..2 {
+        llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("mock-model")
+            .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+            .tools_with_context(&toolset, ())
+            .transport(transport.clone())
+            .complete::<TextResponse>()
+            .await
+            .expect("completion should succeed");
+    }
+
+    let bodies = transport.bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 2);
+    for body in bodies.iter() {
+        assert_eq!(body["tools"][0]["name"], "calculate_sum");
+    }
+}
+
+#[tokio::test]
+async fn test_tool_loop_resumes_from_a_checkpoint() {
+    let responses = vec![
+        (
+            200,
+            responses_tool_calls(vec![function_call(
+                "call_1",
+                "calculate_sum",
+                json!({ "a": 1, "b": 2 }),
+            )]),
+        ),
+        (400, json!({ "error": { "message": "worker shut down" } })),
+    ];
+    let checkpoints = Arc::new(Mutex::new(Vec::new()));
+    let sink = checkpoints.clone();
+    let config = ToolCallingConfig::default().on_checkpoint(move |checkpoint| {
+        let sink = sink.clone();
+        async move { sink.lock().unwrap().push(checkpoint) }
+    });
+
+    let messages = vec![Message::new(ChatRole::User, "What is 1 + 2?")];
+    let interrupted = llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Custom("test-key".to_string()))
+        .unwrap()
+        .model("mock-model")
+        .messages(messages.clone())
+        .transport(Arc::new(StatusTransport::new(responses)))
+        .tools(sum_toolset())
+        .tool_calling(config)
+        .complete::<TextResponse>()
+        .await;
+    assert!(interrupted.is_err());
+
+    let checkpoint = checkpoints.lock().unwrap().pop().expect("checkpoint");
+    assert_eq!(checkpoint.iteration, 1);
+    assert_eq!(checkpoint.tool_calls, 1);
+    assert_eq!(checkpoint.usage.total_tokens, 15);
+    assert_eq!(checkpoint.messages.len(), 3);
+    let json = serde_json::to_string(&checkpoint).unwrap();
+    let checkpoint: ToolLoopCheckpoint = serde_json::from_str(&json).unwrap();
+
+    let transport = Arc::new(CapturingTransport::new(responses_answer("The sum is 3")));
+    let finished = Arc::new(Mutex::new(None));
+    let sink = finished.clone();
+    let config = ToolCallingConfig::default()
+        .resume(checkpoint)
+        .on_event(move |event| {
+            let sink = sink.clone();
+            async move {
+                if let ToolLoopEvent::Finished { .. } = event {
+                    *sink.lock().unwrap() = Some(event);
+                }
+            }
+        });
+
+    let response = llm::with(Provider::OpenAI)
+        .api_key(ApiKey::Custom("test-key".to_string()))
+        .unwrap()
+        .model("mock-model")
+        .messages(messages)
+        .transport(transport.clone())
+        .tools(sum_toolset())
+        .tool_calling(config)
+        .complete::<TextResponse>()
+        .await
+        .unwrap();
+    assert_eq!(response.text, "The sum is 3");
+
+    // The resumed loop continues with the tool result instead of calling the tool again
+    let bodies = transport.bodies.lock().unwrap();
+    assert_eq!(bodies.len(), 1);
+    let input = bodies[0]["input"].as_array().unwrap();
+    assert_eq!(input.len(), 3);
+    assert_eq!(input[1]["type"], "function_call");
+    assert_eq!(input[2]["type"], "function_call_output");
+    assert_eq!(input[2]["output"], json!({ "sum": 3 }));
+
+    let Some(ToolLoopEvent::Finished {
+        iterations,
+        tool_calls,
+        usage,
+    }) = finished.lock().unwrap().take()
+    else {
+        panic!("loop did not finish");
+    };
+    assert_eq!((iterations, tool_calls, usage.total_tokens), (2, 1, 30));
+}
+
+#[tokio::test]
+async fn test_hidden_tools_are_only_sent_when_enabled() {
+    let transport = Arc::new(CapturingTransport::new(responses_answer("{\"sum\":3}")));
+
+    let builder = || {
+        llm::with(Provider::OpenAI)
+            .api_key(ApiKey::Custom("test-key".to_string()))
+            .unwrap()
+            .model("mock-model")
+            .messages(vec![Message::new(ChatRole::User, "Add 1 and 2")])
+            .tools(toolset![calculate_sum, subtract_values])
+    };
+
+    builder()
+        .transport(transport.clone())
+        .complete::<SumResponse>()
+        .await
+        .expect("completion should succeed");
+    builder()
+        .enabled_tools(&["calculate_sum", "subtract_values"])
+        .transport(transport.clone())
+        .complete::<SumResponse>()
+        .await
+        .expect("completion should succeed");
+
+    let tool_names = |body: &Value| {
+        let mut names: Vec<String> = body["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+    let bodies = transport.bodies.lock().unwrap();
+    assert_eq!(tool_names(&bodies[0]), vec!["calculate_sum"]);
+    assert_eq!(
+        tool_names(&bodies[1]),
+        vec!["calculate_sum", "subtract_values"]
+    );
+}
+
+/// Transport that records request bodies and always returns the same response.
+struct CapturingTransport {
+    response: Value,
+    bodies: Mutex<Vec<Value>>,
+}
+
+impl CapturingTransport {
+    fn new(response: Value) -> Self {
+        Self {
+            response,
+            bodies: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for CapturingTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        self.bodies.lock().unwrap().push(request.body);
+        Ok(TransportResponse {
+            status: 200,
+            body: self.response.to_string(),
+        })
+    }
+}
+
+/// Transport that answers with scripted status codes and bodies in order, recording the
+/// request bodies.
+struct StatusTransport {
+    responses: Mutex<std::collections::VecDeque<(u16, Value)>>,
+    bodies: Mutex<Vec<Value>>,
+}
+
+impl StatusTransport {
+    fn new(responses: Vec<(u16, Value)>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into()),
+            bodies: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StatusTransport {
+    async fn send(&self, request: TransportRequest) -> Result<TransportResponse, LlmError> {
+        self.bodies.lock().unwrap().push(request.body);
+        let (status, body) = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no scripted response left");
+        Ok(TransportResponse {
+            status,
+            body: body.to_string(),
+        })
+    }
+}
+
 fn client_for(server: &MockServer, config: Option<ToolCallingConfig>) -> OpenAiClient {
     let base_url = format!("{}/v1", server.uri());
     let client = OpenAiClient::new("test-key".to_string())
@@ -272,7 +856,10 @@ fn client_for(server: &MockServer, config: Option<ToolCallingConfig>) -> OpenAiC
     }
 }
 
-fn tool_config_for(toolset: &ToolSet, parallel: Option<bool>) -> ToolConfig {
+fn tool_config_for<C: Send + Sync + 'static>(
+    toolset: &ToolSet<C>,
+    parallel: Option<bool>,
+) -> ToolConfig {
     ToolConfig {
         tools: Some(toolset.tools().expect("schemas").into_boxed_slice()),
         tool_choice: Some(ToolChoice::Auto),
@@ -291,22 +878,17 @@ fn sum_and_product_toolset() -> ToolSet {
 fn build_request(prompt: &str, tool_config: ToolConfig) -> StructuredRequest {
     StructuredRequest {
         model: "mock-model".to_string(),
-        messages: vec![ConversationMessage::Chat(Message {
-            role: ChatRole::User,
-            content: prompt.to_string(),
-        })],
+        messages: vec![ConversationMessage::Chat(Message::new(
+            ChatRole::User,
+            prompt.to_string(),
+        ))],
         tool_config: Some(tool_config),
         generation_config: None,
     }
 }
 
 fn tool_call_response(function_calls: Vec<Value>) -> ResponseTemplate {
-    ResponseTemplate::new(200).set_body_json(json!({
-        "id": "mock-response",
-        "model": "mock-model",
-        "output": function_calls,
-        "usage": usage_payload(),
-    }))
+    ResponseTemplate::new(200).set_body_json(responses_tool_calls(function_calls))
 }
 
 fn function_call(call_id: &str, name: &str, arguments: Value) -> Value {
@@ -320,21 +902,33 @@ fn function_call(call_id: &str, name: &str, arguments: Value) -> Value {
 }
 
 fn final_response(body: Value) -> ResponseTemplate {
-    ResponseTemplate::new(200).set_body_json(json!({
-        "id": "mock-final",
+    ResponseTemplate::new(200).set_body_json(responses_answer(&body.to_string()))
+}
+
+/// A Responses API answer with `text` as its only output.
+fn responses_answer(text: &str) -> Value {
+    json!({
+        "id": "resp_1",
         "model": "mock-model",
         "output": [{
             "id": "msg_1",
             "type": "message",
             "status": "completed",
             "role": "assistant",
-            "content": [{
-                "type": "output_text",
-                "text": body.to_string()
-            }]
+            "content": [{ "type": "output_text", "text": text }]
         }],
         "usage": usage_payload()
-    }))
+    })
+}
+
+/// A Responses API answer making the given function calls.
+fn responses_tool_calls(function_calls: Vec<Value>) -> Value {
+    json!({
+        "id": "resp_call",
+        "model": "mock-model",
+        "output": function_calls,
+        "usage": usage_payload()
+    })
 }
 
 fn usage_payload() -> Value {
@@ -0,0 +1,95 @@
+//! Tests for `chrono` and `uuid` support in structured outputs and tool parameters.
+#![cfg(all(feature = "chrono", feature = "uuid"))]
+
+use rsai::chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use rsai::uuid::Uuid;
+use rsai::{ToolCall, ToolSet, completion_schema, tool, toolset};
+use schemars::schema_for;
+use serde_json::json;
+
+#[completion_schema]
+struct Booking {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    check_in: NaiveDate,
+}
+
+#[tool]
+/// Look up bookings created after a point in time.
+/// guest: Guest identifier
+/// since: Only include bookings created after this time
+/// on: Check-in date
+fn find_bookings(guest: Uuid, since: DateTime<Utc>, on: Option<NaiveDate>) -> String {
+    format!(
+        "{guest} {} {}",
+        since.to_rfc3339(),
+        on.map(|d| d.to_string()).unwrap_or_default()
+    )
+}
+
+#[test]
+fn test_completion_schema_formats() {
+    let schema = serde_json::to_value(schema_for!(Booking)).unwrap();
+    let properties = &schema["properties"];
+
+    assert_eq!(properties["id"]["format"], "uuid");
+    assert_eq!(properties["created_at"]["format"], "date-time");
+    assert_eq!(properties["check_in"]["format"], "date");
+}
+
+#[test]
+fn test_completion_schema_deserializes() {
+    let booking: Booking = serde_json::from_value(json!({
+        "id": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+        "created_at": "2025-03-01T12:30:00Z",
+        "check_in": "2025-04-15"
+    }))
+    .unwrap();
+
+    assert_eq!(
+        booking.id,
+        Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap()
+    );
+    assert_eq!(
+        booking.created_at,
+        Utc.with_ymd_and_hms(2025, 3, 1, 12, 30, 0).unwrap()
+    );
+    assert_eq!(
+        booking.check_in,
+        NaiveDate::from_ymd_opt(2025, 4, 15).unwrap()
+    );
+}
+
+#[test]
+fn test_tool_parameter_formats() {
+    let schema = FindBookingsTool.schema();
+    let properties = &schema.parameters["properties"];
+
+    assert_eq!(properties["guest"]["type"], "string");
+    assert_eq!(properties["guest"]["format"], "uuid");
+    assert_eq!(properties["since"]["type"], "string");
+    assert_eq!(properties["since"]["format"], "date-time");
+    assert_eq!(properties["on"]["format"], "date");
+}
+
+#[tokio::test]
+async fn test_tool_parameters_deserialize() {
+    let tools: ToolSet = toolset![find_bookings];
+    let call = ToolCall {
+        id: "call_1".to_string(),
+        call_id: "call_1".to_string(),
+        name: "find_bookings".to_string(),
+        arguments: json!({
+            "guest": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "since": "2025-03-01T12:30:00Z",
+            "on": "2025-04-15"
+        }),
+    };
+
+    let result = tools.registry.execute(&call).await.unwrap();
+
+    assert_eq!(
+        result,
+        json!("67e55044-10b1-426f-9247-bb680e5fe0c8 2025-03-01T12:30:00+00:00 2025-04-15")
+    );
+}
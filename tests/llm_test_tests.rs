@@ -0,0 +1,42 @@
+//! Tests for the prompt regression harness, replaying the cassettes in `tests/cassettes`.
+
+use rsai::testing::{Judge, TestCase};
+use rsai::{ChatRole, LlmError, Message, Provider, completion_schema, llm, llm_test};
+use serde::Serialize;
+use serde_json::json;
+
+#[completion_schema]
+#[derive(Serialize)]
+struct Invoice {
+    /// Company that issued the invoice
+    vendor: String,
+    /// Amount due
+    total: f64,
+}
+
+#[llm_test]
+async fn extracts_invoice(case: TestCase) -> Result<(), LlmError> {
+    let invoice = llm::with(Provider::OpenAI)
+        .api_key(case.api_key())?
+        .model("gpt-4o-mini")
+        .messages(vec![Message::new(
+            ChatRole::User,
+            "ACME Corp, total due: $120.50",
+        )])
+        .transport(case.transport()?)
+        .complete::<Invoice>()
+        .await?;
+
+    case.assert_fields(&invoice.content, json!({ "vendor": "ACME Corp" }));
+    case.assert_snapshot(&invoice.content);
+
+    let verdict = case
+        .judge(
+            &Judge::new(Provider::OpenAI, "gpt-4o-mini"),
+            &invoice.content,
+            "The total is 120.50",
+        )
+        .await?;
+    assert!(verdict.score >= 0.8, "{}", verdict.reasoning);
+    Ok(())
+}
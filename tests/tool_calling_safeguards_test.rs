@@ -111,6 +111,7 @@ async fn test_openai_config_tool_calling() {
     let custom_config = ToolCallingConfig {
         max_iterations: 75,
         timeout: Duration::from_secs(600),
+        ..ToolCallingConfig::default()
     };
 
     let config_with_custom =
@@ -134,6 +135,7 @@ async fn test_openrouter_config_tool_calling() {
     let custom_config = ToolCallingConfig {
         max_iterations: 100,
         timeout: Duration::from_secs(900),
+        ..ToolCallingConfig::default()
     };
 
     let config_with_custom =
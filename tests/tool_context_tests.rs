@@ -2,6 +2,7 @@
 
 use rsai::{Ctx, ToolCall, ToolSet, ToolSetBuilder, tool, toolset};
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
 
 // Mock resources that tools might need
 struct DatabasePool {
@@ -86,6 +87,136 @@ fn add_numbers(a: i32, b: i32) -> i32 {
     a + b
 }
 
+// Shared mutable state for tools
+#[derive(Default)]
+struct ShoppingCart {
+    items: Vec<(String, u32)>,
+}
+
+#[derive(Default)]
+struct Shop {
+    cart: Mutex<ShoppingCart>,
+    audit_log: tokio::sync::Mutex<Vec<String>>,
+}
+
+impl AsRef<Mutex<ShoppingCart>> for Shop {
+    fn as_ref(&self) -> &Mutex<ShoppingCart> {
+        &self.cart
+    }
+}
+
+impl AsRef<tokio::sync::Mutex<Vec<String>>> for Shop {
+    fn as_ref(&self) -> &tokio::sync::Mutex<Vec<String>> {
+        &self.audit_log
+    }
+}
+
+#[tool]
+/// Add an item to the shopping cart.
+/// item: Name of the item.
+/// quantity: How many to add.
+fn add_to_cart(cart: Ctx<&Mutex<ShoppingCart>>, item: String, quantity: u32) -> usize {
+    let mut cart = cart.lock().unwrap();
+    cart.items.push((item, quantity));
+    cart.items.len()
+}
+
+#[tool]
+/// Record an event in the audit log.
+/// event: Description of the event.
+async fn audit(log: Ctx<&tokio::sync::Mutex<Vec<String>>>, event: String) -> usize {
+    let mut log = log.lock().await;
+    tokio::task::yield_now().await;
+    log.push(event);
+    log.len()
+}
+
+fn call(name: &str, arguments: serde_json::Value) -> ToolCall {
+    ToolCall {
+        id: "call_1".to_string(),
+        call_id: "call_1".to_string(),
+        name: name.to_string(),
+        arguments,
+    }
+}
+
+#[tokio::test]
+async fn test_tools_accumulate_state_in_shared_context() {
+    let shop = Arc::new(Shop::default());
+    let toolset = toolset![Shop => add_to_cart, audit].with_shared_context(shop.clone());
+
+    for (item, quantity) in [("apple", 3), ("bread", 1)] {
+        toolset
+            .registry
+            .execute(&call(
+                "add_to_cart",
+                serde_json::json!({ "item": item, "quantity": quantity }),
+            ))
+            .await
+            .expect("execution");
+    }
+    let count = toolset
+        .registry
+        .execute(&call("audit", serde_json::json!({ "event": "checkout" })))
+        .await
+        .expect("execution");
+    assert_eq!(count, 1);
+
+    // The caller's handle sees everything the tools changed
+    assert_eq!(
+        shop.cart.lock().unwrap().items,
+        vec![("apple".to_string(), 3), ("bread".to_string(), 1)]
+    );
+    assert_eq!(*shop.audit_log.lock().await, vec!["checkout".to_string()]);
+    assert_eq!(
+        toolset.registry.context().cart.lock().unwrap().items.len(),
+        2
+    );
+}
+
+struct Session {
+    user: String,
+}
+
+impl AsRef<Session> for Session {
+    fn as_ref(&self) -> &Session {
+        self
+    }
+}
+
+#[tool]
+/// Greet the user of the current session.
+fn greet_user(session: Ctx<&Session>) -> String {
+    format!("Hello, {}!", session.user)
+}
+
+#[tokio::test]
+async fn test_scoped_toolset_uses_request_context() {
+    let toolset = toolset![Session => greet_user].with_context(Session {
+        user: "nobody".to_string(),
+    });
+
+    for user in ["ada", "grace"] {
+        let scoped = toolset.scoped(Session {
+            user: user.to_string(),
+        });
+        let greeting = scoped
+            .registry
+            .execute(&call("greet_user", serde_json::json!({})))
+            .await
+            .expect("execution");
+        assert_eq!(greeting, format!("Hello, {user}!"));
+    }
+
+    // The original toolset keeps its own context
+    let greeting = toolset
+        .registry
+        .execute(&call("greet_user", serde_json::json!({})))
+        .await
+        .expect("execution");
+    assert_eq!(greeting, "Hello, nobody!");
+}
+
 #[tokio::test]
 async fn test_context_tool_execution() {
     let context = AppContext {
@@ -216,6 +347,27 @@ fn test_toolset_builder_creates_correct_type() {
     let _builder: ToolSetBuilder<AppContext> = toolset![AppContext => search_docs, add_numbers];
 }
 
+toolset! {
+    enum AppTools: AppContext => search_docs, add_numbers
+}
+
+#[test]
+fn test_typed_toolset_with_context() {
+    let context = AppContext {
+        db: DatabasePool::new(),
+        cache: CacheClient::new(),
+    };
+
+    let toolset: ToolSet<AppContext> = AppTools::toolset().with_context(context);
+    let schemas = toolset.tools().expect("schemas");
+
+    let mut names: Vec<_> = schemas.iter().map(|s| s.name.as_str()).collect();
+    names.sort();
+    let mut expected: Vec<_> = AppTools::ALL.iter().map(AppTools::name).collect();
+    expected.sort();
+    assert_eq!(names, expected);
+}
+
 #[test]
 fn test_tool_schemas_exclude_context_parameter() {
     let context = AppContext {
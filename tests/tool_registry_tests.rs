@@ -1,4 +1,6 @@
-use rsai::{BoxFuture, LlmError, Tool, ToolCall, ToolFunction, ToolRegistry, tool};
+use rsai::{
+    BoxFuture, LlmError, NamespacedTool, Tool, ToolCall, ToolFunction, ToolRegistry, ToolSet, tool,
+};
 use serde_json::json;
 use std::sync::Arc;
 use std::time::Duration;
@@ -21,6 +23,110 @@ async fn test_tool_b(value: f64) -> serde_json::Value {
     json!({ "result": "success_b" })
 }
 
+#[derive(serde::Serialize, schemars::JsonSchema)]
+struct Forecast {
+    city: String,
+    celsius: f64,
+}
+
+#[tool(output_schema)]
+/// Forecast the weather for a city.
+/// city: Name of the city.
+fn forecast(city: String) -> Forecast {
+    Forecast {
+        city,
+        celsius: 21.5,
+    }
+}
+
+/// Declares an integer result but returns a string.
+struct MislabeledTool;
+
+impl ToolFunction<()> for MislabeledTool {
+    fn schema(&self) -> Tool {
+        Tool {
+            name: "mislabeled".to_string(),
+            description: None,
+            parameters: json!({ "type": "object", "properties": {}, "required": [] }),
+            strict: Some(true),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a (),
+        _params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        Box::pin(async move { Ok(json!("forty-two")) })
+    }
+
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        Some(json!({ "type": "integer" }))
+    }
+}
+
+#[tool]
+/// Divide two integers.
+/// a: Dividend.
+/// b: Divisor.
+async fn divide(a: i64, b: i64) -> Result<i64, String> {
+    if b == 0 {
+        return Err("cannot divide by zero".to_string());
+    }
+    Ok(a / b)
+}
+
+#[derive(serde::Deserialize)]
+struct Greeting {
+    punctuation: String,
+}
+
+#[tool]
+/// Join words with borrowed parameters.
+/// separator: Text placed between words.
+/// words: Words to join.
+/// prefix: Optional text placed before the result.
+/// suffix: Text placed after the result.
+/// greeting: Formatting options.
+fn join_words(
+    separator: &str,
+    words: &[String],
+    prefix: Option<&str>,
+    suffix: std::borrow::Cow<'_, str>,
+    greeting: &Greeting,
+) -> String {
+    format!(
+        "{}{}{}{}",
+        prefix.unwrap_or_default(),
+        words.join(separator),
+        suffix,
+        greeting.punctuation
+    )
+}
+
+#[tool]
+/// Describe the weather forecast.
+/// city: Name of the city.
+/// unit: Temperature unit @default celsius
+/// days: Number of days to forecast @default 3
+/// hourly: Include hourly details @default false
+fn describe_forecast(city: String, unit: String, days: u32, hourly: Option<bool>) -> String {
+    format!("{city}: {days} days in {unit}, hourly: {hourly:?}")
+}
+
+#[tool(deprecated = "use forecast instead")]
+/// Get the current temperature.
+/// city: Name of the city.
+fn temperature(city: String) -> String {
+    format!("{city}: 20C")
+}
+
+#[tool(hidden)]
+/// Reset the internal cache.
+fn reset_cache() -> bool {
+    true
+}
+
 fn tool_a() -> Arc<dyn ToolFunction<()>> {
     Arc::new(TestToolATool)
 }
@@ -115,6 +221,25 @@ async fn test_different_tools_same_name() {
     }
 }
 
+#[tokio::test]
+async fn test_names_equal_on_the_wire_are_rejected() {
+    let registry = ToolRegistry::new();
+    let nested = NamespacedTool::new("weather", Arc::new(NamespacedTool::new("eu", tool_a())));
+    registry.register(Arc::new(nested)).unwrap();
+
+    let error = registry
+        .register(Arc::new(NamespacedTool::new("weather__eu", tool_a())))
+        .unwrap_err();
+    match error {
+        LlmError::ToolRegistration { tool_name, message } => {
+            assert_eq!(tool_name, "weather__eu.test_tool_a");
+            assert!(message.contains("weather__eu__test_tool_a"));
+            assert!(message.contains("weather.eu.test_tool_a"));
+        }
+        _ => panic!("Expected ToolRegistration error"),
+    }
+}
+
 #[tokio::test]
 async fn test_error_message_clarity() {
     let registry = ToolRegistry::new();
@@ -346,6 +471,413 @@ async fn test_tool_not_found_error() {
     }
 }
 
+#[tokio::test]
+async fn test_tool_output_schema_is_described_and_validated() {
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(ForecastTool)).unwrap();
+    registry.register(Arc::new(MislabeledTool)).unwrap();
+
+    let description = ForecastTool.schema().description.unwrap();
+    assert!(description.starts_with("Forecast the weather for a city."));
+    assert!(description.contains("Returns JSON matching this schema:"));
+    assert!(description.contains("celsius"));
+    assert_eq!(
+        ToolFunction::<()>::output_schema(&ForecastTool).unwrap()["required"],
+        json!(["city", "celsius"])
+    );
+
+    let call = |name: &str, arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: name.to_string(),
+        arguments,
+    };
+
+    let result = registry
+        .execute(&call("forecast", json!({ "city": "Oslo" })))
+        .await
+        .unwrap();
+    assert_eq!(result["celsius"], 21.5);
+
+    let err = registry
+        .execute(&call("mislabeled", json!({})))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, LlmError::ToolExecution { ref message, .. } if message.contains("expected integer")),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn test_result_tools_unwrap_ok_and_report_err() {
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(DivideTool)).unwrap();
+
+    let call = |arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: "divide".to_string(),
+        arguments,
+    };
+
+    let result = registry
+        .execute(&call(json!({ "a": 9, "b": 3 })))
+        .await
+        .unwrap();
+    assert_eq!(result, json!(3));
+
+    let err = registry
+        .execute(&call(json!({ "a": 1, "b": 0 })))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, LlmError::ToolExecution { ref message, .. } if message == "cannot divide by zero"),
+        "{err}"
+    );
+}
+
+/// Returns its arguments unchanged.
+struct EchoTool;
+
+impl ToolFunction<()> for EchoTool {
+    fn schema(&self) -> Tool {
+        Tool {
+            name: "echo".to_string(),
+            description: None,
+            parameters: json!({
+                "type": "object",
+                "properties": { "count": { "type": "integer" } },
+                "required": ["count"]
+            }),
+            strict: Some(true),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a (),
+        params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        Box::pin(async move { Ok(params) })
+    }
+}
+
+#[tokio::test]
+async fn test_arguments_are_coerced_to_parameter_types() {
+    let call = |name: &str, arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: name.to_string(),
+        arguments,
+    };
+
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(DivideTool)).unwrap();
+    registry.register(Arc::new(EchoTool)).unwrap();
+    let result = registry
+        .execute(&call("divide", json!({ "a": "9", "b": 3.0 })))
+        .await
+        .unwrap();
+    assert_eq!(result, json!(3));
+
+    let echo = call("echo", json!({ "count": "4" }));
+    assert_eq!(
+        registry.execute(&echo).await.unwrap(),
+        json!({ "count": "4" })
+    );
+    let registry = registry.with_argument_coercion(true);
+    assert_eq!(
+        registry.execute(&echo).await.unwrap(),
+        json!({ "count": 4 })
+    );
+}
+
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BookingArgs {
+    guests: u32,
+}
+
+/// Books a table, rejecting arguments it doesn't know.
+struct BookingTool {
+    tolerant: bool,
+}
+
+impl ToolFunction<()> for BookingTool {
+    fn schema(&self) -> Tool {
+        Tool {
+            name: if self.tolerant {
+                "book_tolerant"
+            } else {
+                "book"
+            }
+            .to_string(),
+            description: None,
+            parameters: json!({
+                "type": "object",
+                "properties": { "guests": { "type": "integer" } },
+                "required": ["guests"],
+                "additionalProperties": false
+            }),
+            strict: Some(true),
+        }
+    }
+
+    fn execute<'a>(
+        &'a self,
+        _ctx: &'a (),
+        params: serde_json::Value,
+    ) -> BoxFuture<'a, Result<serde_json::Value, LlmError>> {
+        Box::pin(async move {
+            let args: BookingArgs =
+                serde_json::from_value(params).map_err(|e| LlmError::ToolExecution {
+                    message: e.to_string(),
+                    source: None,
+                })?;
+            Ok(json!(args.guests))
+        })
+    }
+
+    fn ignore_unknown_arguments(&self) -> bool {
+        self.tolerant
+    }
+}
+
+#[tokio::test]
+async fn test_unknown_arguments_can_be_ignored() {
+    let call = |name: &str| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: name.to_string(),
+        arguments: json!({ "guests": 2, "note": "window seat" }),
+    };
+
+    let registry = ToolRegistry::new();
+    registry
+        .register(Arc::new(BookingTool { tolerant: false }))
+        .unwrap();
+    registry
+        .register(Arc::new(BookingTool { tolerant: true }))
+        .unwrap();
+
+    let err = registry.execute(&call("book")).await.unwrap_err();
+    assert!(
+        matches!(err, LlmError::ToolExecution { ref message, .. } if message.contains("unknown field `note`")),
+        "{err}"
+    );
+    assert_eq!(
+        registry.execute(&call("book_tolerant")).await.unwrap(),
+        json!(2)
+    );
+
+    let registry = registry.with_unknown_arguments_ignored(true);
+    assert_eq!(registry.execute(&call("book")).await.unwrap(), json!(2));
+}
+
+#[test]
+fn test_validate_call_checks_arguments_without_executing() {
+    let call = |name: &str, arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: name.to_string(),
+        arguments,
+    };
+    let invalid = |registry: &ToolRegistry, arguments| match registry
+        .validate_call(&call("divide", arguments))
+    {
+        Err(LlmError::ToolExecution { message, .. }) => message,
+        other => panic!("expected invalid arguments, got {other:?}"),
+    };
+
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(DivideTool)).unwrap();
+
+    registry
+        .validate_call(&call("divide", json!({ "a": 1, "b": 0 })))
+        .unwrap();
+    // `#[tool]` wrappers coerce their arguments when executed
+    registry
+        .validate_call(&call("divide", json!({ "a": "9", "b": 3 })))
+        .unwrap();
+    assert!(invalid(&registry, json!({ "a": 1 })).contains("missing required field `b`"));
+    assert!(invalid(&registry, json!({ "a": "one", "b": 2 })).contains("/a: expected integer"));
+    assert!(invalid(&registry, json!({ "a": 1, "b": 2, "c": 3 })).contains("/c"));
+    assert!(matches!(
+        registry.validate_call(&call("missing", json!({}))),
+        Err(LlmError::ToolNotFound(name)) if name == "missing"
+    ));
+
+    let registry = registry
+        .with_argument_coercion(true)
+        .with_unknown_arguments_ignored(true);
+    registry
+        .validate_call(&call("divide", json!({ "a": "1", "b": 2, "c": 3 })))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_stats_count_calls_and_errors_per_tool() {
+    let call = |arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: "divide".to_string(),
+        arguments,
+    };
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(DivideTool)).unwrap();
+    let scoped = registry.scoped(());
+
+    registry
+        .execute(&call(json!({ "a": 6, "b": 3 })))
+        .await
+        .unwrap();
+    scoped
+        .execute(&call(json!({ "a": 1, "b": 0 })))
+        .await
+        .unwrap_err();
+    registry
+        .filter(|_| true)
+        .unwrap()
+        .execute(&call(json!({ "a": 4, "b": 2 })))
+        .await
+        .unwrap();
+
+    let namespaced = ToolSet {
+        registry: registry.clone(),
+    }
+    .namespaced("math")
+    .unwrap();
+    namespaced
+        .registry
+        .execute(&ToolCall {
+            name: "math.divide".to_string(),
+            ..call(json!({ "a": 4, "b": 2 }))
+        })
+        .await
+        .unwrap();
+    registry
+        .execute(&ToolCall {
+            name: "multiply".to_string(),
+            ..call(json!({}))
+        })
+        .await
+        .unwrap_err();
+
+    let stats = registry.stats();
+    assert_eq!(stats.len(), 3);
+    assert_eq!(stats["divide"].calls, 3);
+    assert_eq!(stats["divide"].errors, 1);
+    assert!(stats["divide"].last_called.is_some());
+    assert_eq!(stats["math.divide"].calls, 1);
+    assert_eq!(stats["multiply"].errors, 1);
+    assert_eq!(scoped.stats(), stats);
+    assert_eq!(namespaced.registry.stats(), stats);
+
+    registry.reset_stats();
+    assert!(scoped.stats().is_empty());
+}
+
+#[tokio::test]
+async fn test_borrowed_parameters_are_deserialized_as_owned() {
+    let schema = JoinWordsTool.schema();
+    let properties = &schema.parameters["properties"];
+    assert_eq!(properties["separator"]["type"], "string");
+    assert_eq!(properties["words"]["type"], "array");
+    assert_eq!(properties["prefix"]["type"], "string");
+    assert_eq!(properties["suffix"]["type"], "string");
+    assert_eq!(properties["greeting"]["type"], "object");
+    assert_eq!(
+        schema.parameters["required"],
+        json!(["separator", "words", "suffix", "greeting"])
+    );
+
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(JoinWordsTool)).unwrap();
+    let result = registry
+        .execute(&ToolCall {
+            id: "test_id".to_string(),
+            call_id: "call_123".to_string(),
+            name: "join_words".to_string(),
+            arguments: json!({
+                "separator": ", ",
+                "words": ["a", "b"],
+                "suffix": " c",
+                "greeting": { "punctuation": "!" }
+            }),
+        })
+        .await
+        .unwrap();
+    assert_eq!(result, "a, b c!");
+}
+
+#[tokio::test]
+async fn test_parameter_defaults_are_described_and_applied() {
+    let schema = DescribeForecastTool.schema();
+    let properties = &schema.parameters["properties"];
+    assert_eq!(properties["unit"]["default"], "celsius");
+    assert_eq!(properties["unit"]["description"], "Temperature unit");
+    assert_eq!(properties["days"]["default"], 3);
+    assert_eq!(properties["hourly"]["default"], false);
+    assert_eq!(schema.parameters["required"], json!(["city"]));
+
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(DescribeForecastTool)).unwrap();
+    let call = |arguments| ToolCall {
+        id: "test_id".to_string(),
+        call_id: "call_123".to_string(),
+        name: "describe_forecast".to_string(),
+        arguments,
+    };
+
+    let result = registry
+        .execute(&call(json!({ "city": "Oslo", "days": null })))
+        .await
+        .unwrap();
+    assert_eq!(result, "Oslo: 3 days in celsius, hourly: Some(false)");
+
+    let result = registry
+        .execute(&call(
+            json!({ "city": "Oslo", "unit": "kelvin", "hourly": true }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(result, "Oslo: 3 days in kelvin, hourly: Some(true)");
+}
+
+#[tokio::test]
+async fn test_deprecated_and_hidden_tools() {
+    let registry = ToolRegistry::new();
+    registry.register(Arc::new(TemperatureTool)).unwrap();
+    registry.register(Arc::new(ResetCacheTool)).unwrap();
+
+    assert_eq!(
+        TemperatureTool.schema().description.unwrap(),
+        "Get the current temperature. (Deprecated: use forecast instead)"
+    );
+    assert_eq!(
+        ToolFunction::<()>::deprecated(&TemperatureTool),
+        Some("use forecast instead")
+    );
+
+    let schemas = registry.get_schemas().unwrap();
+    assert_eq!(schemas.len(), 1);
+    assert_eq!(schemas[0].name, "temperature");
+    assert_eq!(registry.get_all_schemas().unwrap().len(), 2);
+
+    // Hidden tools are still registered and can be executed
+    let result = registry
+        .execute(&ToolCall {
+            id: "test_id".to_string(),
+            call_id: "call_123".to_string(),
+            name: "reset_cache".to_string(),
+            arguments: json!({}),
+        })
+        .await
+        .unwrap();
+    assert_eq!(result, true);
+}
+
 // ============================================================================
 // SCHEMA RETRIEVAL TESTS
 // ============================================================================